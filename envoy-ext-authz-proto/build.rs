@@ -0,0 +1,15 @@
+fn main() {
+    let iface_files = &["envoy/service/auth/v3/external_auth.proto"];
+    let dirs = &["."];
+
+    tonic_build::configure()
+        .build_client(true)
+        .build_server(false)
+        .compile(iface_files, dirs)
+        .unwrap_or_else(|e| panic!("protobuf compilation failed: {}", e));
+
+    // recompile protobufs only if any of the proto files changes.
+    for file in iface_files {
+        println!("cargo:rerun-if-changed={}", file);
+    }
+}
@@ -0,0 +1,36 @@
+//! gRPC bindings for Envoy's external authorization (ext_authz) API.
+//!
+//! Vendored from <https://github.com/envoyproxy/envoy/>.
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+#![allow(clippy::inconsistent_struct_constructor, rustdoc::bare_urls)]
+
+// Envoy's packages are nested under `envoy` so that the generated code can
+// refer to `google.rpc` types relative to the crate's root.
+pub mod envoy {
+    pub mod config {
+        pub mod core {
+            pub mod v3 {
+                include!(concat!(env!("OUT_DIR"), "/envoy.config.core.v3.rs"));
+            }
+        }
+    }
+    pub mod service {
+        pub mod auth {
+            pub mod v3 {
+                include!(concat!(env!("OUT_DIR"), "/envoy.service.auth.v3.rs"));
+            }
+        }
+    }
+    pub mod r#type {
+        pub mod v3 {
+            include!(concat!(env!("OUT_DIR"), "/envoy.type.v3.rs"));
+        }
+    }
+}
+pub mod google {
+    pub mod rpc {
+        include!(concat!(env!("OUT_DIR"), "/google.rpc.rs"));
+    }
+}
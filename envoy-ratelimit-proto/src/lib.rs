@@ -0,0 +1,34 @@
+//! gRPC bindings for Envoy's rate limit service (RLS) API.
+//!
+//! Vendored from <https://github.com/envoyproxy/envoy/>.
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+#![allow(clippy::inconsistent_struct_constructor, rustdoc::bare_urls)]
+
+pub mod config {
+    pub mod core {
+        pub mod v3 {
+            include!(concat!(env!("OUT_DIR"), "/envoy.config.core.v3.rs"));
+        }
+    }
+}
+pub mod extensions {
+    pub mod common {
+        pub mod ratelimit {
+            pub mod v3 {
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/envoy.extensions.common.ratelimit.v3.rs"
+                ));
+            }
+        }
+    }
+}
+pub mod service {
+    pub mod ratelimit {
+        pub mod v3 {
+            include!(concat!(env!("OUT_DIR"), "/envoy.service.ratelimit.v3.rs"));
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! gRPC bindings for Envoy's cluster and endpoint discovery (xDS) APIs.
+//!
+//! Vendored from <https://github.com/envoyproxy/envoy/>.
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+#![allow(clippy::inconsistent_struct_constructor, rustdoc::bare_urls)]
+
+// Envoy's packages are nested under `envoy` so that the generated code can
+// refer to `google.rpc` types relative to the crate's root.
+pub mod envoy {
+    pub mod config {
+        pub mod cluster {
+            pub mod v3 {
+                include!(concat!(env!("OUT_DIR"), "/envoy.config.cluster.v3.rs"));
+            }
+        }
+        pub mod core {
+            pub mod v3 {
+                include!(concat!(env!("OUT_DIR"), "/envoy.config.core.v3.rs"));
+            }
+        }
+        pub mod endpoint {
+            pub mod v3 {
+                include!(concat!(env!("OUT_DIR"), "/envoy.config.endpoint.v3.rs"));
+            }
+        }
+    }
+    pub mod service {
+        pub mod discovery {
+            pub mod v3 {
+                include!(concat!(env!("OUT_DIR"), "/envoy.service.discovery.v3.rs"));
+            }
+        }
+    }
+}
+pub mod google {
+    pub mod rpc {
+        include!(concat!(env!("OUT_DIR"), "/google.rpc.rs"));
+    }
+}
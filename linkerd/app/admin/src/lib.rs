@@ -4,5 +4,7 @@
 mod server;
 mod stack;
 
-pub use self::server::{Admin, Latch, Readiness};
+pub use self::server::{
+    Admin, Latch, Readiness, Shutdown, SubsystemReady, SubsystemState, Subsystems,
+};
 pub use self::stack::{Config, Task};
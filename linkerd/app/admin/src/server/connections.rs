@@ -0,0 +1,44 @@
+use hyper::Body;
+use linkerd_app_core::{transport, Error};
+use serde_json::json;
+
+/// Lists the proxy's currently-open connections as JSON.
+pub(super) fn serve<B>(
+    transport: &transport::Metrics,
+    req: http::Request<B>,
+) -> Result<http::Response<Body>, Error> {
+    if req.method() != http::Method::GET {
+        return Ok(http::Response::builder()
+            .status(http::StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "GET")
+            .body(Body::empty())
+            .expect("builder with known status code must not fail"));
+    }
+
+    let mut conns = transport.connections();
+    // List the longest-lived connections first.
+    conns.sort_by(|(_, a), (_, b)| b.age.cmp(&a.age));
+
+    let conns = conns
+        .into_iter()
+        .map(|(key, conn)| {
+            json!({
+                "direction": key.direction().to_string(),
+                "peer_addr": conn.peer_addr.map(|a| a.to_string()),
+                "target_addr": key.target_addr().map(|a| a.to_string()),
+                "identity": key.peer_identity(),
+                "tls": key.tls(),
+                "age_ms": conn.age.as_millis() as u64,
+                "read_bytes": conn.read_bytes,
+                "write_bytes": conn.write_bytes,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let body = serde_json::to_string(&json!({ "connections": conns }))?;
+    Ok(http::Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(body.into())
+        .expect("Response must be valid"))
+}
@@ -0,0 +1,128 @@
+use super::Shutdown;
+use hyper::Body;
+use linkerd_app_core::{transport, Error};
+use serde_json::json;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Error)]
+#[error("invalid grace period: {0}")]
+struct InvalidGrace(String);
+
+/// Starts draining the proxy.
+///
+/// The proxy stops accepting connections, waits for in-flight work to complete
+/// (for at most the `grace` period, if one is specified), and then shuts down.
+/// The response reports the drain that was initiated and the number of
+/// connections that remained open when it started.
+pub(super) fn serve<B>(
+    shutdown_tx: &mpsc::UnboundedSender<Shutdown>,
+    transport: &transport::Metrics,
+    req: http::Request<B>,
+) -> Result<http::Response<Body>, Error> {
+    let grace = match grace_param(&req) {
+        None => None,
+        Some(Ok(grace)) => Some(grace),
+        Some(Err(error)) => {
+            return Ok(rsp_json(
+                http::StatusCode::BAD_REQUEST,
+                json!({ "error": error.to_string() }),
+            ))
+        }
+    };
+
+    let open_connections = transport.connections().len();
+    if shutdown_tx.send(Shutdown { grace }).is_err() {
+        return Ok(rsp_json(
+            http::StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": "shutdown listener dropped" }),
+        ));
+    }
+
+    tracing::info!(?grace, open_connections, "Draining via admin interface");
+    Ok(rsp_json(
+        http::StatusCode::ACCEPTED,
+        json!({
+            "state": "draining",
+            "grace_ms": grace.map(|g| g.as_millis() as u64),
+            "open_connections": open_connections,
+        }),
+    ))
+}
+
+fn grace_param<B>(req: &http::Request<B>) -> Option<Result<Duration, InvalidGrace>> {
+    let query = req.uri().query()?;
+    let value = query.split('&').find_map(|kv| {
+        let mut parts = kv.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some("grace"), Some(v)) if !v.is_empty() => Some(v),
+            _ => None,
+        }
+    })?;
+    Some(parse_duration(value).ok_or_else(|| InvalidGrace(value.to_string())))
+}
+
+/// Parses durations like `500ms`, `30s`, or `2m`.
+fn parse_duration(s: &str) -> Option<Duration> {
+    let idx = s.find(|c: char| !c.is_ascii_digit())?;
+    let (magnitude, unit) = s.split_at(idx);
+    let magnitude = magnitude.parse::<u64>().ok()?;
+    match unit {
+        "ms" => Some(Duration::from_millis(magnitude)),
+        "s" => Some(Duration::from_secs(magnitude)),
+        "m" => Some(Duration::from_secs(magnitude.checked_mul(60)?)),
+        _ => None,
+    }
+}
+
+fn rsp_json(status: http::StatusCode, body: serde_json::Value) -> http::Response<Body> {
+    http::Response::builder()
+        .status(status)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string().into())
+        .expect("Response must be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn req(uri: &str) -> http::Request<()> {
+        http::Request::post(uri).body(()).unwrap()
+    }
+
+    #[test]
+    fn parses_grace() {
+        let grace = |uri| grace_param(&req(uri)).map(|r| r.ok());
+        assert_eq!(grace("http://admin/drain"), None);
+        assert_eq!(
+            grace("http://admin/drain?grace=30s"),
+            Some(Some(Duration::from_secs(30)))
+        );
+        assert_eq!(
+            grace("http://admin/drain?x=y&grace=1500ms"),
+            Some(Some(Duration::from_millis(1500)))
+        );
+        assert_eq!(
+            grace("http://admin/drain?grace=2m"),
+            Some(Some(Duration::from_secs(120)))
+        );
+        assert_eq!(grace("http://admin/drain?grace=30"), Some(None));
+        assert_eq!(grace("http://admin/drain?grace=s"), Some(None));
+    }
+
+    #[tokio::test]
+    async fn sends_shutdown() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (transport, _) = transport::Metrics::new(Duration::from_secs(10));
+        let rsp = serve(&tx, &transport, req("http://admin/drain?grace=30s")).unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::ACCEPTED);
+        assert_eq!(
+            rx.recv().await,
+            Some(Shutdown {
+                grace: Some(Duration::from_secs(30))
+            })
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use hyper::Body;
+use linkerd_app_core::{identity, proxy::identity::LocalCrtKey, Error};
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Describes the proxy's local identity as JSON, including its current
+/// certificate chain, the trust anchors used to validate peers, and when the
+/// certificate will next be refreshed.
+pub(super) fn serve<B>(
+    local: Option<&LocalCrtKey>,
+    req: http::Request<B>,
+) -> Result<http::Response<Body>, Error> {
+    if req.method() != http::Method::GET {
+        return Ok(http::Response::builder()
+            .status(http::StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "GET")
+            .body(Body::empty())
+            .expect("builder with known status code must not fail"));
+    }
+
+    let body = match local {
+        Some(local) => describe(local, SystemTime::now()),
+        None => json!({ "enabled": false }),
+    };
+    let body = serde_json::to_string(&body)?;
+    Ok(http::Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(body.into())
+        .expect("Response must be valid"))
+}
+
+fn describe(local: &LocalCrtKey, now: SystemTime) -> serde_json::Value {
+    let crt_key = local.crt_key();
+    let chain = crt_key
+        .as_ref()
+        .map(|c| c.describe_chain())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|crt| describe_crt(crt, now))
+        .collect::<Vec<_>>();
+    // Once certified, report the trust anchors that are actually in use, as
+    // they may have been reloaded.
+    let trust_anchors = crt_key
+        .as_ref()
+        .map(|c| c.describe_trust_anchors())
+        .unwrap_or_else(|| local.trust_anchors().describe())
+        .into_iter()
+        .map(|ta| json!({ "subject": ta.subject, "spki_sha256": ta.spki_sha256 }))
+        .collect::<Vec<_>>();
+    let next_refresh = local.next_refresh();
+
+    json!({
+        "enabled": true,
+        "name": local.name().to_string(),
+        "certified": crt_key.is_some(),
+        "chain": chain,
+        "trust_anchors": trust_anchors,
+        "federated_domains": local.trust_anchors().federated_domains(),
+        "refreshes": local.refreshes(),
+        "next_refresh": next_refresh.and_then(unix_secs),
+        "next_refresh_in_ms": next_refresh.map(|t| millis_until(now, t)),
+    })
+}
+
+fn describe_crt(crt: identity::CrtInfo, now: SystemTime) -> serde_json::Value {
+    json!({
+        "dns_names": crt.dns_names,
+        "issuer": crt.issuer,
+        "not_before": crt.not_before.and_then(unix_secs),
+        "not_after": crt.not_after.and_then(unix_secs),
+        "expires_in_ms": crt.not_after.map(|t| millis_until(now, t)),
+        "sha256": crt.sha256,
+    })
+}
+
+fn unix_secs(t: SystemTime) -> Option<u64> {
+    t.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+/// Returns the milliseconds until `t`, or zero if it has passed.
+fn millis_until(now: SystemTime, t: SystemTime) -> u64 {
+    t.duration_since(now).unwrap_or_default().as_millis() as u64
+}
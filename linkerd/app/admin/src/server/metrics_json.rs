@@ -0,0 +1,243 @@
+use hyper::Body;
+use linkerd_app_core::Error;
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+
+/// Renders Prometheus-formatted metrics as JSON.
+///
+/// Metrics are grouped by family, e.g.:
+///
+/// ```json
+/// {
+///   "request_total": {
+///     "type": "counter",
+///     "help": "Total count of HTTP requests.",
+///     "series": [{ "labels": { "direction": "inbound" }, "value": 3 }]
+///   }
+/// }
+/// ```
+///
+/// Samples whose name differs from their family's (e.g. a histogram's
+/// `_bucket`, `_sum`, and `_count` series) include a `name`. Families may be
+/// selected with one or more `family` query parameters.
+pub(super) fn serve<B>(text: &str, req: http::Request<B>) -> Result<http::Response<Body>, Error> {
+    if req.method() != http::Method::GET {
+        return Ok(http::Response::builder()
+            .status(http::StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "GET")
+            .body(Body::empty())
+            .expect("builder with known status code must not fail"));
+    }
+
+    let filter = family_params(&req);
+    let body = serde_json::to_string(&to_json(text, filter.as_ref()))?;
+    Ok(http::Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(body.into())
+        .expect("Response must be valid"))
+}
+
+fn family_params<B>(req: &http::Request<B>) -> Option<HashSet<String>> {
+    let query = req.uri().query()?;
+    let families = query
+        .split('&')
+        .filter_map(|kv| {
+            let mut parts = kv.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some("family"), Some(v)) if !v.is_empty() => Some(v.to_string()),
+                _ => None,
+            }
+        })
+        .collect::<HashSet<_>>();
+    if families.is_empty() {
+        return None;
+    }
+    Some(families)
+}
+
+fn to_json(text: &str, filter: Option<&HashSet<String>>) -> Value {
+    let mut families = Map::new();
+    // The family most recently described by a `TYPE` comment.
+    let mut current: Option<&str> = None;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut parts = comment.trim_start().splitn(3, ' ');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some("HELP"), Some(name), help) => {
+                    family(&mut families, name)
+                        .insert("help".into(), help.unwrap_or_default().into());
+                }
+                (Some("TYPE"), Some(name), Some(kind)) => {
+                    family(&mut families, name).insert("type".into(), kind.trim().into());
+                    current = Some(name);
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let (name, labels, value) = match parse_sample(line) {
+            Some(sample) => sample,
+            None => {
+                tracing::debug!(%line, "Skipping malformed sample");
+                continue;
+            }
+        };
+
+        let family_name = match current {
+            Some(fam) if is_member(fam, name) => fam,
+            _ => name,
+        };
+        let mut sample = Map::new();
+        if name != family_name {
+            sample.insert("name".into(), name.into());
+        }
+        sample.insert("labels".into(), Value::Object(labels));
+        sample.insert("value".into(), value);
+
+        let series = family(&mut families, family_name)
+            .entry("series")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Value::Array(series) = series {
+            series.push(Value::Object(sample));
+        }
+    }
+
+    match filter {
+        Some(filter) => families
+            .into_iter()
+            .filter(|(name, _)| filter.contains(name))
+            .collect(),
+        None => Value::Object(families),
+    }
+}
+
+fn family<'m>(families: &'m mut Map<String, Value>, name: &str) -> &'m mut Map<String, Value> {
+    let family = families
+        .entry(name)
+        .or_insert_with(|| json!({ "series": [] }));
+    match family {
+        Value::Object(family) => family,
+        _ => unreachable!("families must be objects"),
+    }
+}
+
+/// Determines whether a sample belongs to the named family, including the
+/// component series of histograms and summaries.
+fn is_member(family: &str, name: &str) -> bool {
+    matches!(
+        name.strip_prefix(family),
+        Some("") | Some("_bucket") | Some("_sum") | Some("_count")
+    )
+}
+
+/// Parses a line like `name{key="value",...} 1.5`.
+fn parse_sample(line: &str) -> Option<(&str, Map<String, Value>, Value)> {
+    let name_end = line.find(|c| c == '{' || c == ' ')?;
+    let (name, mut rest) = line.split_at(name_end);
+
+    let mut labels = Map::new();
+    if let Some(mut s) = rest.strip_prefix('{') {
+        loop {
+            s = s.trim_start_matches(',');
+            if let Some(r) = s.strip_prefix('}') {
+                rest = r;
+                break;
+            }
+            let eq = s.find('=')?;
+            let key = &s[..eq];
+            let (value, r) = parse_label_value(s[eq + 1..].strip_prefix('"')?)?;
+            labels.insert(key.to_string(), value.into());
+            s = r;
+        }
+    }
+
+    // Ignore timestamps, if any.
+    let value = rest.split_whitespace().next()?;
+    let value = match value.parse::<f64>() {
+        Ok(v) if v.is_finite() => json!(v),
+        _ => value.into(),
+    };
+    Some((name, labels, value))
+}
+
+/// Reads a quoted label value, returning the unescaped value and the remaining
+/// input following its closing quote.
+fn parse_label_value(s: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &s[i + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "\
+# HELP request_total Total count of HTTP requests.
+# TYPE request_total counter
+request_total{direction=\"inbound\",authority=\"a\\\"b\"} 3
+request_total{direction=\"outbound\"} 1
+# HELP response_latency_ms Elapsed times.
+# TYPE response_latency_ms histogram
+response_latency_ms_bucket{le=\"10\"} 1
+response_latency_ms_bucket{le=\"+Inf\"} 2
+response_latency_ms_count 2
+response_latency_ms_sum 12
+";
+
+    #[test]
+    fn renders_families() {
+        assert_eq!(
+            to_json(TEXT, None),
+            json!({
+                "request_total": {
+                    "type": "counter",
+                    "help": "Total count of HTTP requests.",
+                    "series": [
+                        { "labels": { "direction": "inbound", "authority": "a\"b" }, "value": 3.0 },
+                        { "labels": { "direction": "outbound" }, "value": 1.0 },
+                    ],
+                },
+                "response_latency_ms": {
+                    "type": "histogram",
+                    "help": "Elapsed times.",
+                    "series": [
+                        { "name": "response_latency_ms_bucket", "labels": { "le": "10" }, "value": 1.0 },
+                        { "name": "response_latency_ms_bucket", "labels": { "le": "+Inf" }, "value": 2.0 },
+                        { "name": "response_latency_ms_count", "labels": {}, "value": 2.0 },
+                        { "name": "response_latency_ms_sum", "labels": {}, "value": 12.0 },
+                    ],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn filters_families() {
+        let req = http::Request::get("http://admin/metrics.json?family=request_total")
+            .body(())
+            .unwrap();
+        let filter = family_params(&req);
+        let json = to_json(TEXT, filter.as_ref());
+        assert!(json.get("request_total").is_some());
+        assert!(json.get("response_latency_ms").is_none());
+    }
+}
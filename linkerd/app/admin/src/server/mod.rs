@@ -2,13 +2,33 @@
 //!
 //! * `GET /metrics` -- reports prometheus-formatted metrics.
 //! * `GET /ready` -- returns 200 when the proxy is ready to participate in meshed
-//!   traffic.
+//!   traffic. With `?verbose=1`, returns JSON describing the readiness of each
+//!   of the proxy's subsystems.
+//! * `GET /metrics.json?family=<name>` -- reports metrics as JSON, optionally
+//!   limited to the named families.
 //! * `GET /live` -- returns 200 when the proxy is live.
 //! * `GET /proxy-log-level` -- returns the current proxy tracing filter.
 //! * `PUT /proxy-log-level` -- sets a new tracing filter.
 //! * `GET /tasks` -- returns a dump of spawned Tokio tasks (when enabled by the
 //!   tracing configuration).
+//! * `GET /debug/outbound/routes?authority=<name>` -- returns the profile, routes,
+//!   traffic split, and balancer endpoints discovered for a logical destination.
+//! * `GET /debug/connections` -- lists the proxy's open inbound and outbound
+//!   connections.
+//! * `GET /debug/stacks?format=dot` -- describes the layers of the proxy's
+//!   inbound and outbound stacks, as JSON or as a Graphviz graph.
+//! * `GET /debug/identity` -- describes the local identity's certificate chain,
+//!   its trust anchors, and when it will next be refreshed.
+//! * `POST /drain?grace=<duration>` -- stops accepting connections and shuts
+//!   down the proxy once in-flight work completes or the grace period elapses.
 //! * `POST /shutdown` -- shuts down the proxy.
+//!
+//! Endpoints that modify or inspect the proxy's state (i.e., all but `/metrics`,
+//! `/metrics.json`, `/ready`, and `/live`) are only served to clients on localhost or to meshed
+//! clients with a permitted identity. When a shutdown token is configured, `/shutdown` and
+//! `/drain` additionally require that it be presented in the `l5d-admin-token` header. Calls that
+//! shut down the proxy or change its log level are logged for auditing and, when an access log is
+//! configured, recorded in it as audit events.
 
 use futures::future;
 use http::StatusCode;
@@ -17,30 +37,58 @@ use hyper::{
     Request, Response,
 };
 use linkerd_app_core::{
+    access_log::{self, AccessLog},
+    introspect,
     metrics::{self as metrics, FmtMetrics},
-    proxy::http::ClientHandle,
-    trace, Error,
+    proxy::{http::ClientHandle, identity::LocalCrtKey},
+    tls, trace, transport, Error,
 };
+use linkerd_app_outbound::RouteTable;
 use std::{
+    collections::HashSet,
     future::Future,
     net::SocketAddr,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 use tokio::sync::mpsc;
 
+mod connections;
+mod drain;
+mod identity;
 mod level;
+mod metrics_json;
 mod readiness;
+mod routes;
+mod stacks;
 mod tasks;
 
-pub use self::readiness::{Latch, Readiness};
+pub use self::readiness::{Latch, Readiness, SubsystemReady, SubsystemState, Subsystems};
 
 #[derive(Clone)]
 pub struct Admin<M> {
     metrics: metrics::Serve<M>,
     tracing: trace::Handle,
     ready: Readiness,
-    shutdown_tx: mpsc::UnboundedSender<()>,
+    shutdown_tx: mpsc::UnboundedSender<Shutdown>,
+    routes: RouteTable,
+    transport: transport::Metrics,
+    stacks: introspect::Registry,
+    identity: Option<LocalCrtKey>,
+    audit_log: Option<AccessLog>,
+    permitted_client_ids: Arc<HashSet<tls::ClientId>>,
+    shutdown_token: Option<Arc<str>>,
+    client_id: Option<tls::ClientId>,
+    local_client: bool,
+}
+
+/// A request, issued via the admin server, to shut down the proxy.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Shutdown {
+    /// Bounds the time spent waiting for in-flight work to complete, if set.
+    pub grace: Option<Duration>,
 }
 
 #[derive(Clone)]
@@ -55,6 +103,9 @@ pub struct Serve<S> {
     inner: S,
 }
 
+/// The header in which clients present the shutdown token.
+const ADMIN_TOKEN_HEADER: &str = "l5d-admin-token";
+
 pub type ResponseFuture =
     Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send + 'static>>;
 
@@ -62,18 +113,89 @@ impl<M> Admin<M> {
     pub fn new(
         metrics: M,
         ready: Readiness,
-        shutdown_tx: mpsc::UnboundedSender<()>,
+        shutdown_tx: mpsc::UnboundedSender<Shutdown>,
         tracing: trace::Handle,
+        routes: RouteTable,
+        transport: transport::Metrics,
     ) -> Self {
         Self {
             metrics: metrics::Serve::new(metrics),
             ready,
             shutdown_tx,
             tracing,
+            routes,
+            transport,
+            stacks: Default::default(),
+            identity: None,
+            audit_log: None,
+            permitted_client_ids: Default::default(),
+            shutdown_token: None,
+            client_id: None,
+            local_client: false,
+        }
+    }
+
+    /// Permits meshed clients with the given identities to access privileged
+    /// endpoints.
+    pub fn with_permitted_client_ids(self, ids: Arc<HashSet<tls::ClientId>>) -> Self {
+        Self {
+            permitted_client_ids: ids,
+            ..self
+        }
+    }
+
+    /// Requires that clients present `token` to shut down or drain the proxy.
+    pub fn with_shutdown_token(self, token: Option<String>) -> Self {
+        Self {
+            shutdown_token: token.map(Into::into),
+            ..self
+        }
+    }
+
+    /// Describes the given stacks from `/debug/stacks`.
+    pub fn with_stacks(self, stacks: introspect::Registry) -> Self {
+        Self { stacks, ..self }
+    }
+
+    /// Describes the given local identity from `/debug/identity`.
+    pub fn with_identity(self, identity: Option<LocalCrtKey>) -> Self {
+        Self { identity, ..self }
+    }
+
+    /// Records privileged actions as audit events in the given access log.
+    pub fn with_audit_log(self, audit_log: Option<AccessLog>) -> Self {
+        Self { audit_log, ..self }
+    }
+
+    /// Returns a handle for serving requests from a client with the given
+    /// (authenticated) identity.
+    pub fn for_client(&self, client_id: Option<tls::ClientId>) -> Self
+    where
+        M: Clone,
+    {
+        Self {
+            client_id,
+            ..self.clone()
         }
     }
 
-    fn ready_rsp(&self) -> Response<Body> {
+    /// Returns a handle for serving requests from clients that are as trusted
+    /// as those on localhost (e.g. over a Unix domain socket).
+    pub fn for_local_client(&self) -> Self
+    where
+        M: Clone,
+    {
+        Self {
+            local_client: true,
+            ..self.clone()
+        }
+    }
+
+    fn ready_rsp<B>(&self, req: &Request<B>) -> Response<Body> {
+        if Self::is_verbose(req) {
+            return self.ready_verbose_rsp();
+        }
+
         if self.ready.is_ready() {
             Response::builder()
                 .status(StatusCode::OK)
@@ -88,6 +210,49 @@ impl<M> Admin<M> {
         }
     }
 
+    fn ready_verbose_rsp(&self) -> Response<Body> {
+        let ready = self.ready.is_ready();
+        let subsystems = self
+            .ready
+            .subsystems()
+            .into_iter()
+            .map(|(name, state)| {
+                let status = match state {
+                    SubsystemState::Failed(ref error) => {
+                        serde_json::json!({ "state": state.as_str(), "error": error })
+                    }
+                    _ => serde_json::json!({ "state": state.as_str() }),
+                };
+                (name.to_string(), status)
+            })
+            .collect::<serde_json::Map<_, _>>();
+        let body = serde_json::json!({
+            "ready": ready,
+            "state": if ready { "ready" } else { "initializing" },
+            "subsystems": subsystems,
+        });
+        let status = if ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        Response::builder()
+            .status(status)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string().into())
+            .expect("builder with known status code must not fail")
+    }
+
+    fn is_verbose<B>(req: &Request<B>) -> bool {
+        req.uri()
+            .query()
+            .map(|q| {
+                q.split('&')
+                    .any(|kv| kv == "verbose" || kv == "verbose=1" || kv == "verbose=true")
+            })
+            .unwrap_or(false)
+    }
+
     fn live_rsp() -> Response<Body> {
         Response::builder()
             .status(StatusCode::OK)
@@ -97,7 +262,7 @@ impl<M> Admin<M> {
     }
 
     fn shutdown(&self) -> Response<Body> {
-        if self.shutdown_tx.send(()).is_ok() {
+        if self.shutdown_tx.send(Shutdown { grace: None }).is_ok() {
             Response::builder()
                 .status(StatusCode::OK)
                 .header(http::header::CONTENT_TYPE, "text/plain")
@@ -134,14 +299,66 @@ impl<M> Admin<M> {
             .expect("builder with known status code must not fail")
     }
 
-    fn forbidden_not_localhost() -> Response<Body> {
+    fn forbidden_invalid_token() -> Response<Body> {
         Response::builder()
             .status(http::StatusCode::FORBIDDEN)
             .header(http::header::CONTENT_TYPE, "text/plain")
-            .body("Requests are only permitted from localhost.".into())
+            .body("A valid admin token is required.".into())
             .expect("builder with known status code must not fail")
     }
 
+    fn forbidden_unauthorized() -> Response<Body> {
+        Response::builder()
+            .status(http::StatusCode::FORBIDDEN)
+            .header(http::header::CONTENT_TYPE, "text/plain")
+            .body("Requests are only permitted from localhost or authorized clients.".into())
+            .expect("builder with known status code must not fail")
+    }
+
+    /// Determines whether the client may access privileged endpoints.
+    fn client_is_authorized<B>(&self, req: &Request<B>) -> bool {
+        if self.local_client || Self::client_is_localhost(req) {
+            return true;
+        }
+        self.client_id
+            .as_ref()
+            .map(|id| self.permitted_client_ids.contains(id))
+            .unwrap_or(false)
+    }
+
+    /// Determines whether the request presents the configured shutdown token,
+    /// if one is required.
+    fn shutdown_token_is_valid<B>(&self, req: &Request<B>) -> bool {
+        let token = match self.shutdown_token.as_ref() {
+            Some(token) => token,
+            None => return true,
+        };
+        req.headers()
+            .get(ADMIN_TOKEN_HEADER)
+            .map(|v| constant_time_eq(v.as_bytes(), token.as_bytes()))
+            .unwrap_or(false)
+    }
+
+    /// Records a privileged action taken by a client.
+    fn audit<B>(&self, req: &Request<B>, action: &'static str) {
+        let client_addr = req.extensions().get::<ClientHandle>().map(|c| c.addr);
+        tracing::info!(
+            %action,
+            client.addr = ?client_addr,
+            client.id = ?self.client_id,
+            local = self.local_client,
+            "Admin request"
+        );
+        if let Some(log) = self.audit_log.as_ref() {
+            log.audit(access_log::AuditEvent {
+                action,
+                client_addr,
+                identity: self.client_id.as_ref().map(|id| id.0.clone()),
+                local: self.local_client,
+            });
+        }
+    }
+
     fn client_is_localhost<B>(req: &Request<B>) -> bool {
         req.extensions()
             .get::<ClientHandle>()
@@ -168,7 +385,7 @@ where
     fn call(&mut self, req: Request<B>) -> Self::Future {
         match req.uri().path() {
             "/live" => Box::pin(future::ok(Self::live_rsp())),
-            "/ready" => Box::pin(future::ok(self.ready_rsp())),
+            "/ready" => Box::pin(future::ok(self.ready_rsp(&req))),
             "/metrics" => {
                 let rsp = self.metrics.serve(req).unwrap_or_else(|error| {
                     ::tracing::error!(%error, "Failed to format metrics");
@@ -176,8 +393,19 @@ where
                 });
                 Box::pin(future::ok(rsp))
             }
+            "/metrics.json" => {
+                let rsp =
+                    metrics_json::serve(&self.metrics.to_text(), req).unwrap_or_else(|error| {
+                        ::tracing::error!(%error, "Failed to format metrics");
+                        Self::internal_error_rsp(error)
+                    });
+                Box::pin(future::ok(rsp))
+            }
             "/proxy-log-level" => {
-                if Self::client_is_localhost(&req) {
+                if self.client_is_authorized(&req) {
+                    if req.method() == http::Method::PUT {
+                        self.audit(&req, "set-log-level");
+                    }
                     let level = self.tracing.level().cloned();
                     Box::pin(async move {
                         let rsp = match level {
@@ -192,22 +420,91 @@ where
                         Ok(rsp)
                     })
                 } else {
-                    Box::pin(future::ok(Self::forbidden_not_localhost()))
+                    Box::pin(future::ok(Self::forbidden_unauthorized()))
                 }
             }
             "/shutdown" => {
                 if req.method() == http::Method::POST {
-                    if Self::client_is_localhost(&req) {
+                    if !self.client_is_authorized(&req) {
+                        Box::pin(future::ok(Self::forbidden_unauthorized()))
+                    } else if !self.shutdown_token_is_valid(&req) {
+                        tracing::warn!("Rejected shutdown without a valid admin token");
+                        Box::pin(future::ok(Self::forbidden_invalid_token()))
+                    } else {
+                        self.audit(&req, "shutdown");
                         Box::pin(future::ok(self.shutdown()))
+                    }
+                } else {
+                    Box::pin(future::ok(Self::method_not_allowed()))
+                }
+            }
+            "/drain" => {
+                if req.method() == http::Method::POST {
+                    if !self.client_is_authorized(&req) {
+                        Box::pin(future::ok(Self::forbidden_unauthorized()))
+                    } else if !self.shutdown_token_is_valid(&req) {
+                        tracing::warn!("Rejected drain without a valid admin token");
+                        Box::pin(future::ok(Self::forbidden_invalid_token()))
                     } else {
-                        Box::pin(future::ok(Self::forbidden_not_localhost()))
+                        self.audit(&req, "drain");
+                        let rsp = drain::serve(&self.shutdown_tx, &self.transport, req)
+                            .unwrap_or_else(|error| {
+                                tracing::error!(%error, "Failed to drain");
+                                Self::internal_error_rsp(error)
+                            });
+                        Box::pin(future::ok(rsp))
                     }
                 } else {
                     Box::pin(future::ok(Self::method_not_allowed()))
                 }
             }
+            "/debug/outbound/routes" => {
+                if self.client_is_authorized(&req) {
+                    let rsp = routes::serve(&self.routes, req).unwrap_or_else(|error| {
+                        tracing::error!(%error, "Failed to render outbound routes");
+                        Self::internal_error_rsp(error)
+                    });
+                    Box::pin(future::ok(rsp))
+                } else {
+                    Box::pin(future::ok(Self::forbidden_unauthorized()))
+                }
+            }
+            "/debug/connections" => {
+                if self.client_is_authorized(&req) {
+                    let rsp = connections::serve(&self.transport, req).unwrap_or_else(|error| {
+                        tracing::error!(%error, "Failed to list connections");
+                        Self::internal_error_rsp(error)
+                    });
+                    Box::pin(future::ok(rsp))
+                } else {
+                    Box::pin(future::ok(Self::forbidden_unauthorized()))
+                }
+            }
+            "/debug/stacks" => {
+                if self.client_is_authorized(&req) {
+                    let rsp = stacks::serve(&self.stacks, req).unwrap_or_else(|error| {
+                        tracing::error!(%error, "Failed to render stacks");
+                        Self::internal_error_rsp(error)
+                    });
+                    Box::pin(future::ok(rsp))
+                } else {
+                    Box::pin(future::ok(Self::forbidden_unauthorized()))
+                }
+            }
+            "/debug/identity" => {
+                if self.client_is_authorized(&req) {
+                    let rsp =
+                        identity::serve(self.identity.as_ref(), req).unwrap_or_else(|error| {
+                            tracing::error!(%error, "Failed to describe identity");
+                            Self::internal_error_rsp(error)
+                        });
+                    Box::pin(future::ok(rsp))
+                } else {
+                    Box::pin(future::ok(Self::forbidden_unauthorized()))
+                }
+            }
             path if path.starts_with("/tasks") => {
-                if Self::client_is_localhost(&req) {
+                if self.client_is_authorized(&req) {
                     let rsp = match self.tracing.tasks() {
                         Some(tasks) => tasks::serve(tasks, req).unwrap_or_else(|error| {
                             tracing::error!(%error, "Failed to fetch tasks");
@@ -217,7 +514,7 @@ where
                     };
                     Box::pin(future::ok(rsp))
                 } else {
-                    Box::pin(future::ok(Self::forbidden_not_localhost()))
+                    Box::pin(future::ok(Self::forbidden_unauthorized()))
                 }
             }
             _ => Box::pin(future::ok(Self::not_found())),
@@ -225,16 +522,44 @@ where
     }
 }
 
+/// Compares secrets without exiting early on the first mismatched byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use http::method::Method;
-    use std::time::Duration;
     use tokio::{sync::mpsc, time::timeout};
     use tower::util::ServiceExt;
 
     const TIMEOUT: Duration = Duration::from_secs(1);
 
+    #[tokio::test]
+    async fn subsystem_ready_completes_when_set() {
+        let subsystems = Subsystems::default();
+        let dst = subsystems.register("destination");
+        assert!(timeout(Duration::from_millis(10), dst.ready())
+            .await
+            .is_err());
+
+        dst.fail("unreachable");
+        assert!(timeout(Duration::from_millis(10), dst.ready())
+            .await
+            .is_err());
+
+        let waiting = tokio::spawn({
+            let dst = dst.clone();
+            async move { dst.ready().await }
+        });
+        dst.set();
+        timeout(TIMEOUT, waiting)
+            .await
+            .expect("timeout")
+            .expect("task");
+    }
+
     #[tokio::test]
     async fn ready_when_latches_dropped() {
         let (r, l0) = Readiness::new();
@@ -242,7 +567,8 @@ mod tests {
 
         let (_, t) = trace::Settings::default().build();
         let (s, _) = mpsc::unbounded_channel();
-        let admin = Admin::new((), r, s, t);
+        let (m, _) = transport::Metrics::new(Duration::from_secs(10));
+        let admin = Admin::new((), r, s, t, RouteTable::default(), m);
         macro_rules! call {
             () => {{
                 let r = Request::builder()
@@ -263,4 +589,174 @@ mod tests {
         drop(l1);
         assert_eq!(call!().status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn ready_verbose() {
+        let subsystems = Subsystems::default();
+        let identity = subsystems.register("identity");
+        let dst = subsystems.register("destination");
+        let (r, l) = Readiness::new();
+        let r = r.with_subsystems(subsystems);
+
+        let (_, t) = trace::Settings::default().build();
+        let (s, _) = mpsc::unbounded_channel();
+        let (m, _) = transport::Metrics::new(Duration::from_secs(10));
+        let admin = Admin::new((), r, s, t, RouteTable::default(), m);
+        let call = |admin: Admin<()>| async move {
+            let r = Request::builder()
+                .method(Method::GET)
+                .uri("http://0.0.0.0/ready?verbose=1")
+                .body(Body::empty())
+                .unwrap();
+            let rsp = timeout(TIMEOUT, admin.oneshot(r))
+                .await
+                .expect("timeout")
+                .expect("call");
+            let status = rsp.status();
+            let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+            let json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+            (status, json)
+        };
+
+        let (status, json) = call(admin.clone()).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "ready": false,
+                "state": "initializing",
+                "subsystems": {
+                    "identity": { "state": "initializing" },
+                    "destination": { "state": "initializing" },
+                },
+            })
+        );
+
+        identity.set();
+        dst.fail("connection refused");
+        l.release();
+        let (status, json) = call(admin).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "ready": true,
+                "state": "ready",
+                "subsystems": {
+                    "identity": { "state": "ready" },
+                    "destination": { "state": "failed", "error": "connection refused" },
+                },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn privileged_endpoints_require_permitted_client() {
+        let (r, _) = Readiness::new();
+        let (_, t) = trace::Settings::default().build();
+        let (s, _) = mpsc::unbounded_channel();
+        let (m, _) = transport::Metrics::new(Duration::from_secs(10));
+        let id = |s: &str| tls::ClientId(s.parse().unwrap());
+        let admin =
+            Admin::new((), r, s, t, RouteTable::default(), m).with_permitted_client_ids(Arc::new(
+                vec![id(
+                    "admin.linkerd.serviceaccount.identity.linkerd.cluster.local",
+                )]
+                .into_iter()
+                .collect(),
+            ));
+        let call = |admin: Admin<()>, path: &str| {
+            let r = Request::builder()
+                .method(Method::GET)
+                .uri(format!("http://0.0.0.0{}", path))
+                .body(Body::empty())
+                .unwrap();
+            timeout(TIMEOUT, admin.oneshot(r))
+        };
+
+        let rsp = call(admin.for_client(None), "/debug/connections").await;
+        assert_eq!(
+            rsp.expect("timeout").expect("call").status(),
+            StatusCode::FORBIDDEN
+        );
+
+        let client = admin.for_client(Some(id(
+            "web.ns.serviceaccount.identity.linkerd.cluster.local",
+        )));
+        let rsp = call(client, "/debug/connections").await;
+        assert_eq!(
+            rsp.expect("timeout").expect("call").status(),
+            StatusCode::FORBIDDEN
+        );
+
+        let client = admin.for_client(Some(id(
+            "admin.linkerd.serviceaccount.identity.linkerd.cluster.local",
+        )));
+        let rsp = call(client, "/debug/connections").await;
+        assert_eq!(
+            rsp.expect("timeout").expect("call").status(),
+            StatusCode::OK
+        );
+
+        let rsp = call(admin.for_client(None), "/live").await;
+        assert_eq!(
+            rsp.expect("timeout").expect("call").status(),
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn identity_disabled() {
+        let (r, _) = Readiness::new();
+        let (_, t) = trace::Settings::default().build();
+        let (s, _) = mpsc::unbounded_channel();
+        let (m, _) = transport::Metrics::new(Duration::from_secs(10));
+        let admin = Admin::new((), r, s, t, RouteTable::default(), m).for_local_client();
+        let r = Request::builder()
+            .method(Method::GET)
+            .uri("http://0.0.0.0/debug/identity")
+            .body(Body::empty())
+            .unwrap();
+        let rsp = timeout(TIMEOUT, admin.oneshot(r))
+            .await
+            .expect("timeout")
+            .expect("call");
+        assert_eq!(rsp.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(rsp.into_body()).await.unwrap();
+        let json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+        assert_eq!(json, serde_json::json!({ "enabled": false }));
+    }
+
+    #[tokio::test]
+    async fn shutdown_requires_token() {
+        let (r, _) = Readiness::new();
+        let (_, t) = trace::Settings::default().build();
+        let (s, mut shutdown_rx) = mpsc::unbounded_channel();
+        let (m, _) = transport::Metrics::new(Duration::from_secs(10));
+        let admin = Admin::new((), r, s, t, RouteTable::default(), m)
+            .with_shutdown_token(Some("s3cr3t".to_string()))
+            .for_local_client();
+        let call = |token: Option<&str>| {
+            let mut r = Request::builder()
+                .method(Method::POST)
+                .uri("http://0.0.0.0/shutdown");
+            if let Some(token) = token {
+                r = r.header(ADMIN_TOKEN_HEADER, token);
+            }
+            timeout(
+                TIMEOUT,
+                admin.clone().oneshot(r.body(Body::empty()).unwrap()),
+            )
+        };
+
+        let rsp = call(None).await.expect("timeout").expect("call");
+        assert_eq!(rsp.status(), StatusCode::FORBIDDEN);
+        let rsp = call(Some("wrong")).await.expect("timeout").expect("call");
+        assert_eq!(rsp.status(), StatusCode::FORBIDDEN);
+        assert!(shutdown_rx.try_recv().is_err());
+
+        let rsp = call(Some("s3cr3t")).await.expect("timeout").expect("call");
+        assert_eq!(rsp.status(), StatusCode::OK);
+        assert_eq!(shutdown_rx.try_recv().ok(), Some(Shutdown { grace: None }));
+    }
 }
@@ -1,23 +1,76 @@
-use std::sync::{Arc, Weak};
+use linkerd_app_core::metrics::{metrics, FmtLabels, FmtMetrics, Gauge};
+use std::{
+    fmt,
+    sync::{Arc, Mutex, Weak},
+};
+use tokio::sync::watch;
+
+metrics! {
+    proxy_subsystem_ready: Gauge {
+        "Indicates whether each of the proxy's subsystems has initialized (1) or not (0)"
+    },
+    proxy_subsystem_failed: Gauge {
+        "Indicates whether each of the proxy's subsystems has failed to initialize"
+    }
+}
 
 /// Tracks the processes's readiness to serve traffic.
 ///
 /// Once `is_ready()` returns true, it will never return false.
 #[derive(Clone, Debug)]
-pub struct Readiness(Weak<()>);
+pub struct Readiness {
+    latch: Weak<()>,
+    subsystems: Subsystems,
+}
 
 /// When all latches are dropped, the process is considered ready.
 #[derive(Clone, Debug)]
 pub struct Latch(Arc<()>);
 
+/// Tracks the state of the proxy's individual subsystems (e.g. identity or
+/// the destination controller), for diagnostic purposes.
+///
+/// Subsystem states do not affect the process's overall readiness.
+#[derive(Clone, Debug, Default)]
+pub struct Subsystems(Arc<Mutex<Vec<(&'static str, watch::Receiver<SubsystemState>)>>>);
+
+/// Updates the state of a single subsystem.
+#[derive(Clone, Debug)]
+pub struct SubsystemReady {
+    tx: Arc<watch::Sender<SubsystemState>>,
+    rx: watch::Receiver<SubsystemState>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubsystemState {
+    Initializing,
+    Ready,
+    Failed(String),
+}
+
+struct SubsystemLabel(&'static str);
+
 impl Readiness {
     pub fn new() -> (Readiness, Latch) {
         let r = Arc::new(());
-        (Readiness(Arc::downgrade(&r)), Latch(r))
+        let readiness = Readiness {
+            latch: Arc::downgrade(&r),
+            subsystems: Subsystems::default(),
+        };
+        (readiness, Latch(r))
+    }
+
+    pub fn with_subsystems(self, subsystems: Subsystems) -> Self {
+        Self { subsystems, ..self }
     }
 
     pub fn is_ready(&self) -> bool {
-        self.0.upgrade().is_none()
+        self.latch.upgrade().is_none()
+    }
+
+    /// Returns the state of each registered subsystem.
+    pub fn subsystems(&self) -> Vec<(&'static str, SubsystemState)> {
+        self.subsystems.states()
     }
 }
 
@@ -34,3 +87,99 @@ impl Latch {
         drop(self);
     }
 }
+
+// === impl Subsystems ===
+
+impl Subsystems {
+    /// Registers a subsystem that is initializing.
+    pub fn register(&self, name: &'static str) -> SubsystemReady {
+        let (tx, rx) = watch::channel(SubsystemState::Initializing);
+        self.0
+            .lock()
+            .expect("subsystems lock poisoned")
+            .push((name, rx.clone()));
+        SubsystemReady {
+            tx: Arc::new(tx),
+            rx,
+        }
+    }
+
+    fn states(&self) -> Vec<(&'static str, SubsystemState)> {
+        self.0
+            .lock()
+            .expect("subsystems lock poisoned")
+            .iter()
+            .map(|(name, rx)| (*name, rx.borrow().clone()))
+            .collect()
+    }
+}
+
+impl FmtMetrics for Subsystems {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let states = self.states();
+        if states.is_empty() {
+            return Ok(());
+        }
+
+        proxy_subsystem_ready.fmt_help(f)?;
+        for (name, state) in states.iter() {
+            let ready = Gauge::from((*state == SubsystemState::Ready) as u64);
+            proxy_subsystem_ready.fmt_metric_labeled(f, &ready, SubsystemLabel(*name))?;
+        }
+
+        proxy_subsystem_failed.fmt_help(f)?;
+        for (name, state) in states.iter() {
+            let failed = Gauge::from(matches!(state, SubsystemState::Failed(_)) as u64);
+            proxy_subsystem_failed.fmt_metric_labeled(f, &failed, SubsystemLabel(*name))?;
+        }
+
+        Ok(())
+    }
+}
+
+// === impl SubsystemReady ===
+
+impl SubsystemReady {
+    /// Marks the subsystem as ready.
+    pub fn set(&self) {
+        let _ = self.tx.send(SubsystemState::Ready);
+    }
+
+    /// Marks the subsystem as having failed to initialize.
+    pub fn fail(&self, error: impl fmt::Display) {
+        let _ = self.tx.send(SubsystemState::Failed(error.to_string()));
+    }
+
+    pub fn is_ready(&self) -> bool {
+        *self.rx.borrow() == SubsystemState::Ready
+    }
+
+    /// Completes once the subsystem has been marked as ready.
+    pub async fn ready(&self) {
+        let mut rx = self.rx.clone();
+        while *rx.borrow() != SubsystemState::Ready {
+            // The sender is held by `self`, so it cannot be dropped.
+            if rx.changed().await.is_err() {
+                return futures::future::pending().await;
+            }
+        }
+    }
+}
+
+// === impl SubsystemState ===
+
+impl SubsystemState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Initializing => "initializing",
+            Self::Ready => "ready",
+            Self::Failed(_) => "failed",
+        }
+    }
+}
+
+impl FmtLabels for SubsystemLabel {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "subsystem=\"{}\"", self.0)
+    }
+}
@@ -0,0 +1,165 @@
+use hyper::Body;
+use linkerd_app_core::{proxy::api_resolve::ProtocolHint, Error, NameAddr};
+use linkerd_app_outbound::RouteTable;
+use serde_json::json;
+
+/// Renders the discovery state for the logical destination named by the
+/// request's `authority` query parameter.
+///
+/// When no authority is specified, the known logical destinations are listed.
+pub(super) fn serve<B>(
+    routes: &RouteTable,
+    req: http::Request<B>,
+) -> Result<http::Response<Body>, Error> {
+    if req.method() != http::Method::GET {
+        return Ok(http::Response::builder()
+            .status(http::StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "GET")
+            .body(Body::empty())
+            .expect("builder with known status code must not fail"));
+    }
+
+    let authority = match authority_param(&req) {
+        None => {
+            let addrs = routes
+                .logical_addrs()
+                .into_iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>();
+            return Ok(rsp_json(
+                http::StatusCode::OK,
+                json!({ "authorities": addrs }),
+            ));
+        }
+        Some(Ok(authority)) => authority,
+        Some(Err(error)) => {
+            return Ok(rsp_json(
+                http::StatusCode::BAD_REQUEST,
+                json!({ "error": error.to_string() }),
+            ))
+        }
+    };
+
+    let snapshot = match routes.get(&authority) {
+        Some(snapshot) => snapshot,
+        None => {
+            return Ok(rsp_json(
+                http::StatusCode::NOT_FOUND,
+                json!({ "error": format!("{} has not been discovered", authority) }),
+            ))
+        }
+    };
+
+    let profile = snapshot.profile;
+    let routes = profile
+        .http_routes
+        .iter()
+        .map(|(matches, route)| {
+            json!({
+                "match": format!("{:?}", matches),
+                "labels": &**route.labels(),
+                "timeout_ms": route.timeout().map(|t| t.as_millis() as u64),
+                "retryable": route.retries().is_some(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let targets = profile
+        .targets
+        .iter()
+        .map(|t| json!({ "addr": t.addr.to_string(), "weight": t.weight }))
+        .collect::<Vec<_>>();
+    let balancers = snapshot
+        .endpoints
+        .into_iter()
+        .map(|(concrete, endpoints)| {
+            let endpoints = endpoints.map(|eps| {
+                eps.into_iter()
+                    .map(|(addr, meta)| {
+                        json!({
+                            "addr": addr.to_string(),
+                            "identity": meta.identity().map(|id| id.to_string()),
+                            "protocol_hint": match meta.protocol_hint() {
+                                ProtocolHint::Unknown => "unknown",
+                                ProtocolHint::Http2 => "h2",
+                            },
+                            "labels": &*meta.labels(),
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            });
+            json!({ "concrete": concrete.to_string(), "endpoints": endpoints })
+        })
+        .collect::<Vec<_>>();
+
+    let body = json!({
+        "authority": authority.to_string(),
+        "opaque_protocol": profile.opaque_protocol,
+        "routes": routes,
+        "targets": targets,
+        "balancers": balancers,
+    });
+    Ok(rsp_json(http::StatusCode::OK, body))
+}
+
+fn authority_param<B>(req: &http::Request<B>) -> Option<Result<NameAddr, Error>> {
+    let query = req.uri().query()?;
+    let value = query.split('&').find_map(|kv| {
+        let mut parts = kv.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some("authority"), Some(v)) if !v.is_empty() => Some(v),
+            _ => None,
+        }
+    })?;
+    let addr = match value.parse::<NameAddr>() {
+        Ok(addr) => Ok(addr),
+        // Authorities without an explicit port are assumed to use HTTP's default port.
+        Err(_) => NameAddr::from_str_and_port(value, 80),
+    };
+    Some(addr.map_err(Into::into))
+}
+
+fn rsp_json(status: http::StatusCode, body: serde_json::Value) -> http::Response<Body> {
+    http::Response::builder()
+        .status(status)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string().into())
+        .expect("Response must be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn req(uri: &str) -> http::Request<()> {
+        http::Request::get(uri).body(()).unwrap()
+    }
+
+    #[test]
+    fn parses_authority() {
+        let authority = |uri| authority_param(&req(uri)).map(|r| r.unwrap().to_string());
+        assert_eq!(authority("http://admin/debug/outbound/routes"), None);
+        assert_eq!(
+            authority("http://admin/debug/outbound/routes?authority=web.ns.svc.cluster.local:8080"),
+            Some("web.ns.svc.cluster.local:8080".to_string())
+        );
+        assert_eq!(
+            authority("http://admin/debug/outbound/routes?x=y&authority=web.ns"),
+            Some("web.ns:80".to_string())
+        );
+        assert!(
+            authority_param(&req("http://admin/debug/outbound/routes?authority=:80"))
+                .unwrap()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn unknown_authority() {
+        let rsp = serve(
+            &RouteTable::default(),
+            req("http://admin/debug/outbound/routes?authority=web.ns:80"),
+        )
+        .unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::NOT_FOUND);
+    }
+}
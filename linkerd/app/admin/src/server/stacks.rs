@@ -0,0 +1,42 @@
+use hyper::Body;
+use linkerd_app_core::{introspect, Error};
+
+/// Renders the structure of the proxy's stacks.
+///
+/// By default, stacks are described as JSON, including the stacks each one
+/// wraps, the number of services held in its cache, and the number of its
+/// services that are in failfast. With `?format=dot`, the stacks are rendered
+/// as a Graphviz graph.
+pub(super) fn serve<B>(
+    stacks: &introspect::Registry,
+    req: http::Request<B>,
+) -> Result<http::Response<Body>, Error> {
+    if req.method() != http::Method::GET {
+        return Ok(http::Response::builder()
+            .status(http::StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "GET")
+            .body(Body::empty())
+            .expect("builder with known status code must not fail"));
+    }
+
+    let (content_type, body) = if is_dot(&req) {
+        ("text/vnd.graphviz", stacks.to_dot())
+    } else {
+        (
+            "application/json",
+            serde_json::to_string(&stacks.to_json())?,
+        )
+    };
+    Ok(http::Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, content_type)
+        .body(body.into())
+        .expect("Response must be valid"))
+}
+
+fn is_dot<B>(req: &http::Request<B>) -> bool {
+    req.uri()
+        .query()
+        .map(|q| q.split('&').any(|kv| kv == "format=dot"))
+        .unwrap_or(false)
+}
@@ -1,4 +1,5 @@
 use linkerd_app_core::{
+    access_log::AccessLog,
     classify,
     config::ServerConfig,
     detect, drain, errors,
@@ -7,23 +8,62 @@ use linkerd_app_core::{
     serve,
     svc::{self, ExtractParam, InsertParam, Param},
     tls, trace,
-    transport::{self, listen::Bind, ClientAddr, Local, OrigDstAddr, Remote, ServerAddr},
+    transport::{
+        self, listen::Bind, ClientAddr, Local, OrigDstAddr, Origin, OriginNetworks, Remote,
+        ServerAddr,
+    },
     Error, Result,
 };
-use linkerd_app_inbound as inbound;
-use std::{pin::Pin, time::Duration};
+use linkerd_app_inbound::{
+    self as inbound,
+    policy::{AllowPolicy, DeniedUnauthorized, Permit, ServerPolicy},
+};
+use linkerd_app_outbound::RouteTable;
+use std::{
+    collections::HashSet,
+    io,
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    pin::Pin,
+    time::Duration,
+};
 use thiserror::Error;
-use tokio::sync::mpsc;
-use tracing::debug;
+use tokio::{net::UnixListener, sync::mpsc};
+use tracing::{debug, info_span, warn, Instrument};
 
 #[derive(Clone, Debug)]
 pub struct Config {
     pub server: ServerConfig,
+
+    /// Whether the admin server listens on `server`'s TCP address.
+    pub tcp_enabled: bool,
+
+    /// If set, the admin server also listens on a Unix domain socket at this
+    /// path.
+    pub uds_path: Option<PathBuf>,
+
     pub metrics_retain_idle: Duration,
+
+    /// If set, the proxy does not report readiness until the destination and
+    /// policy controllers have been reached, or until this timeout elapses.
+    pub control_plane_readiness_timeout: Option<Duration>,
+
+    pub transport_aggregation: transport::labels::Aggregation,
+    pub origin_networks: OriginNetworks,
+
+    /// Identities of meshed clients that may access privileged endpoints.
+    pub permitted_client_ids: HashSet<tls::server::ClientId>,
+
+    /// If set, a token that must be presented to shut down the proxy.
+    pub shutdown_token: Option<String>,
+
+    /// The policy that authorizes requests to the admin server.
+    pub policy: ServerPolicy,
 }
 
 pub struct Task {
-    pub listen_addr: Local<ServerAddr>,
+    pub listen_addr: Option<Local<ServerAddr>>,
+    pub uds_path: Option<PathBuf>,
     pub latch: crate::Latch,
     pub serve: Pin<Box<dyn std::future::Future<Output = ()> + Send + 'static>>,
 }
@@ -41,6 +81,8 @@ struct Tcp {
     addr: Local<ServerAddr>,
     client: Remote<ClientAddr>,
     tls: tls::ConditionalServerTls,
+    origin: Origin,
+    policy: AllowPolicy,
 }
 
 #[derive(Clone, Debug)]
@@ -49,6 +91,13 @@ struct Http {
     version: http::Version,
 }
 
+/// An HTTP request that has been authorized by the admin server's policy.
+#[derive(Clone, Debug)]
+struct Permitted {
+    permit: Permit,
+    http: Http,
+}
+
 #[derive(Clone)]
 struct TlsParams {
     identity: Option<LocalCrtKey>,
@@ -68,22 +117,56 @@ impl Config {
         metrics: inbound::Metrics,
         trace: trace::Handle,
         drain: drain::Watch,
-        shutdown: mpsc::UnboundedSender<()>,
+        shutdown: mpsc::UnboundedSender<crate::Shutdown>,
+        routes: RouteTable,
+        subsystems: crate::Subsystems,
+        audit_log: Option<AccessLog>,
     ) -> Result<Task, Error>
     where
         R: FmtMetrics + Clone + Send + Sync + Unpin + 'static,
         B: Bind<ServerConfig>,
         B::Addrs: svc::Param<Remote<ClientAddr>> + svc::Param<Local<ServerAddr>>,
     {
-        let (listen_addr, listen) = bind.bind(&self.server)?;
+        let listen = if self.tcp_enabled {
+            Some(bind.bind(&self.server)?)
+        } else {
+            None
+        };
+        let uds = match self.uds_path {
+            Some(path) => Some((bind_uds(&path)?, path)),
+            None => None,
+        };
+        let origin_networks = self.origin_networks;
+        let policy = self.policy;
 
         let (ready, latch) = crate::server::Readiness::new();
-        let admin = crate::server::Admin::new(report, ready, shutdown, trace);
-        let admin = svc::stack(move |_| admin.clone())
-            .push(metrics.proxy.http_endpoint.to_layer::<classify::Response, _, Http>())
+        let ready = ready.with_subsystems(subsystems);
+        let admin = crate::server::Admin::new(
+            report,
+            ready,
+            shutdown,
+            trace,
+            routes,
+            metrics.proxy.transport.clone(),
+        )
+        .with_permitted_client_ids(self.permitted_client_ids.into())
+        .with_shutdown_token(self.shutdown_token)
+        .with_stacks(metrics.proxy.introspect.clone())
+        .with_identity(identity.clone())
+        .with_audit_log(audit_log);
+        let local_admin = admin.for_local_client();
+        let admin = svc::stack(move |p: Permitted| admin.for_client(p.http.client_id()))
+            .push(metrics.proxy.http_endpoint.to_layer::<classify::Response, _, Permitted>())
+            .push_map_target(|(permit, http): (Permit, Http)| Permitted { permit, http })
+            // Authorizes each request with the admin server's policy.
+            .push(inbound::policy::NewAuthorizeHttp::layer_enforced(&metrics))
             .push_on_service(
                 svc::layers()
                     .push(errors::NewRespond::layer(|error: Error| -> Result<_> {
+                        let cause = errors::root_cause(&*error);
+                        if cause.is::<DeniedUnauthorized>() {
+                            return Ok(errors::SyntheticHttpResponse::permission_denied(cause));
+                        }
                         tracing::warn!(%error, "Unexpected error");
                         Ok(errors::SyntheticHttpResponse::unexpected_error())
                     }))
@@ -135,12 +218,16 @@ impl Config {
             .push(detect::NewDetectService::layer(detect::Config::<http::DetectHttp>::from_timeout(DETECT_TIMEOUT)))
             .push(transport::metrics::NewServer::layer(metrics.proxy.transport))
             .push_map_target(move |(tls, addrs): (tls::ConditionalServerTls, B::Addrs)| {
-                // TODO(ver): We should enforce policy here; but we need to permit liveness probes
-                // for destination pods to startup...
+                // Requests are authorized by the admin server's policy; privileged endpoints are
+                // further restricted by the admin server itself.
+                let client: Remote<ClientAddr> = addrs.param();
+                let addr: Local<ServerAddr> = addrs.param();
                 Tcp {
                     tls,
-                    client: addrs.param(),
-                    addr: addrs.param(),
+                    origin: origin_networks.classify(client.ip()),
+                    client,
+                    policy: AllowPolicy::fixed(OrigDstAddr(addr.into()), policy.clone()),
+                    addr,
                 }
             })
             .push(svc::BoxNewService::layer())
@@ -149,15 +236,119 @@ impl Config {
             }))
             .into_inner();
 
-        let serve = Box::pin(serve::serve(listen, admin, drain.signaled()));
+        let (listen_addr, serve_tcp) = match listen {
+            Some((addr, listen)) => {
+                let serve = serve::serve(listen, admin, drain.clone().signaled());
+                (Some(addr), Some(serve))
+            }
+            None => (None, None),
+        };
+        let (uds_path, serve_uds) = match uds {
+            Some((listen, path)) => {
+                let serve = serve_uds(listen, local_admin, drain)
+                    .instrument(info_span!("uds", path = %path.display()));
+                (Some(path), Some(serve))
+            }
+            None => (None, None),
+        };
+
+        let serve = Box::pin(async move {
+            tokio::join!(
+                async move {
+                    if let Some(serve) = serve_tcp {
+                        serve.await
+                    }
+                },
+                async move {
+                    if let Some(serve) = serve_uds {
+                        serve.await
+                    }
+                },
+            );
+        });
         Ok(Task {
             listen_addr,
+            uds_path,
             latch,
             serve,
         })
     }
 }
 
+/// Binds a Unix domain socket, replacing a stale socket left at `path` by a
+/// prior process.
+fn bind_uds(path: &Path) -> io::Result<UnixListener> {
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        // A socket left behind by a prior process is removed so that it may be
+        // rebound, but a socket that is still being served is left in place.
+        if meta.file_type().is_socket() {
+            match std::os::unix::net::UnixStream::connect(path) {
+                Ok(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("{} is in use", path.display()),
+                    ))
+                }
+                Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+                    std::fs::remove_file(path)?
+                }
+                Err(_) => {}
+            }
+        }
+    }
+    let l = std::os::unix::net::UnixListener::bind(path)?;
+    // Ensure that O_NONBLOCK is set on the socket before using it with Tokio.
+    l.set_nonblocking(true)?;
+    UnixListener::from_std(l)
+}
+
+/// Serves the admin server on a Unix domain socket.
+///
+/// Access to the socket is governed by its filesystem permissions, so its
+/// clients are afforded the same privileges as clients on localhost. These
+/// connections bypass the TCP server's TLS detection and transport metrics.
+async fn serve_uds<M>(listen: UnixListener, admin: crate::server::Admin<M>, drain: drain::Watch)
+where
+    M: FmtMetrics + Clone + Send + Sync + Unpin + 'static,
+{
+    let server = hyper::server::conn::Http::new();
+    let shutdown = drain.clone().signaled();
+    tokio::pin!(shutdown);
+    loop {
+        let io = tokio::select! {
+            res = listen.accept() => match res {
+                Ok((io, _)) => io,
+                Err(error) => {
+                    warn!(%error, "Server failed to accept connection");
+                    continue;
+                }
+            },
+            _ = &mut shutdown => return,
+        };
+
+        let mut conn = server.serve_connection(io, admin.clone());
+        let drain = drain.clone();
+        tokio::spawn(
+            async move {
+                tokio::select! {
+                    res = &mut conn => {
+                        if let Err(error) = res {
+                            debug!(%error, "Connection closed");
+                        }
+                    }
+                    shutdown = drain.signaled() => {
+                        Pin::new(&mut conn).graceful_shutdown();
+                        if let Err(error) = shutdown.release_after(conn).await {
+                            debug!(%error, "Connection closed");
+                        }
+                    }
+                }
+            }
+            .in_current_span(),
+        );
+    }
+}
+
 // === impl Tcp ===
 
 impl Param<transport::labels::Key> for Tcp {
@@ -165,14 +356,26 @@ impl Param<transport::labels::Key> for Tcp {
         transport::labels::Key::inbound_server(
             self.tls.clone(),
             self.addr.into(),
-            // TODO(ver) enforce policies on the proxy's admin port.
-            metrics::ServerLabel("default:admin".to_string()),
+            self.policy.server_label(),
+            self.origin,
         )
     }
 }
 
 // === impl Http ===
 
+impl Http {
+    fn client_id(&self) -> Option<tls::server::ClientId> {
+        match self.tcp.tls {
+            tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                client_id: Some(ref id),
+                ..
+            }) => Some(id.clone()),
+            _ => None,
+        }
+    }
+}
+
 impl Param<http::Version> for Http {
     fn param(&self) -> http::Version {
         self.version
@@ -187,20 +390,38 @@ impl Param<OrigDstAddr> for Http {
 
 impl Param<metrics::ServerLabel> for Http {
     fn param(&self) -> metrics::ServerLabel {
-        metrics::ServerLabel("default:admin".to_string())
+        self.tcp.policy.server_label()
     }
 }
 
-impl Param<metrics::EndpointLabels> for Http {
+impl Param<AllowPolicy> for Http {
+    fn param(&self) -> AllowPolicy {
+        self.tcp.policy.clone()
+    }
+}
+
+impl Param<Remote<ClientAddr>> for Http {
+    fn param(&self) -> Remote<ClientAddr> {
+        self.tcp.client
+    }
+}
+
+impl Param<tls::ConditionalServerTls> for Http {
+    fn param(&self) -> tls::ConditionalServerTls {
+        self.tcp.tls.clone()
+    }
+}
+
+// === impl Permitted ===
+
+impl Param<metrics::EndpointLabels> for Permitted {
     fn param(&self) -> metrics::EndpointLabels {
         metrics::InboundEndpointLabels {
-            tls: self.tcp.tls.clone(),
+            tls: self.http.tcp.tls.clone(),
             authority: None,
-            target_addr: self.tcp.addr.into(),
-            policy: metrics::AuthzLabels {
-                server: self.param(),
-                authz: "default:all-unauthenticated".to_string(),
-            },
+            target_addr: self.http.tcp.addr.into(),
+            policy: self.permit.labels.clone(),
+            origin: self.http.tcp.origin,
         }
         .into()
     }
@@ -0,0 +1,1606 @@
+//! Writes a structured record for each HTTP request handled by the proxy.
+//!
+//! Records are written one per line to a configured destination, either as
+//! JSON objects or in the Apache common or combined log formats. Requests
+//! never wait on the destination: records are enqueued on a bounded buffer
+//! that is drained by a background task, and records are dropped (and
+//! counted) when the buffer is full.
+//!
+//! Connections that are forwarded as opaque TCP streams are logged as well:
+//! a record is written when each connection is opened and when it is closed.
+//! Because the Apache formats describe HTTP requests, connection records are
+//! always written as JSON. Privileged admin actions may also be recorded as
+//! audit events alongside access records.
+//!
+//! Records may also be sent, one per datagram, to a Unix datagram socket or to
+//! a syslog endpoint, so that node-level log agents can collect them without
+//! sharing a volume with the proxy.
+//!
+//! To limit the volume of records, a `Filter` may restrict logging to
+//! particular status classes, routes, or authorities, and may sample
+//! successful responses so that only one in every N is logged.
+
+use crate::{
+    dns,
+    http_tracing::Precedence,
+    identity,
+    io::{self, Write},
+    metrics::{metrics, Counter, Direction, FmtMetrics},
+    proxy::http::{self, h1, normalize_uri::DefaultAuthority, ClientHandle},
+    svc::{self, Param},
+    Addr, Error, NameMatch,
+};
+use bytes::Buf;
+use futures::prelude::*;
+use linkerd_errno::Errno;
+use linkerd_trace_context as trace_context;
+use parking_lot::Mutex;
+use pin_project::{pin_project, pinned_drop};
+use std::{
+    fmt,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    pin::Pin,
+    process,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt, BufWriter},
+    net::{UdpSocket, UnixDatagram},
+    sync::mpsc,
+};
+use tracing::{debug, warn};
+
+metrics! {
+    access_log_records_total: Counter {
+        "Total count of access log records written"
+    },
+    access_log_dropped_records_total: Counter {
+        "Total count of access log records dropped because the log could not keep up"
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub destination: Destination,
+    pub format: Format,
+    pub filter: Filter,
+    pub buffer_capacity: usize,
+
+    /// Whether request query strings are logged. Query strings may carry
+    /// credentials or other sensitive values, so only paths are logged by
+    /// default.
+    pub include_query: bool,
+}
+
+/// Determines which records are written.
+///
+/// Each criterion matches all records when it is empty.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    /// Status classes to log, e.g. `5` for 5XX responses. Requests that fail
+    /// without a response are considered to be 5XX.
+    pub status_classes: Vec<u16>,
+
+    /// Routes to log, by the value of their `rt_route` label.
+    pub routes: Vec<String>,
+
+    /// Suffixes of the request authorities to log.
+    pub authorities: NameMatch,
+
+    /// If set, only one in every N responses that are not 5XX is logged.
+    pub success_sample_interval: Option<u64>,
+}
+
+/// Where access log records are written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Destination {
+    Stdout,
+    Stderr,
+    /// A file descriptor inherited from the proxy's parent process.
+    Fd(u32),
+    Path(PathBuf),
+    /// A Unix datagram socket, to which each record is sent as a datagram.
+    Unix(PathBuf),
+    /// A syslog endpoint, to which each record is sent as an RFC 5424
+    /// message.
+    Syslog(SyslogAddr),
+}
+
+/// The address of a syslog endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyslogAddr {
+    /// A Unix datagram socket, e.g. `/dev/log`.
+    Unix(PathBuf),
+    Udp(SocketAddr),
+}
+
+/// How access log records are encoded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    /// The Apache common log format, i.e. `%h %l %u %t "%r" %>s %b`.
+    ApacheCommon,
+    /// The Apache combined log format, which extends the common log format
+    /// with the request's `referer` and `user-agent` headers.
+    ApacheCombined,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("not a valid access log destination: {0}")]
+pub struct InvalidDestination(String);
+
+pub type Task = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// A handle for enqueuing access log records.
+#[derive(Clone, Debug)]
+pub struct AccessLog {
+    tx: mpsc::Sender<Entry>,
+    metrics: Arc<Metrics>,
+    filter: Arc<Filter>,
+    successes: Arc<AtomicU64>,
+    include_query: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Report(Option<Arc<Metrics>>);
+
+/// Records labels describing the route that handled a request.
+///
+/// A handle is added to the extensions of each logged request so that inner
+/// layers may annotate its record.
+#[derive(Clone, Debug, Default)]
+pub struct RouteLabels(Arc<Mutex<Vec<(String, String)>>>);
+
+/// Describes a privileged action taken by an admin client.
+#[derive(Clone, Debug)]
+pub struct AuditEvent {
+    pub action: &'static str,
+    pub client_addr: Option<SocketAddr>,
+    pub identity: Option<identity::Name>,
+
+    /// Indicates whether the client is trusted as though it were on localhost.
+    pub local: bool,
+}
+
+/// Describes the target of a logged TCP connection.
+#[derive(Clone, Debug)]
+pub struct ConnectionTarget {
+    pub addr: Addr,
+
+    /// The identity of the connection's peer, i.e. the client of an inbound
+    /// connection or the server of an outbound connection.
+    pub identity: Option<identity::Name>,
+}
+
+#[derive(Clone, Debug)]
+pub struct NewLogRequests<N> {
+    inner: N,
+    log: Option<AccessLog>,
+    direction: Direction,
+    precedence: Precedence,
+}
+
+#[derive(Clone, Debug)]
+pub struct LogRequests<S> {
+    inner: S,
+    log: Option<AccessLog>,
+    direction: Direction,
+    precedence: Precedence,
+    target_addr: Option<http::uri::Authority>,
+    identity: Option<identity::Name>,
+}
+
+#[derive(Clone, Debug)]
+pub struct NewLogConnections<N> {
+    inner: N,
+    log: Option<AccessLog>,
+    direction: Direction,
+}
+
+#[derive(Clone, Debug)]
+pub struct LogConnections<S> {
+    inner: S,
+    log: Option<AccessLog>,
+    direction: Direction,
+    target: ConnectionTarget,
+}
+
+/// Counts the bytes read from and written to a logged connection's client.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionSensor(Arc<ConnectionBytes>);
+
+/// Counts the bytes of a logged response, emitting its record once the
+/// response body has been dropped.
+#[pin_project(PinnedDrop)]
+#[derive(Debug)]
+pub struct ResponseBody<B> {
+    #[pin]
+    inner: B,
+    pending: Option<Pending>,
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    records: Counter,
+    dropped: Counter,
+}
+
+#[derive(Debug, Default)]
+struct ConnectionBytes {
+    read: AtomicU64,
+    written: AtomicU64,
+}
+
+/// Writes a connection's close record when the connection completes or is
+/// dropped.
+#[derive(Debug)]
+struct PendingConnection {
+    log: AccessLog,
+    record: ConnectionRecord,
+    start: Instant,
+    bytes: ConnectionSensor,
+}
+
+#[derive(Debug)]
+enum Entry {
+    Request(Record),
+    Connection(ConnectionRecord),
+    Audit(AuditRecord),
+}
+
+#[derive(Debug)]
+struct AuditRecord {
+    timestamp: SystemTime,
+    event: AuditEvent,
+}
+
+enum Output {
+    Stream(BufWriter<Box<dyn AsyncWrite + Send + Unpin>>),
+    /// Each record is sent as a single datagram, optionally framed as a syslog
+    /// message.
+    Datagram {
+        socket: Reconnect,
+        syslog: bool,
+    },
+}
+
+/// A datagram socket that is reconnected, with backoff, when sends fail (e.g.
+/// because the syslog daemon has restarted). Records are dropped while the
+/// socket is disconnected.
+struct Reconnect {
+    addr: DatagramAddr,
+    socket: Option<Datagram>,
+    backoff: Duration,
+    retry_at: Instant,
+}
+
+#[derive(Clone, Debug)]
+enum DatagramAddr {
+    Unix(PathBuf),
+    Udp(SocketAddr),
+}
+
+enum Datagram {
+    Unix(UnixDatagram),
+    Udp(UdpSocket),
+}
+
+#[derive(Debug)]
+struct Pending {
+    log: AccessLog,
+    record: Record,
+    start: Instant,
+    labels: RouteLabels,
+}
+
+#[derive(Clone, Debug)]
+struct Record {
+    timestamp: SystemTime,
+    direction: Direction,
+    client_addr: Option<SocketAddr>,
+    target_addr: Option<String>,
+    identity: Option<identity::Name>,
+    method: http::Method,
+    authority: Option<http::uri::Authority>,
+    path: String,
+    version: http::Version,
+    referer: Option<String>,
+    user_agent: Option<String>,
+    status: Option<http::StatusCode>,
+    latency: Duration,
+    response_bytes: u64,
+    route_labels: Vec<(String, String)>,
+    trace_id: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct ConnectionRecord {
+    timestamp: SystemTime,
+    event: ConnectionEvent,
+    direction: Direction,
+    client_addr: Option<SocketAddr>,
+    target_addr: Addr,
+    identity: Option<identity::Name>,
+    duration: Option<Duration>,
+    bytes_in: u64,
+    bytes_out: u64,
+    error: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ConnectionEvent {
+    Open,
+    Close,
+}
+
+// === impl Config ===
+
+impl Config {
+    /// Opens the configured destination, returning a handle for enqueuing
+    /// records and a task that writes them.
+    pub fn build(self) -> io::Result<(AccessLog, Task)> {
+        let out = match self.destination {
+            Destination::Stdout => Output::stream(tokio::io::stdout()),
+            Destination::Stderr => Output::stream(tokio::io::stderr()),
+            Destination::Fd(fd) => Output::stream(open(format!("/dev/fd/{}", fd).as_ref())?),
+            Destination::Path(ref path) => Output::stream(open(path)?),
+            Destination::Unix(ref path) => Output::Datagram {
+                socket: Reconnect::new(DatagramAddr::Unix(path.clone())),
+                syslog: false,
+            },
+            Destination::Syslog(SyslogAddr::Unix(ref path)) => Output::Datagram {
+                socket: Reconnect::new(DatagramAddr::Unix(path.clone())),
+                syslog: true,
+            },
+            Destination::Syslog(SyslogAddr::Udp(addr)) => Output::Datagram {
+                socket: Reconnect::new(DatagramAddr::Udp(addr)),
+                syslog: true,
+            },
+        };
+        debug!(destination = ?self.destination, "Writing access log");
+
+        let (tx, rx) = mpsc::channel(self.buffer_capacity);
+        let metrics = Arc::new(Metrics::default());
+        let task = Box::pin(write_records(rx, self.format, out, metrics.clone()));
+        let log = AccessLog {
+            tx,
+            metrics,
+            filter: Arc::new(self.filter),
+            successes: Arc::new(AtomicU64::new(0)),
+            include_query: self.include_query,
+        };
+        Ok((log, task))
+    }
+}
+
+fn open(path: &Path) -> io::Result<tokio::fs::File> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    Ok(tokio::fs::File::from_std(file))
+}
+
+async fn write_records(
+    mut rx: mpsc::Receiver<Entry>,
+    format: Format,
+    mut out: Output,
+    metrics: Arc<Metrics>,
+) {
+    let mut line = Vec::new();
+    while let Some(entry) = rx.recv().await {
+        // Write all of the records that are already available before flushing.
+        let mut next = Some(entry);
+        while let Some(entry) = next.take() {
+            line.clear();
+            if let Err(error) = out.encode(format, &entry, &mut line) {
+                warn!(%error, "Failed to encode access log record");
+                metrics.dropped.incr();
+            } else {
+                match out.write(&line).await {
+                    Ok(()) => metrics.records.incr(),
+                    Err(error) if error.kind() == io::ErrorKind::NotConnected => {
+                        debug!(%error, "Dropped access log record");
+                        metrics.dropped.incr();
+                    }
+                    Err(error) => {
+                        warn!(%error, "Failed to write access log record");
+                        metrics.dropped.incr();
+                    }
+                }
+            }
+            next = rx.recv().now_or_never().flatten();
+        }
+
+        if let Err(error) = out.flush().await {
+            warn!(%error, "Failed to flush access log");
+        }
+    }
+    debug!("Access log closed");
+}
+
+// === impl Output ===
+
+impl Output {
+    fn stream(out: impl AsyncWrite + Send + Unpin + 'static) -> Self {
+        Self::Stream(BufWriter::new(Box::new(out)))
+    }
+
+    fn encode(&self, format: Format, entry: &Entry, buf: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            Self::Stream(_) => {
+                format.encode(entry, buf)?;
+                buf.push(b'\n');
+                Ok(())
+            }
+            Self::Datagram { syslog: false, .. } => format.encode(entry, buf),
+            Self::Datagram { syslog: true, .. } => {
+                write_syslog_header(entry, SystemTime::now(), buf)?;
+                format.encode(entry, buf)
+            }
+        }
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Stream(out) => out.write_all(buf).await,
+            Self::Datagram { socket, .. } => socket.send(buf).await,
+        }
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stream(out) => out.flush().await,
+            Self::Datagram { .. } => Ok(()),
+        }
+    }
+}
+
+// === impl Reconnect ===
+
+impl Reconnect {
+    const MIN_BACKOFF: Duration = Duration::from_millis(100);
+    const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+    /// Connects to `addr`. A destination that is not yet available is retried
+    /// when records are written.
+    fn new(addr: DatagramAddr) -> Self {
+        let mut reconnect = Self {
+            addr,
+            socket: None,
+            backoff: Self::MIN_BACKOFF,
+            retry_at: Instant::now(),
+        };
+        if let Err(error) = reconnect.connect() {
+            warn!(%error, addr = ?reconnect.addr, "Failed to connect to access log destination");
+        }
+        reconnect
+    }
+
+    fn connect(&mut self) -> io::Result<()> {
+        let res = match self.addr {
+            DatagramAddr::Unix(ref path) => Datagram::unix(path),
+            DatagramAddr::Udp(addr) => Datagram::udp(addr),
+        };
+        match res {
+            Ok(socket) => {
+                self.socket = Some(socket);
+                Ok(())
+            }
+            Err(error) => {
+                self.disconnected();
+                Err(error)
+            }
+        }
+    }
+
+    /// Schedules the next connection attempt.
+    fn disconnected(&mut self) {
+        self.socket = None;
+        self.retry_at = Instant::now() + self.backoff;
+        self.backoff = (self.backoff * 2).min(Self::MAX_BACKOFF);
+    }
+
+    async fn send(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.socket.is_none() {
+            if Instant::now() < self.retry_at {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    "access log destination is disconnected",
+                ));
+            }
+            debug!(addr = ?self.addr, "Reconnecting to access log destination");
+            self.connect()?;
+        }
+
+        let socket = self.socket.as_ref().expect("socket must be connected");
+        match socket.send(buf).await {
+            Ok(()) => {
+                self.backoff = Self::MIN_BACKOFF;
+                Ok(())
+            }
+            // A record that doesn't fit in a datagram doesn't indicate that
+            // the destination is unavailable.
+            Err(error) if error.kind() == io::ErrorKind::WriteZero => Err(error),
+            Err(error) => {
+                warn!(%error, addr = ?self.addr, "Access log destination disconnected");
+                self.disconnected();
+                Err(error)
+            }
+        }
+    }
+}
+
+// === impl Datagram ===
+
+impl Datagram {
+    fn unix(path: &Path) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Self::Unix(socket))
+    }
+
+    fn udp(addr: SocketAddr) -> io::Result<Self> {
+        let bind: SocketAddr = if addr.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = std::net::UdpSocket::bind(bind)?;
+        socket.connect(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self::Udp(UdpSocket::from_std(socket)?))
+    }
+
+    async fn send(&self, buf: &[u8]) -> io::Result<()> {
+        let sz = match self {
+            Self::Unix(socket) => socket.send(buf).await?,
+            Self::Udp(socket) => socket.send(buf).await?,
+        };
+        if sz < buf.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "access log record was truncated",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Writes an RFC 5424 header, e.g.
+/// `<190>1 2021-01-01T00:00:00.000Z - linkerd-proxy 1 access - `.
+///
+/// Records are logged with the `local7` facility. Access records have the
+/// `info` severity and audit records have the `notice` severity.
+fn write_syslog_header(entry: &Entry, now: SystemTime, buf: &mut Vec<u8>) -> io::Result<()> {
+    const LOCAL7: u8 = 23;
+    const NOTICE: u8 = 5;
+    const INFO: u8 = 6;
+
+    let (severity, msgid) = match entry {
+        Entry::Audit(_) => (NOTICE, "audit"),
+        Entry::Request(_) | Entry::Connection(_) => (INFO, "access"),
+    };
+    write!(
+        buf,
+        "<{}>1 {} - linkerd-proxy {} {} - ",
+        LOCAL7 * 8 + severity,
+        fmt_rfc3339(now),
+        process::id(),
+        msgid
+    )
+}
+
+// === impl Format ===
+
+impl Default for Format {
+    fn default() -> Self {
+        Self::Json
+    }
+}
+
+impl Format {
+    fn encode(&self, entry: &Entry, buf: &mut Vec<u8>) -> io::Result<()> {
+        let record = match entry {
+            Entry::Request(record) => record,
+            Entry::Connection(record) => {
+                return serde_json::to_writer(buf, &record.to_json()).map_err(Into::into)
+            }
+            Entry::Audit(record) => {
+                return serde_json::to_writer(buf, &record.to_json()).map_err(Into::into)
+            }
+        };
+        match self {
+            Self::Json => serde_json::to_writer(buf, &record.to_json()).map_err(Into::into),
+            Self::ApacheCommon => record.write_apache(buf, false),
+            Self::ApacheCombined => record.write_apache(buf, true),
+        }
+    }
+}
+
+// === impl Destination ===
+
+/// Parses `stdout`, `stderr`, `fd:<n>`, or a file path.
+impl FromStr for Destination {
+    type Err = InvalidDestination;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err(InvalidDestination(s.to_string())),
+            "stdout" => Ok(Self::Stdout),
+            "stderr" => Ok(Self::Stderr),
+            s => {
+                if let Some(fd) = s.strip_prefix("fd:") {
+                    return fd
+                        .parse()
+                        .map(Self::Fd)
+                        .map_err(|_| InvalidDestination(s.to_string()));
+                }
+                if let Some(path) = s.strip_prefix("unix:") {
+                    if path.is_empty() {
+                        return Err(InvalidDestination(s.to_string()));
+                    }
+                    return Ok(Self::Unix(path.into()));
+                }
+                if let Some(addr) = s.strip_prefix("syslog:") {
+                    return addr
+                        .parse()
+                        .map(Self::Syslog)
+                        .map_err(|_| InvalidDestination(s.to_string()));
+                }
+                Ok(Self::Path(s.into()))
+            }
+        }
+    }
+}
+
+// === impl SyslogAddr ===
+
+/// Parses a UDP socket address (e.g. `127.0.0.1:514`) or the path of a Unix
+/// datagram socket (e.g. `/dev/log`).
+impl FromStr for SyslogAddr {
+    type Err = InvalidDestination;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(addr) = s.parse() {
+            return Ok(Self::Udp(addr));
+        }
+        if s.starts_with('/') {
+            return Ok(Self::Unix(s.into()));
+        }
+        Err(InvalidDestination(s.to_string()))
+    }
+}
+
+// === impl AccessLog ===
+
+impl AccessLog {
+    pub fn report(&self) -> Report {
+        Report(Some(self.metrics.clone()))
+    }
+
+    fn send(&self, record: Record) {
+        if !self.filter.matches(&record) || !self.sample(&record) {
+            return;
+        }
+        self.enqueue(Entry::Request(record));
+    }
+
+    /// Records a privileged action taken by an admin client. Audit events are
+    /// not filtered.
+    pub fn audit(&self, event: AuditEvent) {
+        self.enqueue(Entry::Audit(AuditRecord {
+            timestamp: SystemTime::now(),
+            event,
+        }));
+    }
+
+    /// Connection records are not filtered.
+    fn send_connection(&self, record: ConnectionRecord) {
+        self.enqueue(Entry::Connection(record));
+    }
+
+    fn enqueue(&self, entry: Entry) {
+        if self.tx.try_send(entry).is_err() {
+            self.metrics.dropped.incr();
+        }
+    }
+
+    /// Errors are always logged, but other responses may be sampled.
+    fn sample(&self, record: &Record) -> bool {
+        let interval = match self.filter.success_sample_interval {
+            Some(n) if n > 1 => n,
+            _ => return true,
+        };
+        if record.status_class() == 5 {
+            return true;
+        }
+        self.successes.fetch_add(1, Ordering::Relaxed) % interval == 0
+    }
+}
+
+// === impl Filter ===
+
+impl Filter {
+    fn matches(&self, record: &Record) -> bool {
+        if !self.status_classes.is_empty() && !self.status_classes.contains(&record.status_class())
+        {
+            return false;
+        }
+
+        if !self.routes.is_empty() {
+            let route = record
+                .route_labels
+                .iter()
+                .find(|(k, _)| k == "rt_route")
+                .map(|(_, v)| v);
+            if !route.map(|r| self.routes.contains(r)).unwrap_or(false) {
+                return false;
+            }
+        }
+
+        if !self.authorities.is_empty() {
+            let name = record
+                .authority
+                .as_ref()
+                .and_then(|a| dns::Name::from_str(a.host()).ok());
+            if !name.map(|n| self.authorities.matches(&n)).unwrap_or(false) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+// === impl Report ===
+
+impl Report {
+    pub fn disabled() -> Self {
+        Self(None)
+    }
+}
+
+impl FmtMetrics for Report {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics = match self.0 {
+            Some(ref metrics) => metrics,
+            None => return Ok(()),
+        };
+
+        access_log_records_total.fmt_help(f)?;
+        access_log_records_total.fmt_metric(f, &metrics.records)?;
+
+        access_log_dropped_records_total.fmt_help(f)?;
+        access_log_dropped_records_total.fmt_metric(f, &metrics.dropped)?;
+
+        Ok(())
+    }
+}
+
+// === impl RouteLabels ===
+
+impl RouteLabels {
+    /// Adds labels to the request's record.
+    pub fn extend(&self, labels: impl IntoIterator<Item = (String, String)>) {
+        self.0.lock().extend(labels);
+    }
+
+    fn take(&self) -> Vec<(String, String)> {
+        std::mem::take(&mut *self.0.lock())
+    }
+}
+
+// === impl NewLogRequests ===
+
+impl<N> NewLogRequests<N> {
+    pub fn layer(
+        log: Option<AccessLog>,
+        direction: Direction,
+        precedence: Precedence,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            log: log.clone(),
+            direction,
+            precedence,
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewLogRequests<N>
+where
+    T: Param<DefaultAuthority> + Param<Option<identity::Name>>,
+    N: svc::NewService<T>,
+{
+    type Service = LogRequests<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let DefaultAuthority(target_addr) = target.param();
+        let identity = target.param();
+        LogRequests {
+            log: self.log.clone(),
+            direction: self.direction,
+            precedence: self.precedence,
+            target_addr,
+            identity,
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl LogRequests ===
+
+impl<S, B, RspB> svc::Service<http::Request<B>> for LogRequests<S>
+where
+    S: svc::Service<http::Request<B>, Response = http::Response<RspB>>,
+    S::Error: Send,
+    S::Future: Send + 'static,
+    RspB: Send + 'static,
+{
+    type Response = http::Response<ResponseBody<RspB>>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, S::Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        let log = match self.log.clone() {
+            Some(log) => log,
+            None => {
+                return Box::pin(self.inner.call(req).map_ok(|rsp| {
+                    rsp.map(|inner| ResponseBody {
+                        inner,
+                        pending: None,
+                    })
+                }))
+            }
+        };
+
+        // Allow inner layers to annotate the record.
+        let labels = RouteLabels::default();
+        req.extensions_mut().insert(labels.clone());
+
+        let record = Record {
+            timestamp: SystemTime::now(),
+            direction: self.direction,
+            client_addr: req.extensions().get::<ClientHandle>().map(|c| c.addr),
+            target_addr: self.target_addr.as_ref().map(ToString::to_string),
+            identity: self.identity.clone(),
+            method: req.method().clone(),
+            authority: req
+                .uri()
+                .authority()
+                .cloned()
+                .or_else(|| h1::authority_from_host(&req)),
+            path: request_path(req.uri(), log.include_query),
+            version: req.version(),
+            referer: header_str(&req, http::header::REFERER),
+            user_agent: header_str(&req, http::header::USER_AGENT),
+            status: None,
+            latency: Duration::default(),
+            response_bytes: 0,
+            route_labels: Vec::new(),
+            trace_id: trace_context::request_trace_id(&req, self.precedence)
+                .map(|id| id.to_string()),
+        };
+        let mut pending = Pending {
+            log,
+            record,
+            start: Instant::now(),
+            labels,
+        };
+
+        Box::pin(self.inner.call(req).map(move |res| match res {
+            Ok(rsp) => {
+                pending.record.status = Some(rsp.status());
+                Ok(rsp.map(|inner| ResponseBody {
+                    inner,
+                    pending: Some(pending),
+                }))
+            }
+            Err(error) => {
+                pending.complete();
+                Err(error)
+            }
+        }))
+    }
+}
+
+fn request_path(uri: &http::Uri, include_query: bool) -> String {
+    match uri.path_and_query() {
+        Some(pq) if include_query => pq.as_str().to_string(),
+        Some(pq) => pq.path().to_string(),
+        None => "/".to_string(),
+    }
+}
+
+fn header_str<B>(req: &http::Request<B>, name: http::header::HeaderName) -> Option<String> {
+    let value = req.headers().get(name)?;
+    Some(String::from_utf8_lossy(value.as_bytes()).into_owned())
+}
+
+// === impl NewLogConnections ===
+
+impl<N> NewLogConnections<N> {
+    pub fn layer(
+        log: Option<AccessLog>,
+        direction: Direction,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            log: log.clone(),
+            direction,
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewLogConnections<N>
+where
+    T: Param<ConnectionTarget>,
+    N: svc::NewService<T>,
+{
+    type Service = LogConnections<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        LogConnections {
+            log: self.log.clone(),
+            direction: self.direction,
+            target: target.param(),
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl LogConnections ===
+
+impl<S, I> svc::Service<I> for LogConnections<S>
+where
+    I: io::PeerAddr,
+    S: svc::Service<io::SensorIo<I, ConnectionSensor>, Response = ()>,
+    S::Error: Into<Error>,
+    S::Future: Send + 'static,
+{
+    type Response = ();
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        let bytes = ConnectionSensor::default();
+        let record = ConnectionRecord {
+            timestamp: SystemTime::now(),
+            event: ConnectionEvent::Open,
+            direction: self.direction,
+            client_addr: io.peer_addr().ok(),
+            target_addr: self.target.addr.clone(),
+            identity: self.target.identity.clone(),
+            duration: None,
+            bytes_in: 0,
+            bytes_out: 0,
+            error: None,
+        };
+        let mut pending = self.log.clone().map(|log| {
+            log.send_connection(record.clone());
+            PendingConnection {
+                log,
+                record,
+                start: Instant::now(),
+                bytes: bytes.clone(),
+            }
+        });
+
+        let io = io::SensorIo::new(io, bytes);
+        Box::pin(self.inner.call(io).err_into::<Error>().map(move |res| {
+            if let (Err(error), Some(pending)) = (res.as_ref(), pending.as_mut()) {
+                pending.record.error = Some(error.to_string());
+            }
+            res
+        }))
+    }
+}
+
+// === impl ConnectionSensor ===
+
+impl io::Sensor for ConnectionSensor {
+    fn record_read(&mut self, sz: usize) {
+        self.0.read.fetch_add(sz as u64, Ordering::Relaxed);
+    }
+
+    fn record_write(&mut self, sz: usize) {
+        self.0.written.fetch_add(sz as u64, Ordering::Relaxed);
+    }
+
+    fn record_close(&mut self, _: Option<Errno>) {}
+
+    fn record_error<T>(&mut self, op: io::Poll<T>) -> io::Poll<T> {
+        op
+    }
+}
+
+// === impl PendingConnection ===
+
+impl Drop for PendingConnection {
+    fn drop(&mut self) {
+        let mut record = self.record.clone();
+        record.event = ConnectionEvent::Close;
+        record.timestamp = SystemTime::now();
+        record.duration = Some(self.start.elapsed());
+        record.bytes_in = self.bytes.0.read.load(Ordering::Relaxed);
+        record.bytes_out = self.bytes.0.written.load(Ordering::Relaxed);
+        self.log.send_connection(record);
+    }
+}
+
+// === impl ResponseBody ===
+
+impl<B: http_body::Body> http_body::Body for ResponseBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let frame = futures::ready!(this.inner.poll_data(cx));
+        if let (Some(Ok(data)), Some(pending)) = (frame.as_ref(), this.pending.as_mut()) {
+            pending.record.response_bytes += data.remaining() as u64;
+        }
+        Poll::Ready(frame)
+    }
+
+    #[inline]
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx)
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[pinned_drop]
+impl<B> PinnedDrop for ResponseBody<B> {
+    fn drop(self: Pin<&mut Self>) {
+        if let Some(pending) = self.project().pending.take() {
+            pending.complete();
+        }
+    }
+}
+
+// === impl Pending ===
+
+impl Pending {
+    fn complete(self) {
+        let Self {
+            log,
+            mut record,
+            start,
+            labels,
+        } = self;
+        record.latency = start.elapsed();
+        record.route_labels = labels.take();
+        log.send(record);
+    }
+}
+
+// === impl Record ===
+
+impl Record {
+    /// Returns the class of the response status, e.g. `2` for 2XX responses.
+    /// Requests that failed without a response are considered to be 5XX.
+    fn status_class(&self) -> u16 {
+        self.status.map(|s| s.as_u16() / 100).unwrap_or(5)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let route_labels = self
+            .route_labels
+            .iter()
+            .map(|(k, v)| (k.clone(), serde_json::Value::from(v.clone())))
+            .collect::<serde_json::Map<_, _>>();
+        serde_json::json!({
+            "timestamp": fmt_rfc3339(self.timestamp),
+            "direction": self.direction.to_string(),
+            "client_addr": self.client_addr.map(|a| a.to_string()),
+            "target_addr": self.target_addr,
+            "identity": self.identity.as_ref().map(|id| id.as_ref().to_string()),
+            "method": self.method.as_str(),
+            "authority": self.authority.as_ref().map(|a| a.as_str()),
+            "path": self.path,
+            "status": self.status.map(|s| s.as_u16()),
+            "latency_ms": self.latency.as_secs_f64() * 1_000.0,
+            "response_bytes": self.response_bytes,
+            "route_labels": route_labels,
+            "trace_id": self.trace_id,
+        })
+    }
+
+    /// Writes the record in the Apache common log format, e.g.:
+    ///
+    /// ```text
+    /// 10.0.0.1 - foo.ns.serviceaccount.identity.linkerd.cluster.local [01/Jan/1970:00:00:00 +0000] "GET /ready HTTP/1.1" 200 2
+    /// ```
+    ///
+    /// The client's identity, if any, is used as the remote user. When
+    /// `combined` is set, the quoted `referer` and `user-agent` headers are
+    /// appended.
+    fn write_apache(&self, buf: &mut Vec<u8>, combined: bool) -> io::Result<()> {
+        match self.client_addr {
+            Some(addr) => write!(buf, "{} - ", addr.ip())?,
+            None => buf.extend_from_slice(b"- - "),
+        }
+        match self.identity {
+            Some(ref id) => write!(buf, "{} ", id.as_ref())?,
+            None => buf.extend_from_slice(b"- "),
+        }
+
+        let t = DateTime::from(self.timestamp);
+        write!(
+            buf,
+            "[{:02}/{}/{:04}:{:02}:{:02}:{:02} +0000] \"{} ",
+            t.day,
+            MONTHS[t.month as usize - 1],
+            t.year,
+            t.hour,
+            t.minute,
+            t.second,
+            self.method,
+        )?;
+        write_escaped(buf, &self.path);
+        write!(buf, " {:?}\" ", self.version)?;
+
+        match self.status {
+            Some(status) => write!(buf, "{} ", status.as_u16())?,
+            None => buf.extend_from_slice(b"- "),
+        }
+        if self.response_bytes == 0 {
+            buf.push(b'-');
+        } else {
+            write!(buf, "{}", self.response_bytes)?;
+        }
+
+        if combined {
+            for header in &[&self.referer, &self.user_agent] {
+                match header {
+                    Some(value) => {
+                        buf.extend_from_slice(b" \"");
+                        write_escaped(buf, value);
+                        buf.push(b'"');
+                    }
+                    None => buf.extend_from_slice(b" \"-\""),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// === impl ConnectionRecord ===
+
+impl ConnectionRecord {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "timestamp": fmt_rfc3339(self.timestamp),
+            "protocol": "tcp",
+            "event": match self.event {
+                ConnectionEvent::Open => "open",
+                ConnectionEvent::Close => "close",
+            },
+            "direction": self.direction.to_string(),
+            "client_addr": self.client_addr.map(|a| a.to_string()),
+            "target_addr": self.target_addr.to_string(),
+            "identity": self.identity.as_ref().map(|id| id.as_ref().to_string()),
+            "duration_ms": self.duration.map(|d| d.as_secs_f64() * 1_000.0),
+            "bytes_in": self.bytes_in,
+            "bytes_out": self.bytes_out,
+            "error": self.error,
+        })
+    }
+}
+
+// === impl AuditRecord ===
+
+impl AuditRecord {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "timestamp": fmt_rfc3339(self.timestamp),
+            "event": "audit",
+            "action": self.event.action,
+            "client_addr": self.event.client_addr.map(|a| a.to_string()),
+            "identity": self.event.identity.as_ref().map(|id| id.as_ref().to_string()),
+            "local": self.event.local,
+        })
+    }
+}
+
+/// Writes a value that is quoted in the Apache log formats, escaping quotes,
+/// backslashes, and non-printable characters.
+fn write_escaped(buf: &mut Vec<u8>, value: &str) {
+    for b in value.bytes() {
+        match b {
+            b'"' | b'\\' => {
+                buf.push(b'\\');
+                buf.push(b);
+            }
+            0x20..=0x7e => buf.push(b),
+            b => {
+                let _ = write!(buf, "\\x{:02x}", b);
+            }
+        }
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// A UTC calendar date and time.
+struct DateTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u64,
+    minute: u64,
+    second: u64,
+    millis: u32,
+}
+
+impl From<SystemTime> for DateTime {
+    fn from(time: SystemTime) -> Self {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since_epoch.as_secs();
+        let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+        let secs_of_day = secs % 86_400;
+        Self {
+            year,
+            month,
+            day,
+            hour: secs_of_day / 3_600,
+            minute: secs_of_day / 60 % 60,
+            second: secs_of_day % 60,
+            millis: since_epoch.subsec_millis(),
+        }
+    }
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp with millisecond precision,
+/// e.g. `2021-09-01T12:30:00.000Z`.
+fn fmt_rfc3339(time: SystemTime) -> String {
+    let t = DateTime::from(time);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        t.year, t.month, t.day, t.hour, t.minute, t.second, t.millis
+    )
+}
+
+/// Converts a count of days since the UNIX epoch to a `(year, month, day)`
+/// date in the proleptic Gregorian calendar.
+///
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_destinations() {
+        assert_eq!(
+            "stdout".parse::<Destination>().unwrap(),
+            Destination::Stdout
+        );
+        assert_eq!(
+            "stderr".parse::<Destination>().unwrap(),
+            Destination::Stderr
+        );
+        assert_eq!("fd:3".parse::<Destination>().unwrap(), Destination::Fd(3));
+        assert_eq!(
+            "/var/log/access.log".parse::<Destination>().unwrap(),
+            Destination::Path("/var/log/access.log".into())
+        );
+        assert_eq!(
+            "unix:/run/access.sock".parse::<Destination>().unwrap(),
+            Destination::Unix("/run/access.sock".into())
+        );
+        assert_eq!(
+            "syslog:/dev/log".parse::<Destination>().unwrap(),
+            Destination::Syslog(SyslogAddr::Unix("/dev/log".into()))
+        );
+        assert_eq!(
+            "syslog:127.0.0.1:514".parse::<Destination>().unwrap(),
+            Destination::Syslog(SyslogAddr::Udp(([127, 0, 0, 1], 514).into()))
+        );
+        assert!("fd:three".parse::<Destination>().is_err());
+        assert!("unix:".parse::<Destination>().is_err());
+        assert!("syslog:localhost".parse::<Destination>().is_err());
+        assert!("".parse::<Destination>().is_err());
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        assert_eq!(fmt_rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            fmt_rfc3339(UNIX_EPOCH + Duration::from_millis(951_827_696_789)),
+            "2000-02-29T12:34:56.789Z"
+        );
+        assert_eq!(
+            fmt_rfc3339(UNIX_EPOCH + Duration::from_secs(1_640_995_199)),
+            "2021-12-31T23:59:59.000Z"
+        );
+    }
+
+    fn record() -> Record {
+        Record {
+            timestamp: UNIX_EPOCH,
+            direction: Direction::In,
+            client_addr: Some(([10, 0, 0, 1], 41000).into()),
+            target_addr: Some("10.0.0.2:8080".to_string()),
+            identity: Some(
+                "foo.ns.serviceaccount.identity.linkerd.cluster.local"
+                    .parse()
+                    .unwrap(),
+            ),
+            method: http::Method::GET,
+            authority: Some(http::uri::Authority::from_static(
+                "web.ns.svc.cluster.local:8080",
+            )),
+            path: "/ready?verbose".to_string(),
+            version: http::Version::HTTP_11,
+            referer: None,
+            user_agent: Some("curl/7.79.1".to_string()),
+            status: Some(http::StatusCode::OK),
+            latency: Duration::from_micros(1_500),
+            response_bytes: 2,
+            route_labels: vec![("rt_route".to_string(), "GET /ready".to_string())],
+            trace_id: None,
+        }
+    }
+
+    #[test]
+    fn renders_json() {
+        let record = record();
+        assert_eq!(
+            record.to_json(),
+            serde_json::json!({
+                "timestamp": "1970-01-01T00:00:00.000Z",
+                "direction": "inbound",
+                "client_addr": "10.0.0.1:41000",
+                "target_addr": "10.0.0.2:8080",
+                "identity": "foo.ns.serviceaccount.identity.linkerd.cluster.local",
+                "method": "GET",
+                "authority": "web.ns.svc.cluster.local:8080",
+                "path": "/ready?verbose",
+                "status": 200,
+                "latency_ms": 1.5,
+                "response_bytes": 2,
+                "route_labels": { "rt_route": "GET /ready" },
+                "trace_id": null,
+            })
+        );
+    }
+
+    #[test]
+    fn renders_connections() {
+        let record = ConnectionRecord {
+            timestamp: UNIX_EPOCH,
+            event: ConnectionEvent::Close,
+            direction: Direction::Out,
+            client_addr: Some(([10, 0, 0, 1], 41000).into()),
+            target_addr: "db.ns.svc.cluster.local:5432".parse().unwrap(),
+            identity: None,
+            duration: Some(Duration::from_millis(250)),
+            bytes_in: 10,
+            bytes_out: 20,
+            error: Some("connection reset".to_string()),
+        };
+        // Connection records are JSON regardless of the configured format.
+        let mut buf = Vec::new();
+        Format::ApacheCommon
+            .encode(&Entry::Connection(record), &mut buf)
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&buf).unwrap(),
+            serde_json::json!({
+                "timestamp": "1970-01-01T00:00:00.000Z",
+                "protocol": "tcp",
+                "event": "close",
+                "direction": "outbound",
+                "client_addr": "10.0.0.1:41000",
+                "target_addr": "db.ns.svc.cluster.local:5432",
+                "identity": null,
+                "duration_ms": 250.0,
+                "bytes_in": 10,
+                "bytes_out": 20,
+                "error": "connection reset",
+            })
+        );
+    }
+
+    #[test]
+    fn frames_syslog_messages() {
+        let mut buf = Vec::new();
+        let entry = Entry::Request(record());
+        write_syslog_header(&entry, UNIX_EPOCH, &mut buf).unwrap();
+        Format::Json.encode(&entry, &mut buf).unwrap();
+        let msg = String::from_utf8(buf).unwrap();
+        let header = format!(
+            "<190>1 1970-01-01T00:00:00.000Z - linkerd-proxy {} access - {{",
+            process::id()
+        );
+        assert!(msg.starts_with(&header), "{}", msg);
+        assert!(!msg.ends_with('\n'));
+    }
+
+    #[test]
+    fn renders_apache() {
+        let mut buf = Vec::new();
+        Format::ApacheCommon
+            .encode(&Entry::Request(record()), &mut buf)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "10.0.0.1 - foo.ns.serviceaccount.identity.linkerd.cluster.local \
+            [01/Jan/1970:00:00:00 +0000] \"GET /ready?verbose HTTP/1.1\" 200 2"
+        );
+
+        let record = Record {
+            client_addr: None,
+            identity: None,
+            path: "/\"quoted\"".to_string(),
+            status: None,
+            response_bytes: 0,
+            ..record()
+        };
+        let mut buf = Vec::new();
+        Format::ApacheCombined
+            .encode(&Entry::Request(record), &mut buf)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "- - - [01/Jan/1970:00:00:00 +0000] \"GET /\\\"quoted\\\" HTTP/1.1\" - - \
+            \"-\" \"curl/7.79.1\""
+        );
+    }
+
+    #[test]
+    fn filters_records() {
+        let with_status = |status: Option<u16>| Record {
+            status: status.map(|s| http::StatusCode::from_u16(s).unwrap()),
+            ..record()
+        };
+
+        assert!(Filter::default().matches(&record()));
+
+        let filter = Filter {
+            status_classes: vec![4, 5],
+            ..Filter::default()
+        };
+        assert!(!filter.matches(&with_status(Some(200))));
+        assert!(filter.matches(&with_status(Some(404))));
+        assert!(filter.matches(&with_status(None)));
+
+        let filter = Filter {
+            routes: vec!["GET /ready".to_string()],
+            ..Filter::default()
+        };
+        assert!(filter.matches(&record()));
+        let unrouted = Record {
+            route_labels: vec![],
+            ..record()
+        };
+        assert!(!filter.matches(&unrouted));
+
+        let filter = Filter {
+            authorities: Some("ns.svc.cluster.local".parse().unwrap())
+                .into_iter()
+                .collect(),
+            ..Filter::default()
+        };
+        assert!(filter.matches(&record()));
+        let other = Record {
+            authority: Some(http::uri::Authority::from_static(
+                "web.other.svc.cluster.local",
+            )),
+            ..record()
+        };
+        assert!(!filter.matches(&other));
+    }
+
+    #[test]
+    fn samples_successes() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let log = AccessLog {
+            tx,
+            metrics: Default::default(),
+            filter: Arc::new(Filter {
+                success_sample_interval: Some(3),
+                ..Filter::default()
+            }),
+            successes: Default::default(),
+            include_query: false,
+        };
+        for _ in 0..6 {
+            log.send(record());
+        }
+        log.send(Record {
+            status: Some(http::StatusCode::BAD_GATEWAY),
+            ..record()
+        });
+
+        let mut statuses = Vec::new();
+        while let Some(Some(Entry::Request(record))) = rx.recv().now_or_never() {
+            statuses.push(record.status.unwrap().as_u16());
+        }
+        assert_eq!(statuses, vec![200, 200, 502]);
+    }
+
+    #[tokio::test]
+    async fn reconnects_datagram_destinations() {
+        let path = std::env::temp_dir().join(format!("linkerd-access-log-{}.sock", process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // The destination doesn't exist yet, so records are dropped until a
+        // connection is retried.
+        let mut socket = Reconnect::new(DatagramAddr::Unix(path.clone()));
+        let error = socket.send(b"a").await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotConnected);
+
+        let server = UnixDatagram::bind(&path).unwrap();
+        socket.retry_at = Instant::now();
+        socket.send(b"b").await.unwrap();
+        let mut buf = [0u8; 1];
+        server.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"b");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn omits_query_by_default() {
+        let uri = "http://web.ns.svc.cluster.local/login?token=secret"
+            .parse::<http::Uri>()
+            .unwrap();
+        assert_eq!(request_path(&uri, false), "/login");
+        assert_eq!(request_path(&uri, true), "/login?token=secret");
+        let uri = "web.ns.svc.cluster.local:80".parse::<http::Uri>().unwrap();
+        assert_eq!(request_path(&uri, false), "/");
+    }
+}
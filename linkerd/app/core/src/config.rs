@@ -2,7 +2,7 @@ pub use crate::exp_backoff::ExponentialBackoff;
 use crate::{
     proxy::http::{self, h1, h2},
     svc::Param,
-    transport::{Keepalive, ListenAddr},
+    transport::{accept, proxy_protocol, Backlog, Keepalive, ListenAddr},
 };
 use std::time::Duration;
 
@@ -11,6 +11,15 @@ pub struct ServerConfig {
     pub addr: ListenAddr,
     pub keepalive: Keepalive,
     pub h2_settings: h2::Settings,
+
+    /// Networks from which accepted connections must begin with a PROXY
+    /// protocol header describing the original client.
+    pub proxy_protocol: proxy_protocol::TrustedNetworks,
+
+    pub backlog: Backlog,
+
+    /// Limits the connections that the server accepts.
+    pub accept_limits: accept::Limits,
 }
 
 #[derive(Clone, Debug)]
@@ -54,3 +63,15 @@ impl Param<Keepalive> for ServerConfig {
         self.keepalive
     }
 }
+
+impl Param<Backlog> for ServerConfig {
+    fn param(&self) -> Backlog {
+        self.backlog
+    }
+}
+
+impl Param<proxy_protocol::TrustedNetworks> for ServerConfig {
+    fn param(&self) -> proxy_protocol::TrustedNetworks {
+        self.proxy_protocol.clone()
+    }
+}
@@ -12,6 +12,7 @@ use tracing::warn;
 pub struct Config {
     pub addr: ControlAddr,
     pub connect: config::ConnectConfig,
+    pub connect_mark: Option<u32>,
     pub buffer_capacity: usize,
 }
 
@@ -77,7 +78,7 @@ impl Config {
             }
         };
 
-        svc::stack(ConnectTcp::new(self.connect.keepalive))
+        svc::stack(ConnectTcp::new(self.connect.keepalive).with_mark(self.connect_mark))
             .push(tls::Client::layer(identity))
             .push_connect_timeout(self.connect.timeout)
             .push(self::client::layer())
@@ -0,0 +1,266 @@
+//! Propagates request deadlines across proxies.
+//!
+//! Clients may bound the time they will wait for a response with the
+//! `grpc-timeout` request header or, for non-gRPC requests, the equivalent
+//! `l5d-timeout` header (which uses the same encoding). Servers record the
+//! deadline implied by these headers when a request is received, as a
+//! [`Deadline`] request extension. Clients enforce the deadline on the
+//! outbound leg and rewrite the headers with the time that remains, so that
+//! the upstream sees the client's deadline, less the time spent in the proxy.
+
+use crate::{proxy::http, svc};
+use futures::prelude::*;
+use linkerd_error::Error;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use thiserror::Error;
+use tokio::time::{self, Instant};
+use tracing::{debug, trace};
+
+pub const GRPC_TIMEOUT: &str = "grpc-timeout";
+pub const L5D_TIMEOUT: &str = "l5d-timeout";
+
+/// The largest value that may be encoded in a timeout header.
+const MAX_TIMEOUT_VALUE: u128 = 99_999_999;
+
+/// The time by which a request's response must be received.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Deadline(pub Instant);
+
+#[derive(Debug, Error)]
+#[error("request deadline exceeded")]
+pub struct DeadlineExceeded(());
+
+/// Records the deadlines of requests with timeout headers.
+#[derive(Clone, Debug)]
+pub struct RecordDeadline<S> {
+    inner: S,
+}
+
+/// Enforces the deadlines of requests, propagating the remaining time in the
+/// requests' timeout headers.
+#[derive(Clone, Debug)]
+pub struct EnforceDeadline<S> {
+    inner: S,
+}
+
+type ResponseFuture<R> = Pin<Box<dyn Future<Output = Result<R, Error>> + Send + 'static>>;
+
+/// Parses a timeout header value, i.e. at most 8 digits followed by one of the
+/// `H`, `M`, `S`, `m`, `u`, or `n` units.
+pub fn parse_timeout(value: &http::HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?;
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (digits, unit) = value.split_at(value.len() - 1);
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n = digits.parse::<u64>().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(n * 60 * 60)),
+        "M" => Some(Duration::from_secs(n * 60)),
+        "S" => Some(Duration::from_secs(n)),
+        "m" => Some(Duration::from_millis(n)),
+        "u" => Some(Duration::from_micros(n)),
+        "n" => Some(Duration::from_nanos(n)),
+        _ => None,
+    }
+}
+
+/// Encodes a timeout header value in the finest unit that can represent it.
+pub fn encode_timeout(timeout: Duration) -> http::HeaderValue {
+    const UNITS: [(u128, char); 6] = [
+        (1, 'n'),
+        (1_000, 'u'),
+        (1_000_000, 'm'),
+        (1_000_000_000, 'S'),
+        (60 * 1_000_000_000, 'M'),
+        (60 * 60 * 1_000_000_000, 'H'),
+    ];
+    let nanos = timeout.as_nanos();
+    let (value, unit) = UNITS
+        .iter()
+        .map(|&(per, unit)| (nanos / per, unit))
+        .find(|&(value, _)| value <= MAX_TIMEOUT_VALUE)
+        .unwrap_or((MAX_TIMEOUT_VALUE, 'H'));
+    http::HeaderValue::from_str(&format!("{}{}", value, unit))
+        .expect("timeouts must be valid header values")
+}
+
+// === impl RecordDeadline ===
+
+impl<S> RecordDeadline<S> {
+    pub fn layer() -> impl svc::layer::Layer<S, Service = Self> + Clone + Copy {
+        svc::layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<S, B> svc::Service<http::Request<B>> for RecordDeadline<S>
+where
+    S: svc::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        // If both headers are set, the shorter timeout wins.
+        let timeout = [GRPC_TIMEOUT, L5D_TIMEOUT]
+            .iter()
+            .filter_map(|h| parse_timeout(req.headers().get(*h)?))
+            .min();
+        if let Some(timeout) = timeout {
+            trace!(?timeout, "Recording deadline");
+            req.extensions_mut()
+                .insert(Deadline(Instant::now() + timeout));
+        }
+        self.inner.call(req)
+    }
+}
+
+// === impl EnforceDeadline ===
+
+impl<S> EnforceDeadline<S> {
+    pub fn layer() -> impl svc::layer::Layer<S, Service = Self> + Clone + Copy {
+        svc::layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<S, B> svc::Service<http::Request<B>> for EnforceDeadline<S>
+where
+    S: svc::Service<http::Request<B>>,
+    S::Error: Into<Error>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<future::ErrInto<S::Future, Error>, ResponseFuture<S::Response>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        let Deadline(deadline) = match req.extensions().get::<Deadline>() {
+            Some(deadline) => *deadline,
+            None => return future::Either::Left(self.inner.call(req).err_into()),
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::from_secs(0) {
+            debug!("Request deadline exceeded before dispatch");
+            return future::Either::Right(Box::pin(future::err::<S::Response, Error>(
+                DeadlineExceeded(()).into(),
+            )));
+        }
+
+        // Only the headers that the client set are propagated.
+        let value = encode_timeout(remaining);
+        for header in &[GRPC_TIMEOUT, L5D_TIMEOUT] {
+            if let Some(v) = req.headers_mut().get_mut(*header) {
+                *v = value.clone();
+            }
+        }
+        trace!(?remaining, "Enforcing deadline");
+
+        let call = self.inner.call(req);
+        future::Either::Right(Box::pin(async move {
+            match time::timeout_at(deadline, call).await {
+                Ok(res) => res.map_err(Into::into),
+                Err(_) => {
+                    debug!("Request deadline exceeded");
+                    Err(DeadlineExceeded(()).into())
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use svc::ServiceExt;
+
+    #[test]
+    fn parses_and_encodes_timeouts() {
+        for (value, timeout) in &[
+            ("1H", Duration::from_secs(60 * 60)),
+            ("3M", Duration::from_secs(3 * 60)),
+            ("10S", Duration::from_secs(10)),
+            ("250m", Duration::from_millis(250)),
+            ("99999999u", Duration::from_micros(99_999_999)),
+            ("5n", Duration::from_nanos(5)),
+        ] {
+            let v = http::HeaderValue::from_static(*value);
+            assert_eq!(parse_timeout(&v), Some(*timeout), "{}", value);
+            assert_eq!(parse_timeout(&encode_timeout(*timeout)), Some(*timeout));
+        }
+        assert_eq!(
+            encode_timeout(Duration::from_secs(6)),
+            http::HeaderValue::from_static("6000000u")
+        );
+
+        for invalid in &["", "10", "S", "10s", "-10S", "123456789S", "1.5S"] {
+            let v = http::HeaderValue::from_static(*invalid);
+            assert_eq!(parse_timeout(&v), None, "{}", invalid);
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn propagates_remaining_timeout() {
+        let mut req = http::Request::new(());
+        req.headers_mut()
+            .insert(GRPC_TIMEOUT, http::HeaderValue::from_static("10S"));
+        let req = RecordDeadline {
+            inner: svc::mk(future::ok::<_, Error>),
+        }
+        .oneshot(req)
+        .await
+        .unwrap();
+        let deadline = *req.extensions().get::<Deadline>().unwrap();
+        assert_eq!(deadline, Deadline(Instant::now() + Duration::from_secs(10)));
+
+        time::advance(Duration::from_secs(4)).await;
+        let svc = EnforceDeadline {
+            inner: svc::mk(|req: http::Request<()>| {
+                future::ok::<_, Error>(req.headers().get(GRPC_TIMEOUT).cloned())
+            }),
+        };
+        let timeout = svc.clone().oneshot(req).await.unwrap();
+        assert_eq!(timeout, Some(http::HeaderValue::from_static("6000000u")));
+
+        // Requests whose deadlines have passed are not dispatched.
+        time::advance(Duration::from_secs(6)).await;
+        let mut req = http::Request::new(());
+        req.extensions_mut().insert(deadline);
+        let err = svc.oneshot(req).await.unwrap_err();
+        assert!(err.is::<DeadlineExceeded>());
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn enforces_deadline() {
+        let mut req = http::Request::new(());
+        req.extensions_mut()
+            .insert(Deadline(Instant::now() + Duration::from_secs(1)));
+        let err = EnforceDeadline {
+            inner: svc::mk(|_: http::Request<()>| {
+                time::sleep(Duration::from_secs(10)).map(Ok::<_, Error>)
+            }),
+        }
+        .oneshot(req)
+        .await
+        .unwrap_err();
+        assert!(err.is::<DeadlineExceeded>());
+    }
+}
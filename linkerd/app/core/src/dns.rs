@@ -6,6 +6,8 @@ use std::time::Duration;
 pub struct Config {
     pub min_ttl: Option<Duration>,
     pub max_ttl: Option<Duration>,
+    pub negative_ttl: Duration,
+    pub ip_preference: IpPreference,
     pub resolv_conf_path: PathBuf,
 }
 
@@ -17,19 +19,22 @@ pub struct Dns {
 
 impl Config {
     pub fn build(self) -> Dns {
-        let resolver =
-            Resolver::from_system_config_with(&self).expect("system DNS config must be valid");
+        let resolver = Resolver::from_system_config_with(&self)
+            .expect("system DNS config must be valid")
+            .with_ip_preference(self.ip_preference);
         Dns { resolver }
     }
 }
 
 impl ConfigureResolver for Config {
     /// Modify a `trust-dns-resolver::config::ResolverOpts` to reflect
-    /// the configured minimum and maximum DNS TTL values.
+    /// the configured minimum and maximum DNS TTL values, negative TTL, and
+    /// address families.
     fn configure_resolver(&self, opts: &mut ResolverOpts) {
         opts.positive_min_ttl = self.min_ttl;
         opts.positive_max_ttl = self.max_ttl;
-        opts.negative_min_ttl = self.min_ttl;
-        opts.negative_max_ttl = self.max_ttl;
+        opts.negative_min_ttl = Some(self.negative_ttl);
+        opts.negative_max_ttl = Some(self.negative_ttl);
+        opts.ip_strategy = self.ip_preference.lookup_strategy();
     }
 }
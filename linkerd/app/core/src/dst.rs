@@ -1,5 +1,5 @@
 use super::classify;
-use crate::profiles;
+use crate::{profiles, NameAddr};
 use linkerd_http_classify::CanClassify;
 use linkerd_proxy_http::timeout;
 use std::time::Duration;
@@ -9,6 +9,8 @@ pub struct Route {
     pub addr: profiles::LogicalAddr,
     pub route: profiles::http::Route,
     pub direction: super::metrics::Direction,
+    /// The name from which the route's authority was rewritten, if any.
+    pub rewritten_from: Option<NameAddr>,
 }
 
 // === impl Route ===
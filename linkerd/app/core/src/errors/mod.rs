@@ -2,6 +2,7 @@ pub mod respond;
 
 pub use self::respond::{HttpRescue, NewRespond, SyntheticHttpResponse};
 pub use linkerd_proxy_http::h2::H2Error;
+use linkerd_proxy_http::{h2, HasH2Reason};
 pub use linkerd_timeout::{FailFastError, ResponseTimeout};
 use thiserror::Error;
 
@@ -9,6 +10,48 @@ use thiserror::Error;
 #[error("connect timed out after {0:?}")]
 pub struct ConnectTimeout(pub(crate) std::time::Duration);
 
+/// Indicates that a request was shed without being dispatched because its
+/// endpoint's concurrency limit was reached.
+#[derive(Debug, Error)]
+#[error("endpoint concurrency limit of {0} requests reached")]
+pub struct ConcurrencyLimitExceeded(pub usize);
+
+/// Marks responses synthesized for requests that failed before the server
+/// could have processed them, so that they may be safely retried.
+#[derive(Copy, Clone, Debug)]
+pub struct Unprocessed(pub(crate) ());
+
+/// Indicates whether an error proves that the server did not process the
+/// request, i.e. because a connection could not be established, the request
+/// was never dispatched on its connection (e.g. because the connection was
+/// closed by a GOAWAY or shed by the endpoint's concurrency limit), or the
+/// server refused the HTTP/2 stream.
+pub fn is_unprocessed(mut error: &(dyn std::error::Error + 'static)) -> bool {
+    if error.h2_reason() == Some(h2::Reason::REFUSED_STREAM) {
+        return true;
+    }
+
+    loop {
+        if error.is::<ConnectTimeout>() || error.is::<ConcurrencyLimitExceeded>() {
+            return true;
+        }
+        if let Some(e) = error.downcast_ref::<hyper::Error>() {
+            if e.is_connect() || e.is_canceled() {
+                return true;
+            }
+        }
+        if let Some(e) = error.downcast_ref::<std::io::Error>() {
+            if e.kind() == std::io::ErrorKind::ConnectionRefused {
+                return true;
+            }
+        }
+        match error.source() {
+            Some(e) => error = e,
+            None => return false,
+        }
+    }
+}
+
 /// Obtain the source error at the end of a chain of `Error`s.
 pub fn root_cause<'e>(
     mut error: &'e (dyn std::error::Error + 'static),
@@ -2,6 +2,7 @@ use http::header::HeaderValue;
 use linkerd_error::{Error, Result};
 use linkerd_error_respond as respond;
 pub use linkerd_proxy_http::{ClientHandle, HasH2Reason};
+use linkerd_trace_context::SpanEvents;
 use pin_project::pin_project;
 use std::{
     borrow::Cow,
@@ -37,6 +38,7 @@ pub struct Respond<R> {
     version: http::Version,
     is_grpc: bool,
     client: Option<ClientHandle>,
+    span_events: Option<SpanEvents>,
 }
 
 #[pin_project(project = ResponseBodyProj)]
@@ -77,6 +79,15 @@ impl SyntheticHttpResponse {
         }
     }
 
+    pub fn bad_request(msg: impl ToString) -> Self {
+        Self {
+            close_connection: true,
+            http_status: http::StatusCode::BAD_REQUEST,
+            grpc_status: tonic::Code::InvalidArgument,
+            message: Cow::Owned(msg.to_string()),
+        }
+    }
+
     pub fn bad_gateway(msg: impl ToString) -> Self {
         Self {
             close_connection: true,
@@ -95,6 +106,44 @@ impl SyntheticHttpResponse {
         }
     }
 
+    pub fn deadline_exceeded(msg: impl ToString) -> Self {
+        Self {
+            close_connection: false,
+            http_status: http::StatusCode::GATEWAY_TIMEOUT,
+            grpc_status: tonic::Code::DeadlineExceeded,
+            message: Cow::Owned(msg.to_string()),
+        }
+    }
+
+    pub fn unavailable(msg: impl ToString) -> Self {
+        Self {
+            close_connection: false,
+            http_status: http::StatusCode::SERVICE_UNAVAILABLE,
+            grpc_status: tonic::Code::Unavailable,
+            message: Cow::Owned(msg.to_string()),
+        }
+    }
+
+    pub fn rate_limited(msg: impl ToString) -> Self {
+        Self {
+            close_connection: false,
+            http_status: http::StatusCode::TOO_MANY_REQUESTS,
+            grpc_status: tonic::Code::ResourceExhausted,
+            message: Cow::Owned(msg.to_string()),
+        }
+    }
+
+    /// Responds with a `401 Unauthorized` that challenges the client for a
+    /// bearer token.
+    pub fn unauthorized(msg: impl ToString) -> Self {
+        Self {
+            http_status: http::StatusCode::UNAUTHORIZED,
+            grpc_status: tonic::Code::Unauthenticated,
+            close_connection: false,
+            message: Cow::Owned(msg.to_string()),
+        }
+    }
+
     pub fn unauthenticated(msg: impl ToString) -> Self {
         Self {
             http_status: http::StatusCode::FORBIDDEN,
@@ -165,6 +214,10 @@ impl SyntheticHttpResponse {
             .header(http::header::CONTENT_LENGTH, "0")
             .header(L5D_PROXY_ERROR, self.message());
 
+        if self.http_status == http::StatusCode::UNAUTHORIZED {
+            rsp = rsp.header(http::header::WWW_AUTHENTICATE, "Bearer");
+        }
+
         if self.close_connection && version == http::Version::HTTP_11 {
             rsp = rsp.header(http::header::CONNECTION, "close");
         }
@@ -191,6 +244,7 @@ where
     fn new_respond(&self, req: &http::Request<B>) -> Self::Respond {
         let client = req.extensions().get::<ClientHandle>().cloned();
         debug_assert!(client.is_some(), "Missing client handle");
+        let span_events = req.extensions().get::<SpanEvents>().cloned();
 
         let rescue = self.0.clone();
 
@@ -206,6 +260,7 @@ where
                     rescue,
                     is_grpc,
                     version: http::Version::HTTP_2,
+                    span_events,
                 }
             }
             version => Respond {
@@ -213,6 +268,7 @@ where
                 rescue,
                 version,
                 is_grpc: false,
+                span_events,
             },
         }
     }
@@ -258,6 +314,16 @@ where
             Err(error) => error,
         };
 
+        let unprocessed = super::is_unprocessed(&*error);
+        if let Some(events) = self.span_events.as_ref() {
+            let cause = super::root_cause(&*error);
+            if cause.is::<super::FailFastError>() {
+                let mut labels = std::collections::HashMap::with_capacity(1);
+                labels.insert("failfast.reason", cause.to_string());
+                events.record("failfast", labels);
+            }
+        }
+
         let rsp = info_span!("rescue", client.addr = %self.client_addr()).in_scope(|| {
             tracing::info!(%error, "Request failed");
             self.rescue.rescue(error)
@@ -271,11 +337,14 @@ where
             }
         }
 
-        let rsp = if self.is_grpc {
+        let mut rsp = if self.is_grpc {
             rsp.grpc_response()
         } else {
             rsp.http_response(self.version)
         };
+        if unprocessed {
+            rsp.extensions_mut().insert(super::Unprocessed(()));
+        }
 
         Ok(rsp)
     }
@@ -0,0 +1,766 @@
+//! Authorizes inbound HTTP requests with an external authorization service.
+//!
+//! When an external authorizer is configured, the inbound proxy describes each
+//! HTTP request on the configured servers (by the names of their inbound
+//! policies, or all servers if none are named) to the authorizer before the
+//! request is dispatched. The authorizer is called either with the `Check`
+//! method of Envoy's `ext_authz` gRPC API, or, over HTTP, with a body-less copy
+//! of the request.
+//!
+//! Requests that the authorizer denies fail with an [`ExtAuthzDenied`] error,
+//! which carries the status with which the client should be answered. Requests
+//! that it permits are dispatched with the header mutations it requires: gRPC
+//! authorizers may set, append, and remove request headers; HTTP authorizers
+//! set the configured headers of their responses. When the authorizer
+//! cannot be reached, or does not respond within the configured timeout,
+//! requests are permitted if the client fails open, and fail with an
+//! [`ExtAuthzUnavailable`] error if it fails closed.
+//!
+//! Decisions may be cached, keyed by the client's identity and the request's
+//! route, so that the authorizer need not be called for every request. Only
+//! decisions for authenticated clients that don't mutate the request's headers
+//! are cached, since the authorizer may base other decisions on the request's
+//! credentials.
+
+pub use crate::rls::FailureMode;
+use crate::{
+    control, dns,
+    metrics::{self, latency, metrics, Counter, FmtLabels, FmtMetrics, Histogram},
+    proxy::http,
+    svc, tls, Error,
+};
+use envoy_ext_authz_proto::{
+    envoy::{
+        config::core::v3::HeaderValueOption,
+        service::auth::v3::{
+            attribute_context, authorization_client::AuthorizationClient, check_response,
+            AttributeContext, CheckRequest, CheckResponse,
+        },
+    },
+    google::rpc,
+};
+use futures::prelude::*;
+use http_body::Body;
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Arc,
+    time::Duration,
+};
+use thiserror::Error;
+use tokio::time::{self, Instant};
+use tracing::debug;
+
+metrics! {
+    ext_authz_request_duration_ms: Histogram<latency::Ms> {
+        "Elapsed times between an external authorization request being sent and its response being received"
+    },
+    ext_authz_cache_hits_total: Counter {
+        "The total number of requests that were authorized by a cached decision"
+    }
+}
+
+/// Identifies the client whose requests are authorized, set on requests to the
+/// HTTP authorizer.
+const L5D_CLIENT_ID: &str = "l5d-client-id";
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub control: control::Config,
+
+    pub protocol: Protocol,
+
+    pub failure_mode: FailureMode,
+
+    /// The headers of an HTTP authorizer's responses that are set on permitted
+    /// requests. No headers are set when empty.
+    pub headers: Vec<http::HeaderName>,
+
+    /// Bounds the time spent waiting for the authorizer's response.
+    pub timeout: Duration,
+
+    /// How long decisions are cached. Decisions are not cached when zero.
+    pub cache_ttl: Duration,
+
+    /// The maximum number of cached decisions.
+    pub cache_capacity: usize,
+
+    /// The names of the inbound servers whose requests are authorized. All
+    /// servers' requests are authorized when empty.
+    pub servers: HashSet<String>,
+}
+
+/// The API with which the authorizer is called.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Grpc,
+    Http,
+}
+
+#[derive(Clone)]
+pub struct Client {
+    client: control::Client,
+    protocol: Protocol,
+    failure_mode: FailureMode,
+    headers: Arc<Vec<http::HeaderName>>,
+    timeout: Duration,
+    servers: Arc<HashSet<String>>,
+    cache: Option<Cache>,
+    metrics: Metrics,
+}
+
+/// Identifies requests that share a cached decision.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub server: String,
+
+    /// The client's identity, if it's known.
+    pub principal: Option<String>,
+
+    /// The name of the route on which the request was authorized, or, if the
+    /// authorization is not scoped to routes, the request's method and path.
+    pub route: String,
+}
+
+/// Describes the changes that the authorizer requires to a permitted
+/// request's headers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Allow {
+    set: Vec<(http::HeaderName, http::HeaderValue, bool)>,
+    remove: Vec<http::HeaderName>,
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("request denied by external authorizer")]
+pub struct ExtAuthzDenied {
+    status: http::StatusCode,
+}
+
+#[derive(Debug, Error)]
+#[error("external authorizer unavailable")]
+pub struct ExtAuthzUnavailable(());
+
+#[derive(Debug, Error)]
+#[error("external authorizer did not respond within {0:?}")]
+struct ServiceTimeout(Duration);
+
+#[derive(Debug, Error)]
+#[error("external authorizer responded with {0}")]
+struct ServiceError(http::StatusCode);
+
+#[derive(Clone, Debug, Default)]
+pub struct Report(Option<Metrics>);
+
+#[derive(Clone, Debug, Default)]
+struct Metrics {
+    durations: Arc<Mutex<HashMap<Outcome, Histogram<latency::Ms>>>>,
+    cache_hits: Arc<Counter>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Outcome {
+    Allow,
+    Deny,
+    Error,
+}
+
+type Decision = Result<Allow, ExtAuthzDenied>;
+
+#[derive(Clone, Debug)]
+struct Cache {
+    ttl: Duration,
+    capacity: usize,
+    decisions: Arc<Mutex<HashMap<Key, (Instant, Decision)>>>,
+}
+
+// === impl Config ===
+
+impl Config {
+    pub fn build<L>(
+        self,
+        dns: dns::Resolver,
+        client_metrics: metrics::ControlHttp,
+        identity: Option<L>,
+    ) -> Client
+    where
+        L: Clone + svc::Param<tls::client::Config> + Send + Sync + 'static,
+    {
+        use svc::NewService;
+
+        let client = self
+            .control
+            .build(dns, client_metrics, identity)
+            .new_service(());
+        let cache = if self.cache_ttl > Duration::from_secs(0) && self.cache_capacity > 0 {
+            Some(Cache {
+                ttl: self.cache_ttl,
+                capacity: self.cache_capacity,
+                decisions: Default::default(),
+            })
+        } else {
+            None
+        };
+        Client {
+            client,
+            protocol: self.protocol,
+            failure_mode: self.failure_mode,
+            headers: Arc::new(self.headers),
+            timeout: self.timeout,
+            servers: Arc::new(self.servers),
+            cache,
+            metrics: Metrics::default(),
+        }
+    }
+}
+
+// === impl Client ===
+
+impl Client {
+    pub fn report(&self) -> Report {
+        Report(Some(self.metrics.clone()))
+    }
+
+    /// Returns true if requests on the named server are authorized.
+    pub fn applies_to(&self, server: &str) -> bool {
+        self.servers.is_empty() || self.servers.contains(server)
+    }
+
+    /// Asks the authorizer whether the request should be permitted.
+    pub fn check<B>(
+        &self,
+        req: &http::Request<B>,
+        key: Key,
+    ) -> impl Future<Output = Result<Allow, Error>> + Send + 'static {
+        // Decisions for unauthenticated clients may depend on credentials in
+        // the request's headers, so they're never cached.
+        let cache = self.cache.clone().filter(|_| key.principal.is_some());
+        if let Some(decision) = cache.as_ref().and_then(|c| c.get(&key)) {
+            debug!(?key, "Using cached decision");
+            self.metrics.cache_hits.incr();
+            return future::Either::Left(future::ready(decision.map_err(Into::into)));
+        }
+
+        let call = match self.protocol {
+            Protocol::Grpc => {
+                let req = check_request(req, &key);
+                let mut client = AuthorizationClient::new(self.client.clone());
+                async move { Ok::<_, Error>(decide_grpc(client.check(req).await?.into_inner())) }
+                    .boxed()
+            }
+            Protocol::Http => {
+                let req = http_request(req, &key);
+                let client = self.client.clone();
+                let headers = self.headers.clone();
+                async move {
+                    let rsp = svc::ServiceExt::oneshot(client, req).await?;
+                    decide_http(rsp.status(), rsp.headers(), &*headers)
+                }
+                .boxed()
+            }
+        };
+
+        let Self {
+            timeout,
+            failure_mode,
+            metrics,
+            ..
+        } = self.clone();
+        future::Either::Right(async move {
+            let t0 = Instant::now();
+            let rsp = match time::timeout(timeout, call).await {
+                Ok(rsp) => rsp,
+                Err(_) => Err(ServiceTimeout(timeout).into()),
+            };
+            let (outcome, result) = match rsp {
+                Ok(decision) => {
+                    if let Some(cache) = cache {
+                        cache.insert(key, decision.clone());
+                    }
+                    match decision {
+                        Ok(allow) => (Outcome::Allow, Ok(allow)),
+                        Err(denied) => {
+                            debug!(status = %denied.status, "Request denied");
+                            (Outcome::Deny, Err(denied.into()))
+                        }
+                    }
+                }
+                Err(error) => {
+                    debug!(%error, ?failure_mode, "External authorizer failed");
+                    match failure_mode {
+                        FailureMode::Open => (Outcome::Error, Ok(Allow::default())),
+                        FailureMode::Closed => {
+                            (Outcome::Error, Err(ExtAuthzUnavailable(()).into()))
+                        }
+                    }
+                }
+            };
+            metrics.record(outcome, t0.elapsed());
+            result
+        })
+    }
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("protocol", &self.protocol)
+            .field("failure_mode", &self.failure_mode)
+            .field("timeout", &self.timeout)
+            .field("servers", &self.servers)
+            .finish()
+    }
+}
+
+/// Describes a request to a gRPC authorizer.
+fn check_request<B>(req: &http::Request<B>, key: &Key) -> CheckRequest {
+    let headers = req
+        .headers()
+        .keys()
+        .map(|name| {
+            // Values of repeated headers are merged.
+            let value = req
+                .headers()
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .collect::<Vec<_>>()
+                .join(",");
+            (name.as_str().to_string(), value)
+        })
+        .collect();
+    let host = req
+        .uri()
+        .authority()
+        .map(|a| a.as_str())
+        .or_else(|| req.headers().get(http::header::HOST)?.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let http = attribute_context::HttpRequest {
+        id: req
+            .headers()
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string(),
+        method: req.method().to_string(),
+        headers,
+        path: path_and_query(req).to_string(),
+        host,
+        scheme: req.uri().scheme_str().unwrap_or("http").to_string(),
+        protocol: format!("{:?}", req.version()),
+    };
+    let context_extensions = vec![
+        ("server".to_string(), key.server.clone()),
+        ("route".to_string(), key.route.clone()),
+    ];
+    CheckRequest {
+        attributes: Some(AttributeContext {
+            source: Some(attribute_context::Peer {
+                principal: key.principal.clone().unwrap_or_default(),
+                ..Default::default()
+            }),
+            destination: Some(attribute_context::Peer {
+                service: key.server.clone(),
+                ..Default::default()
+            }),
+            request: Some(attribute_context::Request {
+                time: None,
+                http: Some(http),
+            }),
+            context_extensions: context_extensions.into_iter().collect(),
+        }),
+    }
+}
+
+/// Copies a request's method, path, and headers into a request to an HTTP
+/// authorizer.
+fn http_request<B>(req: &http::Request<B>, key: &Key) -> http::Request<tonic::body::BoxBody> {
+    let body = http_body::Empty::new()
+        .map_err(|never| -> tonic::Status { match never {} })
+        .boxed();
+    let mut authz = http::Request::new(body);
+    *authz.method_mut() = req.method().clone();
+    *authz.uri_mut() = path_and_query(req)
+        .parse()
+        .unwrap_or_else(|_| http::uri::Uri::from_static("/"));
+    *authz.headers_mut() = req.headers().clone();
+    for h in &[
+        http::header::CONTENT_LENGTH,
+        http::header::TRANSFER_ENCODING,
+        http::header::CONNECTION,
+        http::header::HOST,
+    ] {
+        authz.headers_mut().remove(h);
+    }
+    authz.headers_mut().remove(L5D_CLIENT_ID);
+    if let Some(id) = key
+        .principal
+        .as_ref()
+        .and_then(|id| http::HeaderValue::from_str(id).ok())
+    {
+        authz.headers_mut().insert(L5D_CLIENT_ID, id);
+    }
+    authz
+}
+
+fn path_and_query<B>(req: &http::Request<B>) -> &str {
+    req.uri()
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/")
+}
+
+fn decide_grpc(rsp: CheckResponse) -> Decision {
+    let ok = rsp
+        .status
+        .as_ref()
+        .map(|rpc::Status { code, .. }| *code == tonic::Code::Ok as i32)
+        .unwrap_or(true);
+    match rsp.http_response {
+        Some(check_response::HttpResponse::DeniedResponse(denied)) if !ok => {
+            let status = denied
+                .status
+                .and_then(|s| http::StatusCode::from_u16(s.code as u16).ok())
+                .filter(|s| s.is_client_error() || s.is_server_error())
+                .unwrap_or(http::StatusCode::FORBIDDEN);
+            Err(ExtAuthzDenied { status })
+        }
+        _ if !ok => Err(ExtAuthzDenied {
+            status: http::StatusCode::FORBIDDEN,
+        }),
+        Some(check_response::HttpResponse::OkResponse(rsp)) => {
+            let set = rsp
+                .headers
+                .into_iter()
+                .filter_map(|HeaderValueOption { header, append }| {
+                    let header = header?;
+                    let name = http::HeaderName::from_bytes(header.key.as_bytes()).ok()?;
+                    let value = http::HeaderValue::from_str(&header.value).ok()?;
+                    // Values are appended unless the authorizer says otherwise.
+                    Some((name, value, append.unwrap_or(true)))
+                })
+                .collect();
+            let remove = rsp
+                .headers_to_remove
+                .iter()
+                .filter_map(|h| http::HeaderName::from_bytes(h.as_bytes()).ok())
+                .collect();
+            Ok(Allow { set, remove })
+        }
+        _ => Ok(Allow::default()),
+    }
+}
+
+fn decide_http(
+    status: http::StatusCode,
+    headers: &http::header::HeaderMap,
+    allowed: &[http::HeaderName],
+) -> Result<Decision, Error> {
+    if status.is_server_error() {
+        return Err(ServiceError(status).into());
+    }
+    if !status.is_success() {
+        return Ok(Err(ExtAuthzDenied { status }));
+    }
+    let set = allowed
+        .iter()
+        .flat_map(|name| {
+            // The first value replaces any that the client set.
+            headers
+                .get_all(name)
+                .iter()
+                .enumerate()
+                .map(move |(i, value)| (name.clone(), value.clone(), i > 0))
+        })
+        .collect();
+    Ok(Ok(Allow {
+        set,
+        remove: vec![],
+    }))
+}
+
+// === impl Allow ===
+
+impl Allow {
+    /// Applies the authorizer's header mutations to a request's headers.
+    pub fn apply(&self, headers: &mut http::header::HeaderMap) {
+        for name in self.remove.iter() {
+            headers.remove(name);
+        }
+        for (name, value, append) in self.set.iter() {
+            if *append {
+                headers.append(name.clone(), value.clone());
+            } else {
+                headers.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+// === impl ExtAuthzDenied ===
+
+impl ExtAuthzDenied {
+    /// The status with which the client should be answered.
+    pub fn status(&self) -> http::StatusCode {
+        self.status
+    }
+}
+
+// === impl Cache ===
+
+impl Cache {
+    fn get(&self, key: &Key) -> Option<Decision> {
+        let decisions = self.decisions.lock();
+        let (expiry, decision) = decisions.get(key)?;
+        if Instant::now() < *expiry {
+            return Some(decision.clone());
+        }
+        None
+    }
+
+    fn insert(&self, key: Key, decision: Decision) {
+        // Header mutations may be specific to the request (e.g. derived from
+        // its credentials), so they're never reused.
+        if matches!(decision, Ok(ref allow) if *allow != Allow::default()) {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut decisions = self.decisions.lock();
+        if decisions.len() >= self.capacity && !decisions.contains_key(&key) {
+            decisions.retain(|_, (expiry, _)| now < *expiry);
+            if decisions.len() >= self.capacity {
+                return;
+            }
+        }
+        decisions.insert(key, (now + self.ttl, decision));
+    }
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    fn record(&self, outcome: Outcome, elapsed: Duration) {
+        self.durations
+            .lock()
+            .entry(outcome)
+            .or_insert_with(|| Histogram::new(latency::BOUNDS))
+            .add(elapsed);
+    }
+}
+
+// === impl Report ===
+
+impl Report {
+    pub fn disabled() -> Self {
+        Self(None)
+    }
+}
+
+impl FmtMetrics for Report {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics = match self.0 {
+            Some(ref metrics) => metrics,
+            None => return Ok(()),
+        };
+
+        ext_authz_cache_hits_total.fmt_help(f)?;
+        ext_authz_cache_hits_total.fmt_metric(f, &*metrics.cache_hits)?;
+
+        let durations = metrics.durations.lock();
+        if durations.is_empty() {
+            return Ok(());
+        }
+        ext_authz_request_duration_ms.fmt_help(f)?;
+        ext_authz_request_duration_ms.fmt_scopes(f, durations.iter(), |h| h)
+    }
+}
+
+impl FmtLabels for Outcome {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = match self {
+            Outcome::Allow => "allow",
+            Outcome::Deny => "deny",
+            Outcome::Error => "error",
+        };
+        write!(f, "result=\"{}\"", result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use envoy_ext_authz_proto::envoy::{
+        config::core::v3::HeaderValue,
+        r#type::v3::HttpStatus,
+        service::auth::v3::{DeniedHttpResponse, OkHttpResponse},
+    };
+
+    fn key(route: &str) -> Key {
+        Key {
+            server: "web".to_string(),
+            principal: Some("client.ns.serviceaccount.identity.linkerd.cluster.local".to_string()),
+            route: route.to_string(),
+        }
+    }
+
+    #[test]
+    fn describes_requests() {
+        let req = http::Request::builder()
+            .method(http::Method::POST)
+            .uri("/books?limit=10")
+            .header(http::header::HOST, "web.ns.svc.cluster.local:8080")
+            .header("x-tenant", "acme")
+            .header(http::header::CONTENT_LENGTH, "12")
+            .body(())
+            .unwrap();
+
+        let CheckRequest { attributes } = check_request(&req, &key("books"));
+        let attributes = attributes.unwrap();
+        assert_eq!(
+            attributes.source.unwrap().principal,
+            "client.ns.serviceaccount.identity.linkerd.cluster.local"
+        );
+        assert_eq!(
+            attributes
+                .context_extensions
+                .get("route")
+                .map(String::as_str),
+            Some("books")
+        );
+        let http = attributes.request.unwrap().http.unwrap();
+        assert_eq!(http.method, "POST");
+        assert_eq!(http.path, "/books?limit=10");
+        assert_eq!(http.host, "web.ns.svc.cluster.local:8080");
+        assert_eq!(
+            http.headers.get("x-tenant").map(String::as_str),
+            Some("acme")
+        );
+
+        let authz = http_request(&req, &key("books"));
+        assert_eq!(authz.method(), http::Method::POST);
+        assert_eq!(authz.uri(), "/books?limit=10");
+        assert!(authz.headers().get(http::header::CONTENT_LENGTH).is_none());
+        assert_eq!(
+            authz
+                .headers()
+                .get(L5D_CLIENT_ID)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "client.ns.serviceaccount.identity.linkerd.cluster.local"
+        );
+    }
+
+    #[test]
+    fn decides_grpc_responses() {
+        let denied = CheckResponse {
+            status: Some(rpc::Status {
+                code: tonic::Code::PermissionDenied as i32,
+                message: String::new(),
+            }),
+            http_response: Some(check_response::HttpResponse::DeniedResponse(
+                DeniedHttpResponse {
+                    status: Some(HttpStatus { code: 401 }),
+                    ..Default::default()
+                },
+            )),
+        };
+        assert_eq!(
+            decide_grpc(denied).unwrap_err().status(),
+            http::StatusCode::UNAUTHORIZED
+        );
+
+        let allowed = CheckResponse {
+            status: Some(rpc::Status::default()),
+            http_response: Some(check_response::HttpResponse::OkResponse(OkHttpResponse {
+                headers: vec![HeaderValueOption {
+                    header: Some(HeaderValue {
+                        key: "x-user".to_string(),
+                        value: "alice".to_string(),
+                    }),
+                    append: Some(false),
+                }],
+                headers_to_remove: vec!["authorization".to_string()],
+            })),
+        };
+        let allow = decide_grpc(allowed).expect("request must be allowed");
+        let mut headers = http::header::HeaderMap::new();
+        headers.insert("x-user", http::HeaderValue::from_static("mallory"));
+        headers.insert(
+            http::header::AUTHORIZATION,
+            http::HeaderValue::from_static("Bearer token"),
+        );
+        allow.apply(&mut headers);
+        assert_eq!(headers.get("x-user").unwrap(), "alice");
+        assert!(headers.get(http::header::AUTHORIZATION).is_none());
+    }
+
+    #[test]
+    fn decides_http_responses() {
+        let mut headers = http::header::HeaderMap::new();
+        headers.insert("x-user", http::HeaderValue::from_static("alice"));
+        headers.insert("x-powered-by", http::HeaderValue::from_static("authz"));
+        headers.insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static("text/plain"),
+        );
+        let allowed = [http::HeaderName::from_static("x-user")];
+        let allow = decide_http(http::StatusCode::OK, &headers, &allowed)
+            .unwrap()
+            .expect("request must be allowed");
+        let mut req = http::header::HeaderMap::new();
+        req.insert("x-user", http::HeaderValue::from_static("mallory"));
+        allow.apply(&mut req);
+        assert_eq!(req.get_all("x-user").iter().collect::<Vec<_>>(), ["alice"]);
+        assert!(req.get("x-powered-by").is_none());
+        assert!(req.get(http::header::CONTENT_TYPE).is_none());
+
+        let denied = decide_http(http::StatusCode::FORBIDDEN, &headers, &allowed).unwrap();
+        assert_eq!(denied.unwrap_err().status(), http::StatusCode::FORBIDDEN);
+        assert!(decide_http(http::StatusCode::BAD_GATEWAY, &headers, &allowed).is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn caches_decisions() {
+        let cache = Cache {
+            ttl: Duration::from_secs(10),
+            capacity: 1,
+            decisions: Default::default(),
+        };
+        cache.insert(key("books"), Ok(Allow::default()));
+        assert!(cache.get(&key("books")).unwrap().is_ok());
+
+        // Decisions are not cached beyond the cache's capacity.
+        cache.insert(
+            key("authors"),
+            Err(ExtAuthzDenied {
+                status: http::StatusCode::FORBIDDEN,
+            }),
+        );
+        assert!(cache.get(&key("authors")).is_none());
+
+        // Expired decisions are evicted to make room.
+        time::advance(Duration::from_secs(11)).await;
+        assert!(cache.get(&key("books")).is_none());
+        cache.insert(
+            key("authors"),
+            Err(ExtAuthzDenied {
+                status: http::StatusCode::FORBIDDEN,
+            }),
+        );
+        assert!(cache.get(&key("authors")).unwrap().is_err());
+
+        // Decisions that mutate headers are never cached.
+        time::advance(Duration::from_secs(11)).await;
+        cache.insert(
+            key("books"),
+            Ok(Allow {
+                set: vec![],
+                remove: vec![http::header::AUTHORIZATION],
+            }),
+        );
+        assert!(cache.get(&key("books")).is_none());
+    }
+}
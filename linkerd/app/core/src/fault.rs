@@ -0,0 +1,210 @@
+//! Fault injection.
+//!
+//! Services may be configured to inject faults into a percentage of their
+//! routes' requests, so that clients' resilience can be exercised without
+//! changes to the application: requests may be delayed by a fixed duration
+//! before they are dispatched, or aborted with a given status. Faults may be
+//! limited to a single route by name.
+//!
+//! Faults are injected beneath the route's timeout, retry, and hedge policies,
+//! so that injected faults exercise those policies, and are recorded by the
+//! route's metrics.
+
+use crate::{
+    dst::Route,
+    route_policy::{RoutePolicies, RoutePolicy},
+};
+use futures::prelude::*;
+use linkerd_error::Error;
+use linkerd_stack::{layer, NewService, Proxy, ProxyService, ServiceExt};
+use rand::Rng;
+use std::{pin::Pin, sync::Arc, time::Duration};
+use tokio::time;
+use tracing::debug;
+
+/// Maps logical service addresses to the faults injected into their routes.
+pub type Faults = RoutePolicies<Fault>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fault {
+    /// If set, the fault is only injected into requests on the route with
+    /// this name.
+    pub route: Option<String>,
+    pub kind: Kind,
+    /// The percentage of requests into which the fault is injected.
+    pub percent: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// Requests are dispatched after a delay.
+    Delay(Duration),
+
+    /// Requests are not dispatched and are answered with the given status.
+    Abort(http::StatusCode),
+}
+
+/// Applies per-route faults.
+#[derive(Clone, Debug)]
+pub struct NewInjectFaults<N> {
+    faults: Faults,
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct InjectFaults<P> {
+    faults: Arc<[Fault]>,
+    inner: P,
+}
+
+type ResponseFuture<B> = Pin<Box<dyn Future<Output = Result<http::Response<B>, Error>> + Send>>;
+
+pub fn layer<N>(faults: Faults) -> impl layer::Layer<N, Service = NewInjectFaults<N>> + Clone {
+    layer::mk(move |inner| NewInjectFaults {
+        faults: faults.clone(),
+        inner,
+    })
+}
+
+// === impl Fault ===
+
+impl RoutePolicy for Fault {
+    fn route_name(&self) -> Option<&str> {
+        self.route.as_deref()
+    }
+}
+
+// === impl NewInjectFaults ===
+
+impl<N> NewService<Route> for NewInjectFaults<N>
+where
+    N: NewService<Route>,
+{
+    type Service = InjectFaults<N::Service>;
+
+    fn new_service(&mut self, route: Route) -> Self::Service {
+        let faults = self.faults.get(&route).cloned().collect();
+        let inner = self.inner.new_service(route);
+        InjectFaults { faults, inner }
+    }
+}
+
+// === impl InjectFaults ===
+
+impl<P, A, B, S> Proxy<http::Request<A>, S> for InjectFaults<P>
+where
+    A: Send + 'static,
+    B: Default + Send + 'static,
+    P: Proxy<http::Request<A>, S, Response = http::Response<B>> + Clone + Send + 'static,
+    P::Future: Send + 'static,
+    S: tower::Service<P::Request> + Clone + Send + 'static,
+    S::Error: Into<Error>,
+{
+    type Request = P::Request;
+    type Response = http::Response<B>;
+    type Error = Error;
+    type Future = future::Either<future::ErrInto<P::Future, Error>, ResponseFuture<B>>;
+
+    fn proxy(&self, svc: &mut S, req: http::Request<A>) -> Self::Future {
+        let mut delay = None;
+        let mut abort = None;
+        if !self.faults.is_empty() {
+            let mut rng = rand::thread_rng();
+            for fault in self.faults.iter() {
+                if !rng.gen_ratio(fault.percent.min(100), 100) {
+                    continue;
+                }
+                match fault.kind {
+                    Kind::Delay(d) => {
+                        delay.get_or_insert(d);
+                    }
+                    Kind::Abort(status) => {
+                        abort.get_or_insert(status);
+                    }
+                }
+            }
+        }
+
+        match (delay, abort) {
+            (None, None) => future::Either::Left(self.inner.proxy(svc, req).err_into()),
+            (delay, Some(status)) => future::Either::Right(Box::pin(async move {
+                if let Some(delay) = delay {
+                    time::sleep(delay).await;
+                }
+                debug!(%status, "Aborting request");
+                let mut rsp = http::Response::new(B::default());
+                *rsp.status_mut() = status;
+                Ok(rsp)
+            })),
+            (Some(delay), None) => {
+                let svc = ProxyService::new(self.inner.clone(), svc.clone());
+                future::Either::Right(Box::pin(async move {
+                    debug!(?delay, "Delaying request");
+                    time::sleep(delay).await;
+                    svc.oneshot(req).await
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::route_policy::test_util::{policies as faults, route};
+    use linkerd_proxy_http::BoxBody;
+
+    async fn send(route: Route, faults: Faults) -> http::StatusCode {
+        let svc = crate::svc::mk(|_: http::Request<BoxBody>| {
+            future::ok::<_, Error>(http::Response::new(BoxBody::default()))
+        });
+        let proxy = NewInjectFaults {
+            faults,
+            inner: |_: Route| (),
+        }
+        .new_service(route);
+        ProxyService::new(proxy, svc)
+            .oneshot(http::Request::new(BoxBody::default()))
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn aborts_requests() {
+        let abort = faults(vec![Fault {
+            route: Some("GET /books".to_string()),
+            kind: Kind::Abort(http::StatusCode::SERVICE_UNAVAILABLE),
+            percent: 100,
+        }]);
+        assert_eq!(
+            send(route("GET /books"), abort.clone()).await,
+            http::StatusCode::SERVICE_UNAVAILABLE
+        );
+        // Other routes are unaffected.
+        assert_eq!(
+            send(route("GET /authors"), abort).await,
+            http::StatusCode::OK
+        );
+
+        let never = faults(vec![Fault {
+            route: None,
+            kind: Kind::Abort(http::StatusCode::SERVICE_UNAVAILABLE),
+            percent: 0,
+        }]);
+        assert_eq!(send(route("GET /books"), never).await, http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn delays_requests() {
+        time::pause();
+        let delay = faults(vec![Fault {
+            route: None,
+            kind: Kind::Delay(Duration::from_secs(5)),
+            percent: 100,
+        }]);
+        let start = time::Instant::now();
+        assert_eq!(send(route("GET /books"), delay).await, http::StatusCode::OK);
+        assert!(start.elapsed() >= Duration::from_secs(5));
+    }
+}
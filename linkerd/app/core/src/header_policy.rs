@@ -0,0 +1,263 @@
+//! Header manipulation policies.
+//!
+//! Services may be configured to modify the headers of their routes' requests
+//! and responses: headers may be added (alongside any existing values), set
+//! (replacing any existing values), or removed, and requests may be given a
+//! generated request ID when they lack one. Policies may be limited to a single
+//! route by name.
+//!
+//! Request headers are modified above the route's retry and hedge policies, so
+//! that every attempt is dispatched with the same headers (including any
+//! generated request ID).
+
+use crate::{
+    dst::Route,
+    route_policy::{RoutePolicies, RoutePolicy},
+};
+use futures::prelude::*;
+use linkerd_stack::{layer, NewService, Proxy};
+use rand::Rng;
+use std::{pin::Pin, sync::Arc};
+
+/// Maps logical service addresses to the header policies of their routes.
+pub type HeaderPolicies = RoutePolicies<HeaderPolicy>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderPolicy {
+    /// If set, the policy only applies to the route with this name.
+    pub route: Option<String>,
+    pub message: Message,
+    pub op: Op,
+}
+
+/// The message whose headers are modified.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Request,
+    Response,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op {
+    /// Adds a value, retaining any existing values.
+    Add(http::HeaderName, http::HeaderValue),
+
+    /// Sets a value, replacing any existing values.
+    Set(http::HeaderName, http::HeaderValue),
+
+    /// Removes all values.
+    Remove(http::HeaderName),
+
+    /// Sets a randomly generated ID, unless the header is already set.
+    RequestId(http::HeaderName),
+}
+
+/// Applies per-route header policies.
+#[derive(Clone, Debug)]
+pub struct NewModifyHeaders<N> {
+    policies: HeaderPolicies,
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct ModifyHeaders<P> {
+    request: Arc<[Op]>,
+    response: Arc<[Op]>,
+    inner: P,
+}
+
+type ResponseFuture<R, E> = Pin<Box<dyn Future<Output = Result<R, E>> + Send + 'static>>;
+
+pub fn layer<N>(
+    policies: HeaderPolicies,
+) -> impl layer::Layer<N, Service = NewModifyHeaders<N>> + Clone {
+    layer::mk(move |inner| NewModifyHeaders {
+        policies: policies.clone(),
+        inner,
+    })
+}
+
+// === impl HeaderPolicy ===
+
+impl RoutePolicy for HeaderPolicy {
+    fn route_name(&self) -> Option<&str> {
+        self.route.as_deref()
+    }
+}
+
+// === impl Op ===
+
+impl Op {
+    fn apply(&self, headers: &mut http::HeaderMap) {
+        match self {
+            Op::Add(name, value) => {
+                headers.append(name.clone(), value.clone());
+            }
+            Op::Set(name, value) => {
+                headers.insert(name.clone(), value.clone());
+            }
+            Op::Remove(name) => {
+                headers.remove(name);
+            }
+            Op::RequestId(name) => {
+                if !headers.contains_key(name) {
+                    let mut rng = rand::thread_rng();
+                    let id = format!("{:016x}{:016x}", rng.gen::<u64>(), rng.gen::<u64>());
+                    let value =
+                        http::HeaderValue::from_str(&id).expect("hex must be a valid value");
+                    headers.insert(name.clone(), value);
+                }
+            }
+        }
+    }
+}
+
+// === impl NewModifyHeaders ===
+
+impl<N> NewService<Route> for NewModifyHeaders<N>
+where
+    N: NewService<Route>,
+{
+    type Service = ModifyHeaders<N::Service>;
+
+    fn new_service(&mut self, route: Route) -> Self::Service {
+        let policies = self.policies.get(&route);
+        let (request, response) =
+            policies.partition::<Vec<_>, _>(|p| p.message == Message::Request);
+        let ops = |ps: Vec<&HeaderPolicy>| ps.into_iter().map(|p| p.op.clone()).collect();
+        let inner = self.inner.new_service(route);
+        ModifyHeaders {
+            request: ops(request),
+            response: ops(response),
+            inner,
+        }
+    }
+}
+
+// === impl ModifyHeaders ===
+
+impl<P, A, B, S> Proxy<http::Request<A>, S> for ModifyHeaders<P>
+where
+    B: 'static,
+    P: Proxy<http::Request<A>, S, Response = http::Response<B>>,
+    P::Error: 'static,
+    P::Future: Send + 'static,
+    S: tower::Service<P::Request>,
+{
+    type Request = P::Request;
+    type Response = http::Response<B>;
+    type Error = P::Error;
+    type Future = future::Either<P::Future, ResponseFuture<http::Response<B>, P::Error>>;
+
+    fn proxy(&self, svc: &mut S, mut req: http::Request<A>) -> Self::Future {
+        for op in self.request.iter() {
+            op.apply(req.headers_mut());
+        }
+
+        let rsp = self.inner.proxy(svc, req);
+        if self.response.is_empty() {
+            return future::Either::Left(rsp);
+        }
+
+        let ops = self.response.clone();
+        future::Either::Right(Box::pin(rsp.map_ok(move |mut rsp| {
+            for op in ops.iter() {
+                op.apply(rsp.headers_mut());
+            }
+            rsp
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::route_policy::test_util::{policies, route};
+    use linkerd_error::Error;
+    use linkerd_proxy_http::BoxBody;
+    use linkerd_stack::{ProxyService, ServiceExt};
+
+    fn name(n: &'static str) -> http::HeaderName {
+        http::HeaderName::from_static(n)
+    }
+
+    /// Sends a request with an `x-internal` header, returning the headers that
+    /// the service received and the headers of its response.
+    async fn send(route: Route, policies: HeaderPolicies) -> (http::HeaderMap, http::HeaderMap) {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let tx = std::sync::Mutex::new(Some(tx));
+        let svc = crate::svc::mk(move |req: http::Request<BoxBody>| {
+            if let Some(tx) = tx.lock().unwrap().take() {
+                let _ = tx.send(req.headers().clone());
+            }
+            let mut rsp = http::Response::new(BoxBody::default());
+            rsp.headers_mut()
+                .insert("x-internal", http::HeaderValue::from_static("true"));
+            future::ok::<_, Error>(rsp)
+        });
+        let proxy = NewModifyHeaders {
+            policies,
+            inner: |_: Route| (),
+        }
+        .new_service(route);
+        let req = http::Request::builder()
+            .header("x-internal", "true")
+            .body(BoxBody::default())
+            .unwrap();
+        let rsp = ProxyService::new(proxy, svc).oneshot(req).await.unwrap();
+        (rx.await.unwrap(), rsp.headers().clone())
+    }
+
+    #[tokio::test]
+    async fn modifies_requests() {
+        let policies = policies(vec![
+            HeaderPolicy {
+                route: None,
+                message: Message::Request,
+                op: Op::Remove(name("x-internal")),
+            },
+            HeaderPolicy {
+                route: None,
+                message: Message::Request,
+                op: Op::RequestId(name("x-request-id")),
+            },
+            HeaderPolicy {
+                route: Some("GET /books".to_string()),
+                message: Message::Request,
+                op: Op::Set(name("x-env"), http::HeaderValue::from_static("prod")),
+            },
+        ]);
+
+        let (req, rsp) = send(route("GET /books"), policies.clone()).await;
+        assert!(req.get("x-internal").is_none());
+        assert_eq!(req.get("x-request-id").unwrap().len(), 32);
+        assert_eq!(req.get("x-env").unwrap(), "prod");
+        assert_eq!(rsp.get("x-internal").unwrap(), "true");
+
+        // Other routes are only modified by unscoped policies.
+        let (req, _) = send(route("GET /authors"), policies).await;
+        assert!(req.get("x-internal").is_none());
+        assert!(req.get("x-env").is_none());
+    }
+
+    #[tokio::test]
+    async fn modifies_responses() {
+        let policies = policies(vec![
+            HeaderPolicy {
+                route: None,
+                message: Message::Response,
+                op: Op::Remove(name("x-internal")),
+            },
+            HeaderPolicy {
+                route: None,
+                message: Message::Response,
+                op: Op::Add(name("x-served-by"), http::HeaderValue::from_static("web")),
+            },
+        ]);
+
+        let (req, rsp) = send(route("GET /books"), policies).await;
+        assert_eq!(req.get("x-internal").unwrap(), "true");
+        assert!(rsp.get("x-internal").is_none());
+        assert_eq!(rsp.get("x-served-by").unwrap(), "web");
+    }
+}
@@ -0,0 +1,430 @@
+//! Request hedging.
+//!
+//! Retries only help requests that fail; they do nothing for requests that
+//! are merely slow. When a request on a hedged route has not been answered
+//! within the route's hedge delay, a duplicate request is dispatched and the
+//! first successful response is used. The other request is canceled.
+//!
+//! The delay is either fixed or a percentile of the route's recent response
+//! latencies (e.g. p95), so that only the slowest requests are hedged. The
+//! hedged request is dispatched through the balancer again. The original
+//! request still counts toward its endpoint's load, so the balancer is likely
+//! to pick a different endpoint.
+//!
+//! Hedged requests withdraw from the route's retry budget. Only retryable
+//! routes are hedged, and hedging stops while the budget is exhausted.
+
+use crate::{classify, dst::Route, profiles, retry::clone_request, NameAddr};
+use futures::prelude::*;
+use linkerd_error::Error;
+use linkerd_http_classify::{Classify, ClassifyEos, ClassifyResponse};
+use linkerd_http_retry::ReplayBody;
+use linkerd_retry as retry;
+use linkerd_stack::{layer, NewService, Proxy, ProxyService, ServiceExt};
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, VecDeque},
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time::{self, Instant};
+use tracing::{debug, trace};
+
+/// The number of recent latencies from which a route's hedge delay is
+/// estimated.
+const MAX_SAMPLES: usize = 1_000;
+
+/// The number of latencies that must be recorded before a route's hedge delay
+/// is estimated.
+const MIN_SAMPLES: usize = 100;
+
+/// The number of latencies recorded between estimates of a route's hedge
+/// delay.
+const ESTIMATE_INTERVAL: usize = 100;
+
+/// Maps logical service addresses to the delays after which requests on their
+/// routes are hedged.
+#[derive(Clone, Debug, Default)]
+pub struct Hedges(Arc<HashMap<NameAddr, Delay>>);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Delay {
+    /// Requests are hedged after a fixed delay.
+    Fixed(Duration),
+
+    /// Requests are hedged after the given percentile (e.g. `0.95`) of the
+    /// route's recent response latencies. Nothing is hedged until enough
+    /// latencies have been recorded.
+    Percentile(f64),
+}
+
+#[derive(Clone, Debug)]
+pub struct NewHedgePolicy {
+    hedges: Hedges,
+    max_buffered_bytes: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct HedgePolicy {
+    delay: Delay,
+    budget: Arc<retry::Budget>,
+    response_classes: profiles::http::ResponseClasses,
+    latencies: Arc<Mutex<Latencies>>,
+    max_buffered_bytes: usize,
+}
+
+/// Applies per-route hedge policies.
+#[derive(Clone, Debug)]
+pub struct NewHedge<N> {
+    new_policy: NewHedgePolicy,
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct Hedge<P> {
+    policy: Option<HedgePolicy>,
+    inner: P,
+}
+
+type ResponseFuture<B> = Pin<Box<dyn Future<Output = Result<http::Response<B>, Error>> + Send>>;
+
+#[derive(Debug, Default)]
+struct Latencies {
+    samples: VecDeque<Duration>,
+    since_estimate: usize,
+    estimate: Option<Duration>,
+}
+
+pub fn layer<N>(policy: NewHedgePolicy) -> impl layer::Layer<N, Service = NewHedge<N>> + Clone {
+    layer::mk(move |inner| NewHedge {
+        new_policy: policy.clone(),
+        inner,
+    })
+}
+
+// === impl Hedges ===
+
+impl Hedges {
+    pub fn new(hedges: impl IntoIterator<Item = (NameAddr, Delay)>) -> Self {
+        Self(Arc::new(hedges.into_iter().collect()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, profiles::LogicalAddr(addr): &profiles::LogicalAddr) -> Option<Delay> {
+        self.0.get(addr).copied()
+    }
+}
+
+// === impl NewHedgePolicy ===
+
+impl NewHedgePolicy {
+    /// Hedges requests whose bodies fit within `max_buffered_bytes`, so that
+    /// they may be replayed.
+    pub fn new(hedges: Hedges, max_buffered_bytes: usize) -> Self {
+        Self {
+            hedges,
+            max_buffered_bytes,
+        }
+    }
+}
+
+impl retry::NewPolicy<Route> for NewHedgePolicy {
+    type Policy = HedgePolicy;
+
+    fn new_policy(&self, route: &Route) -> Option<Self::Policy> {
+        let delay = self.hedges.get(&route.addr)?;
+        let retries = route.route.retries()?;
+        Some(HedgePolicy {
+            delay,
+            budget: retries.budget().clone(),
+            response_classes: route.route.response_classes().clone(),
+            latencies: Default::default(),
+            max_buffered_bytes: self.max_buffered_bytes,
+        })
+    }
+}
+
+// === impl HedgePolicy ===
+
+impl HedgePolicy {
+    fn can_hedge<A: http_body::Body>(&self, req: &http::Request<A>) -> bool {
+        if req.body().is_end_stream() {
+            return true;
+        }
+        req.headers()
+            .get(http::header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse::<usize>().ok())
+            .map(|len| len <= self.max_buffered_bytes)
+            .unwrap_or(false)
+    }
+
+    /// Returns the delay after which the route's requests are hedged, if it is
+    /// known.
+    fn delay(&self) -> Option<Duration> {
+        match self.delay {
+            Delay::Fixed(delay) => Some(delay),
+            Delay::Percentile(_) => self.latencies.lock().estimate,
+        }
+    }
+
+    fn record(&self, latency: Duration) {
+        if let Delay::Percentile(percentile) = self.delay {
+            self.latencies.lock().record(latency, percentile);
+        }
+    }
+
+    fn is_failure<B>(
+        &self,
+        classify: &classify::Response,
+        res: &Result<http::Response<B>, Error>,
+    ) -> bool {
+        match res {
+            Ok(rsp) => classify.clone().start(rsp).eos(None).is_failure(),
+            Err(_) => true,
+        }
+    }
+}
+
+// === impl Latencies ===
+
+impl Latencies {
+    fn record(&mut self, latency: Duration, percentile: f64) {
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+
+        self.since_estimate += 1;
+        if self.samples.len() < MIN_SAMPLES || self.since_estimate < ESTIMATE_INTERVAL {
+            return;
+        }
+        self.since_estimate = 0;
+
+        let mut samples = self.samples.iter().copied().collect::<Vec<_>>();
+        let idx = ((samples.len() - 1) as f64 * percentile).round() as usize;
+        let (_, estimate, _) = samples.select_nth_unstable(idx);
+        trace!(estimate = ?*estimate, "Estimated hedge delay");
+        self.estimate = Some(*estimate);
+    }
+}
+
+// === impl NewHedge ===
+
+impl<N> NewService<Route> for NewHedge<N>
+where
+    N: NewService<Route>,
+{
+    type Service = Hedge<N::Service>;
+
+    fn new_service(&mut self, route: Route) -> Self::Service {
+        let policy = retry::NewPolicy::new_policy(&self.new_policy, &route);
+        let inner = self.inner.new_service(route);
+        Hedge { policy, inner }
+    }
+}
+
+// === impl Hedge ===
+
+impl<P, A, B, S, PReq, PErr, PFut> Proxy<http::Request<A>, S> for Hedge<P>
+where
+    A: http_body::Body + Unpin + Send + 'static,
+    A::Data: Send,
+    A::Error: Into<Error>,
+    B: Send + 'static,
+    P: Proxy<
+            http::Request<A>,
+            S,
+            Request = PReq,
+            Response = http::Response<B>,
+            Error = PErr,
+            Future = PFut,
+        > + Proxy<
+            http::Request<ReplayBody<A>>,
+            S,
+            Request = PReq,
+            Response = http::Response<B>,
+            Error = PErr,
+            Future = PFut,
+        > + Clone
+        + Send
+        + 'static,
+    PErr: Into<Error>,
+    PFut: Future<Output = Result<http::Response<B>, PErr>> + Send + 'static,
+    S: tower::Service<PReq> + Clone + Send + 'static,
+    S::Error: Into<Error>,
+{
+    type Request = PReq;
+    type Response = http::Response<B>;
+    type Error = Error;
+    type Future = future::Either<future::ErrInto<PFut, Error>, ResponseFuture<B>>;
+
+    fn proxy(&self, svc: &mut S, req: http::Request<A>) -> Self::Future {
+        let policy = match self.policy.as_ref() {
+            Some(policy) if policy.can_hedge(&req) => policy.clone(),
+            _ => {
+                let call = Proxy::<http::Request<A>, S>::proxy(&self.inner, svc, req);
+                return future::Either::Left(call.err_into());
+            }
+        };
+
+        // Until the route's delay is known, requests are not hedged, but their
+        // latencies are recorded.
+        let delay = match policy.delay() {
+            Some(delay) => delay,
+            None => {
+                let call = Proxy::<http::Request<A>, S>::proxy(&self.inner, svc, req);
+                let start = Instant::now();
+                return future::Either::Right(Box::pin(async move {
+                    let rsp = call.await.map_err(Into::into)?;
+                    policy.record(start.elapsed());
+                    Ok(rsp)
+                }));
+            }
+        };
+
+        let req = req.map(|body| ReplayBody::new(body, policy.max_buffered_bytes));
+        let hedge_req = clone_request(&req);
+        let classify = classify::Request::from(policy.response_classes.clone()).classify(&req);
+        let hedge = ProxyService::new(self.inner.clone(), svc.clone());
+        let primary = Proxy::<http::Request<ReplayBody<A>>, S>::proxy(&self.inner, svc, req)
+            .err_into::<Error>();
+        future::Either::Right(Box::pin(async move {
+            let start = Instant::now();
+            tokio::pin!(primary);
+            tokio::select! {
+                res = &mut primary => {
+                    if res.is_ok() {
+                        policy.record(start.elapsed());
+                    }
+                    return res;
+                }
+                _ = time::sleep(delay) => {}
+            }
+
+            if policy.budget.withdraw().is_err() {
+                trace!("Retry budget exhausted; not hedging");
+                return primary.await;
+            }
+
+            debug!(?delay, "Hedging request");
+            let hedged_at = Instant::now();
+            let hedged = hedge.oneshot(hedge_req);
+            tokio::pin!(hedged);
+
+            // The first successful response is used. If one request fails
+            // while the other is in flight, the other's result is used. When
+            // this future completes, any outstanding request is dropped and
+            // therefore canceled.
+            match future::select(primary, hedged).await {
+                future::Either::Left((res, hedged)) => {
+                    if policy.is_failure(&classify, &res) {
+                        return hedged.await;
+                    }
+                    policy.record(start.elapsed());
+                    res
+                }
+                future::Either::Right((res, primary)) => {
+                    if policy.is_failure(&classify, &res) {
+                        return primary.await;
+                    }
+                    policy.record(hedged_at.elapsed());
+                    res
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_proxy_http::BoxBody;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A proxy that discards request bodies.
+    #[derive(Clone)]
+    struct DropBody;
+
+    impl<B, S> Proxy<http::Request<B>, S> for DropBody
+    where
+        S: tower::Service<http::Request<()>>,
+        S::Error: Into<Error>,
+    {
+        type Request = http::Request<()>;
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn proxy(&self, svc: &mut S, req: http::Request<B>) -> Self::Future {
+            svc.call(req.map(|_| ()))
+        }
+    }
+
+    fn policy(delay: Delay) -> HedgePolicy {
+        HedgePolicy {
+            delay,
+            budget: Arc::new(retry::Budget::new(Duration::from_secs(10), 10, 0.2)),
+            response_classes: Default::default(),
+            latencies: Default::default(),
+            max_buffered_bytes: 1024,
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn hedges_slow_requests() {
+        let delay = Duration::from_millis(100);
+        let hedge = Hedge {
+            policy: Some(policy(Delay::Fixed(delay))),
+            inner: DropBody,
+        };
+
+        // The first request is much slower than the delay; the hedged request
+        // is answered immediately.
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut svc = crate::svc::mk({
+            let calls = calls.clone();
+            move |_: http::Request<()>| {
+                let call = calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if call == 0 {
+                        time::sleep(Duration::from_secs(10)).await;
+                    }
+                    Ok::<_, Error>(http::Response::new(call))
+                }
+            }
+        });
+
+        let start = Instant::now();
+        let rsp = hedge
+            .proxy(&mut svc, http::Request::new(BoxBody::default()))
+            .await
+            .unwrap();
+        assert_eq!(*rsp.body(), 1, "the hedged request must respond");
+        assert_eq!(start.elapsed(), delay);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Requests that respond within the delay are not hedged.
+        let rsp = hedge
+            .proxy(&mut svc, http::Request::new(BoxBody::default()))
+            .await
+            .unwrap();
+        assert_eq!(*rsp.body(), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn estimates_percentile_delays() {
+        let mut latencies = Latencies::default();
+        for ms in 1..MIN_SAMPLES as u64 {
+            latencies.record(Duration::from_millis(ms), 0.95);
+        }
+        assert_eq!(latencies.estimate, None);
+
+        latencies.record(Duration::from_millis(MIN_SAMPLES as u64), 0.95);
+        assert_eq!(latencies.estimate, Some(Duration::from_millis(95)));
+    }
+}
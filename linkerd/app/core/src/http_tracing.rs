@@ -1,25 +1,57 @@
+use crate::{
+    access_log,
+    svc::{self, Param},
+};
 use linkerd_error::Error;
-use linkerd_opencensus::proto::trace::v1 as oc;
+use linkerd_opencensus::{proto::trace::v1 as oc, queue::SpanSender};
 use linkerd_stack::layer;
-use linkerd_trace_context::{self as trace_context, TraceContext};
-use std::{collections::HashMap, sync::Arc};
+use linkerd_trace_context::{self as trace_context, SpanLabels, TraceContext};
+use std::{
+    collections::HashMap,
+    fmt,
+    marker::PhantomData,
+    sync::Arc,
+    task::{Context, Poll},
+};
 use thiserror::Error;
-use tokio::sync::mpsc;
 
-pub type OpenCensusSink = Option<mpsc::Sender<oc::Span>>;
+pub use linkerd_trace_context::Precedence;
+
+pub type OpenCensusSink = Option<SpanSender>;
 pub type Labels = Arc<HashMap<String, String>>;
 
 /// SpanConverter converts trace_context::Span objects into OpenCensus agent
 /// protobuf span objects. SpanConverter receives trace_context::Span objects by
 /// implmenting the SpanSink trait. For each span that it receives, it converts
-/// it to an OpenCensus span and then sends it on the provided mpsc::Sender.
+/// it to an OpenCensus span and then enqueues it on the provided `SpanSender`.
+/// Spans are dropped rather than waiting when the export queue is full.
 #[derive(Clone)]
 pub struct SpanConverter {
     kind: Kind,
-    sink: mpsc::Sender<oc::Span>,
+    sink: SpanSender,
     labels: Labels,
 }
 
+/// Records a target's metric labels (e.g. `RouteLabels` or `AuthzLabels`) on
+/// the spans of its requests, so that traces may be filtered by the same
+/// dimensions as metrics.
+#[derive(Debug)]
+pub struct NewLabelSpan<L, N> {
+    inner: N,
+    _labels: PhantomData<fn() -> L>,
+}
+
+/// Describes a target's metric labels as span attributes.
+pub trait TraceLabels {
+    fn trace_labels(&self) -> Vec<(String, String)>;
+}
+
+#[derive(Clone, Debug)]
+pub struct LabelSpan<S> {
+    inner: S,
+    labels: Arc<[(String, String)]>,
+}
+
 #[derive(Debug, Error)]
 #[error("ID '{:?} should have {} bytes, but it has {}", self.id, self.expected_size, self.actual_size)]
 pub struct IdLengthError {
@@ -30,16 +62,18 @@ pub struct IdLengthError {
 
 pub fn server<S>(
     sink: OpenCensusSink,
+    precedence: Precedence,
     labels: impl Into<Labels>,
 ) -> impl layer::Layer<S, Service = TraceContext<Option<SpanConverter>, S>> + Clone {
-    SpanConverter::layer(Kind::Server, sink, labels)
+    SpanConverter::layer(Kind::Server, sink, precedence, labels)
 }
 
 pub fn client<S>(
     sink: OpenCensusSink,
+    precedence: Precedence,
     labels: impl Into<Labels>,
 ) -> impl layer::Layer<S, Service = TraceContext<Option<SpanConverter>, S>> + Clone {
-    SpanConverter::layer(Kind::Client, sink, labels)
+    SpanConverter::layer(Kind::Client, sink, precedence, labels)
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -52,35 +86,56 @@ impl SpanConverter {
     fn layer<S>(
         kind: Kind,
         sink: OpenCensusSink,
+        precedence: Precedence,
         labels: impl Into<Labels>,
     ) -> impl layer::Layer<S, Service = TraceContext<Option<Self>, S>> + Clone {
-        TraceContext::layer(sink.map(move |sink| Self {
-            kind,
-            sink,
-            labels: labels.into(),
-        }))
+        let labels: Labels = labels.into();
+        let sink = sink.map(move |sink| {
+            // Attribute dropped spans to the proxy direction and span kind that produced them.
+            let producer = match labels.get("direction") {
+                Some(direction) => format!("{}_{}", direction, kind),
+                None => kind.to_string(),
+            };
+            Self {
+                kind,
+                sink: sink.for_producer(&producer),
+                labels,
+            }
+        });
+        TraceContext::layer(sink, precedence)
     }
 
     fn mk_span(&self, mut span: trace_context::Span) -> Result<oc::Span, IdLengthError> {
         let mut attributes = HashMap::<String, oc::AttributeValue>::new();
         for (k, v) in self.labels.iter() {
-            attributes.insert(
-                k.clone(),
-                oc::AttributeValue {
-                    value: Some(oc::attribute_value::Value::StringValue(truncatable(
-                        v.clone(),
-                    ))),
-                },
-            );
+            attributes.insert(k.clone(), string_attribute(v.clone()));
         }
         for (k, v) in span.labels.drain() {
-            attributes.insert(
-                k.to_string(),
-                oc::AttributeValue {
-                    value: Some(oc::attribute_value::Value::StringValue(truncatable(v))),
-                },
-            );
+            attributes.insert(k.to_string(), string_attribute(v));
+        }
+        for (k, v) in span.target_labels.drain(..) {
+            attributes.insert(k, string_attribute(v));
         }
+        let time_events = span
+            .events
+            .drain(..)
+            .map(|event| oc::span::TimeEvent {
+                time: Some(event.time.into()),
+                value: Some(oc::span::time_event::Value::Annotation(
+                    oc::span::time_event::Annotation {
+                        description: Some(truncatable(event.name.to_string())),
+                        attributes: Some(oc::span::Attributes {
+                            attribute_map: event
+                                .labels
+                                .into_iter()
+                                .map(|(k, v)| (k.to_string(), string_attribute(v)))
+                                .collect(),
+                            dropped_attributes_count: 0,
+                        }),
+                    },
+                )),
+            })
+            .collect::<Vec<_>>();
         Ok(oc::Span {
             trace_id: into_bytes(span.trace_id, 16)?,
             span_id: into_bytes(span.span_id, 8)?,
@@ -95,7 +150,15 @@ impl SpanConverter {
                 dropped_attributes_count: 0,
             }),
             stack_trace: None,
-            time_events: None,
+            time_events: if time_events.is_empty() {
+                None
+            } else {
+                Some(oc::span::TimeEvents {
+                    time_event: time_events,
+                    dropped_annotations_count: 0,
+                    dropped_message_events_count: 0,
+                })
+            },
             links: None,
             status: None, // TODO: this is gRPC status; we must read response trailers to populate this
             resource: None,
@@ -117,6 +180,80 @@ impl trace_context::SpanSink for SpanConverter {
     }
 }
 
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Server => write!(f, "server"),
+            Kind::Client => write!(f, "client"),
+        }
+    }
+}
+
+// === impl NewLabelSpan ===
+
+impl<L, N> NewLabelSpan<L, N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(|inner| Self {
+            inner,
+            _labels: PhantomData,
+        })
+    }
+}
+
+impl<L, N: Clone> Clone for NewLabelSpan<L, N> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _labels: PhantomData,
+        }
+    }
+}
+
+impl<T, L, N> svc::NewService<T> for NewLabelSpan<L, N>
+where
+    T: Param<L>,
+    L: TraceLabels,
+    N: svc::NewService<T>,
+{
+    type Service = LabelSpan<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let labels = target.param().trace_labels();
+        LabelSpan {
+            labels: labels.into(),
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl LabelSpan ===
+
+impl<B, S> svc::Service<http::Request<B>> for LabelSpan<S>
+where
+    S: svc::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        // Only sampled requests have a span.
+        if let Some(span) = req.extensions().get::<SpanLabels>() {
+            span.extend(self.labels.iter().cloned());
+        }
+        // Only logged requests have a record.
+        if let Some(record) = req.extensions().get::<access_log::RouteLabels>() {
+            record.extend(self.labels.iter().cloned());
+        }
+        self.inner.call(req)
+    }
+}
+
 fn into_bytes(id: trace_context::Id, size: usize) -> Result<Vec<u8>, IdLengthError> {
     let bytes: Vec<u8> = id.into();
     if bytes.len() == size {
@@ -131,9 +268,37 @@ fn into_bytes(id: trace_context::Id, size: usize) -> Result<Vec<u8>, IdLengthErr
     }
 }
 
+fn string_attribute(value: String) -> oc::AttributeValue {
+    oc::AttributeValue {
+        value: Some(oc::attribute_value::Value::StringValue(truncatable(value))),
+    }
+}
+
 fn truncatable(value: String) -> oc::TruncatableString {
     oc::TruncatableString {
         value,
         truncated_byte_count: 0,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{AuthzLabels, ServerLabel};
+
+    #[test]
+    fn authz_trace_labels() {
+        let labels = AuthzLabels {
+            server: ServerLabel("web".to_string()),
+            authz: "a \"b\"".to_string(),
+            route: None,
+        };
+        assert_eq!(
+            labels.trace_labels(),
+            vec![
+                ("srv_name".to_string(), "web".to_string()),
+                ("saz_name".to_string(), "a \"b\"".to_string()),
+            ]
+        );
+    }
+}
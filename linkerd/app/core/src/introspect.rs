@@ -0,0 +1,215 @@
+//! Records the structure of the proxy's stacks as they are built, so that it
+//! may be inspected at runtime.
+
+use crate::{
+    cache,
+    metrics::StackLabels,
+    svc::{self, timeout::FailFastGauge, FailFast},
+};
+use parking_lot::Mutex;
+use std::{fmt::Write, hash::Hash, sync::Arc, time::Duration};
+
+/// A registry of the proxy's stacks.
+///
+/// Stack builders register each stack (named by its `StackLabels`) along with
+/// the stacks it wraps and handles to its caches and failfast layers.
+#[derive(Clone, Debug, Default)]
+pub struct Registry(Arc<Mutex<Vec<Entry>>>);
+
+/// A handle for describing a single registered stack.
+#[derive(Clone, Debug)]
+pub struct Node {
+    registry: Registry,
+    labels: StackLabels,
+}
+
+#[derive(Debug)]
+struct Entry {
+    labels: StackLabels,
+    inner: Vec<StackLabels>,
+    caches: Vec<cache::Size>,
+    failfasts: Vec<(&'static str, FailFastGauge)>,
+}
+
+// === impl Registry ===
+
+impl Registry {
+    /// Registers a stack.
+    ///
+    /// Stacks that are built multiple times (or by multiple builders) share a
+    /// single entry.
+    pub fn register(&self, labels: StackLabels) -> Node {
+        let mut entries = self.0.lock();
+        if !entries.iter().any(|e| e.labels == labels) {
+            entries.push(Entry {
+                labels: labels.clone(),
+                inner: Vec::new(),
+                caches: Vec::new(),
+                failfasts: Vec::new(),
+            });
+        }
+        Node {
+            registry: self.clone(),
+            labels,
+        }
+    }
+
+    /// Renders the registered stacks as JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        let entries = self.0.lock();
+        let stacks = entries
+            .iter()
+            .map(|e| {
+                let caches = e.caches.iter().map(cache::Size::get).sum::<usize>();
+                serde_json::json!({
+                    "direction": e.labels.direction.to_string(),
+                    "protocol": e.labels.protocol,
+                    "name": e.labels.name,
+                    "inner": e.inner.iter().map(label_json).collect::<Vec<_>>(),
+                    "cached_services": if e.caches.is_empty() { None } else { Some(caches) },
+                    "failfast": e.failfasts.iter().map(|(scope, gauge)| {
+                        serde_json::json!({ "scope": scope, "services_in_failfast": gauge.get() })
+                    }).collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({ "stacks": stacks })
+    }
+
+    /// Renders the registered stacks as a Graphviz DOT graph.
+    pub fn to_dot(&self) -> String {
+        let entries = self.0.lock();
+        let mut dot = String::from("digraph stacks {\n    node [shape=box];\n");
+        for e in entries.iter() {
+            let mut label = node_id(&e.labels);
+            if !e.caches.is_empty() {
+                let caches = e.caches.iter().map(cache::Size::get).sum::<usize>();
+                let _ = write!(label, "\\ncached_services={}", caches);
+            }
+            for (scope, gauge) in e.failfasts.iter() {
+                let _ = write!(label, "\\nfailfast[{}]={}", scope, gauge.get());
+            }
+            let _ = writeln!(dot, "    \"{}\" [label=\"{}\"];", node_id(&e.labels), label);
+            for inner in e.inner.iter() {
+                let _ = writeln!(
+                    dot,
+                    "    \"{}\" -> \"{}\";",
+                    node_id(&e.labels),
+                    node_id(inner)
+                );
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn update(&self, labels: &StackLabels, f: impl FnOnce(&mut Entry)) {
+        let mut entries = self.0.lock();
+        if let Some(e) = entries.iter_mut().find(|e| e.labels == *labels) {
+            f(e);
+        }
+    }
+}
+
+// === impl Node ===
+
+impl Node {
+    /// Records that this stack wraps `inner`.
+    pub fn push_inner(&self, inner: &Node) {
+        let inner = inner.labels.clone();
+        self.registry.update(&self.labels, |e| {
+            if !e.inner.contains(&inner) {
+                e.inner.push(inner);
+            }
+        });
+    }
+
+    /// Returns a cache layer whose size is reported for this stack.
+    pub fn cache_layer<T, N>(
+        &self,
+        idle: Duration,
+    ) -> impl svc::layer::Layer<N, Service = cache::Cache<T, N>> + Clone
+    where
+        T: Clone + Eq + std::fmt::Debug + Hash + Send + Sync + 'static,
+        N: svc::NewService<T> + 'static,
+        N::Service: Send + Sync + 'static,
+    {
+        let size = cache::Size::default();
+        self.registry
+            .update(&self.labels, |e| e.caches.push(size.clone()));
+        cache::Cache::layer_with_size(idle, size)
+    }
+
+    /// Returns a failfast layer whose state is reported for this stack.
+    pub fn failfast_layer<S>(
+        &self,
+        scope: &'static str,
+        max_unavailable: Duration,
+    ) -> impl svc::layer::Layer<S, Service = FailFast<S>> + Clone {
+        let gauge = FailFastGauge::default();
+        self.registry
+            .update(&self.labels, |e| e.failfasts.push((scope, gauge.clone())));
+        FailFast::layer_with_gauge(scope, max_unavailable, gauge)
+    }
+}
+
+fn node_id(labels: &StackLabels) -> String {
+    format!("{} {} {}", labels.direction, labels.protocol, labels.name)
+}
+
+fn label_json(labels: &StackLabels) -> serde_json::Value {
+    serde_json::json!({
+        "direction": labels.direction.to_string(),
+        "protocol": labels.protocol,
+        "name": labels.name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_registered_stacks() {
+        let registry = Registry::default();
+        let logical = registry.register(StackLabels::outbound("http", "logical"));
+        let balancer = registry.register(StackLabels::outbound("http", "balancer"));
+        logical.push_inner(&balancer);
+        let _ = logical.failfast_layer::<()>("HTTP Logical", Duration::from_secs(1));
+        // Registering a stack again does not duplicate it.
+        registry
+            .register(StackLabels::outbound("http", "logical"))
+            .push_inner(&balancer);
+
+        assert_eq!(
+            registry.to_json(),
+            serde_json::json!({
+                "stacks": [
+                    {
+                        "direction": "outbound",
+                        "protocol": "http",
+                        "name": "logical",
+                        "inner": [{ "direction": "outbound", "protocol": "http", "name": "balancer" }],
+                        "cached_services": null,
+                        "failfast": [{ "scope": "HTTP Logical", "services_in_failfast": 0 }],
+                    },
+                    {
+                        "direction": "outbound",
+                        "protocol": "http",
+                        "name": "balancer",
+                        "inner": [],
+                        "cached_services": null,
+                        "failfast": [],
+                    },
+                ]
+            })
+        );
+        assert_eq!(
+            registry.to_dot(),
+            "digraph stacks {\n    node [shape=box];\n    \
+            \"outbound http logical\" [label=\"outbound http logical\\nfailfast[HTTP Logical]=0\"];\n    \
+            \"outbound http logical\" -> \"outbound http balancer\";\n    \
+            \"outbound http balancer\" [label=\"outbound http balancer\"];\n}\n"
+        );
+    }
+}
@@ -32,17 +32,28 @@ pub use linkerd_transport_header as transport_header;
 
 use thiserror::Error;
 
+pub mod access_log;
 mod addr_match;
 pub mod classify;
 pub mod config;
 pub mod control;
+pub mod deadline;
 pub mod dns;
 pub mod dst;
 pub mod errors;
+pub mod ext_authz;
+pub mod fault;
+pub mod header_policy;
+pub mod hedge;
 pub mod http_tracing;
+pub mod introspect;
 pub mod metrics;
+pub mod peer_version;
 pub mod proxy;
 pub mod retry;
+pub mod rls;
+pub mod route_filter;
+pub mod route_policy;
 pub mod serve;
 pub mod svc;
 pub mod telemetry;
@@ -60,6 +71,11 @@ pub struct ProxyRuntime {
     pub metrics: metrics::Proxy,
     pub tap: proxy::tap::Registry,
     pub span_sink: http_tracing::OpenCensusSink,
+    pub trace_precedence: http_tracing::Precedence,
+    pub access_log: Option<access_log::AccessLog>,
+    pub rate_limit: Option<rls::Client>,
+    pub ext_authz: Option<ext_authz::Client>,
+    pub dns: dns::Resolver,
     pub drain: drain::Watch,
 }
 
@@ -1,6 +1,8 @@
 use crate::{
     classify::{Class, SuccessOrFailure},
-    control, dst, http_metrics, http_metrics as metrics, opencensus, profiles, stack_metrics,
+    control, dst, http_metrics, http_metrics as metrics,
+    http_tracing::TraceLabels,
+    introspect, opencensus, profiles, stack_metrics,
     svc::Param,
     telemetry, tls,
     transport::{
@@ -8,11 +10,13 @@ use crate::{
         labels::{TargetAddr, TlsAccept, TlsConnect},
     },
 };
-use linkerd_addr::Addr;
+use linkerd_addr::{Addr, NameAddr};
 pub use linkerd_metrics::*;
 use std::{
+    collections::BTreeMap,
     fmt::{self, Write},
     net::SocketAddr,
+    sync::Arc,
     time::{Duration, SystemTime},
 };
 
@@ -24,6 +28,8 @@ pub type HttpRoute = http_metrics::Requests<RouteLabels, Class>;
 
 pub type HttpRouteRetry = http_metrics::Retries<RouteLabels>;
 
+pub type HttpRouteGrpc = http_metrics::GrpcStreams<RouteLabels>;
+
 pub type Stack = stack_metrics::Registry<StackLabels>;
 
 #[derive(Clone, Debug)]
@@ -38,9 +44,12 @@ pub struct Proxy {
     pub http_route: HttpRoute,
     pub http_route_actual: HttpRoute,
     pub http_route_retry: HttpRouteRetry,
+    pub http_route_grpc: HttpRouteGrpc,
     pub http_endpoint: HttpEndpoint,
     pub transport: transport::Metrics,
+    pub accept: transport::accept::Metrics,
     pub stack: Stack,
+    pub introspect: introspect::Registry,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -61,6 +70,7 @@ pub struct InboundEndpointLabels {
     pub authority: Option<http::uri::Authority>,
     pub target_addr: SocketAddr,
     pub policy: AuthzLabels,
+    pub origin: transport::Origin,
 }
 
 /// A label referencing an inbound `Server` (i.e. for policy).
@@ -72,6 +82,9 @@ pub struct ServerLabel(pub String);
 pub struct AuthzLabels {
     pub server: ServerLabel,
     pub authz: String,
+
+    /// The name of the HTTP route that was matched, if the authorization is scoped to routes.
+    pub route: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -93,7 +106,8 @@ pub struct StackLabels {
 pub struct RouteLabels {
     direction: Direction,
     addr: profiles::LogicalAddr,
-    labels: Option<String>,
+    rewritten_from: Option<NameAddr>,
+    labels: Arc<BTreeMap<String, String>>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -150,6 +164,12 @@ impl Metrics {
             (m, r)
         };
 
+        let (http_route_grpc, grpc_report) = {
+            let m = metrics::GrpcStreams::<RouteLabels>::default();
+            let r = m.clone().into_report(retain_idle).with_prefix("route");
+            (m, r)
+        };
+
         let (http_route_actual, actual_report) = {
             let m = metrics::Requests::<RouteLabels, Class>::default();
             let r = m
@@ -162,14 +182,18 @@ impl Metrics {
         let stack = stack_metrics::Registry::default();
 
         let (transport, transport_report) = transport::Metrics::new(retain_idle);
+        let accept = transport::accept::Metrics::default();
 
         let proxy = Proxy {
             http_endpoint,
             http_route,
             http_route_retry,
+            http_route_grpc,
             http_route_actual,
             stack: stack.clone(),
             transport,
+            accept: accept.clone(),
+            introspect: introspect::Registry::default(),
         };
 
         let (opencensus, opencensus_report) = opencensus::metrics::new();
@@ -183,9 +207,11 @@ impl Metrics {
         let report = endpoint_report
             .and_then(route_report)
             .and_then(retry_report)
+            .and_then(grpc_report)
             .and_then(actual_report)
             .and_then(control_report)
             .and_then(transport_report)
+            .and_then(accept)
             .and_then(opencensus_report)
             .and_then(stack)
             .and_then(process)
@@ -222,7 +248,8 @@ impl Param<RouteLabels> for dst::Route {
         RouteLabels {
             addr: self.addr.clone(),
             direction: self.direction,
-            labels: prefix_labels("rt", self.route.labels().iter()),
+            rewritten_from: self.rewritten_from.clone(),
+            labels: self.route.labels().clone(),
         }
     }
 }
@@ -232,14 +259,34 @@ impl FmtLabels for RouteLabels {
         self.direction.fmt_labels(f)?;
         write!(f, ",dst=\"{}\"", self.addr)?;
 
-        if let Some(labels) = self.labels.as_ref() {
-            write!(f, ",{}", labels)?;
+        if let Some(from) = self.rewritten_from.as_ref() {
+            write!(f, ",rewritten_from=\"{}\"", from)?;
+        }
+
+        for (k, v) in self.labels.iter() {
+            write!(f, ",rt_{}=\"{}\"", k, v)?;
         }
 
         Ok(())
     }
 }
 
+impl TraceLabels for RouteLabels {
+    fn trace_labels(&self) -> Vec<(String, String)> {
+        let mut labels = vec![
+            ("direction".to_string(), self.direction.to_string()),
+            ("dst".to_string(), self.addr.to_string()),
+        ];
+        if let Some(from) = self.rewritten_from.as_ref() {
+            labels.push(("rewritten_from".to_string(), from.to_string()));
+        }
+        for (k, v) in self.labels.iter() {
+            labels.push((format!("rt_{}", k), v.clone()));
+        }
+        labels
+    }
+}
+
 // === impl EndpointLabels ===
 
 impl From<InboundEndpointLabels> for EndpointLabels {
@@ -271,8 +318,11 @@ impl FmtLabels for InboundEndpointLabels {
         }
 
         (
-            (TargetAddr(self.target_addr), TlsAccept::from(&self.tls)),
-            &self.policy,
+            (
+                (TargetAddr(self.target_addr), TlsAccept::from(&self.tls)),
+                &self.policy,
+            ),
+            self.origin,
         )
             .fmt_labels(f)?;
 
@@ -295,7 +345,24 @@ impl FmtLabels for ServerLabel {
 impl FmtLabels for AuthzLabels {
     fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.server.fmt_labels(f)?;
-        write!(f, ",saz_name=\"{}\"", self.authz)
+        write!(f, ",saz_name=\"{}\"", self.authz)?;
+        if let Some(route) = self.route.as_ref() {
+            write!(f, ",saz_route=\"{}\"", LabelValue(route))?;
+        }
+        Ok(())
+    }
+}
+
+impl TraceLabels for AuthzLabels {
+    fn trace_labels(&self) -> Vec<(String, String)> {
+        let mut labels = vec![
+            ("srv_name".to_string(), self.server.0.clone()),
+            ("saz_name".to_string(), self.authz.clone()),
+        ];
+        if let Some(route) = self.route.as_ref() {
+            labels.push(("saz_route".to_string(), route.clone()));
+        }
+        labels
     }
 }
 
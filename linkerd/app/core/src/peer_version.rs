@@ -0,0 +1,373 @@
+//! Exchanges proxy versions between meshed peers.
+//!
+//! Outbound proxies advertise their version to meshed endpoints via the
+//! `l5d-proxy-version` request header. Inbound proxies strip this header,
+//! expose the version to the rest of the stack as a [`PeerVersion`] request
+//! extension, and export a gauge of connections by peer version so that
+//! data plane version skew can be observed.
+
+use crate::{
+    identity,
+    metrics::{metrics, FmtLabels, FmtMetrics, Gauge},
+    proxy::http,
+    svc, telemetry, tls,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::{debug, trace};
+
+pub const L5D_PROXY_VERSION: &str = "l5d-proxy-version";
+
+/// The maximum number of distinct peer versions that are tracked. Connections
+/// from peers with additional versions are counted under [`OTHER_VERSION`].
+const MAX_VERSIONS: usize = 32;
+
+const OTHER_VERSION: &str = "other";
+
+metrics! {
+    inbound_peer_proxy_connections: Gauge {
+        "The number of open inbound connections from meshed peers, by the peer's proxy version"
+    }
+}
+
+/// The version advertised by a meshed peer proxy.
+///
+/// Inserted as a request extension on inbound requests so that features that
+/// depend on a peer's capabilities may be gated on its version.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PeerVersion(Arc<str>);
+
+/// Tracks the number of open connections by peer proxy version.
+#[derive(Clone, Debug, Default)]
+pub struct PeerVersions(Arc<Mutex<HashMap<PeerVersion, Arc<Gauge>>>>);
+
+#[derive(Clone, Debug)]
+pub struct NewAdvertiseVersion<N> {
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct AdvertiseVersion<S> {
+    inner: S,
+    value: Option<http::HeaderValue>,
+}
+
+#[derive(Clone, Debug)]
+pub struct NewRecordPeerVersion<N> {
+    inner: N,
+    versions: PeerVersions,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordPeerVersion<S> {
+    inner: S,
+    conn: Option<Arc<Connection>>,
+}
+
+/// Per-connection state, shared by all clones of a connection's service.
+#[derive(Debug)]
+struct Connection {
+    versions: PeerVersions,
+    recorded: Mutex<Option<(PeerVersion, Arc<Gauge>)>>,
+}
+
+// === impl PeerVersion ===
+
+impl PeerVersion {
+    /// Returns the version of the local proxy.
+    pub fn local() -> Self {
+        Self(telemetry::build_info::GIT_VERSION.into())
+    }
+
+    fn from_header(value: &http::HeaderValue) -> Option<Self> {
+        // Versions are used as metric labels, so only a conservative set of
+        // characters is accepted.
+        let v = value.to_str().ok()?.trim();
+        let valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+');
+        if v.is_empty() || !v.chars().all(valid) {
+            return None;
+        }
+        Some(Self(v.into()))
+    }
+}
+
+impl AsRef<str> for PeerVersion {
+    fn as_ref(&self) -> &str {
+        &*self.0
+    }
+}
+
+impl fmt::Display for PeerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FmtLabels for PeerVersion {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "peer_version=\"{}\"", self.0)
+    }
+}
+
+// === impl PeerVersions ===
+
+impl PeerVersions {
+    pub fn to_layer<N>(
+        &self,
+    ) -> impl svc::layer::Layer<N, Service = NewRecordPeerVersion<N>> + Clone {
+        let versions = self.clone();
+        svc::layer::mk(move |inner| NewRecordPeerVersion {
+            inner,
+            versions: versions.clone(),
+        })
+    }
+
+    fn connection_opened(&self, version: &PeerVersion) -> Arc<Gauge> {
+        let mut versions = self.0.lock();
+        if !versions.contains_key(version) && versions.len() >= MAX_VERSIONS {
+            // Forget versions without any open connections before bucketing
+            // the new version, so that the map doesn't grow without bound.
+            versions.retain(|_, gauge| gauge.value() > 0);
+        }
+        let key = if versions.contains_key(version) || versions.len() < MAX_VERSIONS {
+            version.clone()
+        } else {
+            PeerVersion(OTHER_VERSION.into())
+        };
+        let gauge = versions.entry(key).or_default().clone();
+        gauge.incr();
+        gauge
+    }
+}
+
+impl FmtMetrics for PeerVersions {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let versions = self.0.lock();
+        if versions.is_empty() {
+            return Ok(());
+        }
+
+        inbound_peer_proxy_connections.fmt_help(f)?;
+        for (version, gauge) in versions.iter() {
+            inbound_peer_proxy_connections.fmt_metric_labeled(f, &**gauge, version)?;
+        }
+
+        Ok(())
+    }
+}
+
+// === impl NewAdvertiseVersion ===
+
+impl<N> NewAdvertiseVersion<N> {
+    pub fn layer() -> impl svc::layer::Layer<N, Service = Self> + Clone + Copy {
+        svc::layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewAdvertiseVersion<N>
+where
+    T: svc::Param<tls::ConditionalClientTls>,
+    N: svc::NewService<T>,
+{
+    type Service = AdvertiseVersion<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        // Only meshed endpoints are informed of the local proxy's version.
+        let value = match svc::Param::<tls::ConditionalClientTls>::param(&target) {
+            tls::ConditionalClientTls::Some(_) => {
+                http::HeaderValue::from_str(PeerVersion::local().as_ref()).ok()
+            }
+            tls::ConditionalClientTls::None(_) => None,
+        };
+        AdvertiseVersion {
+            value,
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+impl<S, B> svc::Service<http::Request<B>> for AdvertiseVersion<S>
+where
+    S: svc::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if let Some(value) = self.value.clone() {
+            req.headers_mut().insert(L5D_PROXY_VERSION, value);
+        } else {
+            req.headers_mut().remove(L5D_PROXY_VERSION);
+        }
+        self.inner.call(req)
+    }
+}
+
+// === impl NewRecordPeerVersion ===
+
+impl<T, N> svc::NewService<T> for NewRecordPeerVersion<N>
+where
+    T: svc::Param<Option<identity::Name>>,
+    N: svc::NewService<T>,
+{
+    type Service = RecordPeerVersion<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        // Versions are only trusted when the client has been authenticated as
+        // a meshed peer.
+        let conn = svc::Param::<Option<identity::Name>>::param(&target).map(|_| {
+            Arc::new(Connection {
+                versions: self.versions.clone(),
+                recorded: Mutex::new(None),
+            })
+        });
+        RecordPeerVersion {
+            conn,
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+impl<S, B> svc::Service<http::Request<B>> for RecordPeerVersion<S>
+where
+    S: svc::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        let header = req.headers_mut().remove(L5D_PROXY_VERSION);
+        match (self.conn.as_ref(), header) {
+            (Some(conn), Some(value)) => {
+                if let Some(version) = PeerVersion::from_header(&value) {
+                    conn.record(&version);
+                    req.extensions_mut().insert(version);
+                }
+            }
+            (None, Some(value)) => {
+                debug!(header = %L5D_PROXY_VERSION, ?value, "Stripped header from unauthenticated client");
+            }
+            (_, None) => {}
+        }
+        self.inner.call(req)
+    }
+}
+
+// === impl Connection ===
+
+impl Connection {
+    fn record(&self, version: &PeerVersion) {
+        let mut recorded = self.recorded.lock();
+        match *recorded {
+            Some((ref prior, _)) if prior == version => {}
+            _ => {
+                trace!(%version, "Peer proxy version");
+                let gauge = self.versions.connection_opened(version);
+                // Replacing a prior version releases its gauge.
+                if let Some((_, prior)) = recorded.replace((version.clone(), gauge)) {
+                    prior.decr();
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some((_, gauge)) = self.recorded.get_mut().take() {
+            gauge.decr();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conn(versions: &PeerVersions) -> Connection {
+        Connection {
+            versions: versions.clone(),
+            recorded: Mutex::new(None),
+        }
+    }
+
+    fn gauge(versions: &PeerVersions, v: &str) -> u64 {
+        versions
+            .0
+            .lock()
+            .get(&PeerVersion(v.into()))
+            .map(|g| g.value())
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn counts_connections_by_version() {
+        let versions = PeerVersions::default();
+        let v1 = PeerVersion("v2.150.0".into());
+        let v2 = PeerVersion("v2.151.0".into());
+
+        let c0 = conn(&versions);
+        c0.record(&v1);
+        c0.record(&v1);
+        let c1 = conn(&versions);
+        c1.record(&v1);
+        assert_eq!(gauge(&versions, "v2.150.0"), 2);
+
+        c1.record(&v2);
+        assert_eq!(gauge(&versions, "v2.150.0"), 1);
+        assert_eq!(gauge(&versions, "v2.151.0"), 1);
+
+        drop((c0, c1));
+        assert_eq!(gauge(&versions, "v2.150.0"), 0);
+        assert_eq!(gauge(&versions, "v2.151.0"), 0);
+    }
+
+    #[test]
+    fn buckets_versions_over_limit() {
+        let versions = PeerVersions::default();
+        let conns = (0..MAX_VERSIONS + 2)
+            .map(|i| {
+                let c = conn(&versions);
+                c.record(&PeerVersion(format!("v{}", i).into()));
+                c
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(versions.0.lock().len(), MAX_VERSIONS + 1);
+        assert_eq!(gauge(&versions, OTHER_VERSION), 2);
+
+        // Once connections are closed, their versions are forgotten to make
+        // room for new versions.
+        drop(conns);
+        let c = conn(&versions);
+        c.record(&PeerVersion("v2.151.0".into()));
+        assert_eq!(gauge(&versions, "v2.151.0"), 1);
+        assert_eq!(versions.0.lock().len(), 1);
+    }
+
+    #[test]
+    fn parses_header() {
+        let parse = |v: &'static str| {
+            PeerVersion::from_header(&http::HeaderValue::from_static(v)).map(|v| v.to_string())
+        };
+        assert_eq!(parse(" v2.150.0 "), Some("v2.150.0".to_string()));
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("v2\"}"), None);
+    }
+}
@@ -9,3 +9,4 @@ pub use linkerd_proxy_identity as identity;
 pub use linkerd_proxy_resolve as resolve;
 pub use linkerd_proxy_tap as tap;
 pub use linkerd_proxy_tcp as tcp;
+pub use linkerd_proxy_xds_resolve as xds_resolve;
@@ -1,43 +1,269 @@
 use super::classify;
 use super::dst::Route;
+use super::errors;
 use super::http_metrics::retries::Handle;
-use super::metrics::HttpRouteRetry;
-use crate::profiles;
+use super::metrics::{metrics, FmtMetrics, Gauge, HttpRouteRetry, RouteLabels};
+use crate::{profiles, NameAddr};
 use futures::future;
 use linkerd_error::Error;
 use linkerd_http_classify::{Classify, ClassifyEos, ClassifyResponse};
 use linkerd_http_retry::ReplayBody;
 use linkerd_proxy_http::ClientHandle;
 use linkerd_retry as retry;
-use linkerd_stack::{layer, Either, Param};
-use std::sync::Arc;
+use linkerd_stack::{layer, Either, NewService, Param};
+use linkerd_timeout::{ResponseTimeout, Timeout};
+use linkerd_trace_context::SpanEvents;
+use parking_lot::Mutex;
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+use tokio::time::{self, Instant};
+
+metrics! {
+    route_retry_suppressed: Gauge {
+        "The number of routes whose retries are suppressed because their retry budgets have been continuously exhausted"
+    }
+}
 
 pub fn layer<N>(
-    metrics: HttpRouteRetry,
+    policy: NewRetryPolicy,
 ) -> impl layer::Layer<N, Service = retry::NewRetry<NewRetryPolicy, N>> + Clone {
-    retry::NewRetry::<_, N>::layer(NewRetryPolicy::new(metrics))
+    retry::NewRetry::<_, N>::layer(policy)
+}
+
+/// Gives each retryable route of the configured services its own retry
+/// budget, in place of the budget shared by all of the service's routes.
+///
+/// This layer must be pushed above the retry and hedge layers, so that they
+/// withdraw from the route's budget.
+pub fn route_budget_layer<N>(
+    budgets: RouteBudgets,
+) -> impl layer::Layer<N, Service = NewRouteBudget<N>> + Clone {
+    layer::mk(move |inner| NewRouteBudget {
+        budgets: budgets.clone(),
+        inner,
+    })
+}
+
+/// Applies per-try timeouts to the attempts of retryable routes.
+///
+/// This layer must be pushed beneath the retry layer (and above the route's
+/// request timeout), so that an attempt that times out may be retried within
+/// the route's overall deadline.
+pub fn per_try_timeout_layer<N>(
+    timeouts: PerTryTimeouts,
+) -> impl layer::Layer<N, Service = NewPerTryTimeout<N>> + Clone {
+    layer::mk(move |inner| NewPerTryTimeout {
+        timeouts: timeouts.clone(),
+        inner,
+    })
 }
 
 #[derive(Clone, Debug)]
 pub struct NewRetryPolicy {
     metrics: HttpRouteRetry,
+    suppression: Option<(Suppressions, Duration)>,
+    max_buffered_bytes: usize,
+    retry_unprocessed: bool,
+    backoffs: Backoffs,
+    per_try_timeouts: PerTryTimeouts,
+    max_retry_after: Duration,
 }
 
 #[derive(Clone, Debug)]
 pub struct RetryPolicy {
+    /// The number of the attempt whose result is being evaluated, starting at 1.
+    attempt: usize,
     metrics: Handle,
     budget: Arc<retry::Budget>,
     response_classes: profiles::http::ResponseClasses,
+    suppression: Option<Suppression>,
+    max_buffered_bytes: usize,
+    /// Whether failed responses are retried, i.e. whether the route is
+    /// retryable.
+    retry_responses: bool,
+    /// Whether requests that the server provably did not process are retried.
+    retry_unprocessed: bool,
+    backoff: Option<Backoff>,
+    /// The route's request timeout, which bounds the time a retry may be
+    /// delayed by a `Retry-After` hint.
+    timeout: Option<Duration>,
+    /// The longest time a retry is delayed by a `Retry-After` hint.
+    max_retry_after: Duration,
+    /// Whether attempts that time out are retried.
+    retry_timeouts: bool,
+}
+
+/// Maps logical service addresses to a retry setting of their routes.
+#[derive(Clone, Debug)]
+pub struct ByService<T>(Arc<HashMap<NameAddr, T>>);
+
+/// Maps logical service addresses to the backoffs applied to their routes'
+/// retries.
+pub type Backoffs = ByService<Backoff>;
+
+/// A jittered exponential backoff between retries.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Backoff {
+    /// The delay before the first retry.
+    pub base: Duration,
+
+    /// The maximum delay before a retry.
+    pub max: Duration,
+
+    /// The factor by which the delay grows with each retry.
+    pub multiplier: f64,
+
+    /// The fraction of each delay, between 0 and 1, that may be randomly
+    /// subtracted from it, so that clients' retries are spread out.
+    pub jitter: f64,
+}
+
+/// Maps logical service addresses to the budgets given to each of their
+/// retryable routes.
+pub type RouteBudgets = ByService<BudgetConfig>;
+
+/// Configures a retry budget.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BudgetConfig {
+    /// The time for which deposits into the budget are retained.
+    pub ttl: Duration,
+
+    /// The number of retries permitted per second, regardless of how many
+    /// requests have been deposited.
+    pub min_per_second: u32,
+
+    /// The ratio of retries to deposited requests that is permitted.
+    pub ratio: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct NewRouteBudget<N> {
+    budgets: RouteBudgets,
+    inner: N,
+}
+
+/// Maps logical service addresses to the timeouts applied to each attempt of
+/// their retryable routes' requests.
+pub type PerTryTimeouts = ByService<Duration>;
+
+#[derive(Clone, Debug)]
+pub struct NewPerTryTimeout<N> {
+    timeouts: PerTryTimeouts,
+    inner: N,
 }
 
-/// Allow buffering requests up to 64 kb
-const MAX_BUFFERED_BYTES: usize = 64 * 1024;
+/// Tracks the routes whose retries have been suppressed.
+///
+/// When a route's retry budget is exhausted continuously, retries are likely
+/// amplifying an outage rather than masking transient failures.
+#[derive(Clone, Debug, Default)]
+pub struct Suppressions(Arc<Mutex<HashMap<RouteLabels, Weak<Mutex<SuppressionState>>>>>);
+
+/// Suppresses retries on a single route.
+#[derive(Clone, Debug)]
+struct Suppression {
+    after: Duration,
+    route: RouteLabels,
+    state: Arc<Mutex<SuppressionState>>,
+}
+
+#[derive(Debug, Default)]
+struct SuppressionState {
+    /// When the budget was first observed to be exhausted, if it has been
+    /// exhausted since retries last succeeded in withdrawing from it.
+    exhausted_since: Option<Instant>,
+    /// When the budget was most recently observed to be exhausted.
+    last_exhausted: Option<Instant>,
+    suppressed: bool,
+}
+
+/// By default, allow buffering requests up to 64 kb
+pub const DEFAULT_MAX_BUFFERED_BYTES: usize = 64 * 1024;
+
+/// By default, `Retry-After` hints delay retries by at most 10 seconds.
+pub const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+/// Routes that are not retryable are only retried when requests could not have
+/// been processed. Such routes have no configured budget, so they use a
+/// budget that permits 10 retries per second plus 20% of requests.
+const UNPROCESSED_BUDGET_TTL: Duration = Duration::from_secs(10);
+const UNPROCESSED_BUDGET_MIN_PER_SECOND: u32 = 10;
+const UNPROCESSED_BUDGET_RATIO: f32 = 0.2;
 
 // === impl NewRetryPolicy ===
 
 impl NewRetryPolicy {
     pub fn new(metrics: HttpRouteRetry) -> Self {
-        Self { metrics }
+        Self {
+            metrics,
+            suppression: None,
+            max_buffered_bytes: DEFAULT_MAX_BUFFERED_BYTES,
+            retry_unprocessed: false,
+            backoffs: Backoffs::default(),
+            per_try_timeouts: PerTryTimeouts::default(),
+            max_retry_after: DEFAULT_MAX_RETRY_AFTER,
+        }
+    }
+
+    /// Bounds the time a retry is delayed by a response's `Retry-After` hint,
+    /// so that routes without a request timeout are not held indefinitely.
+    pub fn with_max_retry_after(self, max_retry_after: Duration) -> Self {
+        Self {
+            max_retry_after,
+            ..self
+        }
+    }
+
+    /// Retries attempts that exceed their routes' per-try timeouts. The
+    /// timeouts themselves are applied by [`per_try_timeout_layer`].
+    pub fn with_per_try_timeouts(self, per_try_timeouts: PerTryTimeouts) -> Self {
+        Self {
+            per_try_timeouts,
+            ..self
+        }
+    }
+
+    /// Delays retries on the routes of the given services.
+    pub fn with_backoffs(self, backoffs: Backoffs) -> Self {
+        Self { backoffs, ..self }
+    }
+
+    /// Retries requests that failed before the server could have processed
+    /// them--e.g. due to connection failures or refused HTTP/2 streams--on all
+    /// routes, regardless of whether the route is retryable or the request is
+    /// idempotent.
+    pub fn with_unprocessed_retries(self) -> Self {
+        Self {
+            retry_unprocessed: true,
+            ..self
+        }
+    }
+
+    /// Limits the size of request bodies that are buffered so that requests
+    /// may be retried. Requests with larger bodies, or whose bodies are of
+    /// unknown length, are not retried. If zero, requests with bodies are never
+    /// retried.
+    pub fn with_max_buffered_bytes(self, max_buffered_bytes: usize) -> Self {
+        Self {
+            max_buffered_bytes,
+            ..self
+        }
+    }
+
+    /// Suppresses retries on routes whose retry budgets have been exhausted
+    /// continuously for `after`. Retries are re-enabled once the budget has
+    /// not been exhausted for the same duration.
+    pub fn with_suppression(self, suppressions: Suppressions, after: Duration) -> Self {
+        Self {
+            suppression: Some((suppressions, after)),
+            ..self
+        }
     }
 }
 
@@ -45,13 +271,38 @@ impl retry::NewPolicy<Route> for NewRetryPolicy {
     type Policy = RetryPolicy;
 
     fn new_policy(&self, route: &Route) -> Option<Self::Policy> {
-        let retries = route.route.retries().cloned()?;
+        let (budget, retry_responses) = match route.route.retries() {
+            Some(retries) => (retries.budget().clone(), true),
+            None if self.retry_unprocessed => {
+                let budget = retry::Budget::new(
+                    UNPROCESSED_BUDGET_TTL,
+                    UNPROCESSED_BUDGET_MIN_PER_SECOND,
+                    UNPROCESSED_BUDGET_RATIO,
+                );
+                (Arc::new(budget), false)
+            }
+            None => return None,
+        };
 
-        let metrics = self.metrics.get_handle(route.param());
+        let labels: RouteLabels = route.param();
+        let suppression = self
+            .suppression
+            .as_ref()
+            .map(|(suppressions, after)| suppressions.get(labels.clone(), *after));
+        let metrics = self.metrics.get_handle(labels);
         Some(RetryPolicy {
+            attempt: 1,
             metrics,
-            budget: retries.budget().clone(),
+            budget,
             response_classes: route.route.response_classes().clone(),
+            suppression,
+            max_buffered_bytes: self.max_buffered_bytes,
+            retry_responses,
+            retry_unprocessed: self.retry_unprocessed,
+            backoff: self.backoffs.get(&route.addr),
+            timeout: route.route.timeout(),
+            max_retry_after: self.max_retry_after,
+            retry_timeouts: retry_responses && self.per_try_timeouts.get(&route.addr).is_some(),
         })
     }
 }
@@ -69,9 +320,9 @@ impl RetryPolicy {
         // Requests without bodies can always be retried, as we will not need to
         // buffer the body. If the request *does* have a body, retry it if and
         // only if the request contains a `content-length` header and the
-        // content length is >= 64 kb.
+        // content length is within the buffering limit.
         let has_body = !req.body().is_end_stream();
-        if has_body && content_length(req).unwrap_or(usize::MAX) > MAX_BUFFERED_BYTES {
+        if has_body && content_length(req).unwrap_or(usize::MAX) > self.max_buffered_bytes {
             tracing::trace!(
                 req.has_body = has_body,
                 req.content_length = ?content_length(req),
@@ -89,38 +340,151 @@ impl RetryPolicy {
     }
 }
 
-impl<A, B, E> retry::Policy<http::Request<A>, http::Response<B>, E> for RetryPolicy
+/// Returns the delay requested by a response's `Retry-After` header.
+///
+/// Only delays expressed in seconds are honored; HTTP-date values are ignored.
+fn retry_after<B>(rsp: &http::Response<B>) -> Option<Duration> {
+    let secs = rsp
+        .headers()
+        .get(http::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    if secs == 0 {
+        return None;
+    }
+    Some(Duration::from_secs(secs))
+}
+
+impl<A, B> retry::Policy<http::Request<A>, http::Response<B>, Error> for RetryPolicy
 where
     A: http_body::Body + Clone,
 {
-    type Future = future::Ready<Self>;
+    type Future = future::Either<
+        future::Ready<Self>,
+        Pin<Box<dyn std::future::Future<Output = Self> + Send + 'static>>,
+    >;
 
     fn retry(
         &self,
         req: &http::Request<A>,
-        result: Result<&http::Response<B>, &E>,
+        result: Result<&http::Response<B>, &Error>,
     ) -> Option<Self::Future> {
-        let retryable = match result {
-            Err(_) => false,
-            Ok(rsp) => classify::Request::from(self.response_classes.clone())
-                .classify(req)
-                .start(rsp)
-                .eos(None)
-                .is_failure(),
+        let unprocessed = self.retry_unprocessed
+            && match result {
+                Err(error) => errors::is_unprocessed(&**error),
+                Ok(rsp) => rsp.extensions().get::<errors::Unprocessed>().is_some(),
+            };
+        let (retryable, hint) = match result {
+            // Requests that are still in flight when the route's request
+            // timeout elapses are canceled, so timeouts that reach the policy
+            // must have been per-try timeouts.
+            Err(error) => (
+                unprocessed
+                    || (self.retry_timeouts
+                        && linkerd_error::is_error::<ResponseTimeout>(&**error)),
+                None,
+            ),
+            Ok(rsp) => {
+                let failed = self.retry_responses
+                    && classify::Request::from(self.response_classes.clone())
+                        .classify(req)
+                        .start(rsp)
+                        .eos(None)
+                        .is_failure();
+                (unprocessed || failed, retry_after(rsp))
+            }
         };
 
         if !retryable {
             self.budget.deposit();
+            self.metrics.incr_deposited();
             return None;
         }
 
+        // If the server asks that the request be retried later than the route
+        // permits, the retry could not complete before the route times out.
+        if let (Some(hint), Some(timeout)) = (hint, self.timeout) {
+            if hint >= timeout {
+                tracing::debug!(?hint, ?timeout, "Retry-After exceeds the route timeout");
+                return None;
+            }
+        }
+        let hint = hint.map(|hint| hint.min(self.max_retry_after));
+
         let withdrew = self.budget.withdraw().is_ok();
-        self.metrics.incr_retryable(withdrew);
-        if !withdrew {
+        if withdrew {
+            self.metrics.incr_withdrawn();
+        } else {
+            self.metrics.incr_exhausted();
+        }
+        let permitted = match self.suppression.as_ref() {
+            Some(suppression) => suppression.permits(withdrew),
+            None => withdrew,
+        };
+        if unprocessed {
+            self.metrics.incr_unprocessed(permitted);
+        } else {
+            self.metrics.incr_retryable(permitted);
+        }
+
+        // Annotate the request's span so that traces explain why the request
+        // was (or was not) retried.
+        let budget = match (withdrew, permitted) {
+            (false, _) => "exhausted",
+            (true, false) => "suppressed",
+            (true, true) => "available",
+        };
+        // Retries wait for the longer of the server's hint and the route's
+        // backoff.
+        let backoff = self
+            .backoff
+            .map(|backoff| backoff.delay(self.attempt, &mut rand::thread_rng()));
+        let delay = match (hint, backoff) {
+            (Some(hint), Some(backoff)) => Some(hint.max(backoff)),
+            (hint, backoff) => hint.or(backoff),
+        };
+
+        let attempt = self.attempt + 1;
+        SpanEvents::record_on(
+            req,
+            if permitted { "retry" } else { "retry skipped" },
+            || {
+                let mut labels = HashMap::with_capacity(4);
+                labels.insert("retry.attempt", attempt.to_string());
+                labels.insert("retry.budget", budget.to_string());
+                if unprocessed {
+                    labels.insert("retry.reason", "unprocessed".to_string());
+                }
+                if let Some(delay) = delay {
+                    labels.insert("retry.delay", format!("{:?}", delay));
+                }
+                labels
+            },
+        );
+        if !permitted {
             return None;
         }
 
-        Some(future::ready(self.clone()))
+        let policy = Self {
+            attempt,
+            ..self.clone()
+        };
+        if hint.is_some() {
+            self.metrics.incr_delayed();
+        }
+        match delay {
+            None => Some(future::Either::Left(future::ready(policy))),
+            Some(delay) => {
+                tracing::debug!(?delay, ?hint, "Delaying retry");
+                Some(future::Either::Right(Box::pin(async move {
+                    time::sleep(delay).await;
+                    policy
+                })))
+            }
+        }
     }
 
     fn clone_request(&self, req: &http::Request<A>) -> Option<http::Request<A>> {
@@ -133,23 +497,33 @@ where
             return None;
         }
 
-        let mut clone = http::Request::new(req.body().clone());
-        *clone.method_mut() = req.method().clone();
-        *clone.uri_mut() = req.uri().clone();
-        *clone.headers_mut() = req.headers().clone();
-        *clone.version_mut() = req.version();
+        Some(clone_request(req))
+    }
+}
+
+/// Clones a request whose body can be replayed, so that it may be sent again.
+pub(crate) fn clone_request<A: Clone>(req: &http::Request<A>) -> http::Request<A> {
+    let mut clone = http::Request::new(req.body().clone());
+    *clone.method_mut() = req.method().clone();
+    *clone.uri_mut() = req.uri().clone();
+    *clone.headers_mut() = req.headers().clone();
+    *clone.version_mut() = req.version();
 
-        // The HTTP server sets a ClientHandle with the client's address and a means to close the
-        // server-side connection.
-        if let Some(client_handle) = req.extensions().get::<ClientHandle>().cloned() {
-            clone.extensions_mut().insert(client_handle);
-        }
+    // The HTTP server sets a ClientHandle with the client's address and a means to close the
+    // server-side connection.
+    if let Some(client_handle) = req.extensions().get::<ClientHandle>().cloned() {
+        clone.extensions_mut().insert(client_handle);
+    }
 
-        Some(clone)
+    // Retries are recorded on the original request's span.
+    if let Some(events) = req.extensions().get::<SpanEvents>().cloned() {
+        clone.extensions_mut().insert(events);
     }
+
+    clone
 }
 
-impl<A, B, E> retry::PrepareRequest<http::Request<A>, http::Response<B>, E> for RetryPolicy
+impl<A, B> retry::PrepareRequest<http::Request<A>, http::Response<B>, Error> for RetryPolicy
 where
     A: http_body::Body + Unpin,
     A::Error: Into<Error>,
@@ -161,8 +535,510 @@ where
         req: http::Request<A>,
     ) -> Either<Self::RetryRequest, http::Request<A>> {
         if self.can_retry(&req) {
-            return Either::A(req.map(|body| ReplayBody::new(body, MAX_BUFFERED_BYTES)));
+            let max = self.max_buffered_bytes;
+            return Either::A(req.map(|body| ReplayBody::new(body, max)));
         }
+        self.metrics.incr_body_too_large();
         Either::B(req)
     }
 }
+
+// === impl ByService ===
+
+impl<T: Copy> ByService<T> {
+    pub fn new(settings: impl IntoIterator<Item = (NameAddr, T)>) -> Self {
+        Self(Arc::new(settings.into_iter().collect()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, profiles::LogicalAddr(addr): &profiles::LogicalAddr) -> Option<T> {
+        self.0.get(addr).copied()
+    }
+}
+
+impl<T> Default for ByService<T> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+// === impl NewRouteBudget ===
+
+impl<N> NewService<Route> for NewRouteBudget<N>
+where
+    N: NewService<Route>,
+{
+    type Service = N::Service;
+
+    fn new_service(&mut self, mut route: Route) -> Self::Service {
+        if route.route.retries().is_some() {
+            if let Some(BudgetConfig {
+                ttl,
+                min_per_second,
+                ratio,
+            }) = self.budgets.get(&route.addr)
+            {
+                let budget = retry::Budget::new(ttl, min_per_second, ratio);
+                route.route.set_retries(Arc::new(budget));
+            }
+        }
+        self.inner.new_service(route)
+    }
+}
+
+// === impl NewPerTryTimeout ===
+
+impl<N> NewService<Route> for NewPerTryTimeout<N>
+where
+    N: NewService<Route>,
+{
+    type Service = Timeout<N::Service>;
+
+    fn new_service(&mut self, route: Route) -> Self::Service {
+        // Only retryable routes' attempts are timed out; on other routes, a
+        // per-try timeout would merely shorten the request timeout.
+        let timeout = route
+            .route
+            .retries()
+            .and_then(|_| self.timeouts.get(&route.addr));
+        let inner = self.inner.new_service(route);
+        match timeout {
+            Some(timeout) => Timeout::new(inner, timeout),
+            None => Timeout::passthru(inner),
+        }
+    }
+}
+
+// === impl Backoff ===
+
+impl Backoff {
+    /// Returns the delay before the given retry, starting at 1.
+    fn delay<R: Rng>(&self, retry: usize, rng: &mut R) -> Duration {
+        let exp = retry.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let secs =
+            (self.base.as_secs_f64() * self.multiplier.powi(exp)).min(self.max.as_secs_f64());
+        let jitter = if self.jitter > 0.0 {
+            rng.gen::<f64>() * self.jitter
+        } else {
+            0.0
+        };
+        Duration::from_secs_f64(secs * (1.0 - jitter))
+    }
+}
+
+// === impl Suppressions ===
+
+impl Suppressions {
+    fn get(&self, route: RouteLabels, after: Duration) -> Suppression {
+        let mut routes = self.0.lock();
+        if let Some(state) = routes.get(&route).and_then(Weak::upgrade) {
+            return Suppression {
+                after,
+                route,
+                state,
+            };
+        }
+
+        // Drop the states of routes that are no longer in use.
+        routes.retain(|_, state| state.strong_count() > 0);
+        let state = Arc::new(Mutex::new(SuppressionState::default()));
+        routes.insert(route.clone(), Arc::downgrade(&state));
+        Suppression {
+            after,
+            route,
+            state,
+        }
+    }
+}
+
+impl FmtMetrics for Suppressions {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let routes = self.0.lock();
+        if routes.is_empty() {
+            return Ok(());
+        }
+
+        let suppressed = routes
+            .values()
+            .filter_map(Weak::upgrade)
+            .filter(|state| state.lock().suppressed)
+            .count();
+        route_retry_suppressed.fmt_help(f)?;
+        route_retry_suppressed.fmt_metric(f, &Gauge::from(suppressed as u64))
+    }
+}
+
+// === impl Suppression ===
+
+impl Suppression {
+    /// Records whether a retry could withdraw from the route's budget,
+    /// returning whether the retry may proceed.
+    fn permits(&self, withdrew: bool) -> bool {
+        let now = Instant::now();
+        let mut state = self.state.lock();
+
+        if !withdrew {
+            let since = *state.exhausted_since.get_or_insert(now);
+            state.last_exhausted = Some(now);
+            if !state.suppressed && now.saturating_duration_since(since) >= self.after {
+                state.suppressed = true;
+                tracing::warn!(
+                    route = ?self.route,
+                    exhausted_for = ?now.saturating_duration_since(since),
+                    "Retry budget exhausted; suppressing retries",
+                );
+            }
+            return false;
+        }
+
+        state.exhausted_since = None;
+        if state.suppressed {
+            let recovered = state
+                .last_exhausted
+                .map(|t| now.saturating_duration_since(t) >= self.after)
+                .unwrap_or(true);
+            if !recovered {
+                return false;
+            }
+            state.suppressed = false;
+            tracing::info!(route = ?self.route, "Retry budget recovered; re-enabling retries");
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Direction;
+
+    fn route() -> RouteLabels {
+        let route = crate::dst::Route {
+            addr: profiles::LogicalAddr("web.ns.svc.cluster.local:80".parse().unwrap()),
+            route: Default::default(),
+            direction: Direction::Out,
+            rewritten_from: None,
+        };
+        route.param()
+    }
+
+    /// A policy for a retryable route, to be customized by each test.
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            attempt: 1,
+            metrics: HttpRouteRetry::default().get_handle(route()),
+            budget: Arc::new(retry::Budget::new(Duration::from_secs(10), 10, 0.2)),
+            response_classes: Default::default(),
+            suppression: None,
+            max_buffered_bytes: 8,
+            retry_responses: true,
+            retry_unprocessed: false,
+            backoff: None,
+            timeout: None,
+            max_retry_after: DEFAULT_MAX_RETRY_AFTER,
+            retry_timeouts: false,
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn suppresses_while_exhausted() {
+        let after = Duration::from_secs(10);
+        let suppressions = Suppressions::default();
+        let suppression = suppressions.get(route(), after);
+
+        // Exhaustion is tolerated until it has persisted for the configured duration.
+        assert!(suppression.permits(true));
+        assert!(!suppression.permits(false));
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert!(suppression.permits(true));
+        assert!(!suppression.permits(false));
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert!(!suppression.permits(false));
+        assert!(!suppression.state.lock().suppressed);
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(!suppression.permits(false));
+        assert!(suppression.state.lock().suppressed);
+
+        // Retries remain suppressed until the budget has recovered for the
+        // configured duration.
+        assert!(!suppression.permits(true));
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(suppression.permits(true));
+        assert!(!suppression.state.lock().suppressed);
+    }
+
+    #[test]
+    fn limits_buffered_bodies() {
+        let policy = policy();
+        let req = |body: &'static str| {
+            http::Request::builder()
+                .header(http::header::CONTENT_LENGTH, body.len())
+                .body(hyper::Body::from(body))
+                .unwrap()
+        };
+
+        assert!(policy.can_retry(&http::Request::new(hyper::Body::empty())));
+        assert!(policy.can_retry(&req("12345678")));
+        assert!(!policy.can_retry(&req("123456789")));
+
+        let policy = RetryPolicy {
+            max_buffered_bytes: 0,
+            ..policy
+        };
+        assert!(policy.can_retry(&http::Request::new(hyper::Body::empty())));
+        assert!(!policy.can_retry(&req("1")));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn honors_retry_after() {
+        let policy = RetryPolicy {
+            timeout: Some(Duration::from_secs(5)),
+            max_retry_after: Duration::from_secs(3),
+            ..policy()
+        };
+        let req = http::Request::new(String::new());
+        let rsp = |retry_after: &str| {
+            http::Response::builder()
+                .status(http::StatusCode::SERVICE_UNAVAILABLE)
+                .header(http::header::RETRY_AFTER, retry_after)
+                .body(())
+                .unwrap()
+        };
+        let retry =
+            |rsp: &http::Response<()>| retry::Policy::<_, _, Error>::retry(&policy, &req, Ok(rsp));
+
+        let start = Instant::now();
+        retry(&rsp("2")).expect("must retry").await;
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+
+        // Retries are not delayed by hints that can't be parsed.
+        let start = Instant::now();
+        retry(&rsp("Wed, 21 Oct 2015 07:28:00 GMT"))
+            .expect("must retry")
+            .await;
+        assert_eq!(start.elapsed(), Duration::from_secs(0));
+
+        // Hints are bounded by the configured maximum...
+        let start = Instant::now();
+        retry(&rsp("4")).expect("must retry").await;
+        assert_eq!(start.elapsed(), Duration::from_secs(3));
+
+        // ...and requests are not retried if the hint exceeds the route
+        // timeout.
+        assert!(retry(&rsp("10")).is_none());
+
+        // Routes without a timeout are still bounded by the maximum.
+        let policy = RetryPolicy {
+            timeout: None,
+            ..policy.clone()
+        };
+        let start = Instant::now();
+        retry::Policy::<_, _, Error>::retry(&policy, &req, Ok(&rsp("3600")))
+            .expect("must retry")
+            .await;
+        assert_eq!(start.elapsed(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn retries_unprocessed_requests() {
+        let policy = RetryPolicy {
+            retry_responses: false,
+            retry_unprocessed: true,
+            ..policy()
+        };
+        let req = http::Request::new(String::new());
+        let retry = |result: Result<&http::Response<()>, &Error>| {
+            retry::Policy::retry(&policy, &req, result).is_some()
+        };
+
+        let failed = http::Response::builder()
+            .status(http::StatusCode::BAD_GATEWAY)
+            .body(())
+            .unwrap();
+        assert!(!retry(Ok(&failed)), "failures must not be retried");
+
+        let mut unprocessed = failed;
+        unprocessed.extensions_mut().insert(errors::Unprocessed(()));
+        assert!(retry(Ok(&unprocessed)));
+
+        let refused = Error::from(std::io::Error::from(std::io::ErrorKind::ConnectionRefused));
+        assert!(retry(Err(&refused)));
+        let reset = Error::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert!(
+            !retry(Err(&reset)),
+            "reset requests may have been processed"
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn retries_per_try_timeouts() {
+        use linkerd_stack::ServiceExt;
+
+        let policy = RetryPolicy {
+            timeout: Some(Duration::from_secs(5)),
+            retry_timeouts: true,
+            ..policy()
+        };
+        let req = http::Request::new(String::new());
+
+        let timeout = Timeout::new(
+            crate::svc::mk(|_: ()| future::pending::<Result<http::Response<()>, Error>>()),
+            Duration::from_secs(1),
+        )
+        .oneshot(())
+        .await
+        .expect_err("attempt must time out");
+        assert!(
+            retry::Policy::<_, http::Response<()>, _>::retry(&policy, &req, Err(&timeout))
+                .is_some()
+        );
+
+        let policy = RetryPolicy {
+            retry_timeouts: false,
+            ..policy
+        };
+        assert!(
+            retry::Policy::<_, http::Response<()>, _>::retry(&policy, &req, Err(&timeout))
+                .is_none(),
+            "timeouts must not be retried without a per-try timeout"
+        );
+    }
+
+    #[test]
+    fn gives_routes_their_own_budgets() {
+        let shared = Arc::new(retry::Budget::new(Duration::from_secs(10), 0, 0.0));
+        let mk_route = |name: &str| {
+            let mut route = profiles::http::Route::new(
+                vec![("route".to_string(), name.to_string())].into_iter(),
+                Vec::new(),
+            );
+            route.set_retries(shared.clone());
+            Route {
+                addr: profiles::LogicalAddr("web.ns.svc.cluster.local:80".parse().unwrap()),
+                route,
+                direction: Direction::Out,
+                rewritten_from: None,
+            }
+        };
+        let budgets = RouteBudgets::new(vec![(
+            "web.ns.svc.cluster.local:80".parse().unwrap(),
+            BudgetConfig {
+                ttl: Duration::from_secs(10),
+                min_per_second: 1,
+                ratio: 0.0,
+            },
+        )]);
+        let mut new_route = NewRouteBudget {
+            budgets,
+            inner: |route: Route| route.route.retries().unwrap().budget().clone(),
+        };
+
+        let a = new_route.new_service(mk_route("a"));
+        let b = new_route.new_service(mk_route("b"));
+        assert!(!Arc::ptr_eq(&a, &shared));
+        assert!(!Arc::ptr_eq(&a, &b));
+
+        // Exhausting one route's budget does not affect another's.
+        assert!(shared.withdraw().is_err());
+        while a.withdraw().is_ok() {}
+        assert!(b.withdraw().is_ok());
+    }
+
+    #[test]
+    fn reports_route_budget_metrics() {
+        use linkerd_metrics::FmtMetrics;
+
+        let metrics = HttpRouteRetry::default();
+        let policy = RetryPolicy {
+            metrics: metrics.get_handle(route()),
+            // Permits a single retry.
+            budget: Arc::new(retry::Budget::new(Duration::from_secs(1), 1, 0.0)),
+            ..policy()
+        };
+        let req = http::Request::new(String::new());
+        let rsp =
+            |status: http::StatusCode| http::Response::builder().status(status).body(()).unwrap();
+        let retry = |rsp: &http::Response<()>| {
+            retry::Policy::<_, _, Error>::retry(&policy, &req, Ok(rsp)).is_some()
+        };
+
+        assert!(!retry(&rsp(http::StatusCode::OK)));
+        assert!(retry(&rsp(http::StatusCode::SERVICE_UNAVAILABLE)));
+        assert!(!retry(&rsp(http::StatusCode::SERVICE_UNAVAILABLE)));
+
+        let report = metrics
+            .into_report(Duration::from_secs(60))
+            .with_prefix("route")
+            .as_display()
+            .to_string();
+        let value = |name: &str| {
+            report
+                .lines()
+                .find(|l| l.starts_with(&format!("{}{{", name)))
+                .and_then(|l| l.rsplit(' ').next())
+                .map(String::from)
+        };
+        assert_eq!(
+            value("route_retry_budget_deposited_total").as_deref(),
+            Some("1")
+        );
+        assert_eq!(
+            value("route_retry_budget_withdrawn_total").as_deref(),
+            Some("1")
+        );
+        assert_eq!(
+            value("route_retry_budget_exhausted_total").as_deref(),
+            Some("1")
+        );
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let backoff = Backoff {
+            base: Duration::from_millis(10),
+            max: Duration::from_millis(50),
+            multiplier: 2.0,
+            jitter: 0.0,
+        };
+        let mut rng = rand::thread_rng();
+        let delays = (1..=4)
+            .map(|retry| backoff.delay(retry, &mut rng))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_millis(10),
+                Duration::from_millis(20),
+                Duration::from_millis(40),
+                Duration::from_millis(50),
+            ]
+        );
+        assert_eq!(backoff.delay(1_000, &mut rng), backoff.max);
+
+        let backoff = Backoff {
+            jitter: 0.5,
+            ..backoff
+        };
+        for _ in 0..100 {
+            let delay = backoff.delay(3, &mut rng);
+            assert!(delay >= Duration::from_millis(20) && delay <= Duration::from_millis(40));
+        }
+    }
+
+    #[test]
+    fn shares_state_by_route() {
+        let after = Duration::from_secs(10);
+        let suppressions = Suppressions::default();
+        let a = suppressions.get(route(), after);
+        let b = suppressions.get(route(), after);
+        assert!(Arc::ptr_eq(&a.state, &b.state));
+
+        drop((a, b));
+        let _c = suppressions.get(route(), after);
+        assert_eq!(suppressions.0.lock().len(), 1);
+    }
+}
@@ -0,0 +1,515 @@
+//! Checks HTTP requests against an external rate limit service.
+//!
+//! When a rate limit service is configured, the proxy calls the service's
+//! `ShouldRateLimit` method (as defined by Envoy's RLS API) for each HTTP
+//! request before it is dispatched. The request is described by a single
+//! descriptor whose entries are built from the request's authority, path,
+//! method, and headers, the route on which it was matched, and the client's
+//! identity. Entries that cannot be built for a request (e.g. because the
+//! client has no identity) are omitted.
+//!
+//! Requests that the service reports as over the limit fail with a
+//! [`RateLimitExceeded`] error. When the service cannot be reached, or does not
+//! respond within the configured timeout, requests are permitted if the client
+//! fails open and fail with a [`RateLimitUnavailable`] error if it fails
+//! closed.
+
+use crate::{
+    control, dns, dst, identity,
+    metrics::{self, latency, metrics, Direction, FmtLabels, FmtMetrics, Histogram},
+    proxy::http,
+    svc::{self, ExtractParam},
+    tls, Error,
+};
+use envoy_ratelimit_proto::{
+    extensions::common::ratelimit::v3::{rate_limit_descriptor, RateLimitDescriptor},
+    service::ratelimit::v3::{
+        rate_limit_response::Code, rate_limit_service_client::RateLimitServiceClient,
+        RateLimitRequest, RateLimitResponse,
+    },
+};
+use futures::prelude::*;
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use thiserror::Error;
+use tokio::time::{self, Instant};
+use tracing::debug;
+
+metrics! {
+    rls_request_duration_ms: Histogram<latency::Ms> {
+        "Elapsed times between a rate limit service request being sent and its response being received"
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub control: control::Config,
+
+    /// The domain in which the service's limits are configured.
+    pub domain: String,
+
+    /// The entries of each request's descriptor.
+    pub descriptor: Vec<Entry>,
+
+    pub failure_mode: FailureMode,
+
+    /// Bounds the time spent waiting for the service's response.
+    pub timeout: Duration,
+
+    /// The proxies (inbound and/or outbound) whose requests are checked.
+    pub directions: HashSet<Direction>,
+}
+
+/// Describes a descriptor entry, by key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Entry {
+    /// `authority`: the request's authority.
+    Authority,
+
+    /// `path`: the request's path.
+    Path,
+
+    /// `method`: the request's method.
+    Method,
+
+    /// `route`: the name of the service profile route on which the request
+    /// was matched, if any.
+    Route,
+
+    /// `client_identity`: the client's identity. For inbound requests, this
+    /// is the identity of the meshed client; for outbound requests, it's the
+    /// proxy's own identity.
+    ClientIdentity,
+
+    /// The value of the named request header, keyed by the header's name.
+    Header(http::HeaderName),
+}
+
+/// Determines whether requests are permitted when the service is unavailable.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FailureMode {
+    Open,
+    Closed,
+}
+
+#[derive(Clone)]
+pub struct Client {
+    client: control::Client,
+    domain: Arc<str>,
+    descriptor: Arc<[Entry]>,
+    failure_mode: FailureMode,
+    timeout: Duration,
+    directions: Arc<HashSet<Direction>>,
+    metrics: Metrics,
+}
+
+#[derive(Debug, Error)]
+#[error("request rate limit exceeded")]
+pub struct RateLimitExceeded(());
+
+#[derive(Debug, Error)]
+#[error("rate limit service unavailable")]
+pub struct RateLimitUnavailable(());
+
+#[derive(Debug, Error)]
+#[error("rate limit service did not respond within {0:?}")]
+struct ServiceTimeout(Duration);
+
+#[derive(Clone, Debug, Default)]
+pub struct Report(Option<Metrics>);
+
+#[derive(Clone, Debug, Default)]
+struct Metrics(Arc<Mutex<HashMap<(Direction, Outcome), Histogram<latency::Ms>>>>);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Outcome {
+    Ok,
+    OverLimit,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct NewCheckRateLimit<X, N> {
+    inner: N,
+    client: Option<Client>,
+    direction: Direction,
+    extract: X,
+}
+
+#[derive(Clone, Debug)]
+pub struct CheckRateLimit<S> {
+    inner: S,
+    check: Option<(Client, Direction, Option<identity::Name>)>,
+}
+
+type ResponseFuture<R> = Pin<Box<dyn Future<Output = Result<R, Error>> + Send + 'static>>;
+
+// === impl Config ===
+
+impl Config {
+    pub fn build<L>(
+        self,
+        dns: dns::Resolver,
+        client_metrics: metrics::ControlHttp,
+        identity: Option<L>,
+    ) -> Client
+    where
+        L: Clone + svc::Param<tls::client::Config> + Send + Sync + 'static,
+    {
+        use svc::NewService;
+
+        let client = self
+            .control
+            .build(dns, client_metrics, identity)
+            .new_service(());
+        Client {
+            client,
+            domain: self.domain.into(),
+            descriptor: self.descriptor.into(),
+            failure_mode: self.failure_mode,
+            timeout: self.timeout,
+            directions: Arc::new(self.directions),
+            metrics: Metrics::default(),
+        }
+    }
+}
+
+// === impl Entry ===
+
+impl Entry {
+    fn key(&self) -> &str {
+        match self {
+            Self::Authority => "authority",
+            Self::Path => "path",
+            Self::Method => "method",
+            Self::Route => "route",
+            Self::ClientIdentity => "client_identity",
+            Self::Header(name) => name.as_str(),
+        }
+    }
+
+    fn value<B>(
+        &self,
+        req: &http::Request<B>,
+        client_id: Option<&identity::Name>,
+    ) -> Option<String> {
+        match self {
+            Self::Authority => req
+                .uri()
+                .authority()
+                .map(|a| a.as_str())
+                .or_else(|| req.headers().get(http::header::HOST)?.to_str().ok())
+                .map(Into::into),
+            Self::Path => Some(req.uri().path().to_string()),
+            Self::Method => Some(req.method().to_string()),
+            Self::Route => req
+                .extensions()
+                .get::<dst::Route>()?
+                .route
+                .labels()
+                .get("route")
+                .cloned(),
+            Self::ClientIdentity => client_id.map(|id| id.to_string()),
+            Self::Header(name) => req.headers().get(name)?.to_str().ok().map(Into::into),
+        }
+    }
+}
+
+// === impl Client ===
+
+impl Client {
+    pub fn report(&self) -> Report {
+        Report(Some(self.metrics.clone()))
+    }
+
+    /// Asks the service whether a request with the given descriptor should be
+    /// rate limited.
+    async fn check(
+        self,
+        direction: Direction,
+        descriptor: RateLimitDescriptor,
+    ) -> Result<(), Error> {
+        let req = RateLimitRequest {
+            domain: self.domain.to_string(),
+            descriptors: vec![descriptor],
+            hits_addend: 1,
+        };
+        let mut client = RateLimitServiceClient::new(self.client.clone());
+        let t0 = Instant::now();
+        let rsp = time::timeout(self.timeout, client.should_rate_limit(req)).await;
+        let rsp = match rsp {
+            Ok(Ok(rsp)) => Ok(rsp.into_inner()),
+            Ok(Err(status)) => Err(status.into()),
+            Err(_) => Err(ServiceTimeout(self.timeout).into()),
+        };
+        let (outcome, result) = decide(self.failure_mode, rsp);
+        self.metrics.record(direction, outcome, t0.elapsed());
+        result
+    }
+}
+
+/// Builds a request's descriptor, if any of its entries apply to the request.
+fn descriptor<B>(
+    entries: &[Entry],
+    req: &http::Request<B>,
+    client_id: Option<&identity::Name>,
+) -> Option<RateLimitDescriptor> {
+    let entries = entries
+        .iter()
+        .filter_map(|entry| {
+            let value = entry.value(req, client_id)?;
+            Some(rate_limit_descriptor::Entry {
+                key: entry.key().to_string(),
+                value,
+            })
+        })
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        return None;
+    }
+    Some(RateLimitDescriptor {
+        entries,
+        limit: None,
+    })
+}
+
+fn decide(
+    failure_mode: FailureMode,
+    rsp: Result<RateLimitResponse, Error>,
+) -> (Outcome, Result<(), Error>) {
+    match rsp {
+        Ok(rsp) if rsp.overall_code() == Code::OverLimit => {
+            debug!("Request rate limit exceeded");
+            (Outcome::OverLimit, Err(RateLimitExceeded(()).into()))
+        }
+        Ok(_) => (Outcome::Ok, Ok(())),
+        Err(error) => {
+            // Failures are recorded by the check metrics; logging each one
+            // would flood the log while the service is unavailable.
+            debug!(%error, ?failure_mode, "Rate limit service failed");
+            match failure_mode {
+                FailureMode::Open => (Outcome::Error, Ok(())),
+                FailureMode::Closed => (Outcome::Error, Err(RateLimitUnavailable(()).into())),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("domain", &self.domain)
+            .field("descriptor", &self.descriptor)
+            .field("failure_mode", &self.failure_mode)
+            .field("timeout", &self.timeout)
+            .field("directions", &self.directions)
+            .finish()
+    }
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    fn record(&self, direction: Direction, outcome: Outcome, elapsed: Duration) {
+        self.0
+            .lock()
+            .entry((direction, outcome))
+            .or_insert_with(|| Histogram::new(latency::BOUNDS))
+            .add(elapsed);
+    }
+}
+
+// === impl Report ===
+
+impl Report {
+    pub fn disabled() -> Self {
+        Self(None)
+    }
+}
+
+impl FmtMetrics for Report {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics = match self.0 {
+            Some(Metrics(ref metrics)) => metrics.lock(),
+            None => return Ok(()),
+        };
+        if metrics.is_empty() {
+            return Ok(());
+        }
+        rls_request_duration_ms.fmt_help(f)?;
+        rls_request_duration_ms.fmt_scopes(f, metrics.iter(), |h| h)
+    }
+}
+
+impl FmtLabels for Outcome {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = match self {
+            Outcome::Ok => "ok",
+            Outcome::OverLimit => "over_limit",
+            Outcome::Error => "error",
+        };
+        write!(f, "result=\"{}\"", result)
+    }
+}
+
+// === impl NewCheckRateLimit ===
+
+impl<X: Clone, N> NewCheckRateLimit<X, N> {
+    /// Checks requests with the client, if it's configured for the given
+    /// direction. The client's identity is obtained from each target via
+    /// `extract`.
+    pub fn layer_via(
+        client: Option<Client>,
+        direction: Direction,
+        extract: X,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        let client = client.filter(|c| c.directions.contains(&direction));
+        svc::layer::mk(move |inner| Self {
+            inner,
+            client: client.clone(),
+            direction,
+            extract: extract.clone(),
+        })
+    }
+}
+
+impl<T, X, N> svc::NewService<T> for NewCheckRateLimit<X, N>
+where
+    X: ExtractParam<Option<identity::Name>, T>,
+    N: svc::NewService<T>,
+{
+    type Service = CheckRateLimit<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let check = self.client.clone().map(|client| {
+            let client_id = self.extract.extract_param(&target);
+            (client, self.direction, client_id)
+        });
+        CheckRateLimit {
+            inner: self.inner.new_service(target),
+            check,
+        }
+    }
+}
+
+// === impl CheckRateLimit ===
+
+impl<B, S> svc::Service<http::Request<B>> for CheckRateLimit<S>
+where
+    B: Send + 'static,
+    S: svc::Service<http::Request<B>> + Clone + Send + 'static,
+    S::Error: Into<Error>,
+    S::Future: Send,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<future::ErrInto<S::Future, Error>, ResponseFuture<S::Response>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let (client, direction, client_id) = match self.check.as_ref() {
+            Some(check) => check,
+            None => return future::Either::Left(self.inner.call(req).err_into()),
+        };
+        let descriptor = match descriptor(&client.descriptor, &req, client_id.as_ref()) {
+            Some(descriptor) => descriptor,
+            None => return future::Either::Left(self.inner.call(req).err_into()),
+        };
+
+        // The request is dispatched after the service responds, so take the
+        // inner service (which is ready) and leave a clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let check = client.clone().check(*direction, descriptor);
+        future::Either::Right(Box::pin(async move {
+            check.await?;
+            inner.call(req).err_into::<Error>().await
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn builds_descriptors() {
+        let entries = vec![
+            Entry::Authority,
+            Entry::Path,
+            Entry::Method,
+            Entry::Route,
+            Entry::ClientIdentity,
+            Entry::Header(http::HeaderName::from_static("x-tenant")),
+        ];
+        let req = http::Request::builder()
+            .method(http::Method::POST)
+            .uri("/books")
+            .header(http::header::HOST, "web.ns.svc.cluster.local:8080")
+            .header("x-tenant", "acme")
+            .body(())
+            .unwrap();
+        let id = identity::Name::from_str("web.ns.serviceaccount.identity.linkerd.cluster.local")
+            .unwrap();
+
+        let descriptor = descriptor(&entries, &req, Some(&id)).unwrap();
+        let entries = descriptor
+            .entries
+            .iter()
+            .map(|e| (e.key.as_str(), e.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                ("authority", "web.ns.svc.cluster.local:8080"),
+                ("path", "/books"),
+                ("method", "POST"),
+                ("client_identity", id.as_ref()),
+                ("x-tenant", "acme"),
+            ]
+        );
+
+        // No descriptor is built when none of its entries apply.
+        assert!(super::descriptor(&[Entry::Route, Entry::ClientIdentity], &req, None).is_none());
+    }
+
+    #[test]
+    fn decides_by_response_and_failure_mode() {
+        let over = RateLimitResponse {
+            overall_code: Code::OverLimit as i32,
+            ..Default::default()
+        };
+        let ok = RateLimitResponse {
+            overall_code: Code::Ok as i32,
+            ..Default::default()
+        };
+
+        let (outcome, res) = decide(FailureMode::Open, Ok(ok));
+        assert_eq!(outcome, Outcome::Ok);
+        assert!(res.is_ok());
+        let (outcome, res) = decide(FailureMode::Open, Ok(over.clone()));
+        assert_eq!(outcome, Outcome::OverLimit);
+        assert!(res.unwrap_err().is::<RateLimitExceeded>());
+        let (outcome, res) = decide(FailureMode::Open, Err("unavailable".into()));
+        assert_eq!(outcome, Outcome::Error);
+        assert!(res.is_ok());
+
+        let (_, res) = decide(FailureMode::Closed, Ok(over));
+        assert!(res.unwrap_err().is::<RateLimitExceeded>());
+        let (outcome, res) = decide(FailureMode::Closed, Err("unavailable".into()));
+        assert_eq!(outcome, Outcome::Error);
+        assert!(res.unwrap_err().is::<RateLimitUnavailable>());
+    }
+}
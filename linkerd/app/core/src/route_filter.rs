@@ -0,0 +1,400 @@
+//! HTTP route filters.
+//!
+//! Services' routes may be configured with filters in the style of the Gateway
+//! API's `HTTPRoute`: a request's path may be rewritten (either in full, or by
+//! replacing a prefix), its host may be rewritten, or it may be answered with
+//! a redirect rather than being dispatched. Filters may be limited to a single
+//! route by name.
+//!
+//! Rewrites are applied above the route's retry and hedge policies, so that
+//! every attempt is dispatched to the same path and host. Redirects are
+//! recorded by the route's metrics.
+
+use crate::{
+    dst::Route,
+    route_policy::{RoutePolicies, RoutePolicy},
+};
+use futures::prelude::*;
+use linkerd_error::Error;
+use linkerd_stack::{layer, NewService, Proxy};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Maps logical service addresses to the filters of their routes.
+pub type RouteFilters = RoutePolicies<RouteFilter>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RouteFilter {
+    /// If set, the filter only applies to the route with this name.
+    pub route: Option<String>,
+    pub kind: Kind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// Replaces the request's path.
+    RewritePath(String),
+
+    /// Replaces the given prefix of the request's path. Requests whose paths
+    /// don't start with the prefix are unmodified.
+    RewritePrefix { prefix: String, replacement: String },
+
+    /// Replaces the request's host.
+    RewriteHost(http::uri::Authority),
+
+    /// Answers the request with a redirect.
+    Redirect(Redirect),
+}
+
+/// Describes a redirect's location, relative to the request's URI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Redirect {
+    pub status: http::StatusCode,
+
+    /// Overrides the request's scheme.
+    pub scheme: Option<http::uri::Scheme>,
+
+    /// Overrides the request's host.
+    pub host: Option<String>,
+
+    /// Overrides the request's port. When the scheme or host are overridden,
+    /// the request's port is not retained.
+    pub port: Option<u16>,
+
+    /// Overrides the request's path (and query).
+    pub path: Option<String>,
+}
+
+/// Applies per-route filters.
+#[derive(Clone, Debug)]
+pub struct NewFilterRoute<N> {
+    filters: RouteFilters,
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct FilterRoute<P> {
+    filters: Arc<[Kind]>,
+    inner: P,
+}
+
+pub fn layer<N>(
+    filters: RouteFilters,
+) -> impl layer::Layer<N, Service = NewFilterRoute<N>> + Clone {
+    layer::mk(move |inner| NewFilterRoute {
+        filters: filters.clone(),
+        inner,
+    })
+}
+
+// === impl RouteFilter ===
+
+impl RoutePolicy for RouteFilter {
+    fn route_name(&self) -> Option<&str> {
+        self.route.as_deref()
+    }
+}
+
+// === impl Kind ===
+
+impl Kind {
+    /// Rewrites the request, or returns the location to which it's redirected.
+    fn apply<B>(
+        &self,
+        req: &mut http::Request<B>,
+    ) -> Option<(http::StatusCode, http::HeaderValue)> {
+        match self {
+            Kind::RewritePath(path) => set_path(req, path),
+            Kind::RewritePrefix {
+                prefix,
+                replacement,
+            } => {
+                let path =
+                    strip_prefix(req.uri().path(), prefix).map(|rest| join(replacement, rest));
+                if let Some(path) = path {
+                    set_path(req, &path);
+                }
+            }
+            Kind::RewriteHost(host) => {
+                if req.uri().authority().is_some() {
+                    let mut parts = req.uri().clone().into_parts();
+                    parts.authority = Some(host.clone());
+                    if let Ok(uri) = http::Uri::from_parts(parts) {
+                        *req.uri_mut() = uri;
+                    }
+                }
+                if let Ok(value) = http::HeaderValue::from_str(host.as_str()) {
+                    req.headers_mut().insert(http::header::HOST, value);
+                }
+            }
+            Kind::Redirect(redirect) => {
+                return redirect.location(req).map(|loc| (redirect.status, loc));
+            }
+        }
+        None
+    }
+}
+
+/// Strips a path prefix, matching whole path segments.
+fn strip_prefix<'p>(path: &'p str, prefix: &str) -> Option<&'p str> {
+    let prefix = prefix.trim_end_matches('/');
+    let rest = path.strip_prefix(prefix)?;
+    if rest.is_empty() || rest.starts_with('/') {
+        return Some(rest);
+    }
+    None
+}
+
+fn join(prefix: &str, rest: &str) -> String {
+    let path = format!("{}{}", prefix.trim_end_matches('/'), rest);
+    if path.is_empty() {
+        return "/".to_string();
+    }
+    path
+}
+
+/// Replaces the request's path, retaining its query.
+fn set_path<B>(req: &mut http::Request<B>, path: &str) {
+    let path_and_query = match req.uri().query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+    let mut parts = req.uri().clone().into_parts();
+    parts.path_and_query = path_and_query.parse().ok();
+    match http::Uri::from_parts(parts) {
+        Ok(uri) => *req.uri_mut() = uri,
+        Err(error) => debug!(%error, %path, "Failed to rewrite path"),
+    }
+}
+
+// === impl Redirect ===
+
+impl Redirect {
+    fn location<B>(&self, req: &http::Request<B>) -> Option<http::HeaderValue> {
+        let authority = req.uri().authority().cloned().or_else(|| {
+            req.headers()
+                .get(http::header::HOST)?
+                .to_str()
+                .ok()?
+                .parse::<http::uri::Authority>()
+                .ok()
+        });
+        let scheme = self
+            .scheme
+            .clone()
+            .or_else(|| req.uri().scheme().cloned())
+            .unwrap_or(http::uri::Scheme::HTTP);
+        let host = self
+            .host
+            .as_deref()
+            .or_else(|| authority.as_ref().map(|a| a.host()))?;
+        let port = self.port.or_else(|| {
+            if self.scheme.is_some() || self.host.is_some() {
+                return None;
+            }
+            authority.as_ref().and_then(|a| a.port_u16())
+        });
+        let path = self.path.as_deref().unwrap_or_else(|| {
+            req.uri()
+                .path_and_query()
+                .map(|pq| pq.as_str())
+                .unwrap_or("/")
+        });
+
+        let location = match port {
+            Some(port) => format!("{}://{}:{}{}", scheme, host, port, path),
+            None => format!("{}://{}{}", scheme, host, path),
+        };
+        http::HeaderValue::from_str(&location).ok()
+    }
+}
+
+// === impl NewFilterRoute ===
+
+impl<N> NewService<Route> for NewFilterRoute<N>
+where
+    N: NewService<Route>,
+{
+    type Service = FilterRoute<N::Service>;
+
+    fn new_service(&mut self, route: Route) -> Self::Service {
+        let filters = self.filters.get(&route).map(|f| f.kind.clone()).collect();
+        let inner = self.inner.new_service(route);
+        FilterRoute { filters, inner }
+    }
+}
+
+// === impl FilterRoute ===
+
+impl<P, A, B, S> Proxy<http::Request<A>, S> for FilterRoute<P>
+where
+    B: Default,
+    P: Proxy<http::Request<A>, S, Response = http::Response<B>>,
+    P::Error: Into<Error>,
+    S: tower::Service<P::Request>,
+{
+    type Request = P::Request;
+    type Response = http::Response<B>;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<P::Future, Error>,
+        future::Ready<Result<http::Response<B>, Error>>,
+    >;
+
+    fn proxy(&self, svc: &mut S, mut req: http::Request<A>) -> Self::Future {
+        for filter in self.filters.iter() {
+            if let Some((status, location)) = filter.apply(&mut req) {
+                debug!(%status, ?location, "Redirecting request");
+                let mut rsp = http::Response::new(B::default());
+                *rsp.status_mut() = status;
+                rsp.headers_mut().insert(http::header::LOCATION, location);
+                return future::Either::Right(future::ok(rsp));
+            }
+        }
+        future::Either::Left(self.inner.proxy(svc, req).err_into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::route_policy::test_util::{policies, route};
+    use linkerd_proxy_http::BoxBody;
+    use linkerd_stack::{ProxyService, ServiceExt};
+
+    fn filters(kinds: Vec<Kind>) -> RouteFilters {
+        policies(
+            kinds
+                .into_iter()
+                .map(|kind| RouteFilter { route: None, kind })
+                .collect(),
+        )
+    }
+
+    /// Sends a request, returning the URI and host that the service received,
+    /// or the response if the service wasn't called.
+    async fn send(
+        filters: RouteFilters,
+        uri: &str,
+    ) -> Result<(http::Uri, Option<http::HeaderValue>), http::Response<BoxBody>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let tx = std::sync::Mutex::new(Some(tx));
+        let svc = crate::svc::mk(move |req: http::Request<BoxBody>| {
+            if let Some(tx) = tx.lock().unwrap().take() {
+                let host = req.headers().get(http::header::HOST).cloned();
+                let _ = tx.send((req.uri().clone(), host));
+            }
+            future::ok::<_, Error>(http::Response::new(BoxBody::default()))
+        });
+        let proxy = NewFilterRoute {
+            filters,
+            inner: |_: Route| (),
+        }
+        .new_service(route("GET /books"));
+        let req = http::Request::builder()
+            .uri(uri)
+            .header(http::header::HOST, "web.ns.svc.cluster.local:8080")
+            .body(BoxBody::default())
+            .unwrap();
+        let rsp = ProxyService::new(proxy, svc).oneshot(req).await.unwrap();
+        rx.await.map_err(|_| rsp)
+    }
+
+    #[tokio::test]
+    async fn rewrites_paths() {
+        let (uri, _) = send(
+            filters(vec![Kind::RewritePrefix {
+                prefix: "/api/v1/".to_string(),
+                replacement: "/v1".to_string(),
+            }]),
+            "/api/v1/books?limit=10",
+        )
+        .await
+        .unwrap();
+        assert_eq!(uri, "/v1/books?limit=10");
+
+        // Prefixes match whole segments.
+        let (uri, _) = send(
+            filters(vec![Kind::RewritePrefix {
+                prefix: "/api".to_string(),
+                replacement: "/".to_string(),
+            }]),
+            "/apis/books",
+        )
+        .await
+        .unwrap();
+        assert_eq!(uri, "/apis/books");
+
+        let (uri, _) = send(
+            filters(vec![Kind::RewritePrefix {
+                prefix: "/api".to_string(),
+                replacement: "/".to_string(),
+            }]),
+            "/api",
+        )
+        .await
+        .unwrap();
+        assert_eq!(uri, "/");
+
+        let (uri, _) = send(
+            filters(vec![Kind::RewritePath("/books".to_string())]),
+            "http://web.ns.svc.cluster.local:8080/library/books",
+        )
+        .await
+        .unwrap();
+        assert_eq!(uri, "http://web.ns.svc.cluster.local:8080/books");
+    }
+
+    #[tokio::test]
+    async fn rewrites_hosts() {
+        let (uri, host) = send(
+            filters(vec![Kind::RewriteHost(
+                "books.example.com".parse().unwrap(),
+            )]),
+            "http://web.ns.svc.cluster.local:8080/books",
+        )
+        .await
+        .unwrap();
+        assert_eq!(uri, "http://books.example.com/books");
+        assert_eq!(host.unwrap(), "books.example.com");
+    }
+
+    #[tokio::test]
+    async fn redirects() {
+        let rsp = send(
+            filters(vec![Kind::Redirect(Redirect {
+                status: http::StatusCode::PERMANENT_REDIRECT,
+                scheme: Some(http::uri::Scheme::HTTPS),
+                host: None,
+                port: None,
+                path: None,
+            })]),
+            "/books?limit=10",
+        )
+        .await
+        .expect_err("request must be redirected");
+        assert_eq!(rsp.status(), http::StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            rsp.headers().get(http::header::LOCATION).unwrap(),
+            "https://web.ns.svc.cluster.local/books?limit=10"
+        );
+
+        let rsp = send(
+            filters(vec![Kind::Redirect(Redirect {
+                status: http::StatusCode::MOVED_PERMANENTLY,
+                scheme: None,
+                host: Some("books.example.com".to_string()),
+                port: Some(8443),
+                path: Some("/catalog".to_string()),
+            })]),
+            "/books",
+        )
+        .await
+        .expect_err("request must be redirected");
+        assert_eq!(rsp.status(), http::StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            rsp.headers().get(http::header::LOCATION).unwrap(),
+            "http://books.example.com:8443/catalog"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Per-route policies.
+//!
+//! Services may be configured with policies for their routes (e.g. faults,
+//! header policies, and route filters). Each policy applies to all of a
+//! service's routes unless it is limited to a single route by name.
+
+use crate::{dst::Route, profiles, NameAddr};
+use std::{collections::HashMap, sync::Arc};
+
+/// A policy that may be limited to a single route by name.
+pub trait RoutePolicy {
+    /// The name of the route to which the policy applies, or `None` if it
+    /// applies to all of the service's routes.
+    fn route_name(&self) -> Option<&str>;
+}
+
+/// Maps logical service addresses to the policies of their routes.
+#[derive(Clone, Debug)]
+pub struct RoutePolicies<P>(Arc<HashMap<NameAddr, Vec<P>>>);
+
+// === impl RoutePolicies ===
+
+impl<P> RoutePolicies<P> {
+    pub fn new(policies: impl IntoIterator<Item = (NameAddr, P)>) -> Self {
+        let mut by_addr = HashMap::<_, Vec<_>>::new();
+        for (addr, policy) in policies {
+            by_addr.entry(addr).or_default().push(policy);
+        }
+        Self(Arc::new(by_addr))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<P: RoutePolicy> RoutePolicies<P> {
+    /// Returns the policies that apply to the given route, in the order in
+    /// which they were configured.
+    pub fn get<'r>(&'r self, route: &'r Route) -> impl Iterator<Item = &'r P> + 'r {
+        let profiles::LogicalAddr(addr) = &route.addr;
+        self.0
+            .get(addr)
+            .into_iter()
+            .flatten()
+            .filter(move |p| applies_to(p.route_name(), &route.route))
+    }
+}
+
+impl<P> Default for RoutePolicies<P> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+fn applies_to(name: Option<&str>, route: &profiles::http::Route) -> bool {
+    match name {
+        Some(name) => route.labels().get("route").map(String::as_str) == Some(name),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_util {
+    use super::*;
+
+    pub(crate) const SERVICE: &str = "web.ns.svc.cluster.local:80";
+
+    /// Returns a route of the `SERVICE` service with the given name.
+    pub(crate) fn route(name: &str) -> Route {
+        Route {
+            addr: profiles::LogicalAddr(SERVICE.parse().unwrap()),
+            route: profiles::http::Route::new(
+                vec![("route".to_string(), name.to_string())].into_iter(),
+                Vec::new(),
+            ),
+            direction: crate::metrics::Direction::Out,
+            rewritten_from: None,
+        }
+    }
+
+    /// Configures the given policies on the `SERVICE` service.
+    pub(crate) fn policies<P>(policies: Vec<P>) -> RoutePolicies<P> {
+        RoutePolicies::new(policies.into_iter().map(|p| (SERVICE.parse().unwrap(), p)))
+    }
+}
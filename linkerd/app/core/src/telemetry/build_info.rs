@@ -6,7 +6,7 @@ use std::sync::Arc;
 
 const GIT_BRANCH: &str = env!("GIT_BRANCH");
 const GIT_SHA: &str = env!("GIT_SHA");
-const GIT_VERSION: &str = env!("GIT_VERSION");
+pub(crate) const GIT_VERSION: &str = env!("GIT_VERSION");
 const PROFILE: &str = env!("PROFILE");
 const RUST_VERSION: &str = env!("RUST_VERSION");
 
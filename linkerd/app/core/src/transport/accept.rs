@@ -0,0 +1,338 @@
+//! Limits the connections accepted by a listener.
+//!
+//! A listener may limit the number of connections that it holds open at once
+//! and the rate at which it accepts new connections. Connections that exceed
+//! these limits are closed as soon as they are accepted, before any of their
+//! data is read, so that a flood of connections can't exhaust the proxy's
+//! resources.
+
+use crate::{
+    io,
+    metrics::{metrics, Counter, FmtLabels, FmtMetrics, Gauge},
+    transport::{Local, ServerAddr},
+};
+use futures::prelude::*;
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    fmt,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::time::Instant;
+use tracing::debug;
+
+metrics! {
+    tcp_accept_open_connections: Gauge {
+        "The number of open connections that were accepted by a listener"
+    },
+    tcp_accept_shed_total: Counter {
+        "The total number of connections closed as soon as they were accepted because a listener's limits were reached"
+    }
+}
+
+/// Configures the limits of a listener's connections.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Limits {
+    /// The number of accepted connections that may be open at once.
+    pub max_open: Option<usize>,
+
+    /// The rate at which connections may be accepted.
+    pub rate: Option<Rate>,
+}
+
+/// A token-bucket limit on the rate at which connections are accepted.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rate {
+    /// The number of connections permitted per second, on average.
+    pub per_second: f64,
+
+    /// The number of connections that may be permitted at once.
+    pub burst: u32,
+}
+
+/// Tracks the connections of each listener.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<Mutex<HashMap<SocketAddr, Arc<ListenerMetrics>>>>);
+
+/// An accepted connection, which is counted as open until it's dropped.
+#[pin_project]
+#[derive(Debug)]
+pub struct Io<I> {
+    #[pin]
+    io: I,
+    _open: Open,
+}
+
+#[derive(Debug, Default)]
+struct ListenerMetrics {
+    open: Gauge,
+    shed_max_open: Counter,
+    shed_rate: Counter,
+}
+
+#[derive(Debug)]
+struct Open(Arc<ListenerMetrics>);
+
+#[derive(Debug)]
+struct Bucket {
+    rate: Rate,
+    tokens: f64,
+    refilled: Instant,
+}
+
+struct ShedLabels<'a> {
+    addr: &'a SocketAddr,
+    reason: &'static str,
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    /// Limits the connections accepted by the listener bound on `addr`.
+    ///
+    /// Connections are always counted, even when the listener has no limits.
+    pub fn limit<A, I>(
+        &self,
+        Local(ServerAddr(addr)): Local<ServerAddr>,
+        limits: Limits,
+        listen: impl Stream<Item = io::Result<(A, I)>> + Send + Sync + 'static,
+    ) -> impl Stream<Item = io::Result<(A, Io<I>)>> + Send + Sync + 'static
+    where
+        A: Send + Sync + 'static,
+        I: Send + Sync + 'static,
+    {
+        let metrics = self.0.lock().entry(addr).or_default().clone();
+        let mut bucket = limits.rate.map(Bucket::new);
+        listen.filter_map(move |res| {
+            let res = res.map(|(addrs, io)| {
+                if let Some(max) = limits.max_open {
+                    if metrics.open.value() >= max as u64 {
+                        debug!(%addr, max, "Shedding connection; too many connections are open");
+                        metrics.shed_max_open.incr();
+                        return None;
+                    }
+                }
+                if let Some(bucket) = bucket.as_mut() {
+                    if !bucket.acquire() {
+                        debug!(%addr, "Shedding connection; connections are being accepted too quickly");
+                        metrics.shed_rate.incr();
+                        return None;
+                    }
+                }
+                let open = Open::new(metrics.clone());
+                Some((addrs, Io { io, _open: open }))
+            });
+            // Dropping a shed connection's socket closes it.
+            future::ready(res.transpose())
+        })
+    }
+}
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let listeners = self.0.lock();
+        if listeners.is_empty() {
+            return Ok(());
+        }
+
+        tcp_accept_open_connections.fmt_help(f)?;
+        for (addr, m) in listeners.iter() {
+            tcp_accept_open_connections.fmt_metric_labeled(f, &m.open, ListenLabel(addr))?;
+        }
+
+        tcp_accept_shed_total.fmt_help(f)?;
+        for (addr, m) in listeners.iter() {
+            for (reason, shed) in &[("max_open", &m.shed_max_open), ("rate", &m.shed_rate)] {
+                tcp_accept_shed_total.fmt_metric_labeled(f, *shed, ShedLabels { addr, reason })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct ListenLabel<'a>(&'a SocketAddr);
+
+impl FmtLabels for ListenLabel<'_> {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "listen_addr=\"{}\"", self.0)
+    }
+}
+
+impl FmtLabels for ShedLabels<'_> {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        ListenLabel(self.addr).fmt_labels(f)?;
+        write!(f, ",reason=\"{}\"", self.reason)
+    }
+}
+
+// === impl Open ===
+
+impl Open {
+    fn new(metrics: Arc<ListenerMetrics>) -> Self {
+        metrics.open.incr();
+        Self(metrics)
+    }
+}
+
+impl Drop for Open {
+    fn drop(&mut self) {
+        self.0.open.decr();
+    }
+}
+
+// === impl Bucket ===
+
+impl Bucket {
+    fn new(rate: Rate) -> Self {
+        Self {
+            rate,
+            tokens: rate.burst as f64,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Takes a token from the bucket, if one is available.
+    fn acquire(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(self.refilled);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate.per_second)
+            .min(self.rate.burst as f64);
+        self.refilled = now;
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+// === impl Io ===
+
+impl<I: io::AsyncRead> io::AsyncRead for Io<I> {
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut io::ReadBuf<'_>,
+    ) -> io::Poll<()> {
+        self.project().io.poll_read(cx, buf)
+    }
+}
+
+impl<I: io::AsyncWrite> io::AsyncWrite for Io<I> {
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        self.project().io.poll_shutdown(cx)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        self.project().io.poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> io::Poll<usize> {
+        self.project().io.poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> io::Poll<usize> {
+        self.project().io.poll_write_vectored(cx, bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+#[async_trait::async_trait]
+impl<I: io::Peek + Send + Sync> io::Peek for Io<I> {
+    async fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.io.peek(buf).await
+    }
+}
+
+impl<I: io::PeerAddr> io::PeerAddr for Io<I> {
+    #[inline]
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.io.peer_addr()
+    }
+}
+
+impl<I: io::Splice> io::Splice for Io<I> {
+    #[inline]
+    fn splice_socket(&self) -> Option<&tokio::net::TcpStream> {
+        self.io.splice_socket()
+    }
+
+    #[inline]
+    fn record_splice_read(&mut self, sz: usize) {
+        self.io.record_splice_read(sz)
+    }
+
+    #[inline]
+    fn record_splice_write(&mut self, sz: usize) {
+        self.io.record_splice_write(sz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listen(n: usize) -> impl Stream<Item = io::Result<((), ())>> + Send + Sync + 'static {
+        stream::iter((0..n).map(|_| Ok(((), ()))))
+    }
+
+    fn addr() -> Local<ServerAddr> {
+        Local(ServerAddr(([127, 0, 0, 1], 4143).into()))
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn sheds_connections_over_max_open() {
+        let metrics = Metrics::default();
+        let limits = Limits {
+            max_open: Some(2),
+            rate: None,
+        };
+        let accepted = metrics
+            .limit(addr(), limits, listen(3))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(accepted.len(), 2);
+
+        let m = metrics.0.lock().get(&addr().0 .0).cloned().unwrap();
+        assert_eq!(m.open.value(), 2);
+        assert_eq!(m.shed_max_open.value(), 1.0);
+
+        // Connections are no longer counted once they're closed.
+        drop(accepted);
+        assert_eq!(m.open.value(), 0);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn limits_accept_rate() {
+        let mut bucket = Bucket::new(Rate {
+            per_second: 10.0,
+            burst: 2,
+        });
+        assert!(bucket.acquire());
+        assert!(bucket.acquire());
+        assert!(!bucket.acquire());
+
+        tokio::time::advance(std::time::Duration::from_millis(100)).await;
+        assert!(bucket.acquire());
+        assert!(!bucket.acquire());
+    }
+}
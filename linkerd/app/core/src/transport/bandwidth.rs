@@ -0,0 +1,404 @@
+//! Limits the bandwidth of forwarded TCP connections.
+//!
+//! Limits are configured per target port as token buckets: each byte takes a
+//! token from the bucket, which holds at most `burst` tokens and is refilled
+//! at `rate` tokens per second. Each direction of a connection is limited
+//! independently. A limit's buckets are either shared by all of the
+//! connections to its port (`Scope::Server`) or created for each connection
+//! (`Scope::Connection`).
+//!
+//! Reads are charged after the fact, so a connection may exceed its bucket by
+//! up to one read buffer; it then waits until the bucket is refilled. Writes
+//! are truncated to the tokens that are available.
+//!
+//! Limited connections are always copied through userspace, since data that is
+//! spliced between sockets can't be metered.
+
+use crate::{io, svc};
+use futures::{prelude::*, ready};
+use linkerd_stack::Param;
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::{self, Instant, Sleep};
+use tracing::trace;
+
+/// Configures a token-bucket bandwidth limit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Limit {
+    /// The number of bytes permitted per second, on average.
+    pub rate: u64,
+
+    /// The number of bytes that may be permitted at once.
+    pub burst: u64,
+
+    pub scope: Scope,
+}
+
+/// Determines which connections share a limit's buckets.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Scope {
+    /// Each connection is limited independently.
+    Connection,
+
+    /// All connections to a port share a limit.
+    Server,
+}
+
+/// Bandwidth limits by target port.
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    ports: Arc<HashMap<u16, Limiter>>,
+    default: Option<Limiter>,
+}
+
+/// Limits the bandwidth of the connections to a target.
+#[derive(Clone, Debug)]
+pub struct NewThrottle<N> {
+    inner: N,
+    limits: Limits,
+}
+
+#[derive(Clone, Debug)]
+pub struct Throttle<S> {
+    inner: S,
+    limiter: Option<Limiter>,
+}
+
+/// An I/O stream whose reads and writes are limited.
+#[pin_project]
+#[derive(Debug)]
+pub struct ThrottleIo<I> {
+    #[pin]
+    io: I,
+    limits: Option<(Wait, Wait)>,
+}
+
+/// Produces the buckets for each connection.
+#[derive(Clone, Debug)]
+struct Limiter {
+    limit: Limit,
+    // Set when the buckets are shared by all connections.
+    shared: Option<(Arc<Bucket>, Arc<Bucket>)>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    limit: Limit,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// Waits for tokens to become available in a bucket.
+#[derive(Debug)]
+struct Wait {
+    bucket: Arc<Bucket>,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+// === impl Limits ===
+
+impl Limits {
+    /// Limits connections to the given ports. Connections to other ports use
+    /// the default limit, if one is set.
+    pub fn new(ports: impl IntoIterator<Item = (u16, Limit)>, default: Option<Limit>) -> Self {
+        let ports = ports
+            .into_iter()
+            .map(|(port, limit)| (port, Limiter::new(limit)))
+            .collect();
+        Self {
+            ports: Arc::new(ports),
+            default: default.map(Limiter::new),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ports.is_empty() && self.default.is_none()
+    }
+
+    /// Returns the limit of connections to the given port, if any.
+    pub fn port(&self, port: u16) -> Option<Limit> {
+        self.limiter(port).map(|l| l.limit)
+    }
+
+    fn limiter(&self, port: u16) -> Option<&Limiter> {
+        self.ports.get(&port).or_else(|| self.default.as_ref())
+    }
+}
+
+// === impl Limiter ===
+
+impl Limiter {
+    fn new(limit: Limit) -> Self {
+        let shared = match limit.scope {
+            Scope::Server => Some((Bucket::new(limit), Bucket::new(limit))),
+            Scope::Connection => None,
+        };
+        Self { limit, shared }
+    }
+
+    /// Returns the read and write buckets for a new connection.
+    fn buckets(&self) -> (Arc<Bucket>, Arc<Bucket>) {
+        match self.shared {
+            Some((ref read, ref write)) => (read.clone(), write.clone()),
+            None => (Bucket::new(self.limit), Bucket::new(self.limit)),
+        }
+    }
+}
+
+// === impl Bucket ===
+
+impl Bucket {
+    fn new(limit: Limit) -> Arc<Self> {
+        Arc::new(Self {
+            limit,
+            state: Mutex::new(BucketState {
+                tokens: limit.burst as f64,
+                refilled: Instant::now(),
+            }),
+        })
+    }
+
+    /// Returns the number of whole tokens in the bucket or, if it's empty, the
+    /// time until a token is available.
+    fn available(&self) -> Result<usize, Duration> {
+        let mut state = self.state.lock();
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(state.refilled);
+        state.tokens = (state.tokens + elapsed.as_secs_f64() * self.limit.rate as f64)
+            .min(self.limit.burst as f64);
+        state.refilled = now;
+        if state.tokens < 1.0 {
+            let wait = (1.0 - state.tokens) / self.limit.rate as f64;
+            return Err(Duration::from_secs_f64(wait));
+        }
+        Ok(state.tokens as usize)
+    }
+
+    /// Takes tokens from the bucket. The bucket may be overdrawn.
+    fn take(&self, sz: usize) {
+        self.state.lock().tokens -= sz as f64;
+    }
+}
+
+// === impl Wait ===
+
+impl Wait {
+    fn new(bucket: Arc<Bucket>) -> Self {
+        Self {
+            bucket,
+            sleep: None,
+        }
+    }
+
+    /// Returns the number of tokens available, once the bucket isn't empty.
+    fn poll_available(&mut self, cx: &mut Context<'_>) -> Poll<usize> {
+        loop {
+            if let Some(sleep) = self.sleep.as_mut() {
+                ready!(sleep.as_mut().poll(cx));
+                self.sleep = None;
+            }
+            match self.bucket.available() {
+                Ok(tokens) => return Poll::Ready(tokens),
+                Err(wait) => {
+                    trace!(?wait, "Throttled");
+                    self.sleep = Some(Box::pin(time::sleep(wait)));
+                }
+            }
+        }
+    }
+}
+
+// === impl NewThrottle ===
+
+impl<N> NewThrottle<N> {
+    pub fn layer(limits: Limits) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            limits: limits.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewThrottle<N>
+where
+    T: Param<u16>,
+    N: svc::NewService<T>,
+{
+    type Service = Throttle<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let limiter = self.limits.limiter(target.param()).cloned();
+        Throttle {
+            inner: self.inner.new_service(target),
+            limiter,
+        }
+    }
+}
+
+// === impl Throttle ===
+
+impl<I, S> svc::Service<I> for Throttle<S>
+where
+    S: svc::Service<ThrottleIo<I>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        let limits = self.limiter.as_ref().map(|l| {
+            let (read, write) = l.buckets();
+            (Wait::new(read), Wait::new(write))
+        });
+        self.inner.call(ThrottleIo { io, limits })
+    }
+}
+
+// === impl ThrottleIo ===
+
+impl<I: io::AsyncRead> io::AsyncRead for ThrottleIo<I> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut io::ReadBuf<'_>,
+    ) -> io::Poll<()> {
+        let this = self.project();
+        let (read, _) = match this.limits.as_mut() {
+            Some(limits) => limits,
+            None => return this.io.poll_read(cx, buf),
+        };
+        ready!(read.poll_available(cx));
+        let filled = buf.filled().len();
+        ready!(this.io.poll_read(cx, buf))?;
+        read.bucket.take(buf.filled().len() - filled);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<I: io::AsyncWrite> io::AsyncWrite for ThrottleIo<I> {
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        self.project().io.poll_shutdown(cx)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> io::Poll<usize> {
+        let this = self.project();
+        let (_, write) = match this.limits.as_mut() {
+            Some(limits) => limits,
+            None => return this.io.poll_write(cx, buf),
+        };
+        let tokens = ready!(write.poll_available(cx));
+        let sz = ready!(this.io.poll_write(cx, &buf[..buf.len().min(tokens)]))?;
+        write.bucket.take(sz);
+        Poll::Ready(Ok(sz))
+    }
+}
+
+impl<I: io::PeerAddr> io::PeerAddr for ThrottleIo<I> {
+    #[inline]
+    fn peer_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.io.peer_addr()
+    }
+}
+
+impl<I: io::Splice> io::Splice for ThrottleIo<I> {
+    /// Only unlimited connections may be spliced.
+    #[inline]
+    fn splice_socket(&self) -> Option<&tokio::net::TcpStream> {
+        if self.limits.is_some() {
+            return None;
+        }
+        self.io.splice_socket()
+    }
+
+    #[inline]
+    fn record_splice_read(&mut self, sz: usize) {
+        self.io.record_splice_read(sz)
+    }
+
+    #[inline]
+    fn record_splice_write(&mut self, sz: usize) {
+        self.io.record_splice_write(sz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svc::{NewService, ServiceExt};
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn token_bucket() {
+        let bucket = Bucket::new(Limit {
+            rate: 100,
+            burst: 200,
+            scope: Scope::Connection,
+        });
+
+        // The bucket starts full and may be overdrawn.
+        assert_eq!(bucket.available(), Ok(200));
+        bucket.take(250);
+        assert!(bucket.available().is_err());
+
+        // It's refilled at the configured rate, up to its burst.
+        time::advance(Duration::from_secs(1)).await;
+        assert_eq!(bucket.available(), Ok(50));
+        time::advance(Duration::from_secs(10)).await;
+        assert_eq!(bucket.available(), Ok(200));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn throttles_writes() {
+        use io::AsyncWriteExt;
+
+        let limit = Limit {
+            rate: 10,
+            burst: 10,
+            scope: Scope::Connection,
+        };
+        let (client, mut server) = io::duplex(1024);
+        let mut stack = NewThrottle {
+            inner: |_: u16| svc::mk(|io| future::ok::<_, ()>(io)),
+            limits: Limits::new(Some((8080, limit)), None),
+        };
+        let mut io = stack.new_service(8080).oneshot(client).await.unwrap();
+        assert!(io.limits.is_some());
+
+        // Writes are truncated to the available tokens.
+        let start = Instant::now();
+        assert_eq!(io.write(&[0; 15]).await.unwrap(), 10);
+        assert_eq!(io.write(&[0; 5]).await.unwrap(), 1);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        let mut buf = [0; 11];
+        io::AsyncReadExt::read_exact(&mut server, &mut buf)
+            .await
+            .unwrap();
+
+        // Connections to other ports aren't limited.
+        let (client, _server) = io::duplex(1024);
+        let io = stack.new_service(80).oneshot(client).await.unwrap();
+        assert!(io.limits.is_none());
+    }
+}
@@ -1,8 +1,12 @@
+use super::Origin;
 pub use crate::metrics::{Direction, OutboundEndpointLabels, ServerLabel as PolicyServerLabel};
 use linkerd_conditional::Conditional;
 use linkerd_metrics::FmtLabels;
 use linkerd_tls as tls;
-use std::{fmt, net::SocketAddr};
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+};
 
 /// Describes a class of transport.
 ///
@@ -13,15 +17,35 @@ use std::{fmt, net::SocketAddr};
 pub enum Key {
     Server(ServerLabels),
     OutboundClient(OutboundEndpointLabels),
+    /// An outbound client connection whose endpoint-specific labels have been
+    /// dropped by [`Aggregation`].
+    AggregatedOutboundClient {
+        server_id: tls::ConditionalClientTls,
+        authority: Option<http::uri::Authority>,
+    },
     InboundClient,
 }
 
+/// Configures which transport families omit endpoint addresses from their
+/// labels.
+///
+/// Destinations with high endpoint churn produce a new series for each address
+/// they use. Aggregating a family merges these series so that the family's
+/// cardinality is bounded by its remaining labels.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Aggregation {
+    pub inbound_server: bool,
+    pub outbound_server: bool,
+    pub outbound_client: bool,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct ServerLabels {
     direction: Direction,
     tls: tls::ConditionalServerTls,
-    target_addr: SocketAddr,
+    target_addr: Option<SocketAddr>,
     policy: Option<PolicyServerLabel>,
+    origin: Option<Origin>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -40,13 +64,75 @@ impl Key {
         tls: tls::ConditionalServerTls,
         target_addr: SocketAddr,
         server: PolicyServerLabel,
+        origin: Origin,
     ) -> Self {
-        Self::Server(ServerLabels::inbound(tls, target_addr, server))
+        Self::Server(ServerLabels::inbound(tls, target_addr, server, origin))
     }
 
     pub fn outbound_server(target_addr: SocketAddr) -> Self {
         Self::Server(ServerLabels::outbound(target_addr))
     }
+
+    pub fn direction(&self) -> Direction {
+        match self {
+            Self::Server(l) => l.direction,
+            Self::OutboundClient(_) | Self::AggregatedOutboundClient { .. } => Direction::Out,
+            Self::InboundClient => Direction::In,
+        }
+    }
+
+    /// Returns the server's address for accepted connections, or the
+    /// endpoint's address for outbound client connections, unless the key has
+    /// been aggregated.
+    pub fn target_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Self::Server(l) => l.target_addr,
+            Self::OutboundClient(l) => Some(l.target_addr),
+            Self::AggregatedOutboundClient { .. } | Self::InboundClient => None,
+        }
+    }
+
+    /// Returns the identity of the connection's peer, if it is known.
+    pub fn peer_identity(&self) -> Option<String> {
+        match self {
+            Self::Server(ServerLabels {
+                tls:
+                    Conditional::Some(tls::ServerTls::Established {
+                        client_id: Some(id),
+                        ..
+                    }),
+                ..
+            }) => Some(id.to_string()),
+            Self::OutboundClient(OutboundEndpointLabels {
+                server_id: Conditional::Some(tls::ClientTls { server_id, .. }),
+                ..
+            })
+            | Self::AggregatedOutboundClient {
+                server_id: Conditional::Some(tls::ClientTls { server_id, .. }),
+                ..
+            } => Some(server_id.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Describes the connection's TLS status, as in the `tls` label.
+    pub fn tls(&self) -> &'static str {
+        match self {
+            Self::Server(ServerLabels { tls, .. }) => match tls {
+                Conditional::None(tls::NoServerTls::Disabled) => "disabled",
+                Conditional::None(_) => "no_identity",
+                Conditional::Some(tls::ServerTls::Established { .. }) => "true",
+                Conditional::Some(tls::ServerTls::Passthru { .. }) => "opaque",
+            },
+            Self::OutboundClient(OutboundEndpointLabels { server_id, .. })
+            | Self::AggregatedOutboundClient { server_id, .. } => match server_id {
+                Conditional::None(tls::NoClientTls::Disabled) => "disabled",
+                Conditional::None(_) => "no_identity",
+                Conditional::Some(_) => "true",
+            },
+            Self::InboundClient => "no_identity",
+        }
+    }
 }
 
 impl FmtLabels for Key {
@@ -60,6 +146,18 @@ impl FmtLabels for Key {
                 endpoint.fmt_labels(f)
             }
 
+            Self::AggregatedOutboundClient {
+                server_id,
+                authority,
+            } => {
+                Direction::Out.fmt_labels(f)?;
+                write!(f, ",peer=\"dst\",")?;
+                if let Some(a) = authority.as_ref() {
+                    write!(f, "authority=\"{}\",", a)?;
+                }
+                TlsConnect(server_id).fmt_labels(f)
+            }
+
             Self::InboundClient => {
                 const NO_TLS: tls::client::ConditionalClientTls =
                     Conditional::None(tls::NoClientTls::Loopback);
@@ -72,17 +170,50 @@ impl FmtLabels for Key {
     }
 }
 
+// === impl Aggregation ===
+
+impl Aggregation {
+    /// Drops endpoint addresses from the key if its family is aggregated.
+    pub fn apply(&self, key: Key) -> Key {
+        match key {
+            Key::Server(mut l) => {
+                let aggregate = match l.direction {
+                    Direction::In => self.inbound_server,
+                    Direction::Out => self.outbound_server,
+                };
+                if aggregate {
+                    l.target_addr = None;
+                }
+                Key::Server(l)
+            }
+            Key::OutboundClient(OutboundEndpointLabels {
+                server_id,
+                authority,
+                ..
+            }) if self.outbound_client => Key::AggregatedOutboundClient {
+                server_id,
+                authority,
+            },
+            key => key,
+        }
+    }
+}
+
+// === impl ServerLabels ===
+
 impl ServerLabels {
     fn inbound(
         tls: tls::ConditionalServerTls,
         target_addr: SocketAddr,
         policy: PolicyServerLabel,
+        origin: Origin,
     ) -> Self {
         ServerLabels {
             direction: Direction::In,
             tls,
-            target_addr,
+            target_addr: Some(target_addr),
             policy: Some(policy),
+            origin: Some(origin),
         }
     }
 
@@ -90,8 +221,9 @@ impl ServerLabels {
         ServerLabels {
             direction: Direction::Out,
             tls: tls::ConditionalServerTls::None(tls::NoServerTls::Loopback),
-            target_addr,
+            target_addr: Some(target_addr),
             policy: None,
+            origin: None,
         }
     }
 }
@@ -100,11 +232,11 @@ impl FmtLabels for ServerLabels {
     fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.direction.fmt_labels(f)?;
         f.write_str(",peer=\"src\",")?;
-        (
-            (TargetAddr(self.target_addr), TlsAccept(&self.tls)),
-            self.policy.as_ref(),
-        )
-            .fmt_labels(f)?;
+        if let Some(addr) = self.target_addr {
+            TargetAddr(addr).fmt_labels(f)?;
+            f.write_str(",")?;
+        }
+        ((TlsAccept(&self.tls), self.policy.as_ref()), self.origin).fmt_labels(f)?;
 
         Ok(())
     }
@@ -164,15 +296,33 @@ impl<'t> FmtLabels for TlsConnect<'t> {
 
 // === impl TargetAddr ===
 
+impl TargetAddr {
+    /// Returns the target IP, rendering IPv4-mapped IPv6 addresses as IPv4 so
+    /// that the same endpoint is labeled identically on dual-stack sockets.
+    fn ip(&self) -> IpAddr {
+        match self.0.ip() {
+            IpAddr::V6(ip) => match ip.segments() {
+                [0, 0, 0, 0, 0, 0xffff, hi, lo] => {
+                    IpAddr::V4(Ipv4Addr::from((u32::from(hi) << 16) | u32::from(lo)))
+                }
+                _ => IpAddr::V6(ip),
+            },
+            ip => ip,
+        }
+    }
+}
+
 impl FmtLabels for TargetAddr {
     fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "target_addr=\"{}\",target_ip=\"{}\",target_port=\"{}\"",
-            self.0,
-            self.0.ip(),
-            self.0.port()
-        )
+        // The address is rendered without an IPv6 scope ID, which is specific
+        // to the local host.
+        let ip = self.ip();
+        let port = self.0.port();
+        match ip {
+            IpAddr::V4(_) => write!(f, "target_addr=\"{}:{}\"", ip, port)?,
+            IpAddr::V6(_) => write!(f, "target_addr=\"[{}]:{}\"", ip, port)?,
+        }
+        write!(f, ",target_ip=\"{}\",target_port=\"{}\"", ip, port)
     }
 }
 
@@ -195,13 +345,84 @@ mod tests {
             }),
             ([192, 0, 2, 4], 40000).into(),
             PolicyServerLabel("testserver".to_string()),
+            Origin::Cluster,
         );
         assert_eq!(
             labels.to_string(),
             "direction=\"inbound\",peer=\"src\",\
             target_addr=\"192.0.2.4:40000\",target_ip=\"192.0.2.4\",target_port=\"40000\",\
             tls=\"true\",client_id=\"foo.id.example.com\",\
-            srv_name=\"testserver\""
+            srv_name=\"testserver\",origin=\"cluster\""
+        );
+    }
+
+    #[test]
+    fn ipv6_target_labels() {
+        struct Labels(TargetAddr);
+        impl std::fmt::Display for Labels {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt_labels(f)
+            }
+        }
+
+        let v6 = std::net::SocketAddrV6::new("2001:db8::1".parse().unwrap(), 8080, 0, 3);
+        assert_eq!(
+            Labels(TargetAddr(v6.into())).to_string(),
+            "target_addr=\"[2001:db8::1]:8080\",target_ip=\"2001:db8::1\",target_port=\"8080\""
+        );
+
+        let mapped: SocketAddr = "[::ffff:192.0.2.4]:8080".parse().unwrap();
+        assert_eq!(
+            Labels(TargetAddr(mapped)).to_string(),
+            "target_addr=\"192.0.2.4:8080\",target_ip=\"192.0.2.4\",target_port=\"8080\""
+        );
+    }
+
+    #[test]
+    fn aggregated_labels() {
+        struct Labels(Key);
+        impl std::fmt::Display for Labels {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt_labels(f)
+            }
+        }
+
+        let aggregation = Aggregation {
+            inbound_server: false,
+            outbound_server: true,
+            outbound_client: true,
+        };
+
+        let server = aggregation.apply(Key::outbound_server(([192, 0, 2, 4], 40000).into()));
+        assert_eq!(server.target_addr(), None);
+        assert_eq!(
+            Labels(server).to_string(),
+            "direction=\"outbound\",peer=\"src\",\
+            tls=\"no_identity\",no_tls_reason=\"loopback\""
+        );
+
+        let client = aggregation.apply(Key::OutboundClient(OutboundEndpointLabels {
+            server_id: Conditional::Some(tls::ClientTls::from(tls::ServerId(
+                "foo.id.example.com".parse().unwrap(),
+            ))),
+            authority: Some("foo.ns.svc.cluster.local:8080".parse().unwrap()),
+            labels: Some("pod=\"foo-1\"".to_string()),
+            target_addr: ([192, 0, 2, 5], 8080).into(),
+        }));
+        assert_eq!(client.target_addr(), None);
+        assert_eq!(
+            Labels(client).to_string(),
+            "direction=\"outbound\",peer=\"dst\",\
+            authority=\"foo.ns.svc.cluster.local:8080\",\
+            tls=\"true\",server_id=\"foo.id.example.com\""
+        );
+
+        let inbound = Key::inbound_server(
+            tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello),
+            ([192, 0, 2, 4], 40000).into(),
+            PolicyServerLabel("testserver".to_string()),
+            Origin::Cluster,
         );
+        assert_eq!(aggregation.apply(inbound.clone()), inbound);
     }
 }
@@ -3,20 +3,45 @@ use linkerd_stack::{ExtractParam, Param};
 pub use linkerd_transport_metrics as metrics;
 use std::sync::Arc;
 
+pub mod accept;
+pub mod bandwidth;
 pub mod labels;
+pub mod origin;
+
+pub use self::origin::{Origin, OriginNetworks};
 
 #[derive(Clone, Debug)]
-pub struct Metrics(metrics::Registry<labels::Key>);
+pub struct Metrics {
+    registry: metrics::Registry<labels::Key>,
+    aggregation: labels::Aggregation,
+}
 
 impl Metrics {
     pub fn new(retain_idle: std::time::Duration) -> (Self, metrics::Report<labels::Key>) {
-        let (reg, report) = metrics::new(retain_idle);
-        (Self(reg), report)
+        let (registry, report) = metrics::new(retain_idle);
+        let metrics = Self {
+            registry,
+            aggregation: labels::Aggregation::default(),
+        };
+        (metrics, report)
+    }
+
+    /// Drops endpoint addresses from the labels of the configured families.
+    pub fn with_aggregation(self, aggregation: labels::Aggregation) -> Self {
+        Self {
+            aggregation,
+            ..self
+        }
+    }
+
+    /// Lists all currently-open connections.
+    pub fn connections(&self) -> Vec<(labels::Key, metrics::Connection)> {
+        self.registry.connections()
     }
 }
 
 impl<T: Param<labels::Key>> ExtractParam<Arc<metrics::Metrics>, T> for Metrics {
     fn extract_param(&self, t: &T) -> Arc<metrics::Metrics> {
-        self.0.metrics(t.param())
+        self.registry.metrics(self.aggregation.apply(t.param()))
     }
 }
@@ -0,0 +1,103 @@
+use crate::IpNet;
+use linkerd_metrics::FmtLabels;
+use std::{collections::HashSet, fmt, net::IpAddr, sync::Arc};
+
+/// Describes the network from which an inbound connection originated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Origin {
+    /// The client is on the same node as the proxy (or is the local host).
+    NodeLocal,
+    /// The client is within one of the cluster's pod networks.
+    Cluster,
+    /// The client is outside of all configured networks.
+    External,
+}
+
+/// Classifies client addresses into an [`Origin`].
+#[derive(Clone, Debug, Default)]
+pub struct OriginNetworks {
+    node_local: Arc<[IpNet]>,
+    cluster: Arc<[IpNet]>,
+}
+
+// === impl Origin ===
+
+impl Origin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NodeLocal => "node_local",
+            Self::Cluster => "cluster",
+            Self::External => "external",
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FmtLabels for Origin {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "origin=\"{}\"", self.as_str())
+    }
+}
+
+// === impl OriginNetworks ===
+
+impl OriginNetworks {
+    pub fn new(node_local: HashSet<IpNet>, cluster: HashSet<IpNet>) -> Self {
+        Self {
+            node_local: node_local.into_iter().collect::<Vec<_>>().into(),
+            cluster: cluster.into_iter().collect::<Vec<_>>().into(),
+        }
+    }
+
+    /// Returns the networks of the local node.
+    pub fn node_local(&self) -> &[IpNet] {
+        &*self.node_local
+    }
+
+    /// Returns the networks of the cluster.
+    pub fn cluster(&self) -> &[IpNet] {
+        &*self.cluster
+    }
+
+    /// Classifies a client address.
+    ///
+    /// Node-local networks take precedence over cluster networks, since a
+    /// node's pod CIDR is typically contained within the cluster's.
+    pub fn classify(&self, ip: IpAddr) -> Origin {
+        if ip.is_loopback() || self.node_local.iter().any(|net| net.contains(&ip)) {
+            return Origin::NodeLocal;
+        }
+        if self.cluster.iter().any(|net| net.contains(&ip)) {
+            return Origin::Cluster;
+        }
+        Origin::External
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify() {
+        let nets = |ns: &[&str]| ns.iter().map(|n| n.parse().unwrap()).collect();
+        let networks = OriginNetworks::new(nets(&["10.0.1.0/24"]), nets(&["10.0.0.0/16"]));
+        let classify = |ip: &str| networks.classify(ip.parse().unwrap());
+
+        assert_eq!(classify("127.0.0.1"), Origin::NodeLocal);
+        assert_eq!(classify("::1"), Origin::NodeLocal);
+        assert_eq!(classify("10.0.1.12"), Origin::NodeLocal);
+        assert_eq!(classify("10.0.2.12"), Origin::Cluster);
+        assert_eq!(classify("192.0.2.12"), Origin::External);
+
+        assert_eq!(
+            OriginNetworks::default().classify("10.0.1.12".parse().unwrap()),
+            Origin::External
+        );
+    }
+}
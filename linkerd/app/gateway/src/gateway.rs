@@ -102,6 +102,7 @@ where
                 profile,
                 protocol: http.version,
                 logical_addr,
+                rewritten_from: None,
             }));
 
         Gateway::new(svc, http.target, local_id)
@@ -79,11 +79,12 @@ pub fn stack<I, O, P, R>(
     resolve: R,
 ) -> svc::BoxNewTcp<GatewayConnection, I>
 where
-    I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + fmt::Debug + Send + Sync + Unpin + 'static,
+    I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + io::Splice,
+    I: fmt::Debug + Send + Sync + Unpin + 'static,
     O: Clone + Send + Sync + Unpin + 'static,
     O: svc::Service<outbound::tcp::Connect, Error = io::Error>,
-    O::Response:
-        io::AsyncRead + io::AsyncWrite + tls::HasNegotiatedProtocol + Send + Unpin + 'static,
+    O::Response: io::AsyncRead + io::AsyncWrite + io::Splice + tls::HasNegotiatedProtocol,
+    O::Response: Send + Unpin + 'static,
     O::Future: Send + Unpin + 'static,
     P: profiles::GetProfile<profiles::LookupAddr> + Clone + Send + Sync + Unpin + 'static,
     P::Future: Send + 'static,
@@ -148,6 +149,7 @@ where
                     profile,
                     protocol: (),
                     logical_addr,
+                    rewritten_from: None,
                 }))
             },
             logical.into_inner(),
@@ -299,6 +301,12 @@ impl Param<Option<identity::Name>> for HttpTransportHeader {
     }
 }
 
+impl Param<Option<tls::ClientCertHash>> for HttpTransportHeader {
+    fn param(&self) -> Option<tls::ClientCertHash> {
+        None
+    }
+}
+
 impl Param<http::Version> for HttpTransportHeader {
     fn param(&self) -> http::Version {
         self.version
@@ -376,6 +384,12 @@ impl Param<Option<identity::Name>> for HttpLegacy {
     }
 }
 
+impl Param<Option<tls::ClientCertHash>> for HttpLegacy {
+    fn param(&self) -> Option<tls::ClientCertHash> {
+        None
+    }
+}
+
 impl Param<http::Version> for HttpLegacy {
     fn param(&self) -> http::Version {
         self.version
@@ -4,7 +4,10 @@ use crate::{
 };
 use linkerd_app_core::{
     io, svc,
-    transport::addrs::{ClientAddr, OrigDstAddr, Remote},
+    transport::{
+        addrs::{ClientAddr, OrigDstAddr, Remote},
+        Origin,
+    },
     Error,
 };
 use std::fmt::Debug;
@@ -14,6 +17,7 @@ use tracing::info_span;
 pub(crate) struct Accept {
     client_addr: Remote<ClientAddr>,
     orig_dst_addr: OrigDstAddr,
+    origin: Origin,
     policy: AllowPolicy,
 }
 
@@ -44,7 +48,8 @@ impl<N> Inbound<N> {
         DSvc::Error: Into<Error>,
         DSvc::Future: Send,
     {
-        self.map_stack(|_, rt, accept| {
+        self.map_stack(|cfg, rt, accept| {
+            let networks = cfg.origin_networks.clone();
             accept
                 .push_switch(
                     // Switch to the `direct` stack when a connection's original destination is the
@@ -57,10 +62,13 @@ impl<N> Inbound<N> {
                         }
                         let orig_dst_addr = t.param();
                         let policy = policies.check_policy(orig_dst_addr)?;
-                        tracing::debug!(?policy, "Accepted");
+                        let client_addr: Remote<ClientAddr> = t.param();
+                        let origin = networks.classify(client_addr.ip());
+                        tracing::debug!(?policy, %origin, "Accepted");
                         Ok(svc::Either::A(Accept {
-                            client_addr: t.param(),
+                            client_addr,
                             orig_dst_addr,
+                            origin,
                             policy,
                         }))
                     },
@@ -99,6 +107,12 @@ impl svc::Param<Remote<ClientAddr>> for Accept {
     }
 }
 
+impl svc::Param<Origin> for Accept {
+    fn param(&self) -> Origin {
+        self.origin
+    }
+}
+
 impl svc::Param<AllowPolicy> for Accept {
     fn param(&self) -> AllowPolicy {
         self.policy.clone()
@@ -128,6 +142,7 @@ mod tests {
                 authorizations: vec![Authorization {
                     authentication: Authentication::Unauthenticated,
                     networks: vec![Default::default()],
+                    routes: vec![],
                     name: "testsaz".to_string(),
                 }],
                 name: "testsrv".to_string(),
@@ -3,13 +3,13 @@ use crate::{
     Inbound,
 };
 use linkerd_app_core::{
-    detect, identity, io,
+    access_log, detect, identity, io,
     proxy::{http, identity::LocalCrtKey},
     svc, tls,
     transport::{
         self,
         addrs::{ClientAddr, OrigDstAddr, Remote},
-        ServerAddr,
+        Origin, ServerAddr,
     },
     Error, Infallible,
 };
@@ -19,6 +19,7 @@ use std::{fmt::Debug, time};
 pub(crate) struct Forward {
     client_addr: Remote<ClientAddr>,
     orig_dst_addr: OrigDstAddr,
+    origin: Origin,
     tls: tls::ConditionalServerTls,
     permit: Permit,
 }
@@ -33,8 +34,10 @@ pub(crate) struct Http {
 struct Tls {
     client_addr: Remote<ClientAddr>,
     orig_dst_addr: OrigDstAddr,
+    origin: Origin,
     status: tls::ConditionalServerTls,
     policy: AllowPolicy,
+    client_cert_hash: Option<tls::ClientCertHash>,
 }
 
 #[derive(Clone, Debug)]
@@ -63,8 +66,9 @@ impl<N> Inbound<N> {
     ) -> Inbound<svc::BoxNewTcp<T, I>>
     where
         T: svc::Param<OrigDstAddr> + svc::Param<Remote<ClientAddr>> + svc::Param<AllowPolicy>,
+        T: svc::Param<Origin>,
         T: Clone + Send + 'static,
-        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr,
+        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr + io::Splice,
         I: Debug + Send + Sync + Unpin + 'static,
         N: svc::NewService<Http, Service = NSvc>,
         N: Clone + Send + Sync + Unpin + 'static,
@@ -86,8 +90,9 @@ impl<N> Inbound<N> {
     fn push_detect_tls<T, I, NSvc, F, FSvc>(self, forward: F) -> Inbound<svc::BoxNewTcp<T, I>>
     where
         T: svc::Param<OrigDstAddr> + svc::Param<Remote<ClientAddr>> + svc::Param<AllowPolicy>,
+        T: svc::Param<Origin>,
         T: Clone + Send + 'static,
-        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr,
+        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr + io::Splice,
         I: Debug + Send + Sync + Unpin + 'static,
         N: svc::NewService<Tls, Service = NSvc>,
         N: Clone + Send + Sync + Unpin + 'static,
@@ -105,26 +110,37 @@ impl<N> Inbound<N> {
                 .push(policy::NewAuthorizeTcp::layer(rt.metrics.tcp_authz.clone()));
 
             let detect_timeout = cfg.proxy.detect_protocol_timeout;
+            let sni_routes = cfg.sni_routes.clone();
             detect
                 .check_new_service::<Tls, _>()
                 .push_switch(
                     // Ensure that the connection is authorized before proceeding with protocol
                     // detection.
-                    |(status, t): (tls::ConditionalServerTls, T)| -> Result<_, Infallible> {
+                    move |(client_cert_hash, (status, t)): (
+                        Option<tls::ClientCertHash>,
+                        (tls::ConditionalServerTls, T),
+                    )|
+                          -> Result<_, Infallible> {
+                        // Passthrough connections that are routed by SNI are forwarded without HTTP
+                        // detection.
+                        let routed = sni_routes.target(&status).is_some();
+
                         let policy: AllowPolicy = t.param();
                         let protocol = policy.protocol();
                         let tls = Tls {
                             client_addr: t.param(),
                             orig_dst_addr: t.param(),
+                            origin: t.param(),
                             status,
                             policy,
+                            client_cert_hash,
                         };
 
                         // If the port is configured to support application TLS, it may have also
                         // been wrapped in mesh identity. In any case, we don't actually validate
                         // whether app TLS was employed, but we use this as a signal that we should
                         // not perform additional protocol detection.
-                        if protocol == Protocol::Tls {
+                        if protocol == Protocol::Tls || routed {
                             return Ok(svc::Either::B(tls));
                         }
 
@@ -135,7 +151,11 @@ impl<N> Inbound<N> {
                         .push_on_service(svc::MapTargetLayer::new(io::BoxedIo::new))
                         .into_inner(),
                 )
-                .check_new_service::<(tls::ConditionalServerTls, T), _>()
+                .check_new_service::<(Option<tls::ClientCertHash>, (tls::ConditionalServerTls, T)), _>()
+                // Records the hash of each meshed client's certificate so that
+                // it may be passed to the application.
+                .push(tls::NewClientCertHash::layer())
+                .check_new_service::<(tls::ConditionalServerTls, T), tls::server::Io<I>>()
                 .push(tls::NewDetectTls::layer(TlsParams {
                     timeout: tls::server::Timeout(detect_timeout),
                     identity: rt.identity.clone(),
@@ -152,8 +172,10 @@ impl<N> Inbound<N> {
                             return Ok(svc::Either::B(Tls {
                                 client_addr: t.param(),
                                 orig_dst_addr: t.param(),
+                                origin: t.param(),
                                 status: TLS_PORT_SKIPPED,
                                 policy,
+                                client_cert_hash: None,
                             }));
                         }
                         Ok(svc::Either::A(t))
@@ -173,7 +195,7 @@ impl<N> Inbound<N> {
     /// passed to the provided 'forward' stack.
     fn push_detect_http<I, NSvc, F, FSvc>(self, forward: F) -> Inbound<svc::BoxNewTcp<Tls, I>>
     where
-        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr,
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + io::Splice,
         I: Debug + Send + Sync + Unpin + 'static,
         N: svc::NewService<Http, Service = NSvc> + Clone + Send + Sync + Unpin + 'static,
         NSvc: svc::Service<io::BoxedIo, Response = ()>,
@@ -259,24 +281,48 @@ impl From<(Permit, Tls)> for Forward {
         Self {
             client_addr: tls.client_addr,
             orig_dst_addr: tls.orig_dst_addr,
+            origin: tls.origin,
             tls: tls.status,
             permit,
         }
     }
 }
 
+impl svc::Param<tls::ConditionalServerTls> for Forward {
+    fn param(&self) -> tls::ConditionalServerTls {
+        self.tls.clone()
+    }
+}
+
 impl svc::Param<u16> for Forward {
     fn param(&self) -> u16 {
         self.orig_dst_addr.as_ref().port()
     }
 }
 
+impl svc::Param<access_log::ConnectionTarget> for Forward {
+    fn param(&self) -> access_log::ConnectionTarget {
+        let identity = self.tls.value().and_then(|server_tls| match server_tls {
+            tls::ServerTls::Established {
+                client_id: Some(id),
+                ..
+            } => Some(id.clone().0),
+            _ => None,
+        });
+        access_log::ConnectionTarget {
+            addr: self.orig_dst_addr.0.into(),
+            identity,
+        }
+    }
+}
+
 impl svc::Param<transport::labels::Key> for Forward {
     fn param(&self) -> transport::labels::Key {
         transport::labels::Key::inbound_server(
             self.tls.clone(),
             self.orig_dst_addr.into(),
             self.permit.labels.server.clone(),
+            self.origin,
         )
     }
 }
@@ -301,6 +347,12 @@ impl svc::Param<Remote<ClientAddr>> for Tls {
     }
 }
 
+impl svc::Param<Origin> for Tls {
+    fn param(&self) -> Origin {
+        self.origin
+    }
+}
+
 impl svc::Param<tls::ConditionalServerTls> for Tls {
     fn param(&self) -> tls::ConditionalServerTls {
         self.status.clone()
@@ -341,6 +393,12 @@ impl svc::Param<Remote<ClientAddr>> for Http {
     }
 }
 
+impl svc::Param<Origin> for Http {
+    fn param(&self) -> Origin {
+        self.tls.origin
+    }
+}
+
 impl svc::Param<tls::ConditionalServerTls> for Http {
     fn param(&self) -> tls::ConditionalServerTls {
         self.tls.status.clone()
@@ -371,6 +429,12 @@ impl svc::Param<Option<identity::Name>> for Http {
     }
 }
 
+impl svc::Param<Option<tls::ClientCertHash>> for Http {
+    fn param(&self) -> Option<tls::ClientCertHash> {
+        self.tls.client_cert_hash
+    }
+}
+
 impl svc::Param<AllowPolicy> for Http {
     fn param(&self) -> AllowPolicy {
         self.tls.policy.clone()
@@ -389,6 +453,7 @@ impl svc::Param<transport::labels::Key> for Http {
             self.tls.status.clone(),
             self.tls.orig_dst_addr.into(),
             self.tls.policy.server_label(),
+            self.tls.origin,
         )
     }
 }
@@ -442,6 +507,7 @@ mod tests {
                 authorizations: vec![Authorization {
                     authentication: Authentication::Unauthenticated,
                     networks: vec![client_addr().ip().into()],
+                    routes: vec![],
                     name: "testsaz".to_string(),
                 }],
                 name: "testsrv".to_string(),
@@ -472,6 +538,7 @@ mod tests {
         let target = Tls {
             client_addr: client_addr(),
             orig_dst_addr: orig_dst_addr(),
+            origin: Origin::External,
             status: tls::ConditionalServerTls::Some(tls::ServerTls::Established {
                 client_id: Some(client_id()),
                 negotiated_protocol: None,
@@ -479,6 +546,7 @@ mod tests {
             policy: allow(Protocol::Detect {
                 timeout: std::time::Duration::from_secs(10),
             }),
+            client_cert_hash: None,
         };
 
         let (ior, mut iow) = io::duplex(100);
@@ -501,6 +569,7 @@ mod tests {
         let target = Tls {
             client_addr: client_addr(),
             orig_dst_addr: orig_dst_addr(),
+            origin: Origin::External,
             status: tls::ConditionalServerTls::Some(tls::ServerTls::Established {
                 client_id: Some(client_id()),
                 negotiated_protocol: None,
@@ -508,6 +577,7 @@ mod tests {
             policy: allow(Protocol::Detect {
                 timeout: std::time::Duration::from_secs(10),
             }),
+            client_cert_hash: None,
         };
 
         let (ior, mut iow) = io::duplex(100);
@@ -529,11 +599,13 @@ mod tests {
         let target = Tls {
             client_addr: client_addr(),
             orig_dst_addr: orig_dst_addr(),
+            origin: Origin::External,
             status: tls::ConditionalServerTls::Some(tls::ServerTls::Established {
                 client_id: Some(client_id()),
                 negotiated_protocol: None,
             }),
             policy: allow(Protocol::Http1),
+            client_cert_hash: None,
         };
 
         let (ior, mut iow) = io::duplex(100);
@@ -555,11 +627,13 @@ mod tests {
         let target = Tls {
             client_addr: client_addr(),
             orig_dst_addr: orig_dst_addr(),
+            origin: Origin::External,
             status: tls::ConditionalServerTls::Some(tls::ServerTls::Established {
                 client_id: Some(client_id()),
                 negotiated_protocol: None,
             }),
             policy: allow(Protocol::Http1),
+            client_cert_hash: None,
         };
 
         let (ior, mut iow) = io::duplex(100);
@@ -581,11 +655,13 @@ mod tests {
         let target = Tls {
             client_addr: client_addr(),
             orig_dst_addr: orig_dst_addr(),
+            origin: Origin::External,
             status: tls::ConditionalServerTls::Some(tls::ServerTls::Established {
                 client_id: Some(client_id()),
                 negotiated_protocol: None,
             }),
             policy: allow(Protocol::Http2),
+            client_cert_hash: None,
         };
 
         let (ior, _) = io::duplex(100);
@@ -646,4 +722,10 @@ mod tests {
             client_addr()
         }
     }
+
+    impl svc::Param<Origin> for Target {
+        fn param(&self) -> Origin {
+            Origin::External
+        }
+    }
 }
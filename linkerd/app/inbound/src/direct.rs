@@ -1,10 +1,10 @@
 use crate::{policy, Inbound};
 use linkerd_app_core::{
-    io,
+    access_log, io,
     proxy::identity::LocalCrtKey,
     svc::{self, ExtractParam, InsertParam, Param},
     tls,
-    transport::{self, metrics::SensorIo, ClientAddr, OrigDstAddr, Remote},
+    transport::{self, metrics::SensorIo, ClientAddr, OrigDstAddr, Origin, Remote},
     transport_header::{self, NewTransportHeaderServer, SessionProtocol, TransportHeader},
     Conditional, Error, NameAddr, Result,
 };
@@ -32,6 +32,7 @@ struct RefusedNoTarget;
 pub(crate) struct Local {
     port: u16,
     client_id: tls::ClientId,
+    origin: Origin,
     permit: policy::Permit,
 }
 
@@ -64,6 +65,7 @@ pub struct ClientInfo {
     pub alpn: Option<tls::NegotiatedProtocol>,
     pub client_addr: Remote<ClientAddr>,
     pub local_addr: OrigDstAddr,
+    pub origin: Origin,
 }
 
 type FwdIo<I> = SensorIo<io::PrefixedIo<tls::server::Io<I>>>;
@@ -110,6 +112,7 @@ impl<N> Inbound<N> {
     {
         self.map_stack(|config, rt, inner| {
             let detect_timeout = config.proxy.detect_protocol_timeout;
+            let networks = config.origin_networks.clone();
 
             inner
                 .push(transport::metrics::NewServer::layer(
@@ -145,7 +148,7 @@ impl<N> Inbound<N> {
                                         },
                                     );
                                     let permit = allow.check_authorized(client.client_addr, &tls)?;
-                                    Ok(svc::Either::A(Local { port, permit, client_id: client.client_id, }))
+                                    Ok(svc::Either::A(Local { port, permit, client_id: client.client_id, origin: client.origin }))
                                 }
                                 TransportHeader {
                                     port,
@@ -219,7 +222,11 @@ impl<N> Inbound<N> {
                 .check_new_service::<ClientInfo, tls::server::Io<I>>()
                 // Build a ClientInfo target for each accepted connection. Refuse the
                 // connection if it doesn't include an mTLS identity.
-                .push_request_filter(ClientInfo::try_from)
+                .push_request_filter(move |(tls, t): (tls::ConditionalServerTls, T)| {
+                    let client_addr: Remote<ClientAddr> = t.param();
+                    let origin = networks.classify(client_addr.ip());
+                    ClientInfo::try_from((tls, t, origin))
+                })
                 .push(svc::BoxNewService::layer())
                 .push(tls::NewDetectTls::layer(TlsParams {
                     timeout: tls::server::Timeout(detect_timeout),
@@ -234,14 +241,16 @@ impl<N> Inbound<N> {
 
 // === impl ClientInfo ===
 
-impl<T> TryFrom<(tls::ConditionalServerTls, T)> for ClientInfo
+impl<T> TryFrom<(tls::ConditionalServerTls, T, Origin)> for ClientInfo
 where
     T: Param<OrigDstAddr>,
     T: Param<Remote<ClientAddr>>,
 {
     type Error = Error;
 
-    fn try_from((tls, addrs): (tls::ConditionalServerTls, T)) -> Result<Self, Self::Error> {
+    fn try_from(
+        (tls, addrs, origin): (tls::ConditionalServerTls, T, Origin),
+    ) -> Result<Self, Self::Error> {
         match tls {
             Conditional::Some(tls::ServerTls::Established {
                 client_id: Some(client_id),
@@ -251,6 +260,7 @@ where
                 alpn: negotiated_protocol,
                 client_addr: addrs.param(),
                 local_addr: addrs.param(),
+                origin,
             }),
             _ => Err(RefusedNoIdentity(()).into()),
         }
@@ -274,6 +284,16 @@ impl Param<u16> for Local {
     }
 }
 
+impl Param<access_log::ConnectionTarget> for Local {
+    fn param(&self) -> access_log::ConnectionTarget {
+        let addr: std::net::SocketAddr = ([127, 0, 0, 1], self.port).into();
+        access_log::ConnectionTarget {
+            addr: addr.into(),
+            identity: Some(self.client_id.0.clone()),
+        }
+    }
+}
+
 impl Param<transport::labels::Key> for Local {
     fn param(&self) -> transport::labels::Key {
         transport::labels::Key::inbound_server(
@@ -283,6 +303,7 @@ impl Param<transport::labels::Key> for Local {
             }),
             ([127, 0, 0, 1], self.port).into(),
             self.permit.labels.server.clone(),
+            self.origin,
         )
     }
 }
@@ -295,10 +316,17 @@ impl Param<transport::labels::Key> for GatewayTransportHeader {
             self.param(),
             self.client.local_addr.into(),
             self.policy.server_label(),
+            self.client.origin,
         )
     }
 }
 
+impl Param<Origin> for GatewayTransportHeader {
+    fn param(&self) -> Origin {
+        self.client.origin
+    }
+}
+
 impl Param<policy::AllowPolicy> for GatewayTransportHeader {
     fn param(&self) -> policy::AllowPolicy {
         self.policy.clone()
@@ -337,6 +365,7 @@ impl Param<transport::labels::Key> for Legacy {
             }),
             self.client.local_addr.into(),
             self.policy.server_label(),
+            self.client.origin,
         )
     }
 }
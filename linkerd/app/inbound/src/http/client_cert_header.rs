@@ -0,0 +1,180 @@
+//! Passes verified client certificate metadata to the application.
+//!
+//! When a header name is configured, meshed requests are annotated with an
+//! XFCC-style (`x-forwarded-client-cert`) value describing the client's
+//! verified certificate, e.g.
+//! `Hash=<hex SHA-256 digest>;DNS=web.ns.serviceaccount.identity.linkerd.cluster.local`.
+//! The header is always stripped from requests as they are received so that
+//! applications can rely on any value they observe having been set by the proxy.
+
+use linkerd_app_core::{identity, proxy::http, svc, tls};
+use std::task::{Context, Poll};
+use tracing::{debug, trace};
+
+#[derive(Clone, Debug)]
+pub struct NewSetClientCertHeader<N> {
+    inner: N,
+    header: Option<http::HeaderName>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SetClientCertHeader<S> {
+    inner: S,
+    header: Option<(http::HeaderName, Option<http::HeaderValue>)>,
+}
+
+// === impl NewSetClientCertHeader ===
+
+impl<N> NewSetClientCertHeader<N> {
+    pub fn layer(header: Option<http::HeaderName>) -> impl svc::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            header: header.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewSetClientCertHeader<N>
+where
+    T: svc::Param<Option<identity::Name>> + svc::Param<Option<tls::ClientCertHash>>,
+    N: svc::NewService<T>,
+{
+    type Service = SetClientCertHeader<N::Service>;
+
+    fn new_service(&mut self, t: T) -> Self::Service {
+        let header = self.header.clone().map(|name| {
+            let id: Option<identity::Name> = t.param();
+            let value = id.map(|id| {
+                let hash: Option<tls::ClientCertHash> = t.param();
+                let value = match hash {
+                    Some(hash) => format!("Hash={};DNS={}", hash, id.as_ref()),
+                    None => format!("DNS={}", id.as_ref()),
+                };
+                http::HeaderValue::from_str(&value).expect("identity must be a valid header value")
+            });
+            (name, value)
+        });
+        SetClientCertHeader {
+            header,
+            inner: self.inner.new_service(t),
+        }
+    }
+}
+
+// === impl SetClientCertHeader ===
+
+impl<S, B> svc::Service<http::Request<B>> for SetClientCertHeader<S>
+where
+    S: svc::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if let Some((ref name, ref value)) = self.header {
+            // Client-supplied values are never forwarded.
+            let prior = req.headers_mut().remove(name);
+            if let Some(value) = value.clone() {
+                trace!(header = %name, ?value, "Setting client certificate header");
+                req.headers_mut().insert(name.clone(), value);
+            }
+            if let Some(value) = prior {
+                debug!(header = %name, ?value, "Stripped client certificate header");
+            }
+        }
+
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::{
+        svc::{NewService, ServiceExt},
+        Error,
+    };
+
+    #[derive(Clone)]
+    struct Target(Option<identity::Name>, Option<tls::ClientCertHash>);
+
+    impl svc::Param<Option<identity::Name>> for Target {
+        fn param(&self) -> Option<identity::Name> {
+            self.0.clone()
+        }
+    }
+
+    impl svc::Param<Option<tls::ClientCertHash>> for Target {
+        fn param(&self) -> Option<tls::ClientCertHash> {
+            self.1
+        }
+    }
+
+    async fn call(target: Target, req: http::Request<()>) -> http::header::HeaderMap {
+        let mut new_svc = NewSetClientCertHeader {
+            inner: |_: Target| {
+                svc::mk(|req: http::Request<()>| {
+                    futures::future::ok::<_, Error>(req.headers().clone())
+                })
+            },
+            header: Some(http::HeaderName::from_static("x-forwarded-client-cert")),
+        };
+        new_svc.new_service(target).oneshot(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn sets_verified_identity() {
+        let id = "foo.ns.serviceaccount.identity.linkerd.cluster.local"
+            .parse::<identity::Name>()
+            .unwrap();
+        let req = http::Request::builder()
+            .header("x-forwarded-client-cert", "DNS=spoofed")
+            .header("x-forwarded-client-cert", "DNS=spoofed2")
+            .body(())
+            .unwrap();
+        let headers = call(Target(Some(id), None), req).await;
+        assert_eq!(
+            headers
+                .get_all("x-forwarded-client-cert")
+                .iter()
+                .collect::<Vec<_>>(),
+            vec!["DNS=foo.ns.serviceaccount.identity.linkerd.cluster.local"]
+        );
+    }
+
+    #[tokio::test]
+    async fn strips_unauthenticated() {
+        let req = http::Request::builder()
+            .header("x-forwarded-client-cert", "DNS=spoofed")
+            .body(())
+            .unwrap();
+        let headers = call(Target(None, None), req).await;
+        assert!(headers.get("x-forwarded-client-cert").is_none());
+    }
+
+    #[tokio::test]
+    async fn sets_certificate_hash() {
+        let id = "foo.ns.serviceaccount.identity.linkerd.cluster.local"
+            .parse::<identity::Name>()
+            .unwrap();
+        let hash = tls::ClientCertHash([0xab; 32]);
+        let req = http::Request::builder()
+            .header("x-forwarded-client-cert", "Hash=spoofed;DNS=spoofed")
+            .body(())
+            .unwrap();
+        let headers = call(Target(Some(id), Some(hash)), req).await;
+        assert_eq!(
+            headers.get("x-forwarded-client-cert").unwrap(),
+            &format!(
+                "Hash={};DNS=foo.ns.serviceaccount.identity.linkerd.cluster.local",
+                "ab".repeat(32)
+            )
+        );
+    }
+}
@@ -0,0 +1,599 @@
+//! Cross-origin resource sharing (CORS) for inbound HTTP requests.
+//!
+//! Servers (by port) and individual routes (by the route names of the inbound
+//! service's profile) may be configured with a CORS policy, so that
+//! applications need not implement CORS themselves. Preflight requests (i.e.
+//! `OPTIONS` requests with an `Origin` and an `Access-Control-Request-Method`)
+//! are answered by the proxy: if the origin and method are allowed, with a
+//! `204 No Content` response describing the policy; otherwise, with a `403
+//! Forbidden` response. Other requests from allowed origins are dispatched to
+//! the application, and its responses are given the policy's CORS headers.
+//! Requests without an `Origin` are not affected.
+//!
+//! Server policies are applied before requests are authorized, since browsers
+//! do not send credentials with preflight requests. Route policies can only be
+//! applied once the request's route is known, so, when any are configured,
+//! preflight requests bypass JWT validation and external authorization. Those
+//! that aren't answered by a route's policy are denied rather than being
+//! dispatched to the application.
+
+use futures::prelude::*;
+use linkerd_app_core::{
+    dst,
+    proxy::http,
+    svc::{self, Param},
+    transport::OrigDstAddr,
+    Error, NameAddr,
+};
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tracing::debug;
+
+/// Configures a CORS policy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cors {
+    /// The origins (e.g. `https://app.example.com`) from which requests are
+    /// allowed, or `*` to allow requests from all origins.
+    pub origins: Vec<String>,
+
+    /// The methods allowed on cross-origin requests.
+    pub methods: Vec<http::Method>,
+
+    /// The request headers allowed on cross-origin requests.
+    pub headers: Vec<http::HeaderName>,
+
+    /// The response headers exposed to cross-origin clients.
+    pub expose_headers: Vec<http::HeaderName>,
+
+    /// Whether cross-origin requests may include credentials.
+    pub allow_credentials: bool,
+
+    /// How long clients may cache preflight responses.
+    pub max_age: Option<Duration>,
+}
+
+/// CORS policies for inbound servers and routes.
+#[derive(Clone, Debug, Default)]
+pub struct CorsPolicies {
+    servers: Arc<HashMap<u16, Arc<Cors>>>,
+    routes: Arc<HashMap<NameAddr, Vec<(String, Arc<Cors>)>>>,
+}
+
+/// Applies the CORS policies of servers, by port.
+#[derive(Clone, Debug)]
+pub struct NewCors<N> {
+    inner: N,
+    policies: CorsPolicies,
+}
+
+/// Applies the CORS policies of routes.
+#[derive(Clone, Debug)]
+pub struct NewRouteCors<N> {
+    inner: N,
+    policies: CorsPolicies,
+}
+
+/// Applies a server's CORS policy (as a `Service`), or a route's policy (as a
+/// `Proxy`).
+#[derive(Clone, Debug)]
+pub struct HandleCors<S> {
+    inner: S,
+    cors: Option<Arc<Cors>>,
+}
+
+/// Marks a preflight request that bypassed authentication so that it may be
+/// answered by its route's policy.
+#[derive(Clone, Debug)]
+pub(crate) struct DeferredPreflight(());
+
+/// Denies deferred preflight requests that weren't answered by a route's
+/// policy.
+#[derive(Clone, Debug)]
+pub(crate) struct DenyDeferredPreflight<S> {
+    inner: S,
+}
+
+/// Describes how a request is handled.
+enum Handle<B> {
+    /// The request is dispatched without modification.
+    Forward,
+
+    /// The request is answered by the proxy.
+    Respond(http::Response<B>),
+
+    /// The request is dispatched and the response is given CORS headers for
+    /// the origin.
+    Dispatch(Arc<Cors>, http::HeaderValue),
+}
+
+type ResponseFuture<B> =
+    Pin<Box<dyn Future<Output = Result<http::Response<B>, Error>> + Send + 'static>>;
+
+// === impl Cors ===
+
+impl Cors {
+    fn allows_origin(&self, origin: &http::HeaderValue) -> bool {
+        let origin = match origin.to_str() {
+            Ok(origin) => origin,
+            Err(_) => return false,
+        };
+        self.origins
+            .iter()
+            .any(|o| o == "*" || o.eq_ignore_ascii_case(origin))
+    }
+
+    fn allows_any_origin(&self) -> bool {
+        self.origins.iter().any(|o| o == "*")
+    }
+
+    fn handle<A, B: Default>(self: &Arc<Self>, req: &http::Request<A>) -> Handle<B> {
+        let origin = match req.headers().get(http::header::ORIGIN) {
+            Some(origin) => origin.clone(),
+            None => return Handle::Forward,
+        };
+        let requested_method = req
+            .headers()
+            .get(http::header::ACCESS_CONTROL_REQUEST_METHOD);
+
+        match requested_method {
+            Some(method) if req.method() == http::Method::OPTIONS => {
+                let allowed = self.allows_origin(&origin)
+                    && self
+                        .methods
+                        .iter()
+                        .any(|m| m.as_str().as_bytes() == method.as_bytes());
+                if !allowed {
+                    debug!(?origin, ?method, "Preflight request denied");
+                    let mut rsp = http::Response::new(B::default());
+                    *rsp.status_mut() = http::StatusCode::FORBIDDEN;
+                    return Handle::Respond(rsp);
+                }
+                Handle::Respond(self.preflight(origin))
+            }
+            _ if self.allows_origin(&origin) => Handle::Dispatch(self.clone(), origin),
+            _ => Handle::Forward,
+        }
+    }
+
+    fn preflight<B: Default>(&self, origin: http::HeaderValue) -> http::Response<B> {
+        let mut rsp = http::Response::new(B::default());
+        *rsp.status_mut() = http::StatusCode::NO_CONTENT;
+        self.set_origin(rsp.headers_mut(), origin);
+        let headers = rsp.headers_mut();
+        if let Some(methods) = join(self.methods.iter().map(http::Method::as_str)) {
+            headers.insert(http::header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+        if let Some(allowed) = join(self.headers.iter().map(http::HeaderName::as_str)) {
+            headers.insert(http::header::ACCESS_CONTROL_ALLOW_HEADERS, allowed);
+        }
+        if let Some(max_age) = self.max_age {
+            headers.insert(
+                http::header::ACCESS_CONTROL_MAX_AGE,
+                http::HeaderValue::from(max_age.as_secs()),
+            );
+        }
+        rsp
+    }
+
+    /// Sets the CORS headers of a response to a request from the origin.
+    fn set_headers(&self, headers: &mut http::header::HeaderMap, origin: http::HeaderValue) {
+        self.set_origin(headers, origin);
+        if let Some(exposed) = join(self.expose_headers.iter().map(http::HeaderName::as_str)) {
+            headers.insert(http::header::ACCESS_CONTROL_EXPOSE_HEADERS, exposed);
+        }
+    }
+
+    fn set_origin(&self, headers: &mut http::header::HeaderMap, origin: http::HeaderValue) {
+        // Credentialed requests may not use the wildcard origin, so the
+        // request's origin is echoed unless all origins are allowed without
+        // credentials.
+        if self.allows_any_origin() && !self.allow_credentials {
+            headers.insert(
+                http::header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                http::HeaderValue::from_static("*"),
+            );
+        } else {
+            headers.insert(http::header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+            headers.append(http::header::VARY, http::HeaderValue::from_static("origin"));
+        }
+        if self.allow_credentials {
+            headers.insert(
+                http::header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                http::HeaderValue::from_static("true"),
+            );
+        }
+    }
+}
+
+/// Marks a preflight request so that it bypasses authentication, returning
+/// true if the request is a preflight request.
+pub(crate) fn defer_preflight<B>(req: &mut http::Request<B>) -> bool {
+    let is_preflight = req.method() == http::Method::OPTIONS
+        && req.headers().contains_key(http::header::ORIGIN)
+        && req
+            .headers()
+            .contains_key(http::header::ACCESS_CONTROL_REQUEST_METHOD);
+    if is_preflight {
+        req.extensions_mut().insert(DeferredPreflight(()));
+    }
+    is_preflight
+}
+
+fn join<'a>(values: impl Iterator<Item = &'a str>) -> Option<http::HeaderValue> {
+    let joined = values.collect::<Vec<_>>().join(", ");
+    if joined.is_empty() {
+        return None;
+    }
+    http::HeaderValue::from_str(&joined).ok()
+}
+
+// === impl CorsPolicies ===
+
+impl CorsPolicies {
+    pub fn new(
+        servers: impl IntoIterator<Item = (u16, Cors)>,
+        routes: impl IntoIterator<Item = (NameAddr, String, Cors)>,
+    ) -> Self {
+        let servers = servers
+            .into_iter()
+            .map(|(port, cors)| (port, Arc::new(cors)))
+            .collect();
+        let mut by_addr = HashMap::<_, Vec<_>>::new();
+        for (addr, route, cors) in routes {
+            by_addr
+                .entry(addr)
+                .or_default()
+                .push((route, Arc::new(cors)));
+        }
+        Self {
+            servers: Arc::new(servers),
+            routes: Arc::new(by_addr),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty() && self.routes.is_empty()
+    }
+
+    /// Returns true if preflight requests must bypass authentication to be
+    /// answered by routes' policies.
+    pub(crate) fn defers_preflights(&self) -> bool {
+        !self.routes.is_empty()
+    }
+
+    /// Returns the policy of the server on the given port, if any.
+    pub fn server(&self, port: u16) -> Option<&Cors> {
+        self.servers.get(&port).map(|c| &**c)
+    }
+
+    /// Returns the policy of the named route of the given service, if any.
+    pub fn route(&self, addr: &NameAddr, route: &str) -> Option<&Cors> {
+        self.route_policy(addr, route).map(|c| &**c)
+    }
+
+    fn route_policy(&self, addr: &NameAddr, route: &str) -> Option<&Arc<Cors>> {
+        self.routes
+            .get(addr)?
+            .iter()
+            .find(|(r, _)| r == route)
+            .map(|(_, cors)| cors)
+    }
+}
+
+// === impl NewCors ===
+
+impl<N> NewCors<N> {
+    pub fn layer(policies: CorsPolicies) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            policies: policies.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewCors<N>
+where
+    T: Param<OrigDstAddr>,
+    N: svc::NewService<T>,
+{
+    type Service = HandleCors<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let OrigDstAddr(addr) = target.param();
+        let cors = self.policies.servers.get(&addr.port()).cloned();
+        HandleCors {
+            inner: self.inner.new_service(target),
+            cors,
+        }
+    }
+}
+
+// === impl NewRouteCors ===
+
+impl<N> NewRouteCors<N> {
+    pub fn layer(policies: CorsPolicies) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            policies: policies.clone(),
+        })
+    }
+}
+
+impl<N> svc::NewService<dst::Route> for NewRouteCors<N>
+where
+    N: svc::NewService<dst::Route>,
+{
+    type Service = HandleCors<N::Service>;
+
+    fn new_service(&mut self, route: dst::Route) -> Self::Service {
+        let cors = route
+            .route
+            .labels()
+            .get("route")
+            .and_then(|name| self.policies.route_policy(&route.addr.0, name))
+            .cloned();
+        HandleCors {
+            inner: self.inner.new_service(route),
+            cors,
+        }
+    }
+}
+
+// === impl HandleCors ===
+
+impl<S> HandleCors<S> {
+    fn handle<A, B: Default>(&self, req: &http::Request<A>) -> Handle<B> {
+        match self.cors.as_ref() {
+            Some(cors) => cors.handle(req),
+            None => Handle::Forward,
+        }
+    }
+}
+
+fn dispatch<B, F>(rsp: F, cors: Arc<Cors>, origin: http::HeaderValue) -> ResponseFuture<B>
+where
+    F: TryFuture<Ok = http::Response<B>> + Send + 'static,
+    F::Error: Into<Error>,
+{
+    Box::pin(async move {
+        let mut rsp = rsp.into_future().await.map_err(Into::into)?;
+        cors.set_headers(rsp.headers_mut(), origin);
+        Ok(rsp)
+    })
+}
+
+impl<A, B, S> svc::Service<http::Request<A>> for HandleCors<S>
+where
+    B: Default + Send + 'static,
+    S: svc::Service<http::Request<A>, Response = http::Response<B>>,
+    S::Error: Into<Error>,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<B>;
+    type Error = Error;
+    type Future = future::Either<future::ErrInto<S::Future, Error>, ResponseFuture<B>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        match self.handle(&req) {
+            Handle::Forward => future::Either::Left(self.inner.call(req).err_into()),
+            Handle::Respond(rsp) => future::Either::Right(Box::pin(future::ok(rsp))),
+            Handle::Dispatch(cors, origin) => {
+                future::Either::Right(dispatch(self.inner.call(req), cors, origin))
+            }
+        }
+    }
+}
+
+impl<A, B, S, P> svc::Proxy<http::Request<A>, S> for HandleCors<P>
+where
+    B: Default + Send + 'static,
+    P: svc::Proxy<http::Request<A>, S, Response = http::Response<B>>,
+    P::Error: Into<Error>,
+    P::Future: Send + 'static,
+    S: svc::Service<P::Request>,
+{
+    type Request = P::Request;
+    type Response = http::Response<B>;
+    type Error = Error;
+    type Future = future::Either<future::ErrInto<P::Future, Error>, ResponseFuture<B>>;
+
+    fn proxy(&self, svc: &mut S, req: http::Request<A>) -> Self::Future {
+        match self.handle(&req) {
+            Handle::Forward => future::Either::Left(self.inner.proxy(svc, req).err_into()),
+            Handle::Respond(rsp) => future::Either::Right(Box::pin(future::ok(rsp))),
+            Handle::Dispatch(cors, origin) => {
+                future::Either::Right(dispatch(self.inner.proxy(svc, req), cors, origin))
+            }
+        }
+    }
+}
+
+// === impl DenyDeferredPreflight ===
+
+impl<S> DenyDeferredPreflight<S> {
+    pub(crate) fn layer() -> impl svc::layer::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<A, B, S> svc::Service<http::Request<A>> for DenyDeferredPreflight<S>
+where
+    B: Default,
+    S: svc::Service<http::Request<A>, Response = http::Response<B>>,
+{
+    type Response = http::Response<B>;
+    type Error = S::Error;
+    type Future = future::Either<S::Future, future::Ready<Result<http::Response<B>, S::Error>>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        if req.extensions().get::<DeferredPreflight>().is_some() {
+            debug!("Preflight request not answered by a route's policy");
+            let mut rsp = http::Response::new(B::default());
+            *rsp.status_mut() = http::StatusCode::FORBIDDEN;
+            return future::Either::Right(future::ok(rsp));
+        }
+        future::Either::Left(self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::{layer::Layer, NewService, ServiceExt};
+
+    fn cors() -> Cors {
+        Cors {
+            origins: vec!["https://app.example.com".to_string()],
+            methods: vec![http::Method::GET, http::Method::POST],
+            headers: vec![http::header::CONTENT_TYPE],
+            expose_headers: vec![http::HeaderName::from_static("x-request-id")],
+            allow_credentials: true,
+            max_age: Some(Duration::from_secs(600)),
+        }
+    }
+
+    async fn send(cors: Cors, req: http::Request<()>) -> http::Response<()> {
+        let mut stack = NewCors {
+            inner: |_: OrigDstAddr| {
+                svc::mk(|_: http::Request<()>| future::ok::<_, Error>(http::Response::new(())))
+            },
+            policies: CorsPolicies::new(Some((8080, cors)), None),
+        };
+        stack
+            .new_service(OrigDstAddr(([192, 0, 2, 3], 8080).into()))
+            .oneshot(req)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn answers_preflights() {
+        let req = http::Request::builder()
+            .method(http::Method::OPTIONS)
+            .header(http::header::ORIGIN, "https://app.example.com")
+            .header(http::header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(())
+            .unwrap();
+        let rsp = send(cors(), req).await;
+        assert_eq!(rsp.status(), http::StatusCode::NO_CONTENT);
+        let headers = rsp.headers();
+        assert_eq!(
+            headers[http::header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(
+            headers[http::header::ACCESS_CONTROL_ALLOW_METHODS],
+            "GET, POST"
+        );
+        assert_eq!(
+            headers[http::header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "content-type"
+        );
+        assert_eq!(
+            headers[http::header::ACCESS_CONTROL_ALLOW_CREDENTIALS],
+            "true"
+        );
+        assert_eq!(headers[http::header::ACCESS_CONTROL_MAX_AGE], "600");
+
+        for (origin, method) in &[
+            ("https://evil.example.com", "POST"),
+            ("https://app.example.com", "DELETE"),
+        ] {
+            let req = http::Request::builder()
+                .method(http::Method::OPTIONS)
+                .header(http::header::ORIGIN, *origin)
+                .header(http::header::ACCESS_CONTROL_REQUEST_METHOD, *method)
+                .body(())
+                .unwrap();
+            let rsp = send(cors(), req).await;
+            assert_eq!(rsp.status(), http::StatusCode::FORBIDDEN);
+            assert!(rsp
+                .headers()
+                .get(http::header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn sets_response_headers() {
+        let req = http::Request::builder()
+            .header(http::header::ORIGIN, "https://app.example.com")
+            .body(())
+            .unwrap();
+        let rsp = send(cors(), req).await;
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+        let headers = rsp.headers();
+        assert_eq!(
+            headers[http::header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(
+            headers[http::header::ACCESS_CONTROL_EXPOSE_HEADERS],
+            "x-request-id"
+        );
+        assert_eq!(headers[http::header::VARY], "origin");
+
+        // All origins may be allowed.
+        let any = Cors {
+            origins: vec!["*".to_string()],
+            allow_credentials: false,
+            ..cors()
+        };
+        let req = http::Request::builder()
+            .header(http::header::ORIGIN, "https://other.example.com")
+            .body(())
+            .unwrap();
+        let rsp = send(any, req).await;
+        assert_eq!(
+            rsp.headers()[http::header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "*"
+        );
+
+        // Requests without an origin are unaffected.
+        let rsp = send(cors(), http::Request::new(())).await;
+        assert!(rsp
+            .headers()
+            .get(http::header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn denies_unanswered_deferred_preflights() {
+        let mut deny = DenyDeferredPreflight::layer().layer(svc::mk(|_: http::Request<()>| {
+            future::ok::<_, Error>(http::Response::new(()))
+        }));
+
+        let mut req = http::Request::builder()
+            .method(http::Method::OPTIONS)
+            .header(http::header::ORIGIN, "https://app.example.com")
+            .header(http::header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(())
+            .unwrap();
+        assert!(defer_preflight(&mut req));
+        let rsp = (&mut deny).oneshot(req).await.unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::FORBIDDEN);
+
+        let mut req = http::Request::builder()
+            .method(http::Method::OPTIONS)
+            .body(())
+            .unwrap();
+        assert!(!defer_preflight(&mut req));
+        let rsp = (&mut deny).oneshot(req).await.unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+    }
+}
@@ -0,0 +1,395 @@
+//! Normalizes duplicate critical headers on inbound HTTP requests.
+//!
+//! RFC 7230 forbids a request from having more than one `Host` header, and
+//! permits repeated `Content-Length` values only when they're identical, but
+//! applications differ in how they handle requests that violate these rules.
+//! Servers (by port) may be configured to handle requests with duplicate
+//! `Host`, `Content-Length`, or `TE` headers consistently:
+//!
+//! - `merge` collapses identical `Host` and `Content-Length` values into a
+//!   single value and joins `TE` values into a single comma-separated list.
+//!   Requests with conflicting `Host` or `Content-Length` values are rejected.
+//! - `reject` fails requests with any duplicate critical header.
+//! - `first-wins` keeps the first value of each critical header and drops the
+//!   rest.
+//!
+//! Rejected requests are answered with a `400 Bad Request` response. Each
+//! violation is counted by the `inbound_http_duplicate_headers_total` metric,
+//! labeled by the server's address, the header, and the action taken.
+
+use futures::prelude::*;
+use linkerd_app_core::{
+    metrics::{metrics, Counter, FmtLabels, FmtMetrics},
+    proxy::http,
+    svc::{self, Param},
+    transport::{labels::TargetAddr, OrigDstAddr},
+    Error,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tracing::debug;
+
+metrics! {
+    inbound_http_duplicate_headers_total: Counter {
+        "The total number of inbound HTTP requests with duplicate critical headers"
+    }
+}
+
+/// The headers that are normalized.
+const HEADERS: [http::header::HeaderName; 3] = [
+    http::header::HOST,
+    http::header::CONTENT_LENGTH,
+    http::header::TE,
+];
+
+/// Determines how a server handles duplicate critical headers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DuplicateHeaderMode {
+    Merge,
+    Reject,
+    FirstWins,
+}
+
+/// The duplicate header modes of inbound servers.
+#[derive(Clone, Debug, Default)]
+pub struct DuplicateHeaders {
+    default: Option<DuplicateHeaderMode>,
+    servers: Arc<HashMap<u16, DuplicateHeaderMode>>,
+}
+
+#[derive(Debug, Error)]
+#[error("request has duplicate {0} headers")]
+pub struct DuplicateHeader(http::header::HeaderName);
+
+#[derive(Clone, Debug, Default)]
+pub struct DuplicateHeaderMetrics(Arc<Mutex<HashMap<Violation, Counter>>>);
+
+/// Normalizes the duplicate headers of requests to servers, by port.
+#[derive(Clone, Debug)]
+pub struct NewNormalizeDuplicateHeaders<N> {
+    inner: N,
+    headers: DuplicateHeaders,
+    metrics: DuplicateHeaderMetrics,
+}
+
+#[derive(Clone, Debug)]
+pub struct NormalizeDuplicateHeaders<S> {
+    inner: S,
+    mode: Option<DuplicateHeaderMode>,
+    server: TargetAddr,
+    metrics: DuplicateHeaderMetrics,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Action {
+    Merged,
+    Rejected,
+    Dropped,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Violation {
+    server: TargetAddr,
+    header: http::header::HeaderName,
+    action: Action,
+}
+
+// === impl DuplicateHeaderMode ===
+
+impl std::str::FromStr for DuplicateHeaderMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "merge" => Ok(Self::Merge),
+            "reject" => Ok(Self::Reject),
+            "first-wins" => Ok(Self::FirstWins),
+            _ => Err(()),
+        }
+    }
+}
+
+// === impl DuplicateHeaders ===
+
+impl DuplicateHeaders {
+    /// Configures the modes of servers, by port, with a `default` mode for
+    /// servers that aren't configured. Requests to servers without a mode are
+    /// not modified.
+    pub fn new(
+        default: Option<DuplicateHeaderMode>,
+        servers: impl IntoIterator<Item = (u16, DuplicateHeaderMode)>,
+    ) -> Self {
+        Self {
+            default,
+            servers: Arc::new(servers.into_iter().collect()),
+        }
+    }
+
+    /// Returns the mode of the server on the given port, if any.
+    pub fn server(&self, port: u16) -> Option<DuplicateHeaderMode> {
+        self.servers.get(&port).copied().or(self.default)
+    }
+}
+
+// === impl DuplicateHeaderMetrics ===
+
+impl DuplicateHeaderMetrics {
+    fn incr(&self, server: TargetAddr, header: &http::header::HeaderName, action: Action) {
+        self.0
+            .lock()
+            .entry(Violation {
+                server,
+                header: header.clone(),
+                action,
+            })
+            .or_default()
+            .incr();
+    }
+}
+
+impl FmtMetrics for DuplicateHeaderMetrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics = self.0.lock();
+        if metrics.is_empty() {
+            return Ok(());
+        }
+        inbound_http_duplicate_headers_total.fmt_help(f)?;
+        inbound_http_duplicate_headers_total.fmt_scopes(f, metrics.iter(), |c| c)
+    }
+}
+
+impl FmtLabels for Violation {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.server.fmt_labels(f)?;
+        let action = match self.action {
+            Action::Merged => "merged",
+            Action::Rejected => "rejected",
+            Action::Dropped => "dropped",
+        };
+        write!(f, ",header=\"{}\",action=\"{}\"", self.header, action)
+    }
+}
+
+// === impl NewNormalizeDuplicateHeaders ===
+
+impl<N> NewNormalizeDuplicateHeaders<N> {
+    pub fn layer(
+        headers: DuplicateHeaders,
+        metrics: DuplicateHeaderMetrics,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            headers: headers.clone(),
+            metrics: metrics.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewNormalizeDuplicateHeaders<N>
+where
+    T: Param<OrigDstAddr>,
+    N: svc::NewService<T>,
+{
+    type Service = NormalizeDuplicateHeaders<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let OrigDstAddr(addr) = target.param();
+        NormalizeDuplicateHeaders {
+            mode: self.headers.server(addr.port()),
+            server: TargetAddr(addr),
+            inner: self.inner.new_service(target),
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+// === impl NormalizeDuplicateHeaders ===
+
+impl<S> NormalizeDuplicateHeaders<S> {
+    /// Normalizes the request's critical headers, returning an error if the
+    /// request must be rejected.
+    fn normalize(
+        &self,
+        mode: DuplicateHeaderMode,
+        headers: &mut ::http::HeaderMap,
+    ) -> Result<(), DuplicateHeader> {
+        for name in HEADERS.iter() {
+            let mut values = headers.get_all(name).iter();
+            let first = match (values.next(), values.next()) {
+                (Some(first), Some(_)) => first.clone(),
+                _ => continue,
+            };
+
+            let action = match mode {
+                DuplicateHeaderMode::Reject => Action::Rejected,
+                DuplicateHeaderMode::FirstWins => {
+                    headers.insert(name, first);
+                    Action::Dropped
+                }
+                DuplicateHeaderMode::Merge if *name == http::header::TE => {
+                    let merged = headers
+                        .get_all(name)
+                        .iter()
+                        .map(|v| v.as_bytes())
+                        .collect::<Vec<_>>()
+                        .join(&b", "[..]);
+                    match http::HeaderValue::from_bytes(&merged) {
+                        Ok(merged) => {
+                            headers.insert(name, merged);
+                            Action::Merged
+                        }
+                        Err(_) => Action::Rejected,
+                    }
+                }
+                DuplicateHeaderMode::Merge => {
+                    if headers.get_all(name).iter().all(|v| *v == first) {
+                        headers.insert(name, first);
+                        Action::Merged
+                    } else {
+                        Action::Rejected
+                    }
+                }
+            };
+
+            debug!(header = %name, ?action, "Duplicate header");
+            self.metrics.incr(self.server, name, action);
+            if action == Action::Rejected {
+                return Err(DuplicateHeader(name.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<B, S> svc::Service<http::Request<B>> for NormalizeDuplicateHeaders<S>
+where
+    S: svc::Service<http::Request<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<S::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if let Some(mode) = self.mode {
+            if let Err(e) = self.normalize(mode, req.headers_mut()) {
+                return future::Either::Right(future::err(e.into()));
+            }
+        }
+        future::Either::Left(self.inner.call(req).err_into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::{NewService, ServiceExt};
+
+    fn request(headers: &[(&http::header::HeaderName, &'static str)]) -> http::Request<()> {
+        let mut req = http::Request::new(());
+        for (name, value) in headers {
+            req.headers_mut()
+                .append(*name, http::HeaderValue::from_static(value));
+        }
+        req
+    }
+
+    async fn normalize(
+        mode: DuplicateHeaderMode,
+        req: http::Request<()>,
+    ) -> (Result<::http::HeaderMap, Error>, DuplicateHeaderMetrics) {
+        let metrics = DuplicateHeaderMetrics::default();
+        let mut stack = NewNormalizeDuplicateHeaders {
+            inner: |_: OrigDstAddr| {
+                svc::mk(|req: http::Request<()>| future::ok::<_, Error>(req.headers().clone()))
+            },
+            headers: DuplicateHeaders::new(None, Some((8080, mode))),
+            metrics: metrics.clone(),
+        };
+        let addr = OrigDstAddr(([192, 0, 2, 3], 8080).into());
+        let res = stack.new_service(addr).oneshot(req).await;
+        (res, metrics)
+    }
+
+    fn count(
+        metrics: &DuplicateHeaderMetrics,
+        header: http::header::HeaderName,
+        action: Action,
+    ) -> f64 {
+        let violation = Violation {
+            server: TargetAddr(([192, 0, 2, 3], 8080).into()),
+            header,
+            action,
+        };
+        metrics.0.lock().get(&violation).map_or(0.0, |c| c.value())
+    }
+
+    #[tokio::test]
+    async fn merges() {
+        use http::header::{CONTENT_LENGTH, HOST, TE};
+
+        let req = request(&[
+            (&HOST, "web.example.com"),
+            (&HOST, "web.example.com"),
+            (&TE, "trailers"),
+            (&TE, "gzip"),
+        ]);
+        let (res, metrics) = normalize(DuplicateHeaderMode::Merge, req).await;
+        let headers = res.unwrap();
+        assert_eq!(headers.get_all(HOST).iter().count(), 1);
+        assert_eq!(
+            headers.get_all(TE).iter().collect::<Vec<_>>(),
+            vec!["trailers, gzip"]
+        );
+        assert_eq!(count(&metrics, HOST, Action::Merged), 1.0);
+        assert_eq!(count(&metrics, TE, Action::Merged), 1.0);
+
+        let req = request(&[(&CONTENT_LENGTH, "10"), (&CONTENT_LENGTH, "11")]);
+        let (res, metrics) = normalize(DuplicateHeaderMode::Merge, req).await;
+        assert!(res.unwrap_err().is::<DuplicateHeader>());
+        assert_eq!(count(&metrics, CONTENT_LENGTH, Action::Rejected), 1.0);
+    }
+
+    #[tokio::test]
+    async fn rejects() {
+        use http::header::HOST;
+
+        let req = request(&[(&HOST, "web.example.com"), (&HOST, "web.example.com")]);
+        let (res, metrics) = normalize(DuplicateHeaderMode::Reject, req).await;
+        assert!(res.unwrap_err().is::<DuplicateHeader>());
+        assert_eq!(count(&metrics, HOST, Action::Rejected), 1.0);
+
+        let req = request(&[(&HOST, "web.example.com")]);
+        let (res, metrics) = normalize(DuplicateHeaderMode::Reject, req).await;
+        assert!(res.is_ok());
+        assert!(metrics.0.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn first_wins() {
+        use http::header::HOST;
+
+        let req = request(&[(&HOST, "web.example.com"), (&HOST, "evil.example.com")]);
+        let (res, metrics) = normalize(DuplicateHeaderMode::FirstWins, req).await;
+        let headers = res.unwrap();
+        assert_eq!(
+            headers.get_all(HOST).iter().collect::<Vec<_>>(),
+            vec!["web.example.com"]
+        );
+        assert_eq!(count(&metrics, HOST, Action::Dropped), 1.0);
+    }
+}
@@ -0,0 +1,136 @@
+//! Authorizes inbound HTTP requests with an external authorizer.
+//!
+//! Requests on servers for which the external authorizer is configured are
+//! described to it after they're authorized by the server's policy, so that
+//! the authorizer observes the route on which the request was permitted.
+//! Requests that the authorizer denies are not dispatched to the application.
+
+use super::cors;
+use crate::policy::Permit;
+use futures::prelude::*;
+use linkerd_app_core::{
+    ext_authz::{Client, Key},
+    proxy::http,
+    svc, tls, Error,
+};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Authorizes requests on the servers for which the client is configured.
+#[derive(Clone, Debug)]
+pub(crate) struct NewExtAuthz<N> {
+    inner: N,
+    client: Option<Client>,
+    defer_preflights: bool,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ExtAuthz<S> {
+    inner: S,
+    client: Client,
+    server: String,
+    principal: Option<String>,
+    route: Option<String>,
+    defer_preflights: bool,
+}
+
+type ResponseFuture<R> = Pin<Box<dyn Future<Output = Result<R, Error>> + Send + 'static>>;
+
+// === impl NewExtAuthz ===
+
+impl<N> NewExtAuthz<N> {
+    /// When `defer_preflights` is set, CORS preflight requests bypass the
+    /// authorizer so that they may be answered by their routes' CORS policies.
+    pub(crate) fn layer(
+        client: Option<Client>,
+        defer_preflights: bool,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            client: client.clone(),
+            defer_preflights,
+        })
+    }
+}
+
+impl<T, N> svc::NewService<(Permit, T)> for NewExtAuthz<N>
+where
+    T: svc::Param<tls::ConditionalServerTls>,
+    N: svc::NewService<(Permit, T)>,
+{
+    type Service = svc::Either<ExtAuthz<N::Service>, N::Service>;
+
+    fn new_service(&mut self, (permit, target): (Permit, T)) -> Self::Service {
+        let server = permit.labels.server.0.clone();
+        match self.client.as_ref() {
+            Some(client) if client.applies_to(&server) => {
+                let principal = match target.param() {
+                    tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                        client_id: Some(tls::server::ClientId(id)),
+                        ..
+                    }) => Some(id.as_ref().to_string()),
+                    _ => None,
+                };
+                svc::Either::A(ExtAuthz {
+                    client: client.clone(),
+                    server,
+                    principal,
+                    route: permit.labels.route.clone(),
+                    defer_preflights: self.defer_preflights,
+                    inner: self.inner.new_service((permit, target)),
+                })
+            }
+            _ => svc::Either::B(self.inner.new_service((permit, target))),
+        }
+    }
+}
+
+// === impl ExtAuthz ===
+
+impl<B, S> svc::Service<http::Request<B>> for ExtAuthz<S>
+where
+    B: Send + 'static,
+    S: svc::Service<http::Request<B>> + Clone + Send + 'static,
+    S::Error: Into<Error>,
+    S::Future: Send,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = ResponseFuture<S::Response>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if self.defer_preflights && cors::defer_preflight(&mut req) {
+            return Box::pin(self.inner.call(req).err_into::<Error>());
+        }
+
+        // Requests on routes that aren't named by the server's policy are
+        // cached by their method and path.
+        let route = self
+            .route
+            .clone()
+            .unwrap_or_else(|| format!("{} {}", req.method(), req.uri().path()));
+        let key = Key {
+            server: self.server.clone(),
+            principal: self.principal.clone(),
+            route,
+        };
+        let check = self.client.check(&req, key);
+
+        // The request is dispatched after the authorizer responds, so take the
+        // inner service (which is ready) and leave a clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let allow = check.await?;
+            allow.apply(req.headers_mut());
+            inner.call(req).err_into::<Error>().await
+        })
+    }
+}
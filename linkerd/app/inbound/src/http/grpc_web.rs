@@ -0,0 +1,327 @@
+//! Translates gRPC-Web requests into native gRPC for the local application.
+//!
+//! Browsers cannot send native gRPC requests, since they cannot read HTTP/2
+//! trailers. gRPC-Web requests (with an `application/grpc-web` or
+//! `application/grpc-web+proto` content type) use the same message framing as
+//! gRPC, so they are forwarded to the application as HTTP/2 gRPC requests. The
+//! application's response trailers are then encoded into the response body as
+//! a gRPC-Web trailers frame.
+//!
+//! The text (base64) encoding, `application/grpc-web-text`, is not translated.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::ready;
+use linkerd_app_core::{proxy::http, svc, Error};
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tracing::debug;
+
+/// The flag that marks a gRPC-Web frame as carrying trailers.
+const TRAILERS_FLAG: u8 = 0x80;
+
+const GRPC_STATUS: &str = "grpc-status";
+
+#[derive(Clone, Debug)]
+pub struct GrpcWeb<S> {
+    inner: S,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+
+    /// The original request's version, if the request was translated.
+    version: Option<::http::Version>,
+}
+
+/// Encodes the inner body's trailers as the final data frame.
+#[pin_project]
+struct ResponseBody {
+    #[pin]
+    inner: http::BoxBody,
+    trailers_sent: bool,
+}
+
+// === impl GrpcWeb ===
+
+impl<S> GrpcWeb<S> {
+    pub fn layer() -> impl svc::layer::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<S, B> svc::Service<http::Request<B>> for GrpcWeb<S>
+where
+    S: svc::Service<http::Request<B>, Response = http::Response<http::BoxBody>>,
+{
+    type Response = http::Response<http::BoxBody>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        let content_type = match grpc_content_type(&req) {
+            Some(ct) => ct,
+            None => {
+                return ResponseFuture {
+                    inner: self.inner.call(req),
+                    version: None,
+                }
+            }
+        };
+
+        let version = req.version();
+        debug!(?version, "Translating gRPC-Web request");
+        req.headers_mut().insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static(content_type),
+        );
+        req.headers_mut()
+            .insert(http::header::TE, http::HeaderValue::from_static("trailers"));
+        // transfer-encoding is illegal in HTTP2
+        req.headers_mut().remove(http::header::TRANSFER_ENCODING);
+        if req.uri().authority().is_none() {
+            set_authority(&mut req);
+        }
+        *req.version_mut() = ::http::Version::HTTP_2;
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            version: Some(version),
+        }
+    }
+}
+
+/// Returns the native gRPC content type for a gRPC-Web request.
+fn grpc_content_type<B>(req: &http::Request<B>) -> Option<&'static str> {
+    match req.headers().get(http::header::CONTENT_TYPE)?.as_bytes() {
+        b"application/grpc-web" => Some("application/grpc"),
+        b"application/grpc-web+proto" => Some("application/grpc+proto"),
+        _ => None,
+    }
+}
+
+/// Sets an HTTP/1 request's URI authority from its `host` header so that the
+/// request may be sent as an HTTP/2 request.
+fn set_authority<B>(req: &mut http::Request<B>) {
+    let authority = match http::authority_from_header(req, http::header::HOST) {
+        Some(authority) => authority,
+        None => return,
+    };
+    let mut parts = req.uri().clone().into_parts();
+    parts.scheme = Some(http::uri::Scheme::HTTP);
+    parts.authority = Some(authority);
+    if parts.path_and_query.is_none() {
+        parts.path_and_query = Some(http::uri::PathAndQuery::from_static("/"));
+    }
+    if let Ok(uri) = http::uri::Uri::from_parts(parts) {
+        *req.uri_mut() = uri;
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<http::Response<http::BoxBody>, E>>,
+{
+    type Output = Result<http::Response<http::BoxBody>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let rsp = ready!(this.inner.poll(cx))?;
+        let rsp = match this.version.take() {
+            Some(version) => to_grpc_web(rsp, version),
+            None => rsp,
+        };
+        Poll::Ready(Ok(rsp))
+    }
+}
+
+fn to_grpc_web(
+    rsp: http::Response<http::BoxBody>,
+    version: ::http::Version,
+) -> http::Response<http::BoxBody> {
+    let (mut parts, body) = rsp.into_parts();
+    parts.version = version;
+
+    let content_type = parts
+        .headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|ct| ct.strip_prefix("application/grpc"))
+        .filter(|subtype| subtype.is_empty() || subtype.starts_with('+'))
+        .and_then(|subtype| {
+            http::HeaderValue::from_str(&format!("application/grpc-web{}", subtype)).ok()
+        });
+    if let Some(content_type) = content_type {
+        parts
+            .headers
+            .insert(http::header::CONTENT_TYPE, content_type);
+    }
+
+    // Trailers-only responses carry their status in the response headers, so
+    // there are no trailers to encode.
+    if parts.headers.contains_key(GRPC_STATUS) {
+        return http::Response::from_parts(parts, body);
+    }
+
+    parts.headers.remove(http::header::CONTENT_LENGTH);
+    let body = http::BoxBody::new(ResponseBody {
+        inner: body,
+        trailers_sent: false,
+    });
+    http::Response::from_parts(parts, body)
+}
+
+// === impl ResponseBody ===
+
+impl http::HttpBody for ResponseBody {
+    type Data = Bytes;
+    type Error = Error;
+
+    fn is_end_stream(&self) -> bool {
+        self.trailers_sent
+    }
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let mut this = self.project();
+        if *this.trailers_sent {
+            return Poll::Ready(None);
+        }
+
+        if let Some(res) = ready!(this.inner.as_mut().poll_data(cx)) {
+            let data = res.map(|mut data| data.copy_to_bytes(data.remaining()));
+            return Poll::Ready(Some(data));
+        }
+
+        let trailers = match ready!(this.inner.poll_trailers(cx)) {
+            Ok(trailers) => trailers.unwrap_or_default(),
+            Err(e) => return Poll::Ready(Some(Err(e))),
+        };
+        *this.trailers_sent = true;
+        Poll::Ready(Some(Ok(encode_trailers(&trailers))))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::header::HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+}
+
+/// Encodes trailers as a gRPC-Web frame: a flag byte, a 4-byte length, and the
+/// trailers formatted as an HTTP/1 header block.
+fn encode_trailers(trailers: &http::header::HeaderMap) -> Bytes {
+    let mut block = BytesMut::new();
+    for (name, value) in trailers.iter() {
+        block.extend_from_slice(name.as_str().as_bytes());
+        block.extend_from_slice(b": ");
+        block.extend_from_slice(value.as_bytes());
+        block.extend_from_slice(b"\r\n");
+    }
+
+    let mut frame = BytesMut::with_capacity(5 + block.len());
+    frame.put_u8(TRAILERS_FLAG);
+    frame.put_u32(block.len() as u32);
+    frame.extend_from_slice(&block);
+    frame.freeze()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::ServiceExt;
+
+    async fn call(req: http::Request<()>) -> (http::Request<()>, http::Response<Bytes>) {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut tx = Some(tx);
+        let svc = GrpcWeb {
+            inner: svc::mk(move |req: http::Request<()>| {
+                let (mut body_tx, body) = hyper::Body::channel();
+                tokio::spawn(async move {
+                    body_tx
+                        .send_data(Bytes::from_static(b"\0\0\0\0\x01a"))
+                        .await?;
+                    let mut trailers = http::header::HeaderMap::new();
+                    trailers.insert(GRPC_STATUS, http::HeaderValue::from_static("0"));
+                    body_tx.send_trailers(trailers).await
+                });
+                let rsp = http::Response::builder()
+                    .header(http::header::CONTENT_TYPE, "application/grpc+proto")
+                    .body(http::BoxBody::new(body))
+                    .unwrap();
+                let _ = tx.take().unwrap().send(req);
+                futures::future::ok::<_, Error>(rsp)
+            }),
+        };
+        let rsp = svc.oneshot(req).await.unwrap();
+        let req = rx.await.unwrap();
+        let (parts, body) = rsp.into_parts();
+        let body = hyper::body::to_bytes(body).await.unwrap();
+        (req, http::Response::from_parts(parts, body))
+    }
+
+    #[tokio::test]
+    async fn translates_grpc_web() {
+        let req = http::Request::post("/svc.Greeter/Hello")
+            .version(::http::Version::HTTP_11)
+            .header(http::header::HOST, "greeter.ns.svc.cluster.local:8080")
+            .header(http::header::CONTENT_TYPE, "application/grpc-web+proto")
+            .body(())
+            .unwrap();
+        let (req, rsp) = call(req).await;
+
+        assert_eq!(req.version(), ::http::Version::HTTP_2);
+        assert_eq!(
+            req.uri().to_string(),
+            "http://greeter.ns.svc.cluster.local:8080/svc.Greeter/Hello"
+        );
+        assert_eq!(
+            req.headers()[http::header::CONTENT_TYPE],
+            "application/grpc+proto"
+        );
+        assert_eq!(req.headers()[http::header::TE], "trailers");
+
+        assert_eq!(rsp.version(), ::http::Version::HTTP_11);
+        assert_eq!(
+            rsp.headers()[http::header::CONTENT_TYPE],
+            "application/grpc-web+proto"
+        );
+        assert_eq!(
+            rsp.body(),
+            &Bytes::from_static(b"\0\0\0\0\x01a\x80\0\0\0\x10grpc-status: 0\r\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_native_grpc() {
+        let req = http::Request::post("http://greeter/svc.Greeter/Hello")
+            .version(::http::Version::HTTP_2)
+            .header(http::header::CONTENT_TYPE, "application/grpc")
+            .body(())
+            .unwrap();
+        let (req, rsp) = call(req).await;
+
+        assert!(req.headers().get(http::header::TE).is_none());
+        assert_eq!(
+            rsp.headers()[http::header::CONTENT_TYPE],
+            "application/grpc+proto"
+        );
+        assert_eq!(rsp.body(), &Bytes::from_static(b"\0\0\0\0\x01a"));
+    }
+}
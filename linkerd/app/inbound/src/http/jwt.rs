@@ -0,0 +1,835 @@
+//! Validates JSON Web Tokens on inbound HTTP requests.
+//!
+//! When configured, requests to the configured servers (by the names of their
+//! inbound policies, or to all servers if none are named) must carry a bearer
+//! token in the `authorization` header. The token's signature is verified
+//! against a JSON Web Key Set (JWKS) that is read from a file and re-read
+//! periodically in the background, so that rotated keys are honored. The token
+//! must not be expired and, if configured, must have been issued by the
+//! expected issuer for one of the expected audiences.
+//!
+//! Requests without a valid token are not dispatched to the application, and
+//! are answered with a `401 Unauthorized` response. These failures are not
+//! counted as proxy errors; they are counted by the
+//! `inbound_http_jwt_failures_total` metric instead.
+//!
+//! The values of a token's claims may be passed to the application in request
+//! headers. These headers are always removed from inbound requests so that
+//! clients cannot set them.
+
+use super::cors;
+use crate::policy::Permit;
+use futures::prelude::*;
+use linkerd_app_core::{
+    metrics::{metrics, Counter, FmtLabels, FmtMetrics, ServerLabel},
+    proxy::http,
+    svc, Error,
+};
+use parking_lot::Mutex;
+use ring::{hmac, signature};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+use tokio::{sync::watch, time};
+use tracing::{debug, info_span, warn, Instrument};
+
+metrics! {
+    inbound_http_jwt_failures_total: Counter {
+        "The total number of inbound HTTP requests that were rejected for lacking a valid JWT"
+    }
+}
+
+/// The clock skew tolerated when checking a token's `exp` and `nbf` claims.
+const LEEWAY: Duration = Duration::from_secs(60);
+
+/// Configures JWT validation.
+#[derive(Clone, Debug)]
+pub struct JwtConfig {
+    /// The path of a file holding the JSON Web Key Set with which tokens are
+    /// verified.
+    pub jwks: PathBuf,
+
+    /// The interval at which the JWKS file is re-read.
+    pub refresh: Duration,
+
+    /// When set, tokens must have been issued by this issuer.
+    pub issuer: Option<String>,
+
+    /// When not empty, tokens must have been issued for one of these
+    /// audiences.
+    pub audiences: HashSet<String>,
+
+    /// Passes the values of claims to the application in request headers.
+    pub claim_headers: Vec<(String, http::HeaderName)>,
+
+    /// The names of the servers on which tokens are validated. Tokens are
+    /// validated on all servers when empty.
+    pub servers: HashSet<String>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct JwtValidator {
+    config: Arc<JwtConfig>,
+    keys: watch::Receiver<Arc<Vec<Key>>>,
+}
+
+#[derive(Debug, Error)]
+#[error("invalid JWT: {0}")]
+pub struct InvalidJwt(Reason);
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct JwtMetrics(Arc<Mutex<HashMap<(ServerLabel, Reason), Counter>>>);
+
+/// Validates tokens on requests to the configured servers.
+#[derive(Clone, Debug)]
+pub(crate) struct NewValidateJwt<N> {
+    inner: N,
+    validator: Option<JwtValidator>,
+    metrics: JwtMetrics,
+    defer_preflights: bool,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ValidateJwt<S> {
+    inner: S,
+    validator: JwtValidator,
+    server: ServerLabel,
+    metrics: JwtMetrics,
+    defer_preflights: bool,
+}
+
+/// Removes claim headers from requests to servers on which tokens are not
+/// validated.
+#[derive(Clone, Debug)]
+pub(crate) struct StripClaimHeaders<S> {
+    inner: S,
+    config: Option<Arc<JwtConfig>>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Reason {
+    Missing,
+    Malformed,
+    UnknownKey,
+    Signature,
+    Expired,
+    Issuer,
+    Audience,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Algorithm {
+    Hs256,
+    Hs384,
+    Hs512,
+    Rs256,
+    Rs384,
+    Rs512,
+    Ps256,
+    Ps384,
+    Ps512,
+    Es256,
+    Es384,
+}
+
+#[derive(Clone, Debug)]
+struct Key {
+    id: Option<String>,
+    algorithm: Option<Algorithm>,
+    material: KeyMaterial,
+}
+
+#[derive(Clone, Debug)]
+enum KeyMaterial {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    P256(Vec<u8>),
+    P384(Vec<u8>),
+    Hmac(Vec<u8>),
+}
+
+#[derive(Debug, Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    alg: Option<String>,
+    #[serde(rename = "use")]
+    usage: Option<String>,
+    crv: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+    k: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+type Claims = serde_json::Map<String, serde_json::Value>;
+
+// === impl JwtConfig ===
+
+impl JwtConfig {
+    /// Spawns a task that reads the JWKS and periodically re-reads it.
+    ///
+    /// Tokens are rejected until the JWKS has been read. This must be called
+    /// on a Tokio runtime.
+    pub(crate) fn build(self) -> JwtValidator {
+        let (tx, rx) = watch::channel(Arc::new(vec![]));
+        tokio::spawn(refresh(self.jwks.clone(), self.refresh, tx).instrument(info_span!("jwks")));
+
+        JwtValidator {
+            config: Arc::new(self),
+            keys: rx,
+        }
+    }
+
+    fn applies_to(&self, server: &ServerLabel) -> bool {
+        self.servers.is_empty() || self.servers.contains(&server.0)
+    }
+
+    fn strip_claim_headers(&self, headers: &mut http::header::HeaderMap) {
+        for (_, header) in self.claim_headers.iter() {
+            headers.remove(header);
+        }
+    }
+}
+
+async fn refresh(path: PathBuf, interval: Duration, tx: watch::Sender<Arc<Vec<Key>>>) {
+    loop {
+        match read_jwks(&path).await {
+            Ok(keys) => {
+                debug!(keys = keys.len(), "Refreshed JWKS");
+                let _ = tx.send(Arc::new(keys));
+            }
+            Err(error) => {
+                warn!(%error, path = %path.display(), "Failed to refresh JWKS; retaining previous keys")
+            }
+        }
+
+        // Stop refreshing once all validators have been dropped.
+        if time::timeout(interval, tx.closed()).await.is_ok() {
+            return;
+        }
+    }
+}
+
+async fn read_jwks(path: &Path) -> Result<Vec<Key>, Error> {
+    let buf = tokio::fs::read(path).await?;
+    parse_jwks(&buf)
+}
+
+fn parse_jwks(buf: &[u8]) -> Result<Vec<Key>, Error> {
+    let JwkSet { keys } = serde_json::from_slice(buf)?;
+    let keys = keys
+        .into_iter()
+        .filter_map(|jwk| match Key::from_jwk(jwk) {
+            Ok(key) => key,
+            Err(error) => {
+                debug!(%error, "Ignoring unusable key");
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    if keys.is_empty() {
+        return Err("JWKS has no usable signing keys".into());
+    }
+    Ok(keys)
+}
+
+fn decode(b64: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    base64::decode_config(b64.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+}
+
+// === impl JwtValidator ===
+
+impl JwtValidator {
+    /// Validates the bearer token in the given headers, returning its claims.
+    fn validate(
+        &self,
+        headers: &http::header::HeaderMap,
+        now: SystemTime,
+    ) -> Result<Claims, Reason> {
+        let token = headers
+            .get(http::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| {
+                let mut parts = v.splitn(2, ' ');
+                let scheme = parts.next()?;
+                let token = parts.next()?.trim();
+                if scheme.eq_ignore_ascii_case("bearer") && !token.is_empty() {
+                    Some(token)
+                } else {
+                    None
+                }
+            })
+            .ok_or(Reason::Missing)?;
+        let keys = self.keys.borrow().clone();
+        validate_token(&*self.config, &*keys, token, now)
+    }
+
+    fn set_claim_headers(&self, headers: &mut http::header::HeaderMap, claims: &Claims) {
+        for (claim, header) in self.config.claim_headers.iter() {
+            let value = match claims.get(claim) {
+                None | Some(serde_json::Value::Null) => continue,
+                Some(serde_json::Value::String(s)) => http::HeaderValue::from_str(s),
+                Some(v) => http::HeaderValue::from_str(&v.to_string()),
+            };
+            match value {
+                Ok(value) => {
+                    headers.insert(header.clone(), value);
+                }
+                Err(_) => debug!(%claim, "Claim cannot be encoded as a header value"),
+            }
+        }
+    }
+}
+
+fn validate_token(
+    config: &JwtConfig,
+    keys: &[Key],
+    token: &str,
+    now: SystemTime,
+) -> Result<Claims, Reason> {
+    let mut segments = token.split('.');
+    let (header, payload, sig) = match (
+        segments.next(),
+        segments.next(),
+        segments.next(),
+        segments.next(),
+    ) {
+        (Some(h), Some(p), Some(s), None) => (h, p, s),
+        _ => return Err(Reason::Malformed),
+    };
+    let Header { alg, kid } = decode(header)
+        .ok()
+        .and_then(|h| serde_json::from_slice(&h).ok())
+        .ok_or(Reason::Malformed)?;
+    let alg = Algorithm::parse(&alg).ok_or(Reason::Signature)?;
+    let sig = decode(sig).map_err(|_| Reason::Malformed)?;
+
+    // The signed message is the token's encoded header and payload.
+    let msg = &token.as_bytes()[..header.len() + 1 + payload.len()];
+    let mut candidates = keys
+        .iter()
+        .filter(|k| kid.is_none() || k.id == kid)
+        .peekable();
+    if candidates.peek().is_none() {
+        return Err(Reason::UnknownKey);
+    }
+    if !candidates.any(|k| k.verify(alg, msg, &sig)) {
+        return Err(Reason::Signature);
+    }
+
+    let claims = decode(payload)
+        .ok()
+        .and_then(|p| serde_json::from_slice::<Claims>(&p).ok())
+        .ok_or(Reason::Malformed)?;
+
+    let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let leeway = LEEWAY.as_secs();
+    match claims.get("exp").and_then(|v| v.as_u64()) {
+        Some(exp) if now < exp.saturating_add(leeway) => {}
+        _ => return Err(Reason::Expired),
+    }
+    if let Some(nbf) = claims.get("nbf") {
+        match nbf.as_u64() {
+            Some(nbf) if nbf <= now.saturating_add(leeway) => {}
+            _ => return Err(Reason::Expired),
+        }
+    }
+
+    if let Some(issuer) = config.issuer.as_ref() {
+        if claims.get("iss").and_then(|v| v.as_str()) != Some(issuer.as_str()) {
+            return Err(Reason::Issuer);
+        }
+    }
+
+    if !config.audiences.is_empty() {
+        let permitted = match claims.get("aud") {
+            Some(serde_json::Value::String(aud)) => config.audiences.contains(aud),
+            Some(serde_json::Value::Array(auds)) => auds
+                .iter()
+                .filter_map(|v| v.as_str())
+                .any(|aud| config.audiences.contains(aud)),
+            _ => false,
+        };
+        if !permitted {
+            return Err(Reason::Audience);
+        }
+    }
+
+    Ok(claims)
+}
+
+// === impl Algorithm ===
+
+impl Algorithm {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "HS256" => Some(Self::Hs256),
+            "HS384" => Some(Self::Hs384),
+            "HS512" => Some(Self::Hs512),
+            "RS256" => Some(Self::Rs256),
+            "RS384" => Some(Self::Rs384),
+            "RS512" => Some(Self::Rs512),
+            "PS256" => Some(Self::Ps256),
+            "PS384" => Some(Self::Ps384),
+            "PS512" => Some(Self::Ps512),
+            "ES256" => Some(Self::Es256),
+            "ES384" => Some(Self::Es384),
+            _ => None,
+        }
+    }
+}
+
+// === impl Key ===
+
+impl Key {
+    /// Returns `None` for keys that are not used for signatures.
+    fn from_jwk(jwk: Jwk) -> Result<Option<Self>, Error> {
+        if jwk.usage.as_deref().map(|u| u != "sig").unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let algorithm = match jwk.alg.as_deref() {
+            Some(alg) => Some(
+                Algorithm::parse(alg).ok_or_else(|| format!("unsupported algorithm {}", alg))?,
+            ),
+            None => None,
+        };
+
+        let material = match (jwk.kty.as_str(), jwk.crv.as_deref()) {
+            ("RSA", _) => KeyMaterial::Rsa {
+                n: decode(jwk.n.as_deref().ok_or("RSA key missing modulus")?)?,
+                e: decode(jwk.e.as_deref().ok_or("RSA key missing exponent")?)?,
+            },
+            ("EC", Some(crv)) => {
+                // Keys are verified as uncompressed points.
+                let mut point = vec![0x04];
+                point.extend(decode(jwk.x.as_deref().ok_or("EC key missing x")?)?);
+                point.extend(decode(jwk.y.as_deref().ok_or("EC key missing y")?)?);
+                match crv {
+                    "P-256" => KeyMaterial::P256(point),
+                    "P-384" => KeyMaterial::P384(point),
+                    crv => return Err(format!("unsupported curve {}", crv).into()),
+                }
+            }
+            ("oct", _) => KeyMaterial::Hmac(decode(jwk.k.as_deref().ok_or("key missing value")?)?),
+            (kty, _) => return Err(format!("unsupported key type {}", kty).into()),
+        };
+
+        Ok(Some(Self {
+            id: jwk.kid,
+            algorithm,
+            material,
+        }))
+    }
+
+    fn verify(&self, alg: Algorithm, msg: &[u8], sig: &[u8]) -> bool {
+        if self.algorithm.map(|a| a != alg).unwrap_or(false) {
+            return false;
+        }
+
+        let rsa = |params: &'static signature::RsaParameters, n: &[u8], e: &[u8]| {
+            signature::RsaPublicKeyComponents { n, e }
+                .verify(params, msg, sig)
+                .is_ok()
+        };
+        let hmac = |algorithm: hmac::Algorithm, secret: &[u8]| {
+            hmac::verify(&hmac::Key::new(algorithm, secret), msg, sig).is_ok()
+        };
+        match (&self.material, alg) {
+            (KeyMaterial::Rsa { n, e }, Algorithm::Rs256) => {
+                rsa(&signature::RSA_PKCS1_2048_8192_SHA256, n, e)
+            }
+            (KeyMaterial::Rsa { n, e }, Algorithm::Rs384) => {
+                rsa(&signature::RSA_PKCS1_2048_8192_SHA384, n, e)
+            }
+            (KeyMaterial::Rsa { n, e }, Algorithm::Rs512) => {
+                rsa(&signature::RSA_PKCS1_2048_8192_SHA512, n, e)
+            }
+            (KeyMaterial::Rsa { n, e }, Algorithm::Ps256) => {
+                rsa(&signature::RSA_PSS_2048_8192_SHA256, n, e)
+            }
+            (KeyMaterial::Rsa { n, e }, Algorithm::Ps384) => {
+                rsa(&signature::RSA_PSS_2048_8192_SHA384, n, e)
+            }
+            (KeyMaterial::Rsa { n, e }, Algorithm::Ps512) => {
+                rsa(&signature::RSA_PSS_2048_8192_SHA512, n, e)
+            }
+            (KeyMaterial::P256(point), Algorithm::Es256) => {
+                signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                    .verify(msg, sig)
+                    .is_ok()
+            }
+            (KeyMaterial::P384(point), Algorithm::Es384) => {
+                signature::UnparsedPublicKey::new(&signature::ECDSA_P384_SHA384_FIXED, point)
+                    .verify(msg, sig)
+                    .is_ok()
+            }
+            (KeyMaterial::Hmac(secret), Algorithm::Hs256) => hmac(hmac::HMAC_SHA256, secret),
+            (KeyMaterial::Hmac(secret), Algorithm::Hs384) => hmac(hmac::HMAC_SHA384, secret),
+            (KeyMaterial::Hmac(secret), Algorithm::Hs512) => hmac(hmac::HMAC_SHA512, secret),
+            _ => false,
+        }
+    }
+}
+
+// === impl Reason ===
+
+impl Reason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Missing => "missing",
+            Self::Malformed => "malformed",
+            Self::UnknownKey => "unknown_key",
+            Self::Signature => "signature",
+            Self::Expired => "expired",
+            Self::Issuer => "issuer",
+            Self::Audience => "audience",
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "no bearer token"),
+            Self::Malformed => write!(f, "malformed token"),
+            Self::UnknownKey => write!(f, "unknown signing key"),
+            Self::Signature => write!(f, "invalid signature"),
+            Self::Expired => write!(f, "token expired or not yet valid"),
+            Self::Issuer => write!(f, "unexpected issuer"),
+            Self::Audience => write!(f, "unexpected audience"),
+        }
+    }
+}
+
+impl FmtLabels for Reason {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "reason=\"{}\"", self.as_str())
+    }
+}
+
+// === impl JwtMetrics ===
+
+impl JwtMetrics {
+    fn incr(&self, server: &ServerLabel, reason: Reason) {
+        self.0
+            .lock()
+            .entry((server.clone(), reason))
+            .or_default()
+            .incr();
+    }
+}
+
+impl FmtMetrics for JwtMetrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failures = self.0.lock();
+        if failures.is_empty() {
+            return Ok(());
+        }
+        inbound_http_jwt_failures_total.fmt_help(f)?;
+        inbound_http_jwt_failures_total.fmt_scopes(f, failures.iter(), |c| c)
+    }
+}
+
+// === impl NewValidateJwt ===
+
+impl<N> NewValidateJwt<N> {
+    /// When `defer_preflights` is set, CORS preflight requests bypass
+    /// validation so that they may be answered by their routes' CORS policies.
+    pub(crate) fn layer(
+        validator: Option<JwtValidator>,
+        metrics: JwtMetrics,
+        defer_preflights: bool,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            validator: validator.clone(),
+            metrics: metrics.clone(),
+            defer_preflights,
+        })
+    }
+}
+
+impl<T, N> svc::NewService<(Permit, T)> for NewValidateJwt<N>
+where
+    N: svc::NewService<(Permit, T)>,
+{
+    type Service = svc::Either<ValidateJwt<N::Service>, StripClaimHeaders<N::Service>>;
+
+    fn new_service(&mut self, (permit, target): (Permit, T)) -> Self::Service {
+        let server = permit.labels.server.clone();
+        match self.validator.as_ref() {
+            Some(validator) if validator.config.applies_to(&server) => {
+                svc::Either::A(ValidateJwt {
+                    validator: validator.clone(),
+                    server,
+                    metrics: self.metrics.clone(),
+                    defer_preflights: self.defer_preflights,
+                    inner: self.inner.new_service((permit, target)),
+                })
+            }
+            validator => svc::Either::B(StripClaimHeaders {
+                config: validator.map(|v| v.config.clone()),
+                inner: self.inner.new_service((permit, target)),
+            }),
+        }
+    }
+}
+
+// === impl ValidateJwt ===
+
+impl<B, S> svc::Service<http::Request<B>> for ValidateJwt<S>
+where
+    S: svc::Service<http::Request<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<S::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        self.validator.config.strip_claim_headers(req.headers_mut());
+
+        if self.defer_preflights && cors::defer_preflight(&mut req) {
+            return future::Either::Left(self.inner.call(req).err_into::<Error>());
+        }
+
+        match self.validator.validate(req.headers(), SystemTime::now()) {
+            Ok(claims) => {
+                self.validator.set_claim_headers(req.headers_mut(), &claims);
+                future::Either::Left(self.inner.call(req).err_into::<Error>())
+            }
+            Err(reason) => {
+                debug!(%reason, "Invalid JWT");
+                self.metrics.incr(&self.server, reason);
+                future::Either::Right(future::err(InvalidJwt(reason).into()))
+            }
+        }
+    }
+}
+
+// === impl StripClaimHeaders ===
+
+impl<B, S> svc::Service<http::Request<B>> for StripClaimHeaders<S>
+where
+    S: svc::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if let Some(config) = self.config.as_ref() {
+            config.strip_claim_headers(req.headers_mut());
+        }
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"test-secret-that-is-long-enough";
+
+    fn config() -> JwtConfig {
+        JwtConfig {
+            jwks: PathBuf::new(),
+            refresh: Duration::from_secs(60),
+            issuer: Some("https://issuer.example.com".to_string()),
+            audiences: Some("web".to_string()).into_iter().collect(),
+            claim_headers: vec![(
+                "sub".to_string(),
+                http::HeaderName::from_static("x-jwt-sub"),
+            )],
+            servers: HashSet::new(),
+        }
+    }
+
+    fn keys() -> Vec<Key> {
+        let jwks = format!(
+            r#"{{"keys":[{{"kty":"oct","kid":"k1","alg":"HS256","k":"{}"}},{{"kty":"RSA","use":"enc","n":"AQAB","e":"AQAB"}}]}}"#,
+            base64::encode_config(SECRET, base64::URL_SAFE_NO_PAD),
+        );
+        parse_jwks(jwks.as_bytes()).expect("JWKS must be valid")
+    }
+
+    fn sign(kid: &str, claims: serde_json::Value) -> String {
+        let b64 = |v: &[u8]| base64::encode_config(v, base64::URL_SAFE_NO_PAD);
+        let header = serde_json::json!({ "alg": "HS256", "kid": kid });
+        let msg = format!(
+            "{}.{}",
+            b64(header.to_string().as_bytes()),
+            b64(claims.to_string().as_bytes())
+        );
+        let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, SECRET), msg.as_bytes());
+        format!("{}.{}", msg, b64(tag.as_ref()))
+    }
+
+    fn now() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+    }
+
+    #[test]
+    fn validates_tokens() {
+        let config = config();
+        let keys = keys();
+        assert_eq!(keys.len(), 1, "encryption keys must be ignored");
+        let validate = |token: &str| validate_token(&config, &keys, token, now());
+
+        let claims = serde_json::json!({
+            "sub": "alice",
+            "iss": "https://issuer.example.com",
+            "aud": ["api", "web"],
+            "exp": 1_600_000_100u64,
+        });
+        let valid = validate(&sign("k1", claims.clone())).expect("token must be valid");
+        assert_eq!(valid.get("sub"), Some(&serde_json::json!("alice")));
+
+        // Tampering with the payload invalidates the signature.
+        let token = sign("k1", claims.clone());
+        let mut parts = token.split('.').collect::<Vec<_>>();
+        let forged = base64::encode_config(
+            claims.to_string().replace("alice", "mallory"),
+            base64::URL_SAFE_NO_PAD,
+        );
+        parts[1] = &forged;
+        assert_eq!(validate(&parts.join(".")).unwrap_err(), Reason::Signature);
+
+        assert_eq!(
+            validate(&sign("k2", claims.clone())).unwrap_err(),
+            Reason::UnknownKey
+        );
+        assert_eq!(validate("not-a-jwt").unwrap_err(), Reason::Malformed);
+
+        let mut expired = claims.clone();
+        expired["exp"] = serde_json::json!(1_599_999_000u64);
+        assert_eq!(validate(&sign("k1", expired)).unwrap_err(), Reason::Expired);
+
+        let mut issuer = claims.clone();
+        issuer["iss"] = serde_json::json!("https://other.example.com");
+        assert_eq!(validate(&sign("k1", issuer)).unwrap_err(), Reason::Issuer);
+
+        let mut audience = claims;
+        audience["aud"] = serde_json::json!("api");
+        assert_eq!(
+            validate(&sign("k1", audience)).unwrap_err(),
+            Reason::Audience
+        );
+    }
+
+    #[test]
+    fn sets_claim_headers() {
+        let (_tx, keys) = watch::channel(Arc::new(keys()));
+        let validator = JwtValidator {
+            config: Arc::new(config()),
+            keys,
+        };
+
+        let mut headers = http::header::HeaderMap::new();
+        assert_eq!(
+            validator.validate(&headers, now()).unwrap_err(),
+            Reason::Missing
+        );
+
+        let token = sign(
+            "k1",
+            serde_json::json!({
+                "sub": "alice",
+                "iss": "https://issuer.example.com",
+                "aud": "web",
+                "exp": 1_600_000_100u64,
+            }),
+        );
+        headers.insert(
+            http::header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        let claims = validator
+            .validate(&headers, now())
+            .expect("token must be valid");
+        validator.set_claim_headers(&mut headers, &claims);
+        assert_eq!(
+            headers.get("x-jwt-sub"),
+            Some(&http::HeaderValue::from_static("alice"))
+        );
+    }
+
+    #[tokio::test]
+    async fn strips_claim_headers_on_unvalidated_servers() {
+        use crate::policy::{AuthzLabels, Protocol};
+        use linkerd_app_core::{
+            svc::{NewService, ServiceExt},
+            transport::OrigDstAddr,
+        };
+
+        let (_tx, keys) = watch::channel(Arc::new(keys()));
+        let validator = JwtValidator {
+            config: Arc::new(JwtConfig {
+                servers: Some("api".to_string()).into_iter().collect(),
+                ..config()
+            }),
+            keys,
+        };
+        let mut new_svc = NewValidateJwt {
+            inner: |_: (Permit, ())| {
+                svc::mk(|req: http::Request<()>| future::ok::<_, Error>(req.headers().clone()))
+            },
+            validator: Some(validator),
+            metrics: JwtMetrics::default(),
+            defer_preflights: false,
+        };
+        let permit = Permit {
+            dst: OrigDstAddr(([192, 0, 2, 3], 8080).into()),
+            protocol: Protocol::Http1,
+            labels: AuthzLabels {
+                server: ServerLabel("web".to_string()),
+                authz: "default".to_string(),
+                route: None,
+            },
+        };
+
+        let req = http::Request::builder()
+            .header("x-jwt-sub", "mallory")
+            .body(())
+            .unwrap();
+        let headers = new_svc
+            .new_service((permit, ()))
+            .oneshot(req)
+            .await
+            .expect("request must not be validated");
+        assert_eq!(headers.get("x-jwt-sub"), None);
+    }
+}
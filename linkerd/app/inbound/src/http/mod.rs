@@ -1,9 +1,30 @@
+mod client_cert_header;
+mod cors;
+mod duplicate_headers;
+mod ext_authz;
+mod grpc_web;
+mod jwt;
+mod rate_limit;
+mod request_timeout;
 mod router;
 mod server;
 mod set_identity_header;
 #[cfg(test)]
 mod tests;
 
+pub use self::{
+    cors::{Cors, CorsPolicies},
+    duplicate_headers::{DuplicateHeader, DuplicateHeaderMode, DuplicateHeaders},
+    jwt::{InvalidJwt, JwtConfig},
+    rate_limit::{RateLimit, RateLimited, RateLimits},
+    request_timeout::RequestTimeouts,
+};
+pub(crate) use self::{
+    duplicate_headers::DuplicateHeaderMetrics,
+    jwt::{JwtMetrics, JwtValidator},
+    rate_limit::RateLimitMetrics,
+};
+
 fn trace_labels() -> std::collections::HashMap<String, String> {
     let mut l = std::collections::HashMap::new();
     l.insert("direction".to_string(), "inbound".to_string());
@@ -28,7 +49,7 @@ pub mod fuzz {
         proxy::http,
         svc::{self, NewService, Param},
         tls,
-        transport::{ClientAddr, OrigDstAddr, Remote, ServerAddr},
+        transport::{ClientAddr, OrigDstAddr, Origin, Remote, ServerAddr},
         NameAddr, ProxyRuntime,
     };
     pub use linkerd_app_test as support;
@@ -195,6 +216,12 @@ pub mod fuzz {
         }
     }
 
+    impl svc::Param<Origin> for Target {
+        fn param(&self) -> Origin {
+            Origin::External
+        }
+    }
+
     impl svc::Param<http::Version> for Target {
         fn param(&self) -> http::Version {
             self.0
@@ -216,6 +243,7 @@ pub mod fuzz {
                     authorizations: vec![policy::Authorization {
                         authentication: policy::Authentication::Unauthenticated,
                         networks: vec![std::net::IpAddr::from([192, 0, 2, 3]).into()],
+                        routes: vec![],
                         name: "testsaz".to_string(),
                     }],
                     name: "testsrv".to_string(),
@@ -242,4 +270,10 @@ pub mod fuzz {
             None
         }
     }
+
+    impl svc::Param<Option<tls::ClientCertHash>> for Target {
+        fn param(&self) -> Option<tls::ClientCertHash> {
+            None
+        }
+    }
 }
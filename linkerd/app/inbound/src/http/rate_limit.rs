@@ -0,0 +1,421 @@
+//! Local rate limits for inbound HTTP requests.
+//!
+//! Servers (by port) and individual routes (by the route names of the inbound
+//! service's profile) may be configured with token-bucket rate limits: each
+//! request takes a token from the bucket, which holds at most `burst` tokens
+//! and is refilled at `rate` tokens per second. Requests that find the bucket
+//! empty are not dispatched to the application, and are answered with a
+//! `429 Too Many Requests` response. Like unauthorized requests, dropped
+//! requests are not counted as proxy errors; they are counted by the
+//! `inbound_http_ratelimit_dropped_total` metric instead.
+//!
+//! A limit's bucket is shared by all of the connections to its server (or
+//! route).
+
+use futures::prelude::*;
+use linkerd_app_core::{
+    dst,
+    metrics::{metrics, Counter, FmtLabels, FmtMetrics, RouteLabels},
+    svc::{self, Param},
+    transport::{labels::TargetAddr, OrigDstAddr},
+    Error, NameAddr,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tokio::time::Instant;
+use tracing::debug;
+
+metrics! {
+    inbound_http_ratelimit_dropped_total: Counter {
+        "The total number of inbound HTTP requests that were dropped by a rate limit"
+    }
+}
+
+/// Configures a token-bucket rate limit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RateLimit {
+    /// The number of requests permitted per second, on average.
+    pub rate: f64,
+
+    /// The number of requests that may be permitted at once.
+    pub burst: u32,
+}
+
+/// Rate limits for inbound servers and routes.
+#[derive(Clone, Debug, Default)]
+pub struct RateLimits {
+    servers: Arc<HashMap<u16, Arc<Bucket>>>,
+    routes: Arc<HashMap<NameAddr, Vec<(String, Arc<Bucket>)>>>,
+}
+
+#[derive(Debug, Error)]
+#[error("request rate limit exceeded")]
+pub struct RateLimited(());
+
+#[derive(Clone, Debug, Default)]
+pub struct RateLimitMetrics(Arc<Mutex<HashMap<Scope, Counter>>>);
+
+/// Rate limits requests to servers, by port.
+#[derive(Clone, Debug)]
+pub struct NewRateLimit<N> {
+    inner: N,
+    limits: RateLimits,
+    metrics: RateLimitMetrics,
+}
+
+/// Rate limits requests to routes.
+#[derive(Clone, Debug)]
+pub struct NewRouteRateLimit<N> {
+    inner: N,
+    limits: RateLimits,
+    metrics: RateLimitMetrics,
+}
+
+/// Rate limits a server's requests (as a `Service`), or a route's requests (as
+/// a `Proxy`).
+#[derive(Clone, Debug)]
+pub struct EnforceRateLimit<S> {
+    inner: S,
+    limit: Option<(Arc<Bucket>, Scope)>,
+    metrics: RateLimitMetrics,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    limit: RateLimit,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    refilled: Instant,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Scope {
+    Server(TargetAddr),
+    Route(RouteLabels),
+}
+
+// === impl RateLimits ===
+
+impl RateLimits {
+    pub fn new(
+        servers: impl IntoIterator<Item = (u16, RateLimit)>,
+        routes: impl IntoIterator<Item = (NameAddr, String, RateLimit)>,
+    ) -> Self {
+        let servers = servers
+            .into_iter()
+            .map(|(port, limit)| (port, Arc::new(Bucket::new(limit))))
+            .collect();
+        let mut by_addr = HashMap::<_, Vec<_>>::new();
+        for (addr, route, limit) in routes {
+            by_addr
+                .entry(addr)
+                .or_default()
+                .push((route, Arc::new(Bucket::new(limit))));
+        }
+        Self {
+            servers: Arc::new(servers),
+            routes: Arc::new(by_addr),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty() && self.routes.is_empty()
+    }
+
+    /// Returns the limit of the server on the given port, if any.
+    pub fn server(&self, port: u16) -> Option<RateLimit> {
+        self.server_bucket(port).map(|b| b.limit)
+    }
+
+    /// Returns the limit of the named route of the given service, if any.
+    pub fn route(&self, addr: &NameAddr, route: &str) -> Option<RateLimit> {
+        self.route_bucket(addr, route).map(|b| b.limit)
+    }
+
+    fn server_bucket(&self, port: u16) -> Option<Arc<Bucket>> {
+        self.servers.get(&port).cloned()
+    }
+
+    fn route_bucket(&self, addr: &NameAddr, route: &str) -> Option<Arc<Bucket>> {
+        self.routes
+            .get(addr)?
+            .iter()
+            .find(|(r, _)| r == route)
+            .map(|(_, bucket)| bucket.clone())
+    }
+}
+
+// === impl Bucket ===
+
+impl Bucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            state: Mutex::new(BucketState {
+                tokens: limit.burst as f64,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token from the bucket, returning false if it's empty.
+    fn acquire(&self) -> bool {
+        let mut state = self.state.lock();
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(state.refilled);
+        state.tokens =
+            (state.tokens + elapsed.as_secs_f64() * self.limit.rate).min(self.limit.burst as f64);
+        state.refilled = now;
+        if state.tokens < 1.0 {
+            return false;
+        }
+        state.tokens -= 1.0;
+        true
+    }
+}
+
+// === impl RateLimitMetrics ===
+
+impl FmtMetrics for RateLimitMetrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics = self.0.lock();
+        if metrics.is_empty() {
+            return Ok(());
+        }
+        inbound_http_ratelimit_dropped_total.fmt_help(f)?;
+        inbound_http_ratelimit_dropped_total.fmt_scopes(f, metrics.iter(), |c| c)
+    }
+}
+
+impl FmtLabels for Scope {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Server(addr) => addr.fmt_labels(f),
+            Scope::Route(labels) => labels.fmt_labels(f),
+        }
+    }
+}
+
+// === impl NewRateLimit ===
+
+impl<N> NewRateLimit<N> {
+    pub fn layer(
+        limits: RateLimits,
+        metrics: RateLimitMetrics,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            limits: limits.clone(),
+            metrics: metrics.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewRateLimit<N>
+where
+    T: Param<OrigDstAddr>,
+    N: svc::NewService<T>,
+{
+    type Service = EnforceRateLimit<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let OrigDstAddr(addr) = target.param();
+        let limit = self
+            .limits
+            .server_bucket(addr.port())
+            .map(|bucket| (bucket, Scope::Server(TargetAddr(addr))));
+        EnforceRateLimit {
+            inner: self.inner.new_service(target),
+            limit,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+// === impl NewRouteRateLimit ===
+
+impl<N> NewRouteRateLimit<N> {
+    pub fn layer(
+        limits: RateLimits,
+        metrics: RateLimitMetrics,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            limits: limits.clone(),
+            metrics: metrics.clone(),
+        })
+    }
+}
+
+impl<N> svc::NewService<dst::Route> for NewRouteRateLimit<N>
+where
+    N: svc::NewService<dst::Route>,
+{
+    type Service = EnforceRateLimit<N::Service>;
+
+    fn new_service(&mut self, route: dst::Route) -> Self::Service {
+        let limit = route
+            .route
+            .labels()
+            .get("route")
+            .and_then(|name| self.limits.route_bucket(&route.addr.0, name))
+            .map(|bucket| (bucket, Scope::Route(route.param())));
+        EnforceRateLimit {
+            inner: self.inner.new_service(route),
+            limit,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+// === impl EnforceRateLimit ===
+
+impl<S> EnforceRateLimit<S> {
+    /// Returns an error if the request exceeds the limit.
+    fn check(&self) -> Result<(), Error> {
+        if let Some((bucket, scope)) = self.limit.as_ref() {
+            if !bucket.acquire() {
+                debug!(
+                    rate = bucket.limit.rate,
+                    burst = bucket.limit.burst,
+                    "Rate limited"
+                );
+                self.metrics
+                    .0
+                    .lock()
+                    .entry(scope.clone())
+                    .or_default()
+                    .incr();
+                return Err(RateLimited(()).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Req, S> svc::Service<Req> for EnforceRateLimit<S>
+where
+    S: svc::Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<S::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        if let Err(e) = self.check() {
+            return future::Either::Right(future::err(e));
+        }
+        future::Either::Left(self.inner.call(req).err_into())
+    }
+}
+
+impl<Req, S, P> svc::Proxy<Req, S> for EnforceRateLimit<P>
+where
+    P: svc::Proxy<Req, S>,
+    P::Error: Into<Error>,
+    S: svc::Service<P::Request>,
+{
+    type Request = P::Request;
+    type Response = P::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<P::Future, Error>,
+        future::Ready<Result<P::Response, Error>>,
+    >;
+
+    fn proxy(&self, svc: &mut S, req: Req) -> Self::Future {
+        if let Err(e) = self.check() {
+            return future::Either::Right(future::err(e));
+        }
+        future::Either::Left(self.inner.proxy(svc, req).err_into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::{NewService, ServiceExt};
+    use std::time::Duration;
+    use tokio::time;
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn token_bucket() {
+        let bucket = Bucket::new(RateLimit {
+            rate: 2.0,
+            burst: 3,
+        });
+
+        // The bucket starts full.
+        assert!(bucket.acquire());
+        assert!(bucket.acquire());
+        assert!(bucket.acquire());
+        assert!(!bucket.acquire());
+
+        // It's refilled at the configured rate...
+        time::advance(Duration::from_millis(500)).await;
+        assert!(bucket.acquire());
+        assert!(!bucket.acquire());
+
+        // ...up to its burst.
+        time::advance(Duration::from_secs(10)).await;
+        assert!(bucket.acquire());
+        assert!(bucket.acquire());
+        assert!(bucket.acquire());
+        assert!(!bucket.acquire());
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn limits_servers() {
+        let limit = RateLimit {
+            rate: 1.0,
+            burst: 1,
+        };
+        let metrics = RateLimitMetrics::default();
+        let mut stack = NewRateLimit {
+            inner: |_: OrigDstAddr| svc::mk(|()| future::ok::<(), Error>(())),
+            limits: RateLimits::new(Some((8080, limit)), None),
+            metrics: metrics.clone(),
+        };
+
+        let addr = OrigDstAddr(([192, 0, 2, 3], 8080).into());
+        stack.new_service(addr).oneshot(()).await.unwrap();
+        let err = stack.new_service(addr).oneshot(()).await.unwrap_err();
+        assert!(err.is::<RateLimited>());
+        assert_eq!(
+            metrics
+                .0
+                .lock()
+                .get(&Scope::Server(TargetAddr(addr.0)))
+                .unwrap()
+                .value(),
+            1.0
+        );
+
+        // Other servers are not limited.
+        let other = OrigDstAddr(([192, 0, 2, 3], 8081).into());
+        stack.new_service(other).oneshot(()).await.unwrap();
+        stack.new_service(other).oneshot(()).await.unwrap();
+
+        time::advance(Duration::from_secs(1)).await;
+        stack.new_service(addr).oneshot(()).await.unwrap();
+    }
+}
@@ -0,0 +1,67 @@
+//! Per-server request timeouts.
+//!
+//! Servers may be configured with a timeout that bounds the time the proxy
+//! waits for the application to respond to each request. When the timeout
+//! elapses, the request fails with a `ResponseTimeout` error, which is
+//! recorded by the error metrics and answered with a `504 Gateway Timeout`
+//! response.
+
+use linkerd_app_core::{
+    svc::{self, timeout::Timeout},
+    transport::OrigDstAddr,
+};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+/// Maps inbound server ports to their request timeouts.
+#[derive(Clone, Debug, Default)]
+pub struct RequestTimeouts(Arc<HashMap<u16, Duration>>);
+
+#[derive(Clone, Debug)]
+pub struct NewRequestTimeout<N> {
+    inner: N,
+    timeouts: RequestTimeouts,
+}
+
+// === impl RequestTimeouts ===
+
+impl RequestTimeouts {
+    pub fn new(timeouts: impl IntoIterator<Item = (u16, Duration)>) -> Self {
+        Self(Arc::new(timeouts.into_iter().collect()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, port: u16) -> Option<Duration> {
+        self.0.get(&port).copied()
+    }
+}
+
+// === impl NewRequestTimeout ===
+
+impl<N> NewRequestTimeout<N> {
+    pub fn layer(timeouts: RequestTimeouts) -> impl svc::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            timeouts: timeouts.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewRequestTimeout<N>
+where
+    T: svc::Param<OrigDstAddr>,
+    N: svc::NewService<T>,
+{
+    type Service = Timeout<N::Service>;
+
+    fn new_service(&mut self, t: T) -> Self::Service {
+        let OrigDstAddr(addr) = t.param();
+        let inner = self.inner.new_service(t);
+        match self.timeouts.get(addr.port()) {
+            Some(timeout) => Timeout::new(inner, timeout),
+            None => Timeout::passthru(inner),
+        }
+    }
+}
@@ -1,11 +1,18 @@
+use super::{
+    cors::{DenyDeferredPreflight, NewRouteCors},
+    ext_authz::NewExtAuthz,
+    jwt::NewValidateJwt,
+    rate_limit::NewRouteRateLimit,
+};
 use crate::{policy, stack_labels, Inbound};
 use linkerd_app_core::{
-    classify, dst, errors, http_tracing, io, metrics,
+    classify, deadline, dst, errors, header_policy, http_tracing, io, metrics,
     profiles::{self, DiscoveryRejected},
     proxy::{http, tap},
+    route_filter,
     svc::{self, Param},
     tls,
-    transport::{self, ClientAddr, Remote, ServerAddr},
+    transport::{self, ClientAddr, Origin, Remote, ServerAddr},
     Error, Infallible, NameAddr, Result,
 };
 use std::{borrow::Borrow, net::SocketAddr};
@@ -25,6 +32,7 @@ struct LogicalPerRequest {
     client: Remote<ClientAddr>,
     server: Remote<ServerAddr>,
     tls: tls::ConditionalServerTls,
+    origin: Origin,
     permit: policy::Permit,
     labels: tap::Labels,
 }
@@ -37,6 +45,7 @@ struct Logical {
     addr: Remote<ServerAddr>,
     http: http::Version,
     tls: tls::ConditionalServerTls,
+    origin: Origin,
     permit: policy::Permit,
     labels: tap::Labels,
 }
@@ -74,6 +83,7 @@ impl<C> Inbound<C> {
             + Param<Remote<ServerAddr>>
             + Param<Remote<ClientAddr>>
             + Param<tls::ConditionalServerTls>
+            + Param<Origin>
             + Param<policy::AllowPolicy>,
         T: Clone + Send + 'static,
         P: profiles::GetProfile<profiles::LookupAddr> + Clone + Send + Sync + 'static,
@@ -86,6 +96,11 @@ impl<C> Inbound<C> {
     {
         self.map_stack(|config, rt, connect| {
             let allow_profile = config.allow_discovery.clone();
+            let logical = rt
+                .metrics
+                .proxy
+                .introspect
+                .register(stack_labels("http", "logical"));
 
             // Creates HTTP clients for each inbound port & HTTP settings.
             let http = connect
@@ -116,9 +131,19 @@ impl<C> Inbound<C> {
                 )
                 .push_on_service(http_tracing::client(
                     rt.span_sink.clone(),
+                    rt.trace_precedence,
                     super::trace_labels(),
                 ))
+                // Enforces request deadlines, propagating the time that
+                // remains to the application.
+                .push_on_service(deadline::EnforceDeadline::layer())
+                // Labels the server's sampled spans with the request's
+                // authorization.
+                .push(http_tracing::NewLabelSpan::<metrics::AuthzLabels, _>::layer())
                 .push_on_service(http::BoxResponse::layer())
+                // Denies preflight requests that bypassed authentication but
+                // weren't answered by a route's CORS policy.
+                .push_on_service(DenyDeferredPreflight::layer())
                 .check_new_service::<Logical, http::Request<_>>();
 
             // Attempts to discover a service profile for each logical target (as
@@ -132,23 +157,39 @@ impl<C> Inbound<C> {
                 .push(profiles::http::route_request::layer(
                     svc::proxies()
                         .push_on_service(http::BoxRequest::layer())
+                        // Rewrites or redirects the route's requests, beneath
+                        // the route's metrics so that redirects are recorded.
+                        .push(route_filter::layer(config.http_route_filters.clone()))
                         // Records per-route metrics.
                         .push(
                             rt.metrics.proxy
                                 .http_route
                                 .to_layer::<classify::Response, _, dst::Route>(),
                         )
+                        // Records per-route gRPC message metrics.
+                        .push(rt.metrics.proxy.http_route_grpc.to_layer::<_, dst::Route>())
                         // Sets the per-route response classifier as a request
                         // extension.
                         .push(classify::NewClassify::layer())
                         // Sets the route as a request extension so that it can be used
                         // by tap.
                         .push_http_insert_target::<dst::Route>()
+                        // Drops requests that exceed the route's rate limit.
+                        .push(NewRouteRateLimit::layer(
+                            config.rate_limits.clone(),
+                            rt.metrics.rate_limits.clone(),
+                        ))
+                        // Handles cross-origin requests by the route's CORS
+                        // policy.
+                        .push(NewRouteCors::layer(config.cors.clone()))
+                        // Modifies the route's request and response headers.
+                        .push(header_policy::layer(config.http_header_policies.clone()))
                         .push_map_target(|(route, logical): (profiles::http::Route, Profile)| {
                             dst::Route {
                                 route,
                                 addr: logical.addr,
                                 direction: metrics::Direction::In,
+                                rewritten_from: None,
                             }
                         })
                         .push_on_service(http::BoxResponse::layer())
@@ -209,13 +250,10 @@ impl<C> Inbound<C> {
                 .push_on_service(
                     svc::layers()
                         .push(rt.metrics.proxy.stack.layer(stack_labels("http", "logical")))
-                        .push(svc::FailFast::layer(
-                            "HTTP Logical",
-                            config.proxy.dispatch_timeout,
-                        ))
+                        .push(logical.failfast_layer("HTTP Logical", config.proxy.dispatch_timeout))
                         .push_spawn_buffer(config.proxy.buffer_capacity),
                 )
-                .push_cache(config.proxy.cache_max_idle_age)
+                .push(logical.cache_layer(config.proxy.cache_max_idle_age))
                 .push_on_service(
                     svc::layers()
                         .push(http::Retain::layer())
@@ -233,7 +271,22 @@ impl<C> Inbound<C> {
                 // minimize it's type footprint with a Box.
                 .push(svc::BoxNewService::layer())
                 .push(svc::NewRouter::layer(LogicalPerRequest::from))
-                .push(policy::NewAuthorizeHttp::layer(rt.metrics.http_authz.clone()))
+                // Checks authorized requests with the external authorizer, if
+                // one is configured for the server.
+                .push(NewExtAuthz::layer(
+                    rt.ext_authz.clone(),
+                    config.cors.defers_preflights(),
+                ))
+                // Rejects authorized requests that lack a valid JWT.
+                .push(NewValidateJwt::layer(
+                    rt.jwt.clone(),
+                    rt.metrics.jwt.clone(),
+                    config.cors.defers_preflights(),
+                ))
+                .push(policy::NewAuthorizeHttp::layer(
+                    rt.metrics.http_authz.clone(),
+                    config.authz_audit_servers.clone(),
+                ))
                 // Used by tap.
                 .push_http_insert_target::<tls::ConditionalServerTls>()
                 .push_http_insert_target::<Remote<ClientAddr>>()
@@ -249,17 +302,22 @@ where
     T: Param<Remote<ServerAddr>>,
     T: Param<Remote<ClientAddr>>,
     T: Param<tls::ConditionalServerTls>,
+    T: Param<Origin>,
 {
     fn from((permit, t): (policy::Permit, T)) -> Self {
-        let labels = vec![
+        let mut labels = vec![
             ("srv_name".to_string(), permit.labels.server.to_string()),
             ("saz_name".to_string(), permit.labels.authz.to_string()),
         ];
+        if let Some(route) = permit.labels.route.as_ref() {
+            labels.push(("saz_route".to_string(), route.clone()));
+        }
 
         Self {
             client: t.param(),
             server: t.param(),
             tls: t.param(),
+            origin: t.param(),
             permit,
             labels: labels
                 .into_iter()
@@ -298,6 +356,7 @@ impl<A> svc::stack::RecognizeRoute<http::Request<A>> for LogicalPerRequest {
             logical,
             addr: self.server,
             tls: self.tls.clone(),
+            origin: self.origin,
             permit: self.permit.clone(),
             // Use the request's HTTP version (i.e. as modified by orig-proto downgrading).
             http: req
@@ -344,11 +403,18 @@ impl Param<metrics::EndpointLabels> for Logical {
             authority: self.logical.as_ref().map(|d| d.as_http_authority()),
             target_addr: self.addr.into(),
             policy: self.permit.labels.clone(),
+            origin: self.origin,
         }
         .into()
     }
 }
 
+impl Param<metrics::AuthzLabels> for Logical {
+    fn param(&self) -> metrics::AuthzLabels {
+        self.permit.labels.clone()
+    }
+}
+
 impl classify::CanClassify for Logical {
     type Classify = classify::Request;
 
@@ -1,15 +1,22 @@
-use super::set_identity_header::NewSetIdentityHeader;
+use super::{
+    client_cert_header::NewSetClientCertHeader, cors::NewCors,
+    duplicate_headers::NewNormalizeDuplicateHeaders, grpc_web::GrpcWeb, rate_limit::NewRateLimit,
+    request_timeout::NewRequestTimeout, set_identity_header::NewSetIdentityHeader,
+};
 use crate::Inbound;
 pub use linkerd_app_core::proxy::http::{
     normalize_uri, strip_header, uri, BoxBody, BoxResponse, DetectHttp, Request, Response, Retain,
     Version,
 };
 use linkerd_app_core::{
+    access_log,
     config::{ProxyConfig, ServerConfig},
-    errors, http_tracing, identity, io,
-    metrics::ServerLabel,
+    deadline, errors, ext_authz, http_tracing, identity, io,
+    metrics::{Direction, ServerLabel},
     proxy::http,
+    rls,
     svc::{self, Param},
+    tls,
     transport::OrigDstAddr,
     Error, Result,
 };
@@ -18,12 +25,17 @@ use tracing::debug_span;
 #[derive(Copy, Clone, Debug)]
 struct ServerRescue;
 
+/// Extracts the identity of a meshed client from the server's target.
+#[derive(Copy, Clone, Debug)]
+struct ClientIdentity;
+
 impl<H> Inbound<H> {
     pub fn push_http_server<T, I, HSvc>(self) -> Inbound<svc::BoxNewTcp<T, I>>
     where
         T: Param<Version>
             + Param<http::normalize_uri::DefaultAuthority>
             + Param<Option<identity::Name>>
+            + Param<Option<tls::ClientCertHash>>
             + Param<ServerLabel>
             + Param<OrigDstAddr>,
         T: Clone + Send + 'static,
@@ -51,9 +63,27 @@ impl<H> Inbound<H> {
                 // the request may have been downgraded from a HTTP/2 orig-proto request.
                 .push(http::NewNormalizeUri::layer())
                 .push(NewSetIdentityHeader::layer())
+                .push(NewSetClientCertHeader::layer(
+                    config.client_cert_header.clone(),
+                ))
+                // Records the versions of meshed peer proxies.
+                .push(rt.metrics.peer_versions.to_layer())
+                // Checks requests with the rate limit service, if one is
+                // configured, describing each request by its client's
+                // identity.
+                .push(rls::NewCheckRateLimit::layer_via(
+                    rt.rate_limit.clone(),
+                    Direction::In,
+                    ClientIdentity,
+                ))
                 .push_on_service(
                     svc::layers()
                         .push(http::BoxRequest::layer())
+                        // Translates gRPC-Web requests from browsers into
+                        // native gRPC. This must be below the
+                        // `orig_proto::Downgrade` layer so that it observes the
+                        // request's original HTTP version.
+                        .push(GrpcWeb::layer())
                         // Downgrades the protocol if upgraded by an outbound proxy.
                         .push(http::orig_proto::Downgrade::layer())
                         // Limit the number of in-flight requests. When the proxy is
@@ -65,18 +95,48 @@ impl<H> Inbound<H> {
                         .push(svc::ConcurrencyLimitLayer::new(max_in_flight_requests))
                         .push(svc::FailFast::layer("HTTP Server", dispatch_timeout)),
                 )
+                // Bounds the time spent waiting for the application's
+                // response. Timeouts are recorded as errors.
+                .push(NewRequestTimeout::layer(config.request_timeouts.clone()))
+                // Drops requests that exceed the server's rate limit. Dropped
+                // requests are not recorded as errors.
+                .push(NewRateLimit::layer(
+                    config.rate_limits.clone(),
+                    rt.metrics.rate_limits.clone(),
+                ))
+                // Answers CORS preflight requests and sets CORS headers on
+                // responses to cross-origin requests. Preflight requests are
+                // answered before they're rate limited or authorized.
+                .push(NewCors::layer(config.cors.clone()))
+                // Normalizes or rejects requests with duplicate critical
+                // headers before they're handled by any other layer.
+                .push(NewNormalizeDuplicateHeaders::layer(
+                    config.duplicate_headers.clone(),
+                    rt.metrics.duplicate_headers.clone(),
+                ))
                 .push(rt.metrics.http_errors.to_layer())
                 .push_on_service(
                     svc::layers()
                         .push(ServerRescue::layer())
                         .push(http_tracing::server(
                             rt.span_sink.clone(),
+                            rt.trace_precedence,
                             super::trace_labels(),
                         ))
                         // Record when an HTTP/1 URI was in absolute form
                         .push(http::normalize_uri::MarkAbsoluteForm::layer())
-                        .push(http::BoxResponse::layer()),
+                        // Records the deadlines of requests with timeout
+                        // headers, so they're enforced on the inbound leg.
+                        .push(deadline::RecordDeadline::layer()),
                 )
+                // Writes an access log record for each request, including
+                // those that fail with proxy errors.
+                .push(access_log::NewLogRequests::layer(
+                    rt.access_log.clone(),
+                    Direction::In,
+                    rt.trace_precedence,
+                ))
+                .push_on_service(http::BoxResponse::layer())
                 .check_new_service::<T, http::Request<_>>()
                 .instrument(|t: &T| debug_span!("http", v = %Param::<Version>::param(t)))
                 .push(http::NewServeHttp::layer(h2_settings, rt.drain.clone()))
@@ -86,6 +146,16 @@ impl<H> Inbound<H> {
     }
 }
 
+// === impl ClientIdentity ===
+
+impl<T: Param<Option<identity::Name>>> svc::ExtractParam<Option<identity::Name>, T>
+    for ClientIdentity
+{
+    fn extract_param(&self, t: &T) -> Option<identity::Name> {
+        t.param()
+    }
+}
+
 // === impl ServerRescue ===
 
 impl ServerRescue {
@@ -101,6 +171,15 @@ impl errors::HttpRescue<Error> for ServerRescue {
         if cause.is::<crate::policy::DeniedUnauthorized>() {
             return Ok(errors::SyntheticHttpResponse::permission_denied(cause));
         }
+        if cause.is::<super::RateLimited>() {
+            return Ok(errors::SyntheticHttpResponse::rate_limited(cause));
+        }
+        if cause.is::<super::DuplicateHeader>() {
+            return Ok(errors::SyntheticHttpResponse::bad_request(cause));
+        }
+        if cause.is::<super::InvalidJwt>() {
+            return Ok(errors::SyntheticHttpResponse::unauthorized(cause));
+        }
         if cause.is::<crate::GatewayDomainInvalid>() {
             return Ok(errors::SyntheticHttpResponse::not_found(cause));
         }
@@ -113,6 +192,27 @@ impl errors::HttpRescue<Error> for ServerRescue {
         if cause.is::<errors::FailFastError>() {
             return Ok(errors::SyntheticHttpResponse::gateway_timeout(cause));
         }
+        if cause.is::<errors::ResponseTimeout>() {
+            return Ok(errors::SyntheticHttpResponse::gateway_timeout(cause));
+        }
+        if cause.is::<deadline::DeadlineExceeded>() {
+            return Ok(errors::SyntheticHttpResponse::deadline_exceeded(cause));
+        }
+        if cause.is::<rls::RateLimitExceeded>() {
+            return Ok(errors::SyntheticHttpResponse::rate_limited(cause));
+        }
+        if cause.is::<rls::RateLimitUnavailable>() {
+            return Ok(errors::SyntheticHttpResponse::unavailable(cause));
+        }
+        if let Some(denied) = cause.downcast_ref::<ext_authz::ExtAuthzDenied>() {
+            return Ok(errors::SyntheticHttpResponse {
+                http_status: denied.status(),
+                ..errors::SyntheticHttpResponse::permission_denied(cause)
+            });
+        }
+        if cause.is::<ext_authz::ExtAuthzUnavailable>() {
+            return Ok(errors::SyntheticHttpResponse::unavailable(cause));
+        }
 
         if cause.is::<errors::H2Error>() {
             return Err(error);
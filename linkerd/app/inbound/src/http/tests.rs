@@ -13,7 +13,7 @@ use linkerd_app_core::{
     proxy::http,
     svc::{self, NewService, Param},
     tls,
-    transport::{ClientAddr, OrigDstAddr, Remote, ServerAddr},
+    transport::{ClientAddr, OrigDstAddr, Origin, Remote, ServerAddr},
     NameAddr, ProxyRuntime,
 };
 use linkerd_app_test::connect::ConnectFuture;
@@ -233,6 +233,49 @@ async fn http1_connect_timeout_response_error_header() {
     bg.await.expect("background task failed");
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn http1_request_timeout_response_error_header() {
+    let _trace = trace_init();
+    tokio::time::pause();
+
+    // Build a mock connect to a server that is slower to respond than the
+    // server's request timeout.
+    let server = hyper::server::conn::Http::new();
+    let connect = support::connect().endpoint_fn_boxed(Target::addr(), slow_server(server));
+
+    let mut client = ClientBuilder::new();
+    let profiles = profile::resolver();
+    let profile_tx =
+        profiles.profile_tx(NameAddr::from_str_and_port("foo.svc.cluster.local", 5550).unwrap());
+    profile_tx.send(profile::Profile::default()).unwrap();
+    let cfg = Config {
+        request_timeouts: crate::RequestTimeouts::new(vec![(
+            80,
+            std::time::Duration::from_secs(1),
+        )]),
+        ..default_config()
+    };
+    let (rt, _shutdown) = runtime();
+    let server = build_server(cfg, rt, profiles, connect).new_service(Target::HTTP1);
+    let (mut client, bg) = http_util::connect_and_accept(&mut client, server).await;
+
+    let req = Request::builder()
+        .method(http::Method::GET)
+        .uri("http://foo.svc.cluster.local:5550")
+        .body(Body::default())
+        .unwrap();
+    let response = http_util::http_request(&mut client, req).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::GATEWAY_TIMEOUT);
+    let message = response
+        .headers()
+        .get(L5D_PROXY_ERROR)
+        .expect("response did not contain L5D_PROXY_ERROR header");
+    assert_eq!(message, "response timed out after 1s");
+
+    drop(client);
+    bg.await.expect("background task failed");
+}
+
 #[tokio::test(flavor = "current_thread")]
 async fn h2_response_error_header() {
     let _trace = trace_init();
@@ -337,6 +380,24 @@ fn hello_server(
     }
 }
 
+#[tracing::instrument]
+fn slow_server(
+    http: hyper::server::conn::Http,
+) -> impl Fn(Remote<ServerAddr>) -> io::Result<io::BoxedIo> {
+    move |endpoint| {
+        let span = tracing::info_span!("slow_server", ?endpoint);
+        let _e = span.enter();
+        let (client_io, server_io) = support::io::duplex(4096);
+        let slow_svc = hyper::service::service_fn(|_: Request<Body>| async move {
+            tracing::info!("sleeping so that the proxy hits a request timeout");
+            tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+            Ok::<_, io::Error>(Response::new(Body::from("too late")))
+        });
+        tokio::spawn(http.serve_connection(server_io, slow_svc).in_current_span());
+        Ok(io::BoxedIo::new(client_io))
+    }
+}
+
 #[tracing::instrument]
 fn connect_error() -> impl Fn(Remote<ServerAddr>) -> io::Result<io::BoxedIo> {
     move |_| {
@@ -398,6 +459,12 @@ impl svc::Param<Remote<ClientAddr>> for Target {
     }
 }
 
+impl svc::Param<Origin> for Target {
+    fn param(&self) -> Origin {
+        Origin::External
+    }
+}
+
 impl svc::Param<http::Version> for Target {
     fn param(&self) -> http::Version {
         self.0
@@ -419,6 +486,7 @@ impl svc::Param<policy::AllowPolicy> for Target {
                 authorizations: vec![policy::Authorization {
                     authentication: policy::Authentication::Unauthenticated,
                     networks: vec![std::net::IpAddr::from([192, 0, 2, 3]).into()],
+                    routes: vec![],
                     name: "testsaz".to_string(),
                 }],
                 name: "testsrv".to_string(),
@@ -445,3 +513,9 @@ impl svc::Param<Option<identity::Name>> for Target {
         None
     }
 }
+
+impl svc::Param<Option<tls::ClientCertHash>> for Target {
+    fn param(&self) -> Option<tls::ClientCertHash> {
+        None
+    }
+}
@@ -13,22 +13,31 @@ mod http;
 mod metrics;
 pub mod policy;
 mod server;
+pub mod sni;
 #[cfg(any(test, fuzzing))]
 pub(crate) mod test_util;
 
-pub use self::{metrics::Metrics, policy::DefaultPolicy};
+pub use self::{
+    http::{
+        Cors, CorsPolicies, DuplicateHeaderMode, DuplicateHeaders, JwtConfig, RateLimit,
+        RateLimits, RequestTimeouts,
+    },
+    metrics::Metrics,
+    policy::DefaultPolicy,
+};
 use linkerd_app_core::{
-    config::{ConnectConfig, ProxyConfig},
-    drain,
-    http_tracing::OpenCensusSink,
+    access_log,
+    config::{ConnectConfig, ProxyConfig, ServerConfig},
+    drain, ext_authz, header_policy,
+    http_tracing::{self, OpenCensusSink},
     io,
     proxy::tcp,
-    proxy::{identity::LocalCrtKey, tap},
-    svc,
+    proxy::{http::HeaderName, identity::LocalCrtKey, tap},
+    rls, route_filter, svc,
     transport::{self, Remote, ServerAddr},
     Error, NameMatch, ProxyRuntime,
 };
-use std::{fmt::Debug, time::Duration};
+use std::{collections::HashSet, fmt::Debug, time::Duration};
 use thiserror::Error;
 use tracing::debug_span;
 
@@ -41,6 +50,68 @@ pub struct Config {
     pub proxy: ProxyConfig,
     pub policy: policy::Config,
     pub profile_idle_timeout: Duration,
+
+    /// Classifies the networks from which inbound connections originate.
+    pub origin_networks: transport::OriginNetworks,
+
+    /// When set, verified client certificate metadata is passed to the
+    /// application in this header.
+    pub client_cert_header: Option<HeaderName>,
+
+    /// How HTTP servers handle requests with duplicate critical headers.
+    pub duplicate_headers: DuplicateHeaders,
+
+    /// Routes passthrough TLS connections to upstream targets by SNI.
+    pub sni_routes: sni::SniRoutes,
+
+    /// Bounds the time that servers wait for the application to respond to
+    /// each HTTP request.
+    pub request_timeouts: RequestTimeouts,
+
+    /// Limits the rate of HTTP requests to servers and routes.
+    pub rate_limits: RateLimits,
+
+    /// Limits the bandwidth of forwarded TCP connections, by target port.
+    pub tcp_bandwidth_limits: transport::bandwidth::Limits,
+
+    /// CORS policies of HTTP servers and routes.
+    pub cors: CorsPolicies,
+
+    /// Header policies of the HTTP routes of inbound services' profiles.
+    pub http_header_policies: header_policy::HeaderPolicies,
+
+    /// Path and host rewrites and redirects of the HTTP routes of inbound
+    /// services' profiles.
+    pub http_route_filters: route_filter::RouteFilters,
+
+    /// When set, HTTP requests must carry valid JWTs.
+    pub jwt: Option<JwtConfig>,
+
+    /// The names of servers whose policies are audited rather than enforced:
+    /// HTTP requests that would be denied are logged and counted, but are
+    /// permitted.
+    pub authz_audit_servers: HashSet<String>,
+
+    /// Networks from which clients are denied, regardless of the
+    /// authorizations of their servers' policies.
+    pub authz_deny_networks: policy::DenyNetworks,
+
+    /// Additional listeners, each of which forwards to a single application
+    /// port with its own policy.
+    pub listeners: Vec<ListenerConfig>,
+}
+
+/// Configures an additional inbound listener (e.g. a dedicated health check
+/// port that doesn't require TLS, or a port that only accepts HTTP/2).
+///
+/// Connections accepted by the listener target `target_port` on the local
+/// application and are subject to `policy`, which determines both their
+/// protocol and their authorizations.
+#[derive(Clone, Debug)]
+pub struct ListenerConfig {
+    pub server: ServerConfig,
+    pub target_port: u16,
+    pub policy: policy::ServerPolicy,
 }
 
 #[derive(Clone)]
@@ -56,6 +127,11 @@ struct Runtime {
     identity: Option<LocalCrtKey>,
     tap: tap::Registry,
     span_sink: OpenCensusSink,
+    trace_precedence: http_tracing::Precedence,
+    access_log: Option<access_log::AccessLog>,
+    rate_limit: Option<rls::Client>,
+    ext_authz: Option<ext_authz::Client>,
+    jwt: Option<http::JwtValidator>,
     drain: drain::Watch,
 }
 
@@ -93,7 +169,10 @@ impl<S> Inbound<S> {
     pub fn authorize_http<N>(
         &self,
     ) -> impl svc::layer::Layer<N, Service = policy::NewAuthorizeHttp<N>> + Clone {
-        policy::NewAuthorizeHttp::layer(self.runtime.metrics.http_authz.clone())
+        policy::NewAuthorizeHttp::layer(
+            self.runtime.metrics.http_authz.clone(),
+            self.config.authz_audit_servers.clone(),
+        )
     }
 
     /// A helper for gateways to instrument policy checks.
@@ -126,12 +205,21 @@ impl<S> Inbound<S> {
 }
 
 impl Inbound<()> {
+    /// Creates a new `Inbound` stack.
+    ///
+    /// When JWT validation is configured, this must be called on a Tokio runtime, since the JWKS
+    /// is refreshed in the background.
     pub fn new(config: Config, runtime: ProxyRuntime) -> Self {
         let runtime = Runtime {
             metrics: Metrics::new(runtime.metrics),
             identity: runtime.identity,
             tap: runtime.tap,
             span_sink: runtime.span_sink,
+            trace_precedence: runtime.trace_precedence,
+            access_log: runtime.access_log,
+            rate_limit: runtime.rate_limit,
+            ext_authz: runtime.ext_authz,
+            jwt: config.jwt.clone().map(JwtConfig::build),
             drain: runtime.drain,
         };
         Self {
@@ -157,7 +245,7 @@ impl Inbound<()> {
     ) -> Inbound<
         impl svc::Service<
                 T,
-                Response = impl io::AsyncRead + io::AsyncWrite + Send,
+                Response = impl io::AsyncRead + io::AsyncWrite + io::Splice + Send,
                 Error = Error,
                 Future = impl Send,
             > + Clone,
@@ -211,10 +299,10 @@ impl<S> Inbound<S> {
     >
     where
         T: svc::Param<transport::labels::Key> + Clone + Send + 'static,
-        I: io::AsyncRead + io::AsyncWrite,
+        I: io::AsyncRead + io::AsyncWrite + io::Splice,
         I: Debug + Send + Unpin + 'static,
         S: svc::Service<T> + Clone + Send + Sync + Unpin + 'static,
-        S::Response: io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
+        S::Response: io::AsyncRead + io::AsyncWrite + io::Splice + Send + Unpin + 'static,
         S::Error: Into<Error>,
         S::Future: Send,
     {
@@ -13,6 +13,9 @@ metrics! {
     inbound_http_authz_deny_total: Counter {
         "The total number of inbound HTTP requests that could not be processed due to a proxy error."
     },
+    inbound_http_authz_audit_total: Counter {
+        "The total number of inbound HTTP requests that would have been denied, but were permitted because the server's policy is audited"
+    },
 
     inbound_tcp_authz_allow_total: Counter {
         "The total number of inbound TCP connections that were authorized"
@@ -35,6 +38,7 @@ pub(crate) struct TcpAuthzMetrics(Arc<TcpInner>);
 struct HttpInner {
     allow: Mutex<HashMap<(TargetAddr, AuthzLabels), Counter>>,
     deny: Mutex<HashMap<(TargetAddr, ServerLabel), Counter>>,
+    audit: Mutex<HashMap<(TargetAddr, ServerLabel), Counter>>,
 }
 
 #[derive(Debug, Default)]
@@ -72,6 +76,15 @@ impl HttpAuthzMetrics {
             .or_default()
             .incr();
     }
+
+    pub fn audit(&self, policy: &AllowPolicy) {
+        self.0
+            .audit
+            .lock()
+            .entry(server_labels(policy))
+            .or_default()
+            .incr();
+    }
 }
 
 impl FmtMetrics for HttpAuthzMetrics {
@@ -90,6 +103,13 @@ impl FmtMetrics for HttpAuthzMetrics {
         }
         drop(deny);
 
+        let audit = self.0.audit.lock();
+        if !audit.is_empty() {
+            inbound_http_authz_audit_total.fmt_help(f)?;
+            inbound_http_authz_audit_total.fmt_scopes(f, audit.iter(), |c| c)?;
+        }
+        drop(audit);
+
         Ok(())
     }
 }
@@ -3,21 +3,31 @@ mod tcp;
 
 pub(crate) use self::{http::HttpErrorMetrics, tcp::TcpErrorMetrics};
 use crate::{
+    http::{DuplicateHeader, InvalidJwt, RateLimited},
     policy::{DeniedUnauthorized, DeniedUnknownPort},
     GatewayDomainInvalid, GatewayIdentityRequired, GatewayLoop,
 };
-use linkerd_app_core::{errors::FailFastError, metrics::FmtLabels, tls};
+use linkerd_app_core::{
+    errors::{FailFastError, ResponseTimeout},
+    ext_authz::{ExtAuthzDenied, ExtAuthzUnavailable},
+    metrics::FmtLabels,
+    rls::{RateLimitExceeded, RateLimitUnavailable},
+    tls,
+};
 use std::fmt;
 
 /// Inbound proxy error types.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 enum ErrorKind {
     DeniedUnknown,
+    ExtAuthzUnavailable,
     FailFast,
     GatewayDomainInvalid,
     GatewayIdentityRequired,
     GatewayLoop,
     Io,
+    RateLimitUnavailable,
+    ResponseTimeout,
     TlsDetectTimeout,
     Unexpected,
 }
@@ -29,10 +39,30 @@ impl ErrorKind {
         if err.is::<DeniedUnauthorized>() {
             // Unauthorized metrics are tracked separately.and are not considered to be errors.
             None
+        } else if err.is::<RateLimited>() || err.is::<RateLimitExceeded>() {
+            // Rate-limited requests are tracked separately and are not considered to be errors.
+            None
+        } else if err.is::<InvalidJwt>() {
+            // Requests without valid tokens are tracked separately and are not considered to be
+            // errors.
+            None
+        } else if err.is::<DuplicateHeader>() {
+            // Requests with duplicate headers are tracked separately and are not considered to be
+            // errors.
+            None
+        } else if err.is::<ExtAuthzDenied>() {
+            // Requests denied by the external authorizer are tracked by its metrics.
+            None
         } else if err.is::<DeniedUnknownPort>() {
             Some(ErrorKind::DeniedUnknown)
+        } else if err.is::<ExtAuthzUnavailable>() {
+            Some(ErrorKind::ExtAuthzUnavailable)
+        } else if err.is::<RateLimitUnavailable>() {
+            Some(ErrorKind::RateLimitUnavailable)
         } else if err.is::<FailFastError>() {
             Some(ErrorKind::FailFast)
+        } else if err.is::<ResponseTimeout>() {
+            Some(ErrorKind::ResponseTimeout)
         } else if err.is::<std::io::Error>() {
             Some(ErrorKind::Io)
         } else if err.is::<tls::server::ServerTlsTimeoutError>() {
@@ -58,12 +88,15 @@ impl FmtLabels for ErrorKind {
             "error=\"{}\"",
             match self {
                 ErrorKind::DeniedUnknown => "unknown port denied",
+                ErrorKind::ExtAuthzUnavailable => "ext authz unavailable",
                 ErrorKind::FailFast => "failfast",
                 ErrorKind::TlsDetectTimeout => "tls detection timeout",
                 ErrorKind::GatewayIdentityRequired => "gateway identity required",
                 ErrorKind::GatewayLoop => "gateway loop",
                 ErrorKind::GatewayDomainInvalid => "gateway domain invalid",
                 ErrorKind::Io => "i/o",
+                ErrorKind::RateLimitUnavailable => "rate limit unavailable",
+                ErrorKind::ResponseTimeout => "response timeout",
                 ErrorKind::Unexpected => "unexpected",
             }
         )
@@ -12,6 +12,7 @@ pub(crate) mod authz;
 pub(crate) mod error;
 
 pub use linkerd_app_core::metrics::*;
+use linkerd_app_core::peer_version;
 
 /// Holds outbound proxy metrics.
 #[derive(Clone, Debug)]
@@ -22,6 +23,14 @@ pub struct Metrics {
     pub(crate) tcp_authz: authz::TcpAuthzMetrics,
     pub tcp_errors: error::TcpErrorMetrics,
 
+    pub(crate) peer_versions: peer_version::PeerVersions,
+
+    pub(crate) rate_limits: crate::http::RateLimitMetrics,
+
+    pub(crate) jwt: crate::http::JwtMetrics,
+
+    pub(crate) duplicate_headers: crate::http::DuplicateHeaderMetrics,
+
     /// Holds metrics that are common to both inbound and outbound proxies. These metrics are
     /// reported separately
     pub proxy: Proxy,
@@ -34,6 +43,10 @@ impl Metrics {
             http_errors: error::HttpErrorMetrics::default(),
             tcp_authz: authz::TcpAuthzMetrics::default(),
             tcp_errors: error::TcpErrorMetrics::default(),
+            peer_versions: peer_version::PeerVersions::default(),
+            rate_limits: crate::http::RateLimitMetrics::default(),
+            jwt: crate::http::JwtMetrics::default(),
+            duplicate_headers: crate::http::DuplicateHeaderMetrics::default(),
             proxy,
         }
     }
@@ -47,6 +60,12 @@ impl FmtMetrics for Metrics {
         self.tcp_authz.fmt_metrics(f)?;
         self.tcp_errors.fmt_metrics(f)?;
 
+        self.peer_versions.fmt_metrics(f)?;
+
+        self.rate_limits.fmt_metrics(f)?;
+        self.jwt.fmt_metrics(f)?;
+        self.duplicate_headers.fmt_metrics(f)?;
+
         // XXX: Proxy metrics are reported elsewhere.
 
         Ok(())
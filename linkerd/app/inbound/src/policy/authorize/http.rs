@@ -3,23 +3,29 @@ use crate::metrics::authz::HttpAuthzMetrics;
 use super::super::{AllowPolicy, Permit};
 use futures::{future, TryFutureExt};
 use linkerd_app_core::{
+    proxy::http,
     svc::{self, ServiceExt},
     tls,
     transport::{ClientAddr, Remote},
     Error,
 };
-use std::task;
+use std::{collections::HashSet, sync::Arc, task};
 
 /// A middleware that enforces policy on each HTTP request.
 ///
 /// This enforcement is done lazily on each request so that policy updates are honored as the
-/// connection progresses.
+/// connection progresses, and so that authorizations that are scoped to HTTP routes can be matched
+/// against each request's method and path.
 ///
 /// The inner service is created for each request, so it's expected that this is combined with
 /// caching.
+///
+/// Servers may be audited rather than enforced, in which case requests that would be denied are
+/// logged and counted, but are permitted.
 #[derive(Clone, Debug)]
 pub struct NewAuthorizeHttp<N> {
     metrics: HttpAuthzMetrics,
+    audit_servers: Arc<HashSet<String>>,
     inner: N,
 }
 
@@ -30,6 +36,7 @@ pub struct AuthorizeHttp<T, N> {
     tls: tls::ConditionalServerTls,
     policy: AllowPolicy,
     metrics: HttpAuthzMetrics,
+    audit_servers: Arc<HashSet<String>>,
     inner: N,
 }
 
@@ -38,12 +45,23 @@ pub struct AuthorizeHttp<T, N> {
 impl<N> NewAuthorizeHttp<N> {
     pub(crate) fn layer(
         metrics: HttpAuthzMetrics,
+        audit_servers: HashSet<String>,
     ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        let audit_servers = Arc::new(audit_servers);
         svc::layer::mk(move |inner| Self {
             metrics: metrics.clone(),
+            audit_servers: audit_servers.clone(),
             inner,
         })
     }
+
+    /// Enforces policies (without auditing any servers) on servers outside of the inbound proxy,
+    /// e.g. the admin server, recording authorization metrics with the inbound proxy's.
+    pub fn layer_enforced(
+        metrics: &crate::Metrics,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        Self::layer(metrics.http_authz.clone(), HashSet::new())
+    }
 }
 
 impl<T, N> svc::NewService<T> for NewAuthorizeHttp<N>
@@ -65,6 +83,7 @@ where
             tls,
             policy,
             metrics: self.metrics.clone(),
+            audit_servers: self.audit_servers.clone(),
             inner: self.inner.clone(),
         }
     }
@@ -72,17 +91,17 @@ where
 
 // === impl AuthorizeHttp ===
 
-impl<Req, T, N, S> svc::Service<Req> for AuthorizeHttp<T, N>
+impl<B, T, N, S> svc::Service<http::Request<B>> for AuthorizeHttp<T, N>
 where
     T: Clone,
     N: svc::NewService<(Permit, T), Service = S>,
-    S: svc::Service<Req>,
+    S: svc::Service<http::Request<B>>,
     S::Error: Into<Error>,
 {
     type Response = S::Response;
     type Error = Error;
     type Future = future::Either<
-        future::ErrInto<svc::stack::Oneshot<S, Req>, Error>,
+        future::ErrInto<svc::stack::Oneshot<S, http::Request<B>>, Error>,
         future::Ready<Result<Self::Response, Error>>,
     >;
 
@@ -91,8 +110,28 @@ where
         task::Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, req: Req) -> Self::Future {
-        match self.policy.check_authorized(self.client_addr, &self.tls) {
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let authz = self.policy.check_http_authorized(
+            self.client_addr,
+            &self.tls,
+            req.method(),
+            req.uri().path(),
+        );
+        let authz = match authz {
+            Err(denied) if self.audit_servers.contains(&self.policy.server_label().0) => {
+                tracing::debug!(
+                    %denied,
+                    client.addr = %self.client_addr,
+                    method = %req.method(),
+                    path = %req.uri().path(),
+                    "Permitting request in audit mode",
+                );
+                self.metrics.audit(&self.policy);
+                Ok(self.policy.audit())
+            }
+            authz => authz,
+        };
+        match authz {
             Ok(permit) => {
                 self.metrics.allow(&permit);
                 let svc = self.inner.new_service((permit, self.target.clone()));
@@ -1,4 +1,4 @@
-use super::{discover::Discover, DefaultPolicy, ServerPolicy, Store};
+use super::{discover::Discover, DefaultPolicy, HttpRoute, ServerPolicy, Store};
 use linkerd_app_core::{
     control, dns, metrics, proxy::identity::LocalCrtKey, svc::NewService, Result,
 };
@@ -15,6 +15,10 @@ pub enum Config {
         workload: String,
         default: DefaultPolicy,
         ports: HashSet<u16>,
+
+        /// The HTTP routes to which discovered authorizations are scoped, by authorization name.
+        /// The discovery API does not describe routes, so they are configured statically.
+        authorization_routes: HashMap<String, Vec<HttpRoute>>,
     },
     Fixed {
         default: DefaultPolicy,
@@ -46,11 +50,12 @@ impl Config {
                 ports,
                 workload,
                 default,
+                authorization_routes,
             } => {
                 let watch = {
                     let backoff = control.connect.backoff;
                     let c = control.build(dns, metrics, identity).new_service(());
-                    Discover::new(workload, c).into_watch(backoff)
+                    Discover::new(workload, authorization_routes, c).into_watch(backoff)
                 };
                 Store::spawn_discover(default, ports, watch).await
             }
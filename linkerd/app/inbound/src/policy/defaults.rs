@@ -1,5 +1,8 @@
 use linkerd_app_core::{IpNet, Ipv4Net, Ipv6Net};
-use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
+use linkerd_server_policy::{
+    Authentication, Authorization, HttpRoute, HttpRouteMatch, PathMatch, Protocol, ServerPolicy,
+    Suffix,
+};
 use std::time::Duration;
 
 pub fn all_authenticated(timeout: Duration) -> ServerPolicy {
@@ -53,6 +56,58 @@ pub fn all_mtls_unauthenticated(timeout: Duration) -> ServerPolicy {
     )
 }
 
+/// The default policy of the proxy's admin server.
+///
+/// Clients on localhost may access all endpoints, and liveness and readiness probes are permitted
+/// from the node's networks (or from all networks, if the node's networks are not known). All
+/// other requests must be from authenticated clients.
+pub fn admin(node_nets: impl IntoIterator<Item = IpNet>, timeout: Duration) -> ServerPolicy {
+    let node_nets = node_nets.into_iter().collect::<Vec<_>>();
+    let probe_nets = if node_nets.is_empty() {
+        all_nets().collect()
+    } else {
+        node_nets
+    };
+    let probes = ["/live", "/ready"]
+        .iter()
+        .map(|path| HttpRouteMatch {
+            path: Some(PathMatch::Exact(path.to_string())),
+            method: Some(http::Method::GET),
+        })
+        .collect();
+
+    ServerPolicy {
+        protocol: Protocol::Detect { timeout },
+        authorizations: vec![
+            Authorization {
+                networks: vec![
+                    IpNet::from(Ipv4Net::new([127, 0, 0, 0].into(), 8).unwrap()).into(),
+                    IpNet::from(Ipv6Net::new(std::net::Ipv6Addr::LOCALHOST, 128).unwrap()).into(),
+                ],
+                authentication: Authentication::Unauthenticated,
+                routes: vec![],
+                name: "default:localhost".to_string(),
+            },
+            Authorization {
+                networks: probe_nets.into_iter().map(Into::into).collect(),
+                authentication: Authentication::Unauthenticated,
+                routes: vec![HttpRoute {
+                    name: "probes".to_string(),
+                    matches: probes,
+                }],
+                name: "default:probes".to_string(),
+            },
+            Authorization {
+                networks: all_nets().map(Into::into).collect(),
+                authentication: authenticated(),
+                routes: vec![],
+                name: "default:all-authenticated".to_string(),
+            },
+        ],
+        name: "default:admin".to_string(),
+    }
+}
+
 fn all_nets() -> impl Iterator<Item = IpNet> {
     vec![Ipv4Net::default().into(), Ipv6Net::default().into()].into_iter()
 }
@@ -75,6 +130,7 @@ fn mk(
         authorizations: vec![Authorization {
             networks: nets.into_iter().map(Into::into).collect(),
             authentication,
+            routes: vec![],
             name: name.to_string(),
         }],
         name: name.to_string(),
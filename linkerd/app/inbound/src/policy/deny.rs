@@ -0,0 +1,55 @@
+use linkerd_server_policy::Network;
+use std::{collections::HashMap, net::IpAddr, sync::Arc};
+
+/// Networks from which clients are denied, regardless of the authorizations of their servers'
+/// policies.
+///
+/// Deny rules take precedence over authorizations, so that a policy may, for instance, authorize
+/// clients in `10.0.0.0/8` while a deny rule excludes `10.1.2.0/24`. Rules may apply to all
+/// servers or only to the server with a given name.
+#[derive(Clone, Debug, Default)]
+pub struct DenyNetworks {
+    all: Arc<Vec<Network>>,
+    servers: Arc<HashMap<String, Vec<Network>>>,
+}
+
+// === impl DenyNetworks ===
+
+impl DenyNetworks {
+    pub fn new(rules: impl IntoIterator<Item = (Option<String>, Network)>) -> Self {
+        let mut all = Vec::new();
+        let mut servers = HashMap::<_, Vec<_>>::new();
+        for (server, net) in rules {
+            match server {
+                Some(server) => servers.entry(server).or_default().push(net),
+                None => all.push(net),
+            }
+        }
+        Self {
+            all: Arc::new(all),
+            servers: Arc::new(servers),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.all.is_empty() && self.servers.is_empty()
+    }
+
+    /// Returns the networks denied on all servers.
+    pub fn all(&self) -> &[Network] {
+        &*self.all
+    }
+
+    /// Returns the networks denied on the named server, excluding those denied on all servers.
+    pub fn server(&self, server: &str) -> &[Network] {
+        self.servers.get(server).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Checks whether clients with the given IP are denied on the named server.
+    pub(super) fn denies(&self, server: &str, ip: &IpAddr) -> bool {
+        self.all
+            .iter()
+            .chain(self.server(server))
+            .any(|net| net.contains(ip))
+    }
+}
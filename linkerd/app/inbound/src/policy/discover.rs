@@ -9,14 +9,15 @@ use linkerd_app_core::{
     Error, IpNet, Recover, Result,
 };
 use linkerd_server_policy::{
-    Authentication, Authorization, Network, Protocol, ServerPolicy, Suffix,
+    Authentication, Authorization, HttpRoute, Network, Protocol, ServerPolicy, Suffix,
 };
 use linkerd_tonic_watch::StreamWatch;
-use std::convert::TryInto;
+use std::{collections::HashMap, convert::TryInto, sync::Arc};
 
 #[derive(Clone, Debug)]
 pub(super) struct Discover<S> {
     workload: String,
+    authorization_routes: Arc<HashMap<String, Vec<HttpRoute>>>,
     client: ApiClient<S>,
 }
 
@@ -30,9 +31,14 @@ where
     S: tonic::client::GrpcService<tonic::body::BoxBody, Error = Error> + Clone,
     S::ResponseBody: http::HttpBody<Error = Error> + Send + Sync + 'static,
 {
-    pub(super) fn new(workload: String, client: S) -> Self {
+    pub(super) fn new(
+        workload: String,
+        authorization_routes: HashMap<String, Vec<HttpRoute>>,
+        client: S,
+    ) -> Self {
         Self {
             workload,
+            authorization_routes: Arc::new(authorization_routes),
             client: ApiClient::new(client),
         }
     }
@@ -67,11 +73,12 @@ where
             workload: self.workload.clone(),
         };
         let mut client = self.client.clone();
+        let routes = self.authorization_routes.clone();
         Box::pin(async move {
             let rsp = client.watch_port(tonic::Request::new(req)).await?;
             Ok(rsp.map(|updates| {
                 updates
-                    .map(|up| match to_policy(up?) {
+                    .map(move |up| match to_policy(up?, &*routes) {
                         Ok(policy) => {
                             tracing::debug!(?policy);
                             Ok(policy)
@@ -87,7 +94,10 @@ where
     }
 }
 
-fn to_policy(proto: api::Server) -> Result<ServerPolicy> {
+fn to_policy(
+    proto: api::Server,
+    authorization_routes: &HashMap<String, Vec<HttpRoute>>,
+) -> Result<ServerPolicy> {
     let protocol = match proto.protocol {
         Some(api::ProxyProtocol { kind: Some(k) }) => match k {
             api::proxy_protocol::Kind::Detect(api::proxy_protocol::Detect { timeout }) => {
@@ -166,9 +176,14 @@ fn to_policy(proto: api::Server) -> Result<ServerPolicy> {
                     .ok_or("authorization missing 'name' label")?
                     .clone();
 
+                // The discovery API does not scope authorizations to routes, so routes are
+                // configured for authorizations by name.
+                let routes = authorization_routes.get(&name).cloned().unwrap_or_default();
+
                 Ok(Authorization {
                     networks,
                     authentication: authn,
+                    routes,
                     name,
                 })
             },
@@ -1,6 +1,7 @@
 mod authorize;
 mod config;
 pub mod defaults;
+mod deny;
 mod discover;
 mod store;
 #[cfg(test)]
@@ -8,18 +9,28 @@ mod tests;
 
 pub use self::authorize::{NewAuthorizeHttp, NewAuthorizeTcp};
 pub use self::config::Config;
+pub use self::deny::DenyNetworks;
 pub(crate) use self::store::Store;
 
 pub use linkerd_app_core::metrics::{AuthzLabels, ServerLabel};
 use linkerd_app_core::{
+    proxy::http,
     tls,
     transport::{ClientAddr, OrigDstAddr, Remote},
     Result,
 };
-pub use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
+use linkerd_server_policy::normalize_path;
+pub use linkerd_server_policy::{
+    Authentication, Authorization, HttpRoute, HttpRouteMatch, Network, PathMatch, Protocol,
+    ServerPolicy, Suffix,
+};
 use thiserror::Error;
 use tokio::sync::watch;
 
+/// The authorization label of requests that are permitted only because their server's policy is
+/// audited.
+const AUDIT_AUTHZ: &str = "audit";
+
 #[derive(Clone, Debug, Error)]
 #[error("unauthorized connection on unknown port {0}")]
 pub struct DeniedUnknownPort(pub u16);
@@ -45,6 +56,7 @@ pub enum DefaultPolicy {
 pub struct AllowPolicy {
     dst: OrigDstAddr,
     server: watch::Receiver<ServerPolicy>,
+    deny: DenyNetworks,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -66,13 +78,30 @@ impl From<ServerPolicy> for DefaultPolicy {
 // === impl AllowPolicy ===
 
 impl AllowPolicy {
+    /// Returns a policy that never changes, e.g. for servers whose policies are configured at
+    /// startup.
+    pub fn fixed(dst: OrigDstAddr, server: ServerPolicy) -> Self {
+        // It's safe to discard the sender, as the receiver will continue to let us borrow/clone the
+        // fixed policy.
+        let (_, server) = watch::channel(server);
+        Self {
+            dst,
+            server,
+            deny: DenyNetworks::default(),
+        }
+    }
+
     #[cfg(any(test, fuzzing))]
     pub(crate) fn for_test(
         dst: OrigDstAddr,
         server: ServerPolicy,
     ) -> (Self, watch::Sender<ServerPolicy>) {
         let (tx, server) = watch::channel(server);
-        let p = Self { dst, server };
+        let p = Self {
+            dst,
+            server,
+            deny: DenyNetworks::default(),
+        };
         (p, tx)
     }
 
@@ -100,45 +129,65 @@ impl AllowPolicy {
 
     /// Checks whether the destination port's `AllowPolicy` is authorized to accept connections
     /// given the provided TLS state.
+    ///
+    /// Authorizations that are scoped to HTTP routes never permit connections.
     pub(crate) fn check_authorized(
         &self,
         client_addr: Remote<ClientAddr>,
         tls: &tls::ConditionalServerTls,
+    ) -> Result<Permit, DeniedUnauthorized> {
+        self.check(client_addr, tls, None)
+    }
+
+    /// Checks whether the destination port's `AllowPolicy` is authorized to accept an HTTP request
+    /// with the given method and path, given the provided TLS state.
+    pub(crate) fn check_http_authorized(
+        &self,
+        client_addr: Remote<ClientAddr>,
+        tls: &tls::ConditionalServerTls,
+        method: &http::Method,
+        path: &str,
+    ) -> Result<Permit, DeniedUnauthorized> {
+        self.check(client_addr, tls, Some((method, path)))
+    }
+
+    fn check(
+        &self,
+        client_addr: Remote<ClientAddr>,
+        tls: &tls::ConditionalServerTls,
+        req: Option<(&http::Method, &str)>,
     ) -> Result<Permit, DeniedUnauthorized> {
         let server = self.server.borrow();
+
+        // Deny rules take precedence over all authorizations.
+        if self.deny.denies(&server.name, &client_addr.ip()) {
+            tracing::debug!(server = %server.name, client.addr = %client_addr, "Client network denied");
+            return Err(DeniedUnauthorized {
+                server: server.name.clone(),
+            });
+        }
+
+        // Request paths are normalized so that equivalent paths can't evade route matches. Paths
+        // that can't be normalized match no routes.
+        let req = req.map(|(method, path)| (method, normalize_path(path)));
         for authz in server.authorizations.iter() {
-            if authz.networks.iter().any(|n| n.contains(&client_addr.ip())) {
-                match authz.authentication {
-                    Authentication::Unauthenticated => {
-                        return Ok(Permit::new(self.dst, &*server, authz));
-                    }
-
-                    Authentication::TlsUnauthenticated => {
-                        if let tls::ConditionalServerTls::Some(tls::ServerTls::Established {
-                            ..
-                        }) = tls
-                        {
-                            return Ok(Permit::new(self.dst, &*server, authz));
-                        }
-                    }
-
-                    Authentication::TlsAuthenticated {
-                        ref identities,
-                        ref suffixes,
-                    } => {
-                        if let tls::ConditionalServerTls::Some(tls::ServerTls::Established {
-                            client_id: Some(tls::server::ClientId(ref id)),
-                            ..
-                        }) = tls
-                        {
-                            if identities.contains(id.as_ref())
-                                || suffixes.iter().any(|s| s.contains(id.as_ref()))
-                            {
-                                return Ok(Permit::new(self.dst, &*server, authz));
-                            }
-                        }
-                    }
+            let route = if authz.routes.is_empty() {
+                None
+            } else {
+                let route = req.as_ref().and_then(|(method, path)| {
+                    let path = path.as_deref()?;
+                    authz.routes.iter().find(|r| r.matches(method, path))
+                });
+                match route {
+                    Some(route) => Some(route.name.clone()),
+                    None => continue,
                 }
+            };
+
+            if authz.networks.iter().any(|n| n.contains(&client_addr.ip()))
+                && Self::is_authenticated(&authz.authentication, tls)
+            {
+                return Ok(Permit::new(self.dst, &*server, authz, route));
             }
         }
 
@@ -146,18 +195,63 @@ impl AllowPolicy {
             server: server.name.clone(),
         })
     }
+
+    /// Permits a request that was denied by a server whose policy is audited rather than enforced.
+    pub(crate) fn audit(&self) -> Permit {
+        let server = self.server.borrow();
+        Permit {
+            dst: self.dst,
+            protocol: server.protocol,
+            labels: AuthzLabels {
+                server: ServerLabel(server.name.clone()),
+                authz: AUDIT_AUTHZ.to_string(),
+                route: None,
+            },
+        }
+    }
+
+    fn is_authenticated(authn: &Authentication, tls: &tls::ConditionalServerTls) -> bool {
+        match authn {
+            Authentication::Unauthenticated => true,
+
+            Authentication::TlsUnauthenticated => matches!(
+                tls,
+                tls::ConditionalServerTls::Some(tls::ServerTls::Established { .. })
+            ),
+
+            Authentication::TlsAuthenticated {
+                ref identities,
+                ref suffixes,
+            } => match tls {
+                tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                    client_id: Some(tls::server::ClientId(ref id)),
+                    ..
+                }) => {
+                    identities.contains(id.as_ref())
+                        || suffixes.iter().any(|s| s.contains(id.as_ref()))
+                }
+                _ => false,
+            },
+        }
+    }
 }
 
 // === impl Permit ===
 
 impl Permit {
-    fn new(dst: OrigDstAddr, server: &ServerPolicy, authz: &Authorization) -> Self {
+    fn new(
+        dst: OrigDstAddr,
+        server: &ServerPolicy,
+        authz: &Authorization,
+        route: Option<String>,
+    ) -> Self {
         Self {
             dst,
             protocol: server.protocol,
             labels: AuthzLabels {
                 server: ServerLabel(server.name.clone()),
                 authz: authz.name.clone(),
+                route,
             },
         }
     }
@@ -1,4 +1,4 @@
-use super::{discover, AllowPolicy, CheckPolicy, DefaultPolicy, DeniedUnknownPort};
+use super::{discover, AllowPolicy, CheckPolicy, DefaultPolicy, DeniedUnknownPort, DenyNetworks};
 use futures::prelude::*;
 use linkerd_app_core::{proxy::http, transport::OrigDstAddr, Error, Result};
 pub use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
@@ -16,6 +16,7 @@ pub struct Store {
     // When None, the default policy is 'deny'.
     default: Option<Rx>,
     ports: Arc<PortMap<Rx>>,
+    deny: DenyNetworks,
 }
 
 type Tx = watch::Sender<ServerPolicy>;
@@ -64,10 +65,16 @@ impl Store {
         let store = Self {
             default,
             ports: Arc::new(rxs),
+            deny: DenyNetworks::default(),
         };
         (store, default_tx)
     }
 
+    /// Denies clients in the given networks, regardless of their servers' policies.
+    pub(crate) fn with_deny_networks(self, deny: DenyNetworks) -> Self {
+        Self { deny, ..self }
+    }
+
     /// Spawns a watch for each of the given ports.
     ///
     /// The returned future completes when a watch has been successfully created for all of the
@@ -116,6 +123,7 @@ impl Store {
             Ok(Self {
                 default,
                 ports: Arc::new(ports),
+                deny: DenyNetworks::default(),
             })
         }
     }
@@ -139,7 +147,11 @@ impl CheckPolicy for Store {
                 None => Err(DeniedUnknownPort(dst.port())),
             })?;
 
-        Ok(AllowPolicy { dst, server })
+        Ok(AllowPolicy {
+            dst,
+            server,
+            deny: self.deny.clone(),
+        })
     }
 }
 
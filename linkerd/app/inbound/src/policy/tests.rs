@@ -1,5 +1,8 @@
 use super::*;
-use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
+use linkerd_server_policy::{
+    Authentication, Authorization, HttpRoute, HttpRouteMatch, PathMatch, Protocol, ServerPolicy,
+    Suffix,
+};
 use std::collections::HashSet;
 
 #[test]
@@ -9,6 +12,7 @@ fn unauthenticated_allowed() {
         authorizations: vec![Authorization {
             authentication: Authentication::Unauthenticated,
             networks: vec!["192.0.2.0/24".parse().unwrap()],
+            routes: vec![],
             name: "unauth".to_string(),
         }],
         name: "test".to_string(),
@@ -32,6 +36,7 @@ fn unauthenticated_allowed() {
             labels: AuthzLabels {
                 server: ServerLabel("test".to_string()),
                 authz: "unauth".to_string(),
+                route: None,
             }
         }
     );
@@ -47,6 +52,7 @@ fn authenticated_identity() {
                 identities: vec![client_id().to_string()].into_iter().collect(),
             },
             networks: vec!["192.0.2.0/24".parse().unwrap()],
+            routes: vec![],
             name: "tls-auth".to_string(),
         }],
         name: "test".to_string(),
@@ -73,6 +79,7 @@ fn authenticated_identity() {
             labels: AuthzLabels {
                 server: ServerLabel("test".to_string()),
                 authz: "tls-auth".to_string(),
+                route: None,
             }
         }
     );
@@ -103,6 +110,7 @@ fn authenticated_suffix() {
                 ])],
             },
             networks: vec!["192.0.2.0/24".parse().unwrap()],
+            routes: vec![],
             name: "tls-auth".to_string(),
         }],
         name: "test".to_string(),
@@ -128,6 +136,7 @@ fn authenticated_suffix() {
             labels: AuthzLabels {
                 server: ServerLabel("test".to_string()),
                 authz: "tls-auth".to_string(),
+                route: None,
             }
         }
     );
@@ -152,6 +161,7 @@ fn tls_unauthenticated() {
         authorizations: vec![Authorization {
             authentication: Authentication::TlsUnauthenticated,
             networks: vec!["192.0.2.0/24".parse().unwrap()],
+            routes: vec![],
             name: "tls-unauth".to_string(),
         }],
         name: "test".to_string(),
@@ -177,6 +187,7 @@ fn tls_unauthenticated() {
             labels: AuthzLabels {
                 server: ServerLabel("test".to_string()),
                 authz: "tls-unauth".to_string(),
+                route: None,
             }
         }
     );
@@ -191,6 +202,203 @@ fn tls_unauthenticated() {
         .expect_err("policy must require a TLS termination identity");
 }
 
+#[test]
+fn route_scoped() {
+    let policy = ServerPolicy {
+        protocol: Protocol::Http1,
+        authorizations: vec![
+            Authorization {
+                authentication: Authentication::Unauthenticated,
+                networks: vec!["192.0.2.0/24".parse().unwrap()],
+                routes: vec![HttpRoute {
+                    name: "health".to_string(),
+                    matches: vec![HttpRouteMatch {
+                        path: Some(PathMatch::Prefix("/health".to_string())),
+                        method: Some(http::Method::GET),
+                    }],
+                }],
+                name: "probes".to_string(),
+            },
+            Authorization {
+                authentication: Authentication::TlsAuthenticated {
+                    suffixes: vec![],
+                    identities: vec![client_id().to_string()].into_iter().collect(),
+                },
+                networks: vec!["192.0.2.0/24".parse().unwrap()],
+                routes: vec![],
+                name: "tls-auth".to_string(),
+            },
+        ],
+        name: "test".to_string(),
+    };
+
+    let (policies, _tx) = Store::fixed(policy.clone(), None);
+    let allowed = policies
+        .check_policy(orig_dst_addr())
+        .expect("port must be known");
+
+    let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+    assert_eq!(
+        allowed
+            .check_http_authorized(client_addr(), &tls, &http::Method::GET, "/health/ready")
+            .expect("unauthenticated request must be permitted on the route"),
+        Permit {
+            dst: orig_dst_addr(),
+            protocol: policy.protocol,
+            labels: AuthzLabels {
+                server: ServerLabel("test".to_string()),
+                authz: "probes".to_string(),
+                route: Some("health".to_string()),
+            }
+        }
+    );
+    allowed
+        .check_http_authorized(client_addr(), &tls, &http::Method::POST, "/health/ready")
+        .expect_err("unauthenticated request must not be permitted with another method");
+    allowed
+        .check_http_authorized(client_addr(), &tls, &http::Method::GET, "/api")
+        .expect_err("unauthenticated request must not be permitted on another path");
+    for path in &["/health/../api", "/health/%2e%2e/api", "/health/..%2Fapi"] {
+        allowed
+            .check_http_authorized(client_addr(), &tls, &http::Method::GET, path)
+            .expect_err("paths must be normalized before they are matched");
+    }
+    allowed
+        .check_http_authorized(client_addr(), &tls, &http::Method::GET, "/%68ealth/./ready")
+        .expect("normalized paths must be permitted on the route");
+    allowed
+        .check_authorized(client_addr(), &tls)
+        .expect_err("route-scoped authorizations must not permit connections");
+
+    let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+        client_id: Some(client_id()),
+        negotiated_protocol: None,
+    });
+    assert_eq!(
+        allowed
+            .check_http_authorized(client_addr(), &tls, &http::Method::POST, "/api")
+            .expect("authenticated request must be permitted")
+            .labels,
+        AuthzLabels {
+            server: ServerLabel("test".to_string()),
+            authz: "tls-auth".to_string(),
+            route: None,
+        }
+    );
+}
+
+#[test]
+fn audited() {
+    let policy = ServerPolicy {
+        protocol: Protocol::Http1,
+        authorizations: vec![],
+        name: "test".to_string(),
+    };
+
+    let (policies, _tx) = Store::fixed(policy.clone(), None);
+    let allowed = policies
+        .check_policy(orig_dst_addr())
+        .expect("port must be known");
+
+    let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+    allowed
+        .check_http_authorized(client_addr(), &tls, &http::Method::GET, "/")
+        .expect_err("request must be denied");
+    assert_eq!(
+        allowed.audit(),
+        Permit {
+            dst: orig_dst_addr(),
+            protocol: policy.protocol,
+            labels: AuthzLabels {
+                server: ServerLabel("test".to_string()),
+                authz: "audit".to_string(),
+                route: None,
+            }
+        }
+    );
+}
+
+#[test]
+fn admin_default() {
+    let policy = defaults::admin(
+        vec!["192.0.2.0/24".parse().unwrap()],
+        std::time::Duration::from_secs(1),
+    );
+    let allowed = AllowPolicy::fixed(orig_dst_addr(), policy);
+
+    let no_tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+    let authz = |client, tls, path| {
+        allowed
+            .check_http_authorized(client, tls, &http::Method::GET, path)
+            .map(|p| p.labels.authz)
+    };
+
+    // Probes are permitted from the node's networks.
+    assert_eq!(
+        authz(client_addr(), &no_tls, "/ready").unwrap(),
+        "default:probes"
+    );
+    let external = Remote(ClientAddr(([198, 51, 100, 3], 54321).into()));
+    authz(external, &no_tls, "/ready").expect_err("external probes must be denied");
+
+    // Other requests must be authenticated, unless they're from localhost.
+    authz(client_addr(), &no_tls, "/metrics").expect_err("unauthenticated request must be denied");
+    let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+        client_id: Some(client_id()),
+        negotiated_protocol: None,
+    });
+    assert_eq!(
+        authz(external, &tls, "/metrics").unwrap(),
+        "default:all-authenticated"
+    );
+    let localhost = Remote(ClientAddr(([127, 0, 0, 1], 54321).into()));
+    assert_eq!(
+        authz(localhost, &no_tls, "/metrics").unwrap(),
+        "default:localhost"
+    );
+}
+
+#[test]
+fn denied_networks() {
+    let policy = ServerPolicy {
+        protocol: Protocol::Opaque,
+        authorizations: vec![Authorization {
+            authentication: Authentication::Unauthenticated,
+            networks: vec!["192.0.0.0/8".parse().unwrap()],
+            routes: vec![],
+            name: "unauth".to_string(),
+        }],
+        name: "test".to_string(),
+    };
+
+    let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+    let check = |deny: Vec<(Option<String>, Network)>| {
+        let (policies, _tx) = Store::fixed(policy.clone(), None);
+        policies
+            .with_deny_networks(DenyNetworks::new(deny))
+            .check_policy(orig_dst_addr())
+            .expect("port must be known")
+            .check_authorized(client_addr(), &tls)
+    };
+
+    // Deny rules take precedence over authorizations.
+    check(vec![(None, "192.0.2.0/24".parse().unwrap())])
+        .expect_err("denied network must not be permitted");
+    check(vec![(
+        Some("test".to_string()),
+        "192.0.2.0/24".parse().unwrap(),
+    )])
+    .expect_err("denied network must not be permitted");
+
+    // Rules for other networks and servers don't apply.
+    check(vec![(None, "192.0.3.0/24".parse().unwrap())]).expect("other networks must be permitted");
+    check(vec![(
+        Some("other".to_string()),
+        "192.0.2.0/24".parse().unwrap(),
+    )])
+    .expect("other servers' rules must not apply");
+}
+
 fn client_id() -> tls::ClientId {
     "testsa.testns.serviceaccount.identity.linkerd.cluster.local"
         .parse()
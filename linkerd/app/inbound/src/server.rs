@@ -1,11 +1,13 @@
-use crate::{direct, policy, Inbound};
-use futures::Stream;
+use crate::{detect, direct, policy, sni, Inbound, ListenerConfig};
+use futures::{Stream, TryStreamExt};
 use linkerd_app_core::{
-    dns, io, metrics, profiles, serve, svc,
+    access_log, dns, io,
+    metrics::{self, Direction},
+    profiles, serve, svc, tls,
     transport::{self, ClientAddr, Local, OrigDstAddr, Remote, ServerAddr},
-    Error,
+    Error, Infallible,
 };
-use std::fmt::Debug;
+use std::{fmt::Debug, net::SocketAddr};
 use tracing::debug_span;
 
 #[derive(Copy, Clone, Debug)]
@@ -13,6 +15,14 @@ struct TcpEndpoint {
     port: u16,
 }
 
+/// The addresses of a connection accepted by an additional listener, whose
+/// original destination is the listener's target port.
+#[derive(Clone, Debug)]
+struct ListenerAddrs<A> {
+    inner: A,
+    orig_dst: OrigDstAddr,
+}
+
 // === impl Inbound ===
 
 impl Inbound<()> {
@@ -27,6 +37,7 @@ impl Inbound<()> {
             .build(dns, control_metrics, self.runtime.identity.clone())
             .await
             .expect("Failed to fetch port policy")
+            .with_deny_networks(self.config.authz_deny_networks.clone())
     }
 
     pub async fn serve<A, I, G, GSvc, P>(
@@ -38,7 +49,7 @@ impl Inbound<()> {
         gateway: G,
     ) where
         A: svc::Param<Remote<ClientAddr>> + svc::Param<OrigDstAddr> + Clone + Send + Sync + 'static,
-        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr,
+        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr + io::Splice,
         I: Debug + Unpin + Send + Sync + 'static,
         G: svc::NewService<direct::GatewayConnection, Service = GSvc>,
         G: Clone + Send + Sync + Unpin + 'static,
@@ -50,15 +61,56 @@ impl Inbound<()> {
         P::Future: Send,
     {
         let shutdown = self.runtime.drain.clone().signaled();
+        let access_log = self.runtime.access_log.clone();
+
+        // Handles passthrough TLS connections that are routed to an upstream by SNI.
+        let sni_forward = {
+            let connect = &self.config.proxy.connect;
+            let connect = svc::stack(transport::ConnectTcp::new(connect.keepalive))
+                .push_connect_timeout(connect.timeout)
+                .into_inner();
+            self.clone()
+                .with_stack(connect)
+                .push_tcp_forward()
+                .into_stack()
+                .push(access_log::NewLogConnections::layer(
+                    access_log.clone(),
+                    Direction::In,
+                ))
+                .instrument(
+                    |t: &sni::SniTarget| debug_span!("sni", sni = %t.sni, upstream = %t.upstream),
+                )
+                .into_inner()
+        };
 
         // Handles connections to ports that can't be determined to be HTTP.
+        let sni_routes = self.config.sni_routes.clone();
         let forward = self
             .clone()
             .into_tcp_connect(addr.port())
             .push_tcp_forward()
             .into_stack()
+            .push(transport::bandwidth::NewThrottle::layer(
+                self.config.tcp_bandwidth_limits.clone(),
+            ))
             .push_map_target(TcpEndpoint::from_param)
+            .push(access_log::NewLogConnections::layer(
+                access_log.clone(),
+                Direction::In,
+            ))
             .instrument(|_: &_| debug_span!("tcp"))
+            .push_switch(
+                move |fwd: detect::Forward| -> Result<_, Infallible> {
+                    let tls: tls::ConditionalServerTls = svc::Param::param(&fwd);
+                    Ok(match sni_routes.target(&tls) {
+                        Some(target) => svc::Either::B(target),
+                        None => svc::Either::A(fwd),
+                    })
+                },
+                sni_forward,
+            )
+            .push_on_service(svc::BoxService::layer())
+            .push(svc::BoxNewService::layer())
             .into_inner();
 
         // Handles connections that target the inbound proxy port.
@@ -66,7 +118,16 @@ impl Inbound<()> {
             .clone()
             .into_tcp_connect(addr.port())
             .push_tcp_forward()
-            .map_stack(|_, _, s| s.push_map_target(TcpEndpoint::from_param))
+            .map_stack(|config, _, s| {
+                s.push(transport::bandwidth::NewThrottle::layer(
+                    config.tcp_bandwidth_limits.clone(),
+                ))
+                .push_map_target(TcpEndpoint::from_param)
+                .push(access_log::NewLogConnections::layer(
+                    access_log,
+                    Direction::In,
+                ))
+            })
             .push_direct(policies.clone(), gateway)
             .into_stack()
             .instrument(|_: &_| debug_span!("direct"))
@@ -87,6 +148,60 @@ impl Inbound<()> {
 
         serve::serve(listen, server, shutdown).await;
     }
+
+    /// Serves an additional listener. Its connections are handled as if they
+    /// targeted the listener's target port on the loopback address, subject to
+    /// the listener's policy.
+    ///
+    /// `proxy_addr` is the inbound proxy's listen address, so that connections
+    /// are prevented from looping back into the proxy.
+    pub async fn serve_listener<A, I, G, GSvc, P>(
+        self,
+        listener: ListenerConfig,
+        proxy_addr: Local<ServerAddr>,
+        listen: impl Stream<Item = io::Result<(A, I)>> + Send + Sync + 'static,
+        profiles: P,
+        gateway: G,
+    ) where
+        A: svc::Param<Remote<ClientAddr>> + Clone + Send + Sync + 'static,
+        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr + io::Splice,
+        I: Debug + Unpin + Send + Sync + 'static,
+        G: svc::NewService<direct::GatewayConnection, Service = GSvc>,
+        G: Clone + Send + Sync + Unpin + 'static,
+        GSvc: svc::Service<direct::GatewayIo<io::ScopedIo<I>>, Response = ()> + Send + 'static,
+        GSvc::Error: Into<Error>,
+        GSvc::Future: Send,
+        P: profiles::GetProfile<profiles::LookupAddr> + Clone + Send + Sync + Unpin + 'static,
+        P::Error: Send,
+        P::Future: Send,
+    {
+        // The listener's policy applies to all of its connections and never
+        // changes, so its sender may be dropped.
+        let (policies, _) = policy::Store::fixed(listener.policy, None);
+        let policies = policies.with_deny_networks(self.config.authz_deny_networks.clone());
+
+        // Connections are forwarded to the target port on the loopback
+        // address, regardless of the address on which they were accepted.
+        let orig_dst = OrigDstAddr(SocketAddr::from(([127, 0, 0, 1], listener.target_port)));
+        let listen = listen.map_ok(move |(inner, io)| (ListenerAddrs { inner, orig_dst }, io));
+
+        self.serve(proxy_addr, listen, policies, profiles, gateway)
+            .await
+    }
+}
+
+// === impl ListenerAddrs ===
+
+impl<A: svc::Param<Remote<ClientAddr>>> svc::Param<Remote<ClientAddr>> for ListenerAddrs<A> {
+    fn param(&self) -> Remote<ClientAddr> {
+        self.inner.param()
+    }
+}
+
+impl<A> svc::Param<OrigDstAddr> for ListenerAddrs<A> {
+    fn param(&self) -> OrigDstAddr {
+        self.orig_dst
+    }
 }
 
 // === impl TcpEndpoint ===
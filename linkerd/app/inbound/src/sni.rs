@@ -0,0 +1,199 @@
+//! Routes non-mesh TLS connections to upstream targets by SNI.
+//!
+//! When inbound TLS detection finds a ClientHello for a name other than the
+//! proxy's identity, the connection is passed through without being
+//! terminated. By default, it is forwarded to the local application; but if its
+//! SNI matches a configured route, it is instead forwarded to the route's
+//! upstream address, so that the proxy may act as a simple TLS gateway.
+
+use linkerd_app_core::{
+    access_log, svc, tls,
+    transport::{self, Remote, ServerAddr},
+};
+use std::{net::SocketAddr, str::FromStr, sync::Arc};
+use thiserror::Error;
+
+/// Maps SNI patterns to upstream addresses.
+///
+/// Patterns are either exact names (`api.example.com`) or wildcards
+/// (`*.example.com`) that match any name with the given suffix. Routes are
+/// matched in the order they are configured.
+#[derive(Clone, Debug, Default)]
+pub struct SniRoutes(Arc<[(SniPattern, SocketAddr)]>);
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("SNI routes must be formatted as `pattern=ip:port`: {0}")]
+pub struct InvalidSniRoute(String);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum SniPattern {
+    Exact(String),
+    /// Matches names ending with `.suffix`.
+    Wildcard(String),
+}
+
+/// A passthrough TLS connection that is routed to an upstream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SniTarget {
+    pub sni: tls::ServerId,
+    pub upstream: SocketAddr,
+}
+
+// === impl SniRoutes ===
+
+impl SniRoutes {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the upstream address to which connections for `sni` are routed,
+    /// if any.
+    pub fn route(&self, sni: &tls::ServerId) -> Option<SocketAddr> {
+        let name = sni.0.as_ref().trim_end_matches('.').to_ascii_lowercase();
+        self.0
+            .iter()
+            .find(|(pattern, _)| pattern.matches(&name))
+            .map(|(_, upstream)| *upstream)
+    }
+
+    /// Returns a target for connections that were passed through with a routed
+    /// SNI.
+    pub(crate) fn target(&self, tls: &tls::ConditionalServerTls) -> Option<SniTarget> {
+        if self.is_empty() {
+            return None;
+        }
+        match tls {
+            tls::ConditionalServerTls::Some(tls::ServerTls::Passthru { sni }) => {
+                let upstream = self.route(sni)?;
+                Some(SniTarget {
+                    sni: sni.clone(),
+                    upstream,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parses a comma-separated list of `pattern=ip:port` routes.
+impl FromStr for SniRoutes {
+    type Err = InvalidSniRoute;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let routes = s
+            .split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(|r| {
+                let invalid = || InvalidSniRoute(r.to_string());
+                let mut parts = r.splitn(2, '=');
+                let (pattern, upstream) = match (parts.next(), parts.next()) {
+                    (Some(pattern), Some(upstream)) => (pattern.trim(), upstream.trim()),
+                    _ => return Err(invalid()),
+                };
+                let pattern = pattern.trim_end_matches('.').to_ascii_lowercase();
+                let pattern = match pattern.strip_prefix("*.") {
+                    Some(suffix) if !suffix.is_empty() && !suffix.contains('*') => {
+                        SniPattern::Wildcard(suffix.to_string())
+                    }
+                    None if !pattern.is_empty() && !pattern.contains('*') => {
+                        SniPattern::Exact(pattern)
+                    }
+                    _ => return Err(invalid()),
+                };
+                let upstream = upstream.parse().map_err(|_| invalid())?;
+                Ok((pattern, upstream))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self(routes.into()))
+    }
+}
+
+// === impl SniPattern ===
+
+impl SniPattern {
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Exact(n) => n == name,
+            Self::Wildcard(suffix) => name
+                .strip_suffix(suffix.as_str())
+                .map(|prefix| prefix.len() > 1 && prefix.ends_with('.'))
+                .unwrap_or(false),
+        }
+    }
+}
+
+// === impl SniTarget ===
+
+impl svc::Param<Remote<ServerAddr>> for SniTarget {
+    fn param(&self) -> Remote<ServerAddr> {
+        Remote(ServerAddr(self.upstream))
+    }
+}
+
+impl svc::Param<transport::labels::Key> for SniTarget {
+    fn param(&self) -> transport::labels::Key {
+        transport::labels::Key::InboundClient
+    }
+}
+
+impl svc::Param<access_log::ConnectionTarget> for SniTarget {
+    fn param(&self) -> access_log::ConnectionTarget {
+        access_log::ConnectionTarget {
+            addr: self.upstream.into(),
+            identity: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sni(s: &str) -> tls::ServerId {
+        tls::ServerId(s.parse().unwrap())
+    }
+
+    #[test]
+    fn routes_by_sni() {
+        let routes = "api.example.com=10.0.0.1:443, *.example.com=10.0.0.2:443"
+            .parse::<SniRoutes>()
+            .unwrap();
+        assert_eq!(
+            routes.route(&sni("api.example.com")),
+            Some(([10, 0, 0, 1], 443).into())
+        );
+        assert_eq!(
+            routes.route(&sni("web.example.com")),
+            Some(([10, 0, 0, 2], 443).into())
+        );
+        assert_eq!(
+            routes.route(&sni("a.b.example.com")),
+            Some(([10, 0, 0, 2], 443).into())
+        );
+        assert_eq!(routes.route(&sni("example.com")), None);
+        assert_eq!(routes.route(&sni("example.org")), None);
+
+        let passthru = tls::ConditionalServerTls::Some(tls::ServerTls::Passthru {
+            sni: sni("web.example.com"),
+        });
+        assert_eq!(
+            routes.target(&passthru),
+            Some(SniTarget {
+                sni: sni("web.example.com"),
+                upstream: ([10, 0, 0, 2], 443).into(),
+            })
+        );
+        let no_tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+        assert_eq!(routes.target(&no_tls), None);
+    }
+
+    #[test]
+    fn rejects_invalid_routes() {
+        assert!("".parse::<SniRoutes>().unwrap().is_empty());
+        assert!("api.example.com".parse::<SniRoutes>().is_err());
+        assert!("api.example.com=api:443".parse::<SniRoutes>().is_err());
+        assert!("*=10.0.0.1:443".parse::<SniRoutes>().is_err());
+        assert!("a.*.example.com=10.0.0.1:443".parse::<SniRoutes>().is_err());
+    }
+}
@@ -2,7 +2,7 @@ use crate::{policy, Config};
 pub use futures::prelude::*;
 use linkerd_app_core::{
     config,
-    dns::Suffix,
+    dns::{self, Suffix},
     drain, exp_backoff, metrics,
     proxy::{
         http::{h1, h2},
@@ -24,11 +24,14 @@ pub fn default_config() -> Config {
         proxy: config::ProxyConfig {
             server: config::ServerConfig {
                 addr: ListenAddr(([0, 0, 0, 0], 0).into()),
-                keepalive: Keepalive(None),
+                keepalive: Keepalive::default(),
                 h2_settings: h2::Settings::default(),
+                proxy_protocol: Default::default(),
+                backlog: Default::default(),
+                accept_limits: Default::default(),
             },
             connect: config::ConnectConfig {
-                keepalive: Keepalive(None),
+                keepalive: Keepalive::default(),
                 timeout: Duration::from_secs(1),
                 backoff: exp_backoff::ExponentialBackoff::new(
                     Duration::from_millis(100),
@@ -56,6 +59,7 @@ pub fn default_config() -> Config {
                 authorizations: vec![Authorization {
                     authentication: Authentication::Unauthenticated,
                     networks: vec![Default::default()],
+                    routes: vec![],
                     name: "testsaz".to_string(),
                 }],
                 name: "testsrv".to_string(),
@@ -64,6 +68,20 @@ pub fn default_config() -> Config {
             ports: Default::default(),
         },
         profile_idle_timeout: Duration::from_millis(500),
+        origin_networks: Default::default(),
+        client_cert_header: None,
+        duplicate_headers: Default::default(),
+        sni_routes: Default::default(),
+        request_timeouts: Default::default(),
+        rate_limits: Default::default(),
+        tcp_bandwidth_limits: Default::default(),
+        cors: Default::default(),
+        http_header_policies: Default::default(),
+        http_route_filters: Default::default(),
+        jwt: None,
+        authz_audit_servers: Default::default(),
+        authz_deny_networks: Default::default(),
+        listeners: Vec::new(),
     }
 }
 
@@ -76,6 +94,11 @@ pub fn runtime() -> (ProxyRuntime, drain::Signal) {
         metrics: metrics.proxy,
         tap,
         span_sink: None,
+        trace_precedence: Default::default(),
+        access_log: None,
+        rate_limit: None,
+        ext_authz: None,
+        dns: dns::Resolver::new(Default::default(), Default::default()),
         drain,
     };
     (runtime, drain_tx)
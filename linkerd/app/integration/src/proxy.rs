@@ -61,7 +61,7 @@ enum MockOrigDst {
 
 impl<T> listen::Bind<T> for MockOrigDst
 where
-    T: Param<Keepalive> + Param<ListenAddr>,
+    T: Param<Keepalive> + Param<ListenAddr> + Param<listen::Backlog>,
 {
     type Addrs = orig_dst::Addrs;
     type Io = tokio::net::TcpStream;
@@ -386,7 +386,7 @@ async fn run(proxy: Proxy, mut env: TestEnv, random_ports: bool) -> Listening {
                             identity_addr,
                             main.inbound_addr(),
                             main.outbound_addr(),
-                            main.admin_addr(),
+                            main.admin_addr().expect("admin must listen on TCP"),
                         );
                         let mut running = Some((running_tx, addrs));
                         let on_shutdown = futures::future::poll_fn::<(), _>(move |cx| {
@@ -0,0 +1,113 @@
+use linkerd_app_core::{profiles::LogicalAddr, NameAddr};
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+use thiserror::Error;
+
+/// Selects how a service's requests are balanced over its endpoints.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Requests are sent to the less loaded of two endpoints, where load is
+    /// estimated from the endpoints' peak-EWMA latencies and pending requests.
+    PeakEwma,
+
+    /// Requests are sent to the endpoint with fewer pending requests of two,
+    /// regardless of latency. This is better suited than peak-EWMA to services
+    /// whose response latencies are highly bimodal.
+    LeastRequest,
+
+    /// Requests are sent to each ready endpoint in turn, regardless of load or
+    /// weight.
+    RoundRobin,
+}
+
+/// Maps logical service addresses to the algorithms used to balance them.
+#[derive(Clone, Debug, Default)]
+pub struct Algorithms(Arc<HashMap<NameAddr, Algorithm>>);
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("balancer algorithms must be formatted as `authority=peak-ewma`, `authority=least-request`, or `authority=round-robin`: {0}")]
+pub struct InvalidAlgorithm(String);
+
+// === impl Algorithm ===
+
+impl Default for Algorithm {
+    fn default() -> Self {
+        Self::PeakEwma
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = InvalidAlgorithm;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "peak-ewma" => Ok(Self::PeakEwma),
+            "least-request" => Ok(Self::LeastRequest),
+            "round-robin" => Ok(Self::RoundRobin),
+            _ => Err(InvalidAlgorithm(s.to_string())),
+        }
+    }
+}
+
+// === impl Algorithms ===
+
+impl Algorithms {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the algorithm used to balance the given logical address.
+    pub fn get(&self, LogicalAddr(addr): &LogicalAddr) -> Algorithm {
+        self.0.get(addr).copied().unwrap_or_default()
+    }
+}
+
+/// Parses a comma-separated list of `authority=algorithm` pairs.
+impl FromStr for Algorithms {
+    type Err = InvalidAlgorithm;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let algorithms = s
+            .split(',')
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .map(|a| {
+                let invalid = || InvalidAlgorithm(a.to_string());
+                let mut parts = a.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(addr), Some(algorithm)) => {
+                        let addr = NameAddr::from_str(addr.trim()).map_err(|_| invalid())?;
+                        let algorithm = algorithm.trim().parse().map_err(|_| invalid())?;
+                        Ok((addr, algorithm))
+                    }
+                    _ => Err(invalid()),
+                }
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(Self(Arc::new(algorithms)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_algorithms() {
+        let algorithms = "web.ns.svc.cluster.local:80=least-request, \
+                          cache.ns.svc.cluster.local:11211=round-robin"
+            .parse::<Algorithms>()
+            .unwrap();
+        let get = |addr: &str| algorithms.get(&LogicalAddr(addr.parse().unwrap()));
+        assert_eq!(get("web.ns.svc.cluster.local:80"), Algorithm::LeastRequest);
+        assert_eq!(
+            get("cache.ns.svc.cluster.local:11211"),
+            Algorithm::RoundRobin
+        );
+        assert_eq!(get("other.ns.svc.cluster.local:80"), Algorithm::PeakEwma);
+
+        assert!("web.ns.svc.cluster.local:80=random"
+            .parse::<Algorithms>()
+            .is_err());
+        assert!("least-request".parse::<Algorithms>().is_err());
+    }
+}
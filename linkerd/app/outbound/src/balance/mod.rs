@@ -0,0 +1,738 @@
+//! A weighted peak-EWMA load balancer.
+//!
+//! As with `tower`'s peak-EWMA balancer, each endpoint's load is estimated
+//! from its peak-EWMA round-trip time multiplied by its number of pending
+//! requests, and requests are dispatched by the power of two choices. Each
+//! endpoint's load is additionally divided by a weight, so that endpoints with
+//! greater weights appear less loaded and receive a greater share of requests.
+//! Endpoints are weighted by the destination service, relative to its default
+//! weight.
+//!
+//! When the proxy is configured with the zone in which it runs, endpoints in
+//! the same zone are weighted more heavily than those in other zones, which
+//! reduces cross-zone traffic. Because latency is still considered, traffic
+//! spills over to other zones as local endpoints become loaded. Zone
+//! preference only applies while a sufficient fraction of a service's
+//! endpoints are in the local zone; otherwise, requests are balanced evenly
+//! across all zones.
+//!
+//! When a slow-start window is configured, newly discovered endpoints are
+//! weighted by the fraction of the window that has elapsed since they were
+//! added, so that cold endpoints are warmed up gradually rather than receiving
+//! their full share of traffic immediately.
+//!
+//! When outlier detection is configured, endpoints that fail repeatedly are
+//! temporarily ejected from the balancer. See [`outlier`] for details.
+//!
+//! Services may instead be balanced by their endpoints' pending requests
+//! alone, disregarding latency. See [`Algorithm`].
+
+mod algorithm;
+pub mod outlier;
+mod readiness;
+
+pub use self::{
+    algorithm::{Algorithm, Algorithms, InvalidAlgorithm},
+    readiness::{MakeTrackReadiness, Readiness},
+};
+
+use futures::prelude::*;
+use linkerd_app_core::{profiles::LogicalAddr, proxy::api_resolve::Metadata, svc};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tokio::time;
+use tower::{
+    balance::p2c::Balance,
+    discover::{Change, Discover},
+    load::{Load, TrackCompletion},
+};
+
+/// The endpoint label that describes the zone in which an endpoint runs.
+const ZONE_LABEL: &str = "zone";
+
+/// The fraction of its weight that an endpoint receives when it is added
+/// during slow-start.
+const MIN_SLOW_START_FACTOR: f64 = 0.1;
+
+/// Bounds the RTT estimate so that an endpoint's load always reflects its
+/// pending requests, even when the estimate is zero (e.g. when the default
+/// RTT is configured as zero or responses are faster than the clock's
+/// resolution).
+const MIN_RTT: Duration = Duration::from_micros(100);
+
+/// Configures the peak-EWMA load estimate.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EwmaConfig {
+    /// The latency assumed for endpoints that have not yet responded.
+    pub default_rtt: Duration,
+
+    /// The time over which past latencies decay.
+    pub decay: Duration,
+}
+
+/// Configures the balancer to prefer endpoints in the proxy's zone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZoneAffinity {
+    /// The zone in which the proxy runs.
+    pub zone: String,
+
+    /// The factor by which endpoints in the local zone are weighted.
+    pub local_weight: f64,
+
+    /// The minimum fraction of a service's endpoints that must be in the local
+    /// zone for them to be preferred.
+    pub spillover_threshold: f64,
+}
+
+/// Describes how an endpoint is weighted by the balancer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EndpointWeight {
+    /// The endpoint's weight, relative to a default of 1.
+    pub weight: f64,
+
+    /// The zone in which the endpoint runs, if known.
+    pub zone: Option<String>,
+}
+
+/// Annotates endpoint services with their weights so that they may be read by
+/// the balancer.
+#[derive(Clone, Debug)]
+pub struct NewWeighted<N> {
+    inner: N,
+    algorithms: Algorithms,
+}
+
+#[derive(Clone, Debug)]
+pub struct Weighted<S> {
+    inner: S,
+    weight: EndpointWeight,
+    algorithm: Algorithm,
+    readiness: Option<readiness::Tracker>,
+}
+
+/// Wraps discovered endpoints with a weighted peak-EWMA load estimate.
+#[pin_project]
+pub struct WeightedDiscover<D: Discover, C> {
+    #[pin]
+    discover: D,
+    default_rtt: Duration,
+    decay_ns: f64,
+    completion: C,
+    slow_start: Option<Duration>,
+    locality: Locality<D::Key>,
+    ejections: Option<outlier::Ejections>,
+}
+
+/// An endpoint service that is loaded by its weighted peak-EWMA latency.
+pub struct WeightedEwma<S, C> {
+    service: S,
+    completion: C,
+    rtt_estimate: Arc<Mutex<RttEstimate>>,
+    decay_ns: f64,
+    /// If set, latency is disregarded and load is estimated from pending
+    /// requests alone.
+    least_request: bool,
+    weight: f64,
+    /// Set for endpoints in the local zone.
+    local_weight: Option<f64>,
+    prefer_local: Arc<AtomicBool>,
+    slow_start: Option<SlowStart>,
+    outlier: Option<outlier::Detector>,
+    /// Set while the endpoint is ejected, to be notified when it may return.
+    ejection: Option<Pin<Box<time::Sleep>>>,
+    readiness: Option<readiness::Tracker>,
+}
+
+/// Ramps an endpoint's weight after it is added.
+#[derive(Copy, Clone, Debug)]
+struct SlowStart {
+    added_at: Instant,
+    window: Duration,
+}
+
+/// Updates an endpoint's RTT estimate when a request completes.
+pub struct Handle {
+    sent_at: Instant,
+    decay_ns: f64,
+    rtt_estimate: Arc<Mutex<RttEstimate>>,
+}
+
+#[pin_project]
+pub struct ResponseFuture<F, C> {
+    #[pin]
+    future: F,
+    tracking: Option<(C, Handle)>,
+    outlier: Option<outlier::Detector>,
+}
+
+/// Tracks whether a service's endpoints are in the local zone.
+struct Locality<K> {
+    zone: Option<ZoneAffinity>,
+    endpoints: HashMap<K, bool>,
+    prefer_local: Arc<AtomicBool>,
+}
+
+#[derive(Debug)]
+struct RttEstimate {
+    update_at: Instant,
+    rtt_ns: f64,
+}
+
+/// Produces a weighted peak-EWMA balancer.
+pub(crate) fn layer<D, S, C, Req>(
+    ewma: EwmaConfig,
+    completion: C,
+    zone: Option<ZoneAffinity>,
+    slow_start: Option<Duration>,
+    outliers: Option<outlier::Config>,
+    metrics: outlier::Metrics,
+) -> impl svc::layer::Layer<D, Service = Balance<WeightedDiscover<D, C>, Req>> + Clone
+where
+    D: Discover<Service = Weighted<S>>,
+    D::Key: Hash + Clone,
+    C: Clone,
+    WeightedEwma<S, C>: svc::Service<Req>,
+    <WeightedEwma<S, C> as svc::Service<Req>>::Error: Into<linkerd_app_core::Error>,
+{
+    svc::layer::mk(move |discover| {
+        let discover = WeightedDiscover {
+            discover,
+            default_rtt: ewma.default_rtt,
+            decay_ns: nanos(ewma.decay),
+            completion: completion.clone(),
+            slow_start,
+            locality: Locality::new(zone.clone()),
+            ejections: outliers
+                .clone()
+                .map(|config| outlier::Ejections::new(config, metrics.clone())),
+        };
+        Balance::new(discover)
+    })
+}
+
+// === impl EndpointWeight ===
+
+impl Default for EndpointWeight {
+    fn default() -> Self {
+        Self {
+            weight: 1.0,
+            zone: None,
+        }
+    }
+}
+
+impl EndpointWeight {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        // Endpoints are never weighted to zero so that they may still be
+        // selected when no other endpoints are available.
+        let weight = metadata.weight().max(1);
+        Self {
+            weight: f64::from(weight) / f64::from(Metadata::DEFAULT_WEIGHT),
+            zone: metadata.labels().get(ZONE_LABEL).cloned(),
+        }
+    }
+}
+
+// === impl NewWeighted ===
+
+impl<N> NewWeighted<N> {
+    pub fn layer(algorithms: Algorithms) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            algorithms: algorithms.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewWeighted<N>
+where
+    T: svc::Param<EndpointWeight> + svc::Param<Option<LogicalAddr>>,
+    N: svc::NewService<T>,
+{
+    type Service = Weighted<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let weight = svc::Param::<EndpointWeight>::param(&target);
+        let algorithm = svc::Param::<Option<LogicalAddr>>::param(&target)
+            .map(|logical| self.algorithms.get(&logical))
+            .unwrap_or_default();
+        let inner = self.inner.new_service(target);
+        Weighted {
+            inner,
+            weight,
+            algorithm,
+            readiness: None,
+        }
+    }
+}
+
+// === impl Weighted ===
+
+impl<S, Req> svc::Service<Req> for Weighted<S>
+where
+    S: svc::Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        // Endpoints that are balanced by a ring hash are polled directly.
+        let poll = self.inner.poll_ready(cx);
+        if let Some(readiness) = self.readiness.as_mut() {
+            readiness.update(&poll);
+        }
+        poll
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+// === impl WeightedDiscover ===
+
+impl<D, S, C> Stream for WeightedDiscover<D, C>
+where
+    D: Discover<Service = Weighted<S>>,
+    D::Key: Hash + Clone,
+    C: Clone,
+{
+    type Item = Result<Change<D::Key, WeightedEwma<S, C>>, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let change = match futures::ready!(this.discover.poll_discover(cx)) {
+            Some(Ok(change)) => change,
+            Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+            None => return Poll::Ready(None),
+        };
+
+        let change = match change {
+            Change::Insert(
+                key,
+                Weighted {
+                    inner,
+                    weight,
+                    algorithm,
+                    readiness,
+                },
+            ) => {
+                let local_weight = this.locality.insert(key.clone(), &weight);
+                let svc = WeightedEwma {
+                    service: inner,
+                    completion: this.completion.clone(),
+                    rtt_estimate: Arc::new(Mutex::new(RttEstimate::new(nanos(*this.default_rtt)))),
+                    decay_ns: *this.decay_ns,
+                    least_request: algorithm == Algorithm::LeastRequest,
+                    weight: weight.weight,
+                    local_weight,
+                    prefer_local: this.locality.prefer_local.clone(),
+                    slow_start: this.slow_start.map(|window| SlowStart {
+                        added_at: Instant::now(),
+                        window,
+                    }),
+                    outlier: this.ejections.as_ref().map(outlier::Ejections::detector),
+                    ejection: None,
+                    readiness,
+                };
+                Change::Insert(key, svc)
+            }
+            Change::Remove(key) => {
+                this.locality.remove(&key);
+                Change::Remove(key)
+            }
+        };
+        if let Some(ejections) = this.ejections.as_ref() {
+            ejections.set_endpoints(this.locality.endpoints.len());
+        }
+        Poll::Ready(Some(Ok(change)))
+    }
+}
+
+// === impl WeightedEwma ===
+
+impl<S, C> WeightedEwma<S, C> {
+    fn weight(&self) -> f64 {
+        let weight = match self.local_weight {
+            Some(local) if self.prefer_local.load(Ordering::Relaxed) => self.weight * local,
+            _ => self.weight,
+        };
+        match self.slow_start {
+            Some(slow_start) => weight * slow_start.factor(Instant::now()),
+            None => weight,
+        }
+    }
+}
+
+impl<S, C> WeightedEwma<S, C> {
+    fn poll_endpoint_ready<Req>(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>>
+    where
+        S: svc::Service<Req>,
+    {
+        // Ejected endpoints are not ready until their ejection elapses.
+        if let Some(detector) = self.outlier.as_ref() {
+            if let Some(until) = detector.ejected_until() {
+                let ejection = self
+                    .ejection
+                    .get_or_insert_with(|| Box::pin(time::sleep_until(until.into())));
+                if ejection.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                self.ejection = None;
+                detector.uneject();
+            }
+        }
+
+        self.service.poll_ready(cx)
+    }
+}
+
+impl<S, C> Load for WeightedEwma<S, C> {
+    type Metric = f64;
+
+    fn load(&self) -> f64 {
+        // Each pending request holds a reference to the estimate.
+        let pending = Arc::strong_count(&self.rtt_estimate) as u32 - 1;
+        if self.least_request {
+            return f64::from(pending + 1) / self.weight();
+        }
+        let estimate = self.rtt_estimate.lock().decay(self.decay_ns);
+        estimate * f64::from(pending + 1) / self.weight()
+    }
+}
+
+impl<S, C, Req> svc::Service<Req> for WeightedEwma<S, C>
+where
+    S: svc::Service<Req>,
+    C: TrackCompletion<Handle, S::Response> + outlier::ClassifyResponse<S::Response> + Clone,
+{
+    type Response = C::Output;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, C>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        let poll = self.poll_endpoint_ready(cx);
+        if let Some(readiness) = self.readiness.as_mut() {
+            readiness.update(&poll);
+        }
+        poll
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let handle = Handle {
+            sent_at: Instant::now(),
+            decay_ns: self.decay_ns,
+            rtt_estimate: self.rtt_estimate.clone(),
+        };
+        ResponseFuture {
+            future: self.service.call(req),
+            tracking: Some((self.completion.clone(), handle)),
+            outlier: self.outlier.clone(),
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, C, T, E> Future for ResponseFuture<F, C>
+where
+    F: Future<Output = Result<T, E>>,
+    C: TrackCompletion<Handle, T> + outlier::ClassifyResponse<T>,
+{
+    type Output = Result<C::Output, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = futures::ready!(this.future.poll(cx));
+        let (completion, handle) = this.tracking.take().expect("polled after completion");
+        if let Some(detector) = this.outlier.take() {
+            let failure = match res {
+                Ok(ref rsp) => completion.is_failure(rsp),
+                Err(_) => true,
+            };
+            detector.record(handle.sent_at, failure);
+        }
+        let rsp = res?;
+        Poll::Ready(Ok(completion.track_completion(handle, rsp)))
+    }
+}
+
+// === impl Handle ===
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        let recv_at = Instant::now();
+        self.rtt_estimate
+            .lock()
+            .update(self.sent_at, recv_at, self.decay_ns);
+    }
+}
+
+// === impl Locality ===
+
+impl<K: Hash + Eq> Locality<K> {
+    fn new(zone: Option<ZoneAffinity>) -> Self {
+        Self {
+            zone,
+            endpoints: HashMap::new(),
+            prefer_local: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Records an endpoint, returning its weight when it is preferred.
+    fn insert(&mut self, key: K, weight: &EndpointWeight) -> Option<f64> {
+        let local_weight = self.zone.as_ref().and_then(|affinity| {
+            if weight.zone.as_deref() == Some(affinity.zone.as_str()) {
+                Some(affinity.local_weight)
+            } else {
+                None
+            }
+        });
+        self.endpoints.insert(key, local_weight.is_some());
+        self.update();
+        local_weight
+    }
+
+    fn remove(&mut self, key: &K) {
+        self.endpoints.remove(key);
+        self.update();
+    }
+
+    fn update(&self) {
+        let threshold = match self.zone.as_ref() {
+            Some(affinity) => affinity.spillover_threshold,
+            None => return,
+        };
+        let local = self.endpoints.values().filter(|local| **local).count();
+        let prefer = local > 0 && local as f64 >= threshold * self.endpoints.len() as f64;
+        if self.prefer_local.swap(prefer, Ordering::Relaxed) != prefer {
+            tracing::debug!(
+                local,
+                endpoints = self.endpoints.len(),
+                prefer,
+                "Updated zone preference"
+            );
+        }
+    }
+}
+
+// === impl SlowStart ===
+
+impl SlowStart {
+    /// Returns the fraction of its weight that the endpoint receives at `now`.
+    fn factor(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.added_at);
+        if elapsed >= self.window {
+            return 1.0;
+        }
+        let ramp = elapsed.as_secs_f64() / self.window.as_secs_f64();
+        ramp.max(MIN_SLOW_START_FACTOR)
+    }
+}
+
+// === impl RttEstimate ===
+
+impl RttEstimate {
+    fn new(rtt_ns: f64) -> Self {
+        Self {
+            update_at: Instant::now(),
+            rtt_ns: rtt_ns.max(nanos(MIN_RTT)),
+        }
+    }
+
+    /// Decays the estimate toward zero as time passes without updates.
+    fn decay(&mut self, decay_ns: f64) -> f64 {
+        let now = Instant::now();
+        self.update(now, now, decay_ns)
+    }
+
+    /// Updates the estimate with an observed round-trip time.
+    ///
+    /// The estimate immediately rises to an observed peak and otherwise decays
+    /// toward observations over time.
+    fn update(&mut self, sent_at: Instant, recv_at: Instant, decay_ns: f64) -> f64 {
+        let rtt = nanos(recv_at.saturating_duration_since(sent_at));
+        if self.rtt_ns < rtt {
+            self.rtt_ns = rtt;
+        } else {
+            let elapsed = nanos(recv_at.saturating_duration_since(self.update_at));
+            let decay = (-elapsed / decay_ns).exp();
+            self.rtt_ns = (self.rtt_ns * decay) + (rtt * (1.0 - decay));
+        }
+        self.rtt_ns = self.rtt_ns.max(nanos(MIN_RTT));
+        self.update_at = recv_at;
+        self.rtt_ns
+    }
+}
+
+fn nanos(d: Duration) -> f64 {
+    d.as_secs_f64() * 1_000_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn affinity() -> ZoneAffinity {
+        ZoneAffinity {
+            zone: "us-west-1a".to_string(),
+            local_weight: 4.0,
+            spillover_threshold: 0.5,
+        }
+    }
+
+    fn weight(zone: &str) -> EndpointWeight {
+        EndpointWeight {
+            zone: Some(zone.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn ewma(
+        weight: f64,
+        local_weight: Option<f64>,
+        prefer_local: Arc<AtomicBool>,
+    ) -> WeightedEwma<(), ()> {
+        WeightedEwma {
+            service: (),
+            completion: (),
+            rtt_estimate: Arc::new(Mutex::new(RttEstimate::new(1_000_000.0))),
+            decay_ns: nanos(Duration::from_secs(10)),
+            least_request: false,
+            weight,
+            local_weight,
+            prefer_local,
+            slow_start: None,
+            outlier: None,
+            ejection: None,
+            readiness: None,
+        }
+    }
+
+    #[test]
+    fn prefers_local_zone_above_threshold() {
+        let mut locality = Locality::new(Some(affinity()));
+        assert_eq!(locality.insert(1, &weight("us-west-1a")), Some(4.0));
+        assert_eq!(locality.insert(2, &weight("us-west-1b")), None);
+        assert_eq!(locality.insert(3, &EndpointWeight::default()), None);
+        // Only a third of the endpoints are local, so traffic spills over.
+        assert!(!locality.prefer_local.load(Ordering::Relaxed));
+
+        locality.remove(&3);
+        assert!(locality.prefer_local.load(Ordering::Relaxed));
+
+        let local = ewma(1.0, Some(4.0), locality.prefer_local.clone());
+        let remote = ewma(1.0, None, locality.prefer_local.clone());
+        assert!(local.load() < remote.load());
+
+        locality.remove(&1);
+        assert!(!locality.prefer_local.load(Ordering::Relaxed));
+        assert!(local.load() >= remote.load());
+    }
+
+    #[test]
+    fn ignores_zones_without_affinity() {
+        let mut locality = Locality::new(None);
+        assert_eq!(locality.insert(1, &weight("us-west-1a")), None);
+        assert!(!locality.prefer_local.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn weights_endpoints_from_metadata() {
+        let default = EndpointWeight::from_metadata(&Metadata::default());
+        assert_eq!(default.weight, 1.0);
+
+        let canary = Metadata::default().with_weight(Metadata::DEFAULT_WEIGHT / 4);
+        let canary = EndpointWeight::from_metadata(&canary);
+        assert_eq!(canary.weight, 0.25);
+
+        let zero = EndpointWeight::from_metadata(&Metadata::default().with_weight(0));
+        assert!(zero.weight > 0.0);
+
+        let prefer_local = Arc::new(AtomicBool::new(false));
+        let stable = ewma(default.weight, None, prefer_local.clone());
+        let canary = ewma(canary.weight, None, prefer_local);
+        assert!(stable.load() < canary.load());
+    }
+
+    #[test]
+    fn ramps_weight_during_slow_start() {
+        let added_at = Instant::now();
+        let slow_start = SlowStart {
+            added_at,
+            window: Duration::from_secs(10),
+        };
+        assert!((slow_start.factor(added_at) - MIN_SLOW_START_FACTOR).abs() < f64::EPSILON);
+        let halfway = slow_start.factor(added_at + Duration::from_secs(5));
+        assert!((halfway - 0.5).abs() < f64::EPSILON);
+        assert!((slow_start.factor(added_at + Duration::from_secs(20)) - 1.0).abs() < f64::EPSILON);
+
+        let prefer_local = Arc::new(AtomicBool::new(false));
+        let warm = ewma(1.0, None, prefer_local.clone());
+        let cold = WeightedEwma {
+            slow_start: Some(slow_start),
+            ..ewma(1.0, None, prefer_local)
+        };
+        assert!(warm.load() < cold.load());
+    }
+
+    #[test]
+    fn least_request_disregards_latency() {
+        let prefer_local = Arc::new(AtomicBool::new(false));
+        let fast = ewma(1.0, None, prefer_local.clone());
+        let slow = ewma(1.0, None, prefer_local);
+        slow.rtt_estimate.lock().rtt_ns *= 100.0;
+        assert!(fast.load() < slow.load());
+
+        let fast = WeightedEwma {
+            least_request: true,
+            ..fast
+        };
+        let slow = WeightedEwma {
+            least_request: true,
+            ..slow
+        };
+        assert!((fast.load() - slow.load()).abs() < f64::EPSILON);
+
+        // Pending requests hold references to the estimate.
+        let _pending = slow.rtt_estimate.clone();
+        assert!(fast.load() < slow.load());
+    }
+
+    #[test]
+    fn bounds_rtt_estimates() {
+        let mut estimate = RttEstimate::new(0.0);
+        assert!((estimate.rtt_ns - nanos(MIN_RTT)).abs() < f64::EPSILON);
+
+        let now = Instant::now();
+        estimate.update(now, now, nanos(Duration::from_secs(10)));
+        assert!((estimate.rtt_ns - nanos(MIN_RTT)).abs() < f64::EPSILON);
+
+        // An idle endpoint with a zero default RTT still reflects its pending
+        // requests.
+        let prefer_local = Arc::new(AtomicBool::new(false));
+        let idle = WeightedEwma {
+            rtt_estimate: Arc::new(Mutex::new(RttEstimate::new(0.0))),
+            ..ewma(1.0, None, prefer_local.clone())
+        };
+        let busy = WeightedEwma {
+            rtt_estimate: Arc::new(Mutex::new(RttEstimate::new(0.0))),
+            ..ewma(1.0, None, prefer_local)
+        };
+        let _pending = busy.rtt_estimate.clone();
+        assert!(idle.load() < busy.load());
+    }
+}
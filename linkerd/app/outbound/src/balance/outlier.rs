@@ -0,0 +1,362 @@
+//! Passive outlier detection.
+//!
+//! Each endpoint's responses are observed as they complete. An endpoint that
+//! fails (or, optionally, responds too slowly) for several consecutive
+//! requests is ejected from its balancer for a time: while ejected, it is not
+//! ready and so receives no new requests. An endpoint that is ejected again
+//! without having succeeded in between is ejected for exponentially longer, up
+//! to a maximum. So that a widespread failure does not empty a balancer, no
+//! more than a fixed percentage of a balancer's endpoints may be ejected at
+//! once.
+
+use linkerd_app_core::{
+    metrics::{metrics, Counter, FmtMetrics, Gauge},
+    proxy::http,
+};
+use parking_lot::Mutex;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tower::load::CompleteOnResponse;
+use tracing::debug;
+
+metrics! {
+    outbound_endpoint_ejections_total: Counter {
+        "The total number of times that endpoints have been ejected by outlier detection"
+    },
+    outbound_endpoints_ejected: Gauge {
+        "The number of endpoints that are currently ejected by outlier detection"
+    }
+}
+
+/// Configures passive outlier detection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// The number of consecutive failures after which an endpoint is ejected.
+    pub consecutive_failures: u32,
+
+    /// If set, responses that take longer than this are counted as failures.
+    pub failure_latency: Option<Duration>,
+
+    /// The duration of an endpoint's first ejection. Subsequent ejections are
+    /// doubled in duration.
+    pub base_ejection_time: Duration,
+
+    /// The maximum duration of an ejection.
+    pub max_ejection_time: Duration,
+
+    /// The maximum percentage of a balancer's endpoints that may be ejected at
+    /// once.
+    pub max_ejection_percent: u32,
+}
+
+/// Classifies endpoint responses as failures.
+///
+/// This is implemented by the balancer's completion instrument, which
+/// determines how responses are handled by the balancer.
+pub trait ClassifyResponse<Rsp> {
+    fn is_failure(&self, rsp: &Rsp) -> bool;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<EjectionMetrics>);
+
+/// Tracks ejections across all of a balancer's endpoints.
+#[derive(Clone, Debug)]
+pub(crate) struct Ejections(Arc<Shared>);
+
+/// Detects whether an individual endpoint is an outlier.
+#[derive(Clone, Debug)]
+pub(crate) struct Detector(Arc<Mutex<State>>);
+
+#[derive(Debug, Default)]
+struct EjectionMetrics {
+    ejections: Counter,
+    ejected: Gauge,
+}
+
+#[derive(Debug)]
+struct Shared {
+    config: Config,
+    metrics: Metrics,
+    endpoints: AtomicUsize,
+    ejected: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct State {
+    ejections: Ejections,
+    consecutive_failures: u32,
+    /// The number of times the endpoint has been ejected without succeeding in
+    /// between.
+    times_ejected: u32,
+    ejected_until: Option<Instant>,
+}
+
+// === impl ClassifyResponse ===
+
+/// HTTP endpoints fail when they respond with a server error.
+impl<B> ClassifyResponse<http::Response<B>> for http::balance::PendingUntilFirstData {
+    fn is_failure(&self, rsp: &http::Response<B>) -> bool {
+        rsp.status().is_server_error()
+    }
+}
+
+/// TCP endpoints only fail when they cannot be connected to.
+impl<T> ClassifyResponse<T> for CompleteOnResponse {
+    fn is_failure(&self, _: &T) -> bool {
+        false
+    }
+}
+
+// === impl Metrics ===
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        outbound_endpoint_ejections_total.fmt_help(f)?;
+        outbound_endpoint_ejections_total.fmt_metric(f, &self.0.ejections)?;
+
+        outbound_endpoints_ejected.fmt_help(f)?;
+        outbound_endpoints_ejected.fmt_metric(f, &self.0.ejected)?;
+
+        Ok(())
+    }
+}
+
+// === impl Ejections ===
+
+impl Ejections {
+    pub(crate) fn new(config: Config, metrics: Metrics) -> Self {
+        Self(Arc::new(Shared {
+            config,
+            metrics,
+            endpoints: AtomicUsize::new(0),
+            ejected: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Updates the number of endpoints in the balancer.
+    pub(crate) fn set_endpoints(&self, endpoints: usize) {
+        self.0.endpoints.store(endpoints, Ordering::Release);
+    }
+
+    /// Returns a detector for a newly discovered endpoint.
+    pub(crate) fn detector(&self) -> Detector {
+        Detector(Arc::new(Mutex::new(State {
+            ejections: self.clone(),
+            consecutive_failures: 0,
+            times_ejected: 0,
+            ejected_until: None,
+        })))
+    }
+
+    /// Reserves an ejection, unless doing so would eject more than the
+    /// configured percentage of endpoints.
+    ///
+    /// One endpoint may always be ejected, as long as there are others to
+    /// which requests may be sent.
+    fn try_eject(&self) -> bool {
+        let endpoints = self.0.endpoints.load(Ordering::Acquire);
+        if endpoints < 2 {
+            return false;
+        }
+        let percent = self.0.config.max_ejection_percent.min(100) as usize;
+        let max = (endpoints * percent / 100).max(1);
+        let reserved = self
+            .0
+            .ejected
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |ejected| {
+                if ejected < max {
+                    Some(ejected + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok();
+        if reserved {
+            self.0.metrics.0.ejections.incr();
+            self.0.metrics.0.ejected.incr();
+        }
+        reserved
+    }
+
+    fn release(&self) {
+        self.0.ejected.fetch_sub(1, Ordering::AcqRel);
+        self.0.metrics.0.ejected.decr();
+    }
+
+    /// Returns the duration of an endpoint's `n`th consecutive ejection.
+    fn ejection_time(&self, n: u32) -> Duration {
+        let Config {
+            base_ejection_time,
+            max_ejection_time,
+            ..
+        } = self.0.config;
+        base_ejection_time
+            .checked_mul(2u32.saturating_pow(n.saturating_sub(1)))
+            .unwrap_or(max_ejection_time)
+            .min(max_ejection_time)
+    }
+}
+
+// === impl Detector ===
+
+impl Detector {
+    /// Returns the time until which the endpoint is ejected, if it is ejected.
+    pub(crate) fn ejected_until(&self) -> Option<Instant> {
+        self.0.lock().ejected_until
+    }
+
+    /// Returns an ejected endpoint to service once its ejection has elapsed.
+    pub(crate) fn uneject(&self) {
+        let mut state = self.0.lock();
+        if state.ejected_until.take().is_some() {
+            debug!("Endpoint returned from ejection");
+            state.ejections.release();
+        }
+    }
+
+    /// Records the outcome of a request that was sent at `sent_at`.
+    pub(crate) fn record(&self, sent_at: Instant, failure: bool) {
+        let now = Instant::now();
+        let mut state = self.0.lock();
+        let ejections = state.ejections.clone();
+        let config = &ejections.0.config;
+        let slow = config
+            .failure_latency
+            .map(|latency| now.saturating_duration_since(sent_at) > latency)
+            .unwrap_or(false);
+
+        if !(failure || slow) {
+            state.consecutive_failures = 0;
+            if state.ejected_until.is_none() {
+                state.times_ejected = 0;
+            }
+            return;
+        }
+
+        state.consecutive_failures += 1;
+        if state.consecutive_failures < config.consecutive_failures || state.ejected_until.is_some()
+        {
+            return;
+        }
+        if !ejections.try_eject() {
+            debug!(
+                failures = state.consecutive_failures,
+                "Not ejecting endpoint; too many endpoints are ejected"
+            );
+            return;
+        }
+
+        state.consecutive_failures = 0;
+        state.times_ejected += 1;
+        let ejection = ejections.ejection_time(state.times_ejected);
+        debug!(?ejection, "Ejecting endpoint");
+        state.ejected_until = Some(now + ejection);
+    }
+}
+
+// === impl State ===
+
+impl Drop for State {
+    fn drop(&mut self) {
+        if self.ejected_until.is_some() {
+            self.ejections.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ejections(endpoints: usize) -> Ejections {
+        let ejections = Ejections::new(
+            Config {
+                consecutive_failures: 3,
+                failure_latency: Some(Duration::from_secs(1)),
+                base_ejection_time: Duration::from_secs(10),
+                max_ejection_time: Duration::from_secs(30),
+                max_ejection_percent: 50,
+            },
+            Metrics::default(),
+        );
+        ejections.set_endpoints(endpoints);
+        ejections
+    }
+
+    fn ejection(detector: &Detector) -> Option<Duration> {
+        detector
+            .ejected_until()
+            .map(|until| until.saturating_duration_since(Instant::now()))
+    }
+
+    #[test]
+    fn ejects_after_consecutive_failures() {
+        let ejections = ejections(4);
+        let detector = ejections.detector();
+
+        detector.record(Instant::now(), true);
+        detector.record(Instant::now(), true);
+        // A success resets the count of consecutive failures.
+        detector.record(Instant::now(), false);
+        detector.record(Instant::now(), true);
+        detector.record(Instant::now(), true);
+        assert!(detector.ejected_until().is_none());
+
+        // Slow responses are counted as failures.
+        detector.record(Instant::now() - Duration::from_secs(2), false);
+        let ejected = ejection(&detector).expect("endpoint must be ejected");
+        assert!(ejected > Duration::from_secs(9) && ejected <= Duration::from_secs(10));
+        assert_eq!(ejections.0.metrics.0.ejected.value(), 1);
+
+        detector.uneject();
+        assert!(detector.ejected_until().is_none());
+        assert_eq!(ejections.0.metrics.0.ejected.value(), 0);
+
+        // Ejections grow exponentially until the endpoint succeeds.
+        for _ in 0..3 {
+            detector.record(Instant::now(), true);
+        }
+        let ejected = ejection(&detector).expect("endpoint must be ejected");
+        assert!(ejected > Duration::from_secs(19));
+        detector.uneject();
+        for _ in 0..3 {
+            detector.record(Instant::now(), true);
+        }
+        let ejected = ejection(&detector).expect("endpoint must be ejected");
+        assert!(ejected <= Duration::from_secs(30));
+        drop(detector);
+        assert_eq!(ejections.0.metrics.0.ejected.value(), 0);
+        assert_eq!(ejections.0.metrics.0.ejections.value(), 3.0);
+    }
+
+    #[test]
+    fn limits_ejected_endpoints() {
+        let ejections = ejections(4);
+        let detectors = (0..4).map(|_| ejections.detector()).collect::<Vec<_>>();
+        for detector in detectors.iter() {
+            for _ in 0..3 {
+                detector.record(Instant::now(), true);
+            }
+        }
+        let ejected = detectors
+            .iter()
+            .filter(|d| d.ejected_until().is_some())
+            .count();
+        assert_eq!(ejected, 2);
+
+        // A lone endpoint is never ejected.
+        let ejections = self::ejections(1);
+        let detector = ejections.detector();
+        for _ in 0..3 {
+            detector.record(Instant::now(), true);
+        }
+        assert!(detector.ejected_until().is_none());
+    }
+}
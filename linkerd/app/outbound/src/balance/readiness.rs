@@ -0,0 +1,214 @@
+//! Tracks the fraction of a balancer's endpoints that are ready.
+
+use super::Weighted;
+use futures::prelude::*;
+use linkerd_app_core::svc;
+use pin_project::pin_project;
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+use tower::discover::{Change, Discover};
+
+/// Counts the ready endpoints of a balancer.
+#[derive(Clone, Debug, Default)]
+pub struct Readiness(Arc<Counts>);
+
+/// Records whether a single endpoint is ready.
+///
+/// Clones of a tracker record the same endpoint, so the endpoint is counted
+/// until all of them are dropped.
+#[derive(Clone, Debug)]
+pub struct Tracker(Arc<Endpoint>);
+
+/// Annotates the endpoints of made `Discover`s so that their readiness is
+/// recorded for targets that have a [`Readiness`].
+#[derive(Clone, Debug)]
+pub struct MakeTrackReadiness<M> {
+    inner: M,
+}
+
+#[pin_project]
+pub struct TrackReadiness<D> {
+    #[pin]
+    discover: D,
+    readiness: Option<Readiness>,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    endpoints: AtomicUsize,
+    ready: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    counts: Arc<Counts>,
+    ready: AtomicBool,
+}
+
+// === impl Readiness ===
+
+impl Readiness {
+    /// Returns the fraction of endpoints that are ready.
+    ///
+    /// A balancer without endpoints (e.g. before its endpoints have been
+    /// discovered) is not considered degraded, so the ratio is one.
+    pub fn ratio(&self) -> f64 {
+        let endpoints = self.0.endpoints.load(Ordering::Acquire);
+        if endpoints == 0 {
+            return 1.0;
+        }
+        let ready = self.0.ready.load(Ordering::Acquire);
+        ready as f64 / endpoints as f64
+    }
+
+    pub(crate) fn tracker(&self) -> Tracker {
+        self.0.endpoints.fetch_add(1, Ordering::AcqRel);
+        Tracker(Arc::new(Endpoint {
+            counts: self.0.clone(),
+            ready: AtomicBool::new(false),
+        }))
+    }
+}
+
+// === impl Tracker ===
+
+impl Tracker {
+    /// Records the outcome of polling the endpoint for readiness.
+    pub(crate) fn update<E>(&mut self, poll: &Poll<Result<(), E>>) {
+        let ready = matches!(poll, Poll::Ready(Ok(())));
+        let Endpoint { counts, ready: was } = &*self.0;
+        if was.swap(ready, Ordering::AcqRel) != ready {
+            if ready {
+                counts.ready.fetch_add(1, Ordering::AcqRel);
+            } else {
+                counts.ready.fetch_sub(1, Ordering::AcqRel);
+            }
+        }
+    }
+}
+
+// === impl Endpoint ===
+
+impl Drop for Endpoint {
+    fn drop(&mut self) {
+        self.counts.endpoints.fetch_sub(1, Ordering::AcqRel);
+        if *self.ready.get_mut() {
+            self.counts.ready.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+// === impl MakeTrackReadiness ===
+
+impl<M> MakeTrackReadiness<M> {
+    pub fn layer() -> impl svc::layer::Layer<M, Service = Self> + Clone + Copy {
+        svc::layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, M> svc::Service<T> for MakeTrackReadiness<M>
+where
+    T: svc::Param<Option<Readiness>>,
+    M: svc::Service<T>,
+    M::Future: Send + 'static,
+{
+    type Response = TrackReadiness<M::Response>;
+    type Error = M::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, M::Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        let readiness = target.param();
+        Box::pin(
+            self.inner
+                .call(target)
+                .map_ok(move |discover| TrackReadiness {
+                    discover,
+                    readiness,
+                }),
+        )
+    }
+}
+
+// === impl TrackReadiness ===
+
+impl<D, S> Stream for TrackReadiness<D>
+where
+    D: Discover<Service = Weighted<S>>,
+{
+    type Item = Result<Change<D::Key, Weighted<S>>, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let change = futures::ready!(this.discover.poll_discover(cx));
+        Poll::Ready(change.map(|change| {
+            change.map(|change| match change {
+                Change::Insert(key, mut svc) => {
+                    svc.readiness = this.readiness.as_ref().map(Readiness::tracker);
+                    Change::Insert(key, svc)
+                }
+                remove => remove,
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_ready_endpoints() {
+        let readiness = Readiness::default();
+        assert_eq!(readiness.ratio(), 1.0, "no endpoints is not degraded");
+
+        let mut a = readiness.tracker();
+        let mut b = readiness.tracker();
+        assert_eq!(readiness.ratio(), 0.0);
+
+        a.update::<()>(&Poll::Ready(Ok(())));
+        assert_eq!(readiness.ratio(), 0.5);
+        b.update::<()>(&Poll::Ready(Ok(())));
+        assert_eq!(readiness.ratio(), 1.0);
+        b.update::<()>(&Poll::Pending);
+        assert_eq!(readiness.ratio(), 0.5);
+        a.update(&Poll::Ready(Err(())));
+        assert_eq!(readiness.ratio(), 0.0);
+
+        b.update::<()>(&Poll::Ready(Ok(())));
+        drop(a);
+        assert_eq!(readiness.ratio(), 1.0);
+        drop(b);
+        assert_eq!(readiness.ratio(), 1.0);
+    }
+
+    #[test]
+    fn clones_track_the_same_endpoint() {
+        let readiness = Readiness::default();
+        let mut a = readiness.tracker();
+        let _b = readiness.tracker();
+        a.update::<()>(&Poll::Ready(Ok(())));
+        assert_eq!(readiness.ratio(), 0.5);
+
+        let mut a2 = a.clone();
+        assert_eq!(readiness.ratio(), 0.5);
+        a2.update::<()>(&Poll::Pending);
+        assert_eq!(readiness.ratio(), 0.0);
+
+        a2.update::<()>(&Poll::Ready(Ok(())));
+        drop(a);
+        assert_eq!(readiness.ratio(), 0.5);
+        drop(a2);
+        assert_eq!(readiness.ratio(), 0.0);
+    }
+}
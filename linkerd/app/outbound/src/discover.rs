@@ -1,4 +1,4 @@
-use crate::{tcp, Outbound};
+use crate::{route_table, tcp, Outbound};
 use linkerd_app_core::{
     io, profiles,
     svc::{self, stack::Param},
@@ -37,7 +37,15 @@ impl<N> Outbound<N> {
     {
         self.map_stack(|config, rt, accept| {
             let allow = config.allow_discovery.clone();
+
+            // Accepted connections are dispatched to either the TCP or HTTP
+            // logical stacks, depending on the detected protocol.
+            let introspect = &rt.metrics.proxy.introspect;
+            let server = introspect.register(crate::stack_labels("tcp", "server"));
+            server.push_inner(&introspect.register(crate::stack_labels("tcp", "logical")));
+            server.push_inner(&introspect.register(crate::stack_labels("http", "logical")));
             accept
+                .push(route_table::NewRecordProfile::layer(rt.route_table.clone()))
                 .push(profiles::discover::layer(
                     profiles,
                     move |a: tcp::Accept| {
@@ -71,16 +79,13 @@ impl<N> Outbound<N> {
                                 .stack
                                 .layer(crate::stack_labels("tcp", "server")),
                         )
-                        .push(svc::FailFast::layer(
-                            "TCP Server",
-                            config.proxy.dispatch_timeout,
-                        ))
+                        .push(server.failfast_layer("TCP Server", config.proxy.dispatch_timeout))
                         .push_spawn_buffer(config.proxy.buffer_capacity),
                 )
                 .push(transport::metrics::NewServer::layer(
                     rt.metrics.proxy.transport.clone(),
                 ))
-                .push_cache(config.proxy.cache_max_idle_age)
+                .push(server.cache_layer(config.proxy.cache_max_idle_age))
                 .instrument(|a: &tcp::Accept| info_span!("server", orig_dst = %a.orig_dst))
                 .push_request_filter(|t: T| tcp::Accept::try_from(t.param()))
                 .push(rt.metrics.tcp_errors.to_layer())
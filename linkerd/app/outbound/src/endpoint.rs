@@ -1,11 +1,12 @@
-use crate::{http, logical::Concrete, tcp, Outbound};
+use crate::{balance, http, logical::Concrete, tcp, Outbound};
 use linkerd_app_core::{
-    io, metrics,
+    access_log, io,
+    metrics::{self, Direction},
     profiles::LogicalAddr,
     proxy::{api_resolve::Metadata, resolve::map_endpoint::MapEndpoint},
     svc, tls,
     transport::{self, addrs::*},
-    transport_header, Conditional,
+    transport_header, Addr, Conditional,
 };
 use std::{
     collections::HashSet,
@@ -83,6 +84,30 @@ impl<P> svc::Param<tls::ConditionalClientTls> for Endpoint<P> {
     }
 }
 
+impl<P> svc::Param<access_log::ConnectionTarget> for Endpoint<P> {
+    fn param(&self) -> access_log::ConnectionTarget {
+        let Remote(ServerAddr(addr)) = self.addr;
+        access_log::ConnectionTarget {
+            addr: addr.into(),
+            identity: self.tls.value().map(|tls| tls.server_id.0.clone()),
+        }
+    }
+}
+
+/// The target port, used to select the endpoint's bandwidth limit.
+impl<P> svc::Param<u16> for Endpoint<P> {
+    fn param(&self) -> u16 {
+        self.addr.port()
+    }
+}
+
+impl<P> svc::Param<Addr> for Endpoint<P> {
+    fn param(&self) -> Addr {
+        let Remote(ServerAddr(addr)) = self.addr;
+        addr.into()
+    }
+}
+
 impl<P> svc::Param<Option<http::detect::Skip>> for Endpoint<P> {
     fn param(&self) -> Option<http::detect::Skip> {
         if self.opaque_protocol {
@@ -101,6 +126,18 @@ impl<P> svc::Param<Option<tcp::opaque_transport::PortOverride>> for Endpoint<P>
     }
 }
 
+impl<P> svc::Param<Option<LogicalAddr>> for Endpoint<P> {
+    fn param(&self) -> Option<LogicalAddr> {
+        self.logical_addr.clone()
+    }
+}
+
+impl<P> svc::Param<balance::EndpointWeight> for Endpoint<P> {
+    fn param(&self) -> balance::EndpointWeight {
+        balance::EndpointWeight::from_metadata(&self.metadata)
+    }
+}
+
 impl<P> svc::Param<Option<http::AuthorityOverride>> for Endpoint<P> {
     fn param(&self) -> Option<http::AuthorityOverride> {
         self.metadata
@@ -216,10 +253,10 @@ impl<S> Outbound<S> {
     where
         Self: Clone + 'static,
         S: svc::Service<tcp::Connect, Error = io::Error> + Clone + Send + Sync + Unpin + 'static,
-        S::Response:
-            tls::HasNegotiatedProtocol + io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
+        S::Response: tls::HasNegotiatedProtocol + io::AsyncRead + io::AsyncWrite + io::Splice,
+        S::Response: Send + Unpin + 'static,
         S::Future: Send + Unpin,
-        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr,
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + io::Splice,
         I: fmt::Debug + Send + Sync + Unpin + 'static,
     {
         let http = self
@@ -231,6 +268,18 @@ impl<S> Outbound<S> {
 
         self.push_tcp_endpoint()
             .push_tcp_forward()
+            .map_stack(|config, rt, tcp| {
+                tcp.push(transport::bandwidth::NewThrottle::layer(
+                    config.tcp_bandwidth_limits.clone(),
+                ))
+                .push(tcp::proxy_protocol::NewEmitProxyProtocol::layer(
+                    config.proxy_protocol.clone(),
+                ))
+                .push(access_log::NewLogConnections::layer(
+                    rt.access_log.clone(),
+                    Direction::Out,
+                ))
+            })
             .push_detect_http(http)
     }
 }
@@ -0,0 +1,217 @@
+//! Priority-based failover between endpoint sets.
+//!
+//! A logical service may be configured with a secondary service (e.g. the
+//! same service in a remote cluster) to which it fails over. Requests are
+//! balanced over the primary service's endpoints for as long as a sufficient
+//! fraction of them are ready. When the fraction of ready primary endpoints
+//! drops below the configured threshold, requests are instead balanced over
+//! the secondary service's endpoints. The primary balancer continues to be
+//! driven while failed over, so that requests fail back to it automatically
+//! once enough of its endpoints are ready again.
+
+use crate::{balance::Readiness, logical::Concrete};
+use futures::prelude::*;
+use linkerd_app_core::{
+    profiles::LogicalAddr, proxy::api_resolve::ConcreteAddr, svc, Error, NameAddr,
+};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::info;
+
+/// Maps logical service addresses to the services to which they fail over.
+#[derive(Clone, Debug, Default)]
+pub struct Failovers(Arc<HashMap<NameAddr, Target>>);
+
+/// Configures a service's failover.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    /// The service to which requests are sent while the primary is failed
+    /// over.
+    pub secondary: NameAddr,
+
+    /// The minimum fraction of the primary service's endpoints that must be
+    /// ready for it to be used.
+    pub threshold: f64,
+}
+
+/// Builds a pair of balancers for each concrete service that is configured to
+/// fail over.
+#[derive(Clone, Debug)]
+pub struct NewFailover<N> {
+    inner: N,
+    failovers: Failovers,
+}
+
+/// Dispatches requests to either the primary or secondary balancer, depending
+/// on the readiness of the primary balancer's endpoints.
+#[derive(Clone, Debug)]
+pub struct Failover<S> {
+    primary: S,
+    secondary: S,
+    readiness: Readiness,
+    threshold: f64,
+    failed_over: bool,
+}
+
+// === impl Failovers ===
+
+impl Failovers {
+    pub fn new(failovers: impl IntoIterator<Item = (NameAddr, Target)>) -> Self {
+        Self(Arc::new(failovers.into_iter().collect()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, LogicalAddr(addr): &LogicalAddr) -> Option<&Target> {
+        self.0.get(addr)
+    }
+}
+
+// === impl NewFailover ===
+
+impl<N> NewFailover<N> {
+    pub fn layer(failovers: Failovers) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            failovers: failovers.clone(),
+        })
+    }
+}
+
+impl<P, N> svc::NewService<Concrete<P>> for NewFailover<N>
+where
+    P: Clone,
+    N: svc::NewService<Concrete<P>>,
+{
+    type Service = svc::Either<N::Service, Failover<N::Service>>;
+
+    fn new_service(&mut self, concrete: Concrete<P>) -> Self::Service {
+        let target = match self.failovers.get(&concrete.logical.logical_addr) {
+            Some(target) => target.clone(),
+            None => return svc::Either::A(self.inner.new_service(concrete)),
+        };
+
+        let readiness = Readiness::default();
+        let secondary = self.inner.new_service(Concrete {
+            resolve: ConcreteAddr(target.secondary),
+            logical: concrete.logical.clone(),
+            readiness: None,
+        });
+        let primary = self.inner.new_service(Concrete {
+            readiness: Some(readiness.clone()),
+            ..concrete
+        });
+        svc::Either::B(Failover {
+            primary,
+            secondary,
+            readiness,
+            threshold: target.threshold,
+            failed_over: false,
+        })
+    }
+}
+
+// === impl Failover ===
+
+impl<S> Failover<S> {
+    /// Fails over or back if the primary's readiness has crossed the
+    /// threshold.
+    fn update(&mut self) {
+        let ratio = self.readiness.ratio();
+        if self.failed_over {
+            if ratio >= self.threshold {
+                info!(ratio, "Failing back to primary endpoints");
+                self.failed_over = false;
+            }
+        } else if ratio < self.threshold {
+            info!(ratio, "Failing over to secondary endpoints");
+            self.failed_over = true;
+        }
+    }
+}
+
+impl<Req, S> svc::Service<Req> for Failover<S>
+where
+    S: svc::Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::ErrInto<S::Future, Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        // The primary is always polled so that its endpoints are discovered
+        // and driven to readiness, even while it is failed over.
+        let primary = self.primary.poll_ready(cx).map_err(Into::into);
+        self.update();
+        if !self.failed_over {
+            return primary;
+        }
+
+        if let Poll::Ready(Err(e)) = primary {
+            return Poll::Ready(Err(e));
+        }
+        self.secondary.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        if self.failed_over {
+            self.secondary.call(req).err_into()
+        } else {
+            self.primary.call(req).err_into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::ServiceExt;
+
+    fn respond(
+        name: &'static str,
+    ) -> impl svc::Service<
+        (),
+        Response = &'static str,
+        Error = Error,
+        Future = future::Ready<Result<&'static str, Error>>,
+    > {
+        svc::mk(move |()| future::ok(name))
+    }
+
+    #[tokio::test]
+    async fn fails_over_and_back() {
+        let readiness = Readiness::default();
+        let mut failover = Failover {
+            primary: respond("primary"),
+            secondary: respond("secondary"),
+            readiness: readiness.clone(),
+            threshold: 0.5,
+            failed_over: false,
+        };
+
+        let mut a = readiness.tracker();
+        let mut b = readiness.tracker();
+        a.update::<()>(&Poll::Ready(Ok(())));
+        b.update::<()>(&Poll::Ready(Ok(())));
+        let rsp = failover.ready().await.unwrap().call(()).await.unwrap();
+        assert_eq!(rsp, "primary");
+
+        a.update::<()>(&Poll::Pending);
+        let rsp = failover.ready().await.unwrap().call(()).await.unwrap();
+        assert_eq!(rsp, "primary", "must not fail over at the threshold");
+
+        b.update::<()>(&Poll::Pending);
+        let rsp = failover.ready().await.unwrap().call(()).await.unwrap();
+        assert_eq!(rsp, "secondary");
+
+        a.update::<()>(&Poll::Ready(Ok(())));
+        let rsp = failover.ready().await.unwrap().call(()).await.unwrap();
+        assert_eq!(rsp, "primary");
+    }
+}
@@ -0,0 +1,366 @@
+//! Active health checking of balanced endpoints.
+//!
+//! When a logical service is configured with a health check, each of its
+//! endpoints is checked periodically over a dedicated client--so checks share
+//! the endpoint's connection settings, including mTLS--independently of the
+//! endpoints' status in service discovery. An endpoint that fails its checks
+//! enough times in a row is marked unhealthy and is not ready until it passes
+//! its checks enough times in a row, so the balancer sends it no requests in
+//! the meantime.
+
+use crate::http;
+use futures::prelude::*;
+use linkerd_app_core::{
+    profiles::LogicalAddr,
+    svc::{self, ServiceExt},
+    transport::{Remote, ServerAddr},
+    Error, NameAddr,
+};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{sync::watch, time};
+use tracing::{debug, debug_span, warn, Instrument};
+
+const USER_AGENT: &str = "linkerd-proxy-health-check";
+
+/// Maps logical service addresses to the health checks of their endpoints.
+#[derive(Clone, Debug, Default)]
+pub struct HealthChecks(Arc<HashMap<NameAddr, Check>>);
+
+/// Configures how a service's endpoints are checked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Check {
+    pub kind: CheckKind,
+    pub interval: Duration,
+    pub timeout: Duration,
+    /// The number of consecutive passed checks after which an unhealthy
+    /// endpoint is marked healthy.
+    pub healthy_threshold: u32,
+    /// The number of consecutive failed checks after which a healthy endpoint
+    /// is marked unhealthy.
+    pub unhealthy_threshold: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckKind {
+    /// Endpoints pass when a `GET` request for the path succeeds.
+    Http(http::uri::PathAndQuery),
+    /// Endpoints pass when a connection can be established.
+    Tcp,
+}
+
+/// Builds health check requests for `Rsp`-typed endpoint services.
+pub(crate) trait CheckRequest<Rsp>: Sized {
+    /// Returns a request that checks the endpoint, if the check applies to
+    /// this kind of service.
+    fn check_request(kind: &CheckKind, dst: &NameAddr) -> Option<Self>;
+
+    fn is_healthy(rsp: &Rsp) -> bool;
+}
+
+/// Spawns a health check for each endpoint of a service that is configured to
+/// be checked.
+pub struct NewHealthCheck<Req, N> {
+    inner: N,
+    checks: HealthChecks,
+    _marker: PhantomData<fn(Req)>,
+}
+
+pub struct HealthChecked<S> {
+    inner: S,
+    health: Option<Health>,
+}
+
+struct Health {
+    rx: watch::Receiver<bool>,
+    changed: Option<Pin<Box<dyn Future<Output = bool> + Send + 'static>>>,
+}
+
+// === impl HealthChecks ===
+
+impl HealthChecks {
+    pub fn new(checks: impl IntoIterator<Item = (NameAddr, Check)>) -> Self {
+        Self(Arc::new(checks.into_iter().collect()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, LogicalAddr(addr): &LogicalAddr) -> Option<&Check> {
+        self.0.get(addr)
+    }
+}
+
+// === impl CheckRequest ===
+
+impl CheckRequest<http::Response<http::BoxBody>> for http::Request<http::BoxBody> {
+    fn check_request(kind: &CheckKind, dst: &NameAddr) -> Option<Self> {
+        let path = match kind {
+            CheckKind::Http(path) => path,
+            CheckKind::Tcp => return None,
+        };
+        let uri = http::Uri::builder()
+            .scheme(http::uri::Scheme::HTTP)
+            .authority(dst.to_string())
+            .path_and_query(path.clone())
+            .build()
+            .ok()?;
+        http::Request::get(uri)
+            .header(http::header::USER_AGENT, USER_AGENT)
+            .body(http::BoxBody::default())
+            .ok()
+    }
+
+    fn is_healthy(rsp: &http::Response<http::BoxBody>) -> bool {
+        rsp.status().is_success()
+    }
+}
+
+impl<I> CheckRequest<I> for () {
+    fn check_request(kind: &CheckKind, _: &NameAddr) -> Option<Self> {
+        match kind {
+            CheckKind::Tcp => Some(()),
+            CheckKind::Http(_) => None,
+        }
+    }
+
+    fn is_healthy(_: &I) -> bool {
+        true
+    }
+}
+
+// === impl NewHealthCheck ===
+
+impl<Req, N> NewHealthCheck<Req, N> {
+    pub fn layer(checks: HealthChecks) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            checks: checks.clone(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<Req, N: Clone> Clone for NewHealthCheck<Req, N> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            checks: self.checks.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, Req, N> svc::NewService<T> for NewHealthCheck<Req, N>
+where
+    T: svc::Param<Option<LogicalAddr>> + svc::Param<Remote<ServerAddr>> + Clone,
+    N: svc::NewService<T>,
+    N::Service: svc::Service<Req> + Send + 'static,
+    <N::Service as svc::Service<Req>>::Error: Into<Error>,
+    <N::Service as svc::Service<Req>>::Future: Send,
+    Req: CheckRequest<<N::Service as svc::Service<Req>>::Response> + Send + 'static,
+{
+    type Service = HealthChecked<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let health = svc::Param::<Option<LogicalAddr>>::param(&target).and_then(|logical| {
+            let check = self.checks.get(&logical)?.clone();
+            if Req::check_request(&check.kind, &logical.0).is_none() {
+                warn!(?check.kind, %logical, "Health check does not apply to this protocol");
+                return None;
+            }
+
+            let Remote(ServerAddr(addr)) = target.param();
+            let client = self.inner.new_service(target.clone());
+            let (tx, rx) = watch::channel(true);
+            tokio::spawn(
+                run::<_, Req>(client, check, logical.0, tx)
+                    .instrument(debug_span!("health", server.addr = %addr)),
+            );
+            Some(Health { rx, changed: None })
+        });
+
+        HealthChecked {
+            inner: self.inner.new_service(target),
+            health,
+        }
+    }
+}
+
+/// Checks an endpoint until its service is dropped.
+async fn run<S, Req>(mut client: S, check: Check, dst: NameAddr, tx: watch::Sender<bool>)
+where
+    S: svc::Service<Req>,
+    S::Error: Into<Error>,
+    Req: CheckRequest<S::Response>,
+{
+    let mut healthy = true;
+    let mut passed = 0;
+    let mut failed = 0;
+    let mut interval = time::interval(check.interval);
+    loop {
+        tokio::select! {
+            _ = tx.closed() => return,
+            _ = interval.tick() => {}
+        }
+
+        let req = match Req::check_request(&check.kind, &dst) {
+            Some(req) => req,
+            None => return,
+        };
+        let call = client
+            .ready()
+            .err_into::<Error>()
+            .and_then(|svc| svc.call(req).err_into());
+        let pass = match time::timeout(check.timeout, call).await {
+            Ok(Ok(rsp)) => Req::is_healthy(&rsp),
+            Ok(Err(error)) => {
+                debug!(%error, "Health check failed");
+                false
+            }
+            Err(_) => {
+                debug!(timeout = ?check.timeout, "Health check timed out");
+                false
+            }
+        };
+
+        if pass {
+            failed = 0;
+            passed += 1;
+            if !healthy && passed >= check.healthy_threshold {
+                debug!(passed, "Endpoint is healthy");
+                healthy = true;
+                let _ = tx.send(true);
+            }
+        } else {
+            passed = 0;
+            failed += 1;
+            if healthy && failed >= check.unhealthy_threshold {
+                debug!(failed, "Endpoint is unhealthy");
+                healthy = false;
+                let _ = tx.send(false);
+            }
+        }
+    }
+}
+
+// === impl HealthChecked ===
+
+impl<S, Req> svc::Service<Req> for HealthChecked<S>
+where
+    S: svc::Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        // Unhealthy endpoints are not ready until they pass their checks.
+        if let Some(Health { rx, changed }) = self.health.as_mut() {
+            while !*rx.borrow() {
+                let future = changed.get_or_insert_with(|| {
+                    let mut rx = rx.clone();
+                    Box::pin(async move { rx.changed().await.is_ok() })
+                });
+                let checking = futures::ready!(future.as_mut().poll(cx));
+                *changed = None;
+                if !checking {
+                    // The checks have stopped, so the endpoint's health is no
+                    // longer known.
+                    self.health = None;
+                    break;
+                }
+            }
+        }
+
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::Layer;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[derive(Clone)]
+    struct Target(LogicalAddr);
+
+    impl svc::Param<Option<LogicalAddr>> for Target {
+        fn param(&self) -> Option<LogicalAddr> {
+            Some(self.0.clone())
+        }
+    }
+
+    impl svc::Param<Remote<ServerAddr>> for Target {
+        fn param(&self) -> Remote<ServerAddr> {
+            Remote(ServerAddr(([10, 0, 0, 1], 5432).into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn marks_failing_endpoints_unready() {
+        let dst = "db.ns.svc.cluster.local:5432".parse::<NameAddr>().unwrap();
+        let checks = HealthChecks::new(vec![(
+            dst.clone(),
+            Check {
+                kind: CheckKind::Tcp,
+                interval: Duration::from_millis(10),
+                timeout: Duration::from_millis(10),
+                healthy_threshold: 2,
+                unhealthy_threshold: 2,
+            },
+        )]);
+
+        let up = Arc::new(AtomicBool::new(true));
+        let connect = {
+            let up = up.clone();
+            move |_: Target| {
+                let up = up.clone();
+                svc::mk(move |()| {
+                    let res = if up.load(Ordering::SeqCst) {
+                        Ok(())
+                    } else {
+                        Err(Error::from("connection refused"))
+                    };
+                    future::ready(res)
+                })
+            }
+        };
+        let mut new_checked = NewHealthCheck::<(), _>::layer(checks).layer(connect);
+        let mut svc = svc::NewService::new_service(&mut new_checked, Target(LogicalAddr(dst)));
+
+        let ready = Duration::from_secs(1);
+        time::timeout(ready, svc.ready())
+            .await
+            .expect("healthy endpoint must be ready")
+            .unwrap();
+
+        up.store(false, Ordering::SeqCst);
+        time::sleep(Duration::from_millis(100)).await;
+        assert!(
+            time::timeout(Duration::from_millis(50), svc.ready())
+                .await
+                .is_err(),
+            "unhealthy endpoint must not be ready"
+        );
+
+        up.store(true, Ordering::SeqCst);
+        time::timeout(ready, svc.ready())
+            .await
+            .expect("recovered endpoint must be ready")
+            .unwrap();
+    }
+}
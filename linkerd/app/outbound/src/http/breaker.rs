@@ -0,0 +1,524 @@
+//! Circuit breaking for outbound HTTP endpoints.
+//!
+//! Failfast only reacts to services that do not become ready, so an endpoint
+//! that readily accepts requests but fails them is unaffected by it. When a
+//! logical service is configured with a circuit breaker, each of its
+//! endpoints' responses are observed instead. Once an endpoint has failed too
+//! many requests--either a number of consecutive requests, or a fraction of the
+//! requests in a window--its breaker *opens*: the endpoint is not ready, so the
+//! balancer sends it no requests. After a time, the breaker is *half-open* and
+//! admits a limited number of probe requests. It closes once enough probes
+//! succeed, or opens again as soon as one fails.
+//!
+//! Server errors, and requests that fail without a response, are counted as
+//! failures.
+
+use futures::prelude::*;
+use linkerd_app_core::{
+    metrics::{metrics, Counter, FmtLabels, FmtMetrics},
+    profiles::LogicalAddr,
+    proxy::http,
+    svc, Error, NameAddr,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use tokio::time::{self, Instant};
+use tracing::debug;
+
+metrics! {
+    outbound_http_circuit_breaker_transitions_total: Counter {
+        "The total number of times that endpoints' circuit breakers have changed state, by the state entered"
+    }
+}
+
+/// Maps logical service addresses to the circuit breakers of their endpoints.
+#[derive(Clone, Debug, Default)]
+pub struct CircuitBreakers(Arc<HashMap<NameAddr, Breaker>>);
+
+/// Configures the circuit breakers of a service's endpoints.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Breaker {
+    pub trip: Trip,
+
+    /// The time for which an open breaker rejects requests before probing the
+    /// endpoint.
+    pub open_time: Duration,
+
+    /// The number of probe requests that must succeed for a half-open breaker
+    /// to close. No more than this many probes are in flight at once.
+    pub probes: u32,
+}
+
+/// Determines when a closed breaker opens.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trip {
+    /// The breaker opens after this many consecutive failures.
+    ConsecutiveFailures(u32),
+
+    /// The breaker opens when at least `ratio` of the requests that complete
+    /// in a `window` fail, once at least `min_requests` have completed.
+    FailureRatio {
+        ratio: f64,
+        window: Duration,
+        min_requests: u32,
+    },
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<TransitionMetrics>);
+
+/// Wraps the endpoints of services that are configured with circuit breakers.
+#[derive(Clone, Debug)]
+pub struct NewCircuitBreaker<N> {
+    inner: N,
+    breakers: CircuitBreakers,
+    metrics: Metrics,
+}
+
+pub struct CircuitBreaker<S> {
+    inner: S,
+    state: Option<Arc<Mutex<State>>>,
+    open: Option<Pin<Box<time::Sleep>>>,
+}
+
+#[derive(Debug, Default)]
+struct TransitionMetrics {
+    opened: Counter,
+    half_opened: Counter,
+    closed: Counter,
+}
+
+struct StateLabel(&'static str);
+
+#[derive(Debug)]
+struct State {
+    config: Breaker,
+    metrics: Metrics,
+    circuit: Circuit,
+    /// The task that is waiting for a half-open breaker to admit a probe.
+    waker: Option<Waker>,
+}
+
+#[derive(Debug)]
+enum Circuit {
+    Closed(Failures),
+    Open { until: Instant },
+    HalfOpen { probing: u32, succeeded: u32 },
+}
+
+/// Counts the failures of a closed breaker's requests.
+#[derive(Debug)]
+struct Failures {
+    consecutive: u32,
+    window_start: Instant,
+    requests: u32,
+    failed: u32,
+}
+
+/// Records the outcome of a request, releasing its probe if the request is
+/// canceled.
+struct Outcome {
+    state: Arc<Mutex<State>>,
+    probe: bool,
+}
+
+// === impl CircuitBreakers ===
+
+impl CircuitBreakers {
+    pub fn new(breakers: impl IntoIterator<Item = (NameAddr, Breaker)>) -> Self {
+        Self(Arc::new(breakers.into_iter().collect()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, LogicalAddr(addr): &LogicalAddr) -> Option<&Breaker> {
+        self.0.get(addr)
+    }
+}
+
+// === impl Metrics ===
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        outbound_http_circuit_breaker_transitions_total.fmt_help(f)?;
+        for (state, counter) in &[
+            ("open", &self.0.opened),
+            ("half_open", &self.0.half_opened),
+            ("closed", &self.0.closed),
+        ] {
+            outbound_http_circuit_breaker_transitions_total.fmt_metric_labeled(
+                f,
+                *counter,
+                StateLabel(*state),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FmtLabels for StateLabel {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "state=\"{}\"", self.0)
+    }
+}
+
+// === impl NewCircuitBreaker ===
+
+impl<N> NewCircuitBreaker<N> {
+    pub fn layer(
+        breakers: CircuitBreakers,
+        metrics: Metrics,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            breakers: breakers.clone(),
+            metrics: metrics.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewCircuitBreaker<N>
+where
+    T: svc::Param<Option<LogicalAddr>>,
+    N: svc::NewService<T>,
+{
+    type Service = CircuitBreaker<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let state = svc::Param::<Option<LogicalAddr>>::param(&target).and_then(|logical| {
+            let config = self.breakers.get(&logical)?.clone();
+            Some(Arc::new(Mutex::new(State {
+                config,
+                metrics: self.metrics.clone(),
+                circuit: Circuit::Closed(Failures::new(Instant::now())),
+                waker: None,
+            })))
+        });
+        CircuitBreaker {
+            inner: self.inner.new_service(target),
+            state,
+            open: None,
+        }
+    }
+}
+
+// === impl CircuitBreaker ===
+
+impl<S, A, B> svc::Service<http::Request<A>> for CircuitBreaker<S>
+where
+    S: svc::Service<http::Request<A>, Response = http::Response<B>>,
+    S::Error: Into<Error>,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        if let Some(state) = self.state.as_ref() {
+            let mut state = state.lock();
+            if let Circuit::Open { until } = state.circuit {
+                let open = self
+                    .open
+                    .get_or_insert_with(|| Box::pin(time::sleep_until(until)));
+                if open.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                self.open = None;
+                state.half_open();
+            }
+            if let Circuit::HalfOpen { probing, .. } = state.circuit {
+                if probing >= state.config.probes {
+                    state.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+        }
+
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let state = match self.state.clone() {
+            Some(state) => state,
+            None => return Box::pin(self.inner.call(req).err_into::<Error>()),
+        };
+
+        let probe = match state.lock().circuit {
+            Circuit::HalfOpen {
+                ref mut probing, ..
+            } => {
+                *probing += 1;
+                true
+            }
+            _ => false,
+        };
+        let outcome = Outcome { state, probe };
+        Box::pin(self.inner.call(req).err_into::<Error>().map(move |res| {
+            outcome.record(match res {
+                Ok(ref rsp) => rsp.status().is_server_error(),
+                Err(_) => true,
+            });
+            res
+        }))
+    }
+}
+
+// === impl State ===
+
+impl State {
+    fn open(&mut self, now: Instant) {
+        debug!(open_time = ?self.config.open_time, "Opening circuit breaker");
+        self.metrics.0.opened.incr();
+        self.circuit = Circuit::Open {
+            until: now + self.config.open_time,
+        };
+    }
+
+    fn half_open(&mut self) {
+        debug!("Half-opening circuit breaker");
+        self.metrics.0.half_opened.incr();
+        self.circuit = Circuit::HalfOpen {
+            probing: 0,
+            succeeded: 0,
+        };
+    }
+
+    fn close(&mut self, now: Instant) {
+        debug!("Closing circuit breaker");
+        self.metrics.0.closed.incr();
+        self.circuit = Circuit::Closed(Failures::new(now));
+    }
+
+    fn record(&mut self, failure: bool, probe: bool) {
+        let now = Instant::now();
+        match self.circuit {
+            Circuit::Closed(ref mut failures) => {
+                if failures.record(&self.config.trip, now, failure) {
+                    self.open(now);
+                }
+            }
+            Circuit::HalfOpen {
+                ref mut probing,
+                ref mut succeeded,
+            } if probe => {
+                *probing = probing.saturating_sub(1);
+                if failure {
+                    self.open(now);
+                } else {
+                    *succeeded += 1;
+                    if *succeeded >= self.config.probes {
+                        self.close(now);
+                    }
+                }
+                if let Some(waker) = self.waker.take() {
+                    waker.wake();
+                }
+            }
+            // Responses to requests that were sent before the breaker last
+            // changed state are ignored.
+            _ => {}
+        }
+    }
+
+    fn release_probe(&mut self) {
+        if let Circuit::HalfOpen {
+            ref mut probing, ..
+        } = self.circuit
+        {
+            *probing = probing.saturating_sub(1);
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+// === impl Failures ===
+
+impl Failures {
+    fn new(now: Instant) -> Self {
+        Self {
+            consecutive: 0,
+            window_start: now,
+            requests: 0,
+            failed: 0,
+        }
+    }
+
+    /// Records the outcome of a request, returning true if the breaker trips.
+    fn record(&mut self, trip: &Trip, now: Instant, failure: bool) -> bool {
+        match *trip {
+            Trip::ConsecutiveFailures(max) => {
+                self.consecutive = if failure { self.consecutive + 1 } else { 0 };
+                self.consecutive >= max
+            }
+            Trip::FailureRatio {
+                ratio,
+                window,
+                min_requests,
+            } => {
+                if now.saturating_duration_since(self.window_start) > window {
+                    *self = Self::new(now);
+                }
+                self.requests += 1;
+                if failure {
+                    self.failed += 1;
+                }
+                self.requests >= min_requests && self.failed as f64 >= ratio * self.requests as f64
+            }
+        }
+    }
+}
+
+// === impl Outcome ===
+
+impl Outcome {
+    fn record(mut self, failure: bool) {
+        self.state.lock().record(failure, self.probe);
+        self.probe = false;
+    }
+}
+
+impl Drop for Outcome {
+    fn drop(&mut self) {
+        if self.probe {
+            self.state.lock().release_probe();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::{Layer, NewService, ServiceExt};
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use tokio_test::{assert_pending, assert_ready_ok, task};
+
+    #[derive(Clone)]
+    struct Target;
+
+    impl svc::Param<Option<LogicalAddr>> for Target {
+        fn param(&self) -> Option<LogicalAddr> {
+            Some(LogicalAddr("web.ns.svc.cluster.local:80".parse().unwrap()))
+        }
+    }
+
+    fn breaker(
+        trip: Trip,
+        status: Arc<AtomicU16>,
+    ) -> (
+        Metrics,
+        impl svc::Service<http::Request<()>, Response = http::Response<()>, Error = Error>,
+    ) {
+        let metrics = Metrics::default();
+        let breakers = CircuitBreakers::new(vec![(
+            "web.ns.svc.cluster.local:80".parse().unwrap(),
+            Breaker {
+                trip,
+                open_time: Duration::from_secs(10),
+                probes: 2,
+            },
+        )]);
+        let svc = NewCircuitBreaker::layer(breakers, metrics.clone())
+            .layer(move |_: Target| {
+                let status = status.clone();
+                svc::mk(move |_: http::Request<()>| {
+                    let mut rsp = http::Response::new(());
+                    *rsp.status_mut() =
+                        http::StatusCode::from_u16(status.load(Ordering::SeqCst)).unwrap();
+                    future::ok::<_, Error>(rsp)
+                })
+            })
+            .new_service(Target);
+        (metrics, svc)
+    }
+
+    async fn send<S>(svc: &mut S) -> http::StatusCode
+    where
+        S: svc::Service<http::Request<()>, Response = http::Response<()>, Error = Error>,
+    {
+        svc.ready()
+            .await
+            .unwrap()
+            .call(http::Request::new(()))
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn opens_after_consecutive_failures() {
+        time::pause();
+        let status = Arc::new(AtomicU16::new(500));
+        let (metrics, mut svc) = breaker(Trip::ConsecutiveFailures(3), status.clone());
+
+        send(&mut svc).await;
+        send(&mut svc).await;
+        assert_eq!(metrics.0.opened.value(), 0.0);
+        send(&mut svc).await;
+        assert_eq!(metrics.0.opened.value(), 1.0);
+
+        // The endpoint is not ready while the breaker is open.
+        {
+            let mut ready = task::spawn(svc.ready());
+            assert_pending!(ready.poll());
+            time::advance(Duration::from_secs(11)).await;
+            assert_ready_ok!(ready.poll());
+        }
+        assert_eq!(metrics.0.half_opened.value(), 1.0);
+
+        // A failed probe opens the breaker again...
+        send(&mut svc).await;
+        assert_eq!(metrics.0.opened.value(), 2.0);
+        time::advance(Duration::from_secs(11)).await;
+
+        // ...and it closes once enough probes have succeeded.
+        status.store(200, Ordering::SeqCst);
+        send(&mut svc).await;
+        assert_eq!(metrics.0.closed.value(), 0.0);
+        send(&mut svc).await;
+        assert_eq!(metrics.0.closed.value(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn opens_on_failure_ratio() {
+        time::pause();
+        let status = Arc::new(AtomicU16::new(200));
+        let (metrics, mut svc) = breaker(
+            Trip::FailureRatio {
+                ratio: 0.5,
+                window: Duration::from_secs(10),
+                min_requests: 4,
+            },
+            status.clone(),
+        );
+
+        send(&mut svc).await;
+        send(&mut svc).await;
+        status.store(503, Ordering::SeqCst);
+        send(&mut svc).await;
+        assert_eq!(metrics.0.opened.value(), 0.0, "too few requests");
+
+        // Requests in a past window are forgotten.
+        time::advance(Duration::from_secs(11)).await;
+        status.store(200, Ordering::SeqCst);
+        send(&mut svc).await;
+        status.store(503, Ordering::SeqCst);
+        send(&mut svc).await;
+        send(&mut svc).await;
+        assert_eq!(metrics.0.opened.value(), 0.0);
+        send(&mut svc).await;
+        assert_eq!(metrics.0.opened.value(), 1.0);
+    }
+}
@@ -0,0 +1,614 @@
+//! Adaptive concurrency limits for outbound HTTP endpoints.
+//!
+//! When a logical service is configured with a concurrency limit, each of its
+//! endpoints learns how many requests it can sustain in flight from the
+//! latencies of its responses. An endpoint is not ready while its limit is
+//! reached, so that the balancer sends requests to its other endpoints (or,
+//! when all of its endpoints are limited, holds requests until one becomes
+//! ready or the balancer's dispatch timeout elapses).
+//!
+//! Limits are learned by one of two algorithms:
+//!
+//! - *AIMD* grows the limit by one for each timely response while the limit is
+//!   in use, and shrinks it by a constant factor when a request fails or its
+//!   response exceeds a latency threshold.
+//! - *Gradient* compares each response's latency to the endpoint's long-term
+//!   average latency, shrinking the limit as latency grows and growing it (by
+//!   roughly its square root) while latency holds steady.
+
+use futures::prelude::*;
+use linkerd_app_core::{
+    errors::ConcurrencyLimitExceeded,
+    metrics::{metrics, Counter, FmtLabels, FmtMetrics, Gauge, LabelValue},
+    profiles::LogicalAddr,
+    proxy::http,
+    svc, Error, NameAddr,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use tokio::time::Instant;
+use tracing::debug;
+
+metrics! {
+    outbound_http_endpoint_concurrency_limit: Gauge {
+        "The sum of the learned concurrency limits of a service's endpoints"
+    },
+    outbound_http_endpoint_requests_in_flight: Gauge {
+        "The number of requests in flight to a service's concurrency-limited endpoints"
+    },
+    outbound_http_endpoint_concurrency_limited_total: Counter {
+        "The total number of times an endpoint became unavailable because its concurrency limit was reached"
+    }
+}
+
+/// The number of samples over which the gradient algorithm averages an
+/// endpoint's long-term latency.
+const GRADIENT_LONG_WINDOW: f64 = 600.0;
+
+/// The weight given to each new limit computed by the gradient algorithm.
+const GRADIENT_SMOOTHING: f64 = 0.2;
+
+/// Maps logical service addresses to the concurrency limits of their
+/// endpoints.
+#[derive(Clone, Debug, Default)]
+pub struct ConcurrencyLimits(Arc<HashMap<NameAddr, Limit>>);
+
+/// Configures the concurrency limits of a service's endpoints.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Limit {
+    pub algorithm: Algorithm,
+
+    /// The limit of each endpoint before any responses have been observed.
+    pub initial: usize,
+
+    /// Bounds on the learned limit.
+    pub min: usize,
+    pub max: usize,
+}
+
+/// Determines how an endpoint's limit is learned.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Algorithm {
+    /// The limit grows by one when a response arrives within `latency` while
+    /// at least half of the limit is in use, and is multiplied by `backoff`
+    /// when a request fails or its response takes longer.
+    Aimd { latency: Duration, backoff: f64 },
+
+    /// The limit shrinks when response latency exceeds the endpoint's
+    /// long-term latency by more than the `tolerance` factor.
+    Gradient { tolerance: f64 },
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<Mutex<HashMap<NameAddr, Arc<ServiceMetrics>>>>);
+
+/// Limits the concurrency of the endpoints of services that are configured
+/// with concurrency limits.
+#[derive(Clone, Debug)]
+pub struct NewConcurrencyLimit<N> {
+    inner: N,
+    limits: ConcurrencyLimits,
+    metrics: Metrics,
+}
+
+#[derive(Debug)]
+pub struct ConcurrencyLimit<S> {
+    inner: S,
+    state: Option<Arc<Mutex<State>>>,
+    /// Whether this service has reserved a request slot in `poll_ready`.
+    reserved: bool,
+    /// Whether this service was last polled while its limit was reached.
+    limited: bool,
+}
+
+#[derive(Debug, Default)]
+struct ServiceMetrics {
+    limit: Gauge,
+    in_flight: Gauge,
+    limited: Counter,
+}
+
+struct DstLabel<'a>(&'a NameAddr);
+
+#[derive(Debug)]
+struct State {
+    config: Limit,
+    metrics: Arc<ServiceMetrics>,
+    limit: f64,
+    /// The number of requests in flight, including those reserved by ready
+    /// services.
+    in_flight: usize,
+    /// The gradient algorithm's average response latency, in seconds.
+    long_latency: Option<f64>,
+    /// Tasks waiting for the number of requests in flight to fall below the
+    /// limit.
+    waiters: Vec<Waker>,
+}
+
+/// Tracks a request in flight, recording its latency once its response
+/// arrives.
+struct InFlight {
+    state: Arc<Mutex<State>>,
+    in_flight: usize,
+    start: Instant,
+}
+
+// === impl ConcurrencyLimits ===
+
+impl ConcurrencyLimits {
+    pub fn new(limits: impl IntoIterator<Item = (NameAddr, Limit)>) -> Self {
+        Self(Arc::new(limits.into_iter().collect()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, LogicalAddr(addr): &LogicalAddr) -> Option<&Limit> {
+        self.0.get(addr)
+    }
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    fn service(&self, addr: &NameAddr) -> Arc<ServiceMetrics> {
+        self.0.lock().entry(addr.clone()).or_default().clone()
+    }
+}
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let services = self.0.lock();
+        if services.is_empty() {
+            return Ok(());
+        }
+
+        outbound_http_endpoint_concurrency_limit.fmt_help(f)?;
+        for (addr, m) in services.iter() {
+            outbound_http_endpoint_concurrency_limit.fmt_metric_labeled(
+                f,
+                &m.limit,
+                DstLabel(addr),
+            )?;
+        }
+
+        outbound_http_endpoint_requests_in_flight.fmt_help(f)?;
+        for (addr, m) in services.iter() {
+            outbound_http_endpoint_requests_in_flight.fmt_metric_labeled(
+                f,
+                &m.in_flight,
+                DstLabel(addr),
+            )?;
+        }
+
+        outbound_http_endpoint_concurrency_limited_total.fmt_help(f)?;
+        for (addr, m) in services.iter() {
+            outbound_http_endpoint_concurrency_limited_total.fmt_metric_labeled(
+                f,
+                &m.limited,
+                DstLabel(addr),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FmtLabels for DstLabel<'_> {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "dst=\"{}\"", LabelValue(&self.0.to_string()))
+    }
+}
+
+// === impl NewConcurrencyLimit ===
+
+impl<N> NewConcurrencyLimit<N> {
+    pub fn layer(
+        limits: ConcurrencyLimits,
+        metrics: Metrics,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            limits: limits.clone(),
+            metrics: metrics.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewConcurrencyLimit<N>
+where
+    T: svc::Param<Option<LogicalAddr>>,
+    N: svc::NewService<T>,
+{
+    type Service = ConcurrencyLimit<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let state = svc::Param::<Option<LogicalAddr>>::param(&target).and_then(|logical| {
+            let config = self.limits.get(&logical)?.clone();
+            let metrics = self.metrics.service(&logical.0);
+            let limit = config.initial as f64;
+            metrics.limit.add(limit as u64);
+            Some(Arc::new(Mutex::new(State {
+                config,
+                metrics,
+                limit,
+                in_flight: 0,
+                long_latency: None,
+                waiters: Vec::new(),
+            })))
+        });
+        ConcurrencyLimit {
+            inner: self.inner.new_service(target),
+            state,
+            reserved: false,
+            limited: false,
+        }
+    }
+}
+
+// === impl ConcurrencyLimit ===
+
+impl<S: Clone> Clone for ConcurrencyLimit<S> {
+    fn clone(&self) -> Self {
+        // Reservations are not shared with clones.
+        Self {
+            inner: self.inner.clone(),
+            state: self.state.clone(),
+            reserved: false,
+            limited: false,
+        }
+    }
+}
+
+impl<S, A, B> svc::Service<http::Request<A>> for ConcurrencyLimit<S>
+where
+    S: svc::Service<http::Request<A>, Response = http::Response<B>>,
+    S::Error: Into<Error>,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        // A request slot is reserved before the inner service is polled, so
+        // that the endpoint is not ready while its limit is reached.
+        if let (Some(state), false) = (self.state.as_ref(), self.reserved) {
+            let mut state = state.lock();
+            let limit = state.limit();
+            if state.in_flight >= limit {
+                if !self.limited {
+                    debug!(limit, "Concurrency limit reached");
+                    state.metrics.limited.incr();
+                    self.limited = true;
+                }
+                if !state.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                    state.waiters.push(cx.waker().clone());
+                }
+                return Poll::Pending;
+            }
+            state.in_flight += 1;
+            self.reserved = true;
+            self.limited = false;
+        }
+
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let state = match self.state.clone() {
+            Some(state) => state,
+            None => return Box::pin(self.inner.call(req).err_into::<Error>()),
+        };
+
+        // The service must have been driven to readiness, reserving a slot,
+        // before it is called.
+        if !std::mem::replace(&mut self.reserved, false) {
+            let limit = state.lock().limit();
+            return Box::pin(future::err::<S::Response, Error>(
+                ConcurrencyLimitExceeded(limit).into(),
+            ));
+        }
+        let in_flight = {
+            let state = state.lock();
+            state.metrics.in_flight.incr();
+            state.in_flight
+        };
+        let in_flight = InFlight {
+            state,
+            in_flight,
+            start: Instant::now(),
+        };
+        Box::pin(self.inner.call(req).err_into::<Error>().map(move |res| {
+            in_flight.record(match res {
+                Ok(ref rsp) => rsp.status().is_server_error(),
+                Err(_) => true,
+            });
+            res
+        }))
+    }
+}
+
+impl<S> Drop for ConcurrencyLimit<S> {
+    fn drop(&mut self) {
+        if let (Some(state), true) = (self.state.as_ref(), self.reserved) {
+            state.lock().release();
+        }
+    }
+}
+
+// === impl State ===
+
+impl State {
+    fn limit(&self) -> usize {
+        self.limit as usize
+    }
+
+    /// Releases a request slot, waking the tasks waiting for one.
+    fn release(&mut self) {
+        self.in_flight -= 1;
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        if self.in_flight < self.limit() {
+            for waker in self.waiters.drain(..) {
+                waker.wake();
+            }
+        }
+    }
+
+    fn set_limit(&mut self, limit: f64) {
+        let limit = limit
+            .max(self.config.min as f64)
+            .min(self.config.max as f64);
+        let (prev, next) = (self.limit as u64, limit as u64);
+        if next > prev {
+            self.metrics.limit.add(next - prev);
+        } else {
+            self.metrics.limit.sub(prev - next);
+        }
+        if next != prev {
+            debug!(limit = next, "Updated concurrency limit");
+        }
+        self.limit = limit;
+        self.wake();
+    }
+
+    /// Updates the limit from the outcome of a request that was sent with
+    /// `in_flight` requests in flight.
+    fn record(&mut self, latency: Duration, failure: bool, in_flight: usize) {
+        // While less than half of the limit is in use, responses say little
+        // about whether the endpoint could sustain a higher limit.
+        let limited = in_flight * 2 >= self.limit();
+        match self.config.algorithm {
+            Algorithm::Aimd {
+                latency: max_latency,
+                backoff,
+            } => {
+                if failure || latency > max_latency {
+                    self.set_limit(self.limit * backoff);
+                } else if limited {
+                    self.set_limit(self.limit + 1.0);
+                }
+            }
+
+            Algorithm::Gradient { tolerance } => {
+                if failure {
+                    return;
+                }
+                let latency = latency.as_secs_f64().max(f64::EPSILON);
+                let mut long = match self.long_latency {
+                    Some(long) => long + (latency - long) / GRADIENT_LONG_WINDOW,
+                    None => latency,
+                };
+                // Once an endpoint recovers from a period of high latency, its
+                // long-term latency decays quickly so that its limit is not
+                // held up by the stale average.
+                if long > latency * 2.0 {
+                    long *= 0.95;
+                }
+                self.long_latency = Some(long);
+
+                let gradient = (tolerance * long / latency).max(0.5).min(1.0);
+                if gradient >= 1.0 && !limited {
+                    return;
+                }
+                let target = self.limit * gradient + self.limit.sqrt();
+                self.set_limit(
+                    self.limit * (1.0 - GRADIENT_SMOOTHING) + target * GRADIENT_SMOOTHING,
+                );
+            }
+        }
+    }
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        self.metrics.limit.sub(self.limit as u64);
+    }
+}
+
+// === impl InFlight ===
+
+impl InFlight {
+    fn record(self, failure: bool) {
+        let latency = self.start.elapsed();
+        self.state.lock().record(latency, failure, self.in_flight);
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut state = self.state.lock();
+        state.metrics.in_flight.decr();
+        state.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::{Layer, NewService, ServiceExt};
+    use tokio::{
+        sync::{mpsc, oneshot},
+        time,
+    };
+
+    #[derive(Clone)]
+    struct Target;
+
+    impl svc::Param<Option<LogicalAddr>> for Target {
+        fn param(&self) -> Option<LogicalAddr> {
+            Some(LogicalAddr("web.ns.svc.cluster.local:80".parse().unwrap()))
+        }
+    }
+
+    fn limits(algorithm: Algorithm) -> ConcurrencyLimits {
+        ConcurrencyLimits::new(vec![(
+            "web.ns.svc.cluster.local:80".parse().unwrap(),
+            Limit {
+                algorithm,
+                initial: 2,
+                min: 1,
+                max: 10,
+            },
+        )])
+    }
+
+    fn gauges(metrics: &Metrics) -> (u64, u64, f64) {
+        let m = metrics.service(&"web.ns.svc.cluster.local:80".parse().unwrap());
+        (m.limit.value(), m.in_flight.value(), m.limited.value())
+    }
+
+    #[tokio::test]
+    async fn not_ready_over_limit() {
+        let metrics = Metrics::default();
+        let (tx, mut rx) = mpsc::unbounded_channel::<oneshot::Sender<()>>();
+        let mut svc = NewConcurrencyLimit::layer(
+            limits(Algorithm::Aimd {
+                latency: Duration::from_secs(1),
+                backoff: 0.5,
+            }),
+            metrics.clone(),
+        )
+        .layer(move |_: Target| {
+            let tx = tx.clone();
+            svc::mk(move |_: http::Request<()>| {
+                let (done, wait) = oneshot::channel();
+                tx.send(done).unwrap();
+                wait.map(|_| Ok::<_, Error>(http::Response::new(())))
+            })
+        })
+        .new_service(Target);
+
+        let first = tokio::spawn(svc.ready().await.unwrap().call(http::Request::new(())));
+        let second = tokio::spawn(svc.ready().await.unwrap().call(http::Request::new(())));
+        let first_done = rx.recv().await.unwrap();
+        let second_done = rx.recv().await.unwrap();
+        assert_eq!(gauges(&metrics), (2, 2, 0.0));
+
+        // The limit is reached, so the service is not ready until a request
+        // completes.
+        let mut ready = tokio_test::task::spawn(svc.ready());
+        assert!(ready.poll().is_pending());
+        assert_eq!(gauges(&metrics), (2, 2, 1.0));
+
+        // Timely responses grow the limit.
+        first_done.send(()).unwrap();
+        first.await.unwrap().unwrap();
+        assert!(ready.is_woken());
+        assert!(ready.poll().is_ready());
+        drop(ready);
+        second_done.send(()).unwrap();
+        second.await.unwrap().unwrap();
+        assert_eq!(gauges(&metrics), (4, 0, 1.0));
+
+        drop(svc);
+        assert_eq!(gauges(&metrics), (0, 0, 1.0));
+    }
+
+    #[tokio::test]
+    async fn aimd_backs_off_on_slow_responses() {
+        time::pause();
+        let metrics = Metrics::default();
+        let mut svc = NewConcurrencyLimit::layer(
+            limits(Algorithm::Aimd {
+                latency: Duration::from_secs(1),
+                backoff: 0.5,
+            }),
+            metrics.clone(),
+        )
+        .layer(|_: Target| {
+            svc::mk(|_: http::Request<()>| {
+                time::sleep(Duration::from_secs(2))
+                    .map(|()| Ok::<_, Error>(http::Response::new(())))
+            })
+        })
+        .new_service(Target);
+
+        svc.ready()
+            .await
+            .unwrap()
+            .call(http::Request::new(()))
+            .await
+            .unwrap();
+        assert_eq!(gauges(&metrics), (1, 0, 0.0));
+
+        // The limit is never reduced below its minimum.
+        svc.ready()
+            .await
+            .unwrap()
+            .call(http::Request::new(()))
+            .await
+            .unwrap();
+        assert_eq!(gauges(&metrics), (1, 0, 0.0));
+    }
+
+    #[test]
+    fn gradient_shrinks_as_latency_grows() {
+        let mut state = State {
+            config: Limit {
+                algorithm: Algorithm::Gradient { tolerance: 1.5 },
+                initial: 10,
+                min: 1,
+                max: 100,
+            },
+            metrics: Arc::new(ServiceMetrics {
+                limit: 10.into(),
+                ..Default::default()
+            }),
+            limit: 10.0,
+            in_flight: 0,
+            long_latency: None,
+            waiters: Vec::new(),
+        };
+
+        // Steady latency grows the limit while it's in use.
+        for _ in 0..10 {
+            let in_flight = state.limit();
+            state.record(Duration::from_millis(10), false, in_flight);
+        }
+        let grown = state.limit;
+        assert!(grown > 10.0, "{}", grown);
+
+        // ...but not while it's underused.
+        state.record(Duration::from_millis(10), false, 1);
+        assert!((state.limit - grown).abs() < f64::EPSILON);
+
+        // Latency well above the long-term average shrinks the limit.
+        for _ in 0..10 {
+            state.record(Duration::from_millis(100), false, 1);
+        }
+        assert!(state.limit < grown, "{} < {}", state.limit, grown);
+    }
+}
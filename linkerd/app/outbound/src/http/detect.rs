@@ -3,13 +3,95 @@ use linkerd_app_core::{
     config::ServerConfig,
     detect, io,
     svc::{self, Param},
-    Error, Infallible,
+    Addr, Error, Infallible, IpNet,
 };
-use tracing::debug_span;
+use std::{str::FromStr, sync::Arc};
+use thiserror::Error;
+use tracing::{debug, debug_span};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Skip;
 
+/// Destinations for which protocol detection is skipped, regardless of
+/// discovery, e.g. because they serve server-speaks-first protocols like MySQL
+/// or SMTP.
+///
+/// Each rule matches a port, a network, or a port within a network.
+#[derive(Clone, Debug, Default)]
+pub struct SkipDetect(Arc<[SkipRule]>);
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("detection skip rules must be formatted as `port`, `cidr`, or `cidr:port`: {0}")]
+pub struct InvalidSkipDetect(String);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct SkipRule {
+    net: Option<IpNet>,
+    port: Option<u16>,
+}
+
+// === impl SkipDetect ===
+
+impl SkipDetect {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns true if protocol detection should be skipped for connections to
+    /// `addr`.
+    ///
+    /// Named addresses only match rules that do not specify a network.
+    pub fn matches(&self, addr: &Addr) -> bool {
+        self.0.iter().any(|rule| {
+            let port_matches = rule.port.map(|p| p == addr.port()).unwrap_or(true);
+            let net_matches = match (rule.net, addr) {
+                (None, _) => true,
+                (Some(net), Addr::Socket(sa)) => net.contains(&sa.ip()),
+                (Some(_), Addr::Name(_)) => false,
+            };
+            port_matches && net_matches
+        })
+    }
+}
+
+/// Parses a comma-separated list of rules, e.g.
+/// `3306,25,10.1.0.0/16,192.168.0.0/24:5432`.
+impl FromStr for SkipDetect {
+    type Err = InvalidSkipDetect;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rules = s
+            .split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(|r| {
+                let invalid = || InvalidSkipDetect(r.to_string());
+                if !r.contains('/') {
+                    let port = r.parse().map_err(|_| invalid())?;
+                    return Ok(SkipRule {
+                        net: None,
+                        port: Some(port),
+                    });
+                }
+
+                // A port may follow the network's prefix length.
+                let (net, port) = match r.rsplitn(2, ':').collect::<Vec<_>>().as_slice() {
+                    [port, net] if !port.contains('/') => {
+                        (*net, Some(port.parse().map_err(|_| invalid())?))
+                    }
+                    _ => (r, None),
+                };
+                let net = net.parse().map_err(|_| invalid())?;
+                Ok(SkipRule {
+                    net: Some(net),
+                    port,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self(rules.into()))
+    }
+}
+
 impl<N> Outbound<N> {
     pub fn push_detect_http<T, U, NSvc, H, HSvc, I>(self, http: H) -> Outbound<svc::BoxNewTcp<T, I>>
     where
@@ -25,11 +107,12 @@ impl<N> Outbound<N> {
         HSvc: Clone + Send + Sync + Unpin + 'static,
         HSvc::Error: Into<Error>,
         HSvc::Future: Send,
-        T: Param<Option<Skip>> + Clone + Send + Sync + 'static,
+        T: Param<Option<Skip>> + Param<Addr> + Clone + Send + Sync + 'static,
         U: From<(http::Version, T)> + svc::Param<http::Version> + 'static,
     {
         self.map_stack(|config, rt, tcp| {
             let ServerConfig { h2_settings, .. } = config.proxy.server;
+            let skip_detect = config.skip_detect.clone();
 
             let skipped = tcp
                 .clone()
@@ -58,10 +141,17 @@ impl<N> Outbound<N> {
                 .push_switch(
                     // When the target is marked as as opaque, we skip HTTP
                     // detection and just use the TCP stack directly.
-                    |target: T| -> Result<_, Infallible> {
+                    move |target: T| -> Result<_, Infallible> {
                         if let Some(Skip) = target.param() {
                             return Ok(svc::Either::B(target));
                         }
+                        if !skip_detect.is_empty() {
+                            let addr: Addr = target.param();
+                            if skip_detect.matches(&addr) {
+                                debug!(%addr, "Skipping protocol detection");
+                                return Ok(svc::Either::B(target));
+                            }
+                        }
                         Ok(svc::Either::A(target))
                     },
                     skipped,
@@ -72,3 +162,32 @@ impl<N> Outbound<N> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_skip_rules() {
+        assert!("".parse::<SkipDetect>().unwrap().is_empty());
+        assert!("mysql".parse::<SkipDetect>().is_err());
+        assert!("10.0.0.0/8:smtp".parse::<SkipDetect>().is_err());
+        assert!("10.0.0.0/33".parse::<SkipDetect>().is_err());
+
+        let skip = "3306, 10.1.0.0/16, 192.168.0.0/24:25, fd00::/8:5432"
+            .parse::<SkipDetect>()
+            .unwrap();
+        let socket = |s: &str| Addr::Socket(s.parse().unwrap());
+        assert!(skip.matches(&socket("192.0.2.1:3306")));
+        assert!(skip.matches(&socket("10.1.2.3:8080")));
+        assert!(skip.matches(&socket("192.168.0.10:25")));
+        assert!(!skip.matches(&socket("192.168.0.10:80")));
+        assert!(skip.matches(&socket("[fd00::1]:5432")));
+        assert!(!skip.matches(&socket("[fd00::1]:80")));
+        assert!(!skip.matches(&socket("192.0.2.1:80")));
+
+        let name = |s: &str| Addr::Name(s.parse().unwrap());
+        assert!(skip.matches(&name("mysql.example.com:3306")));
+        assert!(!skip.matches(&name("smtp.example.com:25")));
+    }
+}
@@ -1,7 +1,7 @@
 use super::{peer_proxy_errors::PeerProxyErrors, require_id_header};
 use crate::Outbound;
 use linkerd_app_core::{
-    classify, config, errors, http_tracing, metrics,
+    classify, config, deadline, errors, http_tracing, metrics, peer_version,
     proxy::{http, tap},
     svc, tls, Error, Result, CANONICAL_DST_HEADER,
 };
@@ -59,9 +59,15 @@ impl<C> Outbound<C> {
                 )
                 .push_on_service(http_tracing::client(
                     rt.span_sink.clone(),
+                    rt.trace_precedence,
                     crate::trace_labels(),
                 ))
                 .push(require_id_header::NewRequireIdentity::layer())
+                // Informs meshed endpoints of the local proxy's version.
+                .push(peer_version::NewAdvertiseVersion::layer())
+                // Enforces request deadlines, propagating the time that
+                // remains upstream.
+                .push_on_service(deadline::EnforceDeadline::layer())
                 .push(http::NewOverrideAuthority::layer(vec![
                     "host",
                     CANONICAL_DST_HEADER,
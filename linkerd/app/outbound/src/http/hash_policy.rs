@@ -0,0 +1,180 @@
+//! Configures consistent-hash load balancing for outbound HTTP services.
+//!
+//! By default, requests are balanced over a service's endpoints by latency.
+//! Services that depend on session affinity (e.g. caches, or websocket
+//! fan-in) may instead be configured to hash a request key onto a ring of
+//! endpoints, so that requests with the same key are sent to the same endpoint
+//! for as long as it is available.
+
+use crate::ring_hash::HashRequest;
+use fnv::FnvHasher;
+use linkerd_app_core::{profiles::LogicalAddr, proxy::http, NameAddr};
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    str::FromStr,
+    sync::Arc,
+};
+use thiserror::Error;
+
+/// Maps logical service addresses to the request keys used to balance them.
+#[derive(Clone, Debug, Default)]
+pub struct HashPolicies(Arc<HashMap<NameAddr, HashKey>>);
+
+/// The part of a request that is hashed to select an endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HashKey {
+    /// The value of the named header.
+    Header(http::HeaderName),
+    /// The request's authority.
+    Authority,
+    /// The request's path.
+    Path,
+    /// The endpoint named by the request's sticky session cookie. See
+    /// [`super::StickySessions`].
+    Cookie(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("hash policies must be formatted as `authority=header:<name>`, `authority=authority`, or `authority=path`: {0}")]
+pub struct InvalidHashPolicy(String);
+
+// === impl HashPolicies ===
+
+impl HashPolicies {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the key used to balance requests to the given logical address,
+    /// if the service is balanced by consistent hashing.
+    pub fn get(&self, LogicalAddr(addr): &LogicalAddr) -> Option<HashKey> {
+        self.0.get(addr).cloned()
+    }
+}
+
+/// Parses a comma-separated list of `authority=key` policies.
+impl FromStr for HashPolicies {
+    type Err = InvalidHashPolicy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let policies = s
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let invalid = || InvalidHashPolicy(p.to_string());
+                let mut parts = p.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(addr), Some(key)) => {
+                        let addr = NameAddr::from_str(addr.trim()).map_err(|_| invalid())?;
+                        let key = key.trim().parse().map_err(|_| invalid())?;
+                        Ok((addr, key))
+                    }
+                    _ => Err(invalid()),
+                }
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(Self(Arc::new(policies)))
+    }
+}
+
+// === impl HashKey ===
+
+impl FromStr for HashKey {
+    type Err = InvalidHashPolicy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "authority" => Ok(Self::Authority),
+            "path" => Ok(Self::Path),
+            _ => s
+                .strip_prefix("header:")
+                .and_then(|name| http::HeaderName::from_str(name).ok())
+                .map(Self::Header)
+                .ok_or_else(|| InvalidHashPolicy(s.to_string())),
+        }
+    }
+}
+
+impl<B> HashRequest<http::Request<B>> for HashKey {
+    fn hash_request(&self, req: &http::Request<B>) -> Option<u64> {
+        let mut hasher = FnvHasher::default();
+        match self {
+            Self::Header(name) => req.headers().get(name)?.as_bytes().hash(&mut hasher),
+            Self::Authority => req
+                .uri()
+                .authority()
+                .map(|a| a.as_str())
+                .or_else(|| req.headers().get(http::header::HOST)?.to_str().ok())?
+                .hash(&mut hasher),
+            Self::Path => req.uri().path().hash(&mut hasher),
+            // Session cookies hold the hash of an endpoint, so they are not
+            // hashed again.
+            Self::Cookie(name) => return super::sticky::session_hash(req.headers(), name),
+        }
+        Some(hasher.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_policies() {
+        let policies = "cache.ns.svc.cluster.local:11211=header:x-session-id, \
+                        web.ns.svc.cluster.local:80=authority, \
+                        files.ns.svc.cluster.local:8080=path"
+            .parse::<HashPolicies>()
+            .unwrap();
+        let get = |addr: &str| policies.get(&LogicalAddr(addr.parse().unwrap()));
+        assert_eq!(
+            get("cache.ns.svc.cluster.local:11211"),
+            Some(HashKey::Header(http::HeaderName::from_static(
+                "x-session-id"
+            )))
+        );
+        assert_eq!(get("web.ns.svc.cluster.local:80"), Some(HashKey::Authority));
+        assert_eq!(get("files.ns.svc.cluster.local:8080"), Some(HashKey::Path));
+        assert_eq!(get("other.ns.svc.cluster.local:80"), None);
+
+        assert!("web.ns.svc.cluster.local:80"
+            .parse::<HashPolicies>()
+            .is_err());
+        assert!("web.ns.svc.cluster.local:80=cookie"
+            .parse::<HashPolicies>()
+            .is_err());
+        assert!("web.ns.svc.cluster.local:80=header:"
+            .parse::<HashPolicies>()
+            .is_err());
+    }
+
+    #[test]
+    fn hashes_request_keys() {
+        let key = HashKey::Header(http::HeaderName::from_static("x-session-id"));
+        let req = |session: &str, path: &str| {
+            http::Request::builder()
+                .uri(format!("http://web.ns.svc.cluster.local{}", path))
+                .header("x-session-id", session)
+                .body(())
+                .unwrap()
+        };
+
+        assert_eq!(
+            key.hash_request(&req("a", "/foo")),
+            key.hash_request(&req("a", "/bar"))
+        );
+        assert_ne!(
+            key.hash_request(&req("a", "/foo")),
+            key.hash_request(&req("b", "/foo"))
+        );
+        let no_header = http::Request::builder().body(()).unwrap();
+        assert_eq!(key.hash_request(&no_header), None);
+
+        assert_ne!(
+            HashKey::Path.hash_request(&req("a", "/foo")),
+            HashKey::Path.hash_request(&req("a", "/bar"))
+        );
+    }
+}
@@ -1,14 +1,17 @@
 use super::{CanonicalDstHeader, Concrete, Endpoint, Logical};
-use crate::{endpoint, resolve, stack_labels, Outbound};
+use crate::{
+    balance, endpoint, failover, health, multicluster, resolve, ring_hash, route_table,
+    stack_labels, Outbound,
+};
 use linkerd_app_core::{
-    classify, config, dst, profiles,
+    classify, config, dst, fault, header_policy, hedge, http_tracing, metrics, profiles,
     proxy::{
         api_resolve::{ConcreteAddr, Metadata},
         core::Resolve,
         http,
         resolve::map_endpoint,
     },
-    retry, svc, Error, Infallible,
+    retry, rls, route_filter, svc, Error, Infallible,
 };
 use tracing::debug_span;
 
@@ -37,12 +40,49 @@ impl<E> Outbound<E> {
             } = config.proxy;
             let watchdog = cache_max_idle_age * 2;
 
+            let retry_policy = {
+                let mut policy =
+                    retry::NewRetryPolicy::new(rt.metrics.proxy.http_route_retry.clone())
+                        .with_max_buffered_bytes(config.retry_max_buffered_bytes)
+                        .with_max_retry_after(config.retry_after_max)
+                        .with_backoffs(config.retry_backoffs.clone())
+                        .with_per_try_timeouts(config.retry_per_try_timeouts.clone());
+                if config.retry_unprocessed {
+                    policy = policy.with_unprocessed_retries();
+                }
+                match config.retry_suppression_after {
+                    Some(after) => {
+                        policy.with_suppression(rt.metrics.retry_suppressions.clone(), after)
+                    }
+                    None => policy,
+                }
+            };
+            let hedge_policy = hedge::NewHedgePolicy::new(
+                config.http_hedges.clone(),
+                config.retry_max_buffered_bytes,
+            );
+
+            let introspect = &rt.metrics.proxy.introspect;
+            let logical = introspect.register(stack_labels("http", "logical"));
+            let balancer = introspect.register(stack_labels("http", "balancer"));
+            let balance_endpoint = introspect.register(stack_labels("http", "balance.endpoint"));
+            logical.push_inner(&balancer);
+            balancer.push_inner(&balance_endpoint);
+
             let endpoint =
                 endpoint.instrument(|e: &Endpoint| debug_span!("endpoint", server.addr = %e.addr));
 
+            let hash_policies = config.http_hash_policies.clone();
+            let sticky_sessions = config.http_sticky_sessions.clone();
+            let algorithms = config.balancer_algorithms.clone();
+            let zone_affinity = config.zone_affinity.clone();
             let identity_disabled = rt.identity.is_none();
             let resolve = svc::stack(resolve.into_service())
                 .check_service::<ConcreteAddr>()
+                .push(multicluster::Resolve::layer(
+                    config.multicluster_failovers.clone(),
+                ))
+                .push(route_table::RecordResolve::layer(rt.route_table.clone()))
                 .push_request_filter(|c: Concrete| Ok::<_, Infallible>(c.resolve))
                 .push(svc::layer::mk(move |inner| {
                     map_endpoint::Resolve::new(
@@ -72,6 +112,25 @@ impl<E> Outbound<E> {
                         // the balancer need not drive them all directly.
                         .push(svc::layer::mk(svc::SpawnReady::new)),
                 )
+                .push(http::NewSetSessionCookie::layer(
+                    config.http_sticky_sessions.clone(),
+                ))
+                .push(http::breaker::NewCircuitBreaker::layer(
+                    config.http_circuit_breakers.clone(),
+                    rt.metrics.circuit_breakers.clone(),
+                ))
+                .push(http::concurrency::NewConcurrencyLimit::layer(
+                    config.http_concurrency_limits.clone(),
+                    rt.metrics.concurrency_limits.clone(),
+                ))
+                .push(
+                    health::NewHealthCheck::<http::Request<http::BoxBody>, _>::layer(
+                        config.health_checks.clone(),
+                    ),
+                )
+                .push(balance::NewWeighted::layer(
+                    config.balancer_algorithms.clone(),
+                ))
                 .check_new_service::<Endpoint, http::Request<_>>()
                 // Resolve the service to its endpoints and balance requests over them.
                 //
@@ -79,12 +138,38 @@ impl<E> Outbound<E> {
                 // When the balancer is in failfast, spawn the service in a background
                 // task so it becomes ready without new requests.
                 .push(resolve::layer(resolve, watchdog))
-                .push_on_service(
+                .push(balance::MakeTrackReadiness::layer())
+                // Services with sticky sessions or a hashing policy are
+                // balanced by consistent hashing, and round-robin services by
+                // a ring without a hasher; all others are balanced by load.
+                .push(ring_hash::MakeBalance::layer(
                     svc::layers()
-                        .push(http::balance::layer(
-                            crate::EWMA_DEFAULT_RTT,
-                            crate::EWMA_DECAY,
+                        .push(balance::layer(
+                            config.ewma,
+                            http::balance::PendingUntilFirstData::default(),
+                            zone_affinity,
+                            config.slow_start_window,
+                            config.outlier_detection.clone(),
+                            rt.metrics.outliers.clone(),
                         ))
+                        .push(http::BoxResponse::layer()),
+                    http::BoxResponse::layer(),
+                    move |c: &Concrete| {
+                        let addr = &c.logical.logical_addr;
+                        if let Some(cookie) = sticky_sessions.get(addr) {
+                            return Some(Some(http::HashKey::Cookie(cookie.name.clone())));
+                        }
+                        match hash_policies.get(addr) {
+                            Some(key) => Some(Some(key)),
+                            None if algorithms.get(addr) == balance::Algorithm::RoundRobin => {
+                                Some(None)
+                            }
+                            None => None,
+                        }
+                    },
+                ))
+                .push_on_service(
+                    svc::layers()
                         .push(
                             rt.metrics
                                 .proxy
@@ -92,7 +177,7 @@ impl<E> Outbound<E> {
                                 .layer(stack_labels("http", "balancer")),
                         )
                         .push(svc::layer::mk(svc::SpawnReady::new))
-                        .push(svc::FailFast::layer("HTTP Balancer", dispatch_timeout))
+                        .push(balancer.failfast_layer("HTTP Balancer", dispatch_timeout))
                         .push(http::BoxResponse::layer()),
                 )
                 .check_make_service::<Concrete, http::Request<_>>()
@@ -103,6 +188,16 @@ impl<E> Outbound<E> {
                 // resolved. Endpoint resolution is skipped when there is no
                 // concrete address.
                 .instrument(|c: &Concrete| debug_span!("concrete", addr = %c.resolve))
+                // Mirrored services send copies of some requests to their
+                // shadow services. This is beneath failover so that shadow
+                // requests are sent directly to the shadow service.
+                .push(http::mirror::NewMirror::layer(
+                    config.http_mirrors.clone(),
+                    rt.metrics.mirrors.clone(),
+                ))
+                // Services with a failover are balanced over their secondary
+                // endpoints while too few of their primary endpoints are ready.
+                .push(failover::NewFailover::layer(config.failovers.clone()))
                 .push_map_target(Concrete::from)
                 .push(svc::BoxNewService::layer())
                 // Distribute requests over a distribution of balancers via a
@@ -122,11 +217,20 @@ impl<E> Outbound<E> {
                                 .stack
                                 .layer(stack_labels("http", "logical")),
                         )
-                        .push(svc::FailFast::layer("HTTP Logical", dispatch_timeout))
+                        .push(logical.failfast_layer("HTTP Logical", dispatch_timeout))
                         .push_spawn_buffer(buffer_capacity),
                 )
-                .push_cache(cache_max_idle_age)
+                .push(logical.cache_layer(cache_max_idle_age))
                 .push_on_service(http::BoxResponse::layer())
+                // Checks requests with the rate limit service, if one is
+                // configured. This is beneath the route layer so that each
+                // request may be described by its route, and the proxy's own
+                // identity is used as the client's.
+                .push(rls::NewCheckRateLimit::layer_via(
+                    rt.rate_limit.clone(),
+                    metrics::Direction::Out,
+                    rt.identity.as_ref().map(|id| id.name().clone()),
+                ))
                 // Note: routes can't exert backpressure.
                 .push(profiles::http::route_request::layer(
                     svc::proxies()
@@ -144,10 +248,31 @@ impl<E> Outbound<E> {
                         // with both body types.
                         .push_on_service(http::BoxRequest::erased())
                         .push_http_insert_target::<dst::Route>()
+                        // Injects configured faults, beneath the route's
+                        // policies so that they are exercised.
+                        .push(fault::layer(config.http_faults.clone()))
+                        // Sets an optional hedge policy, so that slow
+                        // requests (including retries) are duplicated.
+                        .push(hedge::layer(hedge_policy))
+                        // Sets an optional timeout on each attempt, so that
+                        // stuck attempts are retried.
+                        .push(retry::per_try_timeout_layer(
+                            config.retry_per_try_timeouts.clone(),
+                        ))
                         // Sets an optional retry policy.
-                        .push(retry::layer(rt.metrics.proxy.http_route_retry.clone()))
+                        .push(retry::layer(retry_policy))
+                        // Gives retryable routes their own retry budgets, if
+                        // configured.
+                        .push(retry::route_budget_layer(config.retry_budgets.clone()))
                         // Sets an optional request timeout.
                         .push(http::MakeTimeoutLayer::default())
+                        // Modifies request and response headers, above the
+                        // route's retry policy so that each attempt carries the
+                        // same headers.
+                        .push(header_policy::layer(config.http_header_policies.clone()))
+                        // Rewrites or redirects requests, above the route's
+                        // retry policy so that each attempt is rewritten alike.
+                        .push(route_filter::layer(config.http_route_filters.clone()))
                         // Records per-route metrics.
                         .push(
                             rt.metrics
@@ -155,6 +280,10 @@ impl<E> Outbound<E> {
                                 .http_route
                                 .to_layer::<classify::Response, _, _>(),
                         )
+                        // Records per-route gRPC message metrics.
+                        .push(rt.metrics.proxy.http_route_grpc.to_layer::<_, dst::Route>())
+                        // Labels sampled spans with the route's metric labels.
+                        .push(http_tracing::NewLabelSpan::<metrics::RouteLabels, _>::layer())
                         // Sets the per-route response classifier as a request
                         // extension.
                         .push(classify::NewClassify::layer())
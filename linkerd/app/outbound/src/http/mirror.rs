@@ -0,0 +1,575 @@
+//! Mirroring of outbound HTTP requests to shadow services.
+//!
+//! A logical service may be configured to mirror a percentage of its requests
+//! (optionally, only those on a single route) to a shadow service (e.g. a new
+//! version of the service under test). Mirrored requests are fire-and-forget:
+//! they are only sent while the shadow service is ready, their responses are
+//! discarded, and they are dispatched beneath the route stack so that they are
+//! not recorded by the primary service's route metrics or retried.
+//!
+//! Request bodies are copied to the shadow request as they are read by the
+//! primary service, so they are never buffered. If the shadow service does not
+//! keep up with the primary service, its copy of the body is truncated and the
+//! mirrored request fails.
+//!
+//! Mirrors are applied beneath failover, so that shadow requests are sent
+//! directly to the shadow service's balancer.
+
+use crate::logical::Concrete;
+use bytes::{Buf, Bytes};
+use futures::prelude::*;
+use linkerd_app_core::{
+    dst,
+    metrics::{metrics, Counter, FmtLabels, FmtMetrics},
+    profiles::LogicalAddr,
+    proxy::{api_resolve::ConcreteAddr, http},
+    svc, Error, NameAddr,
+};
+use parking_lot::Mutex;
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tracing::{debug_span, trace, Instrument};
+
+metrics! {
+    outbound_http_mirror_requests_total: Counter {
+        "The total number of requests mirrored to a shadow service"
+    },
+    outbound_http_mirror_errors_total: Counter {
+        "The total number of mirrored requests that failed, by shadow service"
+    }
+}
+
+/// Bounds the number of body chunks buffered for a shadow request before its
+/// body is truncated.
+const SHADOW_BODY_CAPACITY: usize = 16;
+
+/// Maps logical service addresses to the services to which they are mirrored.
+#[derive(Clone, Debug, Default)]
+pub struct Mirrors(Arc<HashMap<NameAddr, Target>>);
+
+/// Configures a service's mirror.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    /// The service to which mirrored requests are sent.
+    pub shadow: NameAddr,
+
+    /// The percentage of requests that are mirrored.
+    pub percent: u32,
+
+    /// If set, only requests on the route with this name (i.e. its `route`
+    /// label) are mirrored.
+    pub route: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<Mutex<HashMap<NameAddr, Arc<ShadowMetrics>>>>);
+
+/// Builds a shadow balancer for each concrete service that is configured to be
+/// mirrored.
+#[derive(Clone, Debug)]
+pub struct NewMirror<N> {
+    inner: N,
+    mirrors: Mirrors,
+    metrics: Metrics,
+}
+
+/// Dispatches requests to the primary service, sending copies of some of them
+/// to the shadow service.
+#[derive(Clone, Debug)]
+pub struct Mirror<S> {
+    primary: S,
+    shadow: S,
+    shadow_ready: bool,
+    percent: u32,
+    route: Option<String>,
+    metrics: Arc<ShadowMetrics>,
+}
+
+#[derive(Debug, Default)]
+struct ShadowMetrics {
+    requests: Counter,
+    errors: Counter,
+}
+
+struct ShadowLabel<'a>(&'a NameAddr);
+
+/// The primary request's body, which copies its data and trailers to a shadow
+/// request's body as they are read.
+struct TeeBody {
+    inner: http::BoxBody,
+    tx: Option<mpsc::Sender<Frame>>,
+    data_done: bool,
+}
+
+/// The shadow request's body, which yields the frames copied from the primary
+/// request's body.
+struct ShadowBody {
+    rx: mpsc::Receiver<Frame>,
+    trailers: Option<Option<http::header::HeaderMap>>,
+}
+
+enum Frame {
+    Data(Bytes),
+    Trailers(Option<http::header::HeaderMap>),
+}
+
+#[derive(Debug, Error)]
+#[error("mirrored request body was truncated")]
+pub struct Truncated(());
+
+// === impl Mirrors ===
+
+impl Mirrors {
+    pub fn new(mirrors: impl IntoIterator<Item = (NameAddr, Target)>) -> Self {
+        Self(Arc::new(mirrors.into_iter().collect()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, LogicalAddr(addr): &LogicalAddr) -> Option<&Target> {
+        self.0.get(addr)
+    }
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    fn shadow(&self, addr: &NameAddr) -> Arc<ShadowMetrics> {
+        self.0.lock().entry(addr.clone()).or_default().clone()
+    }
+}
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shadows = self.0.lock();
+        if shadows.is_empty() {
+            return Ok(());
+        }
+
+        outbound_http_mirror_requests_total.fmt_help(f)?;
+        for (addr, m) in shadows.iter() {
+            outbound_http_mirror_requests_total.fmt_metric_labeled(
+                f,
+                &m.requests,
+                ShadowLabel(addr),
+            )?;
+        }
+
+        outbound_http_mirror_errors_total.fmt_help(f)?;
+        for (addr, m) in shadows.iter() {
+            outbound_http_mirror_errors_total.fmt_metric_labeled(
+                f,
+                &m.errors,
+                ShadowLabel(addr),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FmtLabels for ShadowLabel<'_> {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "shadow=\"{}\"", self.0)
+    }
+}
+
+// === impl NewMirror ===
+
+impl<N> NewMirror<N> {
+    pub fn layer(
+        mirrors: Mirrors,
+        metrics: Metrics,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            mirrors: mirrors.clone(),
+            metrics: metrics.clone(),
+        })
+    }
+}
+
+impl<P, N> svc::NewService<Concrete<P>> for NewMirror<N>
+where
+    P: Clone,
+    N: svc::NewService<Concrete<P>>,
+{
+    type Service = svc::Either<N::Service, Mirror<N::Service>>;
+
+    fn new_service(&mut self, concrete: Concrete<P>) -> Self::Service {
+        let target = match self.mirrors.get(&concrete.logical.logical_addr) {
+            Some(target) => target.clone(),
+            None => return svc::Either::A(self.inner.new_service(concrete)),
+        };
+
+        let metrics = self.metrics.shadow(&target.shadow);
+        let shadow = self.inner.new_service(Concrete {
+            resolve: ConcreteAddr(target.shadow),
+            logical: concrete.logical.clone(),
+            readiness: None,
+        });
+        svc::Either::B(Mirror {
+            primary: self.inner.new_service(concrete),
+            shadow,
+            shadow_ready: false,
+            percent: target.percent,
+            route: target.route,
+            metrics,
+        })
+    }
+}
+
+// === impl Mirror ===
+
+impl<S> Mirror<S> {
+    fn applies_to<B>(&self, req: &http::Request<B>) -> bool {
+        match self.route.as_ref() {
+            Some(name) => req
+                .extensions()
+                .get::<dst::Route>()
+                .map(|r| r.route.labels().get("route") == Some(name))
+                .unwrap_or(false),
+            None => true,
+        }
+    }
+}
+
+impl<S> svc::Service<http::Request<http::BoxBody>> for Mirror<S>
+where
+    S: svc::Service<http::Request<http::BoxBody>>,
+    S::Error: Into<Error>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::ErrInto<S::Future, Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        // The shadow is polled so that its endpoints are discovered and driven
+        // to readiness, but it never holds up the primary.
+        self.shadow_ready = matches!(self.shadow.poll_ready(cx), Poll::Ready(Ok(())));
+        self.primary.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<http::BoxBody>) -> Self::Future {
+        if !self.shadow_ready
+            || !self.applies_to(&req)
+            || !rand::thread_rng().gen_ratio(self.percent.min(100), 100)
+        {
+            return self.primary.call(req).err_into();
+        }
+        self.shadow_ready = false;
+
+        let (parts, body) = req.into_parts();
+        let (primary_body, shadow_body) = if body.is_end_stream() {
+            (body, http::BoxBody::default())
+        } else {
+            let (tee, shadow) = tee(body);
+            (http::BoxBody::new(tee), http::BoxBody::new(shadow))
+        };
+
+        let mut mirror = http::Request::new(shadow_body);
+        *mirror.method_mut() = parts.method.clone();
+        *mirror.uri_mut() = parts.uri.clone();
+        *mirror.headers_mut() = parts.headers.clone();
+        *mirror.version_mut() = parts.version;
+        trace!("Mirroring request");
+        self.metrics.requests.incr();
+        let metrics = self.metrics.clone();
+        tokio::spawn(
+            self.shadow
+                .call(mirror)
+                .err_into::<Error>()
+                .map(move |res| {
+                    if let Err(error) = res {
+                        metrics.errors.incr();
+                        trace!(%error, "Mirrored request failed");
+                    }
+                })
+                .instrument(debug_span!("mirror")),
+        );
+
+        self.primary
+            .call(http::Request::from_parts(parts, primary_body))
+            .err_into()
+    }
+}
+
+// === impl TeeBody ===
+
+fn tee(inner: http::BoxBody) -> (TeeBody, ShadowBody) {
+    let (tx, rx) = mpsc::channel(SHADOW_BODY_CAPACITY);
+    let tee = TeeBody {
+        inner,
+        tx: Some(tx),
+        data_done: false,
+    };
+    let shadow = ShadowBody { rx, trailers: None };
+    (tee, shadow)
+}
+
+impl TeeBody {
+    fn send(&mut self, frame: Frame) {
+        if let Some(tx) = self.tx.as_ref() {
+            // If the shadow can't keep up, its body is truncated rather than
+            // holding up the primary.
+            if tx.try_send(frame).is_err() {
+                trace!("Truncating mirrored request body");
+                self.tx = None;
+            }
+        }
+    }
+}
+
+impl http::HttpBody for TeeBody {
+    type Data = Bytes;
+    type Error = Error;
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn poll_data(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        let this = self.get_mut();
+        match futures::ready!(Pin::new(&mut this.inner).poll_data(cx)) {
+            Some(Ok(mut data)) => {
+                let data = data.copy_to_bytes(data.remaining());
+                this.send(Frame::Data(data.clone()));
+                Poll::Ready(Some(Ok(data)))
+            }
+            Some(Err(e)) => {
+                this.tx = None;
+                Poll::Ready(Some(Err(e)))
+            }
+            None => {
+                this.data_done = true;
+                Poll::Ready(None)
+            }
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::header::HeaderMap>, Error>> {
+        let this = self.get_mut();
+        let res = futures::ready!(Pin::new(&mut this.inner).poll_trailers(cx));
+        match res {
+            Ok(ref trailers) => this.send(Frame::Trailers(trailers.clone())),
+            Err(_) => this.tx = None,
+        }
+        this.tx = None;
+        Poll::Ready(res)
+    }
+}
+
+impl Drop for TeeBody {
+    fn drop(&mut self) {
+        // If the primary's body was read without its trailers being polled,
+        // the shadow's body ends without trailers, too.
+        if self.data_done {
+            self.send(Frame::Trailers(None));
+        }
+    }
+}
+
+// === impl ShadowBody ===
+
+impl http::HttpBody for ShadowBody {
+    type Data = Bytes;
+    type Error = Error;
+
+    fn poll_data(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        let this = self.get_mut();
+        if this.trailers.is_some() {
+            return Poll::Ready(None);
+        }
+        match futures::ready!(this.rx.poll_recv(cx)) {
+            Some(Frame::Data(data)) => Poll::Ready(Some(Ok(data))),
+            Some(Frame::Trailers(trailers)) => {
+                this.trailers = Some(trailers);
+                Poll::Ready(None)
+            }
+            None => Poll::Ready(Some(Err(Truncated(()).into()))),
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::header::HeaderMap>, Error>> {
+        Poll::Ready(Ok(self.get_mut().trailers.take().flatten()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::{profiles, svc::ServiceExt};
+
+    fn respond(
+        name: &'static str,
+        tx: mpsc::UnboundedSender<(&'static str, Bytes)>,
+    ) -> impl svc::Service<
+        http::Request<http::BoxBody>,
+        Response = &'static str,
+        Error = Error,
+        Future = future::BoxFuture<'static, Result<&'static str, Error>>,
+    > {
+        svc::mk(move |req: http::Request<http::BoxBody>| {
+            let tx = tx.clone();
+            Box::pin(async move {
+                let body = hyper::body::to_bytes(req.into_body()).await?;
+                let _ = tx.send((name, body));
+                Ok(name)
+            }) as future::BoxFuture<'static, _>
+        })
+    }
+
+    fn mirror(
+        tx: mpsc::UnboundedSender<(&'static str, Bytes)>,
+        route: Option<&str>,
+    ) -> Mirror<
+        impl svc::Service<
+            http::Request<http::BoxBody>,
+            Response = &'static str,
+            Error = Error,
+            Future = future::BoxFuture<'static, Result<&'static str, Error>>,
+        >,
+    > {
+        Mirror {
+            primary: respond("primary", tx.clone()),
+            shadow: respond("shadow", tx),
+            shadow_ready: false,
+            percent: 100,
+            route: route.map(Into::into),
+            metrics: Default::default(),
+        }
+    }
+
+    fn request(body: &'static str, route: &str) -> http::Request<http::BoxBody> {
+        let mut req = http::Request::new(http::BoxBody::new(hyper::Body::from(body)));
+        req.extensions_mut().insert(dst::Route {
+            addr: LogicalAddr("web.ns.svc.cluster.local:80".parse().unwrap()),
+            route: profiles::http::Route::new(
+                vec![("route".to_string(), route.to_string())].into_iter(),
+                Vec::new(),
+            ),
+            direction: linkerd_app_core::metrics::Direction::Out,
+            rewritten_from: None,
+        });
+        req
+    }
+
+    #[tokio::test]
+    async fn mirrors_request_bodies() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut mirror = mirror(tx, None);
+
+        let rsp = mirror
+            .ready()
+            .await
+            .unwrap()
+            .call(request("hello", "GET /books"))
+            .await
+            .unwrap();
+        assert_eq!(rsp, "primary");
+        let mut sent = vec![rx.recv().await.unwrap(), rx.recv().await.unwrap()];
+        sent.sort_unstable();
+        assert_eq!(
+            sent,
+            vec![
+                ("primary", Bytes::from("hello")),
+                ("shadow", Bytes::from("hello"))
+            ]
+        );
+        assert_eq!(mirror.metrics.requests.value(), 1.0);
+        assert_eq!(mirror.metrics.errors.value(), 0.0);
+
+        // Requests are not mirrored when mirroring is disabled.
+        mirror.percent = 0;
+        mirror
+            .ready()
+            .await
+            .unwrap()
+            .call(request("hello", "GET /books"))
+            .await
+            .unwrap();
+        assert_eq!(rx.recv().await.unwrap().0, "primary");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn mirrors_only_configured_route() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut mirror = mirror(tx, Some("GET /books"));
+
+        mirror
+            .ready()
+            .await
+            .unwrap()
+            .call(request("", "GET /authors"))
+            .await
+            .unwrap();
+        assert_eq!(rx.recv().await.unwrap().0, "primary");
+        assert!(rx.try_recv().is_err());
+
+        mirror
+            .ready()
+            .await
+            .unwrap()
+            .call(request("", "GET /books"))
+            .await
+            .unwrap();
+        let mut sent = vec![rx.recv().await.unwrap().0, rx.recv().await.unwrap().0];
+        sent.sort_unstable();
+        assert_eq!(sent, vec!["primary", "shadow"]);
+    }
+
+    #[tokio::test]
+    async fn truncates_shadow_bodies_that_fall_behind() {
+        struct Chunks(usize);
+
+        impl http::HttpBody for Chunks {
+            type Data = Bytes;
+            type Error = Error;
+
+            fn poll_data(
+                mut self: Pin<&mut Self>,
+                _: &mut Context<'_>,
+            ) -> Poll<Option<Result<Bytes, Error>>> {
+                if self.0 == 0 {
+                    return Poll::Ready(None);
+                }
+                self.0 -= 1;
+                Poll::Ready(Some(Ok(Bytes::from_static(b"x"))))
+            }
+
+            fn poll_trailers(
+                self: Pin<&mut Self>,
+                _: &mut Context<'_>,
+            ) -> Poll<Result<Option<http::header::HeaderMap>, Error>> {
+                Poll::Ready(Ok(None))
+            }
+        }
+
+        let (tee, shadow) = tee(http::BoxBody::new(Chunks(SHADOW_BODY_CAPACITY + 1)));
+
+        let primary = hyper::body::to_bytes(tee).await.unwrap();
+        assert_eq!(primary.len(), SHADOW_BODY_CAPACITY + 1);
+        let err = hyper::body::to_bytes(shadow)
+            .await
+            .expect_err("shadow body must be truncated");
+        assert!(err.is::<Truncated>());
+    }
+}
@@ -1,15 +1,30 @@
+pub mod breaker;
+pub mod concurrency;
 pub mod detect;
 mod endpoint;
+mod hash_policy;
 pub mod logical;
+pub mod mirror;
 mod peer_proxy_errors;
 mod require_id_header;
+mod rewrite_authority;
 mod server;
+mod sticky;
 
-pub(crate) use self::{require_id_header::IdentityRequired, server::ServerRescue};
+pub use self::{
+    detect::{InvalidSkipDetect, SkipDetect},
+    hash_policy::{HashKey, HashPolicies, InvalidHashPolicy},
+    rewrite_authority::{AuthorityRewrites, InvalidRewrite},
+    sticky::{SessionCookie, StickySessions},
+};
+pub(crate) use self::{
+    require_id_header::IdentityRequired, rewrite_authority::NewRewriteAuthority,
+    server::ServerRescue, sticky::NewSetSessionCookie,
+};
 use crate::tcp;
 pub use linkerd_app_core::proxy::http::*;
 use linkerd_app_core::{
-    dst,
+    dst, identity,
     profiles::{self, LogicalAddr},
     proxy::{api_resolve::ProtocolHint, tap},
     svc::Param,
@@ -55,6 +70,13 @@ impl Param<normalize_uri::DefaultAuthority> for Accept {
     }
 }
 
+/// Outbound clients are local applications, which are never authenticated.
+impl Param<Option<identity::Name>> for Accept {
+    fn param(&self) -> Option<identity::Name> {
+        None
+    }
+}
+
 // === impl Logical ===
 
 impl From<(Version, tcp::Logical)> for Logical {
@@ -63,6 +85,7 @@ impl From<(Version, tcp::Logical)> for Logical {
             protocol,
             profile: logical.profile,
             logical_addr: logical.logical_addr,
+            rewritten_from: logical.rewritten_from,
         }
     }
 }
@@ -79,6 +102,12 @@ impl Param<Version> for Logical {
     }
 }
 
+impl Param<Option<identity::Name>> for Logical {
+    fn param(&self) -> Option<identity::Name> {
+        None
+    }
+}
+
 impl Logical {
     pub fn mk_route((route, logical): (profiles::http::Route, Self)) -> dst::Route {
         use linkerd_app_core::metrics::Direction;
@@ -86,6 +115,7 @@ impl Logical {
             route,
             addr: logical.logical_addr,
             direction: Direction::Out,
+            rewritten_from: logical.rewritten_from,
         }
     }
 }
@@ -131,6 +161,12 @@ impl Param<normalize_uri::DefaultAuthority> for Endpoint {
     }
 }
 
+impl Param<Option<identity::Name>> for Endpoint {
+    fn param(&self) -> Option<identity::Name> {
+        None
+    }
+}
+
 impl Param<Version> for Endpoint {
     fn param(&self) -> Version {
         self.protocol
@@ -0,0 +1,227 @@
+//! Rewrites the authorities of outbound requests according to a static table.
+//!
+//! This supports migrations where a service has been renamed (e.g. from
+//! `legacy-name` to `new-name.namespace.svc.cluster.local`) without changing
+//! application configuration. Only the host is rewritten; ports are preserved.
+
+use linkerd_app_core::{dns, proxy::http, svc, NameAddr};
+use std::{
+    collections::HashMap,
+    iter::FromIterator,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tracing::debug;
+
+/// A table of names to be rewritten.
+#[derive(Clone, Debug, Default)]
+pub struct AuthorityRewrites(Arc<HashMap<dns::Name, dns::Name>>);
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("authority rewrites must be formatted as `from=to`: {0}")]
+pub struct InvalidRewrite(String);
+
+#[derive(Clone, Debug)]
+pub(crate) struct NewRewriteAuthority<N> {
+    inner: N,
+    rewrites: AuthorityRewrites,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct RewriteAuthority<S> {
+    inner: S,
+    rewrites: AuthorityRewrites,
+}
+
+// === impl AuthorityRewrites ===
+
+impl AuthorityRewrites {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the rewritten address, if the address's name has a rewrite.
+    pub fn rewrite(&self, addr: &NameAddr) -> Option<NameAddr> {
+        let name = self.0.get(addr.name())?;
+        Some(NameAddr::from((name.clone(), addr.port())))
+    }
+
+    fn rewrite_authority(&self, authority: &http::uri::Authority) -> Option<http::uri::Authority> {
+        let name = dns::Name::from_str(authority.host()).ok()?;
+        let name = self.0.get(&name)?;
+        let rewritten = match authority.port_u16() {
+            Some(port) => format!("{}:{}", name.without_trailing_dot(), port),
+            None => name.without_trailing_dot().to_string(),
+        };
+        http::uri::Authority::from_str(&rewritten).ok()
+    }
+}
+
+impl FromIterator<(dns::Name, dns::Name)> for AuthorityRewrites {
+    fn from_iter<I: IntoIterator<Item = (dns::Name, dns::Name)>>(iter: I) -> Self {
+        Self(Arc::new(iter.into_iter().collect()))
+    }
+}
+
+/// Parses a comma-separated list of `from=to` name pairs.
+impl FromStr for AuthorityRewrites {
+    type Err = InvalidRewrite;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(|r| {
+                let mut parts = r.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(from), Some(to)) => {
+                        let from = dns::Name::from_str(from.trim())
+                            .map_err(|_| InvalidRewrite(r.to_string()))?;
+                        let to = dns::Name::from_str(to.trim())
+                            .map_err(|_| InvalidRewrite(r.to_string()))?;
+                        Ok((from, to))
+                    }
+                    _ => Err(InvalidRewrite(r.to_string())),
+                }
+            })
+            .collect()
+    }
+}
+
+// === impl NewRewriteAuthority ===
+
+impl<N> NewRewriteAuthority<N> {
+    pub fn layer(rewrites: AuthorityRewrites) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            rewrites: rewrites.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewRewriteAuthority<N>
+where
+    N: svc::NewService<T>,
+{
+    type Service = RewriteAuthority<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        RewriteAuthority {
+            inner: self.inner.new_service(target),
+            rewrites: self.rewrites.clone(),
+        }
+    }
+}
+
+// === impl RewriteAuthority ===
+
+impl<S, B> svc::Service<http::Request<B>> for RewriteAuthority<S>
+where
+    S: svc::Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if !self.rewrites.is_empty() {
+            self.rewrite(&mut req);
+        }
+        self.inner.call(req)
+    }
+}
+
+impl<S> RewriteAuthority<S> {
+    fn rewrite<B>(&self, req: &mut http::Request<B>) {
+        if let Some(authority) = req.uri().authority() {
+            if let Some(rewritten) = self.rewrites.rewrite_authority(authority) {
+                debug!(from = %authority, to = %rewritten, "Rewriting request authority");
+                let mut parts = req.uri().clone().into_parts();
+                parts.authority = Some(rewritten);
+                if let Ok(uri) = http::uri::Uri::from_parts(parts) {
+                    *req.uri_mut() = uri;
+                }
+            }
+        }
+
+        if let Some(host) = http::authority_from_header(req, http::header::HOST) {
+            if let Some(rewritten) = self.rewrites.rewrite_authority(&host) {
+                debug!(from = %host, to = %rewritten, "Rewriting host header");
+                let value = http::HeaderValue::from_str(rewritten.as_str())
+                    .expect("authority must be a valid header value");
+                req.headers_mut().insert(http::header::HOST, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::{svc::ServiceExt, Error};
+
+    fn rewrites() -> AuthorityRewrites {
+        "legacy-name=new-name.ns.svc.cluster.local, other=other.ns.svc.cluster.local"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_rewrites() {
+        assert!("".parse::<AuthorityRewrites>().unwrap().is_empty());
+        assert!("legacy-name".parse::<AuthorityRewrites>().is_err());
+        assert!("legacy-name=".parse::<AuthorityRewrites>().is_err());
+
+        let rewrites = rewrites();
+        assert_eq!(
+            rewrites.rewrite(&"legacy-name:8080".parse().unwrap()),
+            Some("new-name.ns.svc.cluster.local:8080".parse().unwrap())
+        );
+        assert_eq!(
+            rewrites.rewrite(&"new-name.ns.svc.cluster.local:8080".parse().unwrap()),
+            None
+        );
+    }
+
+    async fn call(req: http::Request<()>) -> (http::uri::Uri, http::header::HeaderMap) {
+        let svc = RewriteAuthority {
+            inner: svc::mk(|req: http::Request<()>| {
+                futures::future::ok::<_, Error>((req.uri().clone(), req.headers().clone()))
+            }),
+            rewrites: rewrites(),
+        };
+        svc.oneshot(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn rewrites_requests() {
+        let req = http::Request::get("http://legacy-name:8080/path?q=1")
+            .header(http::header::HOST, "legacy-name:8080")
+            .body(())
+            .unwrap();
+        let (uri, headers) = call(req).await;
+        assert_eq!(
+            uri.to_string(),
+            "http://new-name.ns.svc.cluster.local:8080/path?q=1"
+        );
+        assert_eq!(
+            headers[http::header::HOST],
+            "new-name.ns.svc.cluster.local:8080"
+        );
+
+        let req = http::Request::get("/path")
+            .header(http::header::HOST, "unrelated")
+            .body(())
+            .unwrap();
+        let (uri, headers) = call(req).await;
+        assert_eq!(uri.to_string(), "/path");
+        assert_eq!(headers[http::header::HOST], "unrelated");
+    }
+}
@@ -1,6 +1,9 @@
 use super::{peer_proxy_errors::PeerProxyErrors, IdentityRequired};
 use crate::{http, trace_labels, Outbound};
-use linkerd_app_core::{config, errors, http_tracing, svc, Error, Result};
+use linkerd_app_core::{
+    access_log, config, deadline, errors, http_tracing, identity, metrics::Direction, rls, svc,
+    Error, Result,
+};
 
 #[derive(Copy, Clone, Debug)]
 pub(crate) struct ServerRescue;
@@ -20,7 +23,7 @@ impl<N> Outbound<N> {
         >,
     >
     where
-        T: svc::Param<http::normalize_uri::DefaultAuthority>,
+        T: svc::Param<http::normalize_uri::DefaultAuthority> + svc::Param<Option<identity::Name>>,
         N: svc::NewService<T, Service = NSvc> + Clone + Send + Sync + 'static,
         NSvc: svc::Service<http::Request<http::BoxBody>, Response = http::Response<http::BoxBody>>,
         NSvc: Send + 'static,
@@ -54,12 +57,29 @@ impl<N> Outbound<N> {
                         // Synthesizes responses for proxy errors.
                         .push(ServerRescue::layer())
                         // Initiates OpenCensus tracing.
-                        .push(http_tracing::server(rt.span_sink.clone(), trace_labels()))
-                        .push(http::BoxResponse::layer()),
+                        .push(http_tracing::server(
+                            rt.span_sink.clone(),
+                            rt.trace_precedence,
+                            trace_labels(),
+                        ))
+                        // Records the deadlines of requests with timeout
+                        // headers, so they're enforced on the outbound leg.
+                        .push(deadline::RecordDeadline::layer()),
                 )
+                // Writes an access log record for each request, including
+                // those that fail with proxy errors.
+                .push(access_log::NewLogRequests::layer(
+                    rt.access_log.clone(),
+                    Direction::Out,
+                    rt.trace_precedence,
+                ))
+                .push_on_service(http::BoxResponse::layer())
                 // Convert origin form HTTP/1 URIs to absolute form for Hyper's
                 // `Client`.
                 .push(http::NewNormalizeUri::layer())
+                .push(http::NewRewriteAuthority::layer(
+                    config.authority_rewrites.clone(),
+                ))
                 // Record when a HTTP/1 URI originated in absolute form
                 .push_on_service(http::normalize_uri::MarkAbsoluteForm::layer())
                 .check_new_service::<T, http::Request<http::BoxBody>>()
@@ -88,6 +108,18 @@ impl errors::HttpRescue<Error> for ServerRescue {
         if cause.is::<errors::FailFastError>() {
             return Ok(errors::SyntheticHttpResponse::gateway_timeout(cause));
         }
+        if cause.is::<deadline::DeadlineExceeded>() {
+            return Ok(errors::SyntheticHttpResponse::deadline_exceeded(cause));
+        }
+        if cause.is::<errors::ConcurrencyLimitExceeded>() {
+            return Ok(errors::SyntheticHttpResponse::unavailable(cause));
+        }
+        if cause.is::<rls::RateLimitExceeded>() {
+            return Ok(errors::SyntheticHttpResponse::rate_limited(cause));
+        }
+        if cause.is::<rls::RateLimitUnavailable>() {
+            return Ok(errors::SyntheticHttpResponse::unavailable(cause));
+        }
 
         if cause.is::<errors::H2Error>() {
             return Err(error);
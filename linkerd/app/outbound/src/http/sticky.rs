@@ -0,0 +1,258 @@
+//! Cookie-based sticky sessions for outbound HTTP services.
+//!
+//! Some applications assume that all of a client's requests are handled by
+//! the same endpoint. Services configured with sticky sessions are balanced by
+//! consistent hashing, keyed by a session cookie: when an endpoint responds to
+//! a request that does not already carry its cookie, the response sets a
+//! cookie that identifies the endpoint, so that the client's subsequent
+//! requests are sent to the same endpoint until the cookie expires. If the
+//! endpoint is removed or is not ready, the session's requests are sent to
+//! another endpoint, which issues a new cookie.
+//!
+//! Cookies identify endpoints by the hash of their address on the balancer's
+//! hash ring, so that endpoint addresses are not disclosed to clients. They are
+//! issued with `SameSite=Lax` and, unless disabled for plaintext clients,
+//! `Secure`.
+
+use crate::ring_hash;
+use futures::prelude::*;
+use linkerd_app_core::{
+    profiles::LogicalAddr,
+    proxy::http,
+    svc,
+    transport::{Remote, ServerAddr},
+    NameAddr,
+};
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tracing::trace;
+
+/// Maps logical service addresses to the cookies that pin their sessions.
+#[derive(Clone, Debug, Default)]
+pub struct StickySessions(Arc<HashMap<NameAddr, SessionCookie>>);
+
+/// Configures a service's session cookie.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionCookie {
+    pub name: String,
+
+    /// The time for which clients retain the cookie after it is issued.
+    pub ttl: Duration,
+
+    /// Whether clients may only send the cookie over secure connections.
+    pub secure: bool,
+}
+
+/// Sets a session cookie on the responses of endpoints of services that are
+/// configured with sticky sessions.
+#[derive(Clone, Debug)]
+pub struct NewSetSessionCookie<N> {
+    inner: N,
+    sessions: StickySessions,
+}
+
+#[derive(Clone, Debug)]
+pub struct SetSessionCookie<S> {
+    inner: S,
+    cookie: Option<Cookie>,
+}
+
+#[derive(Clone, Debug)]
+struct Cookie {
+    name: String,
+    hash: u64,
+    set_cookie: http::HeaderValue,
+}
+
+// === impl StickySessions ===
+
+impl StickySessions {
+    pub fn new(sessions: impl IntoIterator<Item = (NameAddr, SessionCookie)>) -> Self {
+        Self(Arc::new(sessions.into_iter().collect()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, LogicalAddr(addr): &LogicalAddr) -> Option<&SessionCookie> {
+        self.0.get(addr)
+    }
+}
+
+/// Returns the endpoint hash held by the named session cookie, if the request
+/// has one.
+pub(super) fn session_hash(headers: &http::HeaderMap, name: &str) -> Option<u64> {
+    headers
+        .get_all(http::header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| {
+            let mut kv = pair.trim().splitn(2, '=');
+            match (kv.next(), kv.next()) {
+                (Some(k), Some(v)) if k == name => u64::from_str_radix(v.trim(), 16).ok(),
+                _ => None,
+            }
+        })
+        .next()
+}
+
+// === impl NewSetSessionCookie ===
+
+impl<N> NewSetSessionCookie<N> {
+    pub fn layer(sessions: StickySessions) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            sessions: sessions.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewSetSessionCookie<N>
+where
+    T: svc::Param<Option<LogicalAddr>> + svc::Param<Remote<ServerAddr>>,
+    N: svc::NewService<T>,
+{
+    type Service = SetSessionCookie<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let cookie = svc::Param::<Option<LogicalAddr>>::param(&target).and_then(|logical| {
+            let SessionCookie { name, ttl, secure } = self.sessions.get(&logical)?;
+            // Balancers are keyed by the endpoint's socket address.
+            let Remote(ServerAddr(addr)) = target.param();
+            let hash = ring_hash::endpoint_hash(&addr);
+            let set_cookie = format!(
+                "{}={:016x}; Max-Age={}; Path=/; HttpOnly{}; SameSite=Lax",
+                name,
+                hash,
+                ttl.as_secs(),
+                if *secure { "; Secure" } else { "" },
+            );
+            Some(Cookie {
+                name: name.clone(),
+                hash,
+                set_cookie: http::HeaderValue::from_str(&set_cookie).ok()?,
+            })
+        });
+        SetSessionCookie {
+            inner: self.inner.new_service(target),
+            cookie,
+        }
+    }
+}
+
+// === impl SetSessionCookie ===
+
+impl<S, A, B> svc::Service<http::Request<A>> for SetSessionCookie<S>
+where
+    S: svc::Service<http::Request<A>, Response = http::Response<B>>,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let cookie = match self.cookie.as_ref() {
+            Some(c) if session_hash(req.headers(), &c.name) != Some(c.hash) => c,
+            _ => return Box::pin(self.inner.call(req)),
+        };
+
+        trace!(cookie = %cookie.name, "Issuing session cookie");
+        let set_cookie = cookie.set_cookie.clone();
+        Box::pin(self.inner.call(req).map_ok(move |mut rsp| {
+            rsp.headers_mut()
+                .append(http::header::SET_COOKIE, set_cookie);
+            rsp
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::{Layer, NewService, ServiceExt};
+    use std::net::SocketAddr;
+
+    #[derive(Clone)]
+    struct Target(SocketAddr);
+
+    impl svc::Param<Option<LogicalAddr>> for Target {
+        fn param(&self) -> Option<LogicalAddr> {
+            Some(LogicalAddr("web.ns.svc.cluster.local:80".parse().unwrap()))
+        }
+    }
+
+    impl svc::Param<Remote<ServerAddr>> for Target {
+        fn param(&self) -> Remote<ServerAddr> {
+            Remote(ServerAddr(self.0))
+        }
+    }
+
+    #[tokio::test]
+    async fn issues_session_cookies() {
+        let sessions = StickySessions::new(vec![(
+            "web.ns.svc.cluster.local:80".parse().unwrap(),
+            SessionCookie {
+                name: "l5d-session".to_string(),
+                ttl: Duration::from_secs(3600),
+                secure: true,
+            },
+        )]);
+        let addr = SocketAddr::from(([10, 0, 0, 1], 8080));
+        let mut endpoint = NewSetSessionCookie::layer(sessions)
+            .layer(|_: Target| {
+                svc::mk(|_: http::Request<()>| {
+                    future::ok::<_, linkerd_app_core::Error>(http::Response::new(()))
+                })
+            })
+            .new_service(Target(addr));
+
+        // Requests without the endpoint's cookie are issued one.
+        let rsp = endpoint
+            .ready()
+            .await
+            .unwrap()
+            .call(http::Request::new(()))
+            .await
+            .unwrap();
+        let set_cookie = rsp
+            .headers()
+            .get(http::header::SET_COOKIE)
+            .expect("cookie must be set")
+            .to_str()
+            .unwrap();
+        let hash = ring_hash::endpoint_hash(&addr);
+        assert_eq!(
+            set_cookie,
+            format!(
+                "l5d-session={:016x}; Max-Age=3600; Path=/; HttpOnly; Secure; SameSite=Lax",
+                hash
+            )
+        );
+
+        // The cookie is honored by the balancer...
+        let cookie = set_cookie.split(';').next().unwrap();
+        let req = http::Request::builder()
+            .header(http::header::COOKIE, format!("theme=dark; {}", cookie))
+            .body(())
+            .unwrap();
+        assert_eq!(session_hash(req.headers(), "l5d-session"), Some(hash));
+
+        // ...and is not reissued while it is held.
+        let rsp = endpoint.ready().await.unwrap().call(req).await.unwrap();
+        assert!(rsp.headers().get(http::header::SET_COOKIE).is_none());
+    }
+}
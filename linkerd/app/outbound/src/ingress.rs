@@ -1,7 +1,10 @@
 use crate::{http, stack_labels, tcp, trace_labels, Config, Outbound};
 use linkerd_app_core::{
+    access_log,
     config::{ProxyConfig, ServerConfig},
-    detect, http_tracing, io, profiles,
+    detect, http_tracing, io,
+    metrics::Direction,
+    profiles,
     proxy::{
         api_resolve::{ConcreteAddr, Metadata},
         core::Resolve,
@@ -21,6 +24,8 @@ struct AllowHttpProfile(AddrMatch);
 struct Http<T> {
     target: T,
     version: http::Version,
+    /// The override name from which `target` was rewritten, if any.
+    rewritten_from: Option<NameAddr>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -73,6 +78,7 @@ impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
         let detect_http = config.proxy.detect_http();
         let Config {
             allow_discovery,
+            authority_rewrites,
             proxy:
                 ProxyConfig {
                     server: ServerConfig { h2_settings, .. },
@@ -85,6 +91,12 @@ impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
             ..
         } = config;
         let profile_domains = allow_discovery.names().clone();
+        let rewrite_requests = authority_rewrites.clone();
+        let logical = rt
+            .metrics
+            .proxy
+            .introspect
+            .register(stack_labels("http", "logical"));
 
         http_logical
             // If a profile was discovered, use it to build a logical stack. Otherwise, the override
@@ -98,6 +110,7 @@ impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
                                 profile,
                                 logical_addr,
                                 protocol: http.version,
+                                rewritten_from: http.rewritten_from,
                             });
                         }
                     }
@@ -137,10 +150,10 @@ impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
                             .layer(stack_labels("http", "logical")),
                     )
                     .push(svc::layer::mk(svc::SpawnReady::new))
-                    .push(svc::FailFast::layer("HTTP Logical", dispatch_timeout))
+                    .push(logical.failfast_layer("HTTP Logical", dispatch_timeout))
                     .push_spawn_buffer(buffer_capacity),
             )
-            .push_cache(cache_max_idle_age)
+            .push(logical.cache_layer(cache_max_idle_age))
             .push_on_service(
                 svc::layers()
                     .push(http::strip_header::request::layer(DST_OVERRIDE_HEADER))
@@ -152,10 +165,16 @@ impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
             // header through the (load balanced) logical stack. Route requests without the header
             // through the endpoint stack.
             .push_switch(
-                |Http { target, version }: Http<Target>| match target {
-                    Target::Override(target) => {
-                        Ok::<_, Infallible>(svc::Either::A(Http { target, version }))
-                    }
+                |Http {
+                     target,
+                     version,
+                     rewritten_from,
+                 }: Http<Target>| match target {
+                    Target::Override(target) => Ok::<_, Infallible>(svc::Either::A(Http {
+                        target,
+                        version,
+                        rewritten_from,
+                    })),
                     Target::Forward(OrigDstAddr(addr)) => Ok(svc::Either::B(http::Endpoint {
                         addr: Remote(ServerAddr(addr)),
                         metadata: Metadata::default(),
@@ -182,24 +201,39 @@ impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
             // not cached explicitly, as there are no real resources we need to share across
             // connections. This allows us to avoid buffering requests to these endpoints.
             .push(svc::NewRouter::layer(
-                |http::Accept { orig_dst, protocol }| {
+                move |http::Accept { orig_dst, protocol }| {
+                    let authority_rewrites = authority_rewrites.clone();
                     move |req: &http::Request<_>| {
                         // Use either the override header or the original destination address.
+                        let mut rewritten_from = None;
                         let target = match http::authority_from_header(req, DST_OVERRIDE_HEADER) {
                             None => Target::Forward(orig_dst),
                             Some(a) => {
                                 let dst = NameAddr::from_authority_with_default_port(&a, 80)
                                     .map_err(|_| InvalidOverrideHeader)?;
-                                Target::Override(dst)
+                                match authority_rewrites.rewrite(&dst) {
+                                    Some(rewritten) => {
+                                        tracing::debug!(
+                                            from = %dst,
+                                            to = %rewritten,
+                                            "Rewriting override",
+                                        );
+                                        rewritten_from = Some(dst);
+                                        Target::Override(rewritten)
+                                    }
+                                    None => Target::Override(dst),
+                                }
                             }
                         };
                         Ok(Http {
                             target,
                             version: protocol,
+                            rewritten_from,
                         })
                     }
                 },
             ))
+            .push(http::NewRewriteAuthority::layer(rewrite_requests))
             .push(http::NewNormalizeUri::layer())
             .push_on_service(
                 svc::layers()
@@ -211,10 +245,21 @@ impl Outbound<svc::BoxNewHttp<http::Endpoint>> {
                     .push(svc::FailFast::layer("Ingress server", dispatch_timeout))
                     .push(rt.metrics.http_errors.to_layer())
                     .push(http::ServerRescue::layer())
-                    .push(http_tracing::server(rt.span_sink, trace_labels()))
-                    .push(http::BoxResponse::layer())
+                    .push(http_tracing::server(
+                        rt.span_sink,
+                        rt.trace_precedence,
+                        trace_labels(),
+                    ))
                     .push(http::BoxRequest::layer()),
             )
+            // Writes an access log record for each request, including those
+            // that fail with proxy errors.
+            .push(access_log::NewLogRequests::layer(
+                rt.access_log,
+                Direction::Out,
+                rt.trace_precedence,
+            ))
+            .push_on_service(http::BoxResponse::layer())
             .instrument(|a: &http::Accept| debug_span!("http", v = %a.protocol))
             .push(http::NewServeHttp::layer(h2_settings, rt.drain))
             .push_request_filter(|(http, accept): (Option<http::Version>, _)| {
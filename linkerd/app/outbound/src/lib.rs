@@ -5,24 +5,34 @@
 #![deny(warnings, rust_2018_idioms)]
 #![forbid(unsafe_code)]
 
+pub mod balance;
 mod discover;
 pub mod endpoint;
+pub mod failover;
+pub mod health;
 pub mod http;
 mod ingress;
 pub mod logical;
 mod metrics;
+pub mod multicluster;
+pub mod policy;
+pub mod probe;
 mod resolve;
+mod ring_hash;
+pub mod route_table;
 mod switch_logical;
 pub mod tcp;
 #[cfg(test)]
 pub(crate) mod test_util;
+pub mod udp;
 
-pub use self::metrics::Metrics;
+pub use self::{metrics::Metrics, route_table::RouteTable};
 use futures::Stream;
 use linkerd_app_core::{
+    access_log,
     config::ProxyConfig,
-    drain,
-    http_tracing::OpenCensusSink,
+    dns, drain, fault, header_policy, hedge,
+    http_tracing::{self, OpenCensusSink},
     io, profiles,
     proxy::{
         api_resolve::{ConcreteAddr, Metadata},
@@ -30,7 +40,7 @@ use linkerd_app_core::{
         identity::LocalCrtKey,
         tap,
     },
-    serve,
+    retry, rls, route_filter, serve,
     svc::{self, stack::Param},
     tls,
     transport::{self, addrs::*},
@@ -45,9 +55,6 @@ use std::{
 };
 use tracing::info;
 
-const EWMA_DEFAULT_RTT: Duration = Duration::from_millis(30);
-const EWMA_DECAY: Duration = Duration::from_secs(10);
-
 #[derive(Clone, Debug)]
 pub struct Config {
     pub proxy: ProxyConfig,
@@ -58,6 +65,127 @@ pub struct Config {
     // forwarded without discovery/routing/mTLS.
     pub ingress_mode: bool,
     pub inbound_ips: Arc<HashSet<IpAddr>>,
+
+    /// Synthetic requests to be issued through the outbound proxy, if any.
+    pub probe: Option<probe::Config>,
+
+    /// Names to be rewritten in the authorities of outbound HTTP requests.
+    pub authority_rewrites: http::AuthorityRewrites,
+
+    /// If set, retries are suppressed on routes whose retry budgets have been
+    /// exhausted continuously for this duration.
+    pub retry_suppression_after: Option<Duration>,
+
+    /// The maximum size of request bodies that are buffered so that requests
+    /// may be retried. Requests with bodies are not retried when zero.
+    pub retry_max_buffered_bytes: usize,
+
+    /// The longest time a retry is delayed by a response's `Retry-After`
+    /// header.
+    pub retry_after_max: Duration,
+
+    /// Whether requests that failed before they could have been processed
+    /// (e.g. due to connection failures or refused HTTP/2 streams) are retried
+    /// on all routes.
+    pub retry_unprocessed: bool,
+
+    /// Services whose routes' retries are delayed by a backoff.
+    pub retry_backoffs: retry::Backoffs,
+
+    /// Services whose retryable routes' attempts are individually timed out.
+    pub retry_per_try_timeouts: retry::PerTryTimeouts,
+
+    /// Services whose retryable routes are each given their own retry budget.
+    pub retry_budgets: retry::RouteBudgets,
+
+    /// Determines the destinations to which outbound connections are
+    /// permitted.
+    pub egress_policy: policy::EgressPolicy,
+
+    /// Targets whose opaque TCP connections are prefixed with a PROXY protocol
+    /// header describing the application's address.
+    pub proxy_protocol: tcp::ProxyProtocolTargets,
+
+    /// Unmeshed destinations to which TLS is originated with operator-supplied
+    /// certificates.
+    pub tls_originations: tcp::TlsOriginations,
+
+    /// If set, outbound connections are tunneled through an HTTP forward
+    /// proxy.
+    pub http_proxy: Option<tcp::http_proxy::Config>,
+
+    /// If set, outbound connect sockets are marked with this `SO_MARK` value.
+    pub connect_mark: Option<u32>,
+
+    /// Limits the bandwidth of forwarded TCP connections, by target port.
+    pub tcp_bandwidth_limits: transport::bandwidth::Limits,
+
+    /// Destinations for which protocol detection is skipped.
+    pub skip_detect: http::SkipDetect,
+
+    /// UDP datagrams to be forwarded through the proxy.
+    pub udp: udp::Config,
+
+    /// Services whose HTTP requests are balanced by consistent hashing.
+    pub http_hash_policies: http::HashPolicies,
+
+    /// Services whose HTTP requests are pinned to endpoints by a session
+    /// cookie.
+    pub http_sticky_sessions: http::StickySessions,
+
+    /// Services whose slow HTTP requests are hedged on retryable routes.
+    pub http_hedges: hedge::Hedges,
+
+    /// Faults injected into services' HTTP routes.
+    pub http_faults: fault::Faults,
+
+    /// Header policies of services' HTTP routes.
+    pub http_header_policies: header_policy::HeaderPolicies,
+
+    /// Path and host rewrites and redirects of services' HTTP routes.
+    pub http_route_filters: route_filter::RouteFilters,
+
+    /// Services whose TCP connections are balanced by client address.
+    pub tcp_source_affinity: tcp::SourceAffinity,
+
+    /// Configures the load estimate of peak-EWMA balancers.
+    pub ewma: balance::EwmaConfig,
+
+    /// Services that are balanced by algorithms other than peak-EWMA.
+    pub balancer_algorithms: balance::Algorithms,
+
+    /// If set, balancers prefer endpoints in the proxy's zone.
+    pub zone_affinity: Option<balance::ZoneAffinity>,
+
+    /// If set, newly discovered endpoints are ramped up to their full weight
+    /// over this window.
+    pub slow_start_window: Option<Duration>,
+
+    /// If set, endpoints that fail repeatedly are temporarily ejected from
+    /// their balancers.
+    pub outlier_detection: Option<balance::outlier::Config>,
+
+    /// Services whose HTTP endpoints stop receiving requests for a time after
+    /// failing too many of them.
+    pub http_circuit_breakers: http::breaker::CircuitBreakers,
+
+    /// Services whose HTTP endpoints stop receiving requests while their
+    /// learned concurrency limits are reached.
+    pub http_concurrency_limits: http::concurrency::ConcurrencyLimits,
+
+    /// Services whose endpoints are actively health checked.
+    pub health_checks: health::HealthChecks,
+
+    /// Services that fail over to other services when too few of their
+    /// endpoints are ready.
+    pub failovers: failover::Failovers,
+
+    /// Services that fail over to endpoints mirrored from remote clusters
+    /// when they have no local endpoints.
+    pub multicluster_failovers: multicluster::Failovers,
+
+    /// Services that mirror some of their HTTP requests to shadow services.
+    pub http_mirrors: http::mirror::Mirrors,
 }
 
 #[derive(Clone, Debug)]
@@ -73,7 +201,12 @@ struct Runtime {
     identity: Option<LocalCrtKey>,
     tap: tap::Registry,
     span_sink: OpenCensusSink,
+    trace_precedence: http_tracing::Precedence,
+    access_log: Option<access_log::AccessLog>,
+    rate_limit: Option<rls::Client>,
+    dns: dns::Resolver,
     drain: drain::Watch,
+    route_table: RouteTable,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -91,7 +224,12 @@ impl Outbound<()> {
             identity: runtime.identity,
             tap: runtime.tap,
             span_sink: runtime.span_sink,
+            trace_precedence: runtime.trace_precedence,
+            access_log: runtime.access_log,
+            rate_limit: runtime.rate_limit,
+            dns: runtime.dns,
             drain: runtime.drain,
+            route_table: RouteTable::default(),
         };
         Self {
             config,
@@ -104,6 +242,11 @@ impl Outbound<()> {
         self.runtime.metrics.clone()
     }
 
+    /// Returns a read handle onto the profiles and endpoints discovered by this proxy.
+    pub fn route_table(&self) -> RouteTable {
+        self.runtime.route_table.clone()
+    }
+
     pub fn with_stack<S>(self, stack: S) -> Outbound<S> {
         self.map_stack(move |_, _, _| svc::stack(stack))
     }
@@ -156,7 +299,7 @@ impl Outbound<()> {
         resolve: R,
     ) where
         A: Param<Remote<ClientAddr>> + Param<OrigDstAddr> + Clone + Send + Sync + 'static,
-        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr,
+        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr + io::Splice,
         I: Debug + Unpin + Send + Sync + 'static,
         R: Clone + Send + Sync + Unpin + 'static,
         R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error>,
@@ -1,9 +1,11 @@
-use crate::{http, tcp, Outbound};
+use crate::{balance, http, tcp, Outbound};
 pub use linkerd_app_core::proxy::api_resolve::ConcreteAddr;
 use linkerd_app_core::{
-    io, profiles,
+    access_log, io,
+    metrics::Direction,
+    profiles,
     proxy::{api_resolve::Metadata, core::Resolve},
-    svc, tls, Addr, Error,
+    svc, tls, transport, Addr, Error, NameAddr,
 };
 pub use profiles::LogicalAddr;
 use std::fmt;
@@ -13,12 +15,17 @@ pub struct Logical<P> {
     pub profile: profiles::Receiver,
     pub logical_addr: LogicalAddr,
     pub protocol: P,
+    /// The name from which the logical address was rewritten, if any.
+    pub rewritten_from: Option<NameAddr>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Concrete<P> {
     pub resolve: ConcreteAddr,
     pub logical: Logical<P>,
+    /// Records the readiness of the concrete service's endpoints, if it may
+    /// be failed over.
+    pub readiness: Option<balance::Readiness>,
 }
 
 pub type UnwrapLogical<L, E> = svc::stack::ResultService<svc::Either<L, E>>;
@@ -31,6 +38,7 @@ impl Logical<()> {
             profile,
             logical_addr,
             protocol: (),
+            rewritten_from: None,
         }
     }
 }
@@ -55,6 +63,30 @@ impl<P> svc::Param<LogicalAddr> for Logical<P> {
     }
 }
 
+/// Logical targets are identified by name, so the peer identity is not known
+/// until an endpoint is selected.
+impl svc::Param<access_log::ConnectionTarget> for Logical<()> {
+    fn param(&self) -> access_log::ConnectionTarget {
+        access_log::ConnectionTarget {
+            addr: self.logical_addr.0.clone().into(),
+            identity: None,
+        }
+    }
+}
+
+/// The target port, used to select the service's bandwidth limit.
+impl svc::Param<u16> for Logical<()> {
+    fn param(&self) -> u16 {
+        self.logical_addr.0.port()
+    }
+}
+
+impl svc::Param<Addr> for Logical<()> {
+    fn param(&self) -> Addr {
+        self.logical_addr.0.clone().into()
+    }
+}
+
 // Used for skipping HTTP detection
 impl svc::Param<Option<http::detect::Skip>> for Logical<()> {
     fn param(&self) -> Option<http::detect::Skip> {
@@ -74,7 +106,9 @@ impl<P> Logical<P> {
 
 impl<P: PartialEq> PartialEq<Logical<P>> for Logical<P> {
     fn eq(&self, other: &Logical<P>) -> bool {
-        self.logical_addr == other.logical_addr && self.protocol == other.protocol
+        self.logical_addr == other.logical_addr
+            && self.protocol == other.protocol
+            && self.rewritten_from == other.rewritten_from
     }
 }
 
@@ -84,6 +118,7 @@ impl<P: std::hash::Hash> std::hash::Hash for Logical<P> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.logical_addr.hash(state);
         self.protocol.hash(state);
+        self.rewritten_from.hash(state);
     }
 }
 
@@ -93,6 +128,7 @@ impl<P: std::fmt::Debug> std::fmt::Debug for Logical<P> {
             .field("protocol", &self.protocol)
             .field("profile", &format_args!(".."))
             .field("logical_addr", &self.logical_addr)
+            .field("rewritten_from", &self.rewritten_from)
             .finish()
     }
 }
@@ -101,7 +137,11 @@ impl<P: std::fmt::Debug> std::fmt::Debug for Logical<P> {
 
 impl<P> From<(ConcreteAddr, Logical<P>)> for Concrete<P> {
     fn from((resolve, logical): (ConcreteAddr, Logical<P>)) -> Self {
-        Self { resolve, logical }
+        Self {
+            resolve,
+            logical,
+            readiness: None,
+        }
     }
 }
 
@@ -111,6 +151,12 @@ impl<P> svc::Param<ConcreteAddr> for Concrete<P> {
     }
 }
 
+impl<P> svc::Param<Option<balance::Readiness>> for Concrete<P> {
+    fn param(&self) -> Option<balance::Readiness> {
+        self.readiness.clone()
+    }
+}
+
 // === impl Outbound ===
 
 impl<C> Outbound<C> {
@@ -119,14 +165,14 @@ impl<C> Outbound<C> {
         Self: Clone + 'static,
         C: Clone + Send + Sync + Unpin + 'static,
         C: svc::Service<tcp::Connect, Error = io::Error>,
-        C::Response:
-            tls::HasNegotiatedProtocol + io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
+        C::Response: tls::HasNegotiatedProtocol + io::AsyncRead + io::AsyncWrite + io::Splice,
+        C::Response: Send + Unpin + 'static,
         C::Future: Send + Unpin,
         R: Clone + Send + 'static,
         R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error> + Sync,
         R::Resolution: Send,
         R::Future: Send + Unpin,
-        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr,
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + io::Splice,
         I: fmt::Debug + Send + Sync + Unpin + 'static,
     {
         let http = self
@@ -139,6 +185,18 @@ impl<C> Outbound<C> {
 
         self.push_tcp_endpoint()
             .push_tcp_logical(resolve)
+            .map_stack(|config, rt, tcp| {
+                tcp.push(transport::bandwidth::NewThrottle::layer(
+                    config.tcp_bandwidth_limits.clone(),
+                ))
+                .push(tcp::proxy_protocol::NewEmitProxyProtocol::layer(
+                    config.proxy_protocol.clone(),
+                ))
+                .push(access_log::NewLogConnections::layer(
+                    rt.access_log.clone(),
+                    Direction::Out,
+                ))
+            })
             .push_detect_http(http)
     }
 }
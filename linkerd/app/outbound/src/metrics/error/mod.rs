@@ -2,7 +2,7 @@ mod http;
 mod tcp;
 
 pub(crate) use self::{http::Http, tcp::Tcp};
-use crate::http::IdentityRequired;
+use crate::{http::IdentityRequired, policy::EgressDenied};
 use linkerd_app_core::{
     errors::{FailFastError, ResponseTimeout},
     metrics::FmtLabels,
@@ -12,6 +12,7 @@ use std::fmt;
 /// Outbound proxy error types.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 enum ErrorKind {
+    EgressDenied,
     FailFast,
     IdentityRequired,
     Io,
@@ -27,6 +28,8 @@ impl ErrorKind {
             ErrorKind::Io
         } else if err.is::<IdentityRequired>() {
             ErrorKind::IdentityRequired
+        } else if err.is::<EgressDenied>() {
+            ErrorKind::EgressDenied
         } else if err.is::<FailFastError>() {
             ErrorKind::FailFast
         } else if err.is::<ResponseTimeout>() {
@@ -45,6 +48,7 @@ impl FmtLabels for ErrorKind {
             f,
             "error=\"{}\"",
             match self {
+                ErrorKind::EgressDenied => "egress denied",
                 ErrorKind::FailFast => "failfast",
                 ErrorKind::IdentityRequired => "identity required",
                 ErrorKind::Io => "i/o",
@@ -10,6 +10,9 @@
 
 pub(crate) mod error;
 
+use crate::{balance, http, policy, probe, udp};
+use linkerd_app_core::retry;
+
 pub use linkerd_app_core::metrics::*;
 
 /// Holds outbound proxy metrics.
@@ -17,6 +20,14 @@ pub use linkerd_app_core::metrics::*;
 pub struct Metrics {
     pub(crate) http_errors: error::Http,
     pub(crate) tcp_errors: error::Tcp,
+    pub(crate) egress_denied: policy::Metrics,
+    pub(crate) probes: probe::Metrics,
+    pub(crate) retry_suppressions: retry::Suppressions,
+    pub(crate) udp: udp::Metrics,
+    pub(crate) outliers: balance::outlier::Metrics,
+    pub(crate) circuit_breakers: http::breaker::Metrics,
+    pub(crate) concurrency_limits: http::concurrency::Metrics,
+    pub(crate) mirrors: http::mirror::Metrics,
 
     /// Holds metrics that are common to both inbound and outbound proxies. These metrics are
     /// reported separately
@@ -28,6 +39,14 @@ impl Metrics {
         Self {
             http_errors: error::Http::default(),
             tcp_errors: error::Tcp::default(),
+            egress_denied: policy::Metrics::default(),
+            probes: probe::Metrics::default(),
+            retry_suppressions: retry::Suppressions::default(),
+            udp: udp::Metrics::default(),
+            outliers: balance::outlier::Metrics::default(),
+            circuit_breakers: http::breaker::Metrics::default(),
+            concurrency_limits: http::concurrency::Metrics::default(),
+            mirrors: http::mirror::Metrics::default(),
             proxy,
         }
     }
@@ -37,6 +56,14 @@ impl FmtMetrics for Metrics {
     fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.http_errors.fmt_metrics(f)?;
         self.tcp_errors.fmt_metrics(f)?;
+        self.egress_denied.fmt_metrics(f)?;
+        self.probes.fmt_metrics(f)?;
+        self.retry_suppressions.fmt_metrics(f)?;
+        self.udp.fmt_metrics(f)?;
+        self.outliers.fmt_metrics(f)?;
+        self.circuit_breakers.fmt_metrics(f)?;
+        self.concurrency_limits.fmt_metrics(f)?;
+        self.mirrors.fmt_metrics(f)?;
 
         // XXX: Proxy metrics are reported elsewhere.
 
@@ -0,0 +1,356 @@
+//! Fails over to endpoints mirrored from remote clusters.
+//!
+//! The destination service may resolve a service to its local endpoints along
+//! with endpoints mirrored from remote clusters, which are identified by an
+//! endpoint label. For services configured to fail over, remote endpoints are
+//! only used while the service has no local endpoints; as soon as a local
+//! endpoint is discovered again, traffic fails back to the local endpoints.
+//!
+//! Each endpoint of these services is labeled as either `local` or `remote`
+//! so that its traffic is distinguished in metrics.
+
+use futures::{prelude::*, ready};
+use linkerd_app_core::{
+    proxy::{
+        api_resolve::{ConcreteAddr, Metadata},
+        core::Update,
+    },
+    svc, NameAddr,
+};
+use pin_project::pin_project;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::info;
+
+/// The endpoint label that describes whether an endpoint is local or remote.
+pub const LOCALITY_LABEL: &str = "multicluster";
+
+/// Configures the services that fail over to remote endpoints.
+#[derive(Clone, Debug, Default)]
+pub struct Failovers {
+    services: Arc<HashSet<NameAddr>>,
+    remote_label: Arc<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Resolve<S> {
+    inner: S,
+    failovers: Failovers,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResolveFuture<F> {
+    #[pin]
+    inner: F,
+    state: Option<State>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct Resolution<R> {
+    #[pin]
+    inner: R,
+    state: Option<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    addr: NameAddr,
+    remote_label: Arc<String>,
+    local: HashMap<SocketAddr, Metadata>,
+    remote: HashMap<SocketAddr, Metadata>,
+    failed_over: bool,
+}
+
+// === impl Failovers ===
+
+impl Failovers {
+    /// Endpoints of the given services are remote if they have a non-empty
+    /// value for `remote_label`.
+    pub fn new(
+        remote_label: impl Into<String>,
+        services: impl IntoIterator<Item = NameAddr>,
+    ) -> Self {
+        Self {
+            services: Arc::new(services.into_iter().collect()),
+            remote_label: Arc::new(remote_label.into()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+
+    fn state(&self, addr: &NameAddr) -> Option<State> {
+        if !self.services.contains(addr) {
+            return None;
+        }
+        Some(State {
+            addr: addr.clone(),
+            remote_label: self.remote_label.clone(),
+            local: HashMap::default(),
+            remote: HashMap::default(),
+            failed_over: false,
+        })
+    }
+}
+
+// === impl Resolve ===
+
+impl<S> Resolve<S> {
+    pub fn layer(failovers: Failovers) -> impl svc::layer::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            failovers: failovers.clone(),
+        })
+    }
+}
+
+impl<S> svc::Service<ConcreteAddr> for Resolve<S>
+where
+    S: svc::Service<ConcreteAddr>,
+{
+    type Response = Resolution<S::Response>;
+    type Error = S::Error;
+    type Future = ResolveFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, ConcreteAddr(addr): ConcreteAddr) -> Self::Future {
+        let state = self.failovers.state(&addr);
+        ResolveFuture {
+            inner: self.inner.call(ConcreteAddr(addr)),
+            state,
+        }
+    }
+}
+
+impl<F, R, E> Future for ResolveFuture<F>
+where
+    F: TryFuture<Ok = R, Error = E>,
+{
+    type Output = Result<Resolution<R>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let inner = ready!(this.inner.try_poll(cx))?;
+        Poll::Ready(Ok(Resolution {
+            inner,
+            state: this.state.take(),
+        }))
+    }
+}
+
+// === impl Resolution ===
+
+impl<R, E> Stream for Resolution<R>
+where
+    R: Stream<Item = Result<Update<Metadata>, E>>,
+{
+    type Item = R::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        let state = match this.state {
+            Some(state) => state,
+            None => return this.inner.poll_next(cx),
+        };
+
+        loop {
+            let update = match ready!(this.inner.as_mut().poll_next(cx)) {
+                Some(Ok(update)) => update,
+                res => return Poll::Ready(res),
+            };
+            if let Some(update) = state.update(update) {
+                return Poll::Ready(Some(Ok(update)));
+            }
+        }
+    }
+}
+
+// === impl State ===
+
+impl State {
+    /// Records an update, returning the corresponding update to the active set
+    /// of endpoints, if it changed.
+    fn update(&mut self, update: Update<Metadata>) -> Option<Update<Metadata>> {
+        let was_failed_over = self.failed_over;
+        let mut moved = false;
+        let update = match update {
+            Update::Reset(eps) => {
+                self.local.clear();
+                self.remote.clear();
+                self.insert(eps);
+                None
+            }
+            Update::Add(eps) => {
+                let (active, m) = self.insert(eps);
+                moved = m;
+                Some(Update::Add(active))
+            }
+            Update::Remove(addrs) => {
+                let mut removed = Vec::with_capacity(addrs.len());
+                for addr in addrs.into_iter() {
+                    let local = self.local.remove(&addr).is_some();
+                    let remote = self.remote.remove(&addr).is_some();
+                    if (local && !self.failed_over) || (remote && self.failed_over) {
+                        removed.push(addr);
+                    }
+                }
+                Some(Update::Remove(removed))
+            }
+            Update::DoesNotExist => {
+                self.local.clear();
+                self.remote.clear();
+                self.failed_over = false;
+                return Some(Update::DoesNotExist);
+            }
+        };
+
+        self.failed_over = self.local.is_empty() && !self.remote.is_empty();
+        if self.failed_over != was_failed_over {
+            if self.failed_over {
+                info!(service = %self.addr, endpoints = self.remote.len(), "Failing over to remote endpoints");
+            } else {
+                info!(service = %self.addr, endpoints = self.local.len(), "Failing back to local endpoints");
+            }
+            return Some(Update::Reset(self.active()));
+        }
+
+        match update {
+            // Endpoints that moved between the local and remote sets may have
+            // been removed from the active set.
+            _ if moved => Some(Update::Reset(self.active())),
+            None => Some(Update::Reset(self.active())),
+            Some(Update::Add(eps)) if eps.is_empty() => None,
+            Some(Update::Remove(addrs)) if addrs.is_empty() => None,
+            update => update,
+        }
+    }
+
+    /// Records endpoints, returning those that are in the active set and
+    /// whether any endpoint moved between the local and remote sets.
+    fn insert(&mut self, eps: Vec<(SocketAddr, Metadata)>) -> (Vec<(SocketAddr, Metadata)>, bool) {
+        let mut active = Vec::new();
+        let mut moved = false;
+        for (addr, meta) in eps.into_iter() {
+            let is_remote = meta
+                .labels()
+                .get(self.remote_label.as_str())
+                .map(|v| !v.is_empty())
+                .unwrap_or(false);
+            let (set, other, locality) = if is_remote {
+                (&mut self.remote, &mut self.local, "remote")
+            } else {
+                (&mut self.local, &mut self.remote, "local")
+            };
+            moved |= other.remove(&addr).is_some();
+            let meta = meta.with_label(LOCALITY_LABEL, locality);
+            if is_remote == self.failed_over {
+                active.push((addr, meta.clone()));
+            }
+            set.insert(addr, meta);
+        }
+        (active, moved)
+    }
+
+    fn active(&self) -> Vec<(SocketAddr, Metadata)> {
+        let eps = if self.failed_over {
+            &self.remote
+        } else {
+            &self.local
+        };
+        eps.iter()
+            .map(|(addr, meta)| (*addr, meta.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::proxy::api_resolve::ProtocolHint;
+
+    fn local(port: u16) -> (SocketAddr, Metadata) {
+        (([192, 0, 2, 1], port).into(), Metadata::default())
+    }
+
+    fn remote(port: u16) -> (SocketAddr, Metadata) {
+        let meta = Metadata::new(
+            Some(("remote_cluster".to_string(), "west".to_string())),
+            ProtocolHint::Unknown,
+            None,
+            None,
+            None,
+        );
+        (([203, 0, 113, 1], port).into(), meta)
+    }
+
+    /// Describes each endpoint in an update by its locality label.
+    fn localities(update: Option<Update<Metadata>>) -> Option<Update<String>> {
+        let sorted = |eps: Vec<(SocketAddr, Metadata)>| {
+            let mut eps = eps
+                .into_iter()
+                .map(|(addr, meta)| {
+                    let locality = meta.labels().get(LOCALITY_LABEL).cloned().unwrap();
+                    (addr, locality)
+                })
+                .collect::<Vec<_>>();
+            eps.sort_by_key(|(addr, _)| *addr);
+            eps
+        };
+        update.map(|update| match update {
+            Update::Reset(eps) => Update::Reset(sorted(eps)),
+            Update::Add(eps) => Update::Add(sorted(eps)),
+            Update::Remove(addrs) => Update::Remove(addrs),
+            Update::DoesNotExist => Update::DoesNotExist,
+        })
+    }
+
+    #[test]
+    fn fails_over_and_back() {
+        let failovers = Failovers::new("remote_cluster", Some("web.ns:80".parse().unwrap()));
+        assert!(failovers.state(&"other.ns:80".parse().unwrap()).is_none());
+        let mut state = failovers.state(&"web.ns:80".parse().unwrap()).unwrap();
+
+        let (l0, r0) = (local(80).0, remote(80).0);
+        let update = state.update(Update::Reset(vec![local(80), remote(80)]));
+        assert_eq!(
+            localities(update),
+            Some(Update::Reset(vec![(l0, "local".to_string())]))
+        );
+
+        // Remote endpoints are not used while local endpoints exist.
+        let r1 = remote(81).0;
+        assert_eq!(state.update(Update::Add(vec![remote(81)])), None);
+
+        let update = state.update(Update::Remove(vec![l0]));
+        assert_eq!(
+            localities(update),
+            Some(Update::Reset(vec![
+                (r0, "remote".to_string()),
+                (r1, "remote".to_string()),
+            ]))
+        );
+        assert_eq!(
+            state.update(Update::Remove(vec![r1])),
+            Some(Update::Remove(vec![r1]))
+        );
+
+        let update = state.update(Update::Add(vec![local(80)]));
+        assert_eq!(
+            localities(update),
+            Some(Update::Reset(vec![(l0, "local".to_string())]))
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! Egress authorization policy.
+//!
+//! By default, the outbound proxy permits connections to any destination. When
+//! an egress policy is configured, each outbound connection must match one of
+//! its rules: the connection's original destination address must be in one of
+//! the rule's networks, or the logical name discovered for it must be in one of
+//! the rule's DNS suffixes, and its port must be one of the rule's ports (if
+//! any are listed). Connections that match no rule are refused before they
+//! are forwarded or routed.
+//!
+//! Connections tunneled through an HTTP forward proxy are checked again by
+//! their endpoint's address and logical name, since their destinations may not
+//! be the original destinations of the application's connections (e.g. in
+//! ingress mode). UDP forwards whose upstreams are not permitted drop all of
+//! their datagrams.
+
+use crate::transport::OrigDstAddr;
+use futures::{future, TryFutureExt};
+use linkerd_app_core::{
+    metrics::{metrics, Counter, FmtLabels, FmtMetrics},
+    profiles::{self, LogicalAddr},
+    svc,
+    transport::{Remote, ServerAddr},
+    AddrMatch, Error, IpMatch, NameAddr,
+};
+use std::{
+    collections::HashSet,
+    fmt,
+    net::SocketAddr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tracing::{debug, info};
+
+metrics! {
+    outbound_denied_total: Counter {
+        "The total number of outbound connections (or UDP datagrams) that were denied by the egress policy, by where the policy was enforced"
+    }
+}
+
+/// Determines the destinations to which outbound connections are permitted.
+#[derive(Clone, Debug, Default)]
+pub struct EgressPolicy(Option<Arc<[Rule]>>);
+
+/// Permits connections to a set of destinations.
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub dst: AddrMatch,
+
+    /// The ports to which connections are permitted. All ports are permitted
+    /// when empty.
+    pub ports: HashSet<u16>,
+}
+
+#[derive(Debug, Error)]
+#[error("connection to {0} denied by egress policy")]
+pub struct EgressDenied(SocketAddr);
+
+/// Counts denials by where the policy was enforced, so that the metric's
+/// cardinality is bounded.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<Denials>);
+
+/// Where the egress policy was enforced.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Enforcement {
+    OrigDst,
+    HttpProxy,
+    Udp,
+}
+
+#[derive(Debug, Default)]
+struct Denials {
+    orig_dst: Counter,
+    http_proxy: Counter,
+    udp: Counter,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct NewAuthorizeEgress<N> {
+    inner: N,
+    policy: EgressPolicy,
+    metrics: Metrics,
+}
+
+/// Refuses all connections to a target that is not permitted by the policy.
+#[derive(Clone, Debug)]
+pub(crate) struct Denied {
+    addr: SocketAddr,
+    metrics: Metrics,
+}
+
+/// Refuses connections that would be tunneled through an HTTP forward proxy to
+/// endpoints that are not permitted by the policy.
+#[derive(Clone, Debug)]
+pub(crate) struct AuthorizeTunnel<S> {
+    inner: S,
+    policy: EgressPolicy,
+    /// The networks that are connected to directly, or `None` if no forward
+    /// proxy is configured.
+    tunneled: Option<IpMatch>,
+    metrics: Metrics,
+}
+
+// === impl EgressPolicy ===
+
+impl EgressPolicy {
+    /// Returns a policy that only permits connections that match one of the
+    /// given rules.
+    pub fn allow(rules: impl IntoIterator<Item = Rule>) -> Self {
+        Self(Some(rules.into_iter().collect()))
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Returns true if connections to `orig_dst`, which has the given logical
+    /// name (if any), are permitted.
+    pub fn permits(&self, orig_dst: SocketAddr, logical: Option<&NameAddr>) -> bool {
+        let rules = match self.0.as_ref() {
+            Some(rules) => rules,
+            None => return true,
+        };
+        rules.iter().any(|rule| {
+            (rule.ports.is_empty() || rule.ports.contains(&orig_dst.port()))
+                && (rule.dst.matches_ip(orig_dst.ip())
+                    || logical
+                        .map(|name| rule.dst.names().matches(name.name()))
+                        .unwrap_or(false))
+        })
+    }
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    pub(crate) fn deny(&self, enforcement: Enforcement) {
+        self.counter(enforcement).incr();
+    }
+
+    fn counter(&self, enforcement: Enforcement) -> &Counter {
+        match enforcement {
+            Enforcement::OrigDst => &self.0.orig_dst,
+            Enforcement::HttpProxy => &self.0.http_proxy,
+            Enforcement::Udp => &self.0.udp,
+        }
+    }
+}
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const ENFORCEMENTS: [Enforcement; 3] = [
+            Enforcement::OrigDst,
+            Enforcement::HttpProxy,
+            Enforcement::Udp,
+        ];
+        if ENFORCEMENTS.iter().all(|e| self.counter(*e).value() == 0.0) {
+            return Ok(());
+        }
+
+        outbound_denied_total.fmt_help(f)?;
+        for enforcement in ENFORCEMENTS.iter() {
+            outbound_denied_total.fmt_metric_labeled(
+                f,
+                self.counter(*enforcement),
+                *enforcement,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FmtLabels for Enforcement {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let enforcement = match self {
+            Enforcement::OrigDst => "orig_dst",
+            Enforcement::HttpProxy => "http_proxy",
+            Enforcement::Udp => "udp",
+        };
+        write!(f, "enforcement=\"{}\"", enforcement)
+    }
+}
+
+// === impl NewAuthorizeEgress ===
+
+impl<N> NewAuthorizeEgress<N> {
+    pub(crate) fn layer(
+        policy: EgressPolicy,
+        metrics: Metrics,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            policy: policy.clone(),
+            metrics: metrics.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<(Option<profiles::Receiver>, T)> for NewAuthorizeEgress<N>
+where
+    T: svc::Param<OrigDstAddr>,
+    N: svc::NewService<(Option<profiles::Receiver>, T)>,
+{
+    type Service = svc::Either<N::Service, Denied>;
+
+    fn new_service(&mut self, (profile, target): (Option<profiles::Receiver>, T)) -> Self::Service {
+        if self.policy.is_unrestricted() {
+            return svc::Either::A(self.inner.new_service((profile, target)));
+        }
+
+        let OrigDstAddr(addr) = target.param();
+        let logical = profile.as_ref().and_then(|p| p.logical_addr());
+        if self.policy.permits(addr, logical.as_ref().map(|l| &l.0)) {
+            debug!(%addr, "Egress permitted");
+            return svc::Either::A(self.inner.new_service((profile, target)));
+        }
+
+        info!(%addr, ?logical, "Egress denied");
+        svc::Either::B(Denied {
+            addr,
+            metrics: self.metrics.clone(),
+        })
+    }
+}
+
+// === impl Denied ===
+
+impl<I> svc::Service<I> for Denied {
+    type Response = ();
+    type Error = Error;
+    type Future = future::Ready<Result<(), Error>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: I) -> Self::Future {
+        self.metrics.deny(Enforcement::OrigDst);
+        future::err(EgressDenied(self.addr).into())
+    }
+}
+
+// === impl AuthorizeTunnel ===
+
+impl<S> AuthorizeTunnel<S> {
+    pub(crate) fn layer(
+        policy: EgressPolicy,
+        tunneled: Option<IpMatch>,
+        metrics: Metrics,
+    ) -> impl svc::layer::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            policy: policy.clone(),
+            tunneled: tunneled.clone(),
+            metrics: metrics.clone(),
+        })
+    }
+
+    fn permits(&self, addr: SocketAddr, logical: Option<LogicalAddr>) -> bool {
+        match self.tunneled.as_ref() {
+            Some(exempt) if !exempt.matches(addr.ip()) => {
+                self.policy.permits(addr, logical.as_ref().map(|l| &l.0))
+            }
+            _ => true,
+        }
+    }
+}
+
+impl<T, S> svc::Service<T> for AuthorizeTunnel<S>
+where
+    T: svc::Param<Remote<ServerAddr>> + svc::Param<Option<LogicalAddr>>,
+    S: svc::Service<T>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<S::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        let Remote(ServerAddr(addr)) = target.param();
+        let logical: Option<LogicalAddr> = target.param();
+        if !self.permits(addr, logical.clone()) {
+            info!(%addr, ?logical, "Tunneled egress denied");
+            self.metrics.deny(Enforcement::HttpProxy);
+            return future::Either::Right(future::err(EgressDenied(addr).into()));
+        }
+
+        future::Either::Left(self.inner.call(target).err_into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::{
+        dns,
+        svc::{NewService, ServiceExt},
+    };
+    use std::str::FromStr;
+
+    fn rule(dst: AddrMatch, ports: &[u16]) -> Rule {
+        Rule {
+            dst,
+            ports: ports.iter().copied().collect(),
+        }
+    }
+
+    fn policy() -> EgressPolicy {
+        EgressPolicy::allow(vec![
+            rule(
+                AddrMatch::new(None, Some("10.0.0.0/8".parse().unwrap())),
+                &[],
+            ),
+            rule(
+                AddrMatch::new(
+                    Some(dns::Suffix::from_str("svc.cluster.local").unwrap()),
+                    None,
+                ),
+                &[80, 443],
+            ),
+        ])
+    }
+
+    #[test]
+    fn permits_matching_destinations() {
+        let policy = policy();
+        let web = NameAddr::from_str("web.ns.svc.cluster.local:80").unwrap();
+        let db = NameAddr::from_str("db.ns.svc.cluster.local:5432").unwrap();
+
+        assert!(policy.permits(([10, 1, 2, 3], 5432).into(), None));
+        assert!(policy.permits(([192, 0, 2, 3], 80).into(), Some(&web)));
+        assert!(!policy.permits(([192, 0, 2, 3], 80).into(), None));
+        assert!(!policy.permits(([192, 0, 2, 3], 5432).into(), Some(&db)));
+
+        assert!(EgressPolicy::default().permits(([192, 0, 2, 3], 80).into(), None));
+        assert!(!EgressPolicy::allow(None).permits(([10, 1, 2, 3], 80).into(), None));
+    }
+
+    #[tokio::test]
+    async fn denies_connections() {
+        let metrics = Metrics::default();
+        let mut stack = NewAuthorizeEgress {
+            inner: |_: (Option<profiles::Receiver>, OrigDstAddr)| {
+                svc::mk(|()| future::ok::<(), Error>(()))
+            },
+            policy: policy(),
+            metrics: metrics.clone(),
+        };
+
+        let permitted = OrigDstAddr(([10, 1, 2, 3], 8080).into());
+        stack
+            .new_service((None, permitted))
+            .oneshot(())
+            .await
+            .expect("connection must be permitted");
+
+        let denied = OrigDstAddr(([192, 0, 2, 3], 8080).into());
+        let err = stack
+            .new_service((None, denied))
+            .oneshot(())
+            .await
+            .expect_err("connection must be denied");
+        assert!(err.is::<EgressDenied>());
+        assert_eq!(metrics.counter(Enforcement::OrigDst).value(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn denies_tunneled_connections() {
+        #[derive(Clone, Debug)]
+        struct Target(SocketAddr, Option<LogicalAddr>);
+
+        impl svc::Param<Remote<ServerAddr>> for Target {
+            fn param(&self) -> Remote<ServerAddr> {
+                Remote(ServerAddr(self.0))
+            }
+        }
+
+        impl svc::Param<Option<LogicalAddr>> for Target {
+            fn param(&self) -> Option<LogicalAddr> {
+                self.1.clone()
+            }
+        }
+
+        let metrics = Metrics::default();
+        let exempt = IpMatch::new(Some("192.168.0.0/16".parse().unwrap()));
+        let mut connect = AuthorizeTunnel {
+            inner: svc::mk(|_: Target| future::ok::<(), Error>(())),
+            policy: policy(),
+            tunneled: Some(exempt),
+            metrics: metrics.clone(),
+        };
+
+        let web = LogicalAddr("web.ns.svc.cluster.local:80".parse().unwrap());
+        for permitted in vec![
+            Target(([10, 1, 2, 3], 8080).into(), None),
+            Target(([192, 0, 2, 3], 80).into(), Some(web)),
+            Target(([192, 168, 2, 3], 8080).into(), None),
+        ] {
+            connect
+                .ready()
+                .await
+                .unwrap()
+                .call(permitted.clone())
+                .await
+                .unwrap_or_else(|_| panic!("{:?} must be permitted", permitted));
+        }
+
+        let err = connect
+            .ready()
+            .await
+            .unwrap()
+            .call(Target(([192, 0, 2, 3], 8080).into(), None))
+            .await
+            .expect_err("connection must be denied");
+        assert!(err.is::<EgressDenied>());
+        assert_eq!(metrics.counter(Enforcement::HttpProxy).value(), 1.0);
+    }
+}
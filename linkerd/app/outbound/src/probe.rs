@@ -0,0 +1,337 @@
+//! Issues synthetic requests through the outbound proxy.
+//!
+//! When probes are configured, the proxy periodically sends a request to each
+//! probe's destination through its own logical HTTP stack--with the same
+//! discovery, routing, load balancing, and mTLS as application traffic--and
+//! records the outcome. This allows route and policy changes to be verified
+//! continuously from the data plane's perspective.
+
+use crate::{http, Outbound};
+use linkerd_app_core::{
+    metrics::{latency, metrics, Counter, FmtLabels, FmtMetrics, Histogram},
+    profiles,
+    proxy::{
+        api_resolve::{ConcreteAddr, Metadata},
+        core::Resolve,
+    },
+    svc::{self, NewService, ServiceExt},
+    Error, NameAddr,
+};
+use parking_lot::RwLock;
+use std::{collections::HashMap, fmt, future::Future, str::FromStr, sync::Arc, time::Duration};
+use thiserror::Error;
+use tokio::time::{self, Instant};
+use tracing::{debug, warn};
+
+metrics! {
+    outbound_probe_total: Counter {
+        "The total number of synthetic probe requests, by destination and result"
+    },
+    outbound_probe_latency_ms: Histogram<latency::Ms> {
+        "Elapsed times between a synthetic probe being sent and its response completing"
+    }
+}
+
+const USER_AGENT: &str = "linkerd-proxy-probe";
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub probes: Vec<Probe>,
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+/// A destination to be probed, e.g. `web.ns.svc.cluster.local:8080/healthz`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Probe {
+    pub dst: NameAddr,
+    pub path: http::uri::PathAndQuery,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("invalid probe: {0}")]
+pub struct InvalidProbe(String);
+
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<RwLock<HashMap<NameAddr, ProbeMetrics>>>);
+
+#[derive(Debug)]
+struct ProbeMetrics {
+    success: Counter,
+    failure: Counter,
+    latency: Histogram<latency::Ms>,
+}
+
+#[derive(Debug, Error)]
+#[error("probe timed out after {0:?}")]
+struct ProbeTimeout(Duration);
+
+#[derive(Debug, Error)]
+#[error("no service profile was discovered for {0}")]
+struct ProfileRequired(NameAddr);
+
+#[derive(Debug, Error)]
+#[error("probe failed with status {0}")]
+struct FailedStatus(http::StatusCode);
+
+struct DstLabel<'a>(&'a NameAddr);
+
+struct ResultLabel(bool);
+
+struct Target {
+    probe: Probe,
+    svc: Option<svc::BoxHttp>,
+}
+
+// === impl Outbound ===
+
+impl Outbound<()> {
+    /// Returns a task that runs the configured probes.
+    ///
+    /// If no probes are configured, the task completes immediately.
+    pub fn probe<P, R>(self, profiles: P, resolve: R) -> impl Future<Output = ()> + Send + 'static
+    where
+        P: profiles::GetProfile<profiles::LookupAddr> + Clone + Send + Sync + Unpin + 'static,
+        P::Future: Send,
+        P::Error: Send,
+        R: Clone + Send + Sync + Unpin + 'static,
+        R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error>,
+        R::Resolution: Send,
+        R::Future: Send + Unpin,
+    {
+        let config = self.config.probe.clone();
+        let metrics = self.runtime.metrics.probes.clone();
+        let logical: svc::BoxNewHttp<http::Logical> = self
+            .to_tcp_connect()
+            .push_tcp_endpoint()
+            .push_http_endpoint()
+            .push_http_logical(resolve)
+            .into_inner();
+
+        async move {
+            if let Some(config) = config {
+                if !config.probes.is_empty() {
+                    run(config, logical, profiles, metrics).await;
+                }
+            }
+        }
+    }
+}
+
+async fn run<N, P>(config: Config, mut logical: N, mut profiles: P, metrics: Metrics)
+where
+    N: NewService<http::Logical, Service = svc::BoxHttp>,
+    P: profiles::GetProfile<profiles::LookupAddr>,
+{
+    let Config {
+        probes,
+        interval,
+        timeout,
+    } = config;
+    let mut targets = probes
+        .into_iter()
+        .map(|probe| Target { probe, svc: None })
+        .collect::<Vec<_>>();
+
+    let mut interval = time::interval(interval);
+    loop {
+        interval.tick().await;
+
+        // Build a logical stack for each destination whose profile has not
+        // yet been discovered. Once built, stacks are reused, so that they
+        // observe profile and endpoint updates like any other logical stack.
+        for target in targets.iter_mut().filter(|t| t.svc.is_none()) {
+            let dst = target.probe.dst.clone();
+            match discover(&mut profiles, &dst, timeout).await {
+                Ok(logical_target) => target.svc = Some(logical.new_service(logical_target)),
+                Err(error) => {
+                    warn!(%dst, %error, "Probe failed");
+                    metrics.record(&dst, false, None);
+                }
+            }
+        }
+
+        let probes = targets.iter_mut().filter_map(|t| {
+            let svc = t.svc.as_mut()?;
+            Some(send(svc, &t.probe, timeout, &metrics))
+        });
+        futures::future::join_all(probes).await;
+    }
+}
+
+async fn discover<P>(
+    profiles: &mut P,
+    dst: &NameAddr,
+    timeout: Duration,
+) -> Result<http::Logical, Error>
+where
+    P: profiles::GetProfile<profiles::LookupAddr>,
+{
+    let lookup = profiles.get_profile(profiles::LookupAddr(dst.clone().into()));
+    let profile = time::timeout(timeout, lookup)
+        .await
+        .map_err(|_| ProbeTimeout(timeout))?
+        .map_err(Into::<Error>::into)?
+        .ok_or_else(|| ProfileRequired(dst.clone()))?;
+    let logical_addr = profile
+        .logical_addr()
+        .ok_or_else(|| ProfileRequired(dst.clone()))?;
+    Ok(http::Logical {
+        profile,
+        logical_addr,
+        protocol: http::Version::Http1,
+        rewritten_from: None,
+    })
+}
+
+async fn send(svc: &mut svc::BoxHttp, probe: &Probe, timeout: Duration, metrics: &Metrics) {
+    let req = http::Request::get(probe.uri())
+        .header(http::header::USER_AGENT, USER_AGENT)
+        .body(http::BoxBody::default())
+        .expect("probe request must be valid");
+
+    let start = Instant::now();
+    let rsp = time::timeout(timeout, async move {
+        let mut rsp = svc.ready().await?.call(req).await?;
+        // Read the response body so that the connection may be reused.
+        while let Some(data) = http::HttpBody::data(rsp.body_mut()).await {
+            data?;
+        }
+        if rsp.status().is_server_error() {
+            return Err(FailedStatus(rsp.status()).into());
+        }
+        Ok::<_, Error>(rsp.status())
+    })
+    .await;
+    let elapsed = start.elapsed();
+
+    let res = match rsp {
+        Ok(res) => res,
+        Err(_) => Err(ProbeTimeout(timeout).into()),
+    };
+    match res {
+        Ok(status) => {
+            debug!(dst = %probe.dst, %status, ?elapsed, "Probe succeeded");
+            metrics.record(&probe.dst, true, Some(elapsed));
+        }
+        Err(error) => {
+            warn!(dst = %probe.dst, %error, "Probe failed");
+            metrics.record(&probe.dst, false, Some(elapsed));
+        }
+    }
+}
+
+// === impl Probe ===
+
+impl Probe {
+    fn uri(&self) -> http::uri::Uri {
+        http::uri::Uri::builder()
+            .scheme(http::uri::Scheme::HTTP)
+            .authority(self.dst.as_http_authority())
+            .path_and_query(self.path.clone())
+            .build()
+            .expect("probe URI must be valid")
+    }
+}
+
+impl FromStr for Probe {
+    type Err = InvalidProbe;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (authority, path) = match s.find('/') {
+            Some(idx) => s.split_at(idx),
+            None => (s, "/"),
+        };
+        let dst = NameAddr::from_str(authority)
+            .or_else(|_| NameAddr::from_str_and_port(authority, 80))
+            .map_err(|_| InvalidProbe(s.to_string()))?;
+        let path = path.parse().map_err(|_| InvalidProbe(s.to_string()))?;
+        Ok(Self { dst, path })
+    }
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.dst, self.path)
+    }
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    fn record(&self, dst: &NameAddr, success: bool, elapsed: Option<Duration>) {
+        let mut metrics = self.0.write();
+        let m = metrics.entry(dst.clone()).or_insert_with(|| ProbeMetrics {
+            success: Counter::default(),
+            failure: Counter::default(),
+            latency: Histogram::new(latency::BOUNDS),
+        });
+        if success {
+            m.success.incr();
+        } else {
+            m.failure.incr();
+        }
+        if let Some(elapsed) = elapsed {
+            m.latency.add(elapsed);
+        }
+    }
+}
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics = self.0.read();
+        if metrics.is_empty() {
+            return Ok(());
+        }
+
+        outbound_probe_total.fmt_help(f)?;
+        for (dst, m) in metrics.iter() {
+            outbound_probe_total.fmt_metric_labeled(
+                f,
+                &m.success,
+                (DstLabel(dst), ResultLabel(true)),
+            )?;
+            outbound_probe_total.fmt_metric_labeled(
+                f,
+                &m.failure,
+                (DstLabel(dst), ResultLabel(false)),
+            )?;
+        }
+
+        outbound_probe_latency_ms.fmt_help(f)?;
+        outbound_probe_latency_ms.fmt_scopes(
+            f,
+            metrics.iter().map(|(dst, m)| (DstLabel(dst), m)),
+            |m| &m.latency,
+        )
+    }
+}
+
+impl FmtLabels for DstLabel<'_> {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "dst=\"{}\"", self.0)
+    }
+}
+
+impl FmtLabels for ResultLabel {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = if self.0 { "success" } else { "failure" };
+        write!(f, "result=\"{}\"", result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_probes() {
+        let probe = |s: &str| s.parse::<Probe>().map(|p| p.to_string()).ok();
+        assert_eq!(
+            probe("web.ns.svc.cluster.local:8080/healthz?full=1"),
+            Some("web.ns.svc.cluster.local:8080/healthz?full=1".to_string())
+        );
+        assert_eq!(probe("web.ns"), Some("web.ns:80/".to_string()));
+        assert_eq!(probe(":80/ready"), None);
+    }
+}
@@ -0,0 +1,382 @@
+//! A consistent-hash load balancer.
+//!
+//! Each discovered endpoint is placed at several points on a hash ring.
+//! Requests are hashed onto the ring and dispatched to the first ready
+//! endpoint at or after the request's point, so that requests with the same
+//! key are consistently sent to the same endpoint. When endpoints are added or
+//! removed, only the keys that hashed to the affected points are rebalanced.
+//!
+//! Requests that have no key are distributed over the ready endpoints in
+//! turn, so a ring without a hasher is a round-robin balancer.
+//!
+//! Points are computed with FNV-1a, which (unlike the standard library's
+//! default hasher) is stable across releases, so that endpoint hashes held by
+//! clients remain valid when the proxy is upgraded.
+
+use fnv::FnvHasher;
+use futures::{future, TryFutureExt};
+use linkerd_app_core::{svc, Error};
+use std::{
+    future::Future,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tower::{
+    discover::{Change, Discover},
+    ready_cache::{error::Failed, ReadyCache},
+};
+use tracing::{debug, trace};
+
+/// The number of points at which each endpoint is placed on the ring.
+const POINTS_PER_ENDPOINT: u32 = 100;
+
+/// Hashes requests onto a ring.
+pub trait HashRequest<Req> {
+    /// Returns the request's hash, or `None` if the request has no key.
+    fn hash_request(&self, req: &Req) -> Option<u64>;
+}
+
+/// Balances requests over a discovered set of endpoints by consistent hashing.
+pub struct RingHash<D, H, Req>
+where
+    D: Discover,
+    D::Key: Hash,
+{
+    discover: D,
+    services: ReadyCache<D::Key, D::Service, Req>,
+    ring: Ring<D::Key>,
+    hasher: H,
+    next: usize,
+}
+
+/// Builds balancers for made `Discover`s, using a [`RingHash`] for targets
+/// that have a hashing policy and a `P`-typed balancer otherwise.
+pub struct MakeBalance<M, P, R, F, Req> {
+    inner: M,
+    p2c: P,
+    ring: R,
+    hasher: F,
+    _marker: PhantomData<fn(Req)>,
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("endpoint discovery ended")]
+pub struct DiscoveryEnded(());
+
+#[derive(Debug)]
+struct Ring<K> {
+    points: Vec<(u64, K)>,
+}
+
+// === impl HashRequest ===
+
+/// Balancers without a hasher distribute requests over their endpoints in
+/// turn.
+impl<H: HashRequest<Req>, Req> HashRequest<Req> for Option<H> {
+    fn hash_request(&self, req: &Req) -> Option<u64> {
+        self.as_ref()?.hash_request(req)
+    }
+}
+
+// === impl RingHash ===
+
+impl<D, H, Req> RingHash<D, H, Req>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<Error>,
+    D::Service: svc::Service<Req>,
+    <D::Service as svc::Service<Req>>::Error: Into<Error>,
+{
+    pub fn new(discover: D, hasher: H) -> Self {
+        Self {
+            discover,
+            services: ReadyCache::default(),
+            ring: Ring::default(),
+            hasher,
+            next: 0,
+        }
+    }
+
+    fn update_discover(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        // The ring is only sorted once all pending updates have been applied.
+        let mut changed = false;
+        let res = loop {
+            match Pin::new(&mut self.discover).poll_discover(cx) {
+                Poll::Ready(Some(Ok(Change::Insert(key, svc)))) => {
+                    trace!("Inserting endpoint");
+                    self.ring.remove(&key);
+                    self.ring.push(key.clone());
+                    self.services.push(key, svc);
+                    changed = true;
+                }
+                Poll::Ready(Some(Ok(Change::Remove(key)))) => {
+                    trace!("Removing endpoint");
+                    self.ring.remove(&key);
+                    self.services.evict(&key);
+                }
+                Poll::Ready(Some(Err(e))) => break Poll::Ready(Err(e.into())),
+                Poll::Ready(None) => break Poll::Ready(Err(DiscoveryEnded(()).into())),
+                Poll::Pending => break Poll::Pending,
+            }
+        };
+        if changed {
+            self.ring.sort();
+        }
+        res
+    }
+
+    fn promote_pending_to_ready(&mut self, cx: &mut Context<'_>) {
+        loop {
+            match self.services.poll_pending(cx) {
+                Poll::Ready(Ok(())) | Poll::Pending => return,
+                Poll::Ready(Err(Failed(key, error))) => {
+                    // The failed endpoint has been dropped from the cache, so
+                    // its keys are rebalanced onto the remaining endpoints.
+                    debug!(%error, "Endpoint failed");
+                    self.ring.remove(&key);
+                }
+            }
+        }
+    }
+
+    fn ready_index(&mut self, req: &Req) -> usize
+    where
+        H: HashRequest<Req>,
+    {
+        if let Some(hash) = self.hasher.hash_request(req) {
+            let services = &self.services;
+            if let Some(index) = self
+                .ring
+                .iter_from(hash)
+                .find_map(|key| services.get_ready(key).map(|(index, _, _)| index))
+            {
+                return index;
+            }
+        }
+
+        let index = self.next % self.services.ready_len();
+        self.next = self.next.wrapping_add(1);
+        index
+    }
+}
+
+impl<D, H, Req> svc::Service<Req> for RingHash<D, H, Req>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<Error>,
+    D::Service: svc::Service<Req>,
+    <D::Service as svc::Service<Req>>::Error: Into<Error>,
+    H: HashRequest<Req>,
+{
+    type Response = <D::Service as svc::Service<Req>>::Response;
+    type Error = Error;
+    type Future = future::ErrInto<<D::Service as svc::Service<Req>>::Future, Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Drain all pending discovery updates before checking readiness.
+        if let Poll::Ready(Err(e)) = self.update_discover(cx) {
+            return Poll::Ready(Err(e));
+        }
+        self.promote_pending_to_ready(cx);
+
+        if self.services.ready_len() == 0 {
+            trace!(pending = self.services.pending_len(), "No ready endpoints");
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let index = self.ready_index(&req);
+        self.services.call_ready_index(index, req).err_into()
+    }
+}
+
+/// Returns the hash of an endpoint's first point on the ring.
+///
+/// Requests with this hash are dispatched to the endpoint for as long as it is
+/// available.
+pub(crate) fn endpoint_hash<K: Hash>(key: &K) -> u64 {
+    point(key, 0)
+}
+
+fn point<K: Hash>(key: &K, i: u32) -> u64 {
+    let mut hasher = FnvHasher::default();
+    (key, i).hash(&mut hasher);
+    hasher.finish()
+}
+
+// === impl MakeBalance ===
+
+impl<M, P: Clone, R: Clone, F: Clone, Req> MakeBalance<M, P, R, F, Req> {
+    /// Builds a layer that balances over made `Discover`s.
+    ///
+    /// Targets for which `hasher` returns a `H`-typed request hasher use a
+    /// [`RingHash`] balancer (wrapped by the `ring` layer). All other targets
+    /// use the `p2c` layer.
+    pub fn layer(p2c: P, ring: R, hasher: F) -> impl svc::layer::Layer<M, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            p2c: p2c.clone(),
+            ring: ring.clone(),
+            hasher: hasher.clone(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<M: Clone, P: Clone, R: Clone, F: Clone, Req> Clone for MakeBalance<M, P, R, F, Req> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            p2c: self.p2c.clone(),
+            ring: self.ring.clone(),
+            hasher: self.hasher.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, M, P, R, F, H, Req> svc::Service<T> for MakeBalance<M, P, R, F, Req>
+where
+    M: svc::Service<T>,
+    M::Future: Send + 'static,
+    M::Response: Discover + Unpin,
+    <M::Response as Discover>::Key: Hash + Clone,
+    <M::Response as Discover>::Error: Into<Error>,
+    <M::Response as Discover>::Service: svc::Service<Req>,
+    <<M::Response as Discover>::Service as svc::Service<Req>>::Error: Into<Error>,
+    P: svc::layer::Layer<M::Response> + Clone + Send + 'static,
+    R: svc::layer::Layer<RingHash<M::Response, H, Req>> + Clone + Send + 'static,
+    F: Fn(&T) -> Option<H>,
+    H: Send + 'static,
+{
+    type Response = svc::Either<P::Service, R::Service>;
+    type Error = M::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, M::Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        let hasher = (self.hasher)(&target);
+        let p2c = self.p2c.clone();
+        let ring = self.ring.clone();
+        let discover = self.inner.call(target);
+        Box::pin(async move {
+            let discover = discover.await?;
+            let balance = match hasher {
+                Some(hasher) => {
+                    debug!("Balancing with a ring hash");
+                    svc::Either::B(ring.layer(RingHash::new(discover, hasher)))
+                }
+                None => svc::Either::A(p2c.layer(discover)),
+            };
+            Ok(balance)
+        })
+    }
+}
+
+// === impl Ring ===
+
+impl<K> Default for Ring<K> {
+    fn default() -> Self {
+        Self { points: Vec::new() }
+    }
+}
+
+impl<K: Hash + PartialEq> Ring<K> {
+    /// Adds an endpoint's points to the ring. The ring must be sorted before
+    /// it is used.
+    fn push(&mut self, key: K)
+    where
+        K: Clone,
+    {
+        for i in 0..POINTS_PER_ENDPOINT {
+            self.points.push((point(&key, i), key.clone()));
+        }
+    }
+
+    fn sort(&mut self) {
+        self.points.sort_unstable_by_key(|(point, _)| *point);
+    }
+
+    /// Removes an endpoint's points. The ring remains sorted.
+    fn remove(&mut self, key: &K) {
+        self.points.retain(|(_, k)| k != key);
+    }
+
+    /// Iterates over the ring's keys, starting at the first point at or after
+    /// `hash` and wrapping around the ring.
+    fn iter_from(&self, hash: u64) -> impl Iterator<Item = &K> + '_ {
+        let start = self.points.partition_point(|(point, _)| *point < hash);
+        self.points[start..]
+            .iter()
+            .chain(self.points[..start].iter())
+            .map(|(_, key)| key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    fn addr(n: u8) -> SocketAddr {
+        ([10, 0, 0, n], 8080).into()
+    }
+
+    fn hash(n: u32) -> u64 {
+        let mut hasher = FnvHasher::default();
+        n.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn ring_rebalances_minimally() {
+        let mut ring = Ring::default();
+        for n in 1..=4 {
+            ring.push(addr(n));
+        }
+        ring.sort();
+        let before = (0..1000)
+            .map(|n| *ring.iter_from(hash(n)).next().unwrap())
+            .collect::<Vec<_>>();
+
+        // Every endpoint receives some of the keys.
+        for n in 1..=4 {
+            assert!(before.contains(&addr(n)));
+        }
+
+        // Removing an endpoint only moves the keys that were assigned to it.
+        ring.remove(&addr(4));
+        for (n, prior) in before.iter().enumerate() {
+            let current = *ring.iter_from(hash(n as u32)).next().unwrap();
+            if *prior == addr(4) {
+                assert_ne!(current, addr(4));
+            } else {
+                assert_eq!(current, *prior);
+            }
+        }
+    }
+
+    #[test]
+    fn endpoint_hashes_select_endpoints() {
+        let mut ring = Ring::default();
+        for n in 1..=4 {
+            ring.push(addr(n));
+        }
+        ring.sort();
+        for n in 1..=4 {
+            let hash = endpoint_hash(&addr(n));
+            assert_eq!(*ring.iter_from(hash).next().unwrap(), addr(n));
+        }
+    }
+}
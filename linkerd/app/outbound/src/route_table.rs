@@ -0,0 +1,395 @@
+//! Tracks the profiles and endpoints resolved by the outbound proxy so that
+//! they may be inspected via the admin server.
+
+use futures::{prelude::*, ready};
+use linkerd_app_core::{
+    profiles,
+    proxy::{
+        api_resolve::{ConcreteAddr, Metadata},
+        core::Update,
+    },
+    svc, NameAddr,
+};
+use parking_lot::RwLock;
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+/// A read handle onto the outbound proxy's discovery state.
+///
+/// Entries are only retained while the stacks that discovered them are cached.
+#[derive(Clone, Debug, Default)]
+pub struct RouteTable(Arc<RwLock<Inner>>);
+
+/// The discovery state for a single logical destination.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub profile: profiles::Profile,
+
+    /// The endpoints of each of the profile's concrete targets, when they are
+    /// being balanced.
+    pub endpoints: Vec<(NameAddr, Option<Vec<(SocketAddr, Metadata)>>)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct NewRecordProfile<N> {
+    inner: N,
+    table: RouteTable,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordProfile<S> {
+    inner: S,
+    _guard: Option<Arc<ProfileGuard>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordResolve<S> {
+    inner: S,
+    table: RouteTable,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct RecordResolveFuture<F> {
+    #[pin]
+    inner: F,
+    table: RouteTable,
+    addr: Option<NameAddr>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct RecordResolution<R> {
+    #[pin]
+    inner: R,
+    guard: EndpointsGuard,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    profiles: HashMap<NameAddr, Tracked<profiles::Receiver>>,
+    endpoints: HashMap<NameAddr, Tracked<HashMap<SocketAddr, Metadata>>>,
+}
+
+#[derive(Debug)]
+struct Tracked<T> {
+    value: T,
+    refs: usize,
+}
+
+#[derive(Debug)]
+struct ProfileGuard {
+    table: RouteTable,
+    addr: NameAddr,
+}
+
+#[derive(Debug)]
+struct EndpointsGuard {
+    table: RouteTable,
+    addr: NameAddr,
+}
+
+// === impl RouteTable ===
+
+impl RouteTable {
+    /// Returns the current discovery state for the given logical address, if
+    /// it is known.
+    pub fn get(&self, addr: &NameAddr) -> Option<Snapshot> {
+        let inner = self.0.read();
+        let profile = inner.profiles.get(addr)?.value.profile();
+
+        // When a profile has no traffic split, the logical address is
+        // resolved directly.
+        let concrete = if profile.targets.is_empty() {
+            vec![addr.clone()]
+        } else {
+            profile.targets.iter().map(|t| t.addr.clone()).collect()
+        };
+        let endpoints = concrete
+            .into_iter()
+            .map(|addr| {
+                let eps = inner.endpoints.get(&addr).map(|eps| {
+                    let mut eps = eps
+                        .value
+                        .iter()
+                        .map(|(addr, meta)| (*addr, meta.clone()))
+                        .collect::<Vec<_>>();
+                    eps.sort_by_key(|(addr, _)| *addr);
+                    eps
+                });
+                (addr, eps)
+            })
+            .collect();
+
+        Some(Snapshot { profile, endpoints })
+    }
+
+    /// Returns the logical addresses of all known profiles.
+    pub fn logical_addrs(&self) -> Vec<NameAddr> {
+        let mut addrs = self.0.read().profiles.keys().cloned().collect::<Vec<_>>();
+        addrs.sort_by_key(|a| a.to_string());
+        addrs
+    }
+
+    fn register_profile(&self, addr: NameAddr, rx: profiles::Receiver) -> ProfileGuard {
+        self.0
+            .write()
+            .profiles
+            .entry(addr.clone())
+            .or_insert_with(|| Tracked::new(rx))
+            .refs += 1;
+        ProfileGuard {
+            table: self.clone(),
+            addr,
+        }
+    }
+
+    fn register_endpoints(&self, addr: NameAddr) -> EndpointsGuard {
+        self.0
+            .write()
+            .endpoints
+            .entry(addr.clone())
+            .or_insert_with(|| Tracked::new(HashMap::default()))
+            .refs += 1;
+        EndpointsGuard {
+            table: self.clone(),
+            addr,
+        }
+    }
+}
+
+// === impl Tracked ===
+
+impl<T> Tracked<T> {
+    fn new(value: T) -> Self {
+        Self { value, refs: 0 }
+    }
+}
+
+fn release<T>(map: &mut HashMap<NameAddr, Tracked<T>>, addr: &NameAddr) {
+    if let Some(tracked) = map.get_mut(addr) {
+        tracked.refs -= 1;
+        if tracked.refs == 0 {
+            map.remove(addr);
+        }
+    }
+}
+
+impl Drop for ProfileGuard {
+    fn drop(&mut self) {
+        release(&mut self.table.0.write().profiles, &self.addr);
+    }
+}
+
+impl Drop for EndpointsGuard {
+    fn drop(&mut self) {
+        release(&mut self.table.0.write().endpoints, &self.addr);
+    }
+}
+
+// === impl NewRecordProfile ===
+
+impl<N> NewRecordProfile<N> {
+    pub fn layer(table: RouteTable) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            table: table.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<(Option<profiles::Receiver>, T)> for NewRecordProfile<N>
+where
+    N: svc::NewService<(Option<profiles::Receiver>, T)>,
+{
+    type Service = RecordProfile<N::Service>;
+
+    fn new_service(&mut self, (profile, target): (Option<profiles::Receiver>, T)) -> Self::Service {
+        let guard = profile.as_ref().and_then(|rx| {
+            let profiles::LogicalAddr(addr) = rx.logical_addr()?;
+            Some(Arc::new(self.table.register_profile(addr, rx.clone())))
+        });
+        let inner = self.inner.new_service((profile, target));
+        RecordProfile {
+            inner,
+            _guard: guard,
+        }
+    }
+}
+
+impl<Req, S> svc::Service<Req> for RecordProfile<S>
+where
+    S: svc::Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+// === impl RecordResolve ===
+
+impl<S> RecordResolve<S> {
+    pub fn layer(table: RouteTable) -> impl svc::layer::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            table: table.clone(),
+        })
+    }
+}
+
+impl<S> svc::Service<ConcreteAddr> for RecordResolve<S>
+where
+    S: svc::Service<ConcreteAddr>,
+{
+    type Response = RecordResolution<S::Response>;
+    type Error = S::Error;
+    type Future = RecordResolveFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, ConcreteAddr(addr): ConcreteAddr) -> Self::Future {
+        let inner = self.inner.call(ConcreteAddr(addr.clone()));
+        RecordResolveFuture {
+            inner,
+            table: self.table.clone(),
+            addr: Some(addr),
+        }
+    }
+}
+
+impl<F, R, E> Future for RecordResolveFuture<F>
+where
+    F: TryFuture<Ok = R, Error = E>,
+{
+    type Output = Result<RecordResolution<R>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let inner = ready!(this.inner.try_poll(cx))?;
+        let addr = this.addr.take().expect("polled after ready");
+        let guard = this.table.register_endpoints(addr);
+        Poll::Ready(Ok(RecordResolution { inner, guard }))
+    }
+}
+
+impl<R, E> Stream for RecordResolution<R>
+where
+    R: Stream<Item = Result<Update<Metadata>, E>>,
+{
+    type Item = R::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let update = ready!(this.inner.poll_next(cx));
+        if let Some(Ok(ref update)) = update {
+            let mut table = this.guard.table.0.write();
+            if let Some(eps) = table.endpoints.get_mut(&this.guard.addr) {
+                match update {
+                    Update::Reset(reset) => {
+                        eps.value = reset.iter().cloned().collect();
+                    }
+                    Update::Add(adds) => {
+                        eps.value.extend(adds.iter().cloned());
+                    }
+                    Update::Remove(removes) => {
+                        for addr in removes.iter() {
+                            eps.value.remove(addr);
+                        }
+                    }
+                    Update::DoesNotExist => {
+                        eps.value.clear();
+                    }
+                }
+            }
+        }
+        Poll::Ready(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::watch;
+
+    fn logical(addr: &NameAddr) -> profiles::Receiver {
+        let (_, rx) = watch::channel(profiles::Profile {
+            addr: Some(profiles::LogicalAddr(addr.clone())),
+            ..Default::default()
+        });
+        rx.into()
+    }
+
+    #[test]
+    fn profiles_released_when_services_dropped() {
+        let addr = "web.example.com:8080".parse::<NameAddr>().unwrap();
+        let table = RouteTable::default();
+        let mut new_svc = NewRecordProfile {
+            inner: |_: (Option<profiles::Receiver>, ())| (),
+            table: table.clone(),
+        };
+        let svc0 = svc::NewService::new_service(&mut new_svc, (Some(logical(&addr)), ()));
+        let svc1 = svc::NewService::new_service(&mut new_svc, (Some(logical(&addr)), ()));
+        assert!(table.get(&addr).is_some());
+
+        drop(svc0);
+        assert!(table.get(&addr).is_some(), "still referenced");
+
+        drop(svc1);
+        assert!(table.get(&addr).is_none(), "released");
+    }
+
+    #[tokio::test]
+    async fn endpoints_track_updates() {
+        let addr = "web.example.com:8080".parse::<NameAddr>().unwrap();
+        let table = RouteTable::default();
+        let _profile = table.register_profile(addr.clone(), logical(&addr));
+
+        let ep0 = SocketAddr::from(([192, 0, 2, 10], 8080));
+        let ep1 = SocketAddr::from(([192, 0, 2, 11], 8080));
+        let updates = futures::stream::iter(vec![
+            Ok::<_, ()>(Update::Reset(vec![(ep0, Metadata::default())])),
+            Ok(Update::Add(vec![(ep1, Metadata::default())])),
+            Ok(Update::Remove(vec![ep0])),
+        ]);
+        let mut resolution = RecordResolution {
+            inner: updates,
+            guard: table.register_endpoints(addr.clone()),
+        };
+
+        resolution.next().await.unwrap().unwrap();
+        resolution.next().await.unwrap().unwrap();
+        let eps = |table: &RouteTable| {
+            table.get(&addr).unwrap().endpoints[0]
+                .1
+                .clone()
+                .map(|eps| eps.into_iter().map(|(a, _)| a).collect::<Vec<_>>())
+        };
+        assert_eq!(eps(&table), Some(vec![ep0, ep1]));
+
+        resolution.next().await.unwrap().unwrap();
+        assert_eq!(eps(&table), Some(vec![ep1]));
+
+        drop(resolution);
+        assert_eq!(eps(&table), None);
+    }
+}
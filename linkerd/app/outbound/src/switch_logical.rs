@@ -1,4 +1,7 @@
-use crate::{endpoint::Endpoint, logical::Logical, tcp, transport::OrigDstAddr, Outbound};
+use crate::{
+    endpoint::Endpoint, logical::Logical, policy::NewAuthorizeEgress, tcp, transport::OrigDstAddr,
+    Outbound,
+};
 use linkerd_app_core::{io, profiles, svc, Error, Infallible};
 use std::fmt;
 
@@ -26,7 +29,7 @@ impl<S> Outbound<S> {
         SSvc::Future: Send,
     {
         let no_tls_reason = self.no_tls_reason();
-        self.map_stack(|config, _, endpoint| {
+        self.map_stack(|config, rt, endpoint| {
             let inbound_ips = config.inbound_ips.clone();
             endpoint
                 .push_switch(
@@ -60,6 +63,12 @@ impl<S> Outbound<S> {
                     },
                     logical,
                 )
+                // Refuses connections to destinations that aren't permitted
+                // by the egress policy.
+                .push(NewAuthorizeEgress::layer(
+                    config.egress_policy.clone(),
+                    rt.metrics.egress_denied.clone(),
+                ))
                 .push_on_service(svc::BoxService::layer())
                 .push(svc::BoxNewService::layer())
         })
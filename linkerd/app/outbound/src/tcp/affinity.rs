@@ -0,0 +1,191 @@
+//! Source-address session affinity for opaque TCP services.
+//!
+//! By default, connections to a logical service are balanced over its
+//! endpoints by connect latency. Stateful protocols may instead need all of a
+//! client's connections to reach the same endpoint. Connections to services
+//! configured with source affinity are balanced by a consistent hash of the
+//! client's IP address, so that a client is sent to the same endpoint for as
+//! long as that endpoint is available. When endpoints are added or removed,
+//! only the clients that hashed to the affected endpoints are rebalanced.
+
+use crate::ring_hash::HashRequest;
+use futures::prelude::*;
+use linkerd_app_core::{
+    io,
+    profiles::LogicalAddr,
+    svc,
+    transport::{ClientAddr, Remote},
+    Error, NameAddr,
+};
+use linkerd_duplex::Duplex;
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+/// Logical services whose connections are balanced by client address.
+#[derive(Clone, Debug, Default)]
+pub struct SourceAffinity(Arc<HashSet<NameAddr>>);
+
+/// Hashes a client's IP address, ignoring its port.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct HashClientIp(());
+
+/// Forwards each connection over a balancer that is keyed by the connection's
+/// client address.
+#[derive(Clone, Debug)]
+pub(crate) struct ForwardFromClient<C> {
+    connect: C,
+}
+
+/// Allows an endpoint's connect thunk to be called with a client address, so
+/// that the same endpoint stack may be used by either kind of balancer.
+#[derive(Clone, Debug)]
+pub(crate) struct IgnoreClient<S>(S);
+
+// === impl SourceAffinity ===
+
+impl SourceAffinity {
+    pub fn new(authorities: impl IntoIterator<Item = NameAddr>) -> Self {
+        Self(Arc::new(authorities.into_iter().collect()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns a hasher if connections to the given logical address are
+    /// balanced by client address.
+    pub(crate) fn hasher(&self, LogicalAddr(addr): &LogicalAddr) -> Option<HashClientIp> {
+        if self.0.contains(addr) {
+            Some(HashClientIp(()))
+        } else {
+            None
+        }
+    }
+}
+
+// === impl HashClientIp ===
+
+impl HashRequest<Remote<ClientAddr>> for HashClientIp {
+    fn hash_request(&self, Remote(ClientAddr(addr)): &Remote<ClientAddr>) -> Option<u64> {
+        let mut hasher = DefaultHasher::new();
+        addr.ip().hash(&mut hasher);
+        Some(hasher.finish())
+    }
+}
+
+// === impl ForwardFromClient ===
+
+impl<C> ForwardFromClient<C> {
+    pub fn layer() -> impl svc::layer::Layer<C, Service = Self> + Clone + Copy {
+        svc::layer::mk(|connect| Self { connect })
+    }
+}
+
+impl<C, I> svc::Service<I> for ForwardFromClient<C>
+where
+    I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + Send + Unpin + 'static,
+    C: svc::Service<Remote<ClientAddr>> + Send + 'static,
+    C::Error: Into<Error>,
+    C::Future: Send + 'static,
+    C::Response: io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
+{
+    type Response = ();
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.connect.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, src_io: I) -> Self::Future {
+        let client = match src_io.peer_addr() {
+            Ok(addr) => Remote(ClientAddr(addr)),
+            Err(e) => return Box::pin(future::err(e.into())),
+        };
+        Box::pin(
+            self.connect
+                .call(client)
+                .err_into::<Error>()
+                .and_then(|dst_io| Duplex::new(src_io, dst_io).err_into::<Error>()),
+        )
+    }
+}
+
+// === impl IgnoreClient ===
+
+impl<S> IgnoreClient<S> {
+    pub fn layer() -> impl svc::layer::Layer<S, Service = Self> + Clone + Copy {
+        svc::layer::mk(Self)
+    }
+}
+
+impl<S: svc::Service<()>> svc::Service<()> for IgnoreClient<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, (): ()) -> Self::Future {
+        self.0.call(())
+    }
+}
+
+impl<S: svc::Service<()>> svc::Service<Remote<ClientAddr>> for IgnoreClient<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, _: Remote<ClientAddr>) -> Self::Future {
+        self.0.call(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(ip: [u8; 4], port: u16) -> Remote<ClientAddr> {
+        Remote(ClientAddr((ip, port).into()))
+    }
+
+    #[test]
+    fn hashes_client_ips() {
+        let affinity = SourceAffinity::new(vec!["db.ns.svc.cluster.local:5432".parse().unwrap()]);
+        let hasher = affinity
+            .hasher(&LogicalAddr(
+                "db.ns.svc.cluster.local:5432".parse().unwrap(),
+            ))
+            .expect("service must have affinity");
+        assert!(affinity
+            .hasher(&LogicalAddr("web.ns.svc.cluster.local:80".parse().unwrap()))
+            .is_none());
+
+        // Connections from the same client IP hash identically, regardless of
+        // their source ports.
+        assert_eq!(
+            hasher.hash_request(&client([10, 0, 0, 1], 40000)),
+            hasher.hash_request(&client([10, 0, 0, 1], 50000)),
+        );
+        assert_ne!(
+            hasher.hash_request(&client([10, 0, 0, 1], 40000)),
+            hasher.hash_request(&client([10, 0, 0, 2], 40000)),
+        );
+    }
+}
@@ -1,8 +1,13 @@
-use super::opaque_transport::{self, OpaqueTransport};
-use crate::Outbound;
+use super::{
+    http_proxy::HttpConnect,
+    opaque_transport::{self, OpaqueTransport},
+    tls_origination::OriginateTls,
+};
+use crate::{policy::AuthorizeTunnel, Outbound};
 use futures::future;
 use linkerd_app_core::{
     io,
+    profiles::LogicalAddr,
     proxy::http,
     svc, tls,
     transport::{self, ConnectTcp, Remote, ServerAddr},
@@ -26,8 +31,13 @@ pub struct PreventLoopback<S>(S);
 // === impl Outbound ===
 
 impl Outbound<()> {
-    pub fn to_tcp_connect(&self) -> Outbound<PreventLoopback<ConnectTcp>> {
-        let connect = PreventLoopback(ConnectTcp::new(self.config.proxy.connect.keepalive));
+    pub fn to_tcp_connect(&self) -> Outbound<PreventLoopback<HttpConnect<ConnectTcp>>> {
+        let connect = PreventLoopback(HttpConnect::new(
+            self.config.http_proxy.clone(),
+            self.runtime.dns.clone(),
+            ConnectTcp::new(self.config.proxy.connect.keepalive)
+                .with_mark(self.config.connect_mark),
+        ));
         self.clone().with_stack(connect)
     }
 }
@@ -38,7 +48,7 @@ impl<C> Outbound<C> {
     ) -> Outbound<
         impl svc::Service<
                 T,
-                Response = impl io::AsyncRead + io::AsyncWrite + Send + Unpin,
+                Response = impl io::AsyncRead + io::AsyncWrite + io::Splice + Send + Unpin,
                 Error = Error,
                 Future = impl Send,
             > + Clone,
@@ -49,10 +59,11 @@ impl<C> Outbound<C> {
             + svc::Param<Option<opaque_transport::PortOverride>>
             + svc::Param<Option<http::AuthorityOverride>>
             + svc::Param<Option<SessionProtocol>>
+            + svc::Param<Option<LogicalAddr>>
             + svc::Param<transport::labels::Key>,
         C: svc::Service<Connect, Error = io::Error> + Clone + Send + 'static,
         C::Response: tls::HasNegotiatedProtocol,
-        C::Response: io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
+        C::Response: io::AsyncRead + io::AsyncWrite + io::Splice + Send + Unpin + 'static,
         C::Future: Send + 'static,
     {
         self.map_stack(|config, rt, connect| {
@@ -65,12 +76,26 @@ impl<C> Outbound<C> {
                 // Encodes a transport header if the established connection is TLS'd and
                 // ALPN negotiation indicates support.
                 .push(OpaqueTransport::layer())
+                // Originates TLS to unmeshed destinations that are configured
+                // with their own certificates.
+                .push(OriginateTls::layer(config.tls_originations.clone()))
                 // Limits the time we wait for a connection to be established.
                 .push_connect_timeout(config.proxy.connect.timeout)
                 .push(svc::stack::BoxFuture::layer())
                 .push(transport::metrics::Client::layer(
                     rt.metrics.proxy.transport.clone(),
                 ))
+                // Refuses connections that would be tunneled through an HTTP
+                // forward proxy to endpoints that aren't permitted by the
+                // egress policy.
+                .push(AuthorizeTunnel::layer(
+                    config.egress_policy.clone(),
+                    config
+                        .http_proxy
+                        .as_ref()
+                        .map(|proxy| proxy.exempt_networks.clone()),
+                    rt.metrics.egress_denied.clone(),
+                ))
         })
     }
 
@@ -84,9 +109,10 @@ impl<C> Outbound<C> {
     >
     where
         T: Clone + Send + 'static,
-        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + std::fmt::Debug + Send + Unpin + 'static,
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + io::Splice,
+        I: std::fmt::Debug + Send + Unpin + 'static,
         C: svc::Service<T> + Clone + Send + Sync + 'static,
-        C::Response: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin,
+        C::Response: io::AsyncRead + io::AsyncWrite + io::Splice + Send + Unpin,
         C::Error: Into<Error>,
         C::Future: Send,
     {
@@ -0,0 +1,338 @@
+//! Tunnels outbound connections through an HTTP forward proxy.
+//!
+//! In environments where egress traffic must traverse a forward proxy, each
+//! outbound connection is established by connecting to the proxy and issuing
+//! a `CONNECT` request for the target address. Once the proxy accepts the
+//! request, the tunnel is used as if it were a direct connection to the target,
+//! so mesh TLS and opaque transport are negotiated end-to-end.
+//!
+//! Only plaintext (`http://`) proxies are supported. Connections to exempt
+//! networks bypass the proxy.
+
+use linkerd_app_core::{
+    dns,
+    io::{self, AsyncReadExt, AsyncWriteExt},
+    svc::{self, ServiceExt},
+    transport::{happy_eyeballs, Remote, ServerAddr},
+    Addr, IpMatch,
+};
+use std::{
+    fmt,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tracing::debug;
+
+#[derive(Clone)]
+pub struct Config {
+    /// The address of the forward proxy.
+    pub addr: Addr,
+
+    /// The value of the `proxy-authorization` header sent with each `CONNECT`
+    /// request, if any (e.g. `Basic dXNlcjpwYXNz`).
+    pub authorization: Option<Arc<str>>,
+
+    /// Destination networks that are connected to directly rather than through
+    /// the proxy.
+    pub exempt_networks: IpMatch,
+
+    /// How long to wait on a connection attempt before also trying the next
+    /// resolved address.
+    pub connect_delay: Duration,
+}
+
+/// Establishes connections through a forward proxy, if one is configured.
+#[derive(Clone, Debug)]
+pub struct HttpConnect<C> {
+    inner: C,
+    config: Option<Config>,
+    dns: dns::Resolver,
+}
+
+/// The target passed to the inner connector.
+#[derive(Copy, Clone, Debug)]
+pub struct ConnectAddr(Remote<ServerAddr>);
+
+/// Bounds the size of the proxy's response head.
+const MAX_RESPONSE_HEAD: usize = 8 * 1024;
+
+// === impl Config ===
+
+/// Avoids logging credentials.
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("addr", &self.addr)
+            .field(
+                "authorization",
+                &self.authorization.as_ref().map(|_| "<redacted>"),
+            )
+            .field("exempt_networks", &self.exempt_networks)
+            .field("connect_delay", &self.connect_delay)
+            .finish()
+    }
+}
+
+// === impl HttpConnect ===
+
+impl<C> HttpConnect<C> {
+    pub fn new(config: Option<Config>, dns: dns::Resolver, inner: C) -> Self {
+        Self { inner, config, dns }
+    }
+}
+
+impl<T, C> svc::Service<T> for HttpConnect<C>
+where
+    T: svc::Param<Remote<ServerAddr>>,
+    C: svc::Service<ConnectAddr, Error = io::Error> + Clone + Send + 'static,
+    C::Response: io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
+    C::Future: Send + 'static,
+{
+    type Response = C::Response;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<C::Response>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, t: T) -> Self::Future {
+        let target = t.param();
+        let Config {
+            addr,
+            authorization,
+            exempt_networks,
+            connect_delay,
+        } = match self.config.clone() {
+            Some(config) => config,
+            None => return Box::pin(self.inner.call(ConnectAddr(target))),
+        };
+        if exempt_networks.matches(target.0.ip()) {
+            debug!(dst = %target.0, "Connecting directly to exempt network");
+            return Box::pin(self.inner.call(ConnectAddr(target)));
+        }
+
+        // The proxy's address may need to be resolved before connecting, and it
+        // may resolve to several addresses, so connections are made by clones
+        // of the inner service.
+        let inner = self.inner.clone();
+        let dns = self.dns.clone();
+        Box::pin(async move {
+            let proxies = resolve(&addr, &dns).await?;
+            let Remote(ServerAddr(dst)) = target;
+            debug!(?proxies, %dst, "Connecting through HTTP proxy");
+            let mut io = happy_eyeballs::connect(proxies, connect_delay, move |proxy| {
+                inner
+                    .clone()
+                    .oneshot(ConnectAddr(Remote(ServerAddr(proxy))))
+            })
+            .await?;
+            handshake(&mut io, dst, authorization.as_deref()).await?;
+            Ok(io)
+        })
+    }
+}
+
+/// Resolves the proxy's addresses, ordered by the resolver's address family
+/// preference.
+async fn resolve(addr: &Addr, dns: &dns::Resolver) -> io::Result<Vec<SocketAddr>> {
+    match addr {
+        Addr::Socket(sa) => Ok(vec![*sa]),
+        Addr::Name(na) => {
+            let ips = dns.resolve_ips(na.name()).await.map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("HTTP proxy {} could not be resolved: {}", na, e),
+                )
+            })?;
+            let addrs = ips
+                .into_iter()
+                .map(|ip| SocketAddr::new(ip, na.port()))
+                .collect::<Vec<_>>();
+            if addrs.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("HTTP proxy {} could not be resolved", na),
+                ));
+            }
+            Ok(addrs)
+        }
+    }
+}
+
+/// Issues a `CONNECT` request and waits for the proxy to establish a tunnel.
+async fn handshake<I>(io: &mut I, dst: SocketAddr, authorization: Option<&str>) -> io::Result<()>
+where
+    I: io::AsyncRead + io::AsyncWrite + Unpin,
+{
+    let mut req = format!("CONNECT {0} HTTP/1.1\r\nhost: {0}\r\n", dst);
+    if let Some(authorization) = authorization {
+        // Credentials must not be able to inject headers into the request.
+        if authorization.contains(|c| c == '\r' || c == '\n') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "HTTP proxy authorization must not contain line breaks",
+            ));
+        }
+        req.push_str("proxy-authorization: ");
+        req.push_str(authorization);
+        req.push_str("\r\n");
+    }
+    req.push_str("\r\n");
+    io.write_all(req.as_bytes()).await?;
+    io.flush().await?;
+
+    // Read the response head one byte at a time so that none of the tunneled
+    // data is consumed.
+    let mut head = Vec::with_capacity(64);
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() == MAX_RESPONSE_HEAD {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "HTTP proxy response is too large",
+            ));
+        }
+        head.push(io.read_u8().await?);
+    }
+
+    let status = head
+        .split(|b| *b == b' ')
+        .nth(1)
+        .and_then(|s| std::str::from_utf8(s).ok())
+        .and_then(|s| s.trim().parse::<u16>().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid HTTP proxy response"))?;
+    if !(200..300).contains(&status) {
+        return Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!("HTTP proxy refused to connect to {}: {}", dst, status),
+        ));
+    }
+
+    debug!(%dst, status, "HTTP proxy established tunnel");
+    Ok(())
+}
+
+// === impl ConnectAddr ===
+
+impl svc::Param<Remote<ServerAddr>> for ConnectAddr {
+    fn param(&self) -> Remote<ServerAddr> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::support;
+    use futures::future;
+
+    fn dst() -> Remote<ServerAddr> {
+        Remote(ServerAddr(([10, 0, 0, 2], 4143).into()))
+    }
+
+    fn proxy() -> SocketAddr {
+        ([192, 0, 2, 10], 3128).into()
+    }
+
+    fn resolver() -> dns::Resolver {
+        dns::Resolver::new(Default::default(), Default::default())
+    }
+
+    fn config(authorization: Option<&str>) -> Config {
+        Config {
+            addr: proxy().into(),
+            authorization: authorization.map(Into::into),
+            exempt_networks: Default::default(),
+            connect_delay: Duration::from_millis(250),
+        }
+    }
+
+    #[tokio::test]
+    async fn connects_directly() {
+        let connect = HttpConnect::new(
+            None,
+            resolver(),
+            svc::mk(|ConnectAddr(addr)| {
+                assert_eq!(addr, dst());
+                future::ok::<_, io::Error>(support::io().build())
+            }),
+        );
+        connect.oneshot(dst()).await.expect("must connect");
+    }
+
+    #[tokio::test]
+    async fn tunnels_through_proxy() {
+        let connect = HttpConnect::new(
+            Some(config(Some("Basic dXNlcjpwYXNz"))),
+            resolver(),
+            svc::mk(|ConnectAddr(Remote(ServerAddr(addr)))| {
+                assert_eq!(addr, proxy());
+                let io = support::io()
+                    .write(
+                        b"CONNECT 10.0.0.2:4143 HTTP/1.1\r\n\
+                          host: 10.0.0.2:4143\r\n\
+                          proxy-authorization: Basic dXNlcjpwYXNz\r\n\r\n",
+                    )
+                    .read(b"HTTP/1.1 200 Connection established\r\n\r\nhello")
+                    .build();
+                future::ok::<_, io::Error>(io)
+            }),
+        );
+        let mut io = connect.oneshot(dst()).await.expect("must connect");
+
+        // Data following the response head is left in the tunnel.
+        let mut buf = [0u8; 5];
+        io.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[tokio::test]
+    async fn fails_when_refused() {
+        let connect = HttpConnect::new(
+            Some(config(None)),
+            resolver(),
+            svc::mk(|_: ConnectAddr| {
+                let io = support::io()
+                    .write(b"CONNECT 10.0.0.2:4143 HTTP/1.1\r\nhost: 10.0.0.2:4143\r\n\r\n")
+                    .read(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                    .build();
+                future::ok::<_, io::Error>(io)
+            }),
+        );
+        let err = connect.oneshot(dst()).await.expect_err("must fail");
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+    }
+
+    #[tokio::test]
+    async fn bypasses_exempt_networks() {
+        let config = Config {
+            exempt_networks: IpMatch::new(Some("10.0.0.0/8".parse().unwrap())),
+            ..config(None)
+        };
+        let connect = HttpConnect::new(
+            Some(config),
+            resolver(),
+            svc::mk(|ConnectAddr(addr)| {
+                assert_eq!(addr, dst());
+                future::ok::<_, io::Error>(support::io().build())
+            }),
+        );
+        connect.oneshot(dst()).await.expect("must connect");
+    }
+
+    #[tokio::test]
+    async fn rejects_line_breaks_in_authorization() {
+        let connect = HttpConnect::new(
+            Some(config(Some("Basic dXNlcjpwYXNz\r\nx-injected: true"))),
+            resolver(),
+            svc::mk(|_: ConnectAddr| future::ok::<_, io::Error>(support::io().build())),
+        );
+        let err = connect.oneshot(dst()).await.expect_err("must fail");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -1,5 +1,7 @@
-use super::{Concrete, Endpoint, Logical};
-use crate::{endpoint, resolve, Outbound};
+use super::{affinity, Concrete, Endpoint, Logical};
+use crate::{
+    balance, endpoint, failover, health, multicluster, resolve, ring_hash, route_table, Outbound,
+};
 use linkerd_app_core::{
     config, drain, io, profiles,
     proxy::{
@@ -15,7 +17,7 @@ use tracing::debug_span;
 impl<C> Outbound<C>
 where
     C: svc::Service<Endpoint> + Clone + Send + 'static,
-    C::Response: io::AsyncRead + io::AsyncWrite + Send + Unpin,
+    C::Response: io::AsyncRead + io::AsyncWrite + io::Splice + Send + Unpin,
     C::Error: Into<Error>,
     C::Future: Send,
 {
@@ -30,7 +32,8 @@ where
         >,
     >
     where
-        I: io::AsyncRead + io::AsyncWrite + std::fmt::Debug + Send + Unpin + 'static,
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + io::Splice,
+        I: std::fmt::Debug + Send + Unpin + 'static,
         R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error>
             + Clone
             + Send
@@ -48,9 +51,21 @@ where
                 ..
             } = config.proxy;
 
+            let introspect = &rt.metrics.proxy.introspect;
+            let logical = introspect.register(crate::stack_labels("tcp", "logical"));
+            let balancer = introspect.register(crate::stack_labels("tcp", "balancer"));
+            logical.push_inner(&balancer);
+
+            let source_affinity = config.tcp_source_affinity.clone();
+            let algorithms = config.balancer_algorithms.clone();
+            let zone_affinity = config.zone_affinity.clone();
             let identity_disabled = rt.identity.is_none();
             let resolve = svc::stack(resolve.into_service())
                 .check_service::<ConcreteAddr>()
+                .push(multicluster::Resolve::layer(
+                    config.multicluster_failovers.clone(),
+                ))
+                .push(route_table::RecordResolve::layer(rt.route_table.clone()))
                 .push_request_filter(|c: Concrete| Ok::<_, Infallible>(c.resolve))
                 .push(svc::layer::mk(move |inner| {
                     map_endpoint::Resolve::new(
@@ -66,6 +81,13 @@ where
 
             connect
                 .push_make_thunk()
+                .push(health::NewHealthCheck::<(), _>::layer(
+                    config.health_checks.clone(),
+                ))
+                .push_on_service(affinity::IgnoreClient::layer())
+                .push(balance::NewWeighted::layer(
+                    config.balancer_algorithms.clone(),
+                ))
                 .instrument(|t: &Endpoint| match t.tls.as_ref() {
                     Conditional::Some(tls) => {
                         debug_span!("endpoint", server.addr = %t.addr, server.id = ?tls.server_id)
@@ -75,11 +97,19 @@ where
                     }
                 })
                 .push(resolve::layer(resolve, config.proxy.cache_max_idle_age * 2))
-                .push_on_service(
+                .push(balance::MakeTrackReadiness::layer())
+                // Services with source affinity are balanced by a consistent
+                // hash of the client's address, and round-robin services by a
+                // ring without a hasher; all others are balanced by load.
+                .push(ring_hash::MakeBalance::layer(
                     svc::layers()
-                        .push(tcp::balance::layer(
-                            crate::EWMA_DEFAULT_RTT,
-                            crate::EWMA_DECAY,
+                        .push(balance::layer(
+                            config.ewma,
+                            tower::load::CompleteOnResponse::default(),
+                            zone_affinity,
+                            config.slow_start_window,
+                            config.outlier_detection.clone(),
+                            rt.metrics.outliers.clone(),
                         ))
                         .push(
                             rt.metrics
@@ -87,10 +117,29 @@ where
                                 .stack
                                 .layer(crate::stack_labels("tcp", "balancer")),
                         )
-                        .push(tcp::Forward::layer())
-                        .push(drain::Retain::layer(rt.drain.clone())),
-                )
+                        .push(tcp::Forward::layer()),
+                    svc::layers()
+                        .push(
+                            rt.metrics
+                                .proxy
+                                .stack
+                                .layer(crate::stack_labels("tcp", "balancer")),
+                        )
+                        .push(affinity::ForwardFromClient::layer()),
+                    move |c: &Concrete| {
+                        let addr = &c.logical.logical_addr;
+                        match source_affinity.hasher(addr) {
+                            Some(hasher) => Some(Some(hasher)),
+                            None if algorithms.get(addr) == balance::Algorithm::RoundRobin => {
+                                Some(None)
+                            }
+                            None => None,
+                        }
+                    },
+                ))
+                .push_on_service(drain::Retain::layer(rt.drain.clone()))
                 .into_new_service()
+                .push(failover::NewFailover::layer(config.failovers.clone()))
                 .push_map_target(Concrete::from)
                 .push(svc::BoxNewService::layer())
                 .check_new_service::<(ConcreteAddr, Logical), I>()
@@ -104,10 +153,10 @@ where
                                 .layer(crate::stack_labels("tcp", "logical")),
                         )
                         .push(svc::layer::mk(svc::SpawnReady::new))
-                        .push(svc::FailFast::layer("TCP Logical", dispatch_timeout))
+                        .push(logical.failfast_layer("TCP Logical", dispatch_timeout))
                         .push_spawn_buffer(buffer_capacity),
                 )
-                .push_cache(cache_max_idle_age)
+                .push(logical.cache_layer(cache_max_idle_age))
                 .check_new_service::<Logical, I>()
                 .instrument(|_: &Logical| debug_span!("tcp"))
                 .check_new_service::<Logical, I>()
@@ -146,6 +195,7 @@ mod tests {
             profile: rx.into(),
             logical_addr: logical_addr.clone(),
             protocol: (),
+            rewritten_from: None,
         };
 
         // The resolution resolves a single endpoint.
@@ -209,6 +259,7 @@ mod tests {
             profile: rx.into(),
             logical_addr: logical_addr.clone(),
             protocol: (),
+            rewritten_from: None,
         };
 
         // The resolution resolves a single endpoint.
@@ -1,8 +1,15 @@
+pub mod affinity;
 pub mod connect;
+pub mod http_proxy;
 pub mod logical;
 pub mod opaque_transport;
+pub mod proxy_protocol;
+pub mod tls_origination;
 
-pub use self::connect::Connect;
+pub use self::{
+    affinity::SourceAffinity, connect::Connect, proxy_protocol::ProxyProtocolTargets,
+    tls_origination::TlsOriginations,
+};
 pub use linkerd_app_core::proxy::tcp::Forward;
 use linkerd_app_core::{svc::Param, transport::OrigDstAddr, transport_header::SessionProtocol};
 
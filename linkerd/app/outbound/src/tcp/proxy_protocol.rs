@@ -0,0 +1,184 @@
+//! Prepends PROXY protocol headers to opaque TCP connections.
+//!
+//! Some upstream servers (e.g. external TCP load balancers) need to know the
+//! address of the client that initiated a connection. When a connection's
+//! target matches a configured port or authority, a PROXY protocol v2 header
+//! describing the application's address is written to the upstream connection
+//! before any of the application's data.
+//!
+//! Headers are only emitted for connections that are forwarded opaquely, since
+//! HTTP requests may be multiplexed over shared upstream connections.
+
+use futures::{future, TryFutureExt};
+use linkerd_app_core::{io, svc, transport::proxy_protocol::encode_header, Addr, Error, NameAddr};
+use std::{
+    collections::HashSet,
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::{debug, warn};
+
+/// Ports and authorities whose connections are prefixed with a PROXY protocol
+/// header.
+#[derive(Clone, Debug, Default)]
+pub struct ProxyProtocolTargets {
+    ports: Arc<HashSet<u16>>,
+    authorities: Arc<HashSet<NameAddr>>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct NewEmitProxyProtocol<N> {
+    inner: N,
+    targets: ProxyProtocolTargets,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct EmitProxyProtocol<S> {
+    inner: S,
+
+    /// The server address to encode in headers, if headers are emitted for
+    /// this target.
+    server: Option<SocketAddr>,
+}
+
+// === impl ProxyProtocolTargets ===
+
+impl ProxyProtocolTargets {
+    pub fn new(
+        ports: impl IntoIterator<Item = u16>,
+        authorities: impl IntoIterator<Item = NameAddr>,
+    ) -> Self {
+        Self {
+            ports: Arc::new(ports.into_iter().collect()),
+            authorities: Arc::new(authorities.into_iter().collect()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ports.is_empty() && self.authorities.is_empty()
+    }
+
+    /// Returns the server address to be encoded in headers for connections to
+    /// `addr`, if headers are to be emitted.
+    ///
+    /// Named targets have no single server address, so the unspecified
+    /// address is used with the target's port.
+    fn server_addr(&self, addr: &Addr) -> Option<SocketAddr> {
+        match addr {
+            Addr::Socket(sa) if self.ports.contains(&sa.port()) => Some(*sa),
+            Addr::Name(na) if self.ports.contains(&na.port()) || self.authorities.contains(na) => {
+                Some((Ipv4Addr::UNSPECIFIED, na.port()).into())
+            }
+            _ => None,
+        }
+    }
+}
+
+// === impl NewEmitProxyProtocol ===
+
+impl<N> NewEmitProxyProtocol<N> {
+    pub fn layer(
+        targets: ProxyProtocolTargets,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            targets: targets.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewEmitProxyProtocol<N>
+where
+    T: svc::Param<Addr>,
+    N: svc::NewService<T>,
+{
+    type Service = EmitProxyProtocol<N::Service>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        let server = if self.targets.is_empty() {
+            None
+        } else {
+            self.targets.server_addr(&target.param())
+        };
+        EmitProxyProtocol {
+            inner: self.inner.new_service(target),
+            server,
+        }
+    }
+}
+
+// === impl EmitProxyProtocol ===
+
+impl<I, S> svc::Service<I> for EmitProxyProtocol<S>
+where
+    I: io::PeerAddr,
+    S: svc::Service<io::PrefixedIo<I>>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<S::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        let server = match self.server {
+            Some(server) => server,
+            None => return future::Either::Left(self.inner.call(io.into()).err_into()),
+        };
+
+        let client = match io.peer_addr() {
+            Ok(client) => client,
+            Err(error) => {
+                warn!(%error, "Could not determine the client address");
+                return future::Either::Right(future::err(error.into()));
+            }
+        };
+
+        // The header is read ahead of the application's data, so it is the
+        // first thing written to the upstream connection.
+        debug!(%client, %server, "Emitting PROXY protocol header");
+        let io = io::PrefixedIo::new(encode_header(client, server), io);
+        future::Either::Left(self.inner.call(io).err_into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_targets() {
+        let targets = ProxyProtocolTargets::new(
+            vec![5432],
+            vec!["lb.example.com:443".parse::<NameAddr>().unwrap()],
+        );
+
+        let sa = SocketAddr::from(([10, 0, 0, 2], 5432));
+        assert_eq!(targets.server_addr(&sa.into()), Some(sa));
+        assert_eq!(
+            targets.server_addr(&SocketAddr::from(([10, 0, 0, 2], 80)).into()),
+            None
+        );
+
+        let na = "lb.example.com:443".parse::<NameAddr>().unwrap();
+        assert_eq!(
+            targets.server_addr(&na.into()),
+            Some((Ipv4Addr::UNSPECIFIED, 443).into())
+        );
+        let na = "db.example.com:5432".parse::<NameAddr>().unwrap();
+        assert_eq!(
+            targets.server_addr(&na.into()),
+            Some((Ipv4Addr::UNSPECIFIED, 5432).into())
+        );
+        let na = "other.example.com:443".parse::<NameAddr>().unwrap();
+        assert_eq!(targets.server_addr(&na.into()), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Originates TLS to configured destinations outside of the mesh.
+//!
+//! Applications may connect in plaintext to external servers that require
+//! (mutual) TLS, e.g. APIs and databases. When an unmeshed connection's target
+//! matches a configured authority or address, the proxy originates TLS with
+//! the destination's operator-supplied trust anchors and client certificate,
+//! which are distinct from the mesh identity.
+
+use futures::prelude::*;
+use linkerd_app_core::{
+    io,
+    profiles::LogicalAddr,
+    svc, tls,
+    transport::{Remote, ServerAddr},
+    Addr, Error, NameAddr,
+};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::debug;
+
+/// Authorities and addresses to which TLS is originated.
+#[derive(Clone, Debug, Default)]
+pub struct TlsOriginations {
+    authorities: Arc<HashMap<NameAddr, tls::Originate>>,
+    addrs: Arc<HashMap<SocketAddr, tls::Originate>>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct OriginateTls<S> {
+    originations: TlsOriginations,
+    inner: S,
+}
+
+type Io<I> = io::EitherIo<I, tls::client::TlsStream<I>>;
+
+// === impl TlsOriginations ===
+
+impl TlsOriginations {
+    pub fn new(originations: impl IntoIterator<Item = (Addr, tls::Originate)>) -> Self {
+        let mut authorities = HashMap::new();
+        let mut addrs = HashMap::new();
+        for (addr, originate) in originations {
+            match addr {
+                Addr::Name(name) => {
+                    authorities.insert(name, originate);
+                }
+                Addr::Socket(addr) => {
+                    addrs.insert(addr, originate);
+                }
+            }
+        }
+        Self {
+            authorities: Arc::new(authorities),
+            addrs: Arc::new(addrs),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.authorities.is_empty() && self.addrs.is_empty()
+    }
+
+    /// Returns the TLS configuration for an endpoint, preferring its logical
+    /// authority to its address.
+    fn get(&self, logical: Option<&LogicalAddr>, addr: &SocketAddr) -> Option<&tls::Originate> {
+        logical
+            .and_then(|LogicalAddr(name)| self.authorities.get(name))
+            .or_else(|| self.addrs.get(addr))
+    }
+}
+
+// === impl OriginateTls ===
+
+impl<S> OriginateTls<S> {
+    pub fn layer(
+        originations: TlsOriginations,
+    ) -> impl svc::layer::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            originations: originations.clone(),
+            inner,
+        })
+    }
+}
+
+impl<T, S> svc::Service<T> for OriginateTls<S>
+where
+    T: svc::Param<tls::ConditionalClientTls>
+        + svc::Param<Remote<ServerAddr>>
+        + svc::Param<Option<LogicalAddr>>,
+    S: svc::Service<T, Error = Error>,
+    S::Response: io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Io<S::Response>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        // Meshed endpoints are secured by the mesh identity.
+        let originate = match svc::Param::<tls::ConditionalClientTls>::param(&target) {
+            tls::ConditionalClientTls::Some(_) => None,
+            tls::ConditionalClientTls::None(_) if self.originations.is_empty() => None,
+            tls::ConditionalClientTls::None(_) => {
+                let logical: Option<LogicalAddr> = target.param();
+                let Remote(ServerAddr(addr)) = target.param();
+                self.originations.get(logical.as_ref(), &addr).cloned()
+            }
+        };
+
+        let connect = self.inner.call(target);
+        let originate = match originate {
+            Some(originate) => originate,
+            None => return Box::pin(connect.map_ok(io::EitherIo::Left)),
+        };
+
+        debug!(server.name = %originate.server_name(), "Originating TLS");
+        Box::pin(async move {
+            let io = connect.await?;
+            let io = originate.connect(io).await?;
+            Ok(io::EitherIo::Right(io))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn originate(name: &str) -> tls::Originate {
+        tls::Originate::new(
+            name.parse().unwrap(),
+            include_bytes!("../../../../identity/src/testdata/ca1.pem"),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn prefers_logical_authority() {
+        let originations = TlsOriginations::new(vec![
+            (
+                Addr::from_str("db.example.com:5432").unwrap(),
+                originate("db.example.com"),
+            ),
+            (
+                Addr::from_str("192.0.2.10:443").unwrap(),
+                originate("api.example.com"),
+            ),
+        ]);
+        let name = |o: Option<&tls::Originate>| o.map(|o| o.server_name().to_string());
+
+        let db = LogicalAddr("db.example.com:5432".parse().unwrap());
+        let api = "192.0.2.10:443".parse().unwrap();
+        assert_eq!(
+            name(originations.get(Some(&db), &api)),
+            Some("db.example.com".to_string())
+        );
+        assert_eq!(
+            name(originations.get(None, &api)),
+            Some("api.example.com".to_string())
+        );
+        assert_eq!(
+            name(originations.get(None, &"192.0.2.11:443".parse().unwrap())),
+            None
+        );
+    }
+}
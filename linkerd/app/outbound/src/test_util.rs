@@ -1,7 +1,7 @@
 use crate::Config;
 pub use futures::prelude::*;
 use linkerd_app_core::{
-    config, drain, exp_backoff, metrics,
+    config, dns, drain, exp_backoff, metrics,
     proxy::{
         http::{h1, h2},
         tap,
@@ -15,15 +15,59 @@ use std::{str::FromStr, time::Duration};
 pub(crate) fn default_config() -> Config {
     Config {
         ingress_mode: false,
+        authority_rewrites: Default::default(),
+        retry_suppression_after: None,
+        retry_max_buffered_bytes: 64 * 1024,
+        retry_after_max: Duration::from_secs(10),
+        retry_unprocessed: false,
+        retry_backoffs: Default::default(),
+        retry_per_try_timeouts: Default::default(),
+        retry_budgets: Default::default(),
+        egress_policy: Default::default(),
+        proxy_protocol: Default::default(),
+        tls_originations: Default::default(),
+        http_proxy: None,
+        connect_mark: None,
+        tcp_bandwidth_limits: Default::default(),
+        skip_detect: Default::default(),
+        http_hash_policies: Default::default(),
+        http_sticky_sessions: Default::default(),
+        http_hedges: Default::default(),
+        http_faults: Default::default(),
+        http_header_policies: Default::default(),
+        http_route_filters: Default::default(),
+        tcp_source_affinity: Default::default(),
+        ewma: crate::balance::EwmaConfig {
+            default_rtt: Duration::from_millis(30),
+            decay: Duration::from_secs(10),
+        },
+        balancer_algorithms: Default::default(),
+        zone_affinity: None,
+        slow_start_window: None,
+        outlier_detection: None,
+        http_circuit_breakers: Default::default(),
+        http_concurrency_limits: Default::default(),
+        health_checks: Default::default(),
+        failovers: Default::default(),
+        multicluster_failovers: Default::default(),
+        http_mirrors: Default::default(),
+        udp: crate::udp::Config {
+            forwards: vec![],
+            idle_timeout: Duration::from_secs(10),
+            max_flows: 16,
+        },
         allow_discovery: IpMatch::new(Some(IpNet::from_str("0.0.0.0/0").unwrap())).into(),
         proxy: config::ProxyConfig {
             server: config::ServerConfig {
                 addr: ListenAddr(([0, 0, 0, 0], 0).into()),
-                keepalive: Keepalive(None),
+                keepalive: Keepalive::default(),
                 h2_settings: h2::Settings::default(),
+                proxy_protocol: Default::default(),
+                backlog: Default::default(),
+                accept_limits: Default::default(),
             },
             connect: config::ConnectConfig {
-                keepalive: Keepalive(None),
+                keepalive: Keepalive::default(),
                 timeout: Duration::from_secs(1),
                 backoff: exp_backoff::ExponentialBackoff::new(
                     Duration::from_millis(100),
@@ -44,6 +88,7 @@ pub(crate) fn default_config() -> Config {
             detect_protocol_timeout: Duration::from_secs(3),
         },
         inbound_ips: Default::default(),
+        probe: None,
     }
 }
 
@@ -56,6 +101,11 @@ pub(crate) fn runtime() -> (ProxyRuntime, drain::Signal) {
         metrics: metrics.proxy,
         tap,
         span_sink: None,
+        trace_precedence: Default::default(),
+        access_log: None,
+        rate_limit: None,
+        ext_authz: None,
+        dns: dns::Resolver::new(Default::default(), Default::default()),
         drain,
     };
     (runtime, drain_tx)
@@ -0,0 +1,556 @@
+//! Forwards UDP datagrams (e.g. DNS queries) through the outbound proxy.
+//!
+//! Each configured forward binds a UDP socket (to which application traffic,
+//! e.g. for port 53, may be redirected) and relays datagrams to an upstream
+//! address. Each client address is treated as a flow with its own upstream
+//! socket, so that responses can be returned to the client that sent the
+//! request. Flows are closed once they have been idle for the configured
+//! timeout. The number of flows is bounded, so when a forward has too many
+//! flows the least recently active one is evicted.
+//!
+//! The original destination of a redirected datagram cannot be recovered from
+//! an ordinary UDP socket, so each forward has a fixed upstream (e.g. the
+//! cluster's DNS service). Forwards whose upstreams are not permitted by the
+//! egress policy drop all of their datagrams.
+
+use crate::{policy, Outbound};
+use linkerd_app_core::metrics::{metrics, Counter, FmtLabels, FmtMetrics, Gauge};
+use parking_lot::RwLock;
+use std::{
+    collections::HashMap, fmt, future::Future, io, net::SocketAddr, str::FromStr, sync::Arc,
+    time::Duration,
+};
+use thiserror::Error;
+use tokio::{net::UdpSocket, sync::mpsc, time};
+use tracing::{debug, info_span, warn, Instrument};
+
+metrics! {
+    outbound_udp_flows_total: Counter {
+        "The total number of UDP flows, by upstream address"
+    },
+    outbound_udp_open_flows: Gauge {
+        "The number of UDP flows that have not yet timed out, by upstream address"
+    },
+    outbound_udp_datagrams_total: Counter {
+        "The total number of UDP datagrams forwarded, by upstream address and direction"
+    },
+    outbound_udp_bytes_total: Counter {
+        "The total number of UDP payload bytes forwarded, by upstream address and direction"
+    },
+    outbound_udp_dropped_datagrams_total: Counter {
+        "The total number of UDP datagrams dropped because a flow could not keep up, by upstream address"
+    },
+    outbound_udp_evicted_flows_total: Counter {
+        "The total number of UDP flows closed to make room for new flows, by upstream address"
+    }
+}
+
+/// The largest UDP payload that may be forwarded.
+const MAX_DATAGRAM_SIZE: usize = 65_507;
+
+/// Bounds the number of datagrams buffered for each flow.
+const FLOW_CAPACITY: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub forwards: Vec<Forward>,
+    pub idle_timeout: Duration,
+
+    /// The maximum number of flows each forward maintains.
+    pub max_flows: usize,
+}
+
+/// Forwards datagrams received on `listen` to `upstream`, e.g.
+/// `127.0.0.1:5353=10.96.0.10:53`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Forward {
+    pub listen: SocketAddr,
+    pub upstream: SocketAddr,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("UDP forwards must be formatted as `listen-addr=upstream-addr`: {0}")]
+pub struct InvalidForward(String);
+
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<RwLock<HashMap<SocketAddr, Arc<FlowMetrics>>>>);
+
+#[derive(Debug, Default)]
+struct FlowMetrics {
+    flows: Counter,
+    open: Gauge,
+    sent_datagrams: Counter,
+    sent_bytes: Counter,
+    received_datagrams: Counter,
+    received_bytes: Counter,
+    dropped_datagrams: Counter,
+    evicted_flows: Counter,
+}
+
+/// A flow's handle in the listener's flow table.
+struct Flow {
+    /// Identifies the flow, so that a closed flow's notification does not
+    /// remove a flow that has since replaced it.
+    id: u64,
+    tx: mpsc::Sender<Vec<u8>>,
+    last_active: time::Instant,
+}
+
+struct UpstreamLabel(SocketAddr);
+
+struct DirectionLabel(&'static str);
+
+// === impl Outbound ===
+
+impl Outbound<()> {
+    /// Binds a listener for each configured UDP forward, returning a task that
+    /// forwards datagrams.
+    ///
+    /// Listeners are bound eagerly so that an unavailable address fails
+    /// startup. If no forwards are configured, the task completes immediately.
+    pub fn serve_udp(&self) -> io::Result<impl Future<Output = ()> + Send + 'static> {
+        let Config {
+            forwards,
+            idle_timeout,
+            max_flows,
+        } = self.config.udp.clone();
+        let listeners = forwards
+            .into_iter()
+            .map(|fwd| {
+                let socket = std::net::UdpSocket::bind(fwd.listen).map_err(|e| {
+                    io::Error::new(
+                        e.kind(),
+                        format!("failed to bind UDP forward on {}: {}", fwd.listen, e),
+                    )
+                })?;
+                socket.set_nonblocking(true)?;
+                Ok((fwd, socket))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let policy = self.config.egress_policy.clone();
+        let denied = self.runtime.metrics.egress_denied.clone();
+        let metrics = self.runtime.metrics.udp.clone();
+        Ok(async move {
+            let forwards = listeners.into_iter().map(|(fwd, socket)| {
+                let permitted = policy.permits(fwd.upstream, None);
+                let metrics = metrics.flow(fwd.upstream);
+                let denied = denied.clone();
+                async move {
+                    match UdpSocket::from_std(socket) {
+                        Ok(listener) if permitted => {
+                            serve(listener, fwd, idle_timeout, max_flows, metrics).await
+                        }
+                        Ok(listener) => deny(listener, denied).await,
+                        Err(error) => warn!(%error, "Failed to register UDP listener"),
+                    }
+                }
+                .instrument(info_span!("udp", listen = %fwd.listen, upstream = %fwd.upstream))
+            });
+            futures::future::join_all(forwards).await;
+        })
+    }
+}
+
+async fn serve(
+    listener: UdpSocket,
+    fwd: Forward,
+    idle_timeout: Duration,
+    max_flows: usize,
+    metrics: Arc<FlowMetrics>,
+) {
+    let listener = Arc::new(listener);
+    debug!("Forwarding UDP datagrams");
+
+    let mut flows = HashMap::<SocketAddr, Flow>::new();
+    let mut next_id = 0u64;
+    let (closed_tx, mut closed_rx) = mpsc::unbounded_channel();
+    let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+    loop {
+        let (len, client) = tokio::select! {
+            res = listener.recv_from(&mut buf) => match res {
+                Ok(recvd) => recvd,
+                Err(error) => {
+                    warn!(%error, "Failed to receive datagram");
+                    continue;
+                }
+            },
+            Some((client, id)) = closed_rx.recv() => {
+                if flows.get(&client).map(|f| f.id == id).unwrap_or(false) {
+                    flows.remove(&client);
+                }
+                continue;
+            }
+        };
+
+        let mut datagram = buf[..len].to_vec();
+        if let Some(flow) = flows.get_mut(&client) {
+            flow.last_active = time::Instant::now();
+            match flow.tx.try_send(datagram) {
+                Ok(()) => continue,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    debug!(%client, "Dropping datagram");
+                    metrics.dropped_datagrams.incr();
+                    continue;
+                }
+                // The flow timed out, so a new one is opened.
+                Err(mpsc::error::TrySendError::Closed(d)) => datagram = d,
+            }
+        }
+
+        // Dropping the least recently active flow's sender closes it.
+        if flows.len() >= max_flows {
+            let evicted = flows
+                .iter()
+                .min_by_key(|(_, f)| f.last_active)
+                .map(|(c, _)| *c);
+            if let Some(evicted) = evicted {
+                debug!(client = %evicted, "Evicting flow");
+                flows.remove(&evicted);
+                metrics.evicted_flows.incr();
+            }
+        }
+
+        let id = next_id;
+        next_id += 1;
+        let closed = closed_tx.clone();
+        let on_close = move || {
+            let _ = closed.send((client, id));
+        };
+        let opened = open(
+            client,
+            fwd.upstream,
+            listener.clone(),
+            idle_timeout,
+            metrics.clone(),
+            on_close,
+        )
+        .await;
+        if let Some(tx) = opened {
+            // A new flow's channel has capacity.
+            let _ = tx.try_send(datagram);
+            flows.insert(
+                client,
+                Flow {
+                    id,
+                    tx,
+                    last_active: time::Instant::now(),
+                },
+            );
+        }
+    }
+}
+
+/// Drops all datagrams received by a forward whose upstream is not permitted
+/// by the egress policy.
+async fn deny(listener: UdpSocket, metrics: policy::Metrics) {
+    warn!("UDP forward denied by egress policy");
+    let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+    loop {
+        match listener.recv_from(&mut buf).await {
+            Ok((_, client)) => {
+                debug!(%client, "Egress denied");
+                metrics.deny(policy::Enforcement::Udp);
+            }
+            Err(error) => warn!(%error, "Failed to receive datagram"),
+        }
+    }
+}
+
+/// Opens an upstream socket for a new flow and spawns a task that relays
+/// datagrams until the flow is idle.
+async fn open(
+    client: SocketAddr,
+    upstream: SocketAddr,
+    listener: Arc<UdpSocket>,
+    idle_timeout: Duration,
+    metrics: Arc<FlowMetrics>,
+    on_close: impl FnOnce() + Send + 'static,
+) -> Option<mpsc::Sender<Vec<u8>>> {
+    let local: SocketAddr = match upstream {
+        SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+        SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+    };
+    let socket = match UdpSocket::bind(local).await {
+        Ok(socket) => socket,
+        Err(error) => {
+            warn!(%error, "Failed to bind upstream socket");
+            return None;
+        }
+    };
+    if let Err(error) = socket.connect(upstream).await {
+        warn!(%error, "Failed to connect upstream socket");
+        return None;
+    }
+
+    let (tx, rx) = mpsc::channel(FLOW_CAPACITY);
+    debug!(%client, "Opened flow");
+    metrics.flows.incr();
+    metrics.open.incr();
+    tokio::spawn(
+        async move {
+            relay(socket, rx, &listener, client, idle_timeout, &metrics).await;
+            debug!("Flow closed");
+            metrics.open.decr();
+            on_close();
+        }
+        .instrument(info_span!("flow", %client)),
+    );
+    Some(tx)
+}
+
+async fn relay(
+    socket: UdpSocket,
+    mut rx: mpsc::Receiver<Vec<u8>>,
+    listener: &UdpSocket,
+    client: SocketAddr,
+    idle_timeout: Duration,
+    metrics: &FlowMetrics,
+) {
+    let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+    loop {
+        tokio::select! {
+            datagram = rx.recv() => {
+                let datagram = match datagram {
+                    Some(datagram) => datagram,
+                    None => return,
+                };
+                if let Err(error) = socket.send(&datagram).await {
+                    debug!(%error, "Failed to send datagram upstream");
+                    continue;
+                }
+                metrics.sent_datagrams.incr();
+                metrics.sent_bytes.add(datagram.len() as u64);
+            }
+            res = socket.recv(&mut buf) => {
+                let len = match res {
+                    Ok(len) => len,
+                    Err(error) => {
+                        debug!(%error, "Failed to receive datagram from upstream");
+                        continue;
+                    }
+                };
+                if let Err(error) = listener.send_to(&buf[..len], client).await {
+                    debug!(%error, "Failed to send datagram to client");
+                    continue;
+                }
+                metrics.received_datagrams.incr();
+                metrics.received_bytes.add(len as u64);
+            }
+            _ = time::sleep(idle_timeout) => return,
+        }
+    }
+}
+
+// === impl Forward ===
+
+impl FromStr for Forward {
+    type Err = InvalidForward;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(listen), Some(upstream)) => Ok(Self {
+                listen: listen
+                    .trim()
+                    .parse()
+                    .map_err(|_| InvalidForward(s.to_string()))?,
+                upstream: upstream
+                    .trim()
+                    .parse()
+                    .map_err(|_| InvalidForward(s.to_string()))?,
+            }),
+            _ => Err(InvalidForward(s.to_string())),
+        }
+    }
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    fn flow(&self, upstream: SocketAddr) -> Arc<FlowMetrics> {
+        self.0.write().entry(upstream).or_default().clone()
+    }
+}
+
+impl FmtMetrics for Metrics {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics = self.0.read();
+        if metrics.is_empty() {
+            return Ok(());
+        }
+
+        outbound_udp_flows_total.fmt_help(f)?;
+        for (upstream, m) in metrics.iter() {
+            outbound_udp_flows_total.fmt_metric_labeled(f, &m.flows, UpstreamLabel(*upstream))?;
+        }
+
+        outbound_udp_open_flows.fmt_help(f)?;
+        for (upstream, m) in metrics.iter() {
+            outbound_udp_open_flows.fmt_metric_labeled(f, &m.open, UpstreamLabel(*upstream))?;
+        }
+
+        outbound_udp_datagrams_total.fmt_help(f)?;
+        for (upstream, m) in metrics.iter() {
+            outbound_udp_datagrams_total.fmt_metric_labeled(
+                f,
+                &m.sent_datagrams,
+                (UpstreamLabel(*upstream), DirectionLabel("write")),
+            )?;
+            outbound_udp_datagrams_total.fmt_metric_labeled(
+                f,
+                &m.received_datagrams,
+                (UpstreamLabel(*upstream), DirectionLabel("read")),
+            )?;
+        }
+
+        outbound_udp_bytes_total.fmt_help(f)?;
+        for (upstream, m) in metrics.iter() {
+            outbound_udp_bytes_total.fmt_metric_labeled(
+                f,
+                &m.sent_bytes,
+                (UpstreamLabel(*upstream), DirectionLabel("write")),
+            )?;
+            outbound_udp_bytes_total.fmt_metric_labeled(
+                f,
+                &m.received_bytes,
+                (UpstreamLabel(*upstream), DirectionLabel("read")),
+            )?;
+        }
+
+        outbound_udp_dropped_datagrams_total.fmt_help(f)?;
+        for (upstream, m) in metrics.iter() {
+            outbound_udp_dropped_datagrams_total.fmt_metric_labeled(
+                f,
+                &m.dropped_datagrams,
+                UpstreamLabel(*upstream),
+            )?;
+        }
+
+        outbound_udp_evicted_flows_total.fmt_help(f)?;
+        for (upstream, m) in metrics.iter() {
+            outbound_udp_evicted_flows_total.fmt_metric_labeled(
+                f,
+                &m.evicted_flows,
+                UpstreamLabel(*upstream),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FmtLabels for UpstreamLabel {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "upstream=\"{}\"", self.0)
+    }
+}
+
+impl FmtLabels for DirectionLabel {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "direction=\"{}\"", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_forwards() {
+        assert_eq!(
+            "127.0.0.1:5353=10.96.0.10:53".parse::<Forward>(),
+            Ok(Forward {
+                listen: ([127, 0, 0, 1], 5353).into(),
+                upstream: ([10, 96, 0, 10], 53).into(),
+            })
+        );
+        assert!("127.0.0.1:5353".parse::<Forward>().is_err());
+        assert!("127.0.0.1:5353=kube-dns:53".parse::<Forward>().is_err());
+    }
+
+    /// Spawns an upstream that echoes datagrams.
+    async fn echo() -> SocketAddr {
+        let upstream = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = upstream.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            loop {
+                let (len, peer) = upstream.recv_from(&mut buf).await.unwrap();
+                upstream.send_to(&buf[..len], peer).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    /// Spawns a forward to `upstream`, returning its listen address.
+    async fn forward(
+        upstream: SocketAddr,
+        idle_timeout: Duration,
+        max_flows: usize,
+        metrics: &Metrics,
+    ) -> SocketAddr {
+        let listener = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let listen = listener.local_addr().unwrap();
+        let fwd = Forward { listen, upstream };
+        tokio::spawn(serve(
+            listener,
+            fwd,
+            idle_timeout,
+            max_flows,
+            metrics.flow(upstream),
+        ));
+        listen
+    }
+
+    async fn roundtrip(listen: SocketAddr, msg: &[u8]) -> UdpSocket {
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.connect(listen).await.unwrap();
+        client.send(msg).await.unwrap();
+        let mut buf = [0u8; 512];
+        let len = client.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], msg);
+        client
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn forwards_datagrams() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let upstream = echo().await;
+        let metrics = Metrics::default();
+        let listen = forward(upstream, Duration::from_secs(10), 8, &metrics).await;
+        roundtrip(listen, b"query").await;
+
+        let flow = metrics.flow(upstream);
+        assert_eq!(flow.flows.value(), 1.0);
+        assert_eq!(flow.sent_bytes.value(), 5.0);
+        assert_eq!(flow.received_datagrams.value(), 1.0);
+        assert_eq!(flow.open.value(), 1);
+
+        // The flow is closed once idle.
+        time::sleep(Duration::from_secs(11)).await;
+        assert_eq!(flow.open.value(), 0);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn evicts_least_recently_active_flows() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let upstream = echo().await;
+        let metrics = Metrics::default();
+        let listen = forward(upstream, Duration::from_secs(10), 2, &metrics).await;
+        let _c0 = roundtrip(listen, b"zero").await;
+        time::sleep(Duration::from_secs(1)).await;
+        let _c1 = roundtrip(listen, b"one").await;
+        time::sleep(Duration::from_secs(1)).await;
+        let _c2 = roundtrip(listen, b"two").await;
+        // Let the evicted flow observe that it was closed.
+        time::sleep(Duration::from_millis(1)).await;
+
+        let flow = metrics.flow(upstream);
+        assert_eq!(flow.flows.value(), 3.0);
+        assert_eq!(flow.evicted_flows.value(), 1.0);
+        assert_eq!(flow.open.value(), 2);
+    }
+}
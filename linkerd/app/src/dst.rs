@@ -1,17 +1,63 @@
+use futures::{future, FutureExt, TryFutureExt};
+use linkerd_app_admin::SubsystemReady;
 use linkerd_app_core::{
     control, dns,
     exp_backoff::{ExponentialBackoff, ExponentialBackoffStream},
     metrics,
     profiles::{self, DiscoveryRejected},
-    proxy::{api_resolve as api, identity::LocalCrtKey, resolve::recover},
-    svc::NewService,
-    Error, Recover,
+    proxy::{
+        api_resolve as api, http,
+        identity::LocalCrtKey,
+        resolve::{recover, stale},
+        xds_resolve as xds,
+    },
+    svc::{self, NewService, Param},
+    Error, Infallible, Recover,
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
 };
 
 #[derive(Clone, Debug)]
 pub struct Config {
     pub control: control::Config,
     pub context: String,
+    pub static_endpoints: api::StaticEndpoints,
+    pub xds: Option<XdsConfig>,
+
+    /// How long a resolution's last-known endpoints may be served after the
+    /// control plane connection is lost.
+    pub resolution_cache_max_age: Duration,
+
+    /// Overrides discovery behavior for destinations in specific DNS suffixes.
+    pub suffix_tuning: Vec<SuffixTuning>,
+}
+
+/// Tunes discovery for destinations in a DNS suffix.
+#[derive(Clone, Debug)]
+pub struct SuffixTuning {
+    pub suffix: dns::Suffix,
+
+    /// Backs off retries of failed resolutions and profile lookups.
+    pub backoff: ExponentialBackoff,
+
+    /// Bounds the time spent waiting for a destination's profile, after which
+    /// it is treated as having no profile.
+    pub profile_timeout: Option<Duration>,
+}
+
+/// Configures an Envoy-compatible (xDS) control plane from which endpoints are
+/// resolved for names in its domains.
+#[derive(Clone, Debug)]
+pub struct XdsConfig {
+    pub control: control::Config,
+    pub suffixes: Vec<dns::Suffix>,
+    pub node_id: String,
+    pub node_cluster: String,
 }
 
 /// Handles to destination service clients.
@@ -20,10 +66,59 @@ pub struct Dst {
     pub addr: control::ControlAddr,
 
     /// Resolves profiles.
-    pub profiles: profiles::Client<BackoffUnlessInvalidArgument, control::Client>,
+    pub profiles: Profiles,
 
     /// Resolves endpoints.
-    pub resolve: recover::Resolve<BackoffUnlessInvalidArgument, api::Resolve<control::Client>>,
+    pub resolve: stale::Serve<
+        api::ConcreteAddr,
+        api::Metadata,
+        TunedResolve<
+            recover::Resolve<
+                BackoffUnlessInvalidArgument,
+                stale::Record<api::ConcreteAddr, api::Metadata, api::StaticResolve<Resolve>>,
+            >,
+        >,
+    >,
+
+    /// Caches resolved endpoints so that they may be served while the control
+    /// plane is unavailable.
+    pub resolution_cache: stale::Cache<api::ConcreteAddr, api::Metadata>,
+}
+
+/// Resolves endpoints from the xDS control plane for names in its domains and
+/// from the destination service otherwise.
+#[derive(Clone)]
+pub struct Resolve {
+    dst: api::Resolve<Client>,
+    xds: Option<(Arc<[dns::Suffix]>, xds::Resolve<control::Client>)>,
+}
+
+/// Resolves endpoints with the recovery policy of the destination's DNS
+/// suffix, if it is tuned.
+#[derive(Clone, Debug)]
+pub struct TunedResolve<R> {
+    default: R,
+    tuned: Vec<(dns::Suffix, R)>,
+}
+
+/// Looks up profiles with the recovery policy and timeout of the
+/// destination's DNS suffix, if it is tuned.
+#[derive(Clone)]
+pub struct Profiles {
+    default: profiles::Client<BackoffUnlessInvalidArgument, Client>,
+    tuned: Vec<(
+        dns::Suffix,
+        Option<Duration>,
+        profiles::Client<BackoffUnlessInvalidArgument, Client>,
+    )>,
+}
+
+/// A destination service client that marks the controller as connected once
+/// it has responded to a request.
+#[derive(Clone)]
+pub struct Client {
+    inner: control::Client,
+    connected: SubsystemReady,
 }
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -37,19 +132,185 @@ impl Config {
         dns: dns::Resolver,
         metrics: metrics::ControlHttp,
         identity: Option<LocalCrtKey>,
+        connected: SubsystemReady,
     ) -> Result<Dst, Error> {
         let addr = self.control.addr.clone();
         let backoff = BackoffUnlessInvalidArgument(self.control.connect.backoff);
-        let svc = self.control.build(dns, metrics, identity).new_service(());
+        let xds = self.xds.map(|xds| {
+            let svc = xds
+                .control
+                .build(dns.clone(), metrics.clone(), identity.clone())
+                .new_service(());
+            let resolve = xds::Resolve::new(svc, xds.node_id, xds.node_cluster);
+            (xds.suffixes.into(), resolve)
+        });
+        let svc = Client {
+            inner: self.control.build(dns, metrics, identity).new_service(()),
+            connected,
+        };
+
+        let resolution_cache = stale::Cache::new(self.resolution_cache_max_age);
+        let resolve = api::StaticResolve::new(
+            self.static_endpoints,
+            backoff.0,
+            Resolve {
+                dst: api::Resolve::new(svc.clone(), self.context.clone()),
+                xds,
+            },
+        );
+
+        let profiles = Profiles {
+            default: profiles::Client::new(backoff, svc.clone(), self.context.clone()),
+            tuned: self
+                .suffix_tuning
+                .iter()
+                .map(|t| {
+                    let backoff = BackoffUnlessInvalidArgument(t.backoff);
+                    let client = profiles::Client::new(backoff, svc.clone(), self.context.clone());
+                    (t.suffix.clone(), t.profile_timeout, client)
+                })
+                .collect(),
+        };
+
+        let resolve = TunedResolve {
+            default: recover::Resolve::new(backoff, resolution_cache.record(resolve.clone())),
+            tuned: self
+                .suffix_tuning
+                .into_iter()
+                .map(|t| {
+                    let backoff = BackoffUnlessInvalidArgument(t.backoff);
+                    let resolve = resolution_cache.record(resolve.clone());
+                    (t.suffix, recover::Resolve::new(backoff, resolve))
+                })
+                .collect(),
+        };
 
         Ok(Dst {
             addr,
-            profiles: profiles::Client::new(backoff, svc.clone(), self.context.clone()),
-            resolve: recover::Resolve::new(backoff, api::Resolve::new(svc, self.context)),
+            profiles,
+            resolve: resolution_cache.serve(resolve),
+            resolution_cache,
         })
     }
 }
 
+// === impl Resolve ===
+
+impl<T: Param<api::ConcreteAddr>> svc::Service<T> for Resolve {
+    type Response = <api::Resolve<Client> as svc::Service<T>>::Response;
+    type Error = tonic::Status;
+    type Future = <api::Resolve<Client> as svc::Service<T>>::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Neither resolver applies backpressure.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        if let Some((suffixes, xds)) = self.xds.as_mut() {
+            let api::ConcreteAddr(addr) = target.param();
+            if suffixes.iter().any(|sfx| sfx.contains(addr.name())) {
+                return xds.call(target);
+            }
+        }
+        self.dst.call(target)
+    }
+}
+
+// === impl TunedResolve ===
+
+impl<T, R> svc::Service<T> for TunedResolve<R>
+where
+    T: Param<api::ConcreteAddr>,
+    R: svc::Service<T>,
+{
+    type Response = R::Response;
+    type Error = R::Error;
+    type Future = R::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        for (_, resolve) in self.tuned.iter_mut() {
+            futures::ready!(resolve.poll_ready(cx))?;
+        }
+        self.default.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        let api::ConcreteAddr(addr) = target.param();
+        match self
+            .tuned
+            .iter_mut()
+            .find(|(sfx, _)| sfx.contains(addr.name()))
+        {
+            Some((_, resolve)) => resolve.call(target),
+            None => self.default.call(target),
+        }
+    }
+}
+
+// === impl Profiles ===
+
+impl svc::Service<profiles::LookupAddr> for Profiles {
+    type Response = Option<profiles::Receiver>;
+    type Error = Infallible;
+    type Future = future::BoxFuture<'static, Result<Option<profiles::Receiver>, Infallible>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Profile clients do not apply backpressure.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, target: profiles::LookupAddr) -> Self::Future {
+        let tuned = &mut self.tuned;
+        let tuned = target.0.name_addr().and_then(|addr| {
+            tuned
+                .iter_mut()
+                .find(|(sfx, _, _)| sfx.contains(addr.name()))
+        });
+        let (timeout, client) = match tuned {
+            Some((_, timeout, client)) => (*timeout, client),
+            None => (None, &mut self.default),
+        };
+
+        let lookup = client.call(target);
+        match timeout {
+            None => lookup,
+            Some(timeout) => Box::pin(tokio::time::timeout(timeout, lookup).map(|res| {
+                res.unwrap_or_else(|_| {
+                    tracing::debug!(?timeout, "Profile lookup timed out");
+                    Ok(None)
+                })
+            })),
+        }
+    }
+}
+
+// === impl Client ===
+
+impl svc::Service<http::Request<tonic::body::BoxBody>> for Client {
+    type Response =
+        <control::Client as svc::Service<http::Request<tonic::body::BoxBody>>>::Response;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<tonic::body::BoxBody>) -> Self::Future {
+        let connected = self.connected.clone();
+        Box::pin(self.inner.call(req).inspect_ok(move |_| {
+            if !connected.is_ready() {
+                tracing::debug!("Destination controller connected");
+                connected.set();
+            }
+        }))
+    }
+}
+
 // === impl BackoffUnlessInvalidArgument ===
 
 impl Recover<Error> for BackoffUnlessInvalidArgument {
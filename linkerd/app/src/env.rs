@@ -1,11 +1,16 @@
 use crate::core::{
-    addr,
+    access_log, addr,
     config::*,
     control::{Config as ControlConfig, ControlAddr},
-    proxy::http::{h1, h2},
-    tls,
-    transport::{Keepalive, ListenAddr},
-    Addr, AddrMatch, Conditional, IpNet,
+    ext_authz, fault, header_policy, hedge, http_tracing,
+    metrics::Direction,
+    proxy::{
+        api_resolve::StaticEndpoints,
+        http::{self, h1, h2},
+    },
+    retry, rls, route_filter, tls,
+    transport::{self, Keepalive, ListenAddr, OriginNetworks},
+    Addr, AddrMatch, Conditional, IpMatch, IpNet, NameAddr,
 };
 use crate::{dns, gateway, identity, inbound, oc_collector, outbound};
 use inbound::policy;
@@ -15,6 +20,7 @@ use std::{
     net::{IpAddr, SocketAddr},
     path::PathBuf,
     str::FromStr,
+    sync::Arc,
     time::Duration,
 };
 use thiserror::Error;
@@ -44,6 +50,8 @@ pub enum EnvError {
 pub enum ParseError {
     #[error("not a valid duration")]
     NotADuration,
+    #[error("duration must be greater than zero")]
+    ZeroDuration,
     #[error("not a valid DNS domain suffix")]
     NotADomainSuffix,
     #[error("not a boolean value: {0}")]
@@ -82,8 +90,112 @@ pub enum ParseError {
     InvalidTokenSource,
     #[error("invalid trust anchors")]
     InvalidTrustAnchors,
+    #[error("invalid TLS parameters")]
+    InvalidTlsParams,
     #[error("not a valid port policy: {0}")]
     InvalidPortPolicy(String),
+    #[error("not a valid inbound listener: {0}")]
+    InvalidListener(String),
+    #[error("not a valid header name")]
+    NotAHeaderName,
+    #[error(transparent)]
+    InvalidProbe(#[from] outbound::probe::InvalidProbe),
+    #[error("not a valid duplicate header mode: {0}")]
+    InvalidDuplicateHeaderMode(String),
+    #[error(transparent)]
+    InvalidAuthorityRewrite(#[from] outbound::http::InvalidRewrite),
+    #[error(transparent)]
+    InvalidSniRoute(#[from] inbound::sni::InvalidSniRoute),
+    #[error(transparent)]
+    InvalidUdpForward(#[from] outbound::udp::InvalidForward),
+    #[error(transparent)]
+    InvalidSkipDetect(#[from] outbound::http::InvalidSkipDetect),
+    #[error(transparent)]
+    InvalidHashPolicy(#[from] outbound::http::InvalidHashPolicy),
+    #[error(transparent)]
+    InvalidBalancerAlgorithm(#[from] outbound::balance::InvalidAlgorithm),
+    #[error("not a valid health check: {0}")]
+    InvalidHealthCheck(String),
+    #[error("not a valid sticky session: {0}")]
+    InvalidStickySession(String),
+    #[error("not a valid hedge: {0}")]
+    InvalidHedge(String),
+    #[error("not a valid fault: {0}")]
+    InvalidFault(String),
+    #[error("not a valid header policy: {0}")]
+    InvalidHeaderPolicy(String),
+    #[error("not a valid route filter: {0}")]
+    InvalidRouteFilter(String),
+    #[error("not a valid retry backoff: {0}")]
+    InvalidRetryBackoff(String),
+    #[error("not a valid per-try timeout: {0}")]
+    InvalidPerTryTimeout(String),
+    #[error("not a valid retry budget: {0}")]
+    InvalidRetryBudget(String),
+    #[error("not a valid egress rule: {0}")]
+    InvalidEgressRule(String),
+    #[error("not a valid circuit breaker: {0}")]
+    InvalidCircuitBreaker(String),
+    #[error("not a valid concurrency limit: {0}")]
+    InvalidConcurrencyLimit(String),
+    #[error("not a valid failover: {0}")]
+    InvalidFailover(String),
+    #[error("not a valid mirror: {0}")]
+    InvalidMirror(String),
+    #[error("not a valid request timeout: {0}")]
+    InvalidRequestTimeout(String),
+    #[error("not a valid rate limit: {0}")]
+    InvalidRateLimit(String),
+    #[error("not a valid bandwidth limit: {0}")]
+    InvalidBandwidthLimit(String),
+    #[error("not a valid accept rate limit: {0}")]
+    InvalidAcceptRate(String),
+    #[error("not a valid HTTP/2 window size: {0}")]
+    InvalidH2WindowSize(String),
+    #[error("not a valid HTTP/2 frame size: {0}")]
+    InvalidH2FrameSize(String),
+    #[error("not a valid CORS policy: {0}")]
+    InvalidCors(String),
+    #[error("not a valid TLS origination: {0}")]
+    InvalidTlsOrigination(String),
+    #[error("not a valid claim header: {0}")]
+    InvalidClaimHeader(String),
+    #[error("not a transport metrics family: {0}")]
+    NotATransportFamily(String),
+    #[error("not a trace protocol: {0}")]
+    NotATraceProtocol(String),
+    #[error("not a trace context precedence: {0}")]
+    NotATracePrecedence(String),
+    #[error("not a valid rate limit descriptor entry: {0}")]
+    InvalidRateLimitEntry(String),
+    #[error("not a rate limit failure mode: {0}")]
+    NotAFailureMode(String),
+    #[error("not an external authorization protocol: {0}")]
+    NotAnExtAuthzProtocol(String),
+    #[error("not a proxy direction: {0}")]
+    NotADirection(String),
+    #[error(transparent)]
+    InvalidAccessLogDestination(#[from] access_log::InvalidDestination),
+    #[error("not an access log format: {0}")]
+    NotAnAccessLogFormat(String),
+    #[error("not a status class: {0}")]
+    NotAStatusClass(String),
+    #[error("invalid static endpoints: {0}")]
+    InvalidStaticEndpoints(String),
+    #[error("not an original destination mode: {0}")]
+    NotAnOrigDstMode(String),
+    #[error("not a valid discovery tuning: {0}")]
+    InvalidSuffixTuning(String),
+    #[error(transparent)]
+    InvalidIpPreference(#[from] dns::InvalidIpPreference),
+    #[error("HTTP proxy authorization must not contain line breaks")]
+    InvalidHttpProxyAuthorization,
+    #[error("not a valid zone affinity setting: {0}")]
+    InvalidZoneAffinity(String),
+    #[error("not a percentage: {0}")]
+    NotAPercent(String),
+    #[error("not a valid authorization route: {0}")]
+    InvalidAuthorizationRoute(String),
 }
 
 // Environment variables to look at when loading the configuration
@@ -92,10 +204,347 @@ pub const ENV_INBOUND_LISTEN_ADDR: &str = "LINKERD2_PROXY_INBOUND_LISTEN_ADDR";
 pub const ENV_CONTROL_LISTEN_ADDR: &str = "LINKERD2_PROXY_CONTROL_LISTEN_ADDR";
 pub const ENV_ADMIN_LISTEN_ADDR: &str = "LINKERD2_PROXY_ADMIN_LISTEN_ADDR";
 
+/// A filesystem path at which the admin server listens on a Unix domain socket,
+/// in addition to its TCP listener.
+pub const ENV_ADMIN_UDS_PATH: &str = "LINKERD2_PROXY_ADMIN_UDS_PATH";
+
+/// Disables the admin server's TCP listener, so that it is only served on
+/// `LINKERD2_PROXY_ADMIN_UDS_PATH`.
+pub const ENV_ADMIN_TCP_DISABLED: &str = "LINKERD2_PROXY_ADMIN_TCP_DISABLED";
+
 pub const ENV_METRICS_RETAIN_IDLE: &str = "LINKERD2_PROXY_METRICS_RETAIN_IDLE";
 
+/// Causes `/ready` to wait until the proxy has connected to the destination
+/// controller and fetched its inbound policies.
+const ENV_READINESS_REQUIRE_CONTROL_PLANE: &str = "LINKERD2_PROXY_READINESS_REQUIRE_CONTROL_PLANE";
+
+/// Bounds the time that readiness waits on the control plane, after which the
+/// proxy becomes ready regardless.
+const ENV_READINESS_CONTROL_PLANE_TIMEOUT: &str = "LINKERD2_PROXY_READINESS_CONTROL_PLANE_TIMEOUT";
+
+/// A comma-separated list of transport metrics families whose series omit
+/// endpoint addresses. Valid families are `inbound_server`, `outbound_server`,
+/// and `outbound_client`.
+const ENV_TRANSPORT_METRICS_AGGREGATE: &str = "LINKERD2_PROXY_TRANSPORT_METRICS_AGGREGATE";
+
+/// A comma-separated list of identities of meshed clients that may access the
+/// admin server's privileged endpoints (e.g. `/shutdown` and `/proxy-log-level`).
+///
+/// Clients on localhost are always permitted.
+const ENV_ADMIN_PERMITTED_IDENTITIES: &str = "LINKERD2_PROXY_ADMIN_PERMITTED_IDENTITIES";
+
+/// Configures the policy that authorizes requests to the admin server, as one of
+/// `admin`, `all-authenticated`, `all-unauthenticated`, `cluster-authenticated`,
+/// or `cluster-unauthenticated`.
+///
+/// The default `admin` policy permits all requests from localhost and liveness
+/// and readiness probes from `LINKERD2_PROXY_INBOUND_NODE_NETWORKS` (or from all
+/// networks, if unset); all other requests must be from authenticated clients.
+const ENV_ADMIN_POLICY: &str = "LINKERD2_PROXY_ADMIN_POLICY";
+
+/// Like `LINKERD2_PROXY_INBOUND_PROXY_PROTOCOL_NETWORKS`, for connections to the
+/// admin server.
+const ENV_ADMIN_PROXY_PROTOCOL_NETWORKS: &str = "LINKERD2_PROXY_ADMIN_PROXY_PROTOCOL_NETWORKS";
+
+/// A token that clients must present in the `l5d-admin-token` header to shut
+/// down or drain the proxy via the admin server.
+const ENV_ADMIN_SHUTDOWN_TOKEN: &str = "LINKERD2_PROXY_ADMIN_SHUTDOWN_TOKEN";
+
 const ENV_INGRESS_MODE: &str = "LINKERD2_PROXY_INGRESS_MODE";
 
+/// A comma-separated list of `host:port/path` destinations to be probed with
+/// synthetic requests through the outbound proxy.
+const ENV_OUTBOUND_PROBES: &str = "LINKERD2_PROXY_OUTBOUND_PROBES";
+const ENV_OUTBOUND_PROBE_INTERVAL: &str = "LINKERD2_PROXY_OUTBOUND_PROBE_INTERVAL";
+const ENV_OUTBOUND_PROBE_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_PROBE_TIMEOUT";
+
+/// A comma-separated list of `from=to` name pairs. The authorities of outbound
+/// HTTP requests to `from` are rewritten to `to` (and, in ingress mode,
+/// requests are routed to `to`).
+const ENV_OUTBOUND_AUTHORITY_REWRITES: &str = "LINKERD2_PROXY_OUTBOUND_AUTHORITY_REWRITES";
+
+/// If set, retries are disabled on routes whose retry budgets have been
+/// exhausted continuously for this duration, and are re-enabled once budgets
+/// have not been exhausted for the same duration.
+const ENV_OUTBOUND_RETRY_SUPPRESSION_AFTER: &str =
+    "LINKERD2_PROXY_OUTBOUND_RETRY_SUPPRESSION_AFTER";
+
+/// The maximum size, in bytes, of request bodies that are buffered so that
+/// requests may be retried. Requests with larger bodies, or with bodies of
+/// unknown length, are not retried. If zero, requests with bodies are never
+/// retried.
+const ENV_OUTBOUND_RETRY_MAX_BUFFERED_BYTES: &str =
+    "LINKERD2_PROXY_OUTBOUND_RETRY_MAX_BUFFERED_BYTES";
+
+/// The longest time (e.g. `10s`) that a retry is delayed by a response's
+/// `Retry-After` header. Longer hints are shortened to this duration.
+const ENV_OUTBOUND_RETRY_AFTER_MAX: &str = "LINKERD2_PROXY_OUTBOUND_RETRY_AFTER_MAX";
+
+/// If true, requests that failed before the server could have processed them
+/// (e.g. due to connection failures, refused HTTP/2 streams, or requests that
+/// were not dispatched before a GOAWAY) are retried on all routes, even if the
+/// route is not retryable or the request is not idempotent. Defaults to false.
+const ENV_OUTBOUND_RETRY_UNPROCESSED: &str = "LINKERD2_PROXY_OUTBOUND_RETRY_UNPROCESSED";
+
+/// A comma-separated list of `authority=base` pairs, each optionally followed by
+/// `;max=<duration>`, `;multiplier=<number>`, and `;jitter=<ratio>` settings
+/// (e.g. `web.ns.svc.cluster.local:80=25ms;max=500ms;multiplier=3`). Retries on
+/// the logical service's routes are delayed by a jittered exponential backoff
+/// that starts at the base delay, rather than being retried immediately.
+const ENV_OUTBOUND_RETRY_BACKOFFS: &str = "LINKERD2_PROXY_OUTBOUND_RETRY_BACKOFFS";
+
+/// A comma-separated list of `authority=duration` pairs (e.g.
+/// `web.ns.svc.cluster.local:80=500ms`). Each attempt of a request on the
+/// logical service's retryable routes is timed out after the duration, and
+/// retried, while the route's request timeout continues to bound the request
+/// as a whole.
+const ENV_OUTBOUND_RETRY_PER_TRY_TIMEOUTS: &str = "LINKERD2_PROXY_OUTBOUND_RETRY_PER_TRY_TIMEOUTS";
+
+/// A comma-separated list of `authority=ratio` retry budgets, optionally
+/// followed by `;ttl=duration` and `;min-per-second=n` settings (e.g.
+/// `web.ns.svc.cluster.local:80=0.1;ttl=30s`). Each of the logical service's
+/// retryable routes is given its own budget, so that one route exhausting its
+/// retries does not prevent the service's other routes from retrying.
+const ENV_OUTBOUND_RETRY_BUDGETS: &str = "LINKERD2_PROXY_OUTBOUND_RETRY_BUDGETS";
+
+/// Configures TLS origination to unmeshed destinations, as a comma-separated
+/// list of `<target>=<ca path>[;<setting>...]` entries, where a target is a
+/// `host:port` authority or an `ip:port` address and the CA path names a PEM
+/// bundle of the trust anchors that issue the server's certificate. Settings
+/// are `crt=<path>` and `key=<path>` (a PEM certificate chain and private key
+/// with which the proxy authenticates itself), `server-name=<name>` (which
+/// defaults to the target's host and is required for addresses), and
+/// `sans=<name>|<name>` (the names for which the server's certificate must be
+/// valid, if not the server name).
+const ENV_OUTBOUND_TLS_ORIGINATION: &str = "LINKERD2_PROXY_OUTBOUND_TLS_ORIGINATION";
+
+/// Comma-separated lists of ports and `host:port` authorities. Opaque TCP
+/// connections to matching targets are prefixed with a PROXY protocol v2 header
+/// describing the application's address.
+const ENV_OUTBOUND_PROXY_PROTOCOL_PORTS: &str = "LINKERD2_PROXY_OUTBOUND_PROXY_PROTOCOL_PORTS";
+const ENV_OUTBOUND_PROXY_PROTOCOL_AUTHORITIES: &str =
+    "LINKERD2_PROXY_OUTBOUND_PROXY_PROTOCOL_AUTHORITIES";
+
+/// A comma-separated list of rules permitting outbound connections. Each rule
+/// is a network (e.g. `10.0.0.0/8`) or DNS suffix (e.g. `svc.cluster.local.`),
+/// optionally followed by `=` and `;`-separated ports (e.g.
+/// `svc.cluster.local.=80;443`). When set, outbound connections whose original
+/// destination address or discovered logical name match no rule are refused.
+const ENV_OUTBOUND_EGRESS_ALLOW: &str = "LINKERD2_PROXY_OUTBOUND_EGRESS_ALLOW";
+
+/// A comma-separated list of ports (e.g. `3306`), networks (e.g. `10.1.0.0/16`),
+/// or ports within networks (e.g. `10.1.0.0/16:25`) for which outbound protocol
+/// detection is skipped. This avoids the detection timeout for
+/// server-speaks-first protocols on destinations that are not known to be
+/// opaque through discovery.
+const ENV_OUTBOUND_SKIP_DETECT: &str = "LINKERD2_PROXY_OUTBOUND_SKIP_DETECT";
+
+/// A comma-separated list of `listen-addr=upstream-addr` pairs (e.g.
+/// `127.0.0.1:5353=10.96.0.10:53`). UDP datagrams received on each listen
+/// address are forwarded to the upstream address, so that UDP traffic (e.g.
+/// DNS queries) redirected to the proxy is observed.
+const ENV_OUTBOUND_UDP_FORWARDS: &str = "LINKERD2_PROXY_OUTBOUND_UDP_FORWARDS";
+
+/// The time after which an idle UDP flow is closed.
+const ENV_OUTBOUND_UDP_IDLE_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_UDP_IDLE_TIMEOUT";
+
+/// The maximum number of flows maintained by each UDP forward. When a forward
+/// has this many flows, the least recently active flow is closed to make room
+/// for a new one.
+const ENV_OUTBOUND_UDP_MAX_FLOWS: &str = "LINKERD2_PROXY_OUTBOUND_UDP_MAX_FLOWS";
+
+/// A comma-separated list of `authority=key` pairs (e.g.
+/// `cache.ns.svc.cluster.local:11211=header:x-session-id`). Requests to each
+/// logical service are balanced over its endpoints by consistent hashing of the
+/// key, which is either `header:<name>`, `authority`, or `path`.
+const ENV_OUTBOUND_HTTP_HASH_POLICIES: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_HASH_POLICIES";
+
+/// A comma-separated list of `authority=cookie` pairs, optionally followed by
+/// `;`-separated `ttl=<duration>` and `secure=<bool>` settings (e.g.
+/// `web.ns.svc.cluster.local:80=l5d-session;ttl=30m`). Requests to each logical
+/// service are pinned to an endpoint by the named cookie, which is issued by
+/// the proxy. Cookies are marked `Secure` unless `secure=false` is set for
+/// services whose clients connect over plaintext.
+const ENV_OUTBOUND_HTTP_STICKY_SESSIONS: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_STICKY_SESSIONS";
+
+/// A comma-separated list of `authority=delay` pairs, where the delay is either
+/// a duration or a latency percentile (e.g.
+/// `web.ns.svc.cluster.local:80=p95,api.ns.svc.cluster.local:80=50ms`). Requests
+/// on the logical service's retryable routes that are not answered within the
+/// delay are hedged: a duplicate request is sent, and the first successful
+/// response is used. Hedged requests withdraw from the route's retry budget.
+const ENV_OUTBOUND_HTTP_HEDGES: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_HEDGES";
+
+/// An experimental, comma-separated list of `authority=fault` pairs, where the
+/// fault is either `delay:<duration>` or `abort:<status>`, optionally followed
+/// by `;`-separated `percent` and `route` settings (e.g.
+/// `web.ns.svc.cluster.local:80=abort:503;percent=10;route=GET /books`). Faults
+/// are injected into the given percentage (by default, all) of the requests on
+/// the logical service's routes, or only on the named route.
+const ENV_OUTBOUND_HTTP_FAULTS: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_FAULTS";
+
+/// A comma-separated list of `authority=policy` pairs, where the policy
+/// modifies the headers of the requests (`request.`) or responses
+/// (`response.`) on the logical service's routes: `add:<name>=<value>` adds a
+/// value, `set:<name>=<value>` replaces any values, and `remove:<name>` removes
+/// the header. Requests may also be given a generated ID when they lack one
+/// with `request.id:<name>`. Policies may be followed by a `;route=<name>`
+/// setting, limiting them to the named route (e.g.
+/// `web.ns.svc.cluster.local:80=request.set:x-env=prod;route=GET /books`).
+const ENV_OUTBOUND_HTTP_HEADER_POLICIES: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_HEADER_POLICIES";
+
+/// A comma-separated list of `authority=filter` pairs, where the filter
+/// rewrites or redirects the requests on the logical service's routes:
+/// `path:<path>` replaces the request's path, `prefix:<prefix>=<replacement>`
+/// replaces a prefix of the path, `host:<host>` replaces the request's host,
+/// and `redirect:<status>` answers the request with a redirect (with a 301,
+/// 302, 303, 307, or 308 status), whose location
+/// may be modified by `;`-separated `scheme`, `host`, `port`, and `path`
+/// settings. Filters may be limited to a named route with a `;route=<name>`
+/// setting (e.g.
+/// `web.ns.svc.cluster.local:80=redirect:301;scheme=https;route=GET /login`).
+const ENV_OUTBOUND_HTTP_ROUTE_FILTERS: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_FILTERS";
+
+/// A comma-separated list of logical service authorities (e.g.
+/// `db.ns.svc.cluster.local:5432`) whose opaque TCP connections are balanced by
+/// a consistent hash of the client's IP address, so that each client's
+/// connections are sent to the same endpoint.
+const ENV_OUTBOUND_TCP_SOURCE_AFFINITY: &str = "LINKERD2_PROXY_OUTBOUND_TCP_SOURCE_AFFINITY";
+
+/// The zone (e.g. `us-west-1a`) in which the proxy runs. When set, outbound
+/// balancers prefer endpoints whose `zone` label matches.
+const ENV_OUTBOUND_ZONE: &str = "LINKERD2_PROXY_OUTBOUND_ZONE";
+
+/// The factor by which endpoints in the proxy's zone are weighted.
+const ENV_OUTBOUND_ZONE_LOCAL_WEIGHT: &str = "LINKERD2_PROXY_OUTBOUND_ZONE_LOCAL_WEIGHT";
+
+/// The minimum fraction (between 0 and 1) of a service's endpoints that must be
+/// in the proxy's zone for them to be preferred. Below this threshold, requests
+/// spill over evenly to all zones.
+const ENV_OUTBOUND_ZONE_SPILLOVER_THRESHOLD: &str =
+    "LINKERD2_PROXY_OUTBOUND_ZONE_SPILLOVER_THRESHOLD";
+
+/// The latency (e.g. `30ms`) that outbound balancers assume for endpoints that
+/// have not yet responded.
+const ENV_OUTBOUND_EWMA_DEFAULT_RTT: &str = "LINKERD2_PROXY_OUTBOUND_EWMA_DEFAULT_RTT";
+
+/// The time (e.g. `10s`) over which outbound balancers' latency estimates
+/// decay. Must be non-zero.
+const ENV_OUTBOUND_EWMA_DECAY: &str = "LINKERD2_PROXY_OUTBOUND_EWMA_DECAY";
+
+/// A comma-separated list of `authority=algorithm` pairs (e.g.
+/// `web.ns.svc.cluster.local:80=least-request`) that select how requests to
+/// each logical service are balanced over its endpoints. The algorithm is
+/// either `peak-ewma` (the default), `least-request`, or `round-robin`.
+const ENV_OUTBOUND_BALANCER_ALGORITHMS: &str = "LINKERD2_PROXY_OUTBOUND_BALANCER_ALGORITHMS";
+
+/// The window (e.g. `30s`) over which newly discovered endpoints are ramped up
+/// to their full share of outbound traffic. If unset, endpoints receive their
+/// full share as soon as they are discovered. The window must be non-zero.
+const ENV_OUTBOUND_SLOW_START_WINDOW: &str = "LINKERD2_PROXY_OUTBOUND_SLOW_START_WINDOW";
+
+/// The number of consecutive failures after which an outbound endpoint is
+/// ejected from its balancer. Outlier detection is disabled unless this is set.
+const ENV_OUTBOUND_OUTLIER_CONSECUTIVE_FAILURES: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_CONSECUTIVE_FAILURES";
+
+/// If set, responses that take longer than this duration are counted as
+/// failures by outlier detection.
+const ENV_OUTBOUND_OUTLIER_FAILURE_LATENCY: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_FAILURE_LATENCY";
+
+/// The duration of an endpoint's first ejection. Each subsequent ejection of
+/// an endpoint that has not succeeded in between is doubled, up to
+/// `LINKERD2_PROXY_OUTBOUND_OUTLIER_MAX_EJECTION_TIME`.
+const ENV_OUTBOUND_OUTLIER_BASE_EJECTION_TIME: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_BASE_EJECTION_TIME";
+const ENV_OUTBOUND_OUTLIER_MAX_EJECTION_TIME: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_MAX_EJECTION_TIME";
+
+/// The maximum percentage of a balancer's endpoints that may be ejected at once.
+const ENV_OUTBOUND_OUTLIER_MAX_EJECTION_PERCENT: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_MAX_EJECTION_PERCENT";
+
+/// A comma-separated list of `authority=check` pairs that configure active
+/// health checks for the endpoints of logical services. Checks are either
+/// `http:<path>` or `tcp`, optionally followed by `;`-separated `interval`,
+/// `timeout`, `healthy`, and `unhealthy` settings (e.g.
+/// `web.ns.svc.cluster.local:80=http:/ready;interval=5s;unhealthy=2`).
+const ENV_OUTBOUND_HEALTH_CHECKS: &str = "LINKERD2_PROXY_OUTBOUND_HEALTH_CHECKS";
+
+/// A comma-separated list of `authority=trip` pairs that configure circuit
+/// breakers for the HTTP endpoints of logical services. Breakers trip either
+/// after `failures:<n>` consecutive failures, or when the `ratio:<ratio>` of
+/// failed requests is reached over a `window`, once `min-requests` requests
+/// have completed in it. The trip is optionally followed by `;`-separated
+/// `open`, `probes`, `window`, and `min-requests` settings (e.g.
+/// `web.ns.svc.cluster.local:80=ratio:0.5;window=30s;open=1m`). Endpoints
+/// receive no requests while their breakers are open.
+const ENV_OUTBOUND_HTTP_CIRCUIT_BREAKERS: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_CIRCUIT_BREAKERS";
+
+/// A comma-separated list of `authority=algorithm` pairs that configure
+/// adaptive concurrency limits for the HTTP endpoints of logical services. The
+/// algorithm is either `aimd:<latency>`, which backs off when responses take
+/// longer than the latency, or `gradient`, which backs off as latency grows.
+/// The algorithm is optionally followed by `;`-separated `initial`, `min`, and
+/// `max` limits, and by `backoff` (for `aimd`) or `tolerance` (for `gradient`)
+/// settings (e.g. `web.ns.svc.cluster.local:80=aimd:250ms;max=200`). An
+/// endpoint does not receive requests while its limit is reached.
+const ENV_OUTBOUND_HTTP_CONCURRENCY_LIMITS: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_CONCURRENCY_LIMITS";
+
+/// A comma-separated list of `primary=secondary` pairs that configure logical
+/// services to fail over to other services when too few of their endpoints
+/// are ready, optionally followed by a `;threshold=<ratio>` setting (e.g.
+/// `web.ns.svc.cluster.local:80=web.ns.svc.west.example.com:80;threshold=0.3`).
+const ENV_OUTBOUND_FAILOVER: &str = "LINKERD2_PROXY_OUTBOUND_FAILOVER";
+
+/// A comma-separated list of services (e.g. `web.ns.svc.cluster.local:80`)
+/// whose endpoints mirrored from remote clusters are only used while the
+/// service has no local endpoints.
+const ENV_OUTBOUND_MULTICLUSTER_FAILOVER: &str = "LINKERD2_PROXY_OUTBOUND_MULTICLUSTER_FAILOVER";
+
+/// The endpoint label that identifies endpoints mirrored from remote clusters.
+/// Endpoints with a non-empty value for this label are remote.
+const ENV_OUTBOUND_MULTICLUSTER_REMOTE_LABEL: &str =
+    "LINKERD2_PROXY_OUTBOUND_MULTICLUSTER_REMOTE_LABEL";
+
+/// A comma-separated list of `authority=shadow` pairs that configure logical
+/// services to mirror their HTTP requests to shadow services, optionally
+/// followed by `;percent=<n>` and `;route=<name>` settings (e.g.
+/// `web.ns.svc.cluster.local:80=web-canary.ns.svc.cluster.local:80;percent=10;route=GET /books`).
+/// Mirrored requests' responses are discarded.
+const ENV_OUTBOUND_HTTP_MIRRORS: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_MIRRORS";
+
+/// The address (e.g. `http://proxy.example.com:3128`) of an HTTP forward proxy
+/// through which outbound connections are tunneled with `CONNECT` requests.
+const ENV_OUTBOUND_HTTP_PROXY: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_PROXY";
+
+/// The value of the `proxy-authorization` header sent to the HTTP forward
+/// proxy, e.g. `Basic dXNlcjpwYXNz`.
+const ENV_OUTBOUND_HTTP_PROXY_AUTHORIZATION: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_PROXY_AUTHORIZATION";
+
+/// A comma-separated list of networks (e.g. `10.0.0.0/8,fd00::/8`) to which
+/// outbound connections are made directly, bypassing the HTTP forward proxy.
+const ENV_OUTBOUND_HTTP_PROXY_EXEMPT_NETWORKS: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_PROXY_EXEMPT_NETWORKS";
+
+/// An `SO_MARK` value (e.g. `0x1e7` or `487`) set on outbound and
+/// control-plane connect sockets so that the node's routing policy and packet
+/// filters can distinguish proxy-originated traffic. Requires `CAP_NET_ADMIN`
+/// and is ignored, with a warning, on platforms other than Linux.
+const ENV_OUTBOUND_CONNECT_MARK: &str = "LINKERD2_PROXY_OUTBOUND_CONNECT_MARK";
+
+/// Limits the bandwidth of forwarded outbound TCP connections, by target port,
+/// in the format of `LINKERD2_PROXY_INBOUND_TCP_BANDWIDTH_LIMITS`.
+const ENV_OUTBOUND_TCP_BANDWIDTH_LIMITS: &str = "LINKERD2_PROXY_OUTBOUND_TCP_BANDWIDTH_LIMITS";
+
+/// How long to wait for a connection attempt to succeed before racing it
+/// against the next resolved address (e.g. of the HTTP forward proxy), as
+/// described by RFC 8305.
+const ENV_OUTBOUND_CONNECT_FALLBACK_DELAY: &str = "LINKERD2_PROXY_OUTBOUND_CONNECT_FALLBACK_DELAY";
+
 const ENV_INBOUND_DISPATCH_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_DISPATCH_TIMEOUT";
 const ENV_OUTBOUND_DISPATCH_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_DISPATCH_TIMEOUT";
 
@@ -105,11 +554,11 @@ const ENV_OUTBOUND_DETECT_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_DETECT_TIMEOU
 const ENV_INBOUND_CONNECT_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_CONNECT_TIMEOUT";
 const ENV_OUTBOUND_CONNECT_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_CONNECT_TIMEOUT";
 
-const ENV_INBOUND_ACCEPT_KEEPALIVE: &str = "LINKERD2_PROXY_INBOUND_ACCEPT_KEEPALIVE";
-const ENV_OUTBOUND_ACCEPT_KEEPALIVE: &str = "LINKERD2_PROXY_OUTBOUND_ACCEPT_KEEPALIVE";
-
-const ENV_INBOUND_CONNECT_KEEPALIVE: &str = "LINKERD2_PROXY_INBOUND_CONNECT_KEEPALIVE";
-const ENV_OUTBOUND_CONNECT_KEEPALIVE: &str = "LINKERD2_PROXY_OUTBOUND_CONNECT_KEEPALIVE";
+/// How connections are intercepted: `redirect` (the default), where their
+/// original destinations are read with `SO_ORIGINAL_DST`, or `tproxy`, where
+/// the inbound and outbound listeners are bound with `IP_TRANSPARENT` and
+/// accept connections intercepted by `TPROXY` rules.
+const ENV_ORIG_DST_MODE: &str = "LINKERD2_PROXY_ORIG_DST_MODE";
 
 pub const ENV_BUFFER_CAPACITY: &str = "LINKERD2_PROXY_BUFFER_CAPACITY";
 
@@ -170,15 +619,153 @@ pub const ENV_INBOUND_PORTS_REQUIRE_TLS: &str = "LINKERD2_PROXY_INBOUND_PORTS_RE
 pub const ENV_INBOUND_DEFAULT_POLICY: &str = "LINKERD2_PROXY_INBOUND_DEFAULT_POLICY";
 
 pub const ENV_INBOUND_PORTS: &str = "LINKERD2_PROXY_INBOUND_PORTS";
+
+/// Configures additional inbound listeners, as a comma-separated list of
+/// `<port>=<target port>[;protocol=<protocol>][;policy=<policy>]` entries (e.g.
+/// `4192=8080;protocol=http1;policy=all-unauthenticated`). Each listener is
+/// bound on the inbound listener's IP address and forwards its connections to
+/// the target port of the application. The protocol (one of `detect`,
+/// `http1`, `http2`, `grpc`, `opaque`, or `tls`) and the default policy
+/// (as in `LINKERD2_PROXY_INBOUND_DEFAULT_POLICY`) override those of the
+/// inbound default policy.
+const ENV_INBOUND_LISTENERS: &str = "LINKERD2_PROXY_INBOUND_LISTENERS";
 pub const ENV_POLICY_SVC_BASE: &str = "LINKERD2_PROXY_POLICY_SVC";
 pub const ENV_POLICY_WORKLOAD: &str = "LINKERD2_PROXY_POLICY_WORKLOAD";
 pub const ENV_POLICY_CLUSTER_NETWORKS: &str = "LINKERD2_PROXY_POLICY_CLUSTER_NETWORKS";
 
+/// Scopes discovered authorizations to HTTP routes, as a comma-separated list
+/// of `<authorization>=<route>` entries, optionally followed by `;method=<m>`
+/// and either `;path=<path>` or `;prefix=<path>` settings (e.g.
+/// `probes=health;method=GET;prefix=/health`). Entries with the same
+/// authorization and route name add matches to the route. Authorizations are
+/// identified by their `name` labels.
+const ENV_POLICY_AUTHORIZATION_ROUTES: &str = "LINKERD2_PROXY_POLICY_AUTHORIZATION_ROUTES";
+
+/// Constrains which client addresses are classified as node-local.
+///
+/// The value is a comma-separated list of networks (typically, the node's pod
+/// CIDR). Inbound connections from these networks are labeled with
+/// `origin="node_local"`; connections from `LINKERD2_PROXY_POLICY_CLUSTER_NETWORKS`
+/// are labeled with `origin="cluster"`; and all other connections are labeled
+/// with `origin="external"`.
+pub const ENV_INBOUND_NODE_NETWORKS: &str = "LINKERD2_PROXY_INBOUND_NODE_NETWORKS";
+
 pub const ENV_INBOUND_IPS: &str = "LINKERD2_PROXY_INBOUND_IPS";
 
+/// A comma-separated list of networks (e.g. those of an external load balancer)
+/// from which inbound connections must begin with a PROXY protocol v2 header.
+/// The client address described by the header is used in place of the
+/// connection's peer address.
+pub const ENV_INBOUND_PROXY_PROTOCOL_NETWORKS: &str =
+    "LINKERD2_PROXY_INBOUND_PROXY_PROTOCOL_NETWORKS";
+
+/// Names a header (e.g. `x-forwarded-client-cert`) in which the verified
+/// identity of meshed clients, and the SHA-256 hash of their certificates, are
+/// passed to the application.
+///
+/// Client-supplied values of this header are always stripped.
+const ENV_INBOUND_CLIENT_CERT_HEADER: &str = "LINKERD2_PROXY_INBOUND_CLIENT_CERT_HEADER";
+
+/// A comma-separated list of `port=mode` pairs configuring how inbound HTTP
+/// servers handle requests with duplicate `Host`, `Content-Length`, or `TE`
+/// headers, where the mode is one of `merge`, `reject`, or `first-wins`. A `*`
+/// port configures all other servers (e.g. `*=reject,8080=merge`). Requests to
+/// servers without a mode are not modified.
+const ENV_INBOUND_HTTP_DUPLICATE_HEADERS: &str = "LINKERD2_PROXY_INBOUND_HTTP_DUPLICATE_HEADERS";
+
+/// A comma-separated list of `pattern=ip:port` routes (e.g.
+/// `*.example.com=10.0.0.2:443`). Inbound TLS connections that are not
+/// terminated by the proxy are forwarded to the upstream of the first route
+/// whose pattern matches their SNI, rather than to the local application.
+const ENV_INBOUND_SNI_ROUTES: &str = "LINKERD2_PROXY_INBOUND_SNI_ROUTES";
+
+/// A comma-separated list of `port=duration` pairs (e.g. `8080=10s`). Requests
+/// to inbound servers on these ports fail with a `504 Gateway Timeout` if the
+/// application does not respond within the server's timeout.
+const ENV_INBOUND_REQUEST_TIMEOUTS: &str = "LINKERD2_PROXY_INBOUND_REQUEST_TIMEOUTS";
+
+/// A comma-separated list of `target=rate` pairs, where the rate is a number of
+/// requests per second, optionally followed by `;`-separated `burst` and
+/// `route` settings. The target is either a port, limiting the requests to the
+/// inbound server on that port (e.g. `8080=100;burst=200`), or a service's
+/// authority, limiting the requests on one of its routes (e.g.
+/// `web.ns.svc.cluster.local:80=10;route=GET /books`). Requests that exceed a
+/// limit fail with a `429 Too Many Requests` response. When no burst is set, up
+/// to one second's worth of requests may be permitted at once.
+const ENV_INBOUND_HTTP_RATE_LIMITS: &str = "LINKERD2_PROXY_INBOUND_HTTP_RATE_LIMITS";
+
+/// A comma-separated list of `port=rate` pairs, where the rate is a number of
+/// bytes per second, optionally followed by `;`-separated `burst` and `scope`
+/// settings (e.g. `5432=1048576;burst=4194304;scope=server`). The port may be
+/// `*` to limit connections to all other ports. Each direction of a forwarded
+/// TCP connection is limited independently. A `connection` scope (the
+/// default) limits each connection, while a `server` scope limits all of the
+/// connections to the port together. When no burst is set, up to one second's
+/// worth of bytes may be permitted at once.
+const ENV_INBOUND_TCP_BANDWIDTH_LIMITS: &str = "LINKERD2_PROXY_INBOUND_TCP_BANDWIDTH_LIMITS";
+
+/// Configures CORS policies for inbound HTTP servers and routes, as a
+/// comma-separated list of `<port>=<origins>[;<setting>...]` or
+/// `<authority>=<origins>;route=<name>[;<setting>...]` entries, where origins
+/// are separated by `|` (or `*` to allow all origins). Settings are
+/// `methods=GET|POST`, `headers=content-type|x-token`, `expose=x-request-id`,
+/// `credentials=true`, and `max-age=10m`. Credentials may not be allowed from
+/// all origins.
+const ENV_INBOUND_HTTP_CORS: &str = "LINKERD2_PROXY_INBOUND_HTTP_CORS";
+
+/// Header policies for the routes of inbound services' profiles, in the format
+/// of `LINKERD2_PROXY_OUTBOUND_HTTP_HEADER_POLICIES`.
+const ENV_INBOUND_HTTP_HEADER_POLICIES: &str = "LINKERD2_PROXY_INBOUND_HTTP_HEADER_POLICIES";
+
+/// Filters for the routes of inbound services' profiles, in the format of
+/// `LINKERD2_PROXY_OUTBOUND_HTTP_ROUTE_FILTERS`.
+const ENV_INBOUND_HTTP_ROUTE_FILTERS: &str = "LINKERD2_PROXY_INBOUND_HTTP_ROUTE_FILTERS";
+
+/// The path of a JSON Web Key Set file. When set, inbound HTTP requests must
+/// carry a bearer token that is signed by one of its keys, or they fail with a
+/// `401 Unauthorized` response. The file is re-read at the
+/// `LINKERD2_PROXY_INBOUND_JWT_JWKS_REFRESH` interval.
+const ENV_INBOUND_JWT_JWKS: &str = "LINKERD2_PROXY_INBOUND_JWT_JWKS";
+const ENV_INBOUND_JWT_JWKS_REFRESH: &str = "LINKERD2_PROXY_INBOUND_JWT_JWKS_REFRESH";
+
+/// When set, tokens must have been issued by this issuer.
+const ENV_INBOUND_JWT_ISSUER: &str = "LINKERD2_PROXY_INBOUND_JWT_ISSUER";
+
+/// A comma-separated list of audiences. When set, tokens must have been issued
+/// for one of them.
+const ENV_INBOUND_JWT_AUDIENCES: &str = "LINKERD2_PROXY_INBOUND_JWT_AUDIENCES";
+
+/// A comma-separated list of `claim=header` pairs (e.g. `sub=x-user`). The
+/// values of validated tokens' claims are passed to the application in these
+/// headers.
+const ENV_INBOUND_JWT_CLAIM_HEADERS: &str = "LINKERD2_PROXY_INBOUND_JWT_CLAIM_HEADERS";
+
+/// A comma-separated list of inbound server names. When set, tokens are only
+/// required on these servers.
+const ENV_INBOUND_JWT_SERVERS: &str = "LINKERD2_PROXY_INBOUND_JWT_SERVERS";
+
+/// A comma-separated list of the names of inbound servers whose policies are
+/// audited rather than enforced: HTTP requests that would be denied are logged
+/// and counted, but are permitted.
+const ENV_INBOUND_AUTHZ_AUDIT_SERVERS: &str = "LINKERD2_PROXY_INBOUND_AUTHZ_AUDIT_SERVERS";
+
+/// A comma-separated list of networks (e.g. `10.1.2.0/24`) from which inbound
+/// clients are denied, regardless of the authorizations of their servers'
+/// policies. A network may be scoped to a single server by name, as in
+/// `<server>=<network>`.
+const ENV_INBOUND_AUTHZ_DENY_NETWORKS: &str = "LINKERD2_PROXY_INBOUND_AUTHZ_DENY_NETWORKS";
+
 pub const ENV_IDENTITY_DISABLED: &str = "LINKERD2_PROXY_IDENTITY_DISABLED";
 pub const ENV_IDENTITY_DIR: &str = "LINKERD2_PROXY_IDENTITY_DIR";
 pub const ENV_IDENTITY_TRUST_ANCHORS: &str = "LINKERD2_PROXY_IDENTITY_TRUST_ANCHORS";
+
+/// A comma-separated list of `<trust-domain>=<path>` entries that federate the
+/// local trust domain with other (e.g. SPIFFE-based) trust domains. Each path
+/// names a PEM file of the trust anchors that issue the domain's identities.
+/// Peers whose identities end with a federated trust domain are validated
+/// against its trust anchors rather than `LINKERD2_PROXY_IDENTITY_TRUST_ANCHORS`.
+pub const ENV_IDENTITY_FEDERATED_TRUST_ANCHORS: &str =
+    "LINKERD2_PROXY_IDENTITY_FEDERATED_TRUST_ANCHORS";
 pub const ENV_IDENTITY_IDENTITY_LOCAL_NAME: &str = "LINKERD2_PROXY_IDENTITY_LOCAL_NAME";
 pub const ENV_IDENTITY_TOKEN_FILE: &str = "LINKERD2_PROXY_IDENTITY_TOKEN_FILE";
 pub const ENV_IDENTITY_MIN_REFRESH: &str = "LINKERD2_PROXY_IDENTITY_MIN_REFRESH";
@@ -186,13 +773,199 @@ pub const ENV_IDENTITY_MAX_REFRESH: &str = "LINKERD2_PROXY_IDENTITY_MAX_REFRESH"
 
 pub const ENV_IDENTITY_SVC_BASE: &str = "LINKERD2_PROXY_IDENTITY_SVC";
 
+/// The path of a PEM-encoded certificate chain (leaf first) for the local
+/// identity. When set, the local identity is read from files that are
+/// provisioned by another process (e.g. cert-manager or a SPIRE agent) rather
+/// than by the identity controller, and the files are reloaded as they change.
+pub const ENV_IDENTITY_CRT_FILE: &str = "LINKERD2_PROXY_IDENTITY_CRT_FILE";
+
+/// The path of the local identity's PEM-encoded PKCS#8 private key. Required
+/// when `LINKERD2_PROXY_IDENTITY_CRT_FILE` is set.
+pub const ENV_IDENTITY_KEY_FILE: &str = "LINKERD2_PROXY_IDENTITY_KEY_FILE";
+
+/// The path of a PEM file of trust anchors. Required when
+/// `LINKERD2_PROXY_IDENTITY_CRT_FILE` is set.
+pub const ENV_IDENTITY_TRUST_ANCHORS_FILE: &str = "LINKERD2_PROXY_IDENTITY_TRUST_ANCHORS_FILE";
+
+/// If true, TLS session secrets of inbound and outbound connections are logged
+/// (in the NSS key log format) to the file named by `SSLKEYLOGFILE`, so that
+/// packet captures may be decrypted. This compromises the confidentiality of
+/// all meshed traffic and must only be enabled while debugging.
+pub const ENV_TLS_KEY_LOG_ENABLED: &str = "LINKERD2_PROXY_TLS_KEY_LOG_ENABLED";
+
+/// The file to which TLS session secrets are logged. Only used when
+/// `LINKERD2_PROXY_TLS_KEY_LOG_ENABLED` is true.
+const ENV_SSLKEYLOGFILE: &str = "SSLKEYLOGFILE";
+
+/// The minimum TLS version (`1.2` or `1.3`) negotiated by both the inbound
+/// acceptor and outbound connector. Defaults to `1.2`.
+pub const ENV_TLS_MIN_VERSION: &str = "LINKERD2_PROXY_TLS_MIN_VERSION";
+
+/// The maximum TLS version (`1.2` or `1.3`) negotiated by both the inbound
+/// acceptor and outbound connector. Defaults to `1.3`.
+pub const ENV_TLS_MAX_VERSION: &str = "LINKERD2_PROXY_TLS_MAX_VERSION";
+
+/// A comma-separated list of the cipher suites that may be negotiated, named
+/// as in the IANA registry (e.g. `TLS13_AES_128_GCM_SHA256`). Each enabled TLS
+/// version must have at least one usable cipher suite. Defaults to all cipher
+/// suites supported by rustls.
+pub const ENV_TLS_CIPHER_SUITES: &str = "LINKERD2_PROXY_TLS_CIPHER_SUITES";
+
+/// The maximum number of TLS sessions cached so that outbound mTLS connections
+/// may resume them rather than perform full handshakes. If zero, session
+/// resumption is disabled.
+pub const ENV_OUTBOUND_TLS_SESSION_CACHE_SIZE: &str =
+    "LINKERD2_PROXY_OUTBOUND_TLS_SESSION_CACHE_SIZE";
+
+/// How often identity files are reloaded if no changes are observed.
+pub const ENV_IDENTITY_FILES_RELOAD_INTERVAL: &str =
+    "LINKERD2_PROXY_IDENTITY_FILES_RELOAD_INTERVAL";
+
 pub const ENV_DESTINATION_SVC_BASE: &str = "LINKERD2_PROXY_DESTINATION_SVC";
 
 pub const ENV_HOSTNAME: &str = "HOSTNAME";
 
 pub const ENV_TRACE_COLLECTOR_SVC_BASE: &str = "LINKERD2_PROXY_TRACE_COLLECTOR_SVC";
 
+/// The protocol used to export spans to the trace collector: `opencensus`
+/// (the default) or `otlp` (OTLP over gRPC).
+pub const ENV_TRACE_PROTOCOL: &str = "LINKERD2_PROXY_TRACE_PROTOCOL";
+
+/// Determines whether B3 (`b3`, the default) or W3C (`w3c`) trace context
+/// headers are used when a request has both.
+pub const ENV_TRACE_CONTEXT_PRECEDENCE: &str = "LINKERD2_PROXY_TRACE_CONTEXT_PRECEDENCE";
+
+/// Where access log records are written: `stdout`, `stderr`, `fd:<n>`, a file
+/// path, a Unix datagram socket (`unix:<path>`), or a syslog endpoint
+/// (`syslog:<path>` or `syslog:<ip>:<port>`). Audit events for privileged
+/// admin requests are written to the same destination. Access logging is
+/// disabled when this is not set.
+pub const ENV_ACCESS_LOG: &str = "LINKERD2_PROXY_ACCESS_LOG";
+
+/// How access log records are encoded: `json` (the default), `apache_common`,
+/// or `apache_combined`.
+pub const ENV_ACCESS_LOG_FORMAT: &str = "LINKERD2_PROXY_ACCESS_LOG_FORMAT";
+
+/// A comma-separated list of the response status classes (e.g. `4xx,5xx`) for
+/// which access log records are written.
+pub const ENV_ACCESS_LOG_STATUS_CLASSES: &str = "LINKERD2_PROXY_ACCESS_LOG_STATUS_CLASSES";
+
+/// A comma-separated list of the route names for which access log records
+/// are written.
+pub const ENV_ACCESS_LOG_ROUTES: &str = "LINKERD2_PROXY_ACCESS_LOG_ROUTES";
+
+/// A comma-separated list of the domain suffixes of request authorities for
+/// which access log records are written.
+pub const ENV_ACCESS_LOG_AUTHORITY_SUFFIXES: &str = "LINKERD2_PROXY_ACCESS_LOG_AUTHORITY_SUFFIXES";
+
+/// When set to N, only one in every N non-5XX responses is logged.
+pub const ENV_ACCESS_LOG_SUCCESS_SAMPLE_INTERVAL: &str =
+    "LINKERD2_PROXY_ACCESS_LOG_SUCCESS_SAMPLE_INTERVAL";
+
+/// The number of access log records that may be buffered before records are
+/// dropped.
+pub const ENV_ACCESS_LOG_BUFFER_CAPACITY: &str = "LINKERD2_PROXY_ACCESS_LOG_BUFFER_CAPACITY";
+
+/// If true, request query strings are included in access log records.
+/// Otherwise, only request paths are logged.
+pub const ENV_ACCESS_LOG_QUERY: &str = "LINKERD2_PROXY_ACCESS_LOG_QUERY";
+
+/// Configures the address (`_ADDR`) and identity (`_NAME`) of an external rate
+/// limit service, implementing Envoy's RLS API, with which HTTP requests are
+/// checked before they are dispatched.
+pub const ENV_RATELIMIT_SVC_BASE: &str = "LINKERD2_PROXY_RATELIMIT_SVC";
+
+/// The rate limit service domain in which requests are checked.
+pub const ENV_RATELIMIT_DOMAIN: &str = "LINKERD2_PROXY_RATELIMIT_DOMAIN";
+
+/// A comma-separated list of the entries of each request's descriptor:
+/// `authority`, `path`, `method`, `route`, `client_identity`, or
+/// `header:<name>`.
+pub const ENV_RATELIMIT_DESCRIPTOR: &str = "LINKERD2_PROXY_RATELIMIT_DESCRIPTOR";
+
+/// Determines whether requests are permitted (`open`, the default) or fail
+/// (`closed`) when the rate limit service is unavailable.
+pub const ENV_RATELIMIT_FAILURE_MODE: &str = "LINKERD2_PROXY_RATELIMIT_FAILURE_MODE";
+
+/// Bounds the time spent waiting for the rate limit service's response.
+pub const ENV_RATELIMIT_TIMEOUT: &str = "LINKERD2_PROXY_RATELIMIT_TIMEOUT";
+
+/// A comma-separated list of the proxies (`inbound` and/or `outbound`) whose
+/// requests are checked with the rate limit service.
+pub const ENV_RATELIMIT_DIRECTIONS: &str = "LINKERD2_PROXY_RATELIMIT_DIRECTIONS";
+
+/// Configures the address (`_ADDR`) and identity (`_NAME`) of an external
+/// authorizer with which inbound HTTP requests are authorized before they are
+/// dispatched.
+pub const ENV_INBOUND_EXT_AUTHZ_SVC_BASE: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_SVC";
+
+/// The API with which the external authorizer is called: `grpc` (Envoy's
+/// `ext_authz` API, the default) or `http`.
+pub const ENV_INBOUND_EXT_AUTHZ_PROTOCOL: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_PROTOCOL";
+
+/// Determines whether requests are permitted (`open`) or fail (`closed`, the
+/// default) when the external authorizer is unavailable.
+pub const ENV_INBOUND_EXT_AUTHZ_FAILURE_MODE: &str =
+    "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_FAILURE_MODE";
+
+/// Bounds the time spent waiting for the external authorizer's response.
+pub const ENV_INBOUND_EXT_AUTHZ_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_TIMEOUT";
+
+/// A comma-separated list of the headers of an HTTP external authorizer's
+/// responses that are set on the requests it permits.
+pub const ENV_INBOUND_EXT_AUTHZ_HEADERS: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_HEADERS";
+
+/// How long the external authorizer's decisions are cached, by client identity
+/// and route. Decisions are not cached when zero (the default). Decisions for
+/// clients without an identity, and those that mutate request headers, are
+/// never cached.
+pub const ENV_INBOUND_EXT_AUTHZ_CACHE_TTL: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_CACHE_TTL";
+
+/// The maximum number of cached decisions.
+pub const ENV_INBOUND_EXT_AUTHZ_CACHE_CAPACITY: &str =
+    "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_CACHE_CAPACITY";
+
+/// A comma-separated list of the names of the inbound servers whose requests
+/// are authorized by the external authorizer. All servers' requests are
+/// authorized when unset.
+pub const ENV_INBOUND_EXT_AUTHZ_SERVERS: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_SERVERS";
+
 pub const ENV_DESTINATION_CONTEXT: &str = "LINKERD2_PROXY_DESTINATION_CONTEXT";
+
+/// The path to a YAML or JSON file declaring the endpoints of logical services.
+///
+/// Declared services are resolved from the file when the destination
+/// controller is unavailable or does not know of them.
+pub const ENV_DESTINATION_STATIC_ENDPOINTS_FILE: &str =
+    "LINKERD2_PROXY_DESTINATION_STATIC_ENDPOINTS_FILE";
+
+/// How long a destination's last-known endpoints continue to be served after
+/// the connection to the control plane is lost. Only endpoint resolutions are
+/// cached: service profiles and inbound policies are not.
+pub const ENV_DESTINATION_RESOLUTION_CACHE_MAX_AGE: &str =
+    "LINKERD2_PROXY_DESTINATION_RESOLUTION_CACHE_MAX_AGE";
+
+/// A comma-separated list of `suffix=setting[;setting]` entries that tune
+/// discovery for destinations in a DNS suffix (e.g.
+/// `slow.example.com.=min=1s;max=1m;profile-timeout=5s`). The `min`, `max`, and
+/// `jitter` settings configure the backoff between failed resolutions and
+/// profile lookups; `profile-timeout` bounds the time spent waiting for a
+/// destination's profile, after which it is treated as having no profile.
+pub const ENV_DESTINATION_SUFFIX_TUNING: &str = "LINKERD2_PROXY_DESTINATION_SUFFIX_TUNING";
+
+/// Configures the address (`_ADDR`) and identity (`_NAME`) of an Envoy-compatible
+/// control plane, implementing the aggregated discovery service (ADS), from
+/// which endpoints are resolved instead of the destination service.
+pub const ENV_XDS_SVC_BASE: &str = "LINKERD2_PROXY_XDS_SVC";
+
+/// A comma-separated list of DNS suffixes whose names are resolved from the
+/// xDS control plane. Defaults to all names.
+pub const ENV_XDS_SUFFIXES: &str = "LINKERD2_PROXY_XDS_SUFFIXES";
+
+/// The node ID and cluster with which the proxy identifies itself to the xDS
+/// control plane.
+pub const ENV_XDS_NODE_ID: &str = "LINKERD2_PROXY_XDS_NODE_ID";
+pub const ENV_XDS_NODE_CLUSTER: &str = "LINKERD2_PROXY_XDS_NODE_CLUSTER";
 pub const ENV_DESTINATION_PROFILE_INITIAL_TIMEOUT: &str =
     "LINKERD2_PROXY_DESTINATION_PROFILE_INITIAL_TIMEOUT";
 
@@ -207,6 +980,12 @@ const ENV_DNS_MIN_TTL: &str = "LINKERD2_PROXY_DNS_MIN_TTL";
 ///
 /// Lookups with TTLs above this value will use this value instead.
 const ENV_DNS_MAX_TTL: &str = "LINKERD2_PROXY_DNS_MAX_TTL";
+/// Configures how long a name is assumed not to exist after a lookup returns
+/// NXDOMAIN, before it is looked up again.
+const ENV_DNS_NEGATIVE_TTL: &str = "LINKERD2_PROXY_DNS_NEGATIVE_TTL";
+/// Configures which address families are resolved and preferred: one of
+/// `ipv4` (the default), `ipv6`, `ipv4-only`, or `ipv6-only`.
+const ENV_DNS_IP_PREFERENCE: &str = "LINKERD2_PROXY_DNS_IP_PREFERENCE";
 
 /// Configure the stream or connection level flow control setting for HTTP2.
 ///
@@ -215,12 +994,62 @@ const ENV_INITIAL_STREAM_WINDOW_SIZE: &str = "LINKERD2_PROXY_HTTP2_INITIAL_STREA
 const ENV_INITIAL_CONNECTION_WINDOW_SIZE: &str =
     "LINKERD2_PROXY_HTTP2_INITIAL_CONNECTION_WINDOW_SIZE";
 
+/// The initial stream-level flow control window size.
+///
+/// HTTP/2 settings may also be tuned for the inbound and outbound servers and
+/// clients, and for control plane clients, by setting any of these with the
+/// `LINKERD2_PROXY_{base}_HTTP2_` prefix (e.g.
+/// `LINKERD2_PROXY_OUTBOUND_CONNECT_HTTP2_INITIAL_STREAM_WINDOW_SIZE`). Unset
+/// values fall back to the global settings above.
+const H2_INITIAL_STREAM_WINDOW_SIZE: &str = "INITIAL_STREAM_WINDOW_SIZE";
+/// The initial connection-level flow control window size.
+const H2_INITIAL_CONNECTION_WINDOW_SIZE: &str = "INITIAL_CONNECTION_WINDOW_SIZE";
+/// Only applies to servers.
+const H2_MAX_CONCURRENT_STREAMS: &str = "MAX_CONCURRENT_STREAMS";
+/// The largest frame payload that will be accepted.
+const H2_MAX_FRAME_SIZE: &str = "MAX_FRAME_SIZE";
+/// How often PING frames are sent. Defaults to a quarter of the timeout.
+const H2_KEEPALIVE_INTERVAL: &str = "KEEPALIVE_INTERVAL";
+/// How long to wait for a PING to be acknowledged before closing the
+/// connection.
+const H2_KEEPALIVE_TIMEOUT: &str = "KEEPALIVE_TIMEOUT";
+
+/// Configures how long an accepted HTTP/2 connection may stay open before the
+/// proxy sends a GOAWAY and gracefully drains it, so that clients reconnect
+/// and are rebalanced. Unlimited if unspecified.
+const ENV_INBOUND_HTTP2_MAX_CONNECTION_AGE: &str =
+    "LINKERD2_PROXY_INBOUND_HTTP2_MAX_CONNECTION_AGE";
+const ENV_OUTBOUND_HTTP2_MAX_CONNECTION_AGE: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP2_MAX_CONNECTION_AGE";
+
+/// Configures how long an accepted HTTP/2 connection may have no requests in
+/// flight before it is gracefully closed. Unlimited if unspecified.
+const ENV_INBOUND_HTTP2_MAX_CONNECTION_IDLE: &str =
+    "LINKERD2_PROXY_INBOUND_HTTP2_MAX_CONNECTION_IDLE";
+const ENV_OUTBOUND_HTTP2_MAX_CONNECTION_IDLE: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP2_MAX_CONNECTION_IDLE";
+
+/// Configures how long a connection closed for its age or idleness may take to
+/// complete its in-flight streams before it is closed forcibly. Defaults to
+/// 30s.
+const ENV_INBOUND_HTTP2_MAX_CONNECTION_AGE_GRACE: &str =
+    "LINKERD2_PROXY_INBOUND_HTTP2_MAX_CONNECTION_AGE_GRACE";
+const ENV_OUTBOUND_HTTP2_MAX_CONNECTION_AGE_GRACE: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP2_MAX_CONNECTION_AGE_GRACE";
+
+/// Configures how long the outbound proxy uses an HTTP/2 connection to an
+/// endpoint before replacing it with a new connection, so that requests are
+/// rebalanced over the endpoint's replicas. Unlimited if unspecified.
+const ENV_OUTBOUND_CONNECT_HTTP2_MAX_CONNECTION_AGE: &str =
+    "LINKERD2_PROXY_OUTBOUND_CONNECT_HTTP2_MAX_CONNECTION_AGE";
+
 // Default values for various configuration fields
 const DEFAULT_OUTBOUND_LISTEN_ADDR: &str = "127.0.0.1:4140";
 pub const DEFAULT_INBOUND_LISTEN_ADDR: &str = "0.0.0.0:4143";
 pub const DEFAULT_CONTROL_LISTEN_ADDR: &str = "0.0.0.0:4190";
 const DEFAULT_ADMIN_LISTEN_ADDR: &str = "127.0.0.1:4191";
 const DEFAULT_METRICS_RETAIN_IDLE: Duration = Duration::from_secs(10 * 60);
+const DEFAULT_READINESS_CONTROL_PLANE_TIMEOUT: Duration = Duration::from_secs(60);
 const DEFAULT_INBOUND_DISPATCH_TIMEOUT: Duration = Duration::from_secs(1);
 const DEFAULT_INBOUND_DETECT_TIMEOUT: Duration = Duration::from_secs(10);
 const DEFAULT_INBOUND_CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
@@ -232,11 +1061,47 @@ const DEFAULT_INBOUND_CONNECT_BACKOFF: ExponentialBackoff = ExponentialBackoff {
 const DEFAULT_OUTBOUND_DISPATCH_TIMEOUT: Duration = Duration::from_secs(3);
 const DEFAULT_OUTBOUND_DETECT_TIMEOUT: Duration = Duration::from_secs(10);
 const DEFAULT_OUTBOUND_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+const DEFAULT_OUTBOUND_PROBE_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_OUTBOUND_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+const DEFAULT_OUTBOUND_UDP_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_OUTBOUND_UDP_MAX_FLOWS: usize = 1024;
+const DEFAULT_OUTBOUND_RETRY_MAX_BUFFERED_BYTES: usize = 64 * 1024;
+const DEFAULT_OUTBOUND_RETRY_BACKOFF_MAX: Duration = Duration::from_secs(1);
+const DEFAULT_OUTBOUND_RETRY_BACKOFF_MULTIPLIER: f64 = 2.0;
+const DEFAULT_OUTBOUND_RETRY_BACKOFF_JITTER: f64 = 0.5;
+const DEFAULT_OUTBOUND_RETRY_BUDGET_TTL: Duration = Duration::from_secs(10);
+const DEFAULT_OUTBOUND_RETRY_BUDGET_MIN_PER_SECOND: u32 = 10;
+const DEFAULT_OUTBOUND_EWMA_DEFAULT_RTT: Duration = Duration::from_millis(30);
+const DEFAULT_OUTBOUND_EWMA_DECAY: Duration = Duration::from_secs(10);
+const DEFAULT_OUTBOUND_ZONE_LOCAL_WEIGHT: f64 = 10.0;
+const DEFAULT_OUTBOUND_ZONE_SPILLOVER_THRESHOLD: f64 = 0.25;
+const DEFAULT_OUTBOUND_OUTLIER_BASE_EJECTION_TIME: Duration = Duration::from_secs(30);
+const DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTION_TIME: Duration = Duration::from_secs(300);
+const DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTION_PERCENT: u32 = 10;
+const DEFAULT_OUTBOUND_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_OUTBOUND_HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(1);
+const DEFAULT_OUTBOUND_HEALTH_CHECK_HEALTHY_THRESHOLD: u32 = 2;
+const DEFAULT_OUTBOUND_HEALTH_CHECK_UNHEALTHY_THRESHOLD: u32 = 3;
+const DEFAULT_OUTBOUND_FAILOVER_THRESHOLD: f64 = 0.5;
+const DEFAULT_OUTBOUND_HTTP_MIRROR_PERCENT: u32 = 100;
+const DEFAULT_OUTBOUND_CIRCUIT_BREAKER_OPEN_TIME: Duration = Duration::from_secs(30);
+const DEFAULT_OUTBOUND_CIRCUIT_BREAKER_PROBES: u32 = 1;
+const DEFAULT_OUTBOUND_CIRCUIT_BREAKER_WINDOW: Duration = Duration::from_secs(10);
+const DEFAULT_OUTBOUND_CIRCUIT_BREAKER_MIN_REQUESTS: u32 = 10;
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_INITIAL: usize = 20;
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_MIN: usize = 1;
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_MAX: usize = 1000;
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_BACKOFF: f64 = 0.9;
+const DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_TOLERANCE: f64 = 1.5;
+const DEFAULT_OUTBOUND_HTTP_STICKY_SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+const DEFAULT_OUTBOUND_HTTP_FAULT_PERCENT: u32 = 100;
 const DEFAULT_OUTBOUND_CONNECT_BACKOFF: ExponentialBackoff = ExponentialBackoff {
     min: Duration::from_millis(100),
     max: Duration::from_millis(500),
     jitter: 0.1,
 };
+const DEFAULT_OUTBOUND_MULTICLUSTER_REMOTE_LABEL: &str = "remote_cluster";
+const DEFAULT_OUTBOUND_CONNECT_FALLBACK_DELAY: Duration = Duration::from_millis(250);
 const DEFAULT_RESOLV_CONF: &str = "/etc/resolv.conf";
 
 const DEFAULT_INITIAL_STREAM_WINDOW_SIZE: u32 = 65_535; // Protocol default
@@ -279,14 +1144,32 @@ const DEFAULT_OUTBOUND_MAX_IN_FLIGHT: usize = 100_000;
 // buffer requests for high-load services.
 const DEFAULT_BUFFER_CAPACITY: usize = 10_000;
 
+const DEFAULT_RATELIMIT_DOMAIN: &str = "linkerd";
+const DEFAULT_RATELIMIT_DESCRIPTOR: &str = "authority,route,client_identity";
+const DEFAULT_RATELIMIT_TIMEOUT: Duration = Duration::from_millis(20);
+const DEFAULT_INBOUND_EXT_AUTHZ_TIMEOUT: Duration = Duration::from_millis(200);
+const DEFAULT_INBOUND_EXT_AUTHZ_CACHE_CAPACITY: usize = 10_000;
+
+const DEFAULT_INBOUND_JWT_JWKS_REFRESH: Duration = Duration::from_secs(60);
+
 const DEFAULT_DESTINATION_PROFILE_SUFFIXES: &str = "svc.cluster.local.";
 const DEFAULT_DESTINATION_PROFILE_IDLE_TIMEOUT: Duration = Duration::from_millis(500);
+const DEFAULT_DESTINATION_RESOLUTION_CACHE_MAX_AGE: Duration = Duration::from_secs(5 * 60);
 
 const DEFAULT_IDENTITY_MIN_REFRESH: Duration = Duration::from_secs(10);
 const DEFAULT_IDENTITY_MAX_REFRESH: Duration = Duration::from_secs(60 * 60 * 24);
+const DEFAULT_IDENTITY_FILES_RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_OUTBOUND_TLS_SESSION_CACHE_SIZE: usize = 256;
 
 const INBOUND_CONNECT_BASE: &str = "INBOUND_CONNECT";
 const OUTBOUND_CONNECT_BASE: &str = "OUTBOUND_CONNECT";
+const INBOUND_ACCEPT_BASE: &str = "INBOUND_ACCEPT";
+const OUTBOUND_ACCEPT_BASE: &str = "OUTBOUND_ACCEPT";
+
+/// Connections to control plane components use the inbound or outbound
+/// proxy's keepalive settings unless any are configured with this base. Their
+/// HTTP/2 settings may also be configured with this base.
+const CONTROL_CONNECT_BASE: &str = "CONTROL_CONNECT";
 
 /// Load a `App` by reading ENV variables.
 pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError> {
@@ -295,6 +1178,10 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let outbound_listener_addr = parse(strings, ENV_OUTBOUND_LISTEN_ADDR, parse_socket_addr);
     let inbound_listener_addr = parse(strings, ENV_INBOUND_LISTEN_ADDR, parse_socket_addr);
     let admin_listener_addr = parse(strings, ENV_ADMIN_LISTEN_ADDR, parse_socket_addr);
+    let admin_uds_path = parse(strings, ENV_ADMIN_UDS_PATH, |s| Ok(PathBuf::from(s)));
+    let admin_tcp_disabled = parse(strings, ENV_ADMIN_TCP_DISABLED, parse_bool);
+    let admin_proxy_protocol_networks =
+        parse(strings, ENV_ADMIN_PROXY_PROTOCOL_NETWORKS, parse_networks);
 
     let inbound_detect_timeout = parse(strings, ENV_INBOUND_DETECT_TIMEOUT, parse_duration);
     let inbound_dispatch_timeout = parse(strings, ENV_INBOUND_DISPATCH_TIMEOUT, parse_duration);
@@ -304,11 +1191,16 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let outbound_dispatch_timeout = parse(strings, ENV_OUTBOUND_DISPATCH_TIMEOUT, parse_duration);
     let outbound_connect_timeout = parse(strings, ENV_OUTBOUND_CONNECT_TIMEOUT, parse_duration);
 
-    let inbound_accept_keepalive = parse(strings, ENV_INBOUND_ACCEPT_KEEPALIVE, parse_duration);
-    let outbound_accept_keepalive = parse(strings, ENV_OUTBOUND_ACCEPT_KEEPALIVE, parse_duration);
+    let inbound_accept_keepalive = parse_keepalive(strings, INBOUND_ACCEPT_BASE);
+    let inbound_accept_limits = parse_accept_limits(strings, INBOUND_ACCEPT_BASE);
+    let inbound_proxy_protocol_networks =
+        parse(strings, ENV_INBOUND_PROXY_PROTOCOL_NETWORKS, parse_networks);
+    let outbound_accept_keepalive = parse_keepalive(strings, OUTBOUND_ACCEPT_BASE);
+    let outbound_accept_limits = parse_accept_limits(strings, OUTBOUND_ACCEPT_BASE);
 
-    let inbound_connect_keepalive = parse(strings, ENV_INBOUND_CONNECT_KEEPALIVE, parse_duration);
-    let outbound_connect_keepalive = parse(strings, ENV_OUTBOUND_CONNECT_KEEPALIVE, parse_duration);
+    let inbound_connect_keepalive = parse_keepalive(strings, INBOUND_CONNECT_BASE);
+    let outbound_connect_keepalive = parse_keepalive(strings, OUTBOUND_CONNECT_BASE);
+    let control_connect_keepalive = parse_keepalive(strings, CONTROL_CONNECT_BASE)?;
 
     let inbound_disable_ports = parse(
         strings,
@@ -337,14 +1229,180 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let inbound_max_in_flight = parse(strings, ENV_INBOUND_MAX_IN_FLIGHT, parse_number);
     let outbound_max_in_flight = parse(strings, ENV_OUTBOUND_MAX_IN_FLIGHT, parse_number);
 
+    let outbound_probes = parse(strings, ENV_OUTBOUND_PROBES, parse_probes);
+    let outbound_probe_interval =
+        parse(strings, ENV_OUTBOUND_PROBE_INTERVAL, parse_nonzero_duration);
+    let outbound_probe_timeout = parse(strings, ENV_OUTBOUND_PROBE_TIMEOUT, parse_duration);
+    let outbound_authority_rewrites = parse(
+        strings,
+        ENV_OUTBOUND_AUTHORITY_REWRITES,
+        parse_authority_rewrites,
+    );
+    let outbound_retry_suppression_after = parse(
+        strings,
+        ENV_OUTBOUND_RETRY_SUPPRESSION_AFTER,
+        parse_duration,
+    );
+    let outbound_retry_max_buffered_bytes = parse(
+        strings,
+        ENV_OUTBOUND_RETRY_MAX_BUFFERED_BYTES,
+        parse_number::<usize>,
+    );
+    let outbound_retry_after_max = parse(strings, ENV_OUTBOUND_RETRY_AFTER_MAX, parse_duration);
+    let outbound_retry_unprocessed = parse(strings, ENV_OUTBOUND_RETRY_UNPROCESSED, parse_bool);
+    let outbound_retry_backoffs = parse(strings, ENV_OUTBOUND_RETRY_BACKOFFS, parse_retry_backoffs);
+    let outbound_retry_per_try_timeouts = parse(
+        strings,
+        ENV_OUTBOUND_RETRY_PER_TRY_TIMEOUTS,
+        parse_per_try_timeouts,
+    );
+    let outbound_retry_budgets = parse(strings, ENV_OUTBOUND_RETRY_BUDGETS, parse_retry_budgets);
+    let outbound_egress_allow = parse(strings, ENV_OUTBOUND_EGRESS_ALLOW, parse_egress_policy);
+    let outbound_proxy_protocol_ports =
+        parse(strings, ENV_OUTBOUND_PROXY_PROTOCOL_PORTS, parse_port_set);
+    let outbound_proxy_protocol_authorities = parse(
+        strings,
+        ENV_OUTBOUND_PROXY_PROTOCOL_AUTHORITIES,
+        parse_name_addrs,
+    );
+    let outbound_tls_origination = parse(
+        strings,
+        ENV_OUTBOUND_TLS_ORIGINATION,
+        parse_tls_originations,
+    );
+    let outbound_udp_forwards = parse(strings, ENV_OUTBOUND_UDP_FORWARDS, parse_udp_forwards);
+    let outbound_udp_idle_timeout = parse(strings, ENV_OUTBOUND_UDP_IDLE_TIMEOUT, parse_duration);
+    let outbound_udp_max_flows = parse(strings, ENV_OUTBOUND_UDP_MAX_FLOWS, parse_number);
+    let outbound_skip_detect = parse(strings, ENV_OUTBOUND_SKIP_DETECT, parse_skip_detect);
+    let outbound_http_hash_policies = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_HASH_POLICIES,
+        parse_hash_policies,
+    );
+    let outbound_http_sticky_sessions = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_STICKY_SESSIONS,
+        parse_sticky_sessions,
+    );
+    let outbound_http_hedges = parse(strings, ENV_OUTBOUND_HTTP_HEDGES, parse_hedges);
+    let outbound_http_faults = parse(strings, ENV_OUTBOUND_HTTP_FAULTS, parse_faults);
+    let outbound_http_header_policies = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_HEADER_POLICIES,
+        parse_header_policies,
+    );
+    let outbound_http_route_filters = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_ROUTE_FILTERS,
+        parse_route_filters,
+    );
+    let outbound_tcp_source_affinity =
+        parse(strings, ENV_OUTBOUND_TCP_SOURCE_AFFINITY, parse_name_addrs);
+    let outbound_zone_local_weight =
+        parse(strings, ENV_OUTBOUND_ZONE_LOCAL_WEIGHT, parse_zone_weight);
+    let outbound_zone_spillover_threshold = parse(
+        strings,
+        ENV_OUTBOUND_ZONE_SPILLOVER_THRESHOLD,
+        parse_zone_threshold,
+    );
+    let outbound_ewma_default_rtt = parse(strings, ENV_OUTBOUND_EWMA_DEFAULT_RTT, parse_duration);
+    let outbound_ewma_decay = parse(strings, ENV_OUTBOUND_EWMA_DECAY, parse_nonzero_duration);
+    let outbound_balancer_algorithms = parse(
+        strings,
+        ENV_OUTBOUND_BALANCER_ALGORITHMS,
+        parse_balancer_algorithms,
+    );
+    let outbound_slow_start_window = parse(
+        strings,
+        ENV_OUTBOUND_SLOW_START_WINDOW,
+        parse_nonzero_duration,
+    );
+    let outbound_outlier_consecutive_failures = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_CONSECUTIVE_FAILURES,
+        parse_number::<u32>,
+    );
+    let outbound_outlier_failure_latency = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_FAILURE_LATENCY,
+        parse_duration,
+    );
+    let outbound_outlier_base_ejection_time = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_BASE_EJECTION_TIME,
+        parse_duration,
+    );
+    let outbound_outlier_max_ejection_time = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_MAX_EJECTION_TIME,
+        parse_duration,
+    );
+    let outbound_outlier_max_ejection_percent = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_MAX_EJECTION_PERCENT,
+        parse_percent,
+    );
+    let outbound_health_checks = parse(strings, ENV_OUTBOUND_HEALTH_CHECKS, parse_health_checks);
+    let outbound_http_circuit_breakers = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_CIRCUIT_BREAKERS,
+        parse_circuit_breakers,
+    );
+    let outbound_http_concurrency_limits = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_CONCURRENCY_LIMITS,
+        parse_concurrency_limits,
+    );
+    let outbound_failover = parse(strings, ENV_OUTBOUND_FAILOVER, parse_failovers);
+    let outbound_multicluster_failover = parse(
+        strings,
+        ENV_OUTBOUND_MULTICLUSTER_FAILOVER,
+        parse_name_addrs,
+    );
+    let outbound_http_mirrors = parse(strings, ENV_OUTBOUND_HTTP_MIRRORS, parse_mirrors);
+    let outbound_http_proxy_authorization = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_PROXY_AUTHORIZATION,
+        parse_http_proxy_authorization,
+    );
+    let outbound_http_proxy_exempt_networks = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_PROXY_EXEMPT_NETWORKS,
+        parse_networks,
+    );
+    let outbound_connect_fallback_delay =
+        parse(strings, ENV_OUTBOUND_CONNECT_FALLBACK_DELAY, parse_duration);
+    let outbound_http_proxy = parse(strings, ENV_OUTBOUND_HTTP_PROXY, parse_http_proxy);
+    let outbound_connect_mark = parse(strings, ENV_OUTBOUND_CONNECT_MARK, parse_socket_mark);
+    let outbound_tcp_bandwidth_limits = parse(
+        strings,
+        ENV_OUTBOUND_TCP_BANDWIDTH_LIMITS,
+        parse_bandwidth_limits,
+    );
+
     let metrics_retain_idle = parse(strings, ENV_METRICS_RETAIN_IDLE, parse_duration);
+    let readiness_require_control_plane =
+        parse(strings, ENV_READINESS_REQUIRE_CONTROL_PLANE, parse_bool);
+    let readiness_control_plane_timeout =
+        parse(strings, ENV_READINESS_CONTROL_PLANE_TIMEOUT, parse_duration);
+    let transport_aggregation = parse(
+        strings,
+        ENV_TRANSPORT_METRICS_AGGREGATE,
+        parse_transport_aggregation,
+    );
 
     // DNS
 
     let resolv_conf_path = strings.get(ENV_RESOLV_CONF);
 
+    let orig_dst_mode = parse(strings, ENV_ORIG_DST_MODE, parse_orig_dst_mode);
+
     let dns_min_ttl = parse(strings, ENV_DNS_MIN_TTL, parse_duration);
     let dns_max_ttl = parse(strings, ENV_DNS_MAX_TTL, parse_duration);
+    let dns_negative_ttl = parse(strings, ENV_DNS_NEGATIVE_TTL, parse_duration);
+    let dns_ip_preference = parse(strings, ENV_DNS_IP_PREFERENCE, |s| {
+        s.trim().parse::<dns::IpPreference>().map_err(Into::into)
+    });
 
     let identity_config = parse_identity_config(strings);
 
@@ -359,6 +1417,63 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
     let trace_collector_addr =
         parse_control_addr(strings, ENV_TRACE_COLLECTOR_SVC_BASE, id_disabled);
+    let trace_protocol = parse(strings, ENV_TRACE_PROTOCOL, parse_trace_protocol);
+    let trace_precedence = parse(
+        strings,
+        ENV_TRACE_CONTEXT_PRECEDENCE,
+        parse_trace_context_precedence,
+    );
+
+    let access_log_destination = parse(strings, ENV_ACCESS_LOG, parse_access_log_destination);
+    let access_log_format = parse(strings, ENV_ACCESS_LOG_FORMAT, parse_access_log_format);
+    let access_log_status_classes =
+        parse(strings, ENV_ACCESS_LOG_STATUS_CLASSES, parse_status_classes);
+    let access_log_routes = parse(strings, ENV_ACCESS_LOG_ROUTES, parse_routes);
+    let access_log_authorities = parse(
+        strings,
+        ENV_ACCESS_LOG_AUTHORITY_SUFFIXES,
+        parse_dns_suffixes,
+    );
+    let access_log_success_sample_interval = parse(
+        strings,
+        ENV_ACCESS_LOG_SUCCESS_SAMPLE_INTERVAL,
+        parse_number,
+    );
+    let access_log_buffer_capacity = parse(strings, ENV_ACCESS_LOG_BUFFER_CAPACITY, parse_number);
+    let access_log_query = parse(strings, ENV_ACCESS_LOG_QUERY, parse_bool);
+
+    let rate_limit_addr = parse_control_addr(strings, ENV_RATELIMIT_SVC_BASE, id_disabled);
+    let rate_limit_domain = strings.get(ENV_RATELIMIT_DOMAIN);
+    let rate_limit_descriptor = parse(
+        strings,
+        ENV_RATELIMIT_DESCRIPTOR,
+        parse_rate_limit_descriptor,
+    );
+    let rate_limit_failure_mode = parse(
+        strings,
+        ENV_RATELIMIT_FAILURE_MODE,
+        parse_rate_limit_failure_mode,
+    );
+    let rate_limit_timeout = parse(strings, ENV_RATELIMIT_TIMEOUT, parse_duration);
+    let rate_limit_directions = parse(strings, ENV_RATELIMIT_DIRECTIONS, parse_directions);
+
+    let ext_authz_addr = parse_control_addr(strings, ENV_INBOUND_EXT_AUTHZ_SVC_BASE, id_disabled);
+    let ext_authz_protocol = parse(
+        strings,
+        ENV_INBOUND_EXT_AUTHZ_PROTOCOL,
+        parse_ext_authz_protocol,
+    );
+    let ext_authz_failure_mode = parse(
+        strings,
+        ENV_INBOUND_EXT_AUTHZ_FAILURE_MODE,
+        parse_rate_limit_failure_mode,
+    );
+    let ext_authz_headers = parse(strings, ENV_INBOUND_EXT_AUTHZ_HEADERS, parse_header_names);
+    let ext_authz_timeout = parse(strings, ENV_INBOUND_EXT_AUTHZ_TIMEOUT, parse_duration);
+    let ext_authz_cache_ttl = parse(strings, ENV_INBOUND_EXT_AUTHZ_CACHE_TTL, parse_duration);
+    let ext_authz_cache_capacity =
+        parse(strings, ENV_INBOUND_EXT_AUTHZ_CACHE_CAPACITY, parse_number);
+    let ext_authz_servers = parse(strings, ENV_INBOUND_EXT_AUTHZ_SERVERS, parse_routes);
 
     let gateway_suffixes = parse(strings, ENV_INBOUND_GATEWAY_SUFFIXES, parse_dns_suffixes);
 
@@ -375,10 +1490,70 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         parse_dns_suffixes,
     );
     let dst_profile_networks = parse(strings, ENV_DESTINATION_PROFILE_NETWORKS, parse_networks);
+    let dst_static_endpoints = parse(
+        strings,
+        ENV_DESTINATION_STATIC_ENDPOINTS_FILE,
+        parse_static_endpoints,
+    );
+
+    let dst_resolution_cache_max_age = parse(
+        strings,
+        ENV_DESTINATION_RESOLUTION_CACHE_MAX_AGE,
+        parse_duration,
+    );
+
+    let dst_suffix_tuning = parse(strings, ENV_DESTINATION_SUFFIX_TUNING, parse_suffix_tuning);
 
-    let initial_stream_window_size = parse(strings, ENV_INITIAL_STREAM_WINDOW_SIZE, parse_number);
-    let initial_connection_window_size =
-        parse(strings, ENV_INITIAL_CONNECTION_WINDOW_SIZE, parse_number);
+    let xds_addr = parse_control_addr(strings, ENV_XDS_SVC_BASE, id_disabled);
+    let xds_suffixes = parse(strings, ENV_XDS_SUFFIXES, parse_dns_suffixes);
+    let xds_node_id = strings.get(ENV_XDS_NODE_ID);
+    let xds_node_cluster = strings.get(ENV_XDS_NODE_CLUSTER);
+
+    let initial_stream_window_size = parse(
+        strings,
+        ENV_INITIAL_STREAM_WINDOW_SIZE,
+        parse_h2_window_size,
+    );
+    let initial_connection_window_size = parse(
+        strings,
+        ENV_INITIAL_CONNECTION_WINDOW_SIZE,
+        parse_h2_window_size,
+    );
+    let inbound_h2_max_connection_age = parse(
+        strings,
+        ENV_INBOUND_HTTP2_MAX_CONNECTION_AGE,
+        parse_nonzero_duration,
+    );
+    let inbound_h2_max_connection_idle = parse(
+        strings,
+        ENV_INBOUND_HTTP2_MAX_CONNECTION_IDLE,
+        parse_nonzero_duration,
+    );
+    let inbound_h2_max_connection_age_grace = parse(
+        strings,
+        ENV_INBOUND_HTTP2_MAX_CONNECTION_AGE_GRACE,
+        parse_nonzero_duration,
+    );
+    let outbound_h2_max_connection_age = parse(
+        strings,
+        ENV_OUTBOUND_HTTP2_MAX_CONNECTION_AGE,
+        parse_nonzero_duration,
+    );
+    let outbound_h2_max_connection_idle = parse(
+        strings,
+        ENV_OUTBOUND_HTTP2_MAX_CONNECTION_IDLE,
+        parse_nonzero_duration,
+    );
+    let outbound_h2_max_connection_age_grace = parse(
+        strings,
+        ENV_OUTBOUND_HTTP2_MAX_CONNECTION_AGE_GRACE,
+        parse_nonzero_duration,
+    );
+    let outbound_connect_h2_max_connection_age = parse(
+        strings,
+        ENV_OUTBOUND_CONNECT_HTTP2_MAX_CONNECTION_AGE,
+        parse_nonzero_duration,
+    );
 
     let tap = parse_tap_config(strings, id_disabled);
 
@@ -391,6 +1566,13 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         ),
         ..Default::default()
     };
+    let inbound_accept_h2_settings = parse_h2_settings(strings, INBOUND_ACCEPT_BASE, h2_settings);
+    let outbound_accept_h2_settings = parse_h2_settings(strings, OUTBOUND_ACCEPT_BASE, h2_settings);
+    let inbound_connect_h2_settings = parse_h2_settings(strings, INBOUND_CONNECT_BASE, h2_settings);
+    let outbound_connect_h2_settings =
+        parse_h2_settings(strings, OUTBOUND_CONNECT_BASE, h2_settings);
+    let control_connect_h2_settings =
+        parse_h2_settings(strings, CONTROL_CONNECT_BASE, h2_settings)?;
 
     let buffer_capacity = buffer_capacity?.unwrap_or(DEFAULT_BUFFER_CAPACITY);
 
@@ -418,17 +1600,26 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             outbound_listener_addr?
                 .unwrap_or_else(|| parse_socket_addr(DEFAULT_OUTBOUND_LISTEN_ADDR).unwrap()),
         );
-        let keepalive = Keepalive(outbound_accept_keepalive?);
+        let keepalive = outbound_accept_keepalive?.unwrap_or_default();
+        let (backlog, accept_limits) = outbound_accept_limits?;
         let server = ServerConfig {
             addr,
             keepalive,
-            h2_settings,
+            h2_settings: h2::Settings {
+                max_connection_age: outbound_h2_max_connection_age?,
+                max_connection_idle: outbound_h2_max_connection_idle?,
+                max_connection_age_grace: outbound_h2_max_connection_age_grace?,
+                ..outbound_accept_h2_settings?
+            },
+            proxy_protocol: Default::default(),
+            backlog,
+            accept_limits,
         };
         let cache_max_idle_age =
             outbound_cache_max_idle_age?.unwrap_or(DEFAULT_OUTBOUND_ROUTER_MAX_IDLE_AGE);
         let max_idle =
             outbound_max_idle_per_endpoint?.unwrap_or(DEFAULT_OUTBOUND_MAX_IDLE_CONNS_PER_ENDPOINT);
-        let keepalive = Keepalive(outbound_connect_keepalive?);
+        let keepalive = outbound_connect_keepalive?.unwrap_or_default();
         let connect = ConnectConfig {
             keepalive,
             timeout: outbound_connect_timeout?.unwrap_or(DEFAULT_OUTBOUND_CONNECT_TIMEOUT),
@@ -437,7 +1628,10 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 OUTBOUND_CONNECT_BASE,
                 DEFAULT_OUTBOUND_CONNECT_BACKOFF,
             )?,
-            h2_settings,
+            h2_settings: h2::Settings {
+                max_connection_age: outbound_connect_h2_max_connection_age?,
+                ..outbound_connect_h2_settings?
+            },
             h1_settings: h1::PoolSettings {
                 max_idle,
                 idle_timeout: cache_max_idle_age,
@@ -449,6 +1643,99 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         let dispatch_timeout =
             outbound_dispatch_timeout?.unwrap_or(DEFAULT_OUTBOUND_DISPATCH_TIMEOUT);
 
+        let probe_interval = outbound_probe_interval?.unwrap_or(DEFAULT_OUTBOUND_PROBE_INTERVAL);
+        let probe_timeout = outbound_probe_timeout?.unwrap_or(DEFAULT_OUTBOUND_PROBE_TIMEOUT);
+        let probe = outbound_probes?
+            .filter(|probes| !probes.is_empty())
+            .map(|probes| outbound::probe::Config {
+                probes,
+                interval: probe_interval,
+                timeout: probe_timeout,
+            });
+
+        let authority_rewrites = outbound_authority_rewrites?.unwrap_or_default();
+        let retry_suppression_after = outbound_retry_suppression_after?;
+        let retry_max_buffered_bytes =
+            outbound_retry_max_buffered_bytes?.unwrap_or(DEFAULT_OUTBOUND_RETRY_MAX_BUFFERED_BYTES);
+        let retry_after_max = outbound_retry_after_max?.unwrap_or(retry::DEFAULT_MAX_RETRY_AFTER);
+        let retry_unprocessed = outbound_retry_unprocessed?.unwrap_or(false);
+        let retry_backoffs = outbound_retry_backoffs?.unwrap_or_default();
+        let retry_per_try_timeouts = outbound_retry_per_try_timeouts?.unwrap_or_default();
+        let retry_budgets = outbound_retry_budgets?.unwrap_or_default();
+        let egress_policy = outbound_egress_allow?.unwrap_or_default();
+        let proxy_protocol = outbound::tcp::ProxyProtocolTargets::new(
+            outbound_proxy_protocol_ports?.into_iter().flatten(),
+            outbound_proxy_protocol_authorities?.into_iter().flatten(),
+        );
+        let tls_originations = outbound_tls_origination?.unwrap_or_default();
+        let udp = outbound::udp::Config {
+            forwards: outbound_udp_forwards?.unwrap_or_default(),
+            idle_timeout: outbound_udp_idle_timeout?.unwrap_or(DEFAULT_OUTBOUND_UDP_IDLE_TIMEOUT),
+            max_flows: outbound_udp_max_flows?.unwrap_or(DEFAULT_OUTBOUND_UDP_MAX_FLOWS),
+        };
+        let skip_detect = outbound_skip_detect?.unwrap_or_default();
+        let http_hash_policies = outbound_http_hash_policies?.unwrap_or_default();
+        let http_sticky_sessions = outbound_http_sticky_sessions?.unwrap_or_default();
+        let http_hedges = outbound_http_hedges?.unwrap_or_default();
+        let http_faults = outbound_http_faults?.unwrap_or_default();
+        let http_header_policies = outbound_http_header_policies?.unwrap_or_default();
+        let http_route_filters = outbound_http_route_filters?.unwrap_or_default();
+        let tcp_source_affinity =
+            outbound::tcp::SourceAffinity::new(outbound_tcp_source_affinity?.into_iter().flatten());
+        let zone_affinity = match strings.get(ENV_OUTBOUND_ZONE)? {
+            Some(zone) => Some(outbound::balance::ZoneAffinity {
+                zone,
+                local_weight: outbound_zone_local_weight?
+                    .unwrap_or(DEFAULT_OUTBOUND_ZONE_LOCAL_WEIGHT),
+                spillover_threshold: outbound_zone_spillover_threshold?
+                    .unwrap_or(DEFAULT_OUTBOUND_ZONE_SPILLOVER_THRESHOLD),
+            }),
+            None => None,
+        };
+        let ewma = outbound::balance::EwmaConfig {
+            default_rtt: outbound_ewma_default_rtt?.unwrap_or(DEFAULT_OUTBOUND_EWMA_DEFAULT_RTT),
+            decay: outbound_ewma_decay?.unwrap_or(DEFAULT_OUTBOUND_EWMA_DECAY),
+        };
+        let balancer_algorithms = outbound_balancer_algorithms?.unwrap_or_default();
+        let slow_start_window = outbound_slow_start_window?;
+        let outlier_detection = match outbound_outlier_consecutive_failures? {
+            Some(consecutive_failures) if consecutive_failures > 0 => {
+                Some(outbound::balance::outlier::Config {
+                    consecutive_failures,
+                    failure_latency: outbound_outlier_failure_latency?,
+                    base_ejection_time: outbound_outlier_base_ejection_time?
+                        .unwrap_or(DEFAULT_OUTBOUND_OUTLIER_BASE_EJECTION_TIME),
+                    max_ejection_time: outbound_outlier_max_ejection_time?
+                        .unwrap_or(DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTION_TIME),
+                    max_ejection_percent: outbound_outlier_max_ejection_percent?
+                        .unwrap_or(DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTION_PERCENT),
+                })
+            }
+            _ => None,
+        };
+        let health_checks = outbound_health_checks?.unwrap_or_default();
+        let http_circuit_breakers = outbound_http_circuit_breakers?.unwrap_or_default();
+        let http_concurrency_limits = outbound_http_concurrency_limits?.unwrap_or_default();
+        let failovers = outbound_failover?.unwrap_or_default();
+        let multicluster_failovers = {
+            let services = outbound_multicluster_failover?.unwrap_or_default();
+            let remote_label = strings
+                .get(ENV_OUTBOUND_MULTICLUSTER_REMOTE_LABEL)?
+                .unwrap_or_else(|| DEFAULT_OUTBOUND_MULTICLUSTER_REMOTE_LABEL.to_string());
+            outbound::multicluster::Failovers::new(remote_label, services)
+        };
+        let http_mirrors = outbound_http_mirrors?.unwrap_or_default();
+        let http_proxy_authorization = outbound_http_proxy_authorization?;
+        let http_proxy_exempt_networks = outbound_http_proxy_exempt_networks?.unwrap_or_default();
+        let connect_delay =
+            outbound_connect_fallback_delay?.unwrap_or(DEFAULT_OUTBOUND_CONNECT_FALLBACK_DELAY);
+        let http_proxy = outbound_http_proxy?.map(|addr| outbound::tcp::http_proxy::Config {
+            addr,
+            authorization: http_proxy_authorization,
+            exempt_networks: IpMatch::new(http_proxy_exempt_networks),
+            connect_delay,
+        });
+
         outbound::Config {
             ingress_mode,
             allow_discovery: AddrMatch::new(dst_profile_suffixes.clone(), dst_profile_networks),
@@ -463,6 +1750,41 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 detect_protocol_timeout,
             },
             inbound_ips,
+            probe,
+            authority_rewrites,
+            retry_suppression_after,
+            retry_max_buffered_bytes,
+            retry_after_max,
+            retry_unprocessed,
+            retry_backoffs,
+            retry_per_try_timeouts,
+            retry_budgets,
+            egress_policy,
+            proxy_protocol,
+            tls_originations,
+            http_proxy,
+            connect_mark: supported_socket_mark(outbound_connect_mark?),
+            tcp_bandwidth_limits: outbound_tcp_bandwidth_limits?.unwrap_or_default(),
+            skip_detect,
+            udp,
+            http_hash_policies,
+            http_sticky_sessions,
+            http_hedges,
+            http_faults,
+            http_header_policies,
+            http_route_filters,
+            tcp_source_affinity,
+            ewma,
+            balancer_algorithms,
+            zone_affinity,
+            slow_start_window,
+            outlier_detection,
+            http_circuit_breakers,
+            http_concurrency_limits,
+            health_checks,
+            failovers,
+            multicluster_failovers,
+            http_mirrors,
         }
     };
 
@@ -475,17 +1797,29 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             inbound_listener_addr?
                 .unwrap_or_else(|| parse_socket_addr(DEFAULT_INBOUND_LISTEN_ADDR).unwrap()),
         );
-        let keepalive = Keepalive(inbound_accept_keepalive?);
+        let keepalive = inbound_accept_keepalive?.unwrap_or_default();
+        let (backlog, accept_limits) = inbound_accept_limits?;
         let server = ServerConfig {
             addr,
             keepalive,
-            h2_settings,
+            h2_settings: h2::Settings {
+                max_connection_age: inbound_h2_max_connection_age?,
+                max_connection_idle: inbound_h2_max_connection_idle?,
+                max_connection_age_grace: inbound_h2_max_connection_age_grace?,
+                ..inbound_accept_h2_settings?
+            },
+            proxy_protocol: inbound_proxy_protocol_networks?
+                .into_iter()
+                .flatten()
+                .collect(),
+            backlog,
+            accept_limits,
         };
         let cache_max_idle_age =
             inbound_cache_max_idle_age?.unwrap_or(DEFAULT_INBOUND_ROUTER_MAX_IDLE_AGE);
         let max_idle =
             inbound_max_idle_per_endpoint?.unwrap_or(DEFAULT_INBOUND_MAX_IDLE_CONNS_PER_ENDPOINT);
-        let keepalive = Keepalive(inbound_connect_keepalive?);
+        let keepalive = inbound_connect_keepalive?.unwrap_or_default();
         let connect = ConnectConfig {
             keepalive,
             timeout: inbound_connect_timeout?.unwrap_or(DEFAULT_INBOUND_CONNECT_TIMEOUT),
@@ -494,7 +1828,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 INBOUND_CONNECT_BASE,
                 DEFAULT_INBOUND_CONNECT_BACKOFF,
             )?,
-            h2_settings,
+            h2_settings: inbound_connect_h2_settings?,
             h1_settings: h1::PoolSettings {
                 max_idle,
                 idle_timeout: cache_max_idle_age,
@@ -506,33 +1840,44 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         let dispatch_timeout =
             inbound_dispatch_timeout?.unwrap_or(DEFAULT_INBOUND_DISPATCH_TIMEOUT);
 
+        let cluster_nets = parse(strings, ENV_POLICY_CLUSTER_NETWORKS, parse_networks)?
+            .unwrap_or_else(|| {
+                info!(
+                    "{} not set; cluster-scoped modes are unsupported",
+                    ENV_POLICY_CLUSTER_NETWORKS
+                );
+                Default::default()
+            });
+
+        let origin_networks = {
+            let node_nets =
+                parse(strings, ENV_INBOUND_NODE_NETWORKS, parse_networks)?.unwrap_or_default();
+            OriginNetworks::new(node_nets, cluster_nets.clone())
+        };
+
+        // We always configure a default policy. This policy applies when no other policy is
+        // configured, especially when the port is not documented in via `ENV_INBOUND_PORTS`.
+        let default = parse(strings, ENV_INBOUND_DEFAULT_POLICY, |s| {
+            parse_default_policy(s, cluster_nets.clone(), detect_protocol_timeout)
+        })?
+        .unwrap_or_else(|| {
+            warn!(
+                "{} was not set; using `all-unauthenticated`",
+                ENV_INBOUND_DEFAULT_POLICY
+            );
+            policy::defaults::all_unauthenticated(detect_protocol_timeout).into()
+        });
+
+        let listeners = parse(strings, ENV_INBOUND_LISTENERS, |s| {
+            parse_inbound_listeners(s, &server, &cluster_nets, detect_protocol_timeout, &default)
+        })?
+        .unwrap_or_default();
+
         // Ensure that connections that directly target the inbound port are secured (unless
         // identity is disabled).
         let policy = {
             let inbound_port = server.addr.as_ref().port();
 
-            let cluster_nets = parse(strings, ENV_POLICY_CLUSTER_NETWORKS, parse_networks)?
-                .unwrap_or_else(|| {
-                    info!(
-                        "{} not set; cluster-scoped modes are unsupported",
-                        ENV_POLICY_CLUSTER_NETWORKS
-                    );
-                    Default::default()
-                });
-
-            // We always configure a default policy. This policy applies when no other policy is
-            // configured, especially when the port is not documented in via `ENV_INBOUND_PORTS`.
-            let default = parse(strings, ENV_INBOUND_DEFAULT_POLICY, |s| {
-                parse_default_policy(s, cluster_nets, detect_protocol_timeout)
-            })?
-            .unwrap_or_else(|| {
-                warn!(
-                    "{} was not set; using `all-unauthenticated`",
-                    ENV_INBOUND_DEFAULT_POLICY
-                );
-                policy::defaults::all_unauthenticated(detect_protocol_timeout).into()
-            });
-
             match parse_control_addr(strings, ENV_POLICY_SVC_BASE, id_disabled)? {
                 Some(addr) => {
                     // If the inbound is proxy is configured to discover policies, then load the set
@@ -564,22 +1909,39 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
                     let control = {
                         let connect = if addr.addr.is_loopback() {
-                            connect.clone()
+                            control_connect(
+                                &connect,
+                                control_connect_keepalive,
+                                control_connect_h2_settings,
+                            )
                         } else {
-                            outbound.proxy.connect.clone()
+                            control_connect(
+                                &outbound.proxy.connect,
+                                control_connect_keepalive,
+                                control_connect_h2_settings,
+                            )
                         };
                         ControlConfig {
                             addr,
                             connect,
+                            connect_mark: outbound.connect_mark,
                             buffer_capacity,
                         }
                     };
 
+                    let authorization_routes = parse(
+                        strings,
+                        ENV_POLICY_AUTHORIZATION_ROUTES,
+                        parse_authorization_routes,
+                    )?
+                    .unwrap_or_default();
+
                     inbound::policy::Config::Discover {
                         default,
                         ports,
                         workload,
                         control,
+                        authorization_routes,
                     }
                 }
 
@@ -700,28 +2062,167 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             policy,
             profile_idle_timeout: dst_profile_idle_timeout?
                 .unwrap_or(DEFAULT_DESTINATION_PROFILE_IDLE_TIMEOUT),
+            origin_networks,
+            client_cert_header: parse(strings, ENV_INBOUND_CLIENT_CERT_HEADER, parse_header_name)?,
+            duplicate_headers: parse(
+                strings,
+                ENV_INBOUND_HTTP_DUPLICATE_HEADERS,
+                parse_duplicate_headers,
+            )?
+            .unwrap_or_default(),
+            sni_routes: parse(strings, ENV_INBOUND_SNI_ROUTES, parse_sni_routes)?
+                .unwrap_or_default(),
+            request_timeouts: parse(
+                strings,
+                ENV_INBOUND_REQUEST_TIMEOUTS,
+                parse_request_timeouts,
+            )?
+            .unwrap_or_default(),
+            rate_limits: parse(strings, ENV_INBOUND_HTTP_RATE_LIMITS, parse_rate_limits)?
+                .unwrap_or_default(),
+            tcp_bandwidth_limits: parse(
+                strings,
+                ENV_INBOUND_TCP_BANDWIDTH_LIMITS,
+                parse_bandwidth_limits,
+            )?
+            .unwrap_or_default(),
+            cors: parse(strings, ENV_INBOUND_HTTP_CORS, parse_cors)?.unwrap_or_default(),
+            http_header_policies: parse(
+                strings,
+                ENV_INBOUND_HTTP_HEADER_POLICIES,
+                parse_header_policies,
+            )?
+            .unwrap_or_default(),
+            http_route_filters: parse(
+                strings,
+                ENV_INBOUND_HTTP_ROUTE_FILTERS,
+                parse_route_filters,
+            )?
+            .unwrap_or_default(),
+            jwt: match strings.get(ENV_INBOUND_JWT_JWKS)? {
+                Some(jwks) => Some(inbound::JwtConfig {
+                    jwks: PathBuf::from(jwks),
+                    refresh: parse(strings, ENV_INBOUND_JWT_JWKS_REFRESH, parse_duration)?
+                        .unwrap_or(DEFAULT_INBOUND_JWT_JWKS_REFRESH),
+                    issuer: strings.get(ENV_INBOUND_JWT_ISSUER)?,
+                    audiences: parse(strings, ENV_INBOUND_JWT_AUDIENCES, parse_routes)?
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect(),
+                    claim_headers: parse(
+                        strings,
+                        ENV_INBOUND_JWT_CLAIM_HEADERS,
+                        parse_claim_headers,
+                    )?
+                    .unwrap_or_default(),
+                    servers: parse(strings, ENV_INBOUND_JWT_SERVERS, parse_routes)?
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect(),
+                }),
+                None => None,
+            },
+            authz_audit_servers: parse(strings, ENV_INBOUND_AUTHZ_AUDIT_SERVERS, parse_routes)?
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+            authz_deny_networks: parse(
+                strings,
+                ENV_INBOUND_AUTHZ_DENY_NETWORKS,
+                parse_deny_networks,
+            )?
+            .unwrap_or_default(),
+            listeners,
+        }
+    };
+
+    let xds = match xds_addr? {
+        None => None,
+        Some(addr) => {
+            let connect = if addr.addr.is_loopback() {
+                control_connect(
+                    &inbound.proxy.connect,
+                    control_connect_keepalive,
+                    control_connect_h2_settings,
+                )
+            } else {
+                control_connect(
+                    &outbound.proxy.connect,
+                    control_connect_keepalive,
+                    control_connect_h2_settings,
+                )
+            };
+            let suffixes = match xds_suffixes? {
+                Some(suffixes) => suffixes.into_iter().collect(),
+                None => vec![dns::Suffix::Root],
+            };
+            Some(super::dst::XdsConfig {
+                control: ControlConfig {
+                    addr,
+                    connect,
+                    connect_mark: outbound.connect_mark,
+                    buffer_capacity,
+                },
+                suffixes,
+                node_id: xds_node_id?.unwrap_or_default(),
+                node_cluster: xds_node_cluster?.unwrap_or_default(),
+            })
         }
     };
 
     let dst = {
         let addr = dst_addr?.ok_or(EnvError::NoDestinationAddress)?;
         let connect = if addr.addr.is_loopback() {
-            inbound.proxy.connect.clone()
+            control_connect(
+                &inbound.proxy.connect,
+                control_connect_keepalive,
+                control_connect_h2_settings,
+            )
         } else {
-            outbound.proxy.connect.clone()
+            control_connect(
+                &outbound.proxy.connect,
+                control_connect_keepalive,
+                control_connect_h2_settings,
+            )
         };
         super::dst::Config {
             context: dst_token?.unwrap_or_default(),
+            static_endpoints: dst_static_endpoints?.unwrap_or_default(),
+            xds,
+            resolution_cache_max_age: dst_resolution_cache_max_age?
+                .unwrap_or(DEFAULT_DESTINATION_RESOLUTION_CACHE_MAX_AGE),
+            suffix_tuning: dst_suffix_tuning?.unwrap_or_default(),
             control: ControlConfig {
                 addr,
                 connect,
+                connect_mark: outbound.connect_mark,
                 buffer_capacity,
             },
         }
     };
 
+    let admin_uds_path = admin_uds_path?;
+    let admin_tcp_disabled = admin_tcp_disabled?.unwrap_or(false);
+    if admin_tcp_disabled && admin_uds_path.is_none() {
+        error!(
+            "{} requires {} to be set",
+            ENV_ADMIN_TCP_DISABLED, ENV_ADMIN_UDS_PATH
+        );
+        return Err(EnvError::InvalidEnvVar);
+    }
+
+    let readiness_control_plane_timeout =
+        readiness_control_plane_timeout?.unwrap_or(DEFAULT_READINESS_CONTROL_PLANE_TIMEOUT);
+    let control_plane_readiness_timeout = if readiness_require_control_plane?.unwrap_or(false) {
+        Some(readiness_control_plane_timeout)
+    } else {
+        None
+    };
+
     let admin = super::admin::Config {
         metrics_retain_idle: metrics_retain_idle?.unwrap_or(DEFAULT_METRICS_RETAIN_IDLE),
+        control_plane_readiness_timeout,
+        transport_aggregation: transport_aggregation?.unwrap_or_default(),
         server: ServerConfig {
             addr: ListenAddr(
                 admin_listener_addr?
@@ -729,12 +2230,41 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             ),
             keepalive: inbound.proxy.server.keepalive,
             h2_settings,
+            proxy_protocol: admin_proxy_protocol_networks?
+                .into_iter()
+                .flatten()
+                .collect(),
+            backlog: Default::default(),
+            accept_limits: Default::default(),
         },
+        tcp_enabled: !admin_tcp_disabled,
+        uds_path: admin_uds_path,
+        origin_networks: inbound.origin_networks.clone(),
+        permitted_client_ids: parse(strings, ENV_ADMIN_PERMITTED_IDENTITIES, parse_identities)?
+            .unwrap_or_default(),
+        shutdown_token: strings
+            .get(ENV_ADMIN_SHUTDOWN_TOKEN)?
+            .filter(|t| !t.is_empty()),
+        policy: parse(strings, ENV_ADMIN_POLICY, |s| {
+            parse_admin_policy(
+                s,
+                &inbound.origin_networks,
+                inbound.proxy.detect_protocol_timeout,
+            )
+        })?
+        .unwrap_or_else(|| {
+            policy::defaults::admin(
+                inbound.origin_networks.node_local().iter().copied(),
+                inbound.proxy.detect_protocol_timeout,
+            )
+        }),
     };
 
     let dns = dns::Config {
         min_ttl: dns_min_ttl?,
         max_ttl: dns_max_ttl?,
+        negative_ttl: dns_negative_ttl?.unwrap_or(dns::DEFAULT_NEGATIVE_TTL),
+        ip_preference: dns_ip_preference?.unwrap_or_default(),
         resolv_conf_path: resolv_conf_path?
             .unwrap_or_else(|| DEFAULT_RESOLV_CONF.into())
             .into(),
@@ -744,9 +2274,17 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         None => oc_collector::Config::Disabled,
         Some(addr) => {
             let connect = if addr.addr.is_loopback() {
-                inbound.proxy.connect.clone()
+                control_connect(
+                    &inbound.proxy.connect,
+                    control_connect_keepalive,
+                    control_connect_h2_settings,
+                )
             } else {
-                outbound.proxy.connect.clone()
+                control_connect(
+                    &outbound.proxy.connect,
+                    control_connect_keepalive,
+                    control_connect_h2_settings,
+                )
             };
 
             let attributes = oc_attributes_file_path
@@ -759,15 +2297,108 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             oc_collector::Config::Enabled(Box::new(oc_collector::EnabledConfig {
                 attributes,
                 hostname: hostname?,
+                protocol: trace_protocol?.unwrap_or_default(),
+                precedence: trace_precedence?.unwrap_or_default(),
                 control: ControlConfig {
                     addr,
                     connect,
+                    connect_mark: outbound.connect_mark,
                     buffer_capacity: 10,
                 },
             }))
         }
     };
 
+    let access_log_buffer_capacity = access_log_buffer_capacity?.unwrap_or(DEFAULT_BUFFER_CAPACITY);
+    let access_log_format = access_log_format?.unwrap_or_default();
+    let access_log_query = access_log_query?.unwrap_or(false);
+    let access_log_filter = access_log::Filter {
+        status_classes: access_log_status_classes?.unwrap_or_default(),
+        routes: access_log_routes?.unwrap_or_default(),
+        authorities: access_log_authorities?.into_iter().flatten().collect(),
+        success_sample_interval: access_log_success_sample_interval?,
+    };
+    let access_log = access_log_destination?.map(|destination| access_log::Config {
+        destination,
+        format: access_log_format,
+        filter: access_log_filter,
+        buffer_capacity: access_log_buffer_capacity,
+        include_query: access_log_query,
+    });
+
+    let rate_limit = match rate_limit_addr? {
+        None => None,
+        Some(addr) => {
+            let connect = if addr.addr.is_loopback() {
+                control_connect(
+                    &inbound.proxy.connect,
+                    control_connect_keepalive,
+                    control_connect_h2_settings,
+                )
+            } else {
+                control_connect(
+                    &outbound.proxy.connect,
+                    control_connect_keepalive,
+                    control_connect_h2_settings,
+                )
+            };
+            let descriptor = match rate_limit_descriptor? {
+                Some(descriptor) => descriptor,
+                None => parse_rate_limit_descriptor(DEFAULT_RATELIMIT_DESCRIPTOR)
+                    .expect("default descriptor must be valid"),
+            };
+            Some(rls::Config {
+                control: ControlConfig {
+                    addr,
+                    connect,
+                    connect_mark: outbound.connect_mark,
+                    buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+                },
+                domain: rate_limit_domain?.unwrap_or_else(|| DEFAULT_RATELIMIT_DOMAIN.to_string()),
+                descriptor,
+                failure_mode: rate_limit_failure_mode?.unwrap_or(rls::FailureMode::Open),
+                timeout: rate_limit_timeout?.unwrap_or(DEFAULT_RATELIMIT_TIMEOUT),
+                directions: rate_limit_directions?
+                    .unwrap_or_else(|| Some(Direction::In).into_iter().collect()),
+            })
+        }
+    };
+
+    let ext_authz = match ext_authz_addr? {
+        None => None,
+        Some(addr) => {
+            let connect = if addr.addr.is_loopback() {
+                control_connect(
+                    &inbound.proxy.connect,
+                    control_connect_keepalive,
+                    control_connect_h2_settings,
+                )
+            } else {
+                control_connect(
+                    &outbound.proxy.connect,
+                    control_connect_keepalive,
+                    control_connect_h2_settings,
+                )
+            };
+            Some(ext_authz::Config {
+                control: ControlConfig {
+                    addr,
+                    connect,
+                    connect_mark: outbound.connect_mark,
+                    buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+                },
+                protocol: ext_authz_protocol?.unwrap_or(ext_authz::Protocol::Grpc),
+                failure_mode: ext_authz_failure_mode?.unwrap_or(ext_authz::FailureMode::Closed),
+                headers: ext_authz_headers?.unwrap_or_default(),
+                timeout: ext_authz_timeout?.unwrap_or(DEFAULT_INBOUND_EXT_AUTHZ_TIMEOUT),
+                cache_ttl: ext_authz_cache_ttl?.unwrap_or_default(),
+                cache_capacity: ext_authz_cache_capacity?
+                    .unwrap_or(DEFAULT_INBOUND_EXT_AUTHZ_CACHE_CAPACITY),
+                servers: ext_authz_servers?.unwrap_or_default().into_iter().collect(),
+            })
+        }
+    };
+
     let tap = tap?
         .map(|(addr, ids)| super::tap::Config::Enabled {
             permitted_client_ids: ids,
@@ -775,26 +2406,41 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 addr: ListenAddr(addr),
                 keepalive: inbound.proxy.server.keepalive,
                 h2_settings,
+                proxy_protocol: Default::default(),
+                backlog: Default::default(),
+                accept_limits: Default::default(),
             },
         })
         .unwrap_or(super::tap::Config::Disabled);
 
     let identity = identity_config?
-        .map(|(addr, certify)| {
-            // If the address doesn't have a server identity, then we're on localhost.
-            let connect = if addr.addr.is_loopback() {
-                inbound.proxy.connect.clone()
-            } else {
-                outbound.proxy.connect.clone()
-            };
-            identity::Config::Enabled {
-                certify,
-                control: ControlConfig {
-                    addr,
-                    connect,
-                    buffer_capacity: 1,
-                },
+        .map(|config| match config {
+            IdentityConfig::Control(addr, certify) => {
+                // If the address doesn't have a server identity, then we're on localhost.
+                let connect = if addr.addr.is_loopback() {
+                    control_connect(
+                        &inbound.proxy.connect,
+                        control_connect_keepalive,
+                        control_connect_h2_settings,
+                    )
+                } else {
+                    control_connect(
+                        &outbound.proxy.connect,
+                        control_connect_keepalive,
+                        control_connect_h2_settings,
+                    )
+                };
+                identity::Config::Enabled {
+                    certify,
+                    control: ControlConfig {
+                        addr,
+                        connect,
+                        connect_mark: outbound.connect_mark,
+                        buffer_capacity: 1,
+                    },
+                }
             }
+            IdentityConfig::Files(files) => identity::Config::Files(files),
         })
         .unwrap_or(identity::Config::Disabled);
 
@@ -804,10 +2450,14 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         dst,
         tap,
         oc_collector,
+        access_log,
+        rate_limit,
+        ext_authz,
         identity,
         outbound,
         gateway,
         inbound,
+        orig_dst_mode: orig_dst_mode?.unwrap_or_default(),
     })
 }
 
@@ -824,6 +2474,17 @@ fn oc_trace_attributes(oc_attributes_file_path: String) -> HashMap<String, Strin
     }
 }
 
+fn parse_static_endpoints(path: &str) -> Result<StaticEndpoints, ParseError> {
+    let s = fs::read_to_string(path).map_err(|error| {
+        error!(%path, %error, "Failed to read static endpoints file");
+        ParseError::InvalidStaticEndpoints(path.to_string())
+    })?;
+    StaticEndpoints::from_yaml(&s).map_err(|error| {
+        error!(%path, %error, "Invalid static endpoints file");
+        ParseError::InvalidStaticEndpoints(error.to_string())
+    })
+}
+
 fn convert_attributes_string_to_map(attributes: String) -> HashMap<String, String> {
     attributes
         .lines()
@@ -871,16 +2532,61 @@ impl Env {
 ///   ENV_TAP_SVC_NAME is set.
 /// - If identity is enabled, the status of tap is determined by
 ///   ENV_TAP_SVC_NAME.
-fn parse_tap_config(
-    strings: &dyn Strings,
-    id_disabled: bool,
-) -> Result<Option<(SocketAddr, HashSet<tls::server::ClientId>)>, EnvError> {
-    let tap_identity = parse(strings, ENV_TAP_SVC_NAME, parse_identity)?;
-    if id_disabled {
-        if tap_identity.is_some() {
-            warn!(
-                "{} should not be set if identity is disabled; continuing with tap disabled",
-                ENV_TAP_SVC_NAME
+fn parse_suffix_tuning(list: &str) -> Result<Vec<super::dst::SuffixTuning>, ParseError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            let invalid = || {
+                error!(tuning = %t, "Invalid discovery tuning");
+                ParseError::InvalidSuffixTuning(t.to_string())
+            };
+            let mut parts = t.splitn(2, '=');
+            let (suffix, spec) = match (parts.next(), parts.next()) {
+                (Some(suffix), Some(spec)) => (suffix.trim(), spec.trim()),
+                _ => return Err(invalid()),
+            };
+            let suffix = parse_dns_suffix(suffix).map_err(|_| invalid())?;
+
+            let ExponentialBackoff {
+                mut min,
+                mut max,
+                mut jitter,
+            } = DEFAULT_OUTBOUND_CONNECT_BACKOFF;
+            let mut profile_timeout = None;
+            for setting in spec.split(';').map(str::trim) {
+                let mut kv = setting.splitn(2, '=');
+                match (kv.next(), kv.next()) {
+                    (Some("min"), Some(v)) => min = parse_duration(v).map_err(|_| invalid())?,
+                    (Some("max"), Some(v)) => max = parse_duration(v).map_err(|_| invalid())?,
+                    (Some("jitter"), Some(v)) => jitter = parse_number(v).map_err(|_| invalid())?,
+                    (Some("profile-timeout"), Some(v)) => {
+                        profile_timeout = Some(parse_duration(v).map_err(|_| invalid())?)
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            let backoff = ExponentialBackoff::new(min, max, jitter).map_err(|_| invalid())?;
+
+            Ok(super::dst::SuffixTuning {
+                suffix,
+                backoff,
+                profile_timeout,
+            })
+        })
+        .collect()
+}
+
+fn parse_tap_config(
+    strings: &dyn Strings,
+    id_disabled: bool,
+) -> Result<Option<(SocketAddr, HashSet<tls::server::ClientId>)>, EnvError> {
+    let tap_identity = parse(strings, ENV_TAP_SVC_NAME, parse_identity)?;
+    if id_disabled {
+        if tap_identity.is_some() {
+            warn!(
+                "{} should not be set if identity is disabled; continuing with tap disabled",
+                ENV_TAP_SVC_NAME
             );
         }
     } else {
@@ -908,6 +2614,13 @@ where
     s.parse().map_err(Into::into)
 }
 
+fn parse_percent(s: &str) -> Result<u32, ParseError> {
+    match parse_number::<u32>(s)? {
+        percent if percent <= 100 => Ok(percent),
+        _ => Err(ParseError::NotAPercent(s.to_string())),
+    }
+}
+
 fn parse_duration(s: &str) -> Result<Duration, ParseError> {
     use regex::Regex;
 
@@ -927,6 +2640,14 @@ fn parse_duration(s: &str) -> Result<Duration, ParseError> {
     }
 }
 
+fn parse_nonzero_duration(s: &str) -> Result<Duration, ParseError> {
+    let d = parse_duration(s)?;
+    if d == Duration::from_secs(0) {
+        return Err(ParseError::ZeroDuration);
+    }
+    Ok(d)
+}
+
 fn parse_socket_addr(s: &str) -> Result<SocketAddr, ParseError> {
     match parse_addr(s)? {
         Addr::Socket(a) => Ok(a),
@@ -985,6 +2706,17 @@ where
     }
 }
 
+fn parse_identities(list: &str) -> Result<HashSet<tls::server::ClientId>, ParseError> {
+    let mut ids = HashSet::new();
+    for input in list.split(',') {
+        let input = input.trim();
+        if !input.is_empty() {
+            ids.insert(tls::ClientId(parse_identity(input)?));
+        }
+    }
+    Ok(ids)
+}
+
 #[allow(dead_code)]
 fn parse_deprecated<T, Parse>(
     strings: &dyn Strings,
@@ -1044,372 +2776,3438 @@ fn parse_networks(list: &str) -> Result<HashSet<IpNet>, ParseError> {
     Ok(nets)
 }
 
-fn parse_default_policy(
-    s: &str,
-    cluster_nets: HashSet<IpNet>,
-    detect_timeout: Duration,
-) -> Result<policy::DefaultPolicy, ParseError> {
-    match s {
-        "deny" => Ok(policy::DefaultPolicy::Deny),
-        "all-authenticated" => Ok(policy::defaults::all_authenticated(detect_timeout).into()),
-        "all-unauthenticated" => Ok(policy::defaults::all_unauthenticated(detect_timeout).into()),
+fn parse_deny_networks(list: &str) -> Result<policy::DenyNetworks, ParseError> {
+    let mut rules = Vec::new();
+    for input in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let mut parts = input.rsplitn(2, '=');
+        let net = parts.next().unwrap_or_default().trim();
+        let server = match parts.next().map(str::trim) {
+            Some("") => {
+                error!(%input, "Invalid deny rule");
+                return Err(ParseError::NotANetwork);
+            }
+            server => server.map(String::from),
+        };
+        let net = policy::Network::from_str(net).map_err(|error| {
+            error!(%input, %error, "Invalid network");
+            ParseError::NotANetwork
+        })?;
+        rules.push((server, net));
+    }
+    Ok(policy::DenyNetworks::new(rules))
+}
 
-        // If cluster networks are configured, support cluster-scoped default policies.
-        name if cluster_nets.is_empty() => Err(ParseError::InvalidPortPolicy(name.to_string())),
-        "cluster-authenticated" => {
-            Ok(policy::defaults::cluster_authenticated(cluster_nets, detect_timeout).into())
-        }
-        "cluster-unauthenticated" => {
-            Ok(policy::defaults::cluster_unauthenticated(cluster_nets, detect_timeout).into())
+fn parse_probes(list: &str) -> Result<Vec<outbound::probe::Probe>, ParseError> {
+    let mut probes = Vec::new();
+    for input in list.split(',') {
+        let input = input.trim();
+        if !input.is_empty() {
+            let probe = input.parse().map_err(|error| {
+                error!(%input, %error, "Invalid probe");
+                ParseError::from(error)
+            })?;
+            probes.push(probe);
         }
+    }
+    Ok(probes)
+}
 
-        name => Err(ParseError::InvalidPortPolicy(name.to_string())),
+/// Parses an HTTP proxy address, optionally prefixed with `http://`.
+fn parse_http_proxy(s: &str) -> Result<Addr, ParseError> {
+    let s = s.trim();
+    let s = s.strip_prefix("http://").unwrap_or(s);
+    parse_addr(s.trim_end_matches('/'))
+}
+
+/// Parses a `proxy-authorization` header value, rejecting line breaks so that
+/// credentials cannot inject headers into `CONNECT` requests.
+fn parse_http_proxy_authorization(s: &str) -> Result<Arc<str>, ParseError> {
+    if s.contains(|c| c == '\r' || c == '\n') {
+        error!("Invalid HTTP proxy authorization");
+        return Err(ParseError::InvalidHttpProxyAuthorization);
     }
+    Ok(s.into())
 }
-pub fn parse_backoff<S: Strings>(
-    strings: &S,
-    base: &str,
-    default: ExponentialBackoff,
-) -> Result<ExponentialBackoff, EnvError> {
-    let min_env = format!("LINKERD2_PROXY_{}_EXP_BACKOFF_MIN", base);
-    let min = parse(strings, &min_env, parse_duration);
-    let max_env = format!("LINKERD2_PROXY_{}_EXP_BACKOFF_MAX", base);
-    let max = parse(strings, &max_env, parse_duration);
-    let jitter_env = format!("LINKERD2_PROXY_{}_EXP_BACKOFF_JITTER", base);
-    let jitter = parse(strings, &jitter_env, parse_number::<f64>);
 
-    match (min?, max?, jitter?) {
-        (None, None, None) => Ok(default),
-        (Some(min), Some(max), jitter) => {
-            ExponentialBackoff::new(min, max, jitter.unwrap_or_default()).map_err(|error| {
-                error!(message="Invalid backoff", %error, %min_env, ?min, %max_env, ?max, %jitter_env, ?jitter);
-                EnvError::InvalidEnvVar
-            })
-        }
-        _ => {
-            error!("You need to specify either all of {} {} {} or none of them to use the default backoff", min_env, max_env,jitter_env );
-            Err(EnvError::InvalidEnvVar)
-        }
+fn parse_orig_dst_mode(s: &str) -> Result<transport::orig_dst::Mode, ParseError> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "redirect" => Ok(transport::orig_dst::Mode::Redirect),
+        "tproxy" => Ok(transport::orig_dst::Mode::Tproxy),
+        _ => Err(ParseError::NotAnOrigDstMode(s.to_string())),
     }
 }
 
-pub fn parse_control_addr<S: Strings>(
-    strings: &S,
-    base: &str,
-    id_disabled: bool,
-) -> Result<Option<ControlAddr>, EnvError> {
-    let a = parse(strings, &format!("{}_ADDR", base), parse_addr)?;
-    let n = parse(strings, &format!("{}_NAME", base), parse_identity)?;
-    match (a, n) {
-        (None, None) => Ok(None),
-        (Some(ref addr), _) if addr.is_loopback() => Ok(Some(ControlAddr {
-            addr: addr.clone(),
-            identity: Conditional::None(tls::NoClientTls::Loopback),
-        })),
-        (Some(addr), None) if id_disabled => Ok(Some(ControlAddr {
-            addr,
-            identity: Conditional::None(tls::NoClientTls::Loopback),
-        })),
-        (Some(addr), Some(name)) => Ok(Some(ControlAddr {
-            addr,
-            identity: Conditional::Some(tls::ServerId(name).into()),
-        })),
-        _ => {
-            error!("{}_ADDR and {}_NAME must be specified together", base, base);
-            Err(EnvError::InvalidEnvVar)
-        }
+/// Parses a decimal or `0x`-prefixed hexadecimal socket mark.
+fn parse_socket_mark(s: &str) -> Result<u32, ParseError> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).map_err(Into::into),
+        None => parse_number(s),
     }
 }
 
-pub fn parse_identity_config<S: Strings>(
-    strings: &S,
-) -> Result<Option<(ControlAddr, identity::certify::Config)>, EnvError> {
-    let control = parse_control_addr(strings, ENV_IDENTITY_SVC_BASE, false);
-    let ta = parse(strings, ENV_IDENTITY_TRUST_ANCHORS, |s| {
-        identity::TrustAnchors::from_pem(s).ok_or(ParseError::InvalidTrustAnchors)
-    });
-    let dir = parse(strings, ENV_IDENTITY_DIR, |ref s| Ok(PathBuf::from(s)));
-    let tok = parse(strings, ENV_IDENTITY_TOKEN_FILE, |ref s| {
-        identity::TokenSource::if_nonempty_file(s.to_string()).map_err(|e| {
-            error!("Could not read {}: {}", ENV_IDENTITY_TOKEN_FILE, e);
-            ParseError::InvalidTokenSource
+/// Socket marks are only supported on Linux; elsewhere a configured mark is
+/// ignored.
+fn supported_socket_mark(mark: Option<u32>) -> Option<u32> {
+    if cfg!(target_os = "linux") {
+        return mark;
+    }
+    if mark.is_some() {
+        warn!(
+            "{} is only supported on Linux; ignoring it",
+            ENV_OUTBOUND_CONNECT_MARK
+        );
+    }
+    None
+}
+
+fn parse_name_addrs(list: &str) -> Result<Vec<NameAddr>, ParseError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            NameAddr::from_str(s).map_err(|e| {
+                error!("Not a valid authority: {}", s);
+                ParseError::AddrError(e)
+            })
         })
-    });
-    let li = parse(strings, ENV_IDENTITY_IDENTITY_LOCAL_NAME, parse_identity);
-    let min_refresh = parse(strings, ENV_IDENTITY_MIN_REFRESH, parse_duration);
-    let max_refresh = parse(strings, ENV_IDENTITY_MAX_REFRESH, parse_duration);
+        .collect()
+}
 
-    let disabled = strings
-        .get(ENV_IDENTITY_DISABLED)?
-        .map(|d| !d.is_empty())
-        .unwrap_or(false);
+fn parse_authority_rewrites(list: &str) -> Result<outbound::http::AuthorityRewrites, ParseError> {
+    list.parse().map_err(|error| {
+        error!(%error, "Invalid authority rewrites");
+        ParseError::from(error)
+    })
+}
 
-    match (
-        disabled,
-        control?,
-        ta?,
-        dir?,
-        li?,
-        tok?,
-        min_refresh?,
-        max_refresh?,
-    ) {
-        (disabled, None, None, None, None, None, None, None) => {
-            if !disabled {
-                error!(
-                    "{} must be set or identity configuration must be specified.",
-                    ENV_IDENTITY_DISABLED
-                );
-                Err(EnvError::InvalidEnvVar)
-            } else {
-                Ok(None)
-            }
-        }
-        (
-            false,
-            Some(control),
-            Some(trust_anchors),
-            Some(dir),
-            Some(local_name),
-            Some(token),
-            min_refresh,
-            max_refresh,
-        ) => {
-            let key = {
-                let mut p = dir.clone();
-                p.push("key");
-                p.set_extension("p8");
+fn parse_sni_routes(list: &str) -> Result<inbound::sni::SniRoutes, ParseError> {
+    list.parse().map_err(|error| {
+        error!(%error, "Invalid SNI routes");
+        ParseError::from(error)
+    })
+}
 
-                fs::read(p)
-                    .map_err(|e| {
-                        error!("Failed to read key: {}", e);
-                        EnvError::InvalidEnvVar
-                    })
-                    .and_then(|b| {
-                        identity::Key::from_pkcs8(&b).map_err(|e| {
-                            error!("Invalid key: {}", e);
-                            EnvError::InvalidEnvVar
-                        })
-                    })
+fn parse_request_timeouts(list: &str) -> Result<inbound::RequestTimeouts, ParseError> {
+    let timeouts = list
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            let invalid = || {
+                error!(timeout = %t, "Invalid request timeout");
+                ParseError::InvalidRequestTimeout(t.to_string())
             };
+            let mut parts = t.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(port), Some(timeout)) => {
+                    let port = port.trim().parse::<u16>().map_err(|_| invalid())?;
+                    let timeout = parse_nonzero_duration(timeout.trim()).map_err(|_| invalid())?;
+                    Ok((port, timeout))
+                }
+                _ => Err(invalid()),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(inbound::RequestTimeouts::new(timeouts))
+}
 
-            let csr = {
-                let mut p = dir;
-                p.push("csr");
-                p.set_extension("der");
-
-                fs::read(p)
-                    .map_err(|e| {
-                        error!("Failed to read Csr: {}", e);
-                        EnvError::InvalidEnvVar
-                    })
-                    .and_then(|b| {
-                        identity::Csr::from_der(b).ok_or_else(|| {
-                            error!("No CSR found");
-                            EnvError::InvalidEnvVar
-                        })
-                    })
-            };
+fn parse_rate_limits(list: &str) -> Result<inbound::RateLimits, ParseError> {
+    let mut servers = Vec::new();
+    let mut routes = Vec::new();
+    for l in list.split(',').map(str::trim).filter(|l| !l.is_empty()) {
+        let invalid = || {
+            error!(limit = %l, "Invalid rate limit");
+            ParseError::InvalidRateLimit(l.to_string())
+        };
+        let mut parts = l.splitn(2, '=');
+        let (target, spec) = match (parts.next(), parts.next()) {
+            (Some(target), Some(spec)) => (target.trim(), spec.trim()),
+            _ => return Err(invalid()),
+        };
 
-            Ok(Some((
-                control,
-                identity::certify::Config {
-                    local_id: tls::LocalId(local_name),
-                    token,
-                    trust_anchors,
-                    csr: csr?,
-                    key: key?,
-                    min_refresh: min_refresh.unwrap_or(DEFAULT_IDENTITY_MIN_REFRESH),
-                    max_refresh: max_refresh.unwrap_or(DEFAULT_IDENTITY_MAX_REFRESH),
-                },
-            )))
-        }
-        (disabled, addr, trust_anchors, end_entity_dir, local_id, token, _minr, _maxr) => {
-            if disabled {
-                error!(
-                    "{} must be unset when other identity variables are set.",
-                    ENV_IDENTITY_DISABLED,
-                );
-            }
-            let s = format!("{0}_ADDR and {0}_NAME", ENV_IDENTITY_SVC_BASE);
-            let svc_env: &str = s.as_str();
-            for (unset, name) in &[
-                (addr.is_none(), svc_env),
-                (trust_anchors.is_none(), ENV_IDENTITY_TRUST_ANCHORS),
-                (end_entity_dir.is_none(), ENV_IDENTITY_DIR),
-                (local_id.is_none(), ENV_IDENTITY_IDENTITY_LOCAL_NAME),
-                (token.is_none(), ENV_IDENTITY_TOKEN_FILE),
-            ] {
-                if *unset {
-                    error!(
-                        "{} must be set when other identity variables are set.",
-                        name,
-                    );
+        let mut settings = spec.split(';').map(str::trim);
+        let rate = settings
+            .next()
+            .and_then(|r| r.parse::<f64>().ok())
+            .filter(|r| r.is_finite() && *r > 0.0)
+            .ok_or_else(invalid)?;
+        let mut limit = inbound::RateLimit {
+            rate,
+            burst: rate.ceil() as u32,
+        };
+        let mut route = None;
+        for setting in settings {
+            let mut kv = setting.splitn(2, '=');
+            match (kv.next(), kv.next()) {
+                (Some("burst"), Some(v)) => limit.burst = parse_number(v)?,
+                (Some("route"), Some(v)) if !v.trim().is_empty() => {
+                    route = Some(v.trim().to_string())
                 }
+                _ => return Err(invalid()),
             }
-            Err(EnvError::InvalidEnvVar)
+        }
+        if limit.burst == 0 {
+            return Err(invalid());
+        }
+
+        // Servers are identified by port; routes by their service's authority.
+        match (target.parse::<u16>(), route) {
+            (Ok(port), None) => servers.push((port, limit)),
+            (Err(_), Some(route)) => {
+                let addr = NameAddr::from_str(target).map_err(|_| invalid())?;
+                routes.push((addr, route, limit));
+            }
+            _ => return Err(invalid()),
         }
     }
+    Ok(inbound::RateLimits::new(servers, routes))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+fn parse_bandwidth_limits(list: &str) -> Result<transport::bandwidth::Limits, ParseError> {
+    use transport::bandwidth::{Limit, Limits, Scope};
 
-    fn test_unit<F: Fn(u64) -> Duration>(unit: &str, to_duration: F) {
-        for v in &[0, 1, 23, 456_789] {
-            let d = to_duration(*v);
-            let text = format!("{}{}", v, unit);
-            assert_eq!(parse_duration(&text), Ok(d), "text=\"{}\"", text);
+    let mut ports = Vec::new();
+    let mut default = None;
+    for l in list.split(',').map(str::trim).filter(|l| !l.is_empty()) {
+        let invalid = || {
+            error!(limit = %l, "Invalid bandwidth limit");
+            ParseError::InvalidBandwidthLimit(l.to_string())
+        };
+        let mut parts = l.splitn(2, '=');
+        let (port, spec) = match (parts.next(), parts.next()) {
+            (Some(port), Some(spec)) => (port.trim(), spec.trim()),
+            _ => return Err(invalid()),
+        };
+
+        let mut settings = spec.split(';').map(str::trim);
+        let rate = settings
+            .next()
+            .and_then(|r| r.parse::<u64>().ok())
+            .filter(|r| *r > 0)
+            .ok_or_else(invalid)?;
+        let mut limit = Limit {
+            rate,
+            burst: rate,
+            scope: Scope::Connection,
+        };
+        for setting in settings {
+            let mut kv = setting.splitn(2, '=');
+            match (kv.next(), kv.next().map(str::trim)) {
+                (Some("burst"), Some(v)) => limit.burst = parse_number(v)?,
+                (Some("scope"), Some("connection")) => limit.scope = Scope::Connection,
+                (Some("scope"), Some("server")) => limit.scope = Scope::Server,
+                _ => return Err(invalid()),
+            }
+        }
+        if limit.burst == 0 {
+            return Err(invalid());
+        }
+
+        if port == "*" {
+            if default.replace(limit).is_some() {
+                return Err(invalid());
+            }
+        } else {
+            ports.push((port.parse::<u16>().map_err(|_| invalid())?, limit));
+        }
+    }
+    Ok(Limits::new(ports, default))
+}
+
+fn parse_tls_originations(list: &str) -> Result<outbound::tcp::TlsOriginations, ParseError> {
+    let mut originations = Vec::new();
+    for o in list.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+        let invalid = || {
+            error!(origination = %o, "Invalid TLS origination");
+            ParseError::InvalidTlsOrigination(o.to_string())
+        };
+        let mut parts = o.splitn(2, '=');
+        let (target, spec) = match (parts.next(), parts.next()) {
+            (Some(target), Some(spec)) => (target.trim(), spec.trim()),
+            _ => return Err(invalid()),
+        };
+        let target = Addr::from_str(target).map_err(|_| invalid())?;
+
+        let mut settings = spec.split(';').map(str::trim);
+        let ca = match settings.next() {
+            Some(ca) if !ca.is_empty() => ca,
+            _ => return Err(invalid()),
+        };
+        let (mut crt, mut key, mut server_name) = (None, None, None);
+        let mut sans = Vec::new();
+        for setting in settings {
+            let mut kv = setting.splitn(2, '=');
+            match (kv.next(), kv.next().map(str::trim)) {
+                (Some("crt"), Some(v)) if !v.is_empty() => crt = Some(v),
+                (Some("key"), Some(v)) if !v.is_empty() => key = Some(v),
+                (Some("server-name"), Some(v)) => server_name = Some(parse_identity(v)?),
+                (Some("sans"), Some(v)) => {
+                    sans = v
+                        .split('|')
+                        .map(str::trim)
+                        .filter(|n| !n.is_empty())
+                        .map(parse_identity)
+                        .collect::<Result<_, _>>()?
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        let server_name = match (server_name, &target) {
+            (Some(name), _) => name,
+            (None, Addr::Name(addr)) => identity::Name::from(addr.name().clone()),
+            (None, Addr::Socket(_)) => return Err(invalid()),
+        };
+
+        let read = |path: &str| {
+            fs::read(path).map_err(|error| {
+                error!(origination = %o, %path, %error, "Failed to read TLS origination file");
+                ParseError::InvalidTlsOrigination(o.to_string())
+            })
+        };
+        let ca = read(ca)?;
+        let client = match (crt, key) {
+            (Some(crt), Some(key)) => Some((read(crt)?, read(key)?)),
+            (None, None) => None,
+            _ => return Err(invalid()),
+        };
+        let originate = tls::Originate::new(
+            server_name,
+            &ca,
+            client.as_ref().map(|(crt, key)| (&crt[..], &key[..])),
+        )
+        .map_err(|error| {
+            error!(origination = %o, %error, "Invalid TLS origination");
+            ParseError::InvalidTlsOrigination(o.to_string())
+        })?
+        .with_expected_names(sans);
+        originations.push((target, originate));
+    }
+    Ok(outbound::tcp::TlsOriginations::new(originations))
+}
+
+fn parse_cors(list: &str) -> Result<inbound::CorsPolicies, ParseError> {
+    let mut servers = Vec::new();
+    let mut routes = Vec::new();
+    for c in list.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+        let invalid = || {
+            error!(cors = %c, "Invalid CORS policy");
+            ParseError::InvalidCors(c.to_string())
+        };
+        let mut parts = c.splitn(2, '=');
+        let (target, spec) = match (parts.next(), parts.next()) {
+            (Some(target), Some(spec)) => (target.trim(), spec.trim()),
+            _ => return Err(invalid()),
+        };
+
+        let mut settings = spec.split(';').map(str::trim);
+        let origins = settings
+            .next()
+            .unwrap_or_default()
+            .split('|')
+            .map(str::trim)
+            .filter(|o| !o.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if origins.is_empty() {
+            return Err(invalid());
+        }
+        let mut cors = inbound::Cors {
+            origins,
+            methods: vec![http::Method::GET, http::Method::HEAD, http::Method::POST],
+            headers: Vec::new(),
+            expose_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: None,
+        };
+        let headers = |v: &str| {
+            v.split('|')
+                .map(str::trim)
+                .filter(|h| !h.is_empty())
+                .map(parse_header_name)
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let mut route = None;
+        for setting in settings {
+            let mut kv = setting.splitn(2, '=');
+            match (kv.next(), kv.next()) {
+                (Some("methods"), Some(v)) => {
+                    cors.methods = v
+                        .split('|')
+                        .map(str::trim)
+                        .filter(|m| !m.is_empty())
+                        .map(|m| http::Method::from_bytes(m.as_bytes()).map_err(|_| invalid()))
+                        .collect::<Result<_, _>>()?
+                }
+                (Some("headers"), Some(v)) => cors.headers = headers(v)?,
+                (Some("expose"), Some(v)) => cors.expose_headers = headers(v)?,
+                (Some("credentials"), Some(v)) => cors.allow_credentials = parse_bool(v.trim())?,
+                (Some("max-age"), Some(v)) => cors.max_age = Some(parse_duration(v.trim())?),
+                (Some("route"), Some(v)) if !v.trim().is_empty() => {
+                    route = Some(v.trim().to_string())
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        if cors.allow_credentials && cors.origins.iter().any(|o| o == "*") {
+            return Err(invalid());
+        }
+
+        // Servers are identified by port; routes by their service's authority.
+        match (target.parse::<u16>(), route) {
+            (Ok(port), None) => servers.push((port, cors)),
+            (Err(_), Some(route)) => {
+                let addr = NameAddr::from_str(target).map_err(|_| invalid())?;
+                routes.push((addr, route, cors));
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(inbound::CorsPolicies::new(servers, routes))
+}
+
+fn parse_claim_headers(list: &str) -> Result<Vec<(String, http::HeaderName)>, ParseError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let invalid = || {
+                error!(claim = %s, "Invalid claim header");
+                ParseError::InvalidClaimHeader(s.to_string())
+            };
+            let mut parts = s.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(claim), Some(header)) if !claim.trim().is_empty() => {
+                    let header = parse_header_name(header).map_err(|_| invalid())?;
+                    Ok((claim.trim().to_string(), header))
+                }
+                _ => Err(invalid()),
+            }
+        })
+        .collect()
+}
+
+fn parse_udp_forwards(list: &str) -> Result<Vec<outbound::udp::Forward>, ParseError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse().map_err(|error| {
+                error!(%error, "Invalid UDP forward");
+                ParseError::from(error)
+            })
+        })
+        .collect()
+}
+
+fn parse_skip_detect(list: &str) -> Result<outbound::http::SkipDetect, ParseError> {
+    list.parse().map_err(|error| {
+        error!(%error, "Invalid detection skip rules");
+        ParseError::from(error)
+    })
+}
+
+fn parse_hash_policies(list: &str) -> Result<outbound::http::HashPolicies, ParseError> {
+    list.parse().map_err(|error| {
+        error!(%error, "Invalid HTTP hash policies");
+        ParseError::from(error)
+    })
+}
+
+fn parse_balancer_algorithms(list: &str) -> Result<outbound::balance::Algorithms, ParseError> {
+    list.parse().map_err(|error| {
+        error!(%error, "Invalid balancer algorithms");
+        ParseError::from(error)
+    })
+}
+
+fn parse_health_checks(list: &str) -> Result<outbound::health::HealthChecks, ParseError> {
+    use outbound::health::{Check, CheckKind};
+
+    let checks = list
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(|c| {
+            let invalid = || {
+                error!(check = %c, "Invalid health check");
+                ParseError::InvalidHealthCheck(c.to_string())
+            };
+            let mut parts = c.splitn(2, '=');
+            let (addr, spec) = match (parts.next(), parts.next()) {
+                (Some(addr), Some(spec)) => (addr.trim(), spec.trim()),
+                _ => return Err(invalid()),
+            };
+            let addr = NameAddr::from_str(addr).map_err(|_| invalid())?;
+
+            let mut settings = spec.split(';').map(str::trim);
+            let kind = match settings.next() {
+                Some("tcp") => CheckKind::Tcp,
+                Some(kind) => {
+                    let path = kind.strip_prefix("http:").ok_or_else(invalid)?;
+                    CheckKind::Http(path.parse().map_err(|_| invalid())?)
+                }
+                None => return Err(invalid()),
+            };
+            let mut check = Check {
+                kind,
+                interval: DEFAULT_OUTBOUND_HEALTH_CHECK_INTERVAL,
+                timeout: DEFAULT_OUTBOUND_HEALTH_CHECK_TIMEOUT,
+                healthy_threshold: DEFAULT_OUTBOUND_HEALTH_CHECK_HEALTHY_THRESHOLD,
+                unhealthy_threshold: DEFAULT_OUTBOUND_HEALTH_CHECK_UNHEALTHY_THRESHOLD,
+            };
+            for setting in settings {
+                let mut kv = setting.splitn(2, '=');
+                match (kv.next(), kv.next()) {
+                    (Some("interval"), Some(v)) => check.interval = parse_nonzero_duration(v)?,
+                    (Some("timeout"), Some(v)) => check.timeout = parse_nonzero_duration(v)?,
+                    (Some("healthy"), Some(v)) => check.healthy_threshold = parse_number(v)?,
+                    (Some("unhealthy"), Some(v)) => check.unhealthy_threshold = parse_number(v)?,
+                    _ => return Err(invalid()),
+                }
+            }
+            if check.healthy_threshold == 0 || check.unhealthy_threshold == 0 {
+                return Err(invalid());
+            }
+            Ok((addr, check))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(outbound::health::HealthChecks::new(checks))
+}
+
+fn parse_sticky_sessions(list: &str) -> Result<outbound::http::StickySessions, ParseError> {
+    let sessions = list
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let invalid = || {
+                error!(session = %s, "Invalid sticky session");
+                ParseError::InvalidStickySession(s.to_string())
+            };
+            let mut parts = s.splitn(2, '=');
+            let (addr, spec) = match (parts.next(), parts.next()) {
+                (Some(addr), Some(spec)) => (addr.trim(), spec.trim()),
+                _ => return Err(invalid()),
+            };
+            let addr = NameAddr::from_str(addr).map_err(|_| invalid())?;
+
+            let mut settings = spec.split(';').map(str::trim);
+            let name = match settings.next() {
+                Some(name)
+                    if !name.is_empty()
+                        && name
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)) =>
+                {
+                    name.to_string()
+                }
+                _ => return Err(invalid()),
+            };
+            let mut ttl = DEFAULT_OUTBOUND_HTTP_STICKY_SESSION_TTL;
+            let mut secure = true;
+            for setting in settings {
+                let mut kv = setting.splitn(2, '=');
+                match (kv.next(), kv.next()) {
+                    (Some("ttl"), Some(v)) => ttl = parse_duration(v)?,
+                    (Some("secure"), Some(v)) => secure = parse_bool(v)?,
+                    _ => return Err(invalid()),
+                }
+            }
+            if ttl < Duration::from_secs(1) {
+                return Err(invalid());
+            }
+            Ok((addr, outbound::http::SessionCookie { name, ttl, secure }))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(outbound::http::StickySessions::new(sessions))
+}
+
+fn parse_per_try_timeouts(list: &str) -> Result<retry::PerTryTimeouts, ParseError> {
+    let timeouts = list
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            let invalid = || {
+                error!(timeout = %t, "Invalid per-try timeout");
+                ParseError::InvalidPerTryTimeout(t.to_string())
+            };
+            let mut parts = t.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(addr), Some(timeout)) => {
+                    let addr = NameAddr::from_str(addr.trim()).map_err(|_| invalid())?;
+                    let timeout = parse_nonzero_duration(timeout.trim()).map_err(|_| invalid())?;
+                    Ok((addr, timeout))
+                }
+                _ => Err(invalid()),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(retry::PerTryTimeouts::new(timeouts))
+}
+
+fn parse_egress_policy(list: &str) -> Result<outbound::policy::EgressPolicy, ParseError> {
+    let rules = list
+        .split(',')
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(|r| {
+            let invalid = || {
+                error!(rule = %r, "Invalid egress rule");
+                ParseError::InvalidEgressRule(r.to_string())
+            };
+            let mut parts = r.splitn(2, '=');
+            let dst = parts.next().ok_or_else(invalid)?.trim();
+            let dst = match IpNet::from_str(dst) {
+                Ok(net) => AddrMatch::new(None, Some(net)),
+                Err(_) => AddrMatch::new(Some(parse_dns_suffix(dst).map_err(|_| invalid())?), None),
+            };
+            let ports = match parts.next() {
+                Some(ports) => ports
+                    .split(';')
+                    .map(|p| parse_number::<u16>(p).map_err(|_| invalid()))
+                    .collect::<Result<HashSet<_>, _>>()?,
+                None => HashSet::new(),
+            };
+            Ok(outbound::policy::Rule { dst, ports })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(outbound::policy::EgressPolicy::allow(rules))
+}
+
+fn parse_retry_budgets(list: &str) -> Result<retry::RouteBudgets, ParseError> {
+    let budgets = list
+        .split(',')
+        .map(str::trim)
+        .filter(|b| !b.is_empty())
+        .map(|b| {
+            let invalid = || {
+                error!(budget = %b, "Invalid retry budget");
+                ParseError::InvalidRetryBudget(b.to_string())
+            };
+            let mut parts = b.splitn(2, '=');
+            let (addr, spec) = match (parts.next(), parts.next()) {
+                (Some(addr), Some(spec)) => (addr.trim(), spec.trim()),
+                _ => return Err(invalid()),
+            };
+            let addr = NameAddr::from_str(addr).map_err(|_| invalid())?;
+
+            let mut settings = spec.split(';').map(str::trim);
+            let ratio = settings
+                .next()
+                .and_then(|s| s.parse::<f32>().ok())
+                .ok_or_else(invalid)?;
+            let mut budget = retry::BudgetConfig {
+                ttl: DEFAULT_OUTBOUND_RETRY_BUDGET_TTL,
+                min_per_second: DEFAULT_OUTBOUND_RETRY_BUDGET_MIN_PER_SECOND,
+                ratio,
+            };
+            for setting in settings {
+                let mut kv = setting.splitn(2, '=');
+                match (kv.next(), kv.next()) {
+                    (Some("ttl"), Some(v)) => budget.ttl = parse_duration(v)?,
+                    (Some("min-per-second"), Some(v)) => budget.min_per_second = parse_number(v)?,
+                    _ => return Err(invalid()),
+                }
+            }
+            // The budget's TTL, minimum, and ratio are bounded as they are
+            // for budgets configured by service profiles.
+            if budget.ttl < Duration::from_secs(1)
+                || budget.ttl > Duration::from_secs(60)
+                || budget.min_per_second > std::i32::MAX as u32
+                || !(0.0..=1000.0).contains(&budget.ratio)
+            {
+                return Err(invalid());
+            }
+            Ok((addr, budget))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(retry::RouteBudgets::new(budgets))
+}
+
+fn parse_retry_backoffs(list: &str) -> Result<retry::Backoffs, ParseError> {
+    let backoffs = list
+        .split(',')
+        .map(str::trim)
+        .filter(|b| !b.is_empty())
+        .map(|b| {
+            let invalid = || {
+                error!(backoff = %b, "Invalid retry backoff");
+                ParseError::InvalidRetryBackoff(b.to_string())
+            };
+            let mut parts = b.splitn(2, '=');
+            let (addr, spec) = match (parts.next(), parts.next()) {
+                (Some(addr), Some(spec)) => (addr.trim(), spec.trim()),
+                _ => return Err(invalid()),
+            };
+            let addr = NameAddr::from_str(addr).map_err(|_| invalid())?;
+
+            let mut settings = spec.split(';').map(str::trim);
+            let base = settings
+                .next()
+                .and_then(|s| parse_duration(s).ok())
+                .ok_or_else(invalid)?;
+            let mut backoff = retry::Backoff {
+                base,
+                max: DEFAULT_OUTBOUND_RETRY_BACKOFF_MAX.max(base),
+                multiplier: DEFAULT_OUTBOUND_RETRY_BACKOFF_MULTIPLIER,
+                jitter: DEFAULT_OUTBOUND_RETRY_BACKOFF_JITTER,
+            };
+            for setting in settings {
+                let mut kv = setting.splitn(2, '=');
+                match (kv.next(), kv.next()) {
+                    (Some("max"), Some(v)) => backoff.max = parse_duration(v)?,
+                    (Some("multiplier"), Some(v)) => backoff.multiplier = parse_number(v)?,
+                    (Some("jitter"), Some(v)) => backoff.jitter = parse_number(v)?,
+                    _ => return Err(invalid()),
+                }
+            }
+            if backoff.base == Duration::from_secs(0)
+                || backoff.max < backoff.base
+                || !(backoff.multiplier >= 1.0 && backoff.multiplier.is_finite())
+                || !(0.0..=1.0).contains(&backoff.jitter)
+            {
+                return Err(invalid());
+            }
+            Ok((addr, backoff))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(retry::Backoffs::new(backoffs))
+}
+
+fn parse_hedges(list: &str) -> Result<hedge::Hedges, ParseError> {
+    let hedges = list
+        .split(',')
+        .map(str::trim)
+        .filter(|h| !h.is_empty())
+        .map(|h| {
+            let invalid = || {
+                error!(hedge = %h, "Invalid hedge");
+                ParseError::InvalidHedge(h.to_string())
+            };
+            let mut parts = h.splitn(2, '=');
+            let (addr, delay) = match (parts.next(), parts.next()) {
+                (Some(addr), Some(delay)) => (addr.trim(), delay.trim()),
+                _ => return Err(invalid()),
+            };
+            let addr = NameAddr::from_str(addr).map_err(|_| invalid())?;
+            let delay = match delay.strip_prefix('p') {
+                Some(p) => {
+                    let percentile = p.parse::<f64>().map_err(|_| invalid())?;
+                    if !(percentile > 0.0 && percentile < 100.0) {
+                        return Err(invalid());
+                    }
+                    hedge::Delay::Percentile(percentile / 100.0)
+                }
+                None => hedge::Delay::Fixed(parse_nonzero_duration(delay).map_err(|_| invalid())?),
+            };
+            Ok((addr, delay))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(hedge::Hedges::new(hedges))
+}
+
+fn parse_circuit_breakers(
+    list: &str,
+) -> Result<outbound::http::breaker::CircuitBreakers, ParseError> {
+    use outbound::http::breaker::{Breaker, CircuitBreakers, Trip};
+
+    let breakers = list
+        .split(',')
+        .map(str::trim)
+        .filter(|b| !b.is_empty())
+        .map(|b| {
+            let invalid = || {
+                error!(breaker = %b, "Invalid circuit breaker");
+                ParseError::InvalidCircuitBreaker(b.to_string())
+            };
+            let mut parts = b.splitn(2, '=');
+            let (addr, spec) = match (parts.next(), parts.next()) {
+                (Some(addr), Some(spec)) => (addr.trim(), spec.trim()),
+                _ => return Err(invalid()),
+            };
+            let addr = NameAddr::from_str(addr).map_err(|_| invalid())?;
+
+            let mut settings = spec.split(';').map(str::trim);
+            let mut kv = settings.next().ok_or_else(invalid)?.splitn(2, ':');
+            let trip = match (kv.next(), kv.next()) {
+                (Some("failures"), Some(v)) => Trip::ConsecutiveFailures(parse_number(v)?),
+                (Some("ratio"), Some(v)) => Trip::FailureRatio {
+                    ratio: parse_number(v)?,
+                    window: DEFAULT_OUTBOUND_CIRCUIT_BREAKER_WINDOW,
+                    min_requests: DEFAULT_OUTBOUND_CIRCUIT_BREAKER_MIN_REQUESTS,
+                },
+                _ => return Err(invalid()),
+            };
+            let mut breaker = Breaker {
+                trip,
+                open_time: DEFAULT_OUTBOUND_CIRCUIT_BREAKER_OPEN_TIME,
+                probes: DEFAULT_OUTBOUND_CIRCUIT_BREAKER_PROBES,
+            };
+            for setting in settings {
+                let mut kv = setting.splitn(2, '=');
+                match (kv.next(), kv.next(), &mut breaker.trip) {
+                    (Some("open"), Some(v), _) => breaker.open_time = parse_duration(v)?,
+                    (Some("probes"), Some(v), _) => breaker.probes = parse_number(v)?,
+                    (Some("window"), Some(v), Trip::FailureRatio { window, .. }) => {
+                        *window = parse_duration(v)?
+                    }
+                    (Some("min-requests"), Some(v), Trip::FailureRatio { min_requests, .. }) => {
+                        *min_requests = parse_number(v)?
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            let valid = match breaker.trip {
+                Trip::ConsecutiveFailures(n) => n > 0,
+                Trip::FailureRatio { ratio, window, .. } => {
+                    ratio > 0.0 && ratio <= 1.0 && window > Duration::from_secs(0)
+                }
+            };
+            if !valid || breaker.probes == 0 {
+                return Err(invalid());
+            }
+            Ok((addr, breaker))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(CircuitBreakers::new(breakers))
+}
+
+fn parse_concurrency_limits(
+    list: &str,
+) -> Result<outbound::http::concurrency::ConcurrencyLimits, ParseError> {
+    use outbound::http::concurrency::{Algorithm, ConcurrencyLimits, Limit};
+
+    let limits = list
+        .split(',')
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            let invalid = || {
+                error!(limit = %l, "Invalid concurrency limit");
+                ParseError::InvalidConcurrencyLimit(l.to_string())
+            };
+            let mut parts = l.splitn(2, '=');
+            let (addr, spec) = match (parts.next(), parts.next()) {
+                (Some(addr), Some(spec)) => (addr.trim(), spec.trim()),
+                _ => return Err(invalid()),
+            };
+            let addr = NameAddr::from_str(addr).map_err(|_| invalid())?;
+
+            let mut settings = spec.split(';').map(str::trim);
+            let mut kv = settings.next().ok_or_else(invalid)?.splitn(2, ':');
+            let algorithm = match (kv.next(), kv.next()) {
+                (Some("aimd"), Some(v)) => Algorithm::Aimd {
+                    latency: parse_duration(v)?,
+                    backoff: DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_BACKOFF,
+                },
+                (Some("gradient"), None) => Algorithm::Gradient {
+                    tolerance: DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_TOLERANCE,
+                },
+                _ => return Err(invalid()),
+            };
+            let mut limit = Limit {
+                algorithm,
+                initial: DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_INITIAL,
+                min: DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_MIN,
+                max: DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_MAX,
+            };
+            for setting in settings {
+                let mut kv = setting.splitn(2, '=');
+                match (kv.next(), kv.next(), &mut limit.algorithm) {
+                    (Some("initial"), Some(v), _) => limit.initial = parse_number(v)?,
+                    (Some("min"), Some(v), _) => limit.min = parse_number(v)?,
+                    (Some("max"), Some(v), _) => limit.max = parse_number(v)?,
+                    (Some("backoff"), Some(v), Algorithm::Aimd { backoff, .. }) => {
+                        *backoff = parse_number(v)?
+                    }
+                    (Some("tolerance"), Some(v), Algorithm::Gradient { tolerance }) => {
+                        *tolerance = parse_number(v)?
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            let valid = match limit.algorithm {
+                Algorithm::Aimd { latency, backoff } => {
+                    latency > Duration::from_secs(0) && backoff > 0.0 && backoff < 1.0
+                }
+                Algorithm::Gradient { tolerance } => tolerance >= 1.0 && tolerance.is_finite(),
+            };
+            if !valid || limit.min == 0 || limit.min > limit.max {
+                return Err(invalid());
+            }
+            // The initial limit is clamped to the configured bounds.
+            limit.initial = limit.initial.max(limit.min).min(limit.max);
+            Ok((addr, limit))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ConcurrencyLimits::new(limits))
+}
+
+fn parse_faults(list: &str) -> Result<fault::Faults, ParseError> {
+    let faults = list
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(|f| {
+            let invalid = || {
+                error!(fault = %f, "Invalid fault");
+                ParseError::InvalidFault(f.to_string())
+            };
+            let mut parts = f.splitn(2, '=');
+            let (addr, spec) = match (parts.next(), parts.next()) {
+                (Some(addr), Some(spec)) => (addr.trim(), spec.trim()),
+                _ => return Err(invalid()),
+            };
+            let addr = NameAddr::from_str(addr).map_err(|_| invalid())?;
+
+            let mut settings = spec.split(';').map(str::trim);
+            let mut kv = settings.next().ok_or_else(invalid)?.splitn(2, ':');
+            let kind = match (kv.next(), kv.next()) {
+                (Some("delay"), Some(v)) => fault::Kind::Delay(parse_duration(v)?),
+                (Some("abort"), Some(v)) => {
+                    fault::Kind::Abort(http::StatusCode::from_str(v.trim()).map_err(|_| invalid())?)
+                }
+                _ => return Err(invalid()),
+            };
+            let mut fault = fault::Fault {
+                route: None,
+                kind,
+                percent: DEFAULT_OUTBOUND_HTTP_FAULT_PERCENT,
+            };
+            for setting in settings {
+                let mut kv = setting.splitn(2, '=');
+                match (kv.next(), kv.next()) {
+                    (Some("percent"), Some(v)) => fault.percent = parse_number(v)?,
+                    (Some("route"), Some(v)) if !v.trim().is_empty() => {
+                        fault.route = Some(v.trim().to_string())
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            if fault.percent > 100 {
+                return Err(invalid());
+            }
+            Ok((addr, fault))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(fault::Faults::new(faults))
+}
+
+fn parse_header_policies(list: &str) -> Result<header_policy::HeaderPolicies, ParseError> {
+    let policies = list
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let invalid = || {
+                error!(policy = %p, "Invalid header policy");
+                ParseError::InvalidHeaderPolicy(p.to_string())
+            };
+            let mut parts = p.splitn(2, '=');
+            let (addr, spec) = match (parts.next(), parts.next()) {
+                (Some(addr), Some(spec)) => (addr.trim(), spec.trim()),
+                _ => return Err(invalid()),
+            };
+            let addr = NameAddr::from_str(addr).map_err(|_| invalid())?;
+
+            let mut settings = spec.split(';').map(str::trim);
+            let mut kv = settings.next().ok_or_else(invalid)?.splitn(2, ':');
+            let mut kind = kv.next().ok_or_else(invalid)?.splitn(2, '.');
+            let message = match kind.next() {
+                Some("request") => header_policy::Message::Request,
+                Some("response") => header_policy::Message::Response,
+                _ => return Err(invalid()),
+            };
+            let mut header = kv.next().ok_or_else(invalid)?.splitn(2, '=');
+            let name = header
+                .next()
+                .and_then(|n| http::HeaderName::from_str(n.trim()).ok())
+                .ok_or_else(invalid)?;
+            let value = header
+                .next()
+                .map(|v| http::HeaderValue::from_str(v.trim()).map_err(|_| invalid()))
+                .transpose()?;
+            let op = match (kind.next(), value) {
+                (Some("add"), Some(v)) => header_policy::Op::Add(name, v),
+                (Some("set"), Some(v)) => header_policy::Op::Set(name, v),
+                (Some("remove"), None) => header_policy::Op::Remove(name),
+                (Some("id"), None) if message == header_policy::Message::Request => {
+                    header_policy::Op::RequestId(name)
+                }
+                _ => return Err(invalid()),
+            };
+
+            let mut policy = header_policy::HeaderPolicy {
+                route: None,
+                message,
+                op,
+            };
+            for setting in settings {
+                let mut kv = setting.splitn(2, '=');
+                match (kv.next(), kv.next()) {
+                    (Some("route"), Some(v)) if !v.trim().is_empty() => {
+                        policy.route = Some(v.trim().to_string())
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            Ok((addr, policy))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(header_policy::HeaderPolicies::new(policies))
+}
+
+fn parse_route_filters(list: &str) -> Result<route_filter::RouteFilters, ParseError> {
+    let filters = list
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(|f| {
+            let invalid = || {
+                error!(filter = %f, "Invalid route filter");
+                ParseError::InvalidRouteFilter(f.to_string())
+            };
+            let mut parts = f.splitn(2, '=');
+            let (addr, spec) = match (parts.next(), parts.next()) {
+                (Some(addr), Some(spec)) => (addr.trim(), spec.trim()),
+                _ => return Err(invalid()),
+            };
+            let addr = NameAddr::from_str(addr).map_err(|_| invalid())?;
+
+            let mut settings = spec.split(';').map(str::trim);
+            let mut kv = settings.next().ok_or_else(invalid)?.splitn(2, ':');
+            let mut kind = match (kv.next(), kv.next().map(str::trim)) {
+                (Some("path"), Some(path)) if path.starts_with('/') => {
+                    route_filter::Kind::RewritePath(path.to_string())
+                }
+                (Some("prefix"), Some(v)) => {
+                    let mut kv = v.splitn(2, '=').map(str::trim);
+                    match (kv.next(), kv.next()) {
+                        (Some(prefix), Some(replacement))
+                            if prefix.starts_with('/') && replacement.starts_with('/') =>
+                        {
+                            route_filter::Kind::RewritePrefix {
+                                prefix: prefix.to_string(),
+                                replacement: replacement.to_string(),
+                            }
+                        }
+                        _ => return Err(invalid()),
+                    }
+                }
+                (Some("host"), Some(host)) => {
+                    route_filter::Kind::RewriteHost(host.parse().map_err(|_| invalid())?)
+                }
+                (Some("redirect"), Some(status)) => {
+                    let status = http::StatusCode::from_str(status).map_err(|_| invalid())?;
+                    if !matches!(status.as_u16(), 301 | 302 | 303 | 307 | 308) {
+                        return Err(invalid());
+                    }
+                    route_filter::Kind::Redirect(route_filter::Redirect {
+                        status,
+                        scheme: None,
+                        host: None,
+                        port: None,
+                        path: None,
+                    })
+                }
+                _ => return Err(invalid()),
+            };
+
+            let mut route = None;
+            for setting in settings {
+                let mut kv = setting.splitn(2, '=').map(str::trim);
+                match (kv.next(), kv.next(), &mut kind) {
+                    (Some("route"), Some(v), _) if !v.is_empty() => route = Some(v.to_string()),
+                    (Some("scheme"), Some(v), route_filter::Kind::Redirect(r)) => {
+                        r.scheme = Some(v.parse().map_err(|_| invalid())?)
+                    }
+                    (Some("host"), Some(v), route_filter::Kind::Redirect(r)) if !v.is_empty() => {
+                        r.host = Some(v.to_string())
+                    }
+                    (Some("port"), Some(v), route_filter::Kind::Redirect(r)) => {
+                        r.port = Some(parse_number(v)?)
+                    }
+                    (Some("path"), Some(v), route_filter::Kind::Redirect(r))
+                        if v.starts_with('/') =>
+                    {
+                        r.path = Some(v.to_string())
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            Ok((addr, route_filter::RouteFilter { route, kind }))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(route_filter::RouteFilters::new(filters))
+}
+
+fn parse_failovers(list: &str) -> Result<outbound::failover::Failovers, ParseError> {
+    let failovers = list
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(|f| {
+            let invalid = || {
+                error!(failover = %f, "Invalid failover");
+                ParseError::InvalidFailover(f.to_string())
+            };
+            let mut parts = f.splitn(2, '=');
+            let (primary, spec) = match (parts.next(), parts.next()) {
+                (Some(primary), Some(spec)) => (primary.trim(), spec.trim()),
+                _ => return Err(invalid()),
+            };
+            let primary = NameAddr::from_str(primary).map_err(|_| invalid())?;
+
+            let mut settings = spec.split(';').map(str::trim);
+            let secondary = settings
+                .next()
+                .and_then(|s| NameAddr::from_str(s).ok())
+                .ok_or_else(invalid)?;
+            let mut threshold = DEFAULT_OUTBOUND_FAILOVER_THRESHOLD;
+            for setting in settings {
+                let mut kv = setting.splitn(2, '=');
+                match (kv.next(), kv.next()) {
+                    (Some("threshold"), Some(v)) => threshold = parse_number(v)?,
+                    _ => return Err(invalid()),
+                }
+            }
+            if !(0.0..=1.0).contains(&threshold) || primary == secondary {
+                return Err(invalid());
+            }
+            Ok((
+                primary,
+                outbound::failover::Target {
+                    secondary,
+                    threshold,
+                },
+            ))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(outbound::failover::Failovers::new(failovers))
+}
+
+fn parse_mirrors(list: &str) -> Result<outbound::http::mirror::Mirrors, ParseError> {
+    let mirrors = list
+        .split(',')
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(|m| {
+            let invalid = || {
+                error!(mirror = %m, "Invalid mirror");
+                ParseError::InvalidMirror(m.to_string())
+            };
+            let mut parts = m.splitn(2, '=');
+            let (addr, spec) = match (parts.next(), parts.next()) {
+                (Some(addr), Some(spec)) => (addr.trim(), spec.trim()),
+                _ => return Err(invalid()),
+            };
+            let addr = NameAddr::from_str(addr).map_err(|_| invalid())?;
+
+            let mut settings = spec.split(';').map(str::trim);
+            let shadow = settings
+                .next()
+                .and_then(|s| NameAddr::from_str(s).ok())
+                .ok_or_else(invalid)?;
+            let mut target = outbound::http::mirror::Target {
+                shadow,
+                percent: DEFAULT_OUTBOUND_HTTP_MIRROR_PERCENT,
+                route: None,
+            };
+            for setting in settings {
+                let mut kv = setting.splitn(2, '=');
+                match (kv.next(), kv.next()) {
+                    (Some("percent"), Some(v)) => target.percent = parse_number(v)?,
+                    (Some("route"), Some(v)) if !v.trim().is_empty() => {
+                        target.route = Some(v.trim().to_string())
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            if target.percent > 100 || addr == target.shadow {
+                return Err(invalid());
+            }
+            Ok((addr, target))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(outbound::http::mirror::Mirrors::new(mirrors))
+}
+
+fn parse_transport_aggregation(list: &str) -> Result<transport::labels::Aggregation, ParseError> {
+    let mut aggregation = transport::labels::Aggregation::default();
+    for family in list.split(',') {
+        match family.trim() {
+            "inbound_server" => aggregation.inbound_server = true,
+            "outbound_server" => aggregation.outbound_server = true,
+            "outbound_client" => aggregation.outbound_client = true,
+            "" => {}
+            family => return Err(ParseError::NotATransportFamily(family.to_string())),
+        }
+    }
+    Ok(aggregation)
+}
+
+fn parse_trace_protocol(s: &str) -> Result<oc_collector::Protocol, ParseError> {
+    match s.trim() {
+        "opencensus" => Ok(oc_collector::Protocol::OpenCensus),
+        "otlp" | "otlp-grpc" => Ok(oc_collector::Protocol::Otlp),
+        protocol => Err(ParseError::NotATraceProtocol(protocol.to_string())),
+    }
+}
+
+fn parse_trace_context_precedence(s: &str) -> Result<http_tracing::Precedence, ParseError> {
+    match s.trim() {
+        "b3" => Ok(http_tracing::Precedence::B3),
+        "w3c" => Ok(http_tracing::Precedence::W3c),
+        precedence => Err(ParseError::NotATracePrecedence(precedence.to_string())),
+    }
+}
+
+fn parse_rate_limit_descriptor(list: &str) -> Result<Vec<rls::Entry>, ParseError> {
+    let entries = list
+        .split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .map(|e| match e {
+            "authority" => Ok(rls::Entry::Authority),
+            "path" => Ok(rls::Entry::Path),
+            "method" => Ok(rls::Entry::Method),
+            "route" => Ok(rls::Entry::Route),
+            "client_identity" => Ok(rls::Entry::ClientIdentity),
+            e => {
+                let name = e
+                    .strip_prefix("header:")
+                    .and_then(|h| http::HeaderName::from_str(h.trim()).ok())
+                    .ok_or_else(|| {
+                        error!(entry = %e, "Invalid rate limit descriptor entry");
+                        ParseError::InvalidRateLimitEntry(e.to_string())
+                    })?;
+                Ok(rls::Entry::Header(name))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if entries.is_empty() {
+        error!("The rate limit descriptor must have at least one entry");
+        return Err(ParseError::InvalidRateLimitEntry(list.to_string()));
+    }
+    Ok(entries)
+}
+
+fn parse_rate_limit_failure_mode(s: &str) -> Result<rls::FailureMode, ParseError> {
+    match s.trim() {
+        "open" => Ok(rls::FailureMode::Open),
+        "closed" => Ok(rls::FailureMode::Closed),
+        mode => Err(ParseError::NotAFailureMode(mode.to_string())),
+    }
+}
+
+fn parse_ext_authz_protocol(s: &str) -> Result<ext_authz::Protocol, ParseError> {
+    match s.trim() {
+        "grpc" => Ok(ext_authz::Protocol::Grpc),
+        "http" => Ok(ext_authz::Protocol::Http),
+        protocol => Err(ParseError::NotAnExtAuthzProtocol(protocol.to_string())),
+    }
+}
+
+fn parse_directions(list: &str) -> Result<HashSet<Direction>, ParseError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|d| match d {
+            "inbound" => Ok(Direction::In),
+            "outbound" => Ok(Direction::Out),
+            d => Err(ParseError::NotADirection(d.to_string())),
+        })
+        .collect()
+}
+
+fn parse_access_log_destination(s: &str) -> Result<access_log::Destination, ParseError> {
+    s.parse().map_err(Into::into)
+}
+
+fn parse_access_log_format(s: &str) -> Result<access_log::Format, ParseError> {
+    match s.trim() {
+        "json" => Ok(access_log::Format::Json),
+        "apache_common" | "common" => Ok(access_log::Format::ApacheCommon),
+        "apache_combined" | "combined" => Ok(access_log::Format::ApacheCombined),
+        format => Err(ParseError::NotAnAccessLogFormat(format.to_string())),
+    }
+}
+
+fn parse_routes(list: &str) -> Result<Vec<String>, ParseError> {
+    Ok(list
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+fn parse_status_classes(list: &str) -> Result<Vec<u16>, ParseError> {
+    list.split(',')
+        .map(|s| s.trim().to_ascii_lowercase())
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.as_str() {
+            "1xx" => Ok(1),
+            "2xx" => Ok(2),
+            "3xx" => Ok(3),
+            "4xx" => Ok(4),
+            "5xx" => Ok(5),
+            _ => Err(ParseError::NotAStatusClass(s)),
+        })
+        .collect()
+}
+
+fn parse_header_name(s: &str) -> Result<http::HeaderName, ParseError> {
+    http::HeaderName::from_str(s.trim()).map_err(|_| ParseError::NotAHeaderName)
+}
+
+fn parse_header_names(list: &str) -> Result<Vec<http::HeaderName>, ParseError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|h| !h.is_empty())
+        .map(parse_header_name)
+        .collect()
+}
+
+fn parse_duplicate_headers(list: &str) -> Result<inbound::DuplicateHeaders, ParseError> {
+    let mut default = None;
+    let mut servers = Vec::new();
+    for l in list.split(',').map(str::trim).filter(|l| !l.is_empty()) {
+        let invalid = || {
+            error!(mode = %l, "Invalid duplicate header mode");
+            ParseError::InvalidDuplicateHeaderMode(l.to_string())
+        };
+        let mut parts = l.splitn(2, '=');
+        let (port, mode) = match (parts.next(), parts.next()) {
+            (Some(port), Some(mode)) => (port.trim(), mode.trim()),
+            _ => return Err(invalid()),
+        };
+        let mode = mode
+            .parse::<inbound::DuplicateHeaderMode>()
+            .map_err(|()| invalid())?;
+        if port == "*" {
+            if default.replace(mode).is_some() {
+                return Err(invalid());
+            }
+        } else {
+            let port = port.parse::<u16>().map_err(|_| invalid())?;
+            if servers.iter().any(|(p, _)| *p == port) {
+                return Err(invalid());
+            }
+            servers.push((port, mode));
+        }
+    }
+    Ok(inbound::DuplicateHeaders::new(default, servers))
+}
+
+fn parse_default_policy(
+    s: &str,
+    cluster_nets: HashSet<IpNet>,
+    detect_timeout: Duration,
+) -> Result<policy::DefaultPolicy, ParseError> {
+    match s {
+        "deny" => Ok(policy::DefaultPolicy::Deny),
+        "all-authenticated" => Ok(policy::defaults::all_authenticated(detect_timeout).into()),
+        "all-unauthenticated" => Ok(policy::defaults::all_unauthenticated(detect_timeout).into()),
+
+        // If cluster networks are configured, support cluster-scoped default policies.
+        name if cluster_nets.is_empty() => Err(ParseError::InvalidPortPolicy(name.to_string())),
+        "cluster-authenticated" => {
+            Ok(policy::defaults::cluster_authenticated(cluster_nets, detect_timeout).into())
+        }
+        "cluster-unauthenticated" => {
+            Ok(policy::defaults::cluster_unauthenticated(cluster_nets, detect_timeout).into())
+        }
+
+        name => Err(ParseError::InvalidPortPolicy(name.to_string())),
+    }
+}
+
+fn parse_inbound_listeners(
+    list: &str,
+    server: &ServerConfig,
+    cluster_nets: &HashSet<IpNet>,
+    detect_timeout: Duration,
+    default: &policy::DefaultPolicy,
+) -> Result<Vec<inbound::ListenerConfig>, ParseError> {
+    let inbound_addr = *server.addr.as_ref();
+    let mut ports = HashSet::new();
+    let mut listeners = Vec::new();
+    for l in list.split(',').map(str::trim).filter(|l| !l.is_empty()) {
+        let invalid = || {
+            error!(listener = %l, "Invalid inbound listener");
+            ParseError::InvalidListener(l.to_string())
+        };
+        let mut parts = l.splitn(2, '=');
+        let (port, spec) = match (parts.next(), parts.next()) {
+            (Some(port), Some(spec)) => (port.trim(), spec.trim()),
+            _ => return Err(invalid()),
+        };
+        let port = port.parse::<u16>().map_err(|_| invalid())?;
+
+        let mut settings = spec.split(';').map(str::trim);
+        let target_port = settings
+            .next()
+            .and_then(|p| p.parse::<u16>().ok())
+            .ok_or_else(invalid)?;
+        let mut protocol = None;
+        let mut default = default.clone();
+        for setting in settings {
+            let mut kv = setting.splitn(2, '=');
+            match (kv.next(), kv.next().map(str::trim)) {
+                (Some("protocol"), Some(v)) => {
+                    protocol = Some(match v {
+                        "detect" => policy::Protocol::Detect {
+                            timeout: detect_timeout,
+                        },
+                        "http1" => policy::Protocol::Http1,
+                        "http2" => policy::Protocol::Http2,
+                        "grpc" => policy::Protocol::Grpc,
+                        "opaque" => policy::Protocol::Opaque,
+                        "tls" => policy::Protocol::Tls,
+                        _ => return Err(invalid()),
+                    })
+                }
+                (Some("policy"), Some(v)) => {
+                    default = parse_default_policy(v, cluster_nets.clone(), detect_timeout)?
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        // A listener must permit some connections, and must not accept or
+        // forward connections on the inbound port (or its own port).
+        let mut policy = match default {
+            policy::DefaultPolicy::Allow(policy) => policy,
+            policy::DefaultPolicy::Deny => return Err(invalid()),
+        };
+        if let Some(protocol) = protocol {
+            policy.protocol = protocol;
+        }
+        if port == 0
+            || port == inbound_addr.port()
+            || target_port == port
+            || target_port == inbound_addr.port()
+            || !ports.insert(port)
+        {
+            return Err(invalid());
+        }
+
+        listeners.push(inbound::ListenerConfig {
+            server: ServerConfig {
+                addr: ListenAddr(SocketAddr::new(inbound_addr.ip(), port)),
+                ..server.clone()
+            },
+            target_port,
+            policy,
+        });
+    }
+    Ok(listeners)
+}
+
+fn parse_admin_policy(
+    s: &str,
+    origin_networks: &OriginNetworks,
+    detect_timeout: Duration,
+) -> Result<policy::ServerPolicy, ParseError> {
+    if s == "admin" {
+        let node_nets = origin_networks.node_local().iter().copied();
+        return Ok(policy::defaults::admin(node_nets, detect_timeout));
+    }
+    let cluster_nets = origin_networks.cluster().iter().copied().collect();
+    match parse_default_policy(s, cluster_nets, detect_timeout)? {
+        policy::DefaultPolicy::Allow(policy) => Ok(policy),
+        // The admin server must serve readiness probes.
+        policy::DefaultPolicy::Deny => Err(ParseError::InvalidPortPolicy(s.to_string())),
+    }
+}
+
+fn parse_authorization_routes(
+    list: &str,
+) -> Result<HashMap<String, Vec<policy::HttpRoute>>, ParseError> {
+    let mut authorizations = HashMap::<String, Vec<policy::HttpRoute>>::new();
+    for r in list.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+        let invalid = || {
+            error!(route = %r, "Invalid authorization route");
+            ParseError::InvalidAuthorizationRoute(r.to_string())
+        };
+        let mut parts = r.splitn(2, '=');
+        let (authz, spec) = match (parts.next(), parts.next()) {
+            (Some(authz), Some(spec)) if !authz.trim().is_empty() => (authz.trim(), spec.trim()),
+            _ => return Err(invalid()),
+        };
+
+        let mut settings = spec.split(';').map(str::trim);
+        let name = settings
+            .next()
+            .filter(|n| !n.is_empty())
+            .ok_or_else(invalid)?;
+        let mut m = policy::HttpRouteMatch {
+            path: None,
+            method: None,
+        };
+        for setting in settings {
+            let mut kv = setting.splitn(2, '=');
+            match (kv.next(), kv.next().map(str::trim)) {
+                (Some("method"), Some(v)) => {
+                    m.method = Some(http::Method::from_str(v).map_err(|_| invalid())?)
+                }
+                (Some("path"), Some(v)) if m.path.is_none() && v.starts_with('/') => {
+                    m.path = Some(policy::PathMatch::Exact(v.to_string()))
+                }
+                (Some("prefix"), Some(v)) if m.path.is_none() && v.starts_with('/') => {
+                    m.path = Some(policy::PathMatch::Prefix(v.to_string()))
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        let routes = authorizations.entry(authz.to_string()).or_default();
+        match routes.iter_mut().find(|route| route.name == name) {
+            Some(route) => route.matches.push(m),
+            None => routes.push(policy::HttpRoute {
+                name: name.to_string(),
+                matches: vec![m],
+            }),
+        }
+    }
+    Ok(authorizations)
+}
+
+pub fn parse_backoff<S: Strings>(
+    strings: &S,
+    base: &str,
+    default: ExponentialBackoff,
+) -> Result<ExponentialBackoff, EnvError> {
+    let min_env = format!("LINKERD2_PROXY_{}_EXP_BACKOFF_MIN", base);
+    let min = parse(strings, &min_env, parse_duration);
+    let max_env = format!("LINKERD2_PROXY_{}_EXP_BACKOFF_MAX", base);
+    let max = parse(strings, &max_env, parse_duration);
+    let jitter_env = format!("LINKERD2_PROXY_{}_EXP_BACKOFF_JITTER", base);
+    let jitter = parse(strings, &jitter_env, parse_number::<f64>);
+
+    match (min?, max?, jitter?) {
+        (None, None, None) => Ok(default),
+        (Some(min), Some(max), jitter) => {
+            ExponentialBackoff::new(min, max, jitter.unwrap_or_default()).map_err(|error| {
+                error!(message="Invalid backoff", %error, %min_env, ?min, %max_env, ?max, %jitter_env, ?jitter);
+                EnvError::InvalidEnvVar
+            })
+        }
+        _ => {
+            error!("You need to specify either all of {} {} {} or none of them to use the default backoff", min_env, max_env,jitter_env );
+            Err(EnvError::InvalidEnvVar)
+        }
+    }
+}
+
+/// Parses the TCP keepalive settings configured with the given base, if any
+/// are set.
+pub fn parse_keepalive<S: Strings>(strings: &S, base: &str) -> Result<Option<Keepalive>, EnvError> {
+    let time = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_KEEPALIVE", base),
+        parse_duration,
+    );
+    let interval = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_KEEPALIVE_INTERVAL", base),
+        parse_duration,
+    );
+    let probes = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_KEEPALIVE_PROBES", base),
+        parse_number::<u32>,
+    );
+    let user_timeout = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_USER_TIMEOUT", base),
+        parse_duration,
+    );
+
+    match (time?, interval?, probes?, user_timeout?) {
+        (None, None, None, None) => Ok(None),
+        (time, interval, probes, user_timeout) => Ok(Some(Keepalive {
+            time,
+            interval,
+            probes,
+            user_timeout,
+        })),
+    }
+}
+
+/// Parses the HTTP/2 settings configured with the given base, using `defaults`
+/// for any that are not set.
+pub fn parse_h2_settings<S: Strings>(
+    strings: &S,
+    base: &str,
+    defaults: h2::Settings,
+) -> Result<h2::Settings, EnvError> {
+    let env = |name: &str| format!("LINKERD2_PROXY_{}_HTTP2_{}", base, name);
+    let initial_stream_window_size = parse(
+        strings,
+        &env(H2_INITIAL_STREAM_WINDOW_SIZE),
+        parse_h2_window_size,
+    );
+    let initial_connection_window_size = parse(
+        strings,
+        &env(H2_INITIAL_CONNECTION_WINDOW_SIZE),
+        parse_h2_window_size,
+    );
+    let max_concurrent_streams = parse(
+        strings,
+        &env(H2_MAX_CONCURRENT_STREAMS),
+        parse_number::<u32>,
+    );
+    let max_frame_size = parse(strings, &env(H2_MAX_FRAME_SIZE), parse_h2_frame_size);
+    let keepalive_interval = parse(strings, &env(H2_KEEPALIVE_INTERVAL), parse_nonzero_duration);
+    let keepalive_timeout = parse(strings, &env(H2_KEEPALIVE_TIMEOUT), parse_nonzero_duration);
+
+    Ok(h2::Settings {
+        initial_stream_window_size: initial_stream_window_size?
+            .or(defaults.initial_stream_window_size),
+        initial_connection_window_size: initial_connection_window_size?
+            .or(defaults.initial_connection_window_size),
+        max_concurrent_streams: max_concurrent_streams?.or(defaults.max_concurrent_streams),
+        max_frame_size: max_frame_size?.or(defaults.max_frame_size),
+        keepalive_interval: keepalive_interval?.or(defaults.keepalive_interval),
+        keepalive_timeout: keepalive_timeout?.or(defaults.keepalive_timeout),
+        ..defaults
+    })
+}
+
+/// Parses an HTTP/2 flow control window size, which may not exceed 2^31-1.
+fn parse_h2_window_size(s: &str) -> Result<u32, ParseError> {
+    let sz = parse_number::<u32>(s)?;
+    if sz > (1 << 31) - 1 {
+        error!(size = %s, "HTTP/2 window sizes may not exceed 2^31-1");
+        return Err(ParseError::InvalidH2WindowSize(s.to_string()));
+    }
+    Ok(sz)
+}
+
+/// Parses an HTTP/2 maximum frame size, which must be between 2^14 and 2^24-1.
+fn parse_h2_frame_size(s: &str) -> Result<u32, ParseError> {
+    let sz = parse_number::<u32>(s)?;
+    if !((1 << 14)..(1 << 24)).contains(&sz) {
+        error!(size = %s, "HTTP/2 frame sizes must be between 2^14 and 2^24-1");
+        return Err(ParseError::InvalidH2FrameSize(s.to_string()));
+    }
+    Ok(sz)
+}
+
+/// Parses a listener's backlog and the limits of the connections it accepts:
+///
+/// - `LINKERD2_PROXY_<base>_BACKLOG` sets the number of connections that the
+///   kernel queues before they are accepted;
+/// - `LINKERD2_PROXY_<base>_MAX_OPEN_CONNECTIONS` limits the number of
+///   accepted connections that may be open at once; and
+/// - `LINKERD2_PROXY_<base>_RATE_LIMIT` limits the rate at which connections
+///   are accepted, as `<connections per second>[;burst=<n>]`.
+///
+/// Connections that exceed these limits are closed as soon as they are
+/// accepted.
+fn parse_accept_limits<S: Strings>(
+    strings: &S,
+    base: &str,
+) -> Result<(transport::Backlog, transport::accept::Limits), EnvError> {
+    let backlog = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_BACKLOG", base),
+        parse_number::<u32>,
+    );
+    let max_open = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_MAX_OPEN_CONNECTIONS", base),
+        parse_number::<usize>,
+    );
+    let rate = parse(
+        strings,
+        &format!("LINKERD2_PROXY_{}_RATE_LIMIT", base),
+        parse_accept_rate,
+    );
+
+    let backlog = backlog?.map(transport::Backlog).unwrap_or_default();
+    let limits = transport::accept::Limits {
+        max_open: max_open?,
+        rate: rate?,
+    };
+    Ok((backlog, limits))
+}
+
+/// Parses a zone-local endpoint weight, which must be positive so that local
+/// endpoints are never weighted out entirely.
+fn parse_zone_weight(s: &str) -> Result<f64, ParseError> {
+    s.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|w| w.is_finite() && *w > 0.0)
+        .ok_or_else(|| {
+            error!(weight = %s, "Invalid zone weight");
+            ParseError::InvalidZoneAffinity(s.to_string())
+        })
+}
+
+/// Parses a zone spillover threshold, which is a fraction between 0 and 1.
+fn parse_zone_threshold(s: &str) -> Result<f64, ParseError> {
+    s.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|t| (0.0..=1.0).contains(t))
+        .ok_or_else(|| {
+            error!(threshold = %s, "Invalid zone spillover threshold");
+            ParseError::InvalidZoneAffinity(s.to_string())
+        })
+}
+
+fn parse_accept_rate(s: &str) -> Result<transport::accept::Rate, ParseError> {
+    let invalid = || {
+        error!(rate = %s, "Invalid accept rate limit");
+        ParseError::InvalidAcceptRate(s.to_string())
+    };
+    let mut settings = s.split(';').map(str::trim);
+    let per_second = settings
+        .next()
+        .and_then(|r| r.parse::<f64>().ok())
+        .filter(|r| r.is_finite() && *r > 0.0)
+        .ok_or_else(invalid)?;
+    let mut rate = transport::accept::Rate {
+        per_second,
+        burst: per_second.ceil() as u32,
+    };
+    for setting in settings {
+        let mut kv = setting.splitn(2, '=');
+        match (kv.next(), kv.next()) {
+            (Some("burst"), Some(v)) => rate.burst = parse_number(v.trim())?,
+            _ => return Err(invalid()),
+        }
+    }
+    if rate.burst == 0 {
+        return Err(invalid());
+    }
+    Ok(rate)
+}
+
+/// Configures a control plane client's connections like the proxy's, unless
+/// control plane keepalives are configured, with the control plane's HTTP/2
+/// settings.
+fn control_connect(
+    connect: &ConnectConfig,
+    keepalive: Option<Keepalive>,
+    h2_settings: h2::Settings,
+) -> ConnectConfig {
+    ConnectConfig {
+        keepalive: merge_keepalive(keepalive, connect.keepalive),
+        h2_settings,
+        ..connect.clone()
+    }
+}
+
+/// Overrides the fields of the default keepalive settings that are configured.
+fn merge_keepalive(keepalive: Option<Keepalive>, default: Keepalive) -> Keepalive {
+    match keepalive {
+        None => default,
+        Some(keepalive) => Keepalive {
+            time: keepalive.time.or(default.time),
+            interval: keepalive.interval.or(default.interval),
+            probes: keepalive.probes.or(default.probes),
+            user_timeout: keepalive.user_timeout.or(default.user_timeout),
+        },
+    }
+}
+
+pub fn parse_control_addr<S: Strings>(
+    strings: &S,
+    base: &str,
+    id_disabled: bool,
+) -> Result<Option<ControlAddr>, EnvError> {
+    let a = parse(strings, &format!("{}_ADDR", base), parse_addr)?;
+    let n = parse(strings, &format!("{}_NAME", base), parse_identity)?;
+    match (a, n) {
+        (None, None) => Ok(None),
+        (Some(ref addr), _) if addr.is_loopback() => Ok(Some(ControlAddr {
+            addr: addr.clone(),
+            identity: Conditional::None(tls::NoClientTls::Loopback),
+        })),
+        (Some(addr), None) if id_disabled => Ok(Some(ControlAddr {
+            addr,
+            identity: Conditional::None(tls::NoClientTls::Loopback),
+        })),
+        (Some(addr), Some(name)) => Ok(Some(ControlAddr {
+            addr,
+            identity: Conditional::Some(tls::ServerId(name).into()),
+        })),
+        _ => {
+            error!("{}_ADDR and {}_NAME must be specified together", base, base);
+            Err(EnvError::InvalidEnvVar)
+        }
+    }
+}
+
+/// How the local identity is provisioned.
+pub enum IdentityConfig {
+    /// Certified by the identity controller.
+    Control(ControlAddr, identity::certify::Config),
+
+    /// Read from files that are provisioned by another process.
+    Files(identity::files::Config),
+}
+
+pub fn parse_identity_config<S: Strings>(strings: &S) -> Result<Option<IdentityConfig>, EnvError> {
+    let federated = parse(
+        strings,
+        ENV_IDENTITY_FEDERATED_TRUST_ANCHORS,
+        parse_federated_trust_anchors,
+    )?
+    .unwrap_or_default();
+    let key_log = parse_tls_key_log(strings)?;
+    let tls_params = parse_tls_params(strings)?;
+    let session_cache_size = parse(strings, ENV_OUTBOUND_TLS_SESSION_CACHE_SIZE, parse_number)?
+        .unwrap_or(DEFAULT_OUTBOUND_TLS_SESSION_CACHE_SIZE);
+
+    // Configures TLS for both inbound and outbound connections.
+    let configure_tls = |trust_anchors: identity::TrustAnchors| -> Result<_, EnvError> {
+        let trust_anchors = trust_anchors
+            .with_federated_domains(federated.clone())
+            .with_tls_params(&tls_params)
+            .map_err(|error| {
+                error!(%error, "Invalid TLS configuration");
+                EnvError::InvalidEnvVar
+            })?
+            .with_session_resumption(session_cache_size);
+        if key_log {
+            return Ok(trust_anchors.with_key_log());
+        }
+        Ok(trust_anchors)
+    };
+
+    if let Some(mut files) = parse_identity_files_config(strings)? {
+        files.trust_anchors = configure_tls(files.trust_anchors)?;
+        return Ok(Some(IdentityConfig::Files(files)));
+    }
+
+    let control = parse_control_addr(strings, ENV_IDENTITY_SVC_BASE, false);
+    let ta = parse(strings, ENV_IDENTITY_TRUST_ANCHORS, |s| {
+        identity::TrustAnchors::from_pem(s).ok_or(ParseError::InvalidTrustAnchors)
+    });
+    let dir = parse(strings, ENV_IDENTITY_DIR, |ref s| Ok(PathBuf::from(s)));
+    let tok = parse(strings, ENV_IDENTITY_TOKEN_FILE, |ref s| {
+        identity::TokenSource::if_nonempty_file(s.to_string()).map_err(|e| {
+            error!("Could not read {}: {}", ENV_IDENTITY_TOKEN_FILE, e);
+            ParseError::InvalidTokenSource
+        })
+    });
+    let li = parse(strings, ENV_IDENTITY_IDENTITY_LOCAL_NAME, parse_identity);
+    let min_refresh = parse(strings, ENV_IDENTITY_MIN_REFRESH, parse_duration);
+    let max_refresh = parse(strings, ENV_IDENTITY_MAX_REFRESH, parse_duration);
+
+    let disabled = strings
+        .get(ENV_IDENTITY_DISABLED)?
+        .map(|d| !d.is_empty())
+        .unwrap_or(false);
+
+    match (
+        disabled,
+        control?,
+        ta?,
+        dir?,
+        li?,
+        tok?,
+        min_refresh?,
+        max_refresh?,
+    ) {
+        (disabled, None, None, None, None, None, None, None) => {
+            if !disabled {
+                error!(
+                    "{} must be set or identity configuration must be specified.",
+                    ENV_IDENTITY_DISABLED
+                );
+                Err(EnvError::InvalidEnvVar)
+            } else {
+                Ok(None)
+            }
+        }
+        (
+            false,
+            Some(control),
+            Some(trust_anchors),
+            Some(dir),
+            Some(local_name),
+            Some(token),
+            min_refresh,
+            max_refresh,
+        ) => {
+            let key = {
+                let mut p = dir.clone();
+                p.push("key");
+                p.set_extension("p8");
+
+                fs::read(p)
+                    .map_err(|e| {
+                        error!("Failed to read key: {}", e);
+                        EnvError::InvalidEnvVar
+                    })
+                    .and_then(|b| {
+                        identity::Key::from_pkcs8(&b).map_err(|e| {
+                            error!("Invalid key: {}", e);
+                            EnvError::InvalidEnvVar
+                        })
+                    })
+            };
+
+            let csr = {
+                let mut p = dir;
+                p.push("csr");
+                p.set_extension("der");
+
+                fs::read(p)
+                    .map_err(|e| {
+                        error!("Failed to read Csr: {}", e);
+                        EnvError::InvalidEnvVar
+                    })
+                    .and_then(|b| {
+                        identity::Csr::from_der(b).ok_or_else(|| {
+                            error!("No CSR found");
+                            EnvError::InvalidEnvVar
+                        })
+                    })
+            };
+
+            Ok(Some(IdentityConfig::Control(
+                control,
+                identity::certify::Config {
+                    local_id: tls::LocalId(local_name),
+                    token,
+                    trust_anchors: configure_tls(trust_anchors)?,
+                    csr: csr?,
+                    key: key?,
+                    min_refresh: min_refresh.unwrap_or(DEFAULT_IDENTITY_MIN_REFRESH),
+                    max_refresh: max_refresh.unwrap_or(DEFAULT_IDENTITY_MAX_REFRESH),
+                },
+            )))
+        }
+        (disabled, addr, trust_anchors, end_entity_dir, local_id, token, _minr, _maxr) => {
+            if disabled {
+                error!(
+                    "{} must be unset when other identity variables are set.",
+                    ENV_IDENTITY_DISABLED,
+                );
+            }
+            let s = format!("{0}_ADDR and {0}_NAME", ENV_IDENTITY_SVC_BASE);
+            let svc_env: &str = s.as_str();
+            for (unset, name) in &[
+                (addr.is_none(), svc_env),
+                (trust_anchors.is_none(), ENV_IDENTITY_TRUST_ANCHORS),
+                (end_entity_dir.is_none(), ENV_IDENTITY_DIR),
+                (local_id.is_none(), ENV_IDENTITY_IDENTITY_LOCAL_NAME),
+                (token.is_none(), ENV_IDENTITY_TOKEN_FILE),
+            ] {
+                if *unset {
+                    error!(
+                        "{} must be set when other identity variables are set.",
+                        name,
+                    );
+                }
+            }
+            Err(EnvError::InvalidEnvVar)
+        }
+    }
+}
+
+/// Parses the configuration of an identity that is read from files, if
+/// `LINKERD2_PROXY_IDENTITY_CRT_FILE` is set.
+fn parse_identity_files_config<S: Strings>(
+    strings: &S,
+) -> Result<Option<identity::files::Config>, EnvError> {
+    let crt_path = match strings.get(ENV_IDENTITY_CRT_FILE)? {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => return Ok(None),
+    };
+
+    for conflict in &[
+        ENV_IDENTITY_DISABLED,
+        ENV_IDENTITY_DIR,
+        ENV_IDENTITY_TOKEN_FILE,
+    ] {
+        if strings.get(conflict)?.is_some() {
+            error!(
+                "{} must be unset when {} is set.",
+                conflict, ENV_IDENTITY_CRT_FILE
+            );
+            return Err(EnvError::InvalidEnvVar);
+        }
+    }
+
+    let key_path = parse(strings, ENV_IDENTITY_KEY_FILE, |s| Ok(PathBuf::from(s)));
+    let trust_anchors_path = parse(strings, ENV_IDENTITY_TRUST_ANCHORS_FILE, |s| {
+        Ok(PathBuf::from(s))
+    });
+    let local_name = parse(strings, ENV_IDENTITY_IDENTITY_LOCAL_NAME, parse_identity);
+    let reload_interval = parse(strings, ENV_IDENTITY_FILES_RELOAD_INTERVAL, parse_duration);
+
+    let (key_path, trust_anchors_path, local_name) =
+        match (key_path?, trust_anchors_path?, local_name?) {
+            (Some(key), Some(trust_anchors), Some(name)) => (key, trust_anchors, name),
+            (key, trust_anchors, name) => {
+                for (unset, env) in &[
+                    (key.is_none(), ENV_IDENTITY_KEY_FILE),
+                    (trust_anchors.is_none(), ENV_IDENTITY_TRUST_ANCHORS_FILE),
+                    (name.is_none(), ENV_IDENTITY_IDENTITY_LOCAL_NAME),
+                ] {
+                    if *unset {
+                        error!("{} must be set when {} is set.", env, ENV_IDENTITY_CRT_FILE);
+                    }
+                }
+                return Err(EnvError::InvalidEnvVar);
+            }
+        };
+
+    // The trust anchors are read eagerly so that the proxy may validate peers
+    // before its certificate has been loaded.
+    let trust_anchors = fs::read_to_string(&trust_anchors_path)
+        .map_err(|error| {
+            error!(path = %trust_anchors_path.display(), %error, "Failed to read trust anchors");
+            EnvError::InvalidEnvVar
+        })
+        .and_then(|pem| {
+            identity::TrustAnchors::from_pem(&pem).ok_or_else(|| {
+                error!(path = %trust_anchors_path.display(), "Invalid trust anchors");
+                EnvError::InvalidEnvVar
+            })
+        })?;
+
+    Ok(Some(identity::files::Config {
+        local_id: tls::LocalId(local_name),
+        trust_anchors,
+        trust_anchors_path,
+        crt_path,
+        key_path,
+        reload_interval: reload_interval?.unwrap_or(DEFAULT_IDENTITY_FILES_RELOAD_INTERVAL),
+    }))
+}
+
+fn parse_tls_params<S: Strings>(strings: &S) -> Result<identity::TlsParams, EnvError> {
+    let min_version = parse(strings, ENV_TLS_MIN_VERSION, parse_tls_version);
+    let max_version = parse(strings, ENV_TLS_MAX_VERSION, parse_tls_version);
+    let cipher_suites = parse(strings, ENV_TLS_CIPHER_SUITES, |s| {
+        let suites = s
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if suites.is_empty() {
+            return Err(ParseError::InvalidTlsParams);
+        }
+        Ok(suites)
+    });
+    Ok(identity::TlsParams {
+        min_version: min_version?,
+        max_version: max_version?,
+        cipher_suites: cipher_suites?,
+    })
+}
+
+fn parse_tls_version(s: &str) -> Result<identity::TlsVersion, ParseError> {
+    s.parse().map_err(|error| {
+        error!(%error, "Invalid TLS version");
+        ParseError::InvalidTlsParams
+    })
+}
+
+fn parse_tls_key_log<S: Strings>(strings: &S) -> Result<bool, EnvError> {
+    if !parse(strings, ENV_TLS_KEY_LOG_ENABLED, parse_bool)?.unwrap_or(false) {
+        return Ok(false);
+    }
+
+    match strings.get(ENV_SSLKEYLOGFILE)? {
+        Some(path) if !path.is_empty() => {
+            warn!("**********************************************************************");
+            warn!(
+                "TLS key logging is ENABLED: session secrets are written to {}",
+                path
+            );
+            warn!("Traffic may be decrypted by anyone who can read this file.");
+            warn!(
+                "Disable {} once debugging is complete.",
+                ENV_TLS_KEY_LOG_ENABLED
+            );
+            warn!("**********************************************************************");
+            Ok(true)
+        }
+        _ => {
+            error!(
+                "{} must be set when {} is true",
+                ENV_SSLKEYLOGFILE, ENV_TLS_KEY_LOG_ENABLED
+            );
+            Err(EnvError::InvalidEnvVar)
+        }
+    }
+}
+
+fn parse_federated_trust_anchors(
+    list: &str,
+) -> Result<Vec<(String, identity::TrustAnchors)>, ParseError> {
+    let mut domains = Vec::new();
+    for entry in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let mut parts = entry.splitn(2, '=');
+        let (domain, path) = match (parts.next(), parts.next()) {
+            (Some(domain), Some(path)) if !domain.trim().is_empty() => (domain.trim(), path.trim()),
+            _ => {
+                error!(%entry, "Invalid federated trust domain");
+                return Err(ParseError::InvalidTrustAnchors);
+            }
+        };
+        dns::Suffix::from_str(domain).map_err(|_| {
+            error!(%domain, "Invalid federated trust domain");
+            ParseError::InvalidTrustAnchors
+        })?;
+        let pem = fs::read_to_string(path).map_err(|error| {
+            error!(%domain, %path, %error, "Failed to read federated trust anchors");
+            ParseError::InvalidTrustAnchors
+        })?;
+        let anchors = identity::TrustAnchors::from_pem(&pem).ok_or_else(|| {
+            error!(%domain, %path, "Invalid federated trust anchors");
+            ParseError::InvalidTrustAnchors
+        })?;
+        domains.push((domain.to_string(), anchors));
+    }
+    Ok(domains)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_unit<F: Fn(u64) -> Duration>(unit: &str, to_duration: F) {
+        for v in &[0, 1, 23, 456_789] {
+            let d = to_duration(*v);
+            let text = format!("{}{}", v, unit);
+            assert_eq!(parse_duration(&text), Ok(d), "text=\"{}\"", text);
+
+            let text = format!(" {}{}\t", v, unit);
+            assert_eq!(parse_duration(&text), Ok(d), "text=\"{}\"", text);
+        }
+    }
+
+    #[test]
+    fn parse_duration_unit_ms() {
+        test_unit("ms", Duration::from_millis);
+    }
+
+    #[test]
+    fn parse_duration_unit_s() {
+        test_unit("s", Duration::from_secs);
+    }
+
+    #[test]
+    fn parse_duration_unit_m() {
+        test_unit("m", |v| Duration::from_secs(v * 60));
+    }
+
+    #[test]
+    fn parse_duration_unit_h() {
+        test_unit("h", |v| Duration::from_secs(v * 60 * 60));
+    }
+
+    #[test]
+    fn parse_duration_unit_d() {
+        test_unit("d", |v| Duration::from_secs(v * 60 * 60 * 24));
+    }
+
+    #[test]
+    fn parse_duration_floats_invalid() {
+        assert_eq!(parse_duration(".123h"), Err(ParseError::NotADuration));
+        assert_eq!(parse_duration("1.23h"), Err(ParseError::NotADuration));
+    }
+
+    #[test]
+    fn parse_duration_space_before_unit_invalid() {
+        assert_eq!(parse_duration("1 ms"), Err(ParseError::NotADuration));
+    }
+
+    #[test]
+    fn parse_duration_overflows_invalid() {
+        assert!(matches!(
+            parse_duration("123456789012345678901234567890ms"),
+            Err(ParseError::NotAnInteger(_))
+        ));
+    }
+
+    #[test]
+    fn parse_duration_invalid_unit() {
+        assert_eq!(parse_duration("12moons"), Err(ParseError::NotADuration));
+        assert_eq!(parse_duration("12y"), Err(ParseError::NotADuration));
+    }
+
+    #[test]
+    fn parse_duration_zero_without_unit() {
+        assert_eq!(parse_duration("0"), Ok(Duration::from_secs(0)));
+    }
+
+    #[test]
+    fn parse_duration_number_without_unit_is_invalid() {
+        assert_eq!(parse_duration("1"), Err(ParseError::NotADuration));
+    }
+
+    #[test]
+    fn parse_nonzero_duration_zero_invalid() {
+        assert_eq!(parse_nonzero_duration("0"), Err(ParseError::ZeroDuration));
+        assert_eq!(parse_nonzero_duration("0s"), Err(ParseError::ZeroDuration));
+        assert_eq!(parse_nonzero_duration("10s"), Ok(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn convert_attributes_string_to_map_different_values() {
+        let attributes_string = "\
+            cluster=\"test-cluster1\"\n\
+            rack=\"rack-22\"\n\
+            zone=us-est-coast\n\
+            linkerd.io/control-plane-component=\"controller\"\n\
+            linkerd.io/proxy-deployment=\"linkerd-controller\"\n\
+            workload=\n\
+            kind=\"\"\n\
+            key1=\"=\"\n\
+            key2==value2\n\
+            key3\n\
+            =key4\n\
+            "
+        .to_string();
+
+        let expected = [
+            ("cluster".to_string(), "test-cluster1".to_string()),
+            ("rack".to_string(), "rack-22".to_string()),
+            ("zone".to_string(), "us-est-coast".to_string()),
+            (
+                "linkerd.io/control-plane-component".to_string(),
+                "controller".to_string(),
+            ),
+            (
+                "linkerd.io/proxy-deployment".to_string(),
+                "linkerd-controller".to_string(),
+            ),
+            ("workload".to_string(), "".to_string()),
+            ("kind".to_string(), "".to_string()),
+            ("key1".to_string(), "=".to_string()),
+            ("key2".to_string(), "=value2".to_string()),
+            ("".to_string(), "key4".to_string()),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        assert_eq!(
+            convert_attributes_string_to_map(attributes_string),
+            expected
+        );
+    }
+
+    #[test]
+    fn dns_suffixes() {
+        fn p(s: &str) -> Result<Vec<String>, ParseError> {
+            let mut sfxs = parse_dns_suffixes(s)?
+                .into_iter()
+                .map(|s| format!("{}", s))
+                .collect::<Vec<_>>();
+            sfxs.sort();
+            Ok(sfxs)
+        }
+
+        assert_eq!(p(""), Ok(vec![]), "empty string");
+        assert_eq!(p(",,,"), Ok(vec![]), "empty list components are ignored");
+        assert_eq!(p("."), Ok(vec![".".to_owned()]), "root is valid");
+        assert_eq!(
+            p("a.b.c"),
+            Ok(vec!["a.b.c".to_owned()]),
+            "a name without trailing dot"
+        );
+        assert_eq!(
+            p("a.b.c."),
+            Ok(vec!["a.b.c.".to_owned()]),
+            "a name with a trailing dot"
+        );
+        assert_eq!(
+            p(" a.b.c. , d.e.f. "),
+            Ok(vec!["a.b.c.".to_owned(), "d.e.f.".to_owned()]),
+            "whitespace is ignored"
+        );
+        assert_eq!(
+            p("a .b.c"),
+            Err(ParseError::NotADomainSuffix),
+            "whitespace not allowed within a name"
+        );
+        assert_eq!(
+            p("mUlti.CasE.nAmE"),
+            Ok(vec!["multi.case.name".to_owned()]),
+            "names are coerced to lowercase"
+        );
+    }
+
+    #[test]
+    fn duplicate_headers() {
+        use inbound::DuplicateHeaderMode;
+
+        let headers = parse_duplicate_headers("*=reject, 8080=merge,9090=first-wins").unwrap();
+        assert_eq!(headers.server(8080), Some(DuplicateHeaderMode::Merge));
+        assert_eq!(headers.server(9090), Some(DuplicateHeaderMode::FirstWins));
+        assert_eq!(headers.server(7070), Some(DuplicateHeaderMode::Reject));
+        assert_eq!(
+            parse_duplicate_headers("8080=merge").unwrap().server(7070),
+            None
+        );
+        for invalid in &[
+            "8080",
+            "8080=last-wins",
+            "http=merge",
+            "8080=merge;route=x",
+            "*=merge,*=reject",
+            "8080=merge,8080=reject",
+        ] {
+            assert!(parse_duplicate_headers(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn health_checks() {
+        use crate::core::profiles::LogicalAddr;
+        use outbound::health::CheckKind;
+
+        let checks = parse_health_checks(
+            "web.ns.svc.cluster.local:80=http:/ready;interval=5s;unhealthy=2, \
+             db.ns.svc.cluster.local:5432=tcp",
+        )
+        .unwrap();
+        let get = |addr: &str| {
+            checks
+                .get(&LogicalAddr(addr.parse().unwrap()))
+                .cloned()
+                .expect("check must be configured")
+        };
+
+        let web = get("web.ns.svc.cluster.local:80");
+        assert_eq!(web.kind, CheckKind::Http("/ready".parse().unwrap()));
+        assert_eq!(web.interval, Duration::from_secs(5));
+        assert_eq!(web.timeout, DEFAULT_OUTBOUND_HEALTH_CHECK_TIMEOUT);
+        assert_eq!(
+            web.healthy_threshold,
+            DEFAULT_OUTBOUND_HEALTH_CHECK_HEALTHY_THRESHOLD
+        );
+        assert_eq!(web.unhealthy_threshold, 2);
+        assert_eq!(get("db.ns.svc.cluster.local:5432").kind, CheckKind::Tcp);
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=grpc",
+            "web.ns.svc.cluster.local:80=tcp;interval=0s",
+            "web.ns.svc.cluster.local:80=tcp;timeout=0s",
+            "web.ns.svc.cluster.local:80=tcp;retries=2",
+        ] {
+            assert!(parse_health_checks(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn sticky_sessions() {
+        use crate::core::profiles::LogicalAddr;
+
+        let sessions = parse_sticky_sessions(
+            "web.ns.svc.cluster.local:80=l5d-session;ttl=30m;secure=false, \
+             app.ns.svc.cluster.local:8080=JSESSIONID",
+        )
+        .unwrap();
+        let get = |addr: &str| {
+            sessions
+                .get(&LogicalAddr(addr.parse().unwrap()))
+                .cloned()
+                .expect("sticky session must be configured")
+        };
+
+        let web = get("web.ns.svc.cluster.local:80");
+        assert_eq!(web.name, "l5d-session");
+        assert_eq!(web.ttl, Duration::from_secs(30 * 60));
+        assert!(!web.secure);
+        let app = get("app.ns.svc.cluster.local:8080");
+        assert_eq!(app.name, "JSESSIONID");
+        assert_eq!(app.ttl, DEFAULT_OUTBOUND_HTTP_STICKY_SESSION_TTL);
+        assert!(app.secure);
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=",
+            "web.ns.svc.cluster.local:80=bad cookie",
+            "web.ns.svc.cluster.local:80=session;ttl=0s",
+            "web.ns.svc.cluster.local:80=session;path=/",
+        ] {
+            assert!(parse_sticky_sessions(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn socket_mark() {
+        assert_eq!(parse_socket_mark("487").unwrap(), 487);
+        assert_eq!(parse_socket_mark("0x1e7").unwrap(), 0x1e7);
+        assert_eq!(parse_socket_mark(" 0X1E7 ").unwrap(), 0x1e7);
+        for invalid in &["", "0x", "-1", "0x100000000", "mark"] {
+            assert!(parse_socket_mark(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn failovers() {
+        use crate::core::profiles::LogicalAddr;
+
+        let failovers = parse_failovers(
+            "web.ns.svc.cluster.local:80=web.ns.svc.west.example.com:80;threshold=0.3,\
+             db.ns.svc.cluster.local:5432=db.ns.svc.west.example.com:5432",
+        )
+        .unwrap();
+        let get = |addr: &str| {
+            failovers
+                .get(&LogicalAddr(addr.parse().unwrap()))
+                .cloned()
+                .expect("failover must be configured")
+        };
+
+        let web = get("web.ns.svc.cluster.local:80");
+        assert_eq!(
+            web.secondary,
+            "web.ns.svc.west.example.com:80".parse().unwrap()
+        );
+        assert_eq!(web.threshold, 0.3);
+        assert_eq!(
+            get("db.ns.svc.cluster.local:5432").threshold,
+            DEFAULT_OUTBOUND_FAILOVER_THRESHOLD
+        );
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=web.ns.svc.west.example.com:80;threshold=2",
+            "web.ns.svc.cluster.local:80=web.ns.svc.west.example.com:80;weight=2",
+        ] {
+            assert!(parse_failovers(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn mirrors() {
+        use crate::core::profiles::LogicalAddr;
+
+        let mirrors = parse_mirrors(
+            "web.ns.svc.cluster.local:80=web-canary.ns.svc.cluster.local:80;percent=10,\
+             api.ns.svc.cluster.local:8080=api-next.ns.svc.cluster.local:8080;route=GET /books",
+        )
+        .unwrap();
+        let get = |addr: &str| {
+            mirrors
+                .get(&LogicalAddr(addr.parse().unwrap()))
+                .cloned()
+                .expect("mirror must be configured")
+        };
+
+        let web = get("web.ns.svc.cluster.local:80");
+        assert_eq!(
+            web.shadow,
+            "web-canary.ns.svc.cluster.local:80".parse().unwrap()
+        );
+        assert_eq!(web.percent, 10);
+        assert_eq!(web.route, None);
+        let api = get("api.ns.svc.cluster.local:8080");
+        assert_eq!(api.percent, DEFAULT_OUTBOUND_HTTP_MIRROR_PERCENT);
+        assert_eq!(api.route.as_deref(), Some("GET /books"));
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=web-canary.ns.svc.cluster.local:80;percent=150",
+            "web.ns.svc.cluster.local:80=web-canary.ns.svc.cluster.local:80;ratio=0.1",
+            "web.ns.svc.cluster.local:80=web-canary.ns.svc.cluster.local:80;route=",
+        ] {
+            assert!(parse_mirrors(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn circuit_breakers() {
+        use crate::core::profiles::LogicalAddr;
+        use outbound::http::breaker::Trip;
+
+        let breakers = parse_circuit_breakers(
+            "web.ns.svc.cluster.local:80=failures:5;open=1m;probes=3, \
+             api.ns.svc.cluster.local:8080=ratio:0.25;window=30s",
+        )
+        .unwrap();
+        let get = |addr: &str| {
+            breakers
+                .get(&LogicalAddr(addr.parse().unwrap()))
+                .cloned()
+                .expect("circuit breaker must be configured")
+        };
+
+        let web = get("web.ns.svc.cluster.local:80");
+        assert_eq!(web.trip, Trip::ConsecutiveFailures(5));
+        assert_eq!(web.open_time, Duration::from_secs(60));
+        assert_eq!(web.probes, 3);
+        let api = get("api.ns.svc.cluster.local:8080");
+        assert_eq!(
+            api.trip,
+            Trip::FailureRatio {
+                ratio: 0.25,
+                window: Duration::from_secs(30),
+                min_requests: DEFAULT_OUTBOUND_CIRCUIT_BREAKER_MIN_REQUESTS,
+            }
+        );
+        assert_eq!(api.open_time, DEFAULT_OUTBOUND_CIRCUIT_BREAKER_OPEN_TIME);
+        assert_eq!(api.probes, DEFAULT_OUTBOUND_CIRCUIT_BREAKER_PROBES);
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=failures:0",
+            "web.ns.svc.cluster.local:80=ratio:1.5",
+            "web.ns.svc.cluster.local:80=failures:5;window=30s",
+            "web.ns.svc.cluster.local:80=failures:5;probes=0",
+            "web.ns.svc.cluster.local:80=latency:1s",
+        ] {
+            assert!(parse_circuit_breakers(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn concurrency_limits() {
+        use crate::core::profiles::LogicalAddr;
+        use outbound::http::concurrency::{Algorithm, Limit};
+
+        let limits = parse_concurrency_limits(
+            "web.ns.svc.cluster.local:80=aimd:250ms;backoff=0.5;max=200,\
+             api.ns.svc.cluster.local:8080=gradient;initial=5000",
+        )
+        .unwrap();
+        let get = |addr: &str| limits.get(&LogicalAddr(addr.parse().unwrap())).cloned();
+        assert_eq!(
+            get("web.ns.svc.cluster.local:80"),
+            Some(Limit {
+                algorithm: Algorithm::Aimd {
+                    latency: Duration::from_millis(250),
+                    backoff: 0.5,
+                },
+                initial: DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_INITIAL,
+                min: DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_MIN,
+                max: 200,
+            })
+        );
+        assert_eq!(
+            get("api.ns.svc.cluster.local:8080"),
+            Some(Limit {
+                algorithm: Algorithm::Gradient {
+                    tolerance: DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_TOLERANCE,
+                },
+                initial: DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_MAX,
+                min: DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_MIN,
+                max: DEFAULT_OUTBOUND_CONCURRENCY_LIMIT_MAX,
+            })
+        );
+        assert_eq!(get("other.ns.svc.cluster.local:80"), None);
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=aimd",
+            "web.ns.svc.cluster.local:80=aimd:250ms;backoff=1.5",
+            "web.ns.svc.cluster.local:80=aimd:250ms;tolerance=2",
+            "web.ns.svc.cluster.local:80=gradient;tolerance=0.5",
+            "web.ns.svc.cluster.local:80=gradient;min=0",
+            "web.ns.svc.cluster.local:80=gradient;min=10;max=5",
+            "web.ns.svc.cluster.local:80=vegas",
+        ] {
+            assert!(parse_concurrency_limits(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn retry_backoffs() {
+        use crate::core::profiles::LogicalAddr;
+
+        let backoffs = parse_retry_backoffs(
+            "web.ns.svc.cluster.local:80=25ms;max=500ms;multiplier=3;jitter=0.1,\
+             db.ns.svc.cluster.local:5432=10ms",
+        )
+        .unwrap();
+        let get = |addr: &str| {
+            backoffs
+                .get(&LogicalAddr(addr.parse().unwrap()))
+                .expect("backoff must be configured")
+        };
+        assert_eq!(
+            get("web.ns.svc.cluster.local:80"),
+            retry::Backoff {
+                base: Duration::from_millis(25),
+                max: Duration::from_millis(500),
+                multiplier: 3.0,
+                jitter: 0.1,
+            }
+        );
+        assert_eq!(
+            get("db.ns.svc.cluster.local:5432"),
+            retry::Backoff {
+                base: Duration::from_millis(10),
+                max: DEFAULT_OUTBOUND_RETRY_BACKOFF_MAX,
+                multiplier: DEFAULT_OUTBOUND_RETRY_BACKOFF_MULTIPLIER,
+                jitter: DEFAULT_OUTBOUND_RETRY_BACKOFF_JITTER,
+            }
+        );
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=0ms",
+            "web.ns.svc.cluster.local:80=1s;max=10ms",
+            "web.ns.svc.cluster.local:80=10ms;multiplier=0.5",
+            "web.ns.svc.cluster.local:80=10ms;jitter=2",
+            "web.ns.svc.cluster.local:80=10ms;delay=2s",
+        ] {
+            assert!(parse_retry_backoffs(invalid).is_err(), "{}", invalid);
+        }
+    }
 
-            let text = format!(" {}{}\t", v, unit);
-            assert_eq!(parse_duration(&text), Ok(d), "text=\"{}\"", text);
+    #[test]
+    fn per_try_timeouts() {
+        use crate::core::profiles::LogicalAddr;
+
+        let timeouts = parse_per_try_timeouts(
+            "web.ns.svc.cluster.local:80=500ms, api.ns.svc.cluster.local:8080=2s",
+        )
+        .unwrap();
+        let get = |addr: &str| timeouts.get(&LogicalAddr(addr.parse().unwrap()));
+        assert_eq!(
+            get("web.ns.svc.cluster.local:80"),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            get("api.ns.svc.cluster.local:8080"),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(get("other.ns.svc.cluster.local:80"), None);
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=500",
+            "web.ns.svc.cluster.local:80=0s",
+            "80=500ms",
+        ] {
+            assert!(parse_per_try_timeouts(invalid).is_err(), "{}", invalid);
         }
     }
 
     #[test]
-    fn parse_duration_unit_ms() {
-        test_unit("ms", Duration::from_millis);
+    fn retry_budgets() {
+        use crate::core::profiles::LogicalAddr;
+
+        let budgets = parse_retry_budgets(
+            "web.ns.svc.cluster.local:80=0.1;ttl=30s, api.ns.svc.cluster.local:8080=0.5;min-per-second=1",
+        )
+        .unwrap();
+        let get = |addr: &str| budgets.get(&LogicalAddr(addr.parse().unwrap()));
+        assert_eq!(
+            get("web.ns.svc.cluster.local:80"),
+            Some(retry::BudgetConfig {
+                ttl: Duration::from_secs(30),
+                min_per_second: DEFAULT_OUTBOUND_RETRY_BUDGET_MIN_PER_SECOND,
+                ratio: 0.1,
+            })
+        );
+        assert_eq!(
+            get("api.ns.svc.cluster.local:8080"),
+            Some(retry::BudgetConfig {
+                ttl: DEFAULT_OUTBOUND_RETRY_BUDGET_TTL,
+                min_per_second: 1,
+                ratio: 0.5,
+            })
+        );
+        assert_eq!(get("other.ns.svc.cluster.local:80"), None);
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=lots",
+            "web.ns.svc.cluster.local:80=-0.1",
+            "web.ns.svc.cluster.local:80=0.1;ttl=0s",
+            "web.ns.svc.cluster.local:80=0.1;ttl=5m",
+            "web.ns.svc.cluster.local:80=0.1;min-per-second=4294967295",
+            "web.ns.svc.cluster.local:80=0.1;burst=5",
+            "80=0.1",
+        ] {
+            assert!(parse_retry_budgets(invalid).is_err(), "{}", invalid);
+        }
     }
 
     #[test]
-    fn parse_duration_unit_s() {
-        test_unit("s", Duration::from_secs);
+    fn egress_policy() {
+        let policy = parse_egress_policy("10.0.0.0/8, svc.cluster.local.=80;443").unwrap();
+        let web = NameAddr::from_str("web.ns.svc.cluster.local:80").unwrap();
+        let db = NameAddr::from_str("db.ns.svc.cluster.local:5432").unwrap();
+        assert!(policy.permits(([10, 1, 2, 3], 5432).into(), None));
+        assert!(policy.permits(([192, 0, 2, 3], 80).into(), Some(&web)));
+        assert!(!policy.permits(([192, 0, 2, 3], 5432).into(), Some(&db)));
+        assert!(!policy.permits(([192, 0, 2, 3], 80).into(), None));
+
+        // An empty list of rules denies all connections.
+        let policy = parse_egress_policy("").unwrap();
+        assert!(!policy.is_unrestricted());
+        assert!(!policy.permits(([10, 1, 2, 3], 80).into(), None));
+
+        for invalid in &[
+            "10.0.0.0/33",
+            "10.0.0.0/8=http",
+            "svc.cluster.local.=80;",
+            "=80",
+        ] {
+            assert!(parse_egress_policy(invalid).is_err(), "{}", invalid);
+        }
     }
 
     #[test]
-    fn parse_duration_unit_m() {
-        test_unit("m", |v| Duration::from_secs(v * 60));
+    fn hedges() {
+        use crate::core::profiles::LogicalAddr;
+
+        let hedges = parse_hedges(
+            "web.ns.svc.cluster.local:80=p95,\
+             api.ns.svc.cluster.local:80=p99.9,\
+             db.ns.svc.cluster.local:80=50ms",
+        )
+        .unwrap();
+        let get = |addr: &str| hedges.get(&LogicalAddr(addr.parse().unwrap()));
+        assert_eq!(
+            get("web.ns.svc.cluster.local:80"),
+            Some(hedge::Delay::Percentile(0.95))
+        );
+        match get("api.ns.svc.cluster.local:80") {
+            Some(hedge::Delay::Percentile(p)) => assert!((p - 0.999).abs() < f64::EPSILON),
+            delay => panic!("unexpected delay: {:?}", delay),
+        }
+        assert_eq!(
+            get("db.ns.svc.cluster.local:80"),
+            Some(hedge::Delay::Fixed(Duration::from_millis(50)))
+        );
+        assert_eq!(get("other.ns.svc.cluster.local:80"), None);
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=p100",
+            "web.ns.svc.cluster.local:80=p0",
+            "web.ns.svc.cluster.local:80=0ms",
+            "web.ns.svc.cluster.local:80=fast",
+        ] {
+            assert!(parse_hedges(invalid).is_err(), "{}", invalid);
+        }
     }
 
     #[test]
-    fn parse_duration_unit_h() {
-        test_unit("h", |v| Duration::from_secs(v * 60 * 60));
+    fn faults() {
+        use crate::core::profiles::LogicalAddr;
+
+        let faults = parse_faults(
+            "web.ns.svc.cluster.local:80=abort:503;percent=10;route=GET /books,\
+             web.ns.svc.cluster.local:80=delay:100ms",
+        )
+        .unwrap();
+        let web = faults.get(&LogicalAddr("web.ns.svc.cluster.local:80".parse().unwrap()));
+        assert_eq!(
+            web,
+            &[
+                fault::Fault {
+                    route: Some("GET /books".to_string()),
+                    kind: fault::Kind::Abort(http::StatusCode::SERVICE_UNAVAILABLE),
+                    percent: 10,
+                },
+                fault::Fault {
+                    route: None,
+                    kind: fault::Kind::Delay(Duration::from_millis(100)),
+                    percent: DEFAULT_OUTBOUND_HTTP_FAULT_PERCENT,
+                },
+            ]
+        );
+        assert!(faults
+            .get(&LogicalAddr("api.ns.svc.cluster.local:80".parse().unwrap()))
+            .is_empty());
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=abort:99",
+            "web.ns.svc.cluster.local:80=delay:100ms;percent=101",
+            "web.ns.svc.cluster.local:80=delay:100ms;route=",
+            "web.ns.svc.cluster.local:80=reset",
+        ] {
+            assert!(parse_faults(invalid).is_err(), "{}", invalid);
+        }
     }
 
     #[test]
-    fn parse_duration_unit_d() {
-        test_unit("d", |v| Duration::from_secs(v * 60 * 60 * 24));
+    fn header_policies() {
+        use crate::core::profiles::LogicalAddr;
+
+        let policies = parse_header_policies(
+            "web.ns.svc.cluster.local:80=request.set:x-env=prod;route=GET /books,\
+             web.ns.svc.cluster.local:80=request.id:x-request-id,\
+             web.ns.svc.cluster.local:80=response.remove:x-internal",
+        )
+        .unwrap();
+        let web = policies.get(&LogicalAddr("web.ns.svc.cluster.local:80".parse().unwrap()));
+        assert_eq!(
+            web,
+            &[
+                header_policy::HeaderPolicy {
+                    route: Some("GET /books".to_string()),
+                    message: header_policy::Message::Request,
+                    op: header_policy::Op::Set(
+                        http::HeaderName::from_static("x-env"),
+                        http::HeaderValue::from_static("prod")
+                    ),
+                },
+                header_policy::HeaderPolicy {
+                    route: None,
+                    message: header_policy::Message::Request,
+                    op: header_policy::Op::RequestId(http::HeaderName::from_static("x-request-id")),
+                },
+                header_policy::HeaderPolicy {
+                    route: None,
+                    message: header_policy::Message::Response,
+                    op: header_policy::Op::Remove(http::HeaderName::from_static("x-internal")),
+                },
+            ]
+        );
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=request.set:x-env",
+            "web.ns.svc.cluster.local:80=request.remove:x-env=prod",
+            "web.ns.svc.cluster.local:80=response.id:x-request-id",
+            "web.ns.svc.cluster.local:80=body.set:x-env=prod",
+            "web.ns.svc.cluster.local:80=request.add:bad header=1",
+            "web.ns.svc.cluster.local:80=request.remove:x-env;route=",
+        ] {
+            assert!(parse_header_policies(invalid).is_err(), "{}", invalid);
+        }
     }
 
     #[test]
-    fn parse_duration_floats_invalid() {
-        assert_eq!(parse_duration(".123h"), Err(ParseError::NotADuration));
-        assert_eq!(parse_duration("1.23h"), Err(ParseError::NotADuration));
+    fn route_filters() {
+        use crate::core::profiles::LogicalAddr;
+
+        let filters = parse_route_filters(
+            "web.ns.svc.cluster.local:80=prefix:/api=/;route=GET /api,\
+             web.ns.svc.cluster.local:80=host:books.example.com,\
+             web.ns.svc.cluster.local:80=redirect:308;scheme=https;port=8443;route=GET /login",
+        )
+        .unwrap();
+        let web = filters.get(&LogicalAddr("web.ns.svc.cluster.local:80".parse().unwrap()));
+        assert_eq!(
+            web,
+            &[
+                route_filter::RouteFilter {
+                    route: Some("GET /api".to_string()),
+                    kind: route_filter::Kind::RewritePrefix {
+                        prefix: "/api".to_string(),
+                        replacement: "/".to_string(),
+                    },
+                },
+                route_filter::RouteFilter {
+                    route: None,
+                    kind: route_filter::Kind::RewriteHost("books.example.com".parse().unwrap()),
+                },
+                route_filter::RouteFilter {
+                    route: Some("GET /login".to_string()),
+                    kind: route_filter::Kind::Redirect(route_filter::Redirect {
+                        status: http::StatusCode::PERMANENT_REDIRECT,
+                        scheme: Some(http::uri::Scheme::HTTPS),
+                        host: None,
+                        port: Some(8443),
+                        path: None,
+                    }),
+                },
+            ]
+        );
+
+        for invalid in &[
+            "web.ns.svc.cluster.local:80",
+            "web.ns.svc.cluster.local:80=path:books",
+            "web.ns.svc.cluster.local:80=prefix:/api",
+            "web.ns.svc.cluster.local:80=redirect:200",
+            "web.ns.svc.cluster.local:80=redirect:304",
+            "web.ns.svc.cluster.local:80=host:books.example.com;scheme=https",
+            "web.ns.svc.cluster.local:80=redirect:301;port=http",
+            "web.ns.svc.cluster.local:80=rewrite:/books",
+        ] {
+            assert!(parse_route_filters(invalid).is_err(), "{}", invalid);
+        }
     }
 
     #[test]
-    fn parse_duration_space_before_unit_invalid() {
-        assert_eq!(parse_duration("1 ms"), Err(ParseError::NotADuration));
+    fn rate_limit_service() {
+        assert_eq!(
+            parse_rate_limit_descriptor("authority, route,client_identity,header:x-tenant")
+                .unwrap(),
+            vec![
+                rls::Entry::Authority,
+                rls::Entry::Route,
+                rls::Entry::ClientIdentity,
+                rls::Entry::Header(http::HeaderName::from_static("x-tenant")),
+            ]
+        );
+        parse_rate_limit_descriptor(DEFAULT_RATELIMIT_DESCRIPTOR).unwrap();
+        for invalid in &["", "host", "header:", "header:bad header", "path,,query"] {
+            assert!(parse_rate_limit_descriptor(invalid).is_err(), "{}", invalid);
+        }
+
+        assert_eq!(
+            parse_rate_limit_failure_mode("closed").unwrap(),
+            rls::FailureMode::Closed
+        );
+        assert!(parse_rate_limit_failure_mode("ajar").is_err());
+
+        let directions = parse_directions("inbound, outbound").unwrap();
+        assert!(directions.contains(&Direction::In) && directions.contains(&Direction::Out));
+        assert!(parse_directions("sideways").is_err());
     }
 
     #[test]
-    fn parse_duration_overflows_invalid() {
-        assert!(matches!(
-            parse_duration("123456789012345678901234567890ms"),
-            Err(ParseError::NotAnInteger(_))
-        ));
+    fn ext_authz_protocol() {
+        assert_eq!(
+            parse_ext_authz_protocol(" http").unwrap(),
+            ext_authz::Protocol::Http
+        );
+        assert_eq!(
+            parse_ext_authz_protocol("grpc").unwrap(),
+            ext_authz::Protocol::Grpc
+        );
+        assert!(parse_ext_authz_protocol("thrift").is_err());
     }
 
     #[test]
-    fn parse_duration_invalid_unit() {
-        assert_eq!(parse_duration("12moons"), Err(ParseError::NotADuration));
-        assert_eq!(parse_duration("12y"), Err(ParseError::NotADuration));
+    fn merges_keepalive_fields() {
+        let default = Keepalive {
+            time: Some(Duration::from_secs(10)),
+            interval: Some(Duration::from_secs(5)),
+            probes: Some(3),
+            user_timeout: None,
+        };
+        assert_eq!(merge_keepalive(None, default), default);
+        assert_eq!(
+            merge_keepalive(
+                Some(Keepalive {
+                    probes: Some(9),
+                    user_timeout: Some(Duration::from_secs(30)),
+                    ..Keepalive::default()
+                }),
+                default
+            ),
+            Keepalive {
+                probes: Some(9),
+                user_timeout: Some(Duration::from_secs(30)),
+                ..default
+            }
+        );
     }
 
     #[test]
-    fn parse_duration_zero_without_unit() {
-        assert_eq!(parse_duration("0"), Ok(Duration::from_secs(0)));
+    fn header_names() {
+        assert_eq!(
+            parse_header_names("x-user, X-Tenant,").unwrap(),
+            vec![
+                http::HeaderName::from_static("x-user"),
+                http::HeaderName::from_static("x-tenant"),
+            ]
+        );
+        assert!(parse_header_names("x user").is_err());
     }
 
     #[test]
-    fn parse_duration_number_without_unit_is_invalid() {
-        assert_eq!(parse_duration("1"), Err(ParseError::NotADuration));
+    fn request_timeouts() {
+        let timeouts = parse_request_timeouts("8080=10s, 9090=500ms").unwrap();
+        assert_eq!(timeouts.get(8080), Some(Duration::from_secs(10)));
+        assert_eq!(timeouts.get(9090), Some(Duration::from_millis(500)));
+        assert_eq!(timeouts.get(80), None);
+
+        for invalid in &["8080", "8080=10", "8080=0s", "http=10s", "70000=10s"] {
+            assert!(parse_request_timeouts(invalid).is_err(), "{}", invalid);
+        }
     }
 
     #[test]
-    fn convert_attributes_string_to_map_different_values() {
-        let attributes_string = "\
-            cluster=\"test-cluster1\"\n\
-            rack=\"rack-22\"\n\
-            zone=us-est-coast\n\
-            linkerd.io/control-plane-component=\"controller\"\n\
-            linkerd.io/proxy-deployment=\"linkerd-controller\"\n\
-            workload=\n\
-            kind=\"\"\n\
-            key1=\"=\"\n\
-            key2==value2\n\
-            key3\n\
-            =key4\n\
-            "
-        .to_string();
+    fn claim_headers() {
+        assert_eq!(
+            parse_claim_headers("sub=x-user, email = x-user-email").unwrap(),
+            vec![
+                ("sub".to_string(), http::HeaderName::from_static("x-user")),
+                (
+                    "email".to_string(),
+                    http::HeaderName::from_static("x-user-email")
+                ),
+            ]
+        );
+        assert_eq!(parse_claim_headers("").unwrap(), vec![]);
+        for invalid in &["sub", "=x-user", "sub=not a header"] {
+            assert!(parse_claim_headers(invalid).is_err(), "{}", invalid);
+        }
+    }
 
-        let expected = [
-            ("cluster".to_string(), "test-cluster1".to_string()),
-            ("rack".to_string(), "rack-22".to_string()),
-            ("zone".to_string(), "us-est-coast".to_string()),
+    #[test]
+    fn rate_limits() {
+        let limits = parse_rate_limits(
+            "8080=100, 9090=0.5;burst=3, web.ns.svc.cluster.local:80=10;route=GET /books",
+        )
+        .unwrap();
+        assert_eq!(
+            limits.server(8080),
+            Some(inbound::RateLimit {
+                rate: 100.0,
+                burst: 100
+            })
+        );
+        assert_eq!(
+            limits.server(9090),
+            Some(inbound::RateLimit {
+                rate: 0.5,
+                burst: 3
+            })
+        );
+        assert_eq!(limits.server(80), None);
+        let web = NameAddr::from_str("web.ns.svc.cluster.local:80").unwrap();
+        assert_eq!(
+            limits.route(&web, "GET /books"),
+            Some(inbound::RateLimit {
+                rate: 10.0,
+                burst: 10
+            })
+        );
+        assert_eq!(limits.route(&web, "GET /authors"), None);
+
+        for invalid in &[
+            "8080",
+            "8080=0",
+            "8080=-1",
+            "8080=10;burst=0",
+            "8080=10;route=GET /books",
+            "web.ns.svc.cluster.local:80=10",
+            "web.ns.svc.cluster.local:80=10;route=",
+            "8080=10;percent=10",
+        ] {
+            assert!(parse_rate_limits(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn bandwidth_limits() {
+        use transport::bandwidth::{Limit, Scope};
+
+        let limits = parse_bandwidth_limits("5432=1000;burst=4000;scope=server, *=500").unwrap();
+        assert_eq!(
+            limits.port(5432),
+            Some(Limit {
+                rate: 1000,
+                burst: 4000,
+                scope: Scope::Server,
+            })
+        );
+        assert_eq!(
+            limits.port(80),
+            Some(Limit {
+                rate: 500,
+                burst: 500,
+                scope: Scope::Connection,
+            })
+        );
+        assert!(parse_bandwidth_limits("").unwrap().is_empty());
+
+        for invalid in &[
+            "5432",
+            "5432=0",
+            "5432=1.5",
+            "5432=10;burst=0",
+            "5432=10;scope=pod",
+            "db=10",
+            "*=10,*=20",
+        ] {
+            assert!(parse_bandwidth_limits(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn accept_rate() {
+        assert_eq!(
+            parse_accept_rate("100;burst=500").unwrap(),
+            transport::accept::Rate {
+                per_second: 100.0,
+                burst: 500,
+            }
+        );
+        assert_eq!(
+            parse_accept_rate("0.5").unwrap(),
+            transport::accept::Rate {
+                per_second: 0.5,
+                burst: 1,
+            }
+        );
+        for invalid in &["", "0", "-1", "fast", "10;burst=0", "10;scope=server"] {
+            assert!(parse_accept_rate(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    impl Strings for HashMap<&'static str, &'static str> {
+        fn get(&self, key: &str) -> Result<Option<String>, EnvError> {
+            Ok(HashMap::get(self, key).map(|v| v.to_string()))
+        }
+    }
+
+    #[test]
+    fn h2_settings() {
+        let defaults = h2::Settings {
+            initial_stream_window_size: Some(65_535),
+            initial_connection_window_size: Some(1_048_576),
+            max_connection_age: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let env = vec![
             (
-                "linkerd.io/control-plane-component".to_string(),
-                "controller".to_string(),
+                "LINKERD2_PROXY_INBOUND_ACCEPT_HTTP2_INITIAL_STREAM_WINDOW_SIZE",
+                "1048576",
             ),
             (
-                "linkerd.io/proxy-deployment".to_string(),
-                "linkerd-controller".to_string(),
+                "LINKERD2_PROXY_INBOUND_ACCEPT_HTTP2_MAX_CONCURRENT_STREAMS",
+                "100",
+            ),
+            (
+                "LINKERD2_PROXY_INBOUND_ACCEPT_HTTP2_MAX_FRAME_SIZE",
+                "65536",
+            ),
+            (
+                "LINKERD2_PROXY_INBOUND_ACCEPT_HTTP2_KEEPALIVE_TIMEOUT",
+                "20s",
             ),
-            ("workload".to_string(), "".to_string()),
-            ("kind".to_string(), "".to_string()),
-            ("key1".to_string(), "=".to_string()),
-            ("key2".to_string(), "=value2".to_string()),
-            ("".to_string(), "key4".to_string()),
         ]
-        .iter()
-        .cloned()
-        .collect();
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+        let settings = parse_h2_settings(&env, INBOUND_ACCEPT_BASE, defaults).unwrap();
+        assert_eq!(settings.initial_stream_window_size, Some(1_048_576));
+        assert_eq!(settings.initial_connection_window_size, Some(1_048_576));
+        assert_eq!(settings.max_concurrent_streams, Some(100));
+        assert_eq!(settings.max_frame_size, Some(65_536));
+        assert_eq!(settings.keepalive_interval, None);
+        assert_eq!(settings.keepalive_timeout, Some(Duration::from_secs(20)));
+        assert_eq!(settings.max_connection_age, Some(Duration::from_secs(60)));
+
+        let settings = parse_h2_settings(&env, OUTBOUND_CONNECT_BASE, defaults).unwrap();
+        assert_eq!(settings.initial_stream_window_size, Some(65_535));
+        assert_eq!(settings.max_frame_size, None);
+
+        for invalid in &["2147483648", "-1", "big"] {
+            assert!(parse_h2_window_size(invalid).is_err(), "{}", invalid);
+        }
+        for invalid in &["16383", "16777216", ""] {
+            assert!(parse_h2_frame_size(invalid).is_err(), "{}", invalid);
+        }
+        assert!(parse_h2_frame_size("16384").is_ok());
+        assert!(parse_h2_frame_size("16777215").is_ok());
+
+        for (name, value) in &[
+            (
+                "LINKERD2_PROXY_INBOUND_ACCEPT_HTTP2_KEEPALIVE_INTERVAL",
+                "0s",
+            ),
+            (
+                "LINKERD2_PROXY_INBOUND_ACCEPT_HTTP2_KEEPALIVE_TIMEOUT",
+                "0ms",
+            ),
+        ] {
+            let env = Some((*name, *value)).into_iter().collect::<HashMap<_, _>>();
+            assert!(
+                parse_h2_settings(&env, INBOUND_ACCEPT_BASE, defaults).is_err(),
+                "{}={}",
+                name,
+                value
+            );
+        }
+    }
 
+    #[test]
+    fn inbound_listeners() {
+        let server = ServerConfig {
+            addr: ListenAddr(([10, 0, 0, 1], 4143).into()),
+            keepalive: Keepalive::default(),
+            h2_settings: Default::default(),
+            proxy_protocol: Default::default(),
+            backlog: Default::default(),
+            accept_limits: Default::default(),
+        };
+        let timeout = Duration::from_secs(1);
+        let default = policy::defaults::all_authenticated(timeout).into();
+        let parse = |s| parse_inbound_listeners(s, &server, &HashSet::new(), timeout, &default);
+
+        let listeners =
+            parse("4192=8080;protocol=http1;policy=all-unauthenticated, 4193=9090;protocol=grpc")
+                .unwrap();
+        assert_eq!(listeners.len(), 2);
         assert_eq!(
-            convert_attributes_string_to_map(attributes_string),
-            expected
+            listeners[0].server.addr,
+            ListenAddr(([10, 0, 0, 1], 4192).into())
+        );
+        assert_eq!(listeners[0].target_port, 8080);
+        assert_eq!(
+            listeners[0].policy,
+            policy::ServerPolicy {
+                protocol: policy::Protocol::Http1,
+                ..policy::defaults::all_unauthenticated(timeout)
+            }
         );
+        // Listeners use the inbound default policy unless one is set.
+        assert_eq!(listeners[1].target_port, 9090);
+        assert_eq!(
+            listeners[1].policy,
+            policy::ServerPolicy {
+                protocol: policy::Protocol::Grpc,
+                ..policy::defaults::all_authenticated(timeout)
+            }
+        );
+        assert!(parse("").unwrap().is_empty());
+
+        for invalid in &[
+            "4192",
+            "4192=web",
+            "4192=8080;protocol=h3",
+            "4192=8080;policy=deny",
+            "4192=8080;timeout=1s",
+            "4143=8080",
+            "4192=4143",
+            "4192=4192",
+            "4192=8080,4192=9090",
+        ] {
+            assert!(parse(invalid).is_err(), "{}", invalid);
+        }
     }
 
     #[test]
-    fn dns_suffixes() {
-        fn p(s: &str) -> Result<Vec<String>, ParseError> {
-            let mut sfxs = parse_dns_suffixes(s)?
-                .into_iter()
-                .map(|s| format!("{}", s))
-                .collect::<Vec<_>>();
-            sfxs.sort();
-            Ok(sfxs)
+    fn authorization_routes() {
+        let routes = parse_authorization_routes(
+            "probes=health;method=GET;prefix=/health, probes=health;path=/ready, api=all",
+        )
+        .unwrap();
+        assert_eq!(
+            routes.get("probes"),
+            Some(&vec![policy::HttpRoute {
+                name: "health".to_string(),
+                matches: vec![
+                    policy::HttpRouteMatch {
+                        method: Some(http::Method::GET),
+                        path: Some(policy::PathMatch::Prefix("/health".to_string())),
+                    },
+                    policy::HttpRouteMatch {
+                        method: None,
+                        path: Some(policy::PathMatch::Exact("/ready".to_string())),
+                    },
+                ],
+            }])
+        );
+        assert_eq!(
+            routes.get("api"),
+            Some(&vec![policy::HttpRoute {
+                name: "all".to_string(),
+                matches: vec![policy::HttpRouteMatch {
+                    method: None,
+                    path: None,
+                }],
+            }])
+        );
+
+        for invalid in &[
+            "probes",
+            "=health",
+            "probes=",
+            "probes=health;path=health",
+            "probes=health;path=/a;prefix=/b",
+            "probes=health;method=GE T",
+            "probes=health;port=80",
+        ] {
+            assert!(parse_authorization_routes(invalid).is_err(), "{}", invalid);
         }
+    }
 
-        assert_eq!(p(""), Ok(vec![]), "empty string");
-        assert_eq!(p(",,,"), Ok(vec![]), "empty list components are ignored");
-        assert_eq!(p("."), Ok(vec![".".to_owned()]), "root is valid");
-        assert_eq!(
-            p("a.b.c"),
-            Ok(vec!["a.b.c".to_owned()]),
-            "a name without trailing dot"
+    #[test]
+    fn federated_trust_anchors() {
+        let ca = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../identity/src/testdata/ca1.pem"
         );
+        let domains = parse_federated_trust_anchors(&format!("example.org={}, ", ca)).unwrap();
+        assert_eq!(domains.len(), 1);
+        assert_eq!(domains[0].0, "example.org");
+
+        for invalid in &[
+            "example.org",
+            "=/etc/trust-anchors.pem",
+            "example.org=/nonexistent/trust-anchors.pem",
+        ] {
+            assert!(
+                parse_federated_trust_anchors(invalid).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn tls_originations() {
+        let testdata = concat!(env!("CARGO_MANIFEST_DIR"), "/../../identity/src/testdata");
+        let originations = parse_tls_originations(&format!(
+            "db.example.com:5432={0}/ca1.pem;crt={0}/foo-ns1-ca1/crt.pem;key={0}/foo-ns1-ca1/key.pem, \
+             192.0.2.10:443={0}/ca1.pem;server-name=api.example.com;sans=lb.example.com|api.example.com",
+            testdata
+        ))
+        .unwrap();
+        assert!(!originations.is_empty());
+
+        for invalid in &[
+            "db.example.com:5432",
+            "db.example.com:5432=",
+            "192.0.2.10:443={0}/ca1.pem",
+            "db.example.com:5432={0}/ca1.pem;crt={0}/foo-ns1-ca1/crt.pem",
+            "db.example.com:5432={0}/ca1.pem;tls=true",
+            "db.example.com:5432={0}/nonexistent.pem",
+            "db.example.com:5432={0}/foo-ns1-ca1/key.pem",
+        ] {
+            let invalid = invalid.replace("{0}", testdata);
+            assert!(parse_tls_originations(&invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn deny_networks() {
+        let deny = parse_deny_networks("10.1.2.0/24, web-http=192.0.2.0/24,").unwrap();
+        let net = |s: &str| s.parse::<policy::Network>().unwrap();
+        assert_eq!(deny.all(), &[net("10.1.2.0/24")][..]);
+        assert_eq!(deny.server("web-http"), &[net("192.0.2.0/24")][..]);
+        assert!(deny.server("admin-http").is_empty());
+
+        for invalid in &["10.1.2.0", "=10.1.2.0/24", "web-http=", "web-http"] {
+            assert!(parse_deny_networks(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn cors() {
+        let policies = parse_cors(
+            "8080=https://a.example.com|https://b.example.com;methods=GET|PUT;headers=content-type;\
+             expose=x-request-id;credentials=true;max-age=10m, \
+             web.ns.svc.cluster.local:80=*;route=GET /books",
+        )
+        .unwrap();
         assert_eq!(
-            p("a.b.c."),
-            Ok(vec!["a.b.c.".to_owned()]),
-            "a name with a trailing dot"
+            policies.server(8080),
+            Some(&inbound::Cors {
+                origins: vec![
+                    "https://a.example.com".to_string(),
+                    "https://b.example.com".to_string()
+                ],
+                methods: vec![http::Method::GET, http::Method::PUT],
+                headers: vec![http::header::CONTENT_TYPE],
+                expose_headers: vec![http::HeaderName::from_static("x-request-id")],
+                allow_credentials: true,
+                max_age: Some(Duration::from_secs(600)),
+            })
         );
+        assert_eq!(policies.server(80), None);
+        let web = NameAddr::from_str("web.ns.svc.cluster.local:80").unwrap();
         assert_eq!(
-            p(" a.b.c. , d.e.f. "),
-            Ok(vec!["a.b.c.".to_owned(), "d.e.f.".to_owned()]),
-            "whitespace is ignored"
+            policies.route(&web, "GET /books"),
+            Some(&inbound::Cors {
+                origins: vec!["*".to_string()],
+                methods: vec![http::Method::GET, http::Method::HEAD, http::Method::POST],
+                headers: vec![],
+                expose_headers: vec![],
+                allow_credentials: false,
+                max_age: None,
+            })
         );
+        assert_eq!(policies.route(&web, "GET /authors"), None);
+
+        for invalid in &[
+            "8080",
+            "8080=",
+            "8080=*;credentials=maybe",
+            "8080=*;max-age=forever",
+            "8080=*;headers=bad header",
+            "8080=*;route=GET /books",
+            "web.ns.svc.cluster.local:80=*",
+            "8080=*;origin=*",
+            "8080=*;credentials=true",
+            "8080=https://app.example.com|*;credentials=true",
+        ] {
+            assert!(parse_cors(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn suffix_tuning() {
+        let tuning =
+            parse_suffix_tuning("slow.example.com.=min=1s;max=1m;profile-timeout=5s, .=jitter=0.5")
+                .unwrap();
+        assert_eq!(tuning.len(), 2);
         assert_eq!(
-            p("a .b.c"),
-            Err(ParseError::NotADomainSuffix),
-            "whitespace not allowed within a name"
+            tuning[0].suffix,
+            dns::Suffix::from_str("slow.example.com.").unwrap()
         );
+        assert_eq!(tuning[0].backoff.min, Duration::from_secs(1));
+        assert_eq!(tuning[0].backoff.max, Duration::from_secs(60));
+        assert_eq!(tuning[0].profile_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(tuning[1].suffix, dns::Suffix::Root);
+        assert_eq!(tuning[1].backoff.min, DEFAULT_OUTBOUND_CONNECT_BACKOFF.min);
+        assert!((tuning[1].backoff.jitter - 0.5).abs() < f64::EPSILON);
+        assert_eq!(tuning[1].profile_timeout, None);
+
+        for invalid in &[
+            "slow.example.com.",
+            "slow.example.com.=timeout=5s",
+            "slow.example.com.=min=1m;max=1s",
+            "slow.example.com.=profile-timeout=soon",
+        ] {
+            assert!(parse_suffix_tuning(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn http_proxy_authorization() {
         assert_eq!(
-            p("mUlti.CasE.nAmE"),
-            Ok(vec!["multi.case.name".to_owned()]),
-            "names are coerced to lowercase"
+            parse_http_proxy_authorization("Basic dXNlcjpwYXNz")
+                .unwrap()
+                .as_ref(),
+            "Basic dXNlcjpwYXNz"
         );
+        for invalid in &["Basic dXNlcjpwYXNz\r\nx-injected: true", "Basic\nabc"] {
+            assert!(
+                parse_http_proxy_authorization(invalid).is_err(),
+                "{:?}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn zone_affinity() {
+        assert!((parse_zone_weight("2.5").unwrap() - 2.5).abs() < f64::EPSILON);
+        for invalid in &["0", "-1", "NaN", "inf", "heavy"] {
+            assert!(parse_zone_weight(invalid).is_err(), "{}", invalid);
+        }
+
+        assert!(parse_zone_threshold("0").is_ok());
+        assert!(parse_zone_threshold("1").is_ok());
+        for invalid in &["-0.1", "1.5", "NaN"] {
+            assert!(parse_zone_threshold(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn parse_percent_bounded() {
+        assert_eq!(parse_percent("0").unwrap(), 0);
+        assert_eq!(parse_percent("100").unwrap(), 100);
+        assert!(parse_percent("101").is_err());
+        assert!(parse_percent("-1").is_err());
     }
 }
@@ -1,7 +1,7 @@
 pub use linkerd_app_core::identity::{
     Crt, CrtKey, Csr, InvalidName, Key, Name, TokenSource, TrustAnchors,
 };
-pub use linkerd_app_core::proxy::identity::{certify, metrics, LocalCrtKey};
+pub use linkerd_app_core::proxy::identity::{certify, files, metrics, LocalCrtKey};
 use linkerd_app_core::{
     control, dns,
     exp_backoff::{ExponentialBackoff, ExponentialBackoffStream},
@@ -20,6 +20,9 @@ pub enum Config {
         control: control::Config,
         certify: certify::Config,
     },
+    /// The local identity is read from files that are provisioned by another
+    /// process, rather than by the identity controller.
+    Files(files::Config),
 }
 
 // The Disabled case is extraordinarily rare.
@@ -27,7 +30,9 @@ pub enum Config {
 pub enum Identity {
     Disabled,
     Enabled {
-        addr: control::ControlAddr,
+        /// The identity controller's address, unless the identity is read
+        /// from files.
+        addr: Option<control::ControlAddr>,
         local: LocalCrtKey,
         task: Task,
     },
@@ -60,7 +65,26 @@ impl Config {
                     )
                 };
 
-                Ok(Identity::Enabled { addr, local, task })
+                Ok(Identity::Enabled {
+                    addr: Some(addr),
+                    local,
+                    task,
+                })
+            }
+            Config::Files(files) => {
+                let (local, daemon) = LocalCrtKey::from_files(&files);
+
+                // Save to be spawned on an auxiliary runtime.
+                let task = Box::pin(daemon.run().instrument(tracing::debug_span!(
+                    "identity",
+                    crt = %files.crt_path.display()
+                )));
+
+                Ok(Identity::Enabled {
+                    addr: None,
+                    local,
+                    task,
+                })
             }
         }
     }
@@ -14,12 +14,14 @@ use futures::{future, FutureExt, TryFutureExt};
 use linkerd_app_admin as admin;
 pub use linkerd_app_core::{self as core, metrics, trace};
 use linkerd_app_core::{
+    access_log,
     config::ServerConfig,
     control::ControlAddr,
-    dns, drain,
+    dns, drain, ext_authz,
     metrics::FmtMetrics,
+    rls,
     svc::Param,
-    transport::{listen::Bind, ClientAddr, Local, OrigDstAddr, Remote, ServerAddr},
+    transport::{listen::Bind, orig_dst, ClientAddr, Local, OrigDstAddr, Remote, ServerAddr},
     Error, ProxyRuntime,
 };
 use linkerd_app_gateway as gateway;
@@ -56,6 +58,13 @@ pub struct Config {
     pub admin: admin::Config,
     pub tap: tap::Config,
     pub oc_collector: oc_collector::Config,
+    pub access_log: Option<access_log::Config>,
+    pub rate_limit: Option<rls::Config>,
+    pub ext_authz: Option<ext_authz::Config>,
+
+    /// Determines how the original destinations of connections accepted by
+    /// the inbound and outbound listeners are read.
+    pub orig_dst_mode: orig_dst::Mode,
 }
 
 pub struct App {
@@ -65,6 +74,7 @@ pub struct App {
     identity: identity::Identity,
     inbound_addr: Local<ServerAddr>,
     oc_collector: oc_collector::OcCollector,
+    access_log: Option<access_log::Task>,
     outbound_addr: Local<ServerAddr>,
     start_proxy: Pin<Box<dyn std::future::Future<Output = ()> + Send + 'static>>,
     tap: tap::Tap,
@@ -86,7 +96,7 @@ impl Config {
         bind_in: BIn,
         bind_out: BOut,
         bind_admin: BAdmin,
-        shutdown_tx: mpsc::UnboundedSender<()>,
+        shutdown_tx: mpsc::UnboundedSender<admin::Shutdown>,
         log_level: trace::Handle,
     ) -> Result<App, Error>
     where
@@ -104,12 +114,30 @@ impl Config {
             identity,
             inbound,
             oc_collector,
+            access_log,
+            rate_limit,
+            ext_authz,
             outbound,
             gateway,
             tap,
+            orig_dst_mode: _,
         } = self;
         debug!("building app");
-        let (metrics, report) = Metrics::new(admin.metrics_retain_idle);
+        let (mut metrics, report) = Metrics::new(admin.metrics_retain_idle);
+        metrics.proxy.transport = metrics
+            .proxy
+            .transport
+            .with_aggregation(admin.transport_aggregation);
+
+        // Track the readiness of each subsystem so that it can be reported by
+        // the admin server.
+        let subsystems = admin::Subsystems::default();
+        let identity_ready = subsystems.register("identity");
+        let dst_ready = subsystems.register("destination");
+        let policy_ready = subsystems.register("policy");
+        let inbound_ready = subsystems.register("inbound");
+        let outbound_ready = subsystems.register("outbound");
+        let control_plane_readiness_timeout = admin.control_plane_readiness_timeout;
 
         let dns = dns.build();
 
@@ -129,8 +157,10 @@ impl Config {
         let dst = {
             let metrics = metrics.control.clone();
             let dns = dns.resolver.clone();
-            info_span!("dst").in_scope(|| dst.build(dns, metrics, identity.local()))
+            info_span!("dst")
+                .in_scope(|| dst.build(dns, metrics, identity.local(), dst_ready.clone()))
         }?;
+        let report = dst.resolution_cache.clone().and_then(report);
 
         let oc_collector = {
             let identity = identity.local();
@@ -141,22 +171,71 @@ impl Config {
                 .in_scope(|| oc_collector.build(identity, dns, metrics, client_metrics))
         }?;
 
+        let (access_log, access_log_task) = match access_log {
+            Some(config) => {
+                let (log, task) = info_span!("access_log").in_scope(|| config.build())?;
+                (Some(log), Some(task))
+            }
+            None => (None, None),
+        };
+        let audit_log = access_log.clone();
+        let report = access_log
+            .as_ref()
+            .map(access_log::AccessLog::report)
+            .unwrap_or_else(access_log::Report::disabled)
+            .and_then(report);
+
+        let rate_limit = rate_limit.map(|config| {
+            let identity = identity.local();
+            let dns = dns.resolver.clone();
+            let client_metrics = metrics.control.clone();
+            info_span!("rls").in_scope(|| config.build(dns, client_metrics, identity))
+        });
+        let report = rate_limit
+            .as_ref()
+            .map(rls::Client::report)
+            .unwrap_or_else(rls::Report::disabled)
+            .and_then(report);
+
+        let ext_authz = ext_authz.map(|config| {
+            let identity = identity.local();
+            let dns = dns.resolver.clone();
+            let client_metrics = metrics.control.clone();
+            info_span!("ext_authz").in_scope(|| config.build(dns, client_metrics, identity))
+        });
+        let report = ext_authz
+            .as_ref()
+            .map(ext_authz::Client::report)
+            .unwrap_or_else(ext_authz::Report::disabled)
+            .and_then(report);
+
         let runtime = ProxyRuntime {
             identity: identity.local(),
             metrics: metrics.proxy.clone(),
             tap: tap.registry(),
             span_sink: oc_collector.span_sink(),
+            trace_precedence: oc_collector.trace_precedence(),
+            access_log,
+            rate_limit,
+            ext_authz,
+            dns: dns.resolver.clone(),
             drain: drain_rx.clone(),
         };
         let inbound = Inbound::new(inbound, runtime.clone());
         let outbound = Outbound::new(outbound, runtime);
 
+        // Additional inbound listeners are bound directly (rather than
+        // intercepted), like the admin server.
+        let bind_listeners = bind_admin.clone();
+
         let admin = {
             let identity = identity.local();
             let metrics = inbound.metrics();
+            let routes = outbound.route_table();
             let report = inbound
                 .metrics()
                 .and_then(outbound.metrics())
+                .and_then(subsystems.clone())
                 .and_then(report);
             info_span!("admin").in_scope(move || {
                 admin.build(
@@ -167,10 +246,23 @@ impl Config {
                     log_level,
                     drain_rx,
                     shutdown_tx,
+                    routes,
+                    subsystems,
+                    audit_log,
                 )
             })?
         };
 
+        // If configured, hold readiness until the control plane has been reached.
+        let control_plane_readiness = control_plane_readiness_timeout.map(|timeout| {
+            (
+                admin.latch.clone(),
+                dst_ready,
+                policy_ready.clone(),
+                timeout,
+            )
+        });
+
         let dst_addr = dst.addr.clone();
         let gateway_stack = gateway::stack(
             gateway,
@@ -182,13 +274,43 @@ impl Config {
 
         // Bind the proxy sockets eagerly (so they're reserved and known) but defer building the
         // stacks until the proxy starts running.
+        // Connections that exceed a listener's limits are shed as soon as they're accepted.
+        let accept = metrics.proxy.accept.clone();
+
         let (inbound_addr, inbound_listen) = bind_in
             .bind(&inbound.config().proxy.server)
             .expect("Failed to bind inbound listener");
+        let inbound_listen = accept.limit(
+            inbound_addr,
+            inbound.config().proxy.server.accept_limits,
+            inbound_listen,
+        );
+
+        let listeners = inbound
+            .config()
+            .listeners
+            .iter()
+            .map(|listener| {
+                let (addr, listen) = bind_listeners
+                    .clone()
+                    .bind(&listener.server)
+                    .expect("Failed to bind inbound listener");
+                let listen = accept.limit(addr, listener.server.accept_limits, listen);
+                (listener.clone(), addr, listen)
+            })
+            .collect::<Vec<_>>();
 
         let (outbound_addr, outbound_listen) = bind_out
             .bind(&outbound.config().proxy.server)
             .expect("Failed to bind outbound listener");
+        let outbound_listen = accept.limit(
+            outbound_addr,
+            outbound.config().proxy.server.accept_limits,
+            outbound_listen,
+        );
+        let serve_udp = outbound
+            .serve_udp()
+            .expect("Failed to bind UDP forward listener");
 
         // Build a task that initializes and runs the proxy stacks.
         let start_proxy = {
@@ -200,31 +322,76 @@ impl Config {
             let control_metrics = metrics.control;
 
             Box::pin(async move {
-                Self::await_identity(identity)
-                    .await
-                    .expect("failed to initialize identity");
+                if let Some((latch, dst_ready, policy_ready, timeout)) = control_plane_readiness {
+                    tokio::spawn(
+                        Self::await_control_plane(latch, dst_ready, policy_ready, timeout)
+                            .instrument(info_span!("readiness")),
+                    );
+                }
+
+                // The data plane is not started until identity is initialized;
+                // but the admin server continues to serve so that the failure
+                // may be observed.
+                if let Err(error) = Self::await_identity(identity).await {
+                    tracing::error!(%error, "Failed to initialize identity");
+                    identity_ready.fail(error);
+                    return;
+                }
+                identity_ready.set();
 
                 tokio::spawn(
                     outbound
-                        .serve(outbound_listen, profiles.clone(), resolve)
-                        .instrument(info_span!("outbound")),
+                        .clone()
+                        .probe(profiles.clone(), resolve.clone())
+                        .instrument(info_span!("probe")),
+                );
+
+                tokio::spawn(serve_udp.instrument(info_span!("udp")));
+
+                let serve_outbound = outbound.serve(outbound_listen, profiles.clone(), resolve);
+                tokio::spawn(
+                    async move {
+                        // Readiness is reported once the server is running.
+                        outbound_ready.set();
+                        serve_outbound.await
+                    }
+                    .instrument(info_span!("outbound")),
                 );
 
                 let inbound_policies = inbound
                     .build_policies(dns, control_metrics)
                     .instrument(info_span!("policy"))
                     .await;
+                policy_ready.set();
+
+                for (listener, addr, listen) in listeners.into_iter() {
+                    tokio::spawn(
+                        inbound
+                            .clone()
+                            .serve_listener(
+                                listener,
+                                inbound_addr,
+                                listen,
+                                profiles.clone(),
+                                gateway_stack.clone(),
+                            )
+                            .instrument(info_span!("inbound", listen = %addr)),
+                    );
+                }
 
+                let serve_inbound = inbound.serve(
+                    inbound_addr,
+                    inbound_listen,
+                    inbound_policies,
+                    profiles,
+                    gateway_stack,
+                );
                 tokio::spawn(
-                    inbound
-                        .serve(
-                            inbound_addr,
-                            inbound_listen,
-                            inbound_policies,
-                            profiles,
-                            gateway_stack,
-                        )
-                        .instrument(info_span!("inbound")),
+                    async move {
+                        inbound_ready.set();
+                        serve_inbound.await
+                    }
+                    .instrument(info_span!("inbound")),
                 );
             })
         };
@@ -236,6 +403,7 @@ impl Config {
             identity,
             inbound_addr,
             oc_collector,
+            access_log: access_log_task,
             outbound_addr,
             start_proxy,
             tap,
@@ -265,13 +433,36 @@ impl Config {
             }
         }
     }
+
+    /// Holds the readiness latch until the destination controller has been
+    /// reached and inbound policies have been fetched, or until `timeout`
+    /// elapses.
+    async fn await_control_plane(
+        latch: admin::Latch,
+        dst: admin::SubsystemReady,
+        policy: admin::SubsystemReady,
+        timeout: Duration,
+    ) {
+        let ready = future::join(dst.ready(), policy.ready());
+        if time::timeout(timeout, ready).await.is_err() {
+            tracing::warn!(
+                ?timeout,
+                "Control plane not reached; reporting readiness without it"
+            );
+        }
+        latch.release();
+    }
 }
 
 impl App {
-    pub fn admin_addr(&self) -> Local<ServerAddr> {
+    pub fn admin_addr(&self) -> Option<Local<ServerAddr>> {
         self.admin.listen_addr
     }
 
+    pub fn admin_uds_path(&self) -> Option<&std::path::Path> {
+        self.admin.uds_path.as_deref()
+    }
+
     pub fn inbound_addr(&self) -> Local<ServerAddr> {
         self.inbound_addr
     }
@@ -301,7 +492,7 @@ impl App {
     pub fn identity_addr(&self) -> Option<&ControlAddr> {
         match self.identity {
             identity::Identity::Disabled => None,
-            identity::Identity::Enabled { ref addr, .. } => Some(addr),
+            identity::Identity::Enabled { ref addr, .. } => addr.as_ref(),
         }
     }
 
@@ -318,6 +509,7 @@ impl App {
             drain,
             identity,
             oc_collector,
+            access_log,
             start_proxy,
             tap,
             ..
@@ -328,6 +520,9 @@ impl App {
         // The main reactor holds `admin_shutdown_tx` until the reactor drops
         // the task. This causes the daemon reactor to stop.
         let (admin_shutdown_tx, admin_shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        // The proxy is not started until the admin server is serving, so that
+        // probes and metrics are available while the proxy initializes.
+        let (admin_started_tx, admin_started_rx) = tokio::sync::oneshot::channel::<()>();
         debug!("spawning daemon thread");
         tokio::spawn(future::pending().map(|()| drop(admin_shutdown_tx)));
         std::thread::Builder::new()
@@ -345,8 +540,9 @@ impl App {
                         tokio::spawn(
                             admin
                                 .serve
-                                .instrument(info_span!("admin", listen.addr = %admin.listen_addr)),
+                                .instrument(info_span!("admin", listen.addr = ?admin.listen_addr)),
                         );
+                        let _ = admin_started_tx.send(());
 
                         // Kick off the identity so that the process can become ready.
                         if let identity::Identity::Enabled { local, task, .. } = identity {
@@ -384,6 +580,10 @@ impl App {
                             tokio::spawn(oc.task.instrument(info_span!("opencensus")));
                         }
 
+                        if let Some(task) = access_log {
+                            tokio::spawn(task.instrument(info_span!("access_log")));
+                        }
+
                         // we don't care if the admin shutdown channel is
                         // dropped or actually triggered.
                         let _ = admin_shutdown_rx.await;
@@ -393,7 +593,12 @@ impl App {
             })
             .expect("admin");
 
-        tokio::spawn(start_proxy);
+        tokio::spawn(async move {
+            if admin_started_rx.await.is_err() {
+                debug!("Admin thread terminated before serving");
+            }
+            start_proxy.await
+        });
 
         drain
     }
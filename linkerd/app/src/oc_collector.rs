@@ -1,9 +1,10 @@
 use crate::{dns, identity::LocalCrtKey};
-use linkerd_app_core::{control, metrics::ControlHttp as HttpMetrics, svc::NewService, Error};
-use linkerd_opencensus::{self as opencensus, metrics, proto};
+use linkerd_app_core::{
+    control, http_tracing, metrics::ControlHttp as HttpMetrics, svc::NewService, Error,
+};
+use linkerd_opencensus::{self as opencensus, metrics, proto, queue};
+use linkerd_opentelemetry as opentelemetry;
 use std::{collections::HashMap, future::Future, pin::Pin, time::SystemTime};
-use tokio::sync::mpsc;
-use tokio_stream::wrappers::ReceiverStream;
 use tracing::Instrument;
 
 #[derive(Clone, Debug)]
@@ -17,11 +18,20 @@ pub struct EnabledConfig {
     pub control: control::Config,
     pub attributes: HashMap<String, String>,
     pub hostname: Option<String>,
+    pub protocol: Protocol,
+    pub precedence: http_tracing::Precedence,
+}
+
+/// The protocol used to export spans to the collector.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    OpenCensus,
+    Otlp,
 }
 
 pub type Task = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
 
-pub type SpanSink = mpsc::Sender<proto::trace::v1::Span>;
+pub type SpanSink = queue::SpanSender;
 
 pub enum OcCollector {
     Disabled,
@@ -31,6 +41,7 @@ pub enum OcCollector {
 pub struct EnabledCollector {
     pub addr: control::ControlAddr,
     pub span_sink: SpanSink,
+    pub precedence: http_tracing::Precedence,
     pub task: Task,
 }
 
@@ -49,47 +60,75 @@ impl Config {
             Config::Disabled => Ok(OcCollector::Disabled),
             Config::Enabled(inner) => {
                 let addr = inner.control.addr.clone();
+                let precedence = inner.precedence;
                 let svc = inner
                     .control
                     .build(dns, client_metrics, identity)
                     .new_service(());
 
-                let (span_sink, spans_rx) = mpsc::channel(Self::SPAN_BUFFER_CAPACITY);
-                let spans_rx = ReceiverStream::new(spans_rx);
-
-                let task = {
-                    use self::proto::agent::common::v1 as oc;
-
-                    let node = oc::Node {
-                        identifier: Some(oc::ProcessIdentifier {
-                            host_name: inner.hostname.unwrap_or_default(),
-                            pid: std::process::id(),
-                            start_timestamp: Some(SystemTime::now().into()),
-                        }),
-                        service_info: Some(oc::ServiceInfo {
-                            name: Self::SERVICE_NAME.to_string(),
-                        }),
-                        attributes: inner.attributes,
-                        ..oc::Node::default()
-                    };
-
-                    let addr = addr.clone();
-                    Box::pin(
-                        opencensus::export_spans(svc, node, spans_rx, metrics)
-                            .instrument(tracing::debug_span!("opencensus", peer.addr = %addr)),
-                    )
+                let (span_sink, spans_rx) =
+                    queue::channel(Self::SPAN_BUFFER_CAPACITY, metrics.clone());
+
+                let task: Task = match inner.protocol {
+                    Protocol::OpenCensus => {
+                        use self::proto::agent::common::v1 as oc;
+
+                        let node = oc::Node {
+                            identifier: Some(oc::ProcessIdentifier {
+                                host_name: inner.hostname.unwrap_or_default(),
+                                pid: std::process::id(),
+                                start_timestamp: Some(SystemTime::now().into()),
+                            }),
+                            service_info: Some(oc::ServiceInfo {
+                                name: Self::SERVICE_NAME.to_string(),
+                            }),
+                            attributes: inner.attributes,
+                            ..oc::Node::default()
+                        };
+
+                        let addr = addr.clone();
+                        Box::pin(
+                            opencensus::export_spans(svc, node, spans_rx, metrics)
+                                .instrument(tracing::debug_span!("opencensus", peer.addr = %addr)),
+                        )
+                    }
+
+                    Protocol::Otlp => {
+                        let mut attributes = inner.attributes;
+                        attributes.insert("service.name".into(), Self::SERVICE_NAME.into());
+                        attributes.insert("process.pid".into(), std::process::id().to_string());
+                        if let Some(hostname) = inner.hostname {
+                            attributes.insert("host.name".into(), hostname);
+                        }
+                        let resource = opentelemetry::resource(attributes);
+
+                        let addr = addr.clone();
+                        Box::pin(
+                            opentelemetry::export_spans(svc, resource, spans_rx, metrics)
+                                .instrument(
+                                    tracing::debug_span!("opentelemetry", peer.addr = %addr),
+                                ),
+                        )
+                    }
                 };
 
                 Ok(OcCollector::Enabled(Box::new(EnabledCollector {
                     addr,
                     task,
                     span_sink,
+                    precedence,
                 })))
             }
         }
     }
 }
 
+impl Default for Protocol {
+    fn default() -> Self {
+        Self::OpenCensus
+    }
+}
+
 impl OcCollector {
     pub fn span_sink(&self) -> Option<SpanSink> {
         match self {
@@ -97,4 +136,11 @@ impl OcCollector {
             OcCollector::Enabled(inner) => Some(inner.span_sink.clone()),
         }
     }
+
+    pub fn trace_precedence(&self) -> http_tracing::Precedence {
+        match self {
+            OcCollector::Disabled => Default::default(),
+            OcCollector::Enabled(inner) => inner.precedence,
+        }
+    }
 }
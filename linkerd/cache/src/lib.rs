@@ -6,7 +6,10 @@ use parking_lot::RwLock;
 use std::{
     collections::{hash_map::Entry, HashMap},
     hash::Hash,
-    sync::{Arc, Weak},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Weak,
+    },
     task::{Context, Poll},
 };
 use tokio::{sync::Notify, time};
@@ -21,8 +24,13 @@ where
     inner: N,
     services: Arc<Services<T, N::Service>>,
     idle: time::Duration,
+    size: Size,
 }
 
+/// Observes the number of services held by one or more caches.
+#[derive(Clone, Debug, Default)]
+pub struct Size(Arc<AtomicUsize>);
+
 #[derive(Clone, Debug)]
 pub struct Cached<S>
 where
@@ -44,21 +52,31 @@ where
     N::Service: Send + Sync + 'static,
 {
     pub fn layer(idle: time::Duration) -> impl layer::Layer<N, Service = Self> + Clone {
-        layer::mk(move |inner| Self::new(idle, inner))
+        Self::layer_with_size(idle, Size::default())
+    }
+
+    /// Returns a layer whose caches record their number of services in `size`.
+    pub fn layer_with_size(
+        idle: time::Duration,
+        size: Size,
+    ) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self::new(idle, size.clone(), inner))
     }
 
-    fn new(idle: time::Duration, inner: N) -> Self {
+    fn new(idle: time::Duration, size: Size, inner: N) -> Self {
         let services = Arc::new(Services::default());
         Self {
             inner,
             services,
             idle,
+            size,
         }
     }
 
     fn spawn_idle(
         target: T,
         idle: time::Duration,
+        size: &Size,
         cache: &Arc<Services<T, N::Service>>,
     ) -> Arc<Notify> {
         // Spawn a background task that holds the handle. Every time the handle
@@ -70,16 +88,18 @@ where
             target,
             idle,
             handle.clone(),
+            size.clone(),
             Arc::downgrade(cache),
         ));
         handle
     }
 
-    #[instrument(level = "debug", skip(idle, reset, cache))]
+    #[instrument(level = "debug", skip(idle, reset, size, cache))]
     async fn evict(
         target: T,
         idle: time::Duration,
         mut reset: Arc<Notify>,
+        size: Size,
         cache: Weak<Services<T, N::Service>>,
     ) {
         // Wait for the handle to be notified before starting to track idleness.
@@ -103,6 +123,9 @@ where
                         Ok(_) => {
                             let removed = cache.write().remove(&target).is_some();
                             debug_assert!(removed, "Cache item must exist: {:?}", target);
+                            if removed {
+                                size.0.fetch_sub(1, Ordering::Release);
+                            }
                             debug!("Cache entry dropped");
                             return;
                         }
@@ -160,7 +183,9 @@ where
                     }
                     None => {
                         debug!(?target, "Replacing defunct service");
-                        let handle = Self::spawn_idle(target.clone(), self.idle, &self.services);
+                        // The entry is replaced, so the size is unchanged.
+                        let handle =
+                            Self::spawn_idle(target.clone(), self.idle, &self.size, &self.services);
                         let inner = self.inner.new_service(target);
                         entry.insert((inner.clone(), Arc::downgrade(&handle)));
                         Cached { inner, handle }
@@ -169,7 +194,9 @@ where
             }
             Entry::Vacant(entry) => {
                 debug!(?target, "Caching new service");
-                let handle = Self::spawn_idle(target.clone(), self.idle, &self.services);
+                let handle =
+                    Self::spawn_idle(target.clone(), self.idle, &self.size, &self.services);
+                self.size.0.fetch_add(1, Ordering::Release);
                 let inner = self.inner.new_service(target);
                 entry.insert((inner.clone(), Arc::downgrade(&handle)));
                 Cached { inner, handle }
@@ -178,6 +205,15 @@ where
     }
 }
 
+// === impl Size ===
+
+impl Size {
+    /// Returns the number of services currently cached.
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
 // === impl Cached ===
 
 impl<Req, S> tower::Service<Req> for Cached<S>
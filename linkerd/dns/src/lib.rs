@@ -3,21 +3,39 @@
 
 pub use linkerd_dns_name::{InvalidName, Name, Suffix};
 use linkerd_error::Error;
-use std::{fmt, net};
+use std::{fmt, net, time::Duration};
 use thiserror::Error;
 use tokio::time::{self, Instant};
 use tracing::{debug, trace};
-use trust_dns_resolver::{
-    config::ResolverConfig, proto::rr::rdata, system_conf, AsyncResolver, TokioAsyncResolver,
-};
 pub use trust_dns_resolver::{
     config::ResolverOpts,
     error::{ResolveError, ResolveErrorKind},
 };
+use trust_dns_resolver::{
+    config::{LookupIpStrategy, ResolverConfig},
+    proto::{op::ResponseCode, rr::rdata},
+    system_conf, AsyncResolver, TokioAsyncResolver,
+};
 
 #[derive(Clone)]
 pub struct Resolver {
     dns: TokioAsyncResolver,
+    negative_ttl: Duration,
+    ip_preference: IpPreference,
+}
+
+/// Determines which address families are resolved and which is preferred
+/// when a name has both IPv4 and IPv6 addresses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IpPreference {
+    /// Resolves both IPv4 and IPv6 addresses, preferring IPv4.
+    Ipv4,
+    /// Resolves both IPv4 and IPv6 addresses, preferring IPv6.
+    Ipv6,
+    /// Resolves only IPv4 addresses.
+    Ipv4Only,
+    /// Resolves only IPv6 addresses.
+    Ipv6Only,
 }
 
 pub trait ConfigureResolver {
@@ -28,6 +46,18 @@ pub trait ConfigureResolver {
 #[error("invalid SRV record {:?}", self.0)]
 struct InvalidSrv(rdata::SRV);
 
+/// Indicates that a name does not exist (i.e. a lookup returned NXDOMAIN).
+#[derive(Debug, Clone, Error)]
+#[error("{name} does not exist")]
+pub struct NxDomain {
+    name: Name,
+    ttl: Duration,
+}
+
+/// How long names that do not exist are cached, unless configured otherwise.
+/// Applies when an NXDOMAIN response does not indicate its own TTL.
+pub const DEFAULT_NEGATIVE_TTL: Duration = Duration::from_secs(5);
+
 impl Resolver {
     /// Construct a new `Resolver` from environment variables and system
     /// configuration.
@@ -46,13 +76,31 @@ impl Resolver {
         Ok(Self::new(config, opts))
     }
 
+    /// Lookups' TTLs are bounded by the options' positive and negative TTL
+    /// bounds.
     pub fn new(config: ResolverConfig, mut opts: ResolverOpts) -> Self {
         // Disable Trust-DNS's caching.
         opts.cache_size = 0;
+        let negative_ttl = opts.negative_min_ttl.unwrap_or(DEFAULT_NEGATIVE_TTL);
         // This function is synchronous, but needs to be called within the Tokio
         // 0.2 runtime context, since it gets a handle.
         let dns = AsyncResolver::tokio(config, opts).expect("system DNS config must be valid");
-        Resolver { dns }
+        Resolver {
+            dns,
+            negative_ttl,
+            ip_preference: IpPreference::default(),
+        }
+    }
+
+    /// Orders resolved addresses by the given preference.
+    ///
+    /// The preference's lookup strategy must also be applied to the
+    /// resolver's options so that the appropriate records are queried.
+    pub fn with_ip_preference(self, ip_preference: IpPreference) -> Self {
+        Self {
+            ip_preference,
+            ..self
+        }
     }
 
     /// Resolves a name to a set of addresses, preferring SRV records to normal A
@@ -65,15 +113,58 @@ impl Resolver {
         match self.resolve_srv(name).await {
             Ok(res) => Ok(res),
             Err(e) if e.is::<InvalidSrv>() => {
-                let (ips, delay) = self.resolve_a(name).await?;
+                let (ips, delay) = self
+                    .resolve_a(name)
+                    .await
+                    .map_err(|e| self.nxdomain(name, e.into()))?;
                 let addrs = ips
                     .into_iter()
                     .map(|ip| net::SocketAddr::new(ip, default_port))
                     .collect();
                 Ok((addrs, delay))
             }
-            Err(e) => Err(e),
+            Err(e) => Err(self.nxdomain(name, e)),
+        }
+    }
+
+    /// Resolves a name's A and AAAA records, ordered by the configured address
+    /// family preference.
+    pub async fn resolve_ips(&self, name: &Name) -> Result<Vec<net::IpAddr>, Error> {
+        let (ips, _) = self
+            .resolve_a(name)
+            .await
+            .map_err(|e| self.nxdomain(name, e.into()))?;
+        Ok(ips)
+    }
+
+    /// Replaces NXDOMAIN errors with an `NxDomain` that expires after the
+    /// response's (bounded) negative TTL.
+    fn nxdomain(&self, name: &Name, error: Error) -> Error {
+        if let Some(e) = error.downcast_ref::<ResolveError>() {
+            if let ResolveErrorKind::NoRecordsFound {
+                response_code: ResponseCode::NXDomain,
+                negative_ttl,
+                ..
+            } = e.kind()
+            {
+                let ttl = negative_ttl
+                    .map(|secs| Duration::from_secs(secs.into()))
+                    .unwrap_or(self.negative_ttl);
+                return NxDomain {
+                    name: name.clone(),
+                    ttl,
+                }
+                .into();
+            }
         }
+        error
+    }
+
+    /// Returns a timer that fires when a lookup expires.
+    fn expiry(valid_until: std::time::Instant) -> time::Sleep {
+        let valid_until = Instant::from_std(valid_until);
+        trace!(ttl = ?valid_until.saturating_duration_since(Instant::now()), "Lookup expires");
+        time::sleep_until(valid_until)
     }
 
     async fn resolve_a(
@@ -82,21 +173,21 @@ impl Resolver {
     ) -> Result<(Vec<net::IpAddr>, time::Sleep), ResolveError> {
         debug!(%name, "resolve_a");
         let lookup = self.dns.lookup_ip(name.as_ref()).await?;
-        let valid_until = Instant::from_std(lookup.valid_until());
-        let ips = lookup.iter().collect::<Vec<_>>();
-        Ok((ips, time::sleep_until(valid_until)))
+        let expiry = Self::expiry(lookup.valid_until());
+        let ips = self.ip_preference.order(lookup.iter().collect(), |ip| *ip);
+        Ok((ips, expiry))
     }
 
     async fn resolve_srv(&self, name: &Name) -> Result<(Vec<net::SocketAddr>, time::Sleep), Error> {
         debug!(%name, "resolve_srv");
         let srv = self.dns.srv_lookup(name.as_ref()).await?;
-        let valid_until = Instant::from_std(srv.as_lookup().valid_until());
+        let expiry = Self::expiry(srv.as_lookup().valid_until());
         let addrs = srv
             .into_iter()
             .map(Self::srv_to_socket_addr)
             .collect::<Result<_, InvalidSrv>>()?;
         debug!(?addrs);
-        Ok((addrs, time::sleep_until(valid_until)))
+        Ok((addrs, expiry))
     }
 
     // XXX We need to convert the SRV records to an IP addr manually,
@@ -118,6 +209,79 @@ impl Resolver {
     }
 }
 
+// === impl IpPreference ===
+
+impl Default for IpPreference {
+    fn default() -> Self {
+        Self::Ipv4
+    }
+}
+
+impl IpPreference {
+    /// Returns the strategy with which the resolver should look up addresses.
+    pub fn lookup_strategy(self) -> LookupIpStrategy {
+        match self {
+            Self::Ipv4 | Self::Ipv6 => LookupIpStrategy::Ipv4AndIpv6,
+            Self::Ipv4Only => LookupIpStrategy::Ipv4Only,
+            Self::Ipv6Only => LookupIpStrategy::Ipv6Only,
+        }
+    }
+
+    /// Orders socket addresses so that address families alternate, starting
+    /// with the preferred family (as described by RFC 8305). Addresses in
+    /// excluded families are dropped.
+    pub fn order_addrs(self, addrs: Vec<net::SocketAddr>) -> Vec<net::SocketAddr> {
+        self.order(addrs, net::SocketAddr::ip)
+    }
+
+    fn order<A>(self, addrs: Vec<A>, ip: impl Fn(&A) -> net::IpAddr) -> Vec<A> {
+        let (v4, v6): (Vec<A>, Vec<A>) = addrs.into_iter().partition(|a| ip(a).is_ipv4());
+        let (preferred, other) = match self {
+            Self::Ipv4 => (v4, v6),
+            Self::Ipv6 => (v6, v4),
+            Self::Ipv4Only => (v4, Vec::new()),
+            Self::Ipv6Only => (v6, Vec::new()),
+        };
+
+        let mut ordered = Vec::with_capacity(preferred.len() + other.len());
+        let mut preferred = preferred.into_iter();
+        let mut other = other.into_iter();
+        loop {
+            match (preferred.next(), other.next()) {
+                (None, None) => return ordered,
+                (a, b) => ordered.extend(a.into_iter().chain(b)),
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for IpPreference {
+    type Err = InvalidIpPreference;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ipv4" => Ok(Self::Ipv4),
+            "ipv6" => Ok(Self::Ipv6),
+            "ipv4-only" => Ok(Self::Ipv4Only),
+            "ipv6-only" => Ok(Self::Ipv6Only),
+            _ => Err(InvalidIpPreference(())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+#[error("IP preference must be one of: ipv4, ipv6, ipv4-only, ipv6-only")]
+pub struct InvalidIpPreference(());
+
+// === impl NxDomain ===
+
+impl NxDomain {
+    /// Returns how long the name should be assumed not to exist.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+}
+
 /// Note: `AsyncResolver` does not implement `Debug`, so we must manually
 ///       implement this.
 impl fmt::Debug for Resolver {
@@ -130,8 +294,29 @@ impl fmt::Debug for Resolver {
 
 #[cfg(test)]
 mod tests {
-    use super::{Name, Suffix};
-    use std::str::FromStr;
+    use super::{IpPreference, Name, Suffix};
+    use std::{net::SocketAddr, str::FromStr};
+
+    #[test]
+    fn ip_preference_order() {
+        let v4 = |p: u16| SocketAddr::from(([10, 0, 0, 1], p));
+        let v6 = |p: u16| SocketAddr::from(([0xfd00, 0, 0, 0, 0, 0, 0, 1], p));
+        let addrs = vec![v4(1), v4(2), v4(3), v6(1)];
+
+        assert_eq!(
+            IpPreference::Ipv4.order_addrs(addrs.clone()),
+            vec![v4(1), v6(1), v4(2), v4(3)]
+        );
+        assert_eq!(
+            IpPreference::Ipv6.order_addrs(addrs.clone()),
+            vec![v6(1), v4(1), v4(2), v4(3)]
+        );
+        assert_eq!(
+            IpPreference::Ipv4Only.order_addrs(addrs.clone()),
+            vec![v4(1), v4(2), v4(3)]
+        );
+        assert_eq!(IpPreference::Ipv6Only.order_addrs(addrs), vec![v6(1)]);
+    }
 
     #[test]
     fn test_dns_name_parsing() {
@@ -1,16 +1,20 @@
 //! A utility for copying data bi-directionally between two sockets.
 //!
-//! This module uses unsafe code to implement [`BufMut`].
+//! This module uses unsafe code to implement [`BufMut`] and to splice data
+//! between sockets.
 
 #![deny(warnings, rust_2018_idioms)]
 
+mod splice;
+
+use self::splice::{Pipe, Splicer};
 use bytes::{Buf, BufMut};
 use futures::ready;
 use linkerd_io::{self as io, AsyncRead, AsyncWrite};
 use pin_project::pin_project;
 use std::task::{Context, Poll};
 use std::{future::Future, pin::Pin};
-use tracing::{error, trace};
+use tracing::{debug, error, trace};
 
 /// A future piping data bi-directionally to In and Out.
 #[pin_project]
@@ -28,6 +32,8 @@ struct HalfDuplex<T> {
     io: T,
     direction: &'static str,
     flushing: bool,
+    // Set when data may be spliced directly between sockets.
+    splice: Option<Splicer<T>>,
 }
 
 /// A buffer used to copy bytes from one IO to another.
@@ -66,6 +72,20 @@ where
             half_out: HalfDuplex::new(out_io, "server->client"),
         }
     }
+
+    /// Pipes data like [`Duplex::new`], except that data is moved directly
+    /// between the underlying sockets with `splice(2)` whenever both sides
+    /// expose them. Otherwise, data is copied through a userspace buffer.
+    pub fn splice(in_io: In, out_io: Out) -> Self
+    where
+        In: io::Splice,
+        Out: io::Splice,
+    {
+        Duplex {
+            half_in: HalfDuplex::new(in_io, "client->server").with_splice(),
+            half_out: HalfDuplex::new(out_io, "server->client").with_splice(),
+        }
+    }
 }
 
 impl<In, Out> Future for Duplex<In, Out>
@@ -102,9 +122,18 @@ where
             io,
             direction,
             flushing: false,
+            splice: None,
         }
     }
 
+    fn with_splice(mut self) -> Self
+    where
+        T: io::Splice,
+    {
+        self.splice = Some(Splicer::new());
+        self
+    }
+
     /// Reads data from `self`, buffering it, and writing it to `dst.
     ///
     /// Returns ready when the stream has shutdown such that no more data may be
@@ -130,6 +159,10 @@ where
             ready!(self.poll_flush(dst, cx))?;
         }
 
+        if let Some(poll) = self.poll_splice(dst, cx) {
+            return poll;
+        }
+
         // `needs_flush` is set to true if the buffer is written so that, if a
         // read returns pending, that data may be flushed.
         let mut needs_flush = false;
@@ -197,6 +230,61 @@ where
         }
     }
 
+    /// Moves data directly from the underlying socket to the destination's
+    /// socket, if both are exposed and no data is buffered.
+    ///
+    /// Returns `None` if data must be copied through the buffer instead,
+    /// including once the socket has closed, so that the destination is shut
+    /// down by the copy path.
+    fn poll_splice<U>(
+        &mut self,
+        dst: &mut HalfDuplex<U>,
+        cx: &mut Context<'_>,
+    ) -> Option<io::Poll<()>> {
+        let splicer = self.splice.as_mut()?;
+        let dst_splicer = dst.splice.as_ref()?;
+        match self.buf.as_ref() {
+            Some(buf) if !buf.has_remaining() => {}
+            _ => return None,
+        }
+        let (src_socket, dst_socket) =
+            match ((splicer.socket)(&self.io), (dst_splicer.socket)(&dst.io)) {
+                (Some(src), Some(dst)) => (src, dst),
+                _ => return None,
+            };
+
+        if splicer.pipe.is_none() {
+            match Pipe::new() {
+                Ok(pipe) => splicer.pipe = Some(pipe),
+                Err(error) => {
+                    debug!(direction = %self.direction, %error, "Cannot splice; copying instead");
+                    self.splice = None;
+                    return None;
+                }
+            }
+        }
+        let pipe = splicer.pipe.as_mut().expect("pipe must be set");
+
+        let (mut read, mut written) = (0, 0);
+        let poll = pipe.poll_transfer(cx, src_socket, dst_socket, &mut read, &mut written);
+        trace!(direction = %self.direction, read, written, "spliced");
+        if read > 0 {
+            (splicer.record_read)(&mut self.io, read);
+        }
+        if written > 0 {
+            (dst_splicer.record_write)(&mut dst.io, written);
+        }
+
+        match poll {
+            Poll::Ready(Ok(())) => {
+                trace!(direction = %self.direction, "eof");
+                self.buf = None;
+                None
+            }
+            poll => Some(poll),
+        }
+    }
+
     /// Attempts to read and buffer data from the underlying stream, returning
     /// the number of bytes read. If the buffer already has data, no new data
     /// will be read.
@@ -336,3 +424,43 @@ unsafe impl BufMut for CopyBuf {
         self.write_pos += cnt;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn connected() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        (client, server)
+    }
+
+    #[tokio::test]
+    async fn splices_between_sockets() {
+        let (mut client, client_server) = connected().await;
+        let (server_client, mut server) = connected().await;
+        let duplex = tokio::spawn(Duplex::splice(client_server, server_client));
+
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+
+        server.write_all(b"world").await.unwrap();
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"world");
+
+        // Each half is shut down once its source closes.
+        client.shutdown().await.unwrap();
+        assert_eq!(server.read(&mut buf).await.unwrap(), 0);
+        server.shutdown().await.unwrap();
+        assert_eq!(client.read(&mut buf).await.unwrap(), 0);
+
+        duplex.await.unwrap().unwrap();
+    }
+}
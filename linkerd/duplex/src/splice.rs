@@ -0,0 +1,167 @@
+//! Moves data between sockets through a pipe with `splice(2)`, so that it is
+//! never copied into userspace.
+
+use linkerd_io as io;
+use tokio::net::TcpStream;
+
+#[cfg(target_os = "linux")]
+pub(crate) use self::linux::Pipe;
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) use self::unsupported::Pipe;
+
+/// Returns the underlying socket of an I/O stream, if it may be spliced.
+pub(crate) type Socket<T> = fn(&T) -> Option<&TcpStream>;
+
+/// Records the number of bytes spliced to or from an I/O stream.
+pub(crate) type Record<T> = fn(&mut T, usize);
+
+/// The splicing state of one half of a duplex.
+pub(crate) struct Splicer<T> {
+    pub(crate) socket: Socket<T>,
+    pub(crate) record_read: Record<T>,
+    pub(crate) record_write: Record<T>,
+    pub(crate) pipe: Option<Pipe>,
+}
+
+impl<T: io::Splice> Splicer<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            socket: T::splice_socket,
+            record_read: T::record_splice_read,
+            record_write: T::record_splice_write,
+            pipe: None,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use futures::ready;
+    use linkerd_io as io;
+    use std::{
+        os::unix::io::{AsRawFd, RawFd},
+        ptr,
+        task::{Context, Poll},
+    };
+    use tokio::{io::Interest, net::TcpStream};
+
+    /// The default capacity of a pipe on Linux.
+    const PIPE_SIZE: usize = 64 * 1024;
+
+    #[derive(Debug)]
+    pub(crate) struct Pipe {
+        read: RawFd,
+        write: RawFd,
+        /// The number of bytes in the pipe that have not been written.
+        len: usize,
+    }
+
+    impl Pipe {
+        pub(crate) fn new() -> io::Result<Self> {
+            let mut fds = [0; 2];
+            let res = unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) };
+            if res == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self {
+                read: fds[0],
+                write: fds[1],
+                len: 0,
+            })
+        }
+
+        /// Moves data from `src` to `dst` until `src` is closed.
+        ///
+        /// The number of bytes read and written are added to `read` and
+        /// `written`, respectively.
+        pub(crate) fn poll_transfer(
+            &mut self,
+            cx: &mut Context<'_>,
+            src: &TcpStream,
+            dst: &TcpStream,
+            read: &mut usize,
+            written: &mut usize,
+        ) -> io::Poll<()> {
+            loop {
+                // Drain the pipe before reading more data so that a read never
+                // blocks on the pipe and is only pending on the socket.
+                while self.len > 0 {
+                    ready!(dst.poll_write_ready(cx))?;
+                    let (pipe, len) = (self.read, self.len);
+                    match dst.try_io(Interest::WRITABLE, || splice(pipe, dst.as_raw_fd(), len)) {
+                        Ok(0) => return Poll::Ready(Err(crate::write_zero())),
+                        Ok(sz) => {
+                            self.len -= sz;
+                            *written += sz;
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                        Err(e) => return Poll::Ready(Err(e)),
+                    }
+                }
+
+                ready!(src.poll_read_ready(cx))?;
+                let pipe = self.write;
+                match src.try_io(Interest::READABLE, || {
+                    splice(src.as_raw_fd(), pipe, PIPE_SIZE)
+                }) {
+                    Ok(0) => return Poll::Ready(Ok(())),
+                    Ok(sz) => {
+                        self.len = sz;
+                        *read += sz;
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                    Err(e) => return Poll::Ready(Err(e)),
+                }
+            }
+        }
+    }
+
+    impl Drop for Pipe {
+        fn drop(&mut self) {
+            unsafe {
+                libc::close(self.read);
+                libc::close(self.write);
+            }
+        }
+    }
+
+    fn splice(from: RawFd, to: RawFd, len: usize) -> io::Result<usize> {
+        let flags = libc::SPLICE_F_MOVE | libc::SPLICE_F_NONBLOCK;
+        let sz = unsafe { libc::splice(from, ptr::null_mut(), to, ptr::null_mut(), len, flags) };
+        if sz == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(sz as usize)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod unsupported {
+    use linkerd_io as io;
+    use std::task::Context;
+    use tokio::net::TcpStream;
+
+    #[derive(Debug)]
+    pub(crate) enum Pipe {}
+
+    impl Pipe {
+        pub(crate) fn new() -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "splice is only supported on Linux",
+            ))
+        }
+
+        pub(crate) fn poll_transfer(
+            &mut self,
+            _: &mut Context<'_>,
+            _: &TcpStream,
+            _: &TcpStream,
+            _: &mut usize,
+            _: &mut usize,
+        ) -> io::Poll<()> {
+            match *self {}
+        }
+    }
+}
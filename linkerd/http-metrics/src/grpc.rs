@@ -0,0 +1,496 @@
+//! Message-level metrics for gRPC streams.
+//!
+//! Request metrics describe each gRPC stream as a single request whose latency
+//! spans the lifetime of the stream, so a long-lived streaming call looks like
+//! one very slow request. These metrics instead count the length-prefixed
+//! messages sent and received on each stream as they are framed, and record
+//! each stream's duration once it completes.
+
+use super::{Prefixed, Registry, Report};
+use bytes::{Buf, Bytes};
+use futures::{ready, TryFuture};
+use http_body::Body;
+use linkerd_error::Error;
+use linkerd_metrics::{
+    latency, Counter, FmtLabels, FmtMetrics, Histogram, LastUpdate, Metric, NewMetrics,
+};
+use linkerd_stack::{layer, NewService, Proxy};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    fmt,
+    hash::Hash,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tracing::trace;
+
+#[derive(Debug)]
+pub struct GrpcStreams<T>(Registry<T, Metrics>)
+where
+    T: Hash + Eq;
+
+/// Wraps services to record gRPC stream metrics.
+pub type NewGrpcStreamMetrics<N, K, S> = NewMetrics<N, K, Mutex<Metrics>, GrpcStreamMetrics<S>>;
+
+/// A middleware that records message-level metrics for gRPC requests.
+#[derive(Clone, Debug)]
+pub struct GrpcStreamMetrics<S> {
+    metrics: Arc<Mutex<Metrics>>,
+    inner: S,
+}
+
+#[derive(Debug)]
+pub struct Metrics {
+    last_update: Instant,
+    messages_sent: Counter,
+    messages_received: Counter,
+    stream_duration: Histogram<latency::Ms>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    stream: Option<Arc<Stream>>,
+    #[pin]
+    inner: F,
+}
+
+/// Counts the gRPC messages framed in an inner body.
+#[pin_project]
+#[derive(Debug)]
+pub struct MessageBody<B> {
+    stream: Option<Arc<Stream>>,
+    direction: Direction,
+    framer: Framer,
+    #[pin]
+    inner: B,
+}
+
+/// Records a stream's duration once both its request and response bodies have
+/// completed.
+#[derive(Debug)]
+struct Stream {
+    metrics: Arc<Mutex<Metrics>>,
+    opened_at: Instant,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Direction {
+    Sent,
+    Received,
+}
+
+/// Tracks gRPC message boundaries across data frames.
+///
+/// Each message is prefixed by a one-byte compression flag and a four-byte,
+/// big-endian message length.
+#[derive(Debug, Default)]
+struct Framer {
+    header: [u8; 5],
+    header_read: usize,
+    remaining: usize,
+}
+
+// === impl GrpcStreams ===
+
+impl<T: Hash + Eq> Default for GrpcStreams<T> {
+    fn default() -> Self {
+        GrpcStreams(Registry::default())
+    }
+}
+
+impl<T: Hash + Eq> GrpcStreams<T> {
+    pub fn into_report(self, retain_idle: Duration) -> Report<T, Metrics> {
+        Report::new(retain_idle, self.0)
+    }
+
+    pub fn to_layer<N, Tgt>(
+        &self,
+    ) -> impl layer::Layer<N, Service = NewGrpcStreamMetrics<N, T, N::Service>> + Clone
+    where
+        N: NewService<Tgt>,
+    {
+        NewMetrics::layer(self.0.clone())
+    }
+}
+
+impl<T: Hash + Eq> Clone for GrpcStreams<T> {
+    fn clone(&self) -> Self {
+        GrpcStreams(self.0.clone())
+    }
+}
+
+// === impl GrpcStreamMetrics ===
+
+impl<S> From<(S, Arc<Mutex<Metrics>>)> for GrpcStreamMetrics<S> {
+    fn from((inner, metrics): (S, Arc<Mutex<Metrics>>)) -> Self {
+        Self { metrics, inner }
+    }
+}
+
+impl<S> GrpcStreamMetrics<S> {
+    fn wrap_request<A>(
+        &self,
+        req: http::Request<A>,
+    ) -> (Option<Arc<Stream>>, http::Request<MessageBody<A>>) {
+        let stream = if is_grpc(req.headers()) {
+            Some(Arc::new(Stream {
+                metrics: self.metrics.clone(),
+                opened_at: Instant::now(),
+            }))
+        } else {
+            None
+        };
+
+        let req = req.map(|inner| MessageBody::new(stream.clone(), Direction::Sent, inner));
+        (stream, req)
+    }
+}
+
+impl<P, S, A, B> Proxy<http::Request<A>, S> for GrpcStreamMetrics<P>
+where
+    P: Proxy<http::Request<MessageBody<A>>, S, Response = http::Response<B>>,
+    S: tower::Service<P::Request>,
+    A: Body,
+    B: Body,
+{
+    type Request = P::Request;
+    type Response = http::Response<MessageBody<B>>;
+    type Error = Error;
+    type Future = ResponseFuture<P::Future>;
+
+    fn proxy(&self, svc: &mut S, req: http::Request<A>) -> Self::Future {
+        let (stream, req) = self.wrap_request(req);
+        ResponseFuture {
+            stream,
+            inner: self.inner.proxy(svc, req),
+        }
+    }
+}
+
+impl<S, A, B> tower::Service<http::Request<A>> for GrpcStreamMetrics<S>
+where
+    S: tower::Service<http::Request<MessageBody<A>>, Response = http::Response<B>>,
+    S::Error: Into<Error>,
+    A: Body,
+    B: Body,
+{
+    type Response = http::Response<MessageBody<B>>;
+    type Error = Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let (stream, req) = self.wrap_request(req);
+        ResponseFuture {
+            stream,
+            inner: self.inner.call(req),
+        }
+    }
+}
+
+fn is_grpc(headers: &http::HeaderMap) -> bool {
+    headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+        .map(|ct| {
+            ct == "application/grpc"
+                || ct.starts_with("application/grpc+")
+                || ct.starts_with("application/grpc;")
+        })
+        .unwrap_or(false)
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B> std::future::Future for ResponseFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+    F::Error: Into<Error>,
+    B: Body,
+{
+    type Output = Result<http::Response<MessageBody<B>>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let rsp = ready!(this.inner.try_poll(cx)).map_err(Into::into)?;
+        let stream = this.stream.take();
+        Poll::Ready(Ok(
+            rsp.map(|inner| MessageBody::new(stream, Direction::Received, inner))
+        ))
+    }
+}
+
+// === impl MessageBody ===
+
+impl<B> MessageBody<B> {
+    fn new(stream: Option<Arc<Stream>>, direction: Direction, inner: B) -> Self {
+        Self {
+            stream,
+            direction,
+            framer: Framer::default(),
+            inner,
+        }
+    }
+}
+
+impl<B> Body for MessageBody<B>
+where
+    B: Body,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let mut data = match ready!(this.inner.poll_data(cx)) {
+            Some(Ok(data)) => data,
+            Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+            None => return Poll::Ready(None),
+        };
+
+        let bytes = data.copy_to_bytes(data.remaining());
+        if let Some(stream) = this.stream.as_ref() {
+            let messages = this.framer.decode(&bytes);
+            if messages > 0 {
+                stream.record(*this.direction, messages);
+            }
+        }
+
+        Poll::Ready(Some(Ok(bytes)))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(this.inner.poll_trailers(cx));
+        // The body has completed, so it no longer holds the stream open.
+        this.stream.take();
+        Poll::Ready(trailers)
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl<B: Default> Default for MessageBody<B> {
+    fn default() -> Self {
+        Self::new(None, Direction::Received, B::default())
+    }
+}
+
+// === impl Stream ===
+
+impl Stream {
+    fn record(&self, direction: Direction, messages: u64) {
+        let mut metrics = self.metrics.lock();
+        metrics.last_update = Instant::now();
+        match direction {
+            Direction::Sent => metrics.messages_sent.add(messages),
+            Direction::Received => metrics.messages_received.add(messages),
+        }
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        let now = Instant::now();
+        let mut metrics = self.metrics.lock();
+        metrics.last_update = now;
+        metrics.stream_duration.add(now - self.opened_at);
+    }
+}
+
+// === impl Framer ===
+
+impl Framer {
+    /// Consumes a chunk of a message stream, returning the number of messages
+    /// that were completed by it.
+    fn decode(&mut self, mut buf: &[u8]) -> u64 {
+        let mut messages = 0;
+        while !buf.is_empty() {
+            if self.header_read < self.header.len() {
+                let n = (self.header.len() - self.header_read).min(buf.len());
+                self.header[self.header_read..self.header_read + n].copy_from_slice(&buf[..n]);
+                self.header_read += n;
+                buf = &buf[n..];
+                if self.header_read == self.header.len() {
+                    let mut len = [0u8; 4];
+                    len.copy_from_slice(&self.header[1..]);
+                    self.remaining = u32::from_be_bytes(len) as usize;
+                }
+            } else {
+                let n = self.remaining.min(buf.len());
+                self.remaining -= n;
+                buf = &buf[n..];
+            }
+
+            if self.header_read == self.header.len() && self.remaining == 0 {
+                messages += 1;
+                self.header_read = 0;
+            }
+        }
+        messages
+    }
+}
+
+// === impl Metrics ===
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            last_update: Instant::now(),
+            messages_sent: Counter::default(),
+            messages_received: Counter::default(),
+            stream_duration: Histogram::default(),
+        }
+    }
+}
+
+impl LastUpdate for Metrics {
+    fn last_update(&self) -> Instant {
+        self.last_update
+    }
+}
+
+// === impl Report ===
+
+impl<T> Report<T, Metrics>
+where
+    T: FmtLabels + Hash + Eq,
+{
+    fn messages_sent_total(&self) -> Metric<'_, Prefixed<'_, &'static str>, Counter> {
+        Metric::new(
+            self.prefix_key("grpc_messages_sent_total"),
+            "Total count of gRPC messages sent on request streams.",
+        )
+    }
+
+    fn messages_received_total(&self) -> Metric<'_, Prefixed<'_, &'static str>, Counter> {
+        Metric::new(
+            self.prefix_key("grpc_messages_received_total"),
+            "Total count of gRPC messages received on response streams.",
+        )
+    }
+
+    fn stream_duration_ms(&self) -> Metric<'_, Prefixed<'_, &'static str>, Histogram<latency::Ms>> {
+        Metric::new(
+            self.prefix_key("grpc_stream_duration_ms"),
+            "Elapsed times between a gRPC stream being opened and both of its \
+             request and response streams completing",
+        )
+    }
+}
+
+impl<T> FmtMetrics for Report<T, Metrics>
+where
+    T: FmtLabels + Hash + Eq,
+{
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut registry = self.registry.lock();
+        trace!(
+            prefix = %self.prefix,
+            targets = %registry.len(),
+            "Formatting gRPC stream metrics",
+        );
+
+        if registry.is_empty() {
+            return Ok(());
+        }
+
+        let metric = self.messages_sent_total();
+        metric.fmt_help(f)?;
+        registry.fmt_by_locked(f, metric, |m| &m.messages_sent)?;
+
+        let metric = self.messages_received_total();
+        metric.fmt_help(f)?;
+        registry.fmt_by_locked(f, metric, |m| &m.messages_received)?;
+
+        if self.include_latencies {
+            let metric = self.stream_duration_ms();
+            metric.fmt_help(f)?;
+            registry.fmt_by_locked(f, metric, |m| &m.stream_duration)?;
+        }
+
+        registry.retain_since(Instant::now() - self.retain_idle);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::*;
+
+    fn streams(metrics: &Mutex<Metrics>) -> f64 {
+        let metrics = metrics.lock();
+        metrics
+            .stream_duration
+            .into_iter()
+            .map(|(_, count)| count.value())
+            .sum()
+    }
+
+    fn message(len: u32) -> Vec<u8> {
+        let mut buf = vec![0];
+        buf.extend_from_slice(&len.to_be_bytes());
+        buf.extend(std::iter::repeat(1).take(len as usize));
+        buf
+    }
+
+    #[test]
+    fn counts_messages_across_frames() {
+        let mut framer = Framer::default();
+
+        let mut buf = message(3);
+        buf.extend(message(0));
+        buf.extend(message(10));
+        assert_eq!(framer.decode(&buf), 3);
+
+        // A message split across several data frames is counted once it
+        // completes.
+        let buf = message(8);
+        assert_eq!(framer.decode(&buf[..2]), 0);
+        assert_eq!(framer.decode(&buf[2..7]), 0);
+        assert_eq!(framer.decode(&buf[7..12]), 0);
+        assert_eq!(framer.decode(&buf[12..]), 1);
+    }
+
+    #[test]
+    fn records_stream_duration_when_complete() {
+        let metrics = Arc::new(Mutex::new(Metrics::default()));
+        let stream = Arc::new(Stream {
+            metrics: metrics.clone(),
+            opened_at: Instant::now(),
+        });
+        let req = MessageBody::new(Some(stream.clone()), Direction::Sent, ());
+        let rsp = MessageBody::new(Some(stream), Direction::Received, ());
+
+        req.stream.as_ref().unwrap().record(Direction::Sent, 2);
+        rsp.stream.as_ref().unwrap().record(Direction::Received, 5);
+        assert_eq!(metrics.lock().messages_sent.value(), 2.0);
+        assert_eq!(metrics.lock().messages_received.value(), 5.0);
+
+        drop(req);
+        assert_eq!(streams(&metrics), 0.0);
+        drop(rsp);
+        assert_eq!(streams(&metrics), 1.0);
+    }
+}
@@ -22,6 +22,13 @@ pub struct Metrics {
     last_update: Instant,
     retryable: Counter,
     no_budget: Counter,
+    withdrawn: Counter,
+    deposited: Counter,
+    exhausted: Counter,
+    body_too_large: Counter,
+    delayed: Counter,
+    unprocessed: Counter,
+    unprocessed_no_budget: Counter,
 }
 
 struct NoBudgetLabel;
@@ -62,6 +69,54 @@ impl Handle {
             m.no_budget.incr();
         }
     }
+
+    /// Records a retry that was withdrawn from the route's retry budget.
+    pub fn incr_withdrawn(&self) {
+        let mut m = self.0.lock();
+        m.last_update = Instant::now();
+        m.withdrawn.incr();
+    }
+
+    /// Records a request that was deposited into the route's retry budget.
+    pub fn incr_deposited(&self) {
+        let mut m = self.0.lock();
+        m.last_update = Instant::now();
+        m.deposited.incr();
+    }
+
+    /// Records a retry that could not be withdrawn from the route's exhausted
+    /// retry budget.
+    pub fn incr_exhausted(&self) {
+        let mut m = self.0.lock();
+        m.last_update = Instant::now();
+        m.exhausted.incr();
+    }
+
+    /// Records a request that failed before the server could have processed
+    /// it.
+    pub fn incr_unprocessed(&self, has_budget: bool) {
+        let mut m = self.0.lock();
+        m.last_update = Instant::now();
+        m.unprocessed.incr();
+        if !has_budget {
+            m.unprocessed_no_budget.incr();
+        }
+    }
+
+    /// Records a request that could not be retried because its body could not
+    /// be buffered.
+    pub fn incr_body_too_large(&self) {
+        let mut m = self.0.lock();
+        m.last_update = Instant::now();
+        m.body_too_large.incr();
+    }
+
+    /// Records a retry that was delayed by the server's `Retry-After` hint.
+    pub fn incr_delayed(&self) {
+        let mut m = self.0.lock();
+        m.last_update = Instant::now();
+        m.delayed.incr();
+    }
 }
 
 // === impl Metrics ===
@@ -72,6 +127,13 @@ impl Default for Metrics {
             last_update: Instant::now(),
             retryable: Counter::default(),
             no_budget: Counter::default(),
+            withdrawn: Counter::default(),
+            deposited: Counter::default(),
+            exhausted: Counter::default(),
+            body_too_large: Counter::default(),
+            delayed: Counter::default(),
+            unprocessed: Counter::default(),
+            unprocessed_no_budget: Counter::default(),
         }
     }
 }
@@ -94,6 +156,48 @@ where
             "Total count of retryable HTTP responses.",
         )
     }
+
+    fn retry_budget_withdrawn_total(&self) -> Metric<'_, Prefixed<'_, &'static str>, Counter> {
+        Metric::new(
+            self.prefix_key("retry_budget_withdrawn_total"),
+            "Total count of HTTP retries withdrawn from their route's retry budget.",
+        )
+    }
+
+    fn retry_budget_deposited_total(&self) -> Metric<'_, Prefixed<'_, &'static str>, Counter> {
+        Metric::new(
+            self.prefix_key("retry_budget_deposited_total"),
+            "Total count of HTTP requests deposited into their route's retry budget.",
+        )
+    }
+
+    fn retry_budget_exhausted_total(&self) -> Metric<'_, Prefixed<'_, &'static str>, Counter> {
+        Metric::new(
+            self.prefix_key("retry_budget_exhausted_total"),
+            "Total count of HTTP retries that could not be withdrawn from their route's exhausted retry budget.",
+        )
+    }
+
+    fn unprocessed_total(&self) -> Metric<'_, Prefixed<'_, &'static str>, Counter> {
+        Metric::new(
+            self.prefix_key("retryable_unprocessed_total"),
+            "Total count of HTTP requests that failed before they could be processed and are therefore retryable.",
+        )
+    }
+
+    fn retry_body_too_large_total(&self) -> Metric<'_, Prefixed<'_, &'static str>, Counter> {
+        Metric::new(
+            self.prefix_key("retry_body_too_large_total"),
+            "Total count of HTTP requests that could not be retried because their bodies could not be buffered.",
+        )
+    }
+
+    fn retry_delayed_total(&self) -> Metric<'_, Prefixed<'_, &'static str>, Counter> {
+        Metric::new(
+            self.prefix_key("retry_delayed_total"),
+            "Total count of HTTP retries that were delayed by a Retry-After response header.",
+        )
+    }
 }
 
 impl<T> FmtMetrics for Report<T, Metrics>
@@ -121,6 +225,53 @@ where
                 .fmt_metric_labeled(f, &metric.name, (tgt, NoBudgetLabel))?;
         }
 
+        let metric = self.retry_budget_withdrawn_total();
+        metric.fmt_help(f)?;
+        for (tgt, tm) in registry.iter() {
+            tm.lock()
+                .withdrawn
+                .fmt_metric_labeled(f, &metric.name, tgt)?;
+        }
+
+        let metric = self.retry_budget_deposited_total();
+        metric.fmt_help(f)?;
+        for (tgt, tm) in registry.iter() {
+            tm.lock()
+                .deposited
+                .fmt_metric_labeled(f, &metric.name, tgt)?;
+        }
+
+        let metric = self.retry_budget_exhausted_total();
+        metric.fmt_help(f)?;
+        for (tgt, tm) in registry.iter() {
+            tm.lock()
+                .exhausted
+                .fmt_metric_labeled(f, &metric.name, tgt)?;
+        }
+
+        let metric = self.unprocessed_total();
+        metric.fmt_help(f)?;
+        for (tgt, tm) in registry.iter() {
+            let m = tm.lock();
+            m.unprocessed.fmt_metric_labeled(f, &metric.name, tgt)?;
+            m.unprocessed_no_budget
+                .fmt_metric_labeled(f, &metric.name, (tgt, NoBudgetLabel))?;
+        }
+
+        let metric = self.retry_body_too_large_total();
+        metric.fmt_help(f)?;
+        for (tgt, tm) in registry.iter() {
+            tm.lock()
+                .body_too_large
+                .fmt_metric_labeled(f, &metric.name, tgt)?;
+        }
+
+        let metric = self.retry_delayed_total();
+        metric.fmt_help(f)?;
+        for (tgt, tm) in registry.iter() {
+            tm.lock().delayed.fmt_metric_labeled(f, &metric.name, tgt)?;
+        }
+
         registry.retain_since(Instant::now() - self.retain_idle);
 
         Ok(())
@@ -0,0 +1,211 @@
+//! Validates the certificates of peers in federated trust domains.
+//!
+//! Each federated trust domain (e.g. that of another SPIFFE-based mesh) is
+//! configured with its own trust anchors. A peer's certificate is validated
+//! against the trust anchors of the trust domain of its identity, so that a
+//! federated domain's CA may not issue identities in any other trust domain.
+//! Peers whose identities are not in a federated trust domain are validated
+//! against the local trust anchors.
+
+use std::sync::Arc;
+use tokio_rustls::rustls;
+use tracing::debug;
+
+/// Maps trust domains to the trust anchors that issue their identities.
+#[derive(Clone, Default)]
+pub(crate) struct Federation(Arc<Vec<(String, rustls::RootCertStore)>>);
+
+/// Validates server certificates against the trust anchors of the trust domain
+/// of the expected server identity.
+pub(crate) struct ServerVerifier(Federation);
+
+/// Validates client certificates against the trust anchors of the trust domain
+/// of the client's identity.
+///
+/// Like `rustls::AllowAnyAnonymousOrAuthenticatedClient`, clients need not
+/// present a certificate.
+pub(crate) struct ClientVerifier {
+    local: Arc<dyn rustls::ClientCertVerifier>,
+    federated: Vec<(String, Arc<dyn rustls::ClientCertVerifier>)>,
+    subjects: rustls::DistinguishedNames,
+}
+
+// === impl Federation ===
+
+impl Federation {
+    pub(crate) fn new(domains: impl IntoIterator<Item = (String, rustls::RootCertStore)>) -> Self {
+        let domains = domains
+            .into_iter()
+            .map(|(domain, roots)| (domain.trim_end_matches('.').to_string(), roots))
+            .collect::<Vec<_>>();
+        Self(Arc::new(domains))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn domains(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|(domain, _)| domain.as_str())
+    }
+
+    /// Returns the trust anchors of the federated trust domain of the given
+    /// identity.
+    fn roots(&self, name: &str) -> Option<&rustls::RootCertStore> {
+        self.find(name).map(|(_, roots)| roots)
+    }
+
+    /// Returns the federated trust domain of the given identity, preferring the
+    /// most specific domain.
+    fn find(&self, name: &str) -> Option<&(String, rustls::RootCertStore)> {
+        self.0
+            .iter()
+            .filter(|(domain, _)| in_domain(name, domain))
+            .max_by_key(|(domain, _)| domain.len())
+    }
+}
+
+fn in_domain(name: &str, domain: &str) -> bool {
+    match name.strip_suffix(domain) {
+        Some(prefix) => prefix.is_empty() || prefix.ends_with('.'),
+        None => false,
+    }
+}
+
+// === impl ServerVerifier ===
+
+impl ServerVerifier {
+    pub(crate) fn new(federation: Federation) -> Self {
+        Self(federation)
+    }
+}
+
+impl rustls::ServerCertVerifier for ServerVerifier {
+    fn verify_server_cert(
+        &self,
+        roots: &rustls::RootCertStore,
+        presented_certs: &[rustls::Certificate],
+        dns_name: webpki::DNSNameRef<'_>,
+        ocsp_response: &[u8],
+    ) -> Result<rustls::ServerCertVerified, rustls::TLSError> {
+        let name: &str = dns_name.into();
+        let roots = match self.0.roots(name) {
+            Some(federated) => {
+                debug!(server.id = %name, "Validating federated server identity");
+                federated
+            }
+            None => roots,
+        };
+        rustls::WebPKIVerifier::new().verify_server_cert(
+            roots,
+            presented_certs,
+            dns_name,
+            ocsp_response,
+        )
+    }
+}
+
+// === impl ClientVerifier ===
+
+impl ClientVerifier {
+    pub(crate) fn new(local: &rustls::RootCertStore, federation: &Federation) -> Self {
+        let mut subjects = local.get_subjects();
+        let federated = federation
+            .0
+            .iter()
+            .map(|(domain, roots)| {
+                subjects.extend(roots.get_subjects());
+                let verifier = rustls::AllowAnyAnonymousOrAuthenticatedClient::new(roots.clone());
+                (domain.clone(), verifier)
+            })
+            .collect();
+        Self {
+            local: rustls::AllowAnyAnonymousOrAuthenticatedClient::new(local.clone()),
+            federated,
+            subjects,
+        }
+    }
+
+    fn verifier(&self, name: Option<&str>) -> &dyn rustls::ClientCertVerifier {
+        let federated = name.and_then(|name| {
+            self.federated
+                .iter()
+                .filter(|(domain, _)| in_domain(name, domain))
+                .max_by_key(|(domain, _)| domain.len())
+        });
+        match federated {
+            Some((_, verifier)) => &**verifier,
+            None => &*self.local,
+        }
+    }
+}
+
+impl rustls::ClientCertVerifier for ClientVerifier {
+    fn offer_client_auth(&self) -> bool {
+        true
+    }
+
+    fn client_auth_mandatory(&self, _sni: Option<&webpki::DNSName>) -> Option<bool> {
+        Some(false)
+    }
+
+    fn client_auth_root_subjects(
+        &self,
+        _sni: Option<&webpki::DNSName>,
+    ) -> Option<rustls::DistinguishedNames> {
+        Some(self.subjects.clone())
+    }
+
+    fn verify_client_cert(
+        &self,
+        presented_certs: &[rustls::Certificate],
+        sni: Option<&webpki::DNSName>,
+    ) -> Result<rustls::ClientCertVerified, rustls::TLSError> {
+        // The client's identity is not trusted until its certificate has been
+        // validated, but it determines which trust anchors validate it.
+        let end_cert = presented_certs
+            .first()
+            .and_then(|c| webpki::EndEntityCert::from(c.as_ref()).ok());
+        let dns_names = end_cert.as_ref().and_then(|c| c.dns_names().ok());
+        let name = dns_names
+            .as_ref()
+            .and_then(|names| names.first())
+            .and_then(|name| match name {
+                webpki::GeneralDNSNameRef::DNSName(n) => Some((*n).into()),
+                webpki::GeneralDNSNameRef::Wildcard(_) => None,
+            });
+        self.verifier(name).verify_client_cert(presented_certs, sni)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn federated_roots() {
+        let federation = Federation::new(vec![
+            ("example.org".to_string(), rustls::RootCertStore::empty()),
+            (
+                "prod.example.org.".to_string(),
+                rustls::RootCertStore::empty(),
+            ),
+        ]);
+        let domain = |name: &str| federation.find(name).map(|(d, _)| d.as_str());
+
+        assert_eq!(
+            domain("web.ns.serviceaccount.identity.linkerd.example.org"),
+            Some("example.org")
+        );
+        assert_eq!(
+            domain("web.ns.serviceaccount.identity.linkerd.prod.example.org"),
+            Some("prod.example.org")
+        );
+        assert_eq!(domain("example.org"), Some("example.org"));
+        assert_eq!(
+            domain("web.ns.serviceaccount.identity.linkerd.cluster.local"),
+            None
+        );
+        assert_eq!(domain("web.notexample.org"), None);
+    }
+}
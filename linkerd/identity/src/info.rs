@@ -0,0 +1,154 @@
+//! Describes certificates and trust anchors so that the local identity may be
+//! inspected without decoding certificates out-of-band.
+
+use crate::validity;
+use std::time::SystemTime;
+use tokio_rustls::rustls;
+use x509_parser::prelude::{FromDer, X509Name};
+
+/// Describes a certificate in the local identity's chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrtInfo {
+    /// The certificate's DNS subject alternative names. Empty for CA
+    /// certificates.
+    pub dns_names: Vec<String>,
+    pub issuer: Option<String>,
+    pub not_before: Option<SystemTime>,
+    pub not_after: Option<SystemTime>,
+    /// The hex-encoded SHA-256 fingerprint of the DER-encoded certificate.
+    pub sha256: String,
+}
+
+/// Describes a trust anchor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrustAnchorInfo {
+    pub subject: String,
+    /// The hex-encoded SHA-256 digest of the anchor's public key info.
+    pub spki_sha256: String,
+}
+
+const SEQUENCE: u8 = 0x30;
+
+// === impl CrtInfo ===
+
+impl CrtInfo {
+    pub(crate) fn new(crt: &rustls::Certificate) -> Self {
+        let der = crt.as_ref();
+        let tbs = validity::tbs(der);
+        Self {
+            dns_names: dns_names(der),
+            issuer: tbs.as_ref().map(|tbs| tbs.issuer.clone()),
+            not_before: tbs.as_ref().map(|tbs| tbs.not_before),
+            not_after: tbs.as_ref().map(|tbs| tbs.not_after),
+            sha256: sha256(der),
+        }
+    }
+}
+
+fn dns_names(der: &[u8]) -> Vec<String> {
+    use webpki::GeneralDNSNameRef;
+
+    let crt = match webpki::EndEntityCert::from(der) {
+        Ok(crt) => crt,
+        Err(_) => return vec![],
+    };
+    crt.dns_names()
+        .map(|names| {
+            names
+                .into_iter()
+                .filter_map(|n| match n {
+                    GeneralDNSNameRef::DNSName(n) => {
+                        Some(AsRef::<str>::as_ref(&n.to_owned()).to_string())
+                    }
+                    // Wildcards are not used by mesh identities.
+                    GeneralDNSNameRef::Wildcard(_) => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// === impl TrustAnchorInfo ===
+
+impl TrustAnchorInfo {
+    pub(crate) fn from_roots(roots: &rustls::RootCertStore) -> Vec<Self> {
+        roots
+            .roots
+            .iter()
+            .map(|root| {
+                let anchor = root.to_trust_anchor();
+                Self {
+                    subject: subject(anchor.subject),
+                    spki_sha256: sha256(anchor.spki),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Renders a trust anchor's subject, e.g. `CN=ca, O=org`.
+fn subject(name: &[u8]) -> String {
+    // Trust anchors' subjects omit the name's outer sequence, so it's restored
+    // before the name is parsed.
+    let mut der = vec![SEQUENCE];
+    if name.len() < 0x80 {
+        der.push(name.len() as u8);
+    } else {
+        let len = name.len().to_be_bytes();
+        let len = &len[len.iter().take_while(|b| **b == 0).count()..];
+        der.push(0x80 | len.len() as u8);
+        der.extend_from_slice(len);
+    }
+    der.extend_from_slice(name);
+
+    match X509Name::from_der(&der) {
+        Ok((_, name)) => name.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, bytes)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn describes_crt() {
+        let der = include_bytes!("testdata/foo-ns1-ca1/crt.der");
+        let info = CrtInfo::new(&rustls::Certificate(der.to_vec()));
+        assert_eq!(
+            info.dns_names,
+            vec!["foo.ns1.serviceaccount.identity.linkerd.cluster.local".to_string()]
+        );
+        assert_eq!(info.issuer.as_deref(), Some("OU=None"));
+        assert_eq!(
+            info.not_before,
+            Some(UNIX_EPOCH + Duration::from_secs(1_584_605_340))
+        );
+        assert_eq!(
+            info.not_after,
+            Some(UNIX_EPOCH + Duration::from_secs(1_899_965_340))
+        );
+        assert_eq!(info.sha256.len(), 64);
+    }
+
+    #[test]
+    fn describes_trust_anchors() {
+        let mut roots = rustls::RootCertStore::empty();
+        let (added, _) = roots
+            .add_pem_file(&mut std::io::Cursor::new(include_str!("testdata/ca1.pem")))
+            .unwrap();
+        assert_eq!(added, 1);
+        let anchors = TrustAnchorInfo::from_roots(&roots);
+        assert_eq!(anchors.len(), 1);
+        assert_eq!(anchors[0].subject, "OU=None");
+    }
+}
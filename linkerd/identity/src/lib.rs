@@ -9,9 +9,21 @@ use thiserror::Error;
 use tokio_rustls::rustls;
 use tracing::{debug, warn};
 
+mod federation;
+mod info;
+mod params;
+mod resumption;
 #[cfg(any(test, feature = "test-util"))]
 pub mod test_util;
+mod validity;
 
+use self::federation::Federation;
+
+pub use self::{
+    info::{CrtInfo, TrustAnchorInfo},
+    params::{InvalidTlsParams, TlsParams, TlsVersion},
+    resumption::SessionCache,
+};
 pub use linkerd_dns_name::InvalidName;
 
 /// A DER-encoded X.509 certificate signing request.
@@ -29,7 +41,11 @@ struct SigningKey(Arc<EcdsaKeyPair>);
 struct Signer(Arc<EcdsaKeyPair>);
 
 #[derive(Clone)]
-pub struct TrustAnchors(Arc<rustls::ClientConfig>);
+pub struct TrustAnchors {
+    config: Arc<rustls::ClientConfig>,
+    federation: Federation,
+    session_cache: Option<Arc<SessionCache>>,
+}
 
 #[derive(Clone, Debug)]
 pub struct TokenSource(Arc<String>);
@@ -45,6 +61,7 @@ pub struct Crt {
 pub struct CrtKey {
     id: LocalId,
     expiry: SystemTime,
+    chain: Arc<Vec<rustls::Certificate>>,
     client_config: Arc<rustls::ClientConfig>,
     server_config: Arc<rustls::ServerConfig>,
 }
@@ -94,6 +111,13 @@ impl Key {
         let k = EcdsaKeyPair::from_pkcs8(SIGNATURE_ALG_RING_SIGNING, b)?;
         Ok(Key(Arc::new(k)))
     }
+
+    /// Reads the first PKCS#8 private key from a PEM document.
+    pub fn from_pem(pem: &[u8]) -> Option<Self> {
+        let keys = rustls::internal::pemfile::pkcs8_private_keys(&mut io::Cursor::new(pem)).ok()?;
+        let key = keys.into_iter().next()?;
+        Self::from_pkcs8(&key.0).ok()
+    }
 }
 
 impl rustls::sign::SigningKey for SigningKey {
@@ -215,7 +239,11 @@ impl TokenSource {
 impl TrustAnchors {
     #[cfg(any(test, feature = "test-util"))]
     fn empty() -> Self {
-        TrustAnchors(Arc::new(rustls::ClientConfig::new()))
+        TrustAnchors {
+            config: Arc::new(rustls::ClientConfig::new()),
+            federation: Federation::default(),
+            session_cache: None,
+        }
     }
 
     pub fn from_pem(s: &str) -> Option<Self> {
@@ -239,15 +267,119 @@ impl TrustAnchors {
         // TODO: Change Rustls's API to Avoid needing to clone `root_cert_store`.
         c.root_store = roots;
 
-        // Disable session resumption for the time-being until resumption is
-        // more tested.
+        // Session resumption is disabled unless a session cache is configured
+        // via `with_session_resumption`.
         c.enable_tickets = false;
 
-        Some(TrustAnchors(Arc::new(c)))
+        c.versions = TLS_VERSIONS.to_vec();
+
+        Some(TrustAnchors {
+            config: Arc::new(c),
+            federation: Federation::default(),
+            session_cache: None,
+        })
+    }
+
+    /// Federates the local trust domain with other trust domains, each of which
+    /// has its own trust anchors.
+    ///
+    /// Peers whose identities are in a federated trust domain (i.e. whose names
+    /// end with the domain) are validated against that domain's trust anchors;
+    /// all other peers are validated against the local trust anchors.
+    pub fn with_federated_domains(
+        self,
+        domains: impl IntoIterator<Item = (String, TrustAnchors)>,
+    ) -> Self {
+        let federation = Federation::new(
+            domains
+                .into_iter()
+                .map(|(domain, anchors)| (domain, anchors.config.root_store.clone())),
+        );
+        if federation.is_empty() {
+            return self;
+        }
+
+        let mut c = self.config.as_ref().clone();
+        c.dangerous()
+            .set_certificate_verifier(Arc::new(federation::ServerVerifier::new(
+                federation.clone(),
+            )));
+        TrustAnchors {
+            config: Arc::new(c),
+            federation,
+            ..self
+        }
+    }
+
+    /// Returns trust anchors with the roots in the given PEM document, retaining
+    /// all other configuration (e.g. federated trust domains).
+    pub fn with_roots_from_pem(&self, s: &str) -> Option<Self> {
+        let roots = Self::from_pem(s)?;
+        let mut c = self.config.as_ref().clone();
+        c.root_store = roots.config.root_store.clone();
+        Some(TrustAnchors {
+            config: Arc::new(c),
+            federation: self.federation.clone(),
+            session_cache: self.session_cache.clone(),
+        })
+    }
+
+    /// Constrains the TLS versions and cipher suites negotiated by both clients
+    /// and servers.
+    pub fn with_tls_params(self, params: &TlsParams) -> Result<Self, InvalidTlsParams> {
+        let mut c = self.config.as_ref().clone();
+        params.apply(&mut c)?;
+        Ok(TrustAnchors {
+            config: Arc::new(c),
+            ..self
+        })
+    }
+
+    /// Logs TLS session secrets to the file named by the `SSLKEYLOGFILE`
+    /// environment variable, so that captured traffic may be decrypted.
+    ///
+    /// This compromises the confidentiality of all TLS sessions and must only
+    /// be used for debugging.
+    pub fn with_key_log(self) -> Self {
+        let mut c = self.config.as_ref().clone();
+        c.key_log = Arc::new(rustls::KeyLogFile::new());
+        TrustAnchors {
+            config: Arc::new(c),
+            ..self
+        }
+    }
+
+    /// Resumes the TLS sessions of client connections from a cache of at most
+    /// `size` sessions, or disables resumption if `size` is zero.
+    pub fn with_session_resumption(self, size: usize) -> Self {
+        let mut c = self.config.as_ref().clone();
+        if size == 0 {
+            c.session_persistence = Arc::new(rustls::NoClientSessionStorage {});
+            c.enable_tickets = false;
+            return TrustAnchors {
+                config: Arc::new(c),
+                session_cache: None,
+                ..self
+            };
+        }
+
+        let cache = Arc::new(SessionCache::new(size));
+        c.session_persistence = cache.clone();
+        c.enable_tickets = true;
+        TrustAnchors {
+            config: Arc::new(c),
+            session_cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// Returns the cache of client TLS sessions, if sessions are resumed.
+    pub fn session_cache(&self) -> Option<Arc<SessionCache>> {
+        self.session_cache.clone()
     }
 
     pub fn certify(&self, key: Key, crt: Crt) -> Result<CrtKey, InvalidCrt> {
-        let mut client = self.0.as_ref().clone();
+        let mut client = self.config.as_ref().clone();
 
         // Ensure the certificate is valid for the services we terminate for
         // TLS. This assumes that server cert validation does the same or
@@ -268,6 +400,7 @@ impl TrustAnchors {
             .map_err(InvalidCrt)?;
         debug!("certified {}", crt.id);
 
+        let chain = Arc::new(crt.chain.clone());
         let k = SigningKey(key.0);
         let key = rustls::sign::CertifiedKey::new(crt.chain, Arc::new(Box::new(k)));
         let resolver = Arc::new(CertResolver(key));
@@ -284,22 +417,44 @@ impl TrustAnchors {
         // TODO: lock down the verification further.
         //
         // TODO: Change Rustls's API to Avoid needing to clone `root_cert_store`.
-        let mut server = rustls::ServerConfig::new(
-            rustls::AllowAnyAnonymousOrAuthenticatedClient::new(self.0.root_store.clone()),
-        );
-        server.versions = TLS_VERSIONS.to_vec();
+        //
+        // Clients in federated trust domains are validated against their
+        // domains' trust anchors.
+        let client_verifier: Arc<dyn rustls::ClientCertVerifier> = if self.federation.is_empty() {
+            rustls::AllowAnyAnonymousOrAuthenticatedClient::new(self.config.root_store.clone())
+        } else {
+            Arc::new(federation::ClientVerifier::new(
+                &self.config.root_store,
+                &self.federation,
+            ))
+        };
+        let mut server = rustls::ServerConfig::new(client_verifier);
+        server.versions = client.versions.clone();
+        server.ciphersuites = client.ciphersuites.clone();
         server.cert_resolver = resolver;
+        server.key_log = client.key_log.clone();
 
         Ok(CrtKey {
             id: crt.id,
             expiry: crt.expiry,
+            chain,
             client_config: Arc::new(client),
             server_config: Arc::new(server),
         })
     }
 
     pub fn client_config(&self) -> Arc<rustls::ClientConfig> {
-        self.0.clone()
+        self.config.clone()
+    }
+
+    /// Describes the trust anchors against which peers are validated.
+    pub fn describe(&self) -> Vec<TrustAnchorInfo> {
+        TrustAnchorInfo::from_roots(&self.config.root_store)
+    }
+
+    /// Returns the trust domains federated with the local trust domain.
+    pub fn federated_domains(&self) -> Vec<String> {
+        self.federation.domains().map(String::from).collect()
     }
 }
 
@@ -325,6 +480,20 @@ impl Crt {
         Self { id, chain, expiry }
     }
 
+    /// Reads a certificate chain from a PEM document, leaf first.
+    ///
+    /// The certificate expires at the end of the leaf's validity period.
+    pub fn from_pem(id: LocalId, pem: &[u8]) -> Option<Self> {
+        let mut chain = rustls::internal::pemfile::certs(&mut io::Cursor::new(pem)).ok()?;
+        if chain.is_empty() {
+            return None;
+        }
+        let leaf = chain.remove(0).0;
+        let expiry = validity::not_after(&leaf)?;
+        let intermediates = chain.into_iter().map(|c| c.0).collect();
+        Some(Self::new(id, leaf, intermediates, expiry))
+    }
+
     pub fn name(&self) -> &Name {
         self.id.as_ref()
     }
@@ -358,6 +527,16 @@ impl CrtKey {
     pub fn server_config(&self) -> Arc<rustls::ServerConfig> {
         self.server_config.clone()
     }
+
+    /// Describes each certificate in the chain, starting with the leaf.
+    pub fn describe_chain(&self) -> Vec<CrtInfo> {
+        self.chain.iter().map(CrtInfo::new).collect()
+    }
+
+    /// Describes the trust anchors with which this identity was certified.
+    pub fn describe_trust_anchors(&self) -> Vec<TrustAnchorInfo> {
+        TrustAnchorInfo::from_roots(&self.client_config.root_store)
+    }
 }
 
 impl fmt::Debug for CrtKey {
@@ -457,12 +636,35 @@ impl fmt::Display for LocalId {
 #[cfg(test)]
 mod tests {
     use super::test_util::*;
+    use super::{Crt, Key};
 
     #[test]
     fn can_construct_client_and_server_config_from_valid_settings() {
         FOO_NS1.validate().expect("foo.ns1 must be valid");
     }
 
+    #[test]
+    fn can_certify_from_pem() {
+        let key = Key::from_pem(include_bytes!("testdata/foo-ns1-ca1/key.pem"))
+            .expect("key must be valid");
+        let crt = Crt::from_pem(
+            FOO_NS1.crt().id.clone(),
+            include_bytes!("testdata/foo-ns1-ca1/crt.pem"),
+        )
+        .expect("certificate must be valid");
+        FOO_NS1
+            .trust_anchors()
+            .certify(key, crt)
+            .expect("foo.ns1 must be valid");
+
+        assert!(Key::from_pem(include_bytes!("testdata/foo-ns1-ca1/crt.pem")).is_none());
+        assert!(Crt::from_pem(
+            FOO_NS1.crt().id.clone(),
+            include_bytes!("testdata/foo-ns1-ca1/key.pem"),
+        )
+        .is_none());
+    }
+
     #[test]
     fn recognize_ca_did_not_issue_cert() {
         let s = Identity {
@@ -472,6 +674,42 @@ mod tests {
         assert!(s.validate().is_err(), "ca2 should not validate foo.ns1");
     }
 
+    #[test]
+    fn recognize_federated_trust_domain() {
+        let ca2 = Identity {
+            trust_anchors: include_bytes!("testdata/ca2.pem"),
+            ..FOO_NS1
+        }
+        .trust_anchors();
+        let s = Identity {
+            crt: include_bytes!("testdata/foo-ns1-ca2/crt.der"),
+            key: include_bytes!("testdata/foo-ns1-ca2/key.p8"),
+            ..FOO_NS1
+        };
+        assert!(
+            s.validate().is_err(),
+            "ca1 should not validate foo.ns1 from ca2"
+        );
+
+        let federated = s.trust_anchors().with_federated_domains(vec![(
+            "ns1.serviceaccount.identity.linkerd.cluster.local".to_string(),
+            ca2.clone(),
+        )]);
+        assert!(
+            federated.certify(s.key(), s.crt()).is_ok(),
+            "ca2 should validate foo.ns1 in its federated trust domain"
+        );
+
+        let other = s.trust_anchors().with_federated_domains(vec![(
+            "ns2.serviceaccount.identity.linkerd.cluster.local".to_string(),
+            ca2,
+        )]);
+        assert!(
+            other.certify(s.key(), s.crt()).is_err(),
+            "ca2 should not validate identities outside of its trust domain"
+        );
+    }
+
     #[test]
     fn recognize_cert_is_not_valid_for_identity() {
         let s = Identity {
@@ -0,0 +1,187 @@
+//! Constrains the TLS protocol versions and cipher suites that are negotiated,
+//! e.g. to satisfy compliance requirements.
+
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+use tokio_rustls::rustls;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    Tls12,
+    Tls13,
+}
+
+/// Constrains the TLS sessions negotiated by clients and servers.
+///
+/// By default, TLS 1.2 and 1.3 are negotiated with all of the cipher suites
+/// that rustls supports.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TlsParams {
+    pub min_version: Option<TlsVersion>,
+    pub max_version: Option<TlsVersion>,
+
+    /// If set, only these cipher suites are negotiated, named as in the IANA
+    /// registry (e.g. `TLS13_AES_128_GCM_SHA256`).
+    pub cipher_suites: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum InvalidTlsParams {
+    #[error("invalid TLS version: {0}")]
+    InvalidVersion(String),
+
+    #[error("the minimum TLS version must not exceed the maximum TLS version")]
+    NoVersions,
+
+    #[error("unsupported cipher suite: {0}")]
+    UnsupportedCipherSuite(String),
+
+    #[error("no cipher suites are usable with TLS {0}")]
+    NoCipherSuites(TlsVersion),
+}
+
+// === impl TlsVersion ===
+
+impl TlsVersion {
+    fn protocol(self) -> rustls::ProtocolVersion {
+        match self {
+            TlsVersion::Tls12 => rustls::ProtocolVersion::TLSv1_2,
+            TlsVersion::Tls13 => rustls::ProtocolVersion::TLSv1_3,
+        }
+    }
+}
+
+impl FromStr for TlsVersion {
+    type Err = InvalidTlsParams;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().trim_start_matches("tlsv") {
+            "1.2" => Ok(TlsVersion::Tls12),
+            "1.3" => Ok(TlsVersion::Tls13),
+            _ => Err(InvalidTlsParams::InvalidVersion(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for TlsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsVersion::Tls12 => "1.2".fmt(f),
+            TlsVersion::Tls13 => "1.3".fmt(f),
+        }
+    }
+}
+
+// === impl TlsParams ===
+
+impl TlsParams {
+    pub(crate) fn apply(&self, config: &mut rustls::ClientConfig) -> Result<(), InvalidTlsParams> {
+        let min = self.min_version.unwrap_or(TlsVersion::Tls12);
+        let max = self.max_version.unwrap_or(TlsVersion::Tls13);
+        if min > max {
+            return Err(InvalidTlsParams::NoVersions);
+        }
+        let versions = [TlsVersion::Tls13, TlsVersion::Tls12]
+            .iter()
+            .copied()
+            .filter(|v| min <= *v && *v <= max)
+            .collect::<Vec<_>>();
+
+        let suites = match self.cipher_suites.as_ref() {
+            None => rustls::ALL_CIPHERSUITES.to_vec(),
+            Some(names) => names
+                .iter()
+                .map(|name| {
+                    cipher_suite(name)
+                        .and_then(|suite| {
+                            rustls::ALL_CIPHERSUITES
+                                .iter()
+                                .copied()
+                                .find(|s| s.suite == suite)
+                        })
+                        .ok_or_else(|| InvalidTlsParams::UnsupportedCipherSuite(name.clone()))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        for v in versions.iter() {
+            if !suites.iter().any(|s| s.usable_for_version(v.protocol())) {
+                return Err(InvalidTlsParams::NoCipherSuites(*v));
+            }
+        }
+
+        config.versions = versions.into_iter().map(TlsVersion::protocol).collect();
+        config.ciphersuites = suites;
+        Ok(())
+    }
+}
+
+/// Looks up a cipher suite by its IANA name.
+fn cipher_suite(name: &str) -> Option<rustls::CipherSuite> {
+    use rustls::CipherSuite::*;
+
+    let suite = match name.trim().to_ascii_uppercase().as_str() {
+        "TLS13_AES_128_GCM_SHA256" => TLS13_AES_128_GCM_SHA256,
+        "TLS13_AES_256_GCM_SHA384" => TLS13_AES_256_GCM_SHA384,
+        "TLS13_CHACHA20_POLY1305_SHA256" => TLS13_CHACHA20_POLY1305_SHA256,
+        "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256" => TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+        "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384" => TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+        "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256" => {
+            TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256
+        }
+        "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256" => TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+        "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384" => TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+        "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256" => {
+            TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256
+        }
+        _ => return None,
+    };
+    Some(suite)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply() {
+        let mut config = rustls::ClientConfig::new();
+        TlsParams {
+            min_version: Some(TlsVersion::Tls13),
+            max_version: None,
+            cipher_suites: Some(vec!["tls13_aes_256_gcm_sha384".to_string()]),
+        }
+        .apply(&mut config)
+        .unwrap();
+        assert_eq!(config.versions, vec![rustls::ProtocolVersion::TLSv1_3]);
+        assert_eq!(config.ciphersuites.len(), 1);
+
+        let invalid = |min, max, suites: &[&str]| {
+            TlsParams {
+                min_version: min,
+                max_version: max,
+                cipher_suites: Some(suites.iter().map(|s| s.to_string()).collect()),
+            }
+            .apply(&mut rustls::ClientConfig::new())
+            .unwrap_err()
+        };
+        assert_eq!(
+            invalid(Some(TlsVersion::Tls13), Some(TlsVersion::Tls12), &[]),
+            InvalidTlsParams::NoVersions
+        );
+        assert_eq!(
+            invalid(None, None, &["TLS_RSA_WITH_RC4_128_SHA"]),
+            InvalidTlsParams::UnsupportedCipherSuite("TLS_RSA_WITH_RC4_128_SHA".to_string())
+        );
+        assert_eq!(
+            invalid(None, None, &["TLS13_AES_128_GCM_SHA256"]),
+            InvalidTlsParams::NoCipherSuites(TlsVersion::Tls12)
+        );
+    }
+
+    #[test]
+    fn parse_versions() {
+        assert_eq!("1.2".parse(), Ok(TlsVersion::Tls12));
+        assert_eq!("TLSv1.3".parse(), Ok(TlsVersion::Tls13));
+        assert!("1.1".parse::<TlsVersion>().is_err());
+    }
+}
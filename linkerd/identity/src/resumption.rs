@@ -0,0 +1,84 @@
+//! Caches the TLS sessions of outbound connections so that they may be
+//! resumed, avoiding the cost of full handshakes when connections churn.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio_rustls::rustls;
+
+/// A bounded cache of client TLS sessions that counts how often sessions are
+/// found for resumption.
+pub struct SessionCache {
+    cache: std::sync::Arc<rustls::ClientSessionMemoryCache>,
+    lookups: AtomicU64,
+    resumptions: AtomicU64,
+}
+
+/// Rustls also stores key exchange hints in the session cache; only lookups of
+/// sessions are counted.
+const SESSION_KEY_PREFIX: &[u8] = b"session";
+
+// === impl SessionCache ===
+
+impl SessionCache {
+    /// Returns a cache that holds at most `size` sessions.
+    pub fn new(size: usize) -> Self {
+        Self {
+            cache: rustls::ClientSessionMemoryCache::new(size),
+            lookups: AtomicU64::new(0),
+            resumptions: AtomicU64::new(0),
+        }
+    }
+
+    /// The number of times a session was looked up for a new connection.
+    pub fn lookups(&self) -> u64 {
+        self.lookups.load(Ordering::Acquire)
+    }
+
+    /// The number of times a cached session was offered for resumption.
+    pub fn resumptions(&self) -> u64 {
+        self.resumptions.load(Ordering::Acquire)
+    }
+}
+
+impl rustls::StoresClientSessions for SessionCache {
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> bool {
+        self.cache.put(key, value)
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let value = self.cache.get(key);
+        if key.starts_with(SESSION_KEY_PREFIX) {
+            self.lookups.fetch_add(1, Ordering::Release);
+            if value.is_some() {
+                self.resumptions.fetch_add(1, Ordering::Release);
+            }
+        }
+        value
+    }
+}
+
+impl std::fmt::Debug for SessionCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionCache")
+            .field("lookups", &self.lookups())
+            .field("resumptions", &self.resumptions())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::StoresClientSessions;
+
+    #[test]
+    fn counts_session_lookups() {
+        let cache = SessionCache::new(1);
+        assert_eq!(cache.get(b"sessionfoo"), None);
+        assert!(cache.put(b"sessionfoo".to_vec(), b"ticket".to_vec()));
+        assert_eq!(cache.get(b"sessionfoo"), Some(b"ticket".to_vec()));
+        assert!(cache.put(b"kx-hintfoo".to_vec(), b"x25519".to_vec()));
+        assert!(cache.get(b"kx-hintfoo").is_some());
+        assert_eq!(cache.lookups(), 2);
+        assert_eq!(cache.resumptions(), 1);
+    }
+}
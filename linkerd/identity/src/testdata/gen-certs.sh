@@ -34,12 +34,15 @@ ee() {
   openssl pkcs8 -topk8 -nocrypt -inform pem -outform der \
     -in "${ee}-key.pem" \
     -out "${ee}/key.p8"
+  openssl pkcs8 -topk8 -nocrypt -inform pem -outform pem \
+    -in "${ee}-key.pem" \
+    -out "${ee}/key.pem"
   rm "${ee}-key.pem"
 
   openssl x509 -inform pem -outform der \
     -in "${ee}.pem" \
     -out "${ee}/crt.der"
-  rm "${ee}.pem"
+  mv "${ee}.pem" "${ee}/crt.pem"
 
   ## TODO DER-encode?
   #openssl x509 -inform pem -outform der \
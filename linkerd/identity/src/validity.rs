@@ -0,0 +1,69 @@
+//! Reads a certificate's validity period.
+//!
+//! Certificates issued by the identity controller are accompanied by their
+//! expiration, but certificates read from files are not, so the `notAfter`
+//! time is read from the certificate itself. The certificate is validated by
+//! webpki when it is certified.
+
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use x509_parser::prelude::{ASN1Time, FromDer, X509Certificate};
+
+/// The fields of a certificate that are read without validating it.
+pub(crate) struct Tbs {
+    /// The issuer's distinguished name, e.g. `CN=ca, O=org`.
+    pub issuer: String,
+    pub not_before: SystemTime,
+    pub not_after: SystemTime,
+}
+
+/// Returns the `notAfter` time of a DER-encoded X.509 certificate.
+pub(crate) fn not_after(der: &[u8]) -> Option<SystemTime> {
+    tbs(der).map(|tbs| tbs.not_after)
+}
+
+/// Reads the issuer and validity period of a DER-encoded X.509 certificate.
+pub(crate) fn tbs(der: &[u8]) -> Option<Tbs> {
+    let (_, crt) = X509Certificate::from_der(der).ok()?;
+    let validity = crt.validity();
+    Some(Tbs {
+        issuer: crt.issuer().to_string(),
+        not_before: system_time(&validity.not_before)?,
+        not_after: system_time(&validity.not_after)?,
+    })
+}
+
+fn system_time(time: &ASN1Time) -> Option<SystemTime> {
+    let secs = u64::try_from(time.timestamp()).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_not_after() {
+        let der = include_bytes!("testdata/foo-ns1-ca1/crt.der");
+        // Mar 17 08:09:00 2030 GMT
+        assert_eq!(
+            not_after(der),
+            Some(UNIX_EPOCH + Duration::from_secs(1_899_965_340))
+        );
+
+        assert_eq!(not_after(&der[..der.len() / 4]), None);
+        assert_eq!(not_after(b"not a certificate"), None);
+    }
+
+    #[test]
+    fn reads_not_before() {
+        let der = include_bytes!("testdata/foo-ns1-ca1/crt.der");
+        // Mar 19 08:09:00 2020 GMT
+        assert_eq!(
+            tbs(der).map(|tbs| tbs.not_before),
+            Some(UNIX_EPOCH + Duration::from_secs(1_584_605_340))
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use super::{AsyncRead, AsyncWrite, IoSlice, PeerAddr, Poll, ReadBuf, Result};
+use super::{AsyncRead, AsyncWrite, IoSlice, PeerAddr, Poll, ReadBuf, Result, Splice};
 use std::{pin::Pin, task::Context};
 
 /// A public wrapper around a `Box<Io>`.
@@ -10,14 +10,14 @@ pub struct BoxedIo(Pin<Box<dyn Io + Unpin>>);
 /// This is necessary for `BoxedIo`, as `dyn AsyncRead + AsyncWrite + PeerAddr`
 /// is not a valid trait object. However, it needn't be public --- it's just
 /// used internally.
-trait Io: AsyncRead + AsyncWrite + PeerAddr + Send {}
+trait Io: AsyncRead + AsyncWrite + PeerAddr + Splice + Send {}
 
-impl<I> Io for I where I: AsyncRead + AsyncWrite + PeerAddr + Send {}
+impl<I> Io for I where I: AsyncRead + AsyncWrite + PeerAddr + Splice + Send {}
 
 impl BoxedIo {
     pub fn new<T>(io: T) -> Self
     where
-        T: AsyncRead + AsyncWrite + PeerAddr + Send + Unpin + 'static,
+        T: AsyncRead + AsyncWrite + PeerAddr + Splice + Send + Unpin + 'static,
     {
         BoxedIo(Box::pin(io))
     }
@@ -29,6 +29,20 @@ impl PeerAddr for BoxedIo {
     }
 }
 
+impl Splice for BoxedIo {
+    fn splice_socket(&self) -> Option<&tokio::net::TcpStream> {
+        self.0.splice_socket()
+    }
+
+    fn record_splice_read(&mut self, sz: usize) {
+        self.0.as_mut().get_mut().record_splice_read(sz)
+    }
+
+    fn record_splice_write(&mut self, sz: usize) {
+        self.0.as_mut().get_mut().record_splice_write(sz)
+    }
+}
+
 impl AsyncRead for BoxedIo {
     fn poll_read(
         mut self: Pin<&mut Self>,
@@ -84,6 +98,8 @@ mod tests {
         }
     }
 
+    impl Splice for WriteBufDetector {}
+
     impl AsyncRead for WriteBufDetector {
         fn poll_read(self: Pin<&mut Self>, _: &mut Context<'_>, _: &mut ReadBuf<'_>) -> Poll<()> {
             unreachable!("not called in test")
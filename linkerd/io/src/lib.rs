@@ -88,3 +88,39 @@ impl PeerAddr for tokio::io::DuplexStream {
         Ok(([0, 0, 0, 0], 0).into())
     }
 }
+
+// === Splice ===
+
+/// Exposes the TCP socket underlying an I/O stream so that data may be moved
+/// between sockets without being copied through userspace.
+pub trait Splice {
+    /// Returns the underlying socket if data may be read from and written to it
+    /// directly, i.e. without any framing or buffering by this stream.
+    ///
+    /// Once a socket has been returned, it must continue to be returned.
+    fn splice_socket(&self) -> Option<&tokio::net::TcpStream> {
+        None
+    }
+
+    /// Records that `sz` bytes were read directly from the underlying socket.
+    fn record_splice_read(&mut self, _sz: usize) {}
+
+    /// Records that `sz` bytes were written directly to the underlying socket.
+    fn record_splice_write(&mut self, _sz: usize) {}
+}
+
+impl Splice for tokio::net::TcpStream {
+    fn splice_socket(&self) -> Option<&tokio::net::TcpStream> {
+        Some(self)
+    }
+}
+
+// TLS streams must encrypt and decrypt all data, so they cannot be spliced.
+impl<T> Splice for tokio_rustls::client::TlsStream<T> {}
+
+impl<T> Splice for tokio_rustls::server::TlsStream<T> {}
+
+#[cfg(feature = "tokio-test")]
+impl Splice for tokio_test::io::Mock {}
+
+impl Splice for tokio::io::DuplexStream {}
@@ -50,6 +50,27 @@ impl<I: io::PeerAddr> io::PeerAddr for PrefixedIo<I> {
     }
 }
 
+impl<I: io::Splice> io::Splice for PrefixedIo<I> {
+    /// The socket is only exposed once the prefix has been read.
+    #[inline]
+    fn splice_socket(&self) -> Option<&tokio::net::TcpStream> {
+        if !self.prefix.is_empty() {
+            return None;
+        }
+        self.io.splice_socket()
+    }
+
+    #[inline]
+    fn record_splice_read(&mut self, sz: usize) {
+        self.io.record_splice_read(sz)
+    }
+
+    #[inline]
+    fn record_splice_write(&mut self, sz: usize) {
+        self.io.record_splice_write(sz)
+    }
+}
+
 impl<I: io::AsyncRead> io::AsyncRead for PrefixedIo<I> {
     fn poll_read(
         self: Pin<&mut Self>,
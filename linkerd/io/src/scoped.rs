@@ -77,6 +77,23 @@ impl<I: io::PeerAddr> io::PeerAddr for ScopedIo<I> {
     }
 }
 
+impl<I: io::Splice> io::Splice for ScopedIo<I> {
+    #[inline]
+    fn splice_socket(&self) -> Option<&tokio::net::TcpStream> {
+        self.io.splice_socket()
+    }
+
+    #[inline]
+    fn record_splice_read(&mut self, sz: usize) {
+        self.io.record_splice_read(sz)
+    }
+
+    #[inline]
+    fn record_splice_write(&mut self, sz: usize) {
+        self.io.record_splice_write(sz)
+    }
+}
+
 impl<I: io::AsyncRead> io::AsyncRead for ScopedIo<I> {
     #[inline]
     fn poll_read(
@@ -1,4 +1,4 @@
-use crate::{IoSlice, PeerAddr, Poll};
+use crate::{IoSlice, PeerAddr, Poll, Splice};
 use futures::ready;
 use linkerd_errno::Errno;
 use pin_project::pin_project;
@@ -82,3 +82,20 @@ impl<T: PeerAddr, S> PeerAddr for SensorIo<T, S> {
         self.io.peer_addr()
     }
 }
+
+impl<T: Splice, S: Sensor> Splice for SensorIo<T, S> {
+    #[inline]
+    fn splice_socket(&self) -> Option<&tokio::net::TcpStream> {
+        self.io.splice_socket()
+    }
+
+    fn record_splice_read(&mut self, sz: usize) {
+        self.sensor.record_read(sz);
+        self.io.record_splice_read(sz);
+    }
+
+    fn record_splice_write(&mut self, sz: usize) {
+        self.sensor.record_write(sz);
+        self.io.record_splice_write(sz);
+    }
+}
@@ -17,6 +17,16 @@ impl Gauge {
         self.0.fetch_sub(1, Ordering::Release);
     }
 
+    /// Increment the gauge by `n`.
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Release);
+    }
+
+    /// Decrement the gauge by `n`.
+    pub fn sub(&self, n: u64) {
+        self.0.fetch_sub(n, Ordering::Release);
+    }
+
     pub fn value(&self) -> u64 {
         self.0
             .load(Ordering::Acquire)
@@ -24,7 +24,7 @@ pub use self::{
     counter::Counter,
     gauge::Gauge,
     histogram::Histogram,
-    prom::{FmtLabels, FmtMetric, FmtMetrics, Metric},
+    prom::{FmtLabels, FmtMetric, FmtMetrics, LabelValue, Metric},
     scopes::Scopes,
     serve::Serve,
     store::{LastUpdate, SharedStore, Store},
@@ -38,6 +38,11 @@ pub trait FmtLabels {
     fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
 }
 
+/// Formats a label value, escaping backslashes, double quotes, and newlines as
+/// required by the prometheus text format.
+#[derive(Copy, Clone, Debug)]
+pub struct LabelValue<'a>(pub &'a str);
+
 /// Writes a metric in prometheus-formatted output.
 ///
 /// This trait is implemented by `Counter`, `Gauge`, and `Histogram` to account for the
@@ -146,6 +151,24 @@ impl<N: Clone + fmt::Display, M: FmtMetric> Clone for Metric<'_, N, M> {
 
 impl<N: Copy + fmt::Display, M: FmtMetric> Copy for Metric<'_, N, M> {}
 
+// ===== impl LabelValue =====
+
+impl fmt::Display for LabelValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use fmt::Write;
+
+        for c in self.0.chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '"' => f.write_str("\\\"")?,
+                '\n' => f.write_str("\\n")?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
 // ===== impl FmtLabels =====
 
 impl<'a, A: FmtLabels + 'a> FmtLabels for &'a A {
@@ -210,3 +233,14 @@ impl FmtMetrics for () {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::LabelValue;
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(LabelValue("plain").to_string(), "plain");
+        assert_eq!(LabelValue("a\"b\\c\nd").to_string(), "a\\\"b\\\\c\\nd");
+    }
+}
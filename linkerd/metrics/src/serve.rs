@@ -33,6 +33,11 @@ impl<M> Serve<M> {
 }
 
 impl<M: FmtMetrics> Serve<M> {
+    /// Formats all metrics in the Prometheus text exposition format.
+    pub fn to_text(&self) -> String {
+        self.metrics.as_display().to_string()
+    }
+
     pub fn serve<B>(&self, req: http::Request<B>) -> std::io::Result<http::Response<Body>> {
         if Self::is_gzip(&req) {
             trace!("gzipping metrics");
@@ -2,6 +2,7 @@
 #![forbid(unsafe_code)]
 
 pub mod metrics;
+pub mod queue;
 
 use futures::stream::{Stream, StreamExt};
 use http_body::Body as HttpBody;
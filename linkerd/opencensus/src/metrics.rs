@@ -1,18 +1,29 @@
-use linkerd_metrics::{metrics, Counter, FmtMetrics};
-use std::fmt;
-use std::sync::Arc;
+use linkerd_metrics::{metrics, Counter, FmtLabels, FmtMetrics, Gauge, LabelValue};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 metrics! {
     opencensus_span_export_streams: Counter { "Total count of opened span export streams" },
     opencensus_span_export_requests: Counter { "Total count of span export request messages" },
-    opencensus_span_exports: Counter { "Total count of spans exported" }
+    opencensus_span_exports: Counter { "Total count of spans exported" },
+    opencensus_span_drops: Counter { "Total count of spans dropped before export" },
+    opencensus_span_queue_high_watermark: Gauge { "Largest number of spans observed in the export queue" }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct Metrics {
     streams: Counter,
     requests: Counter,
     spans: Counter,
+    drops: Mutex<HashMap<String, Arc<Counter>>>,
+    queue_high_watermark: AtomicU64,
 }
 
 #[derive(Clone, Debug)]
@@ -21,13 +32,12 @@ pub struct Registry(Arc<Metrics>);
 #[derive(Clone, Debug)]
 pub struct Report(Arc<Metrics>);
 
+/// Labels a producer of spans.
+#[derive(Copy, Clone, Debug)]
+struct Producer<'p>(&'p str);
+
 pub fn new() -> (Registry, Report) {
-    let metrics = Metrics {
-        streams: Counter::default(),
-        requests: Counter::default(),
-        spans: Counter::default(),
-    };
-    let shared = Arc::new(metrics);
+    let shared = Arc::new(Metrics::default());
     (Registry(shared.clone()), Report(shared))
 }
 
@@ -40,6 +50,23 @@ impl Registry {
         self.0.requests.incr();
         self.0.spans.add(spans);
     }
+
+    /// Returns the drop counter for the named span producer.
+    pub(crate) fn drops(&self, producer: &str) -> Arc<Counter> {
+        self.0
+            .drops
+            .lock()
+            .entry(producer.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Records the current depth of the span queue.
+    pub(crate) fn queue_depth(&self, depth: u64) {
+        self.0
+            .queue_high_watermark
+            .fetch_max(depth, Ordering::AcqRel);
+    }
 }
 
 impl FmtMetrics for Report {
@@ -53,6 +80,24 @@ impl FmtMetrics for Report {
         opencensus_span_exports.fmt_help(f)?;
         opencensus_span_exports.fmt_metric(f, &self.0.spans)?;
 
+        let drops = self.0.drops.lock();
+        if !drops.is_empty() {
+            opencensus_span_drops.fmt_help(f)?;
+            for (producer, counter) in drops.iter() {
+                opencensus_span_drops.fmt_metric_labeled(f, &**counter, &Producer(producer))?;
+            }
+        }
+
+        let watermark = Gauge::from(self.0.queue_high_watermark.load(Ordering::Acquire));
+        opencensus_span_queue_high_watermark.fmt_help(f)?;
+        opencensus_span_queue_high_watermark.fmt_metric(f, &watermark)?;
+
         Ok(())
     }
 }
+
+impl<'p> FmtLabels for Producer<'p> {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "producer=\"{}\"", LabelValue(self.0))
+    }
+}
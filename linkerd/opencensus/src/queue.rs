@@ -0,0 +1,145 @@
+use crate::{metrics::Registry, proto::trace::v1::Span};
+use futures::stream::Stream;
+use linkerd_metrics::Counter;
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+/// Creates a bounded, lossy queue of spans to be exported.
+///
+/// Producers never wait on the queue: when it is full (or the exporter has
+/// gone away), spans are dropped and counted against the producer that
+/// attempted to enqueue them.
+pub fn channel(capacity: usize, metrics: Registry) -> (SpanSender, SpanReceiver) {
+    let (tx, rx) = mpsc::channel(capacity);
+    let depth = Arc::new(AtomicU64::new(0));
+    let drops = metrics.drops("unknown");
+    let tx = SpanSender {
+        tx,
+        depth: depth.clone(),
+        metrics,
+        drops,
+    };
+    (tx, SpanReceiver { rx, depth })
+}
+
+#[derive(Clone, Debug)]
+pub struct SpanSender {
+    tx: mpsc::Sender<Span>,
+    depth: Arc<AtomicU64>,
+    metrics: Registry,
+    drops: Arc<Counter>,
+}
+
+#[derive(Debug)]
+pub struct SpanReceiver {
+    rx: mpsc::Receiver<Span>,
+    depth: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Error)]
+pub enum SpanDropped {
+    #[error("span export queue is full")]
+    Full,
+    #[error("span export queue is closed")]
+    Closed,
+}
+
+// === impl SpanSender ===
+
+impl SpanSender {
+    /// Returns a sender whose drops are attributed to the named producer.
+    pub fn for_producer(&self, producer: &str) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            depth: self.depth.clone(),
+            drops: self.metrics.drops(producer),
+            metrics: self.metrics.clone(),
+        }
+    }
+
+    /// Enqueues a span without waiting for capacity.
+    pub fn try_send(&self, span: Span) -> Result<(), SpanDropped> {
+        // Increment the depth before sending so that the receiver can never
+        // observe a span before it has been counted.
+        let depth = self.depth.fetch_add(1, Ordering::AcqRel) + 1;
+        match self.tx.try_send(span) {
+            Ok(()) => {
+                self.metrics.queue_depth(depth);
+                Ok(())
+            }
+            Err(error) => {
+                self.depth.fetch_sub(1, Ordering::AcqRel);
+                self.drops.incr();
+                Err(match error {
+                    mpsc::error::TrySendError::Full(_) => SpanDropped::Full,
+                    mpsc::error::TrySendError::Closed(_) => SpanDropped::Closed,
+                })
+            }
+        }
+    }
+}
+
+// === impl SpanReceiver ===
+
+impl Stream for SpanReceiver {
+    type Item = Span;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Span>> {
+        let span = futures::ready!(self.rx.poll_recv(cx));
+        if span.is_some() {
+            self.depth.fetch_sub(1, Ordering::AcqRel);
+        }
+        Poll::Ready(span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics;
+
+    #[test]
+    fn drops_when_full() {
+        let (registry, _report) = metrics::new();
+        let (tx, _rx) = channel(1, registry.clone());
+        let tx = tx.for_producer("test");
+
+        tx.try_send(Span::default())
+            .expect("queue must have capacity");
+        assert!(tx.try_send(Span::default()).is_err());
+        assert!(tx.try_send(Span::default()).is_err());
+
+        assert_eq!(u64::from(&*registry.drops("test")), 2);
+        assert_eq!(u64::from(&*registry.drops("unknown")), 0);
+    }
+
+    #[test]
+    fn tracks_depth() {
+        use futures::{FutureExt, StreamExt};
+
+        let (registry, _report) = metrics::new();
+        let (tx, mut rx) = channel(2, registry);
+
+        tx.try_send(Span::default()).unwrap();
+        tx.try_send(Span::default()).unwrap();
+        assert_eq!(tx.depth.load(Ordering::Acquire), 2);
+
+        rx.next()
+            .now_or_never()
+            .flatten()
+            .expect("span must be received");
+        assert_eq!(tx.depth.load(Ordering::Acquire), 1);
+
+        drop(rx);
+        assert!(tx.try_send(Span::default()).is_err());
+        assert_eq!(tx.depth.load(Ordering::Acquire), 1);
+    }
+}
@@ -0,0 +1,302 @@
+//! Exports spans to a collector with the OpenTelemetry protocol (OTLP).
+//!
+//! Spans are read from the same queue as the OpenCensus exporter's, so the
+//! proxy's span producers are unaware of the protocol used to export them.
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+use futures::stream::{Stream, StreamExt};
+use http_body::Body as HttpBody;
+use linkerd_error::Error;
+use linkerd_opencensus::{metrics::Registry, proto::trace::v1 as oc};
+pub use opentelemetry_proto as proto;
+use opentelemetry_proto::{
+    collector::trace::v1::{trace_service_client::TraceServiceClient, ExportTraceServiceRequest},
+    common::v1 as common,
+    resource::v1::Resource,
+    trace::v1 as otel,
+};
+use tokio::time;
+use tonic::{self as grpc, body::BoxBody, client::GrpcService};
+use tracing::{debug, trace};
+
+pub async fn export_spans<T, S>(client: T, resource: Resource, spans: S, metrics: Registry)
+where
+    T: GrpcService<BoxBody> + Clone,
+    T::Error: Into<Error>,
+    <T::ResponseBody as HttpBody>::Error: Into<Error> + Send + Sync,
+    T::ResponseBody: Send + Sync + 'static,
+    S: Stream<Item = oc::Span> + Unpin,
+{
+    debug!("Span exporter running");
+    SpanExporter {
+        client,
+        resource,
+        spans,
+        metrics,
+    }
+    .run()
+    .await
+}
+
+/// SpanExporter sends batches of spans to an OTLP collector.
+struct SpanExporter<T, S> {
+    client: T,
+    resource: Resource,
+    spans: S,
+    metrics: Registry,
+}
+
+#[derive(Debug)]
+struct SpanRxClosed;
+
+// === impl SpanExporter ===
+
+impl<T, S> SpanExporter<T, S>
+where
+    T: GrpcService<BoxBody> + Clone,
+    T::Error: Into<Error>,
+    <T::ResponseBody as HttpBody>::Error: Into<Error> + Send + Sync,
+    T::ResponseBody: Send + Sync + 'static,
+    S: Stream<Item = oc::Span> + Unpin,
+{
+    const MAX_BATCH_SIZE: usize = 1000;
+    const MAX_BATCH_IDLE: time::Duration = time::Duration::from_secs(10);
+
+    async fn run(self) {
+        let Self {
+            client,
+            resource,
+            mut spans,
+            mut metrics,
+        } = self;
+
+        // Holds the batch of pending spans. Cleared as the spans are flushed.
+        // Contains no more than MAX_BATCH_SIZE spans.
+        let mut accum = Vec::new();
+
+        loop {
+            let collect = Self::collect_batch(&mut spans, &mut accum).await;
+
+            if !accum.is_empty() {
+                let count = accum.len();
+                let req = ExportTraceServiceRequest {
+                    resource_spans: vec![otel::ResourceSpans {
+                        resource: Some(resource.clone()),
+                        instrumentation_library_spans: vec![otel::InstrumentationLibrarySpans {
+                            spans: accum.drain(..).map(convert).collect(),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }],
+                };
+
+                // Unlike the OpenCensus agent protocol, OTLP exports are
+                // unary. Batches that cannot be exported are dropped rather
+                // than retried so that the queue does not back up.
+                trace!(spans = count, "Sending batch");
+                match Self::send(&client, req).await {
+                    Ok(()) => metrics.send(count as u64),
+                    Err(error) => debug!(%error, spans = count, "Failed to export spans"),
+                }
+            }
+
+            // If the span source was closed, end the task.
+            if let Err(SpanRxClosed) = collect {
+                debug!("Span channel lost");
+                return;
+            }
+        }
+    }
+
+    async fn send(client: &T, req: ExportTraceServiceRequest) -> Result<(), Error> {
+        TraceServiceClient::new(client.clone())
+            .export(grpc::Request::new(req))
+            .await?;
+        Ok(())
+    }
+
+    /// Collects spans from the proxy into `accum`.
+    ///
+    /// Returns an error when the span sream has completed. An error may be
+    /// returned after accumulating spans.
+    async fn collect_batch(spans: &mut S, accum: &mut Vec<oc::Span>) -> Result<(), SpanRxClosed> {
+        loop {
+            if accum.len() == Self::MAX_BATCH_SIZE {
+                trace!(capacity = Self::MAX_BATCH_SIZE, "Batch capacity reached");
+                return Ok(());
+            }
+
+            tokio::select! {
+                biased;
+
+                res = spans.next() => match res {
+                    Some(span) => {
+                        trace!(?span, "Adding to batch");
+                        accum.push(span);
+                    }
+                    None => return Err(SpanRxClosed),
+                },
+
+                // Don't hold spans indefinitely. Return if we hit an idle
+                // timeout and spans have been collected.
+                _ = time::sleep(Self::MAX_BATCH_IDLE) => {
+                    if !accum.is_empty() {
+                        trace!(spans = accum.len(), "Flushing spans due to inactivitiy");
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Builds a resource from string-valued attributes.
+pub fn resource(attributes: impl IntoIterator<Item = (String, String)>) -> Resource {
+    let mut attributes = attributes
+        .into_iter()
+        .map(|(key, value)| key_value(key, common::any_value::Value::StringValue(value)))
+        .collect::<Vec<_>>();
+    attributes.sort_by(|a, b| a.key.cmp(&b.key));
+    Resource {
+        attributes,
+        dropped_attributes_count: 0,
+    }
+}
+
+/// Converts an OpenCensus span into an OTLP span.
+fn convert(span: oc::Span) -> otel::Span {
+    use common::any_value::Value;
+    use oc::attribute_value::Value as OcValue;
+
+    let kind = match oc::span::SpanKind::from_i32(span.kind) {
+        Some(oc::span::SpanKind::Server) => otel::span::SpanKind::Server,
+        Some(oc::span::SpanKind::Client) => otel::span::SpanKind::Client,
+        _ => otel::span::SpanKind::Unspecified,
+    };
+
+    let convert_attributes = |attributes: Option<oc::span::Attributes>| {
+        let mut attributes = attributes
+            .map(|a| a.attribute_map)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let value = match value.value? {
+                    OcValue::StringValue(s) => Value::StringValue(s.value),
+                    OcValue::IntValue(i) => Value::IntValue(i),
+                    OcValue::BoolValue(b) => Value::BoolValue(b),
+                    OcValue::DoubleValue(d) => Value::DoubleValue(d),
+                };
+                Some(key_value(key, value))
+            })
+            .collect::<Vec<_>>();
+        attributes.sort_by(|a, b| a.key.cmp(&b.key));
+        attributes
+    };
+
+    // Annotations are exported as span events; message events are not
+    // produced by the proxy.
+    let events = span
+        .time_events
+        .map(|t| t.time_event)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|event| match event.value? {
+            oc::span::time_event::Value::Annotation(annotation) => Some(otel::span::Event {
+                time_unix_nano: event.time.map(unix_nanos).unwrap_or(0),
+                name: annotation.description.map(|d| d.value).unwrap_or_default(),
+                attributes: convert_attributes(annotation.attributes),
+                dropped_attributes_count: 0,
+            }),
+            oc::span::time_event::Value::MessageEvent(_) => None,
+        })
+        .collect();
+
+    // OpenCensus statuses are gRPC status codes, where 0 indicates success.
+    let status = span.status.filter(|s| s.code != 0).map(|s| otel::Status {
+        message: s.message,
+        code: otel::status::StatusCode::Error as i32,
+    });
+
+    otel::Span {
+        trace_id: span.trace_id,
+        span_id: span.span_id,
+        parent_span_id: span.parent_span_id,
+        name: span.name.map(|n| n.value).unwrap_or_default(),
+        kind: kind as i32,
+        start_time_unix_nano: span.start_time.map(unix_nanos).unwrap_or(0),
+        end_time_unix_nano: span.end_time.map(unix_nanos).unwrap_or(0),
+        attributes: convert_attributes(span.attributes),
+        events,
+        status,
+        ..Default::default()
+    }
+}
+
+fn key_value(key: String, value: common::any_value::Value) -> common::KeyValue {
+    common::KeyValue {
+        key,
+        value: Some(common::AnyValue { value: Some(value) }),
+    }
+}
+
+fn unix_nanos(ts: prost_types::Timestamp) -> u64 {
+    (ts.seconds.max(0) as u64) * 1_000_000_000 + ts.nanos.max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashMap, time::SystemTime};
+
+    #[test]
+    fn converts_spans() {
+        let start = SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(1500);
+        let mut attribute_map = HashMap::new();
+        attribute_map.insert(
+            "http.method".to_string(),
+            oc::AttributeValue {
+                value: Some(oc::attribute_value::Value::StringValue(
+                    oc::TruncatableString {
+                        value: "GET".to_string(),
+                        truncated_byte_count: 0,
+                    },
+                )),
+            },
+        );
+        let span = oc::Span {
+            trace_id: vec![1; 16],
+            span_id: vec![2; 8],
+            parent_span_id: vec![3; 8],
+            name: Some(oc::TruncatableString {
+                value: "web.ns.svc.cluster.local".to_string(),
+                truncated_byte_count: 0,
+            }),
+            kind: oc::span::SpanKind::Client as i32,
+            start_time: Some(start.into()),
+            end_time: Some((start + std::time::Duration::from_nanos(5)).into()),
+            attributes: Some(oc::span::Attributes {
+                attribute_map,
+                dropped_attributes_count: 0,
+            }),
+            ..Default::default()
+        };
+
+        let span = convert(span);
+        assert_eq!(span.trace_id, vec![1; 16]);
+        assert_eq!(span.parent_span_id, vec![3; 8]);
+        assert_eq!(span.name, "web.ns.svc.cluster.local");
+        assert_eq!(span.kind, otel::span::SpanKind::Client as i32);
+        assert_eq!(span.start_time_unix_nano, 1_500_000_000);
+        assert_eq!(span.end_time_unix_nano, 1_500_000_005);
+        assert_eq!(
+            span.attributes,
+            vec![key_value(
+                "http.method".to_string(),
+                common::any_value::Value::StringValue("GET".to_string())
+            )]
+        );
+        assert_eq!(span.status, None);
+    }
+}
@@ -9,13 +9,15 @@ use linkerd_proxy_core as core;
 mod metadata;
 pub mod pb;
 mod resolve;
+mod static_endpoints;
 
 pub use self::metadata::{Metadata, ProtocolHint};
 pub use self::resolve::Resolve;
+pub use self::static_endpoints::{InvalidStaticEndpoints, StaticEndpoints, StaticResolve};
 
 // TODO this should hold a `NameAddr`; but this currently isn't possible due to
 // outbound target types.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ConcreteAddr(pub NameAddr);
 
 impl std::fmt::Display for ConcreteAddr {
@@ -22,6 +22,9 @@ pub struct Metadata {
 
     /// Used to override the the authority if needed
     authority_override: Option<Authority>,
+
+    /// The endpoint's weight relative to other endpoints in its service.
+    weight: u32,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -43,11 +46,16 @@ impl Default for Metadata {
             authority_override: None,
             opaque_transport_port: None,
             protocol_hint: ProtocolHint::Unknown,
+            weight: Self::DEFAULT_WEIGHT,
         }
     }
 }
 
 impl Metadata {
+    /// The weight assigned to endpoints by the destination service when no
+    /// other weight is configured.
+    pub const DEFAULT_WEIGHT: u32 = 10_000;
+
     pub fn new(
         labels: impl IntoIterator<Item = (String, String)>,
         protocol_hint: ProtocolHint,
@@ -61,6 +69,22 @@ impl Metadata {
             opaque_transport_port,
             identity,
             authority_override,
+            weight: Self::DEFAULT_WEIGHT,
+        }
+    }
+
+    /// Sets the endpoint's weight.
+    pub fn with_weight(self, weight: u32) -> Self {
+        Self { weight, ..self }
+    }
+
+    /// Sets a label on the endpoint, replacing any existing value.
+    pub fn with_label(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let mut labels = (*self.labels).clone();
+        labels.insert(key.into(), value.into());
+        Self {
+            labels: labels.into(),
+            ..self
         }
     }
 
@@ -85,6 +109,10 @@ impl Metadata {
         self.authority_override.as_ref()
     }
 
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
     pub fn clear_upgrade(&mut self) {
         self.protocol_hint = ProtocolHint::Unknown;
         self.opaque_transport_port = None;
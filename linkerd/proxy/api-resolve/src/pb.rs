@@ -49,6 +49,13 @@ pub fn to_addr_meta(
         tls_id,
         authority_override,
     );
+    // Older controllers do not set weights, so unset weights are treated as
+    // the default.
+    let meta = if pb.weight > 0 {
+        meta.with_weight(pb.weight)
+    } else {
+        meta
+    };
     Some((addr, meta))
 }
 
@@ -0,0 +1,284 @@
+//! Resolves statically-configured endpoints.
+//!
+//! A bootstrap file declares the endpoints of logical services, e.g.:
+//!
+//! ```yaml
+//! services:
+//!   - name: web.example.com:8080
+//!     endpoints:
+//!       - addr: 10.0.0.1:8080
+//!         weight: 100
+//!         identity: web.ns.serviceaccount.identity.linkerd.cluster.local
+//!         labels:
+//!           zone: west
+//! ```
+//!
+//! As YAML is a superset of JSON, the file may also be written as JSON.
+//!
+//! Declared services are still resolved by the Destination controller. Their
+//! static endpoints are only used when the controller can't resolve them,
+//! either because it is unavailable or because the service isn't known to it
+//! (e.g. when the proxy runs outside of the cluster). While the static
+//! endpoints are used, the controller is retried with a backoff; once it
+//! resolves the service, its endpoints replace the static endpoints.
+
+use crate::{
+    core::resolve::Update,
+    metadata::{Metadata, ProtocolHint},
+    ConcreteAddr,
+};
+use async_stream::stream;
+use futures::{future, prelude::*};
+use linkerd_addr::NameAddr;
+use linkerd_error::Error;
+use linkerd_exp_backoff::ExponentialBackoff;
+use linkerd_stack::Param;
+use linkerd_tls::client::ServerId;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tower::Service;
+use tracing::{debug, warn};
+
+/// Maps logical services to their statically-configured endpoints.
+#[derive(Clone, Debug, Default)]
+pub struct StaticEndpoints(Arc<HashMap<NameAddr, Vec<(SocketAddr, Metadata)>>>);
+
+/// Resolves services through an inner resolver, falling back to static
+/// endpoints when the inner resolution fails.
+#[derive(Clone, Debug)]
+pub struct StaticResolve<R> {
+    endpoints: StaticEndpoints,
+    backoff: ExponentialBackoff,
+    inner: R,
+}
+
+#[derive(Debug, Error)]
+pub enum InvalidStaticEndpoints {
+    #[error("invalid static endpoints file: {0}")]
+    Parse(#[from] serde_yaml::Error),
+
+    #[error("invalid service name: {0}")]
+    InvalidName(String),
+
+    #[error("invalid identity: {0}")]
+    InvalidIdentity(String),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    #[serde(default)]
+    services: Vec<ServiceSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServiceSpec {
+    name: String,
+    #[serde(default)]
+    endpoints: Vec<EndpointSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EndpointSpec {
+    addr: SocketAddr,
+    weight: Option<u32>,
+    identity: Option<String>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
+type UpdatesStream<E> = Pin<Box<dyn Stream<Item = Result<Update<Metadata>, E>> + Send + 'static>>;
+
+// === impl StaticEndpoints ===
+
+impl StaticEndpoints {
+    /// Parses a YAML (or JSON) bootstrap file.
+    pub fn from_yaml(s: &str) -> Result<Self, InvalidStaticEndpoints> {
+        let File { services } = serde_yaml::from_str(s)?;
+        let mut endpoints = HashMap::with_capacity(services.len());
+        for ServiceSpec {
+            name,
+            endpoints: eps,
+        } in services
+        {
+            let addr = NameAddr::from_str(&name)
+                .map_err(|_| InvalidStaticEndpoints::InvalidName(name.clone()))?;
+            let eps = eps
+                .into_iter()
+                .map(|ep| {
+                    let identity = match ep.identity {
+                        Some(id) => Some(
+                            ServerId::from_str(&id)
+                                .map_err(|_| InvalidStaticEndpoints::InvalidIdentity(id))?,
+                        ),
+                        None => None,
+                    };
+                    let meta =
+                        Metadata::new(ep.labels, ProtocolHint::Unknown, None, identity, None)
+                            .with_weight(ep.weight.unwrap_or(Metadata::DEFAULT_WEIGHT));
+                    Ok((ep.addr, meta))
+                })
+                .collect::<Result<Vec<_>, InvalidStaticEndpoints>>()?;
+            endpoints.insert(addr, eps);
+        }
+        Ok(Self(Arc::new(endpoints)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, addr: &NameAddr) -> Option<&[(SocketAddr, Metadata)]> {
+        self.0.get(addr).map(Vec::as_slice)
+    }
+}
+
+// === impl StaticResolve ===
+
+impl<R> StaticResolve<R> {
+    /// Creates a resolver that retries the inner resolver with the given
+    /// backoff while static endpoints are used.
+    pub fn new(endpoints: StaticEndpoints, backoff: ExponentialBackoff, inner: R) -> Self {
+        Self {
+            endpoints,
+            backoff,
+            inner,
+        }
+    }
+}
+
+impl<T, R, S, E> Service<T> for StaticResolve<R>
+where
+    T: Param<ConcreteAddr> + Clone + Send + 'static,
+    R: Service<T, Response = S, Error = E> + Clone + Send + 'static,
+    R::Future: Send + 'static,
+    S: Stream<Item = Result<Update<Metadata>, E>> + Send + 'static,
+    E: Into<Error> + Send + 'static,
+{
+    type Response = UpdatesStream<E>;
+    type Error = E;
+    type Future = Pin<Box<dyn Future<Output = Result<UpdatesStream<E>, E>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), E>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        let ConcreteAddr(addr) = target.param();
+        let fallback = self.endpoints.get(&addr).map(<[_]>::to_vec);
+        let resolve = self.inner.call(target.clone());
+        let mut inner = self.inner.clone();
+        let backoff = self.backoff;
+        Box::pin(async move {
+            let (error, eps) = match (resolve.await, fallback) {
+                (Ok(updates), _) => return Ok(Box::pin(updates) as UpdatesStream<E>),
+                (Err(error), None) => return Err(error),
+                (Err(error), Some(eps)) => (error.into(), eps),
+            };
+            warn!(%addr, %error, "Resolution failed; using static endpoints");
+            debug!(endpoints = ?eps);
+
+            let updates = stream! {
+                yield Ok(Update::Reset(eps));
+
+                // Retry the inner resolver until it recovers.
+                let mut backoff = backoff.stream();
+                let updates = loop {
+                    backoff.next().await;
+                    let resolved = async {
+                        future::poll_fn(|cx| inner.poll_ready(cx)).await?;
+                        inner.call(target.clone()).await
+                    };
+                    match resolved.await {
+                        Ok(updates) => break updates,
+                        Err(error) => {
+                            let error: Error = error.into();
+                            debug!(%addr, %error, "Resolution failed; retaining static endpoints");
+                        }
+                    }
+                };
+                debug!(%addr, "Resolution recovered; replacing static endpoints");
+
+                // The resolution's first update replaces the static endpoints.
+                futures::pin_mut!(updates);
+                let mut first = true;
+                while let Some(update) = updates.next().await {
+                    yield match update {
+                        Ok(Update::Add(eps)) if first => Ok(Update::Reset(eps)),
+                        Ok(Update::Remove(_)) if first => Ok(Update::Reset(vec![])),
+                        update => update,
+                    };
+                    first = false;
+                }
+            };
+            Ok(Box::pin(updates) as UpdatesStream<E>)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_yaml_and_json() {
+        let yaml = r#"
+services:
+  - name: web.example.com:8080
+    endpoints:
+      - addr: 10.0.0.1:8080
+        weight: 100
+        identity: web.ns.serviceaccount.identity.linkerd.cluster.local
+        labels:
+          zone: west
+      - addr: 10.0.0.2:8080
+"#;
+        let json = r#"{"services": [{"name": "web.example.com:8080", "endpoints": [
+            {"addr": "10.0.0.1:8080", "weight": 100,
+             "identity": "web.ns.serviceaccount.identity.linkerd.cluster.local",
+             "labels": {"zone": "west"}},
+            {"addr": "10.0.0.2:8080"}
+        ]}]}"#;
+
+        let web = NameAddr::from_str("web.example.com:8080").unwrap();
+        for s in &[yaml, json] {
+            let endpoints = StaticEndpoints::from_yaml(s).unwrap();
+            let eps = endpoints.get(&web).expect("service must be declared");
+            assert_eq!(eps.len(), 2);
+
+            let (addr, meta) = &eps[0];
+            assert_eq!(*addr, SocketAddr::from(([10, 0, 0, 1], 8080)));
+            assert_eq!(meta.weight(), 100);
+            assert_eq!(
+                meta.identity().map(ToString::to_string).as_deref(),
+                Some("web.ns.serviceaccount.identity.linkerd.cluster.local")
+            );
+            assert_eq!(meta.labels().get("zone").map(String::as_str), Some("west"));
+
+            let (_, meta) = &eps[1];
+            assert_eq!(meta.weight(), Metadata::DEFAULT_WEIGHT);
+            assert!(meta.identity().is_none());
+        }
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        assert!(StaticEndpoints::from_yaml("services: [{name: web}]").is_err());
+        assert!(StaticEndpoints::from_yaml(
+            "services: [{name: 'web:80', endpoints: [{addr: 'nope'}]}]"
+        )
+        .is_err());
+        assert!(StaticEndpoints::from_yaml("services: [{name: 'web:80', bogus: 1}]").is_err());
+    }
+}
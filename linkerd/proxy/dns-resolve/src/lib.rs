@@ -12,7 +12,7 @@ use std::{
     pin::Pin,
     task::{Context, Poll},
 };
-use tokio::sync::mpsc;
+use tokio::{sync::mpsc, time};
 use tracing::instrument::Instrument;
 use tracing::{debug, trace};
 
@@ -70,23 +70,20 @@ async fn resolution(dns: dns::Resolver, na: NameAddr) -> Result<UpdateStream, Er
     // spawn a task to drive the continued resolution.
     //
     // Note: this can't be an async_stream, due to pinniness.
-    let (addrs, expiry) = dns.resolve_addrs(na.name(), na.port()).await?;
+    let (update, expiry) = resolve(&dns, &na).await?;
     let (tx, rx) = mpsc::channel(1);
     tokio::spawn(
         async move {
-            let eps = addrs.into_iter().map(|a| (a, ())).collect();
-            if tx.send(Ok(Update::Reset(eps))).await.is_err() {
+            if tx.send(Ok(update)).await.is_err() {
                 trace!("Closed");
                 return;
             }
             expiry.await;
 
             loop {
-                match dns.resolve_addrs(na.name(), na.port()).await {
-                    Ok((addrs, expiry)) => {
-                        debug!(?addrs);
-                        let eps = addrs.into_iter().map(|a| (a, ())).collect();
-                        if tx.send(Ok(Update::Reset(eps))).await.is_err() {
+                match resolve(&dns, &na).await {
+                    Ok((update, expiry)) => {
+                        if tx.send(Ok(update)).await.is_err() {
                             trace!("Closed");
                             return;
                         }
@@ -106,3 +103,24 @@ async fn resolution(dns: dns::Resolver, na: NameAddr) -> Result<UpdateStream, Er
 
     Ok(Box::pin(ReceiverStream::new(rx)))
 }
+
+/// Resolves a name to an update and the time at which it expires.
+///
+/// Names that do not exist are reported as such and are not looked up again
+/// until the resolver's negative TTL elapses.
+async fn resolve(dns: &dns::Resolver, na: &NameAddr) -> Result<(Update<()>, time::Sleep), Error> {
+    match dns.resolve_addrs(na.name(), na.port()).await {
+        Ok((addrs, expiry)) => {
+            debug!(?addrs);
+            let eps = addrs.into_iter().map(|a| (a, ())).collect();
+            Ok((Update::Reset(eps), expiry))
+        }
+        Err(error) => match error.downcast_ref::<dns::NxDomain>() {
+            Some(nx) => {
+                debug!(ttl = ?nx.ttl(), "Name does not exist");
+                Ok((Update::DoesNotExist, time::sleep(nx.ttl())))
+            }
+            None => Err(error),
+        },
+    }
+}
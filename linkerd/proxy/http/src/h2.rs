@@ -6,14 +6,20 @@ use hyper::{
     client::conn::{self, SendRequest},
 };
 use linkerd_error::{Error, Result};
+use rand::Rng;
 use std::time::Duration;
 use std::{
+    fmt,
     future::Future,
     marker::PhantomData,
     pin::Pin,
     task::{Context, Poll},
 };
-use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    time::{self, Instant, Sleep},
+};
+use tower::ServiceExt;
 use tracing::instrument::Instrument;
 use tracing::{debug, debug_span, trace_span};
 
@@ -21,9 +27,35 @@ use tracing::{debug, debug_span, trace_span};
 pub struct Settings {
     pub initial_stream_window_size: Option<u32>,
     pub initial_connection_window_size: Option<u32>,
+
+    /// Limits the number of streams a client may open concurrently on a
+    /// server's connection. Clients don't advertise a limit.
+    pub max_concurrent_streams: Option<u32>,
+    pub max_frame_size: Option<u32>,
+
+    /// Configures how often PING frames are sent to keep a connection alive.
+    /// Defaults to a quarter of the keepalive timeout.
+    pub keepalive_interval: Option<Duration>,
     pub keepalive_timeout: Option<Duration>,
+
+    /// Servers gracefully close connections (with a GOAWAY) once they have
+    /// been open this long; clients replace them with a new connection. The
+    /// age is jittered by up to 10% so that connections opened together aren't
+    /// all closed at once.
+    pub max_connection_age: Option<Duration>,
+
+    /// Servers gracefully close connections that have had no requests in
+    /// flight for this long.
+    pub max_connection_idle: Option<Duration>,
+
+    /// Bounds how long a server waits for in-flight streams to complete after
+    /// closing a connection for its age or idleness. Defaults to
+    /// `DEFAULT_MAX_CONNECTION_AGE_GRACE`.
+    pub max_connection_age_grace: Option<Duration>,
 }
 
+pub const DEFAULT_MAX_CONNECTION_AGE_GRACE: Duration = Duration::from_secs(30);
+
 #[derive(Debug)]
 pub struct Connect<C, B> {
     connect: C,
@@ -34,6 +66,18 @@ pub struct Connect<C, B> {
 #[derive(Debug)]
 pub struct Connection<B> {
     tx: SendRequest<B>,
+    max_age: Option<MaxAge<B>>,
+}
+
+/// Replaces a client connection once it reaches its maximum age.
+///
+/// The existing connection continues to serve requests until its replacement
+/// is established; dropping it then lets its in-flight streams complete.
+struct MaxAge<B> {
+    age: Duration,
+    expiry: Pin<Box<Sleep>>,
+    reconnect: Reconnect<B>,
+    replacement: Option<ConnectFuture<B>>,
 }
 
 // === impl Connect ===
@@ -60,9 +104,12 @@ impl<C: Clone, B> Clone for Connect<C, B> {
 
 type ConnectFuture<B> = Pin<Box<dyn Future<Output = Result<Connection<B>>> + Send + 'static>>;
 
+type Reconnect<B> = Box<dyn FnMut() -> ConnectFuture<B> + Send + 'static>;
+
 impl<C, B, T> tower::Service<T> for Connect<C, B>
 where
-    C: tower::make::MakeConnection<T>,
+    T: Clone + Send + 'static,
+    C: tower::make::MakeConnection<T> + Clone + Send + 'static,
     C::Future: Send + 'static,
     C::Connection: AsyncRead + AsyncWrite + Unpin + Send + 'static,
     C::Error: Into<Error>,
@@ -83,9 +130,21 @@ where
         let Settings {
             initial_connection_window_size,
             initial_stream_window_size,
+            max_frame_size,
+            keepalive_interval,
             keepalive_timeout,
+            max_connection_age,
+            ..
         } = self.h2_settings;
 
+        let max_age = max_connection_age.map(|age| {
+            let connect = self.clone();
+            let target = target.clone();
+            let reconnect =
+                move || -> ConnectFuture<B> { Box::pin(connect.clone().oneshot(target.clone())) };
+            (age, Box::new(reconnect) as Reconnect<B>)
+        });
+
         let connect = self
             .connect
             .make_connection(target)
@@ -99,17 +158,18 @@ where
                     .http2_only(true)
                     .http2_initial_stream_window_size(initial_stream_window_size)
                     .http2_initial_connection_window_size(initial_connection_window_size)
+                    .http2_max_frame_size(max_frame_size)
                     .executor(trace::Executor::new());
 
                 // Configure HTTP/2 PING frames
-                if let Some(timeout) = keepalive_timeout {
-                    // XXX(eliza): is this a reasonable interval between
-                    // PING frames?
-                    let interval = timeout / 4;
+                let interval = keepalive_interval.or_else(|| keepalive_timeout.map(|t| t / 4));
+                if let Some(interval) = interval {
                     builder
-                        .http2_keep_alive_timeout(timeout)
                         .http2_keep_alive_interval(interval)
                         .http2_keep_alive_while_idle(true);
+                    if let Some(timeout) = keepalive_timeout {
+                        builder.http2_keep_alive_timeout(timeout);
+                    }
                 }
 
                 let (tx, conn) = builder
@@ -123,7 +183,8 @@ where
                         .in_current_span(),
                 );
 
-                Ok(Connection { tx })
+                let max_age = max_age.map(|(age, reconnect)| MaxAge::new(age, reconnect));
+                Ok(Connection { tx, max_age })
             }
             .instrument(debug_span!("h2")),
         )
@@ -142,8 +203,11 @@ where
     type Error = hyper::Error;
     type Future = conn::ResponseFuture;
 
-    #[inline]
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Some(conn) = self.max_age.as_mut().and_then(|m| m.poll_replacement(cx)) {
+            debug!("Replaced a connection that reached its maximum age");
+            *self = conn;
+        }
         self.tx.poll_ready(cx).map_err(From::from)
     }
 
@@ -165,3 +229,58 @@ where
         self.tx.send_request(req)
     }
 }
+
+// === impl MaxAge ===
+
+impl<B> MaxAge<B> {
+    fn new(age: Duration, reconnect: Reconnect<B>) -> Self {
+        Self {
+            age,
+            expiry: Box::pin(time::sleep(jitter(age))),
+            reconnect,
+            replacement: None,
+        }
+    }
+
+    /// Returns a new connection once this one has expired and its replacement
+    /// has been established.
+    fn poll_replacement(&mut self, cx: &mut Context<'_>) -> Option<Connection<B>> {
+        if self.replacement.is_none() {
+            if self.expiry.as_mut().poll(cx).is_pending() {
+                return None;
+            }
+            debug!("Connection reached its maximum age; reconnecting");
+            self.replacement = Some((self.reconnect)());
+        }
+
+        match self.replacement.as_mut()?.as_mut().poll(cx) {
+            Poll::Pending => None,
+            Poll::Ready(Ok(conn)) => Some(conn),
+            Poll::Ready(Err(error)) => {
+                // Keep using the existing connection and try again later.
+                debug!(%error, "Failed to replace connection");
+                self.replacement = None;
+                self.expiry
+                    .as_mut()
+                    .reset(Instant::now() + jitter(self.age));
+                None
+            }
+        }
+    }
+}
+
+impl<B> fmt::Debug for MaxAge<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MaxAge")
+            .field("age", &self.age)
+            .field("expiry", &self.expiry.deadline())
+            .field("replacing", &self.replacement.is_some())
+            .finish()
+    }
+}
+
+/// Jitters a maximum connection age by up to 10% so that connections opened
+/// together aren't all closed at once.
+pub(crate) fn jitter(age: Duration) -> Duration {
+    age.mul_f64(rand::thread_rng().gen_range(0.9..=1.1))
+}
@@ -2,17 +2,25 @@ use crate::{
     self as http,
     client_handle::SetClientHandle,
     glue::{HyperServerSvc, UpgradeBody},
-    h2::Settings as H2Settings,
+    h2::{self, Settings as H2Settings},
     trace, upgrade, Version,
 };
+use futures::{future, prelude::*, ready};
 use linkerd_error::Error;
 use linkerd_io::{self as io, PeerAddr};
 use linkerd_stack::{layer, NewService, Param};
+use pin_project::pin_project;
 use std::{
     future::Future,
     pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
     task::{Context, Poll},
+    time::Duration,
 };
+use tokio::time::{self, Instant};
 use tower::Service;
 use tracing::debug;
 
@@ -22,6 +30,9 @@ type Server = hyper::server::conn::Http<trace::Executor>;
 pub struct NewServeHttp<N> {
     inner: N,
     server: Server,
+    max_age: Option<Duration>,
+    max_idle: Option<Duration>,
+    max_age_grace: Duration,
     drain: drain::Watch,
 }
 
@@ -30,9 +41,47 @@ pub struct ServeHttp<S> {
     version: Version,
     server: Server,
     inner: S,
+    max_age: Option<Duration>,
+    max_idle: Option<Duration>,
+    max_age_grace: Duration,
     drain: drain::Watch,
 }
 
+/// Tracks the requests in flight on an HTTP/2 connection so that it may be
+/// closed once it's idle.
+#[derive(Clone, Debug)]
+struct TrackActivity<S> {
+    inner: S,
+    activity: Arc<Activity>,
+}
+
+#[derive(Debug)]
+struct Activity {
+    active: AtomicUsize,
+    last: Mutex<Instant>,
+}
+
+/// Marks a request as in flight until it's dropped.
+#[derive(Debug)]
+struct Active(Arc<Activity>);
+
+#[pin_project]
+#[derive(Debug)]
+struct TrackFuture<F> {
+    #[pin]
+    inner: F,
+    active: Option<Active>,
+}
+
+/// Holds a request in flight until its response body is dropped.
+#[pin_project]
+#[derive(Debug)]
+struct ActiveBody {
+    #[pin]
+    inner: http::BoxBody,
+    _active: Active,
+}
+
 // === impl NewServeHttp ===
 
 impl<N> NewServeHttp<N> {
@@ -48,21 +97,29 @@ impl<N> NewServeHttp<N> {
         let mut server = hyper::server::conn::Http::new().with_executor(trace::Executor::new());
         server
             .http2_initial_stream_window_size(h2.initial_stream_window_size)
-            .http2_initial_connection_window_size(h2.initial_connection_window_size);
+            .http2_initial_connection_window_size(h2.initial_connection_window_size)
+            .http2_max_concurrent_streams(h2.max_concurrent_streams)
+            .http2_max_frame_size(h2.max_frame_size);
 
         // Configure HTTP/2 PING frames
-        if let Some(timeout) = h2.keepalive_timeout {
-            // XXX(eliza): is this a reasonable interval between
-            // PING frames?
-            let interval = timeout / 4;
-            server
-                .http2_keep_alive_timeout(timeout)
-                .http2_keep_alive_interval(interval);
+        let interval = h2
+            .keepalive_interval
+            .or_else(|| h2.keepalive_timeout.map(|t| t / 4));
+        if let Some(interval) = interval {
+            server.http2_keep_alive_interval(interval);
+            if let Some(timeout) = h2.keepalive_timeout {
+                server.http2_keep_alive_timeout(timeout);
+            }
         }
 
         Self {
             inner,
             server,
+            max_age: h2.max_connection_age,
+            max_idle: h2.max_connection_idle,
+            max_age_grace: h2
+                .max_connection_age_grace
+                .unwrap_or(h2::DEFAULT_MAX_CONNECTION_AGE_GRACE),
             drain,
         }
     }
@@ -83,6 +140,9 @@ where
             inner,
             version,
             server: self.server.clone(),
+            max_age: self.max_age,
+            max_idle: self.max_idle,
+            max_age_grace: self.max_age_grace,
             drain: self.drain.clone(),
         }
     }
@@ -112,6 +172,9 @@ where
         let Self {
             version,
             inner,
+            max_age,
+            max_idle,
+            max_age_grace,
             drain,
             mut server,
         } = self.clone();
@@ -145,6 +208,11 @@ where
                     }
                 }
                 Version::H2 => {
+                    let activity = Arc::new(Activity::new());
+                    let svc = TrackActivity {
+                        inner: svc,
+                        activity: activity.clone(),
+                    };
                     let mut conn = server
                         .http2_only(true)
                         .serve_connection(io, HyperServerSvc::new(svc));
@@ -163,6 +231,16 @@ where
                             Pin::new(&mut conn).graceful_shutdown();
                             conn.await?;
                         }
+                        () = max_age_elapsed(max_age) => {
+                            debug!("The connection has reached its maximum age");
+                            Pin::new(&mut conn).graceful_shutdown();
+                            drain_within(max_age_grace, conn).await?;
+                        }
+                        () = activity.idle(max_idle) => {
+                            debug!("The connection has been idle for too long");
+                            Pin::new(&mut conn).graceful_shutdown();
+                            drain_within(max_age_grace, conn).await?;
+                        }
                     }
                 }
             }
@@ -170,3 +248,219 @@ where
         })
     }
 }
+
+/// Completes once a connection has reached its (jittered) maximum age, or
+/// never if no maximum is configured.
+async fn max_age_elapsed(max_age: Option<Duration>) {
+    match max_age {
+        Some(age) => time::sleep(h2::jitter(age)).await,
+        None => future::pending().await,
+    }
+}
+
+/// Waits for a gracefully-closed connection to complete its in-flight streams,
+/// dropping it if it hasn't drained within the grace period.
+async fn drain_within<F, E>(grace: Duration, conn: F) -> Result<(), E>
+where
+    F: Future<Output = Result<(), E>>,
+{
+    match time::timeout(grace, conn).await {
+        Ok(res) => res,
+        Err(_) => {
+            debug!(?grace, "The connection did not drain in time");
+            Ok(())
+        }
+    }
+}
+
+// === impl TrackActivity ===
+
+impl<S> Service<http::Request<UpgradeBody>> for TrackActivity<S>
+where
+    S: Service<http::Request<UpgradeBody>, Response = http::Response<http::BoxBody>>,
+{
+    type Response = http::Response<http::BoxBody>;
+    type Error = S::Error;
+    type Future = TrackFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<UpgradeBody>) -> Self::Future {
+        let active = Active::new(self.activity.clone());
+        TrackFuture {
+            inner: self.inner.call(req),
+            active: Some(active),
+        }
+    }
+}
+
+impl<F, E> Future for TrackFuture<F>
+where
+    F: TryFuture<Ok = http::Response<http::BoxBody>, Error = E>,
+{
+    type Output = Result<http::Response<http::BoxBody>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let rsp = ready!(this.inner.try_poll(cx))?;
+        let active = this.active.take().expect("polled after ready");
+        Poll::Ready(Ok(rsp.map(|inner| {
+            http::BoxBody::new(ActiveBody {
+                inner,
+                _active: active,
+            })
+        })))
+    }
+}
+
+// === impl Activity ===
+
+impl Activity {
+    fn new() -> Self {
+        Self {
+            active: AtomicUsize::new(0),
+            last: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Completes once no requests have been in flight for `max_idle`, or never
+    /// if no maximum is configured.
+    async fn idle(&self, max_idle: Option<Duration>) {
+        let max_idle = match max_idle {
+            Some(max_idle) => max_idle,
+            None => return future::pending().await,
+        };
+        loop {
+            if self.active.load(Ordering::Acquire) > 0 {
+                time::sleep(max_idle).await;
+                continue;
+            }
+            let deadline = *self.last.lock().unwrap() + max_idle;
+            if deadline <= Instant::now() && self.active.load(Ordering::Acquire) == 0 {
+                return;
+            }
+            time::sleep_until(deadline).await;
+        }
+    }
+}
+
+// === impl Active ===
+
+impl Active {
+    fn new(activity: Arc<Activity>) -> Self {
+        activity.active.fetch_add(1, Ordering::AcqRel);
+        Self(activity)
+    }
+}
+
+impl Drop for Active {
+    fn drop(&mut self) {
+        *self.0.last.lock().unwrap() = Instant::now();
+        self.0.active.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+// === impl ActiveBody ===
+
+impl http::HttpBody for ActiveBody {
+    type Data = <http::BoxBody as http::HttpBody>::Data;
+    type Error = <http::BoxBody as http::HttpBody>::Error;
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.project().inner.poll_data(cx)
+    }
+
+    #[inline]
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<::http::HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_test::{assert_pending, assert_ready, assert_ready_ok, task};
+
+    #[tokio::test]
+    async fn idle_after_requests_complete() {
+        time::pause();
+        let activity = Arc::new(Activity::new());
+        let active = Active::new(activity.clone());
+
+        let mut idle = task::spawn(activity.idle(Some(Duration::from_secs(10))));
+        assert_pending!(idle.poll());
+
+        // The connection isn't idle while a request is in flight.
+        time::advance(Duration::from_secs(20)).await;
+        assert_pending!(idle.poll());
+
+        // Idleness is measured from when the last request completed.
+        drop(active);
+        time::advance(Duration::from_secs(5)).await;
+        assert_pending!(idle.poll());
+        time::advance(Duration::from_secs(5)).await;
+        assert_ready!(idle.poll());
+    }
+
+    #[tokio::test]
+    async fn idle_unbounded() {
+        time::pause();
+        let activity = Arc::new(Activity::new());
+        let mut idle = task::spawn(activity.idle(None));
+        time::advance(Duration::from_secs(60 * 60)).await;
+        assert_pending!(idle.poll());
+    }
+
+    #[tokio::test]
+    async fn max_age_jittered() {
+        time::pause();
+        let mut elapsed = task::spawn(max_age_elapsed(Some(Duration::from_secs(100))));
+        assert_pending!(elapsed.poll());
+        time::advance(Duration::from_secs(89)).await;
+        assert_pending!(elapsed.poll());
+        time::advance(Duration::from_secs(22)).await;
+        assert_ready!(elapsed.poll());
+
+        let mut unbounded = task::spawn(max_age_elapsed(None));
+        time::advance(Duration::from_secs(60 * 60)).await;
+        assert_pending!(unbounded.poll());
+    }
+
+    #[tokio::test]
+    async fn drain_deadline() {
+        time::pause();
+        let mut drain = task::spawn(drain_within(
+            Duration::from_secs(10),
+            future::pending::<Result<(), ()>>(),
+        ));
+        assert_pending!(drain.poll());
+        time::advance(Duration::from_secs(10)).await;
+        assert_ready_ok!(drain.poll());
+
+        let mut drained = task::spawn(drain_within(
+            Duration::from_secs(10),
+            future::ready(Err::<(), _>(())),
+        ));
+        assert_eq!(assert_ready!(drained.poll()), Err(()));
+    }
+}
@@ -11,7 +11,7 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use tokio::sync::watch;
-use tokio::time::{self, Sleep};
+use tokio::time;
 use tonic::{self as grpc, body::BoxBody, client::GrpcService};
 use tracing::{debug, error, trace};
 
@@ -36,6 +36,7 @@ pub struct LocalCrtKey {
     trust_anchors: id::TrustAnchors,
     id: id::LocalId,
     crt_key: watch::Receiver<Option<id::CrtKey>>,
+    next_refresh: watch::Receiver<Option<SystemTime>>,
     refreshes: Arc<Counter>,
 }
 
@@ -49,9 +50,13 @@ pub struct LostDaemon(());
 
 pub type CrtKeySender = watch::Sender<Option<id::CrtKey>>;
 
+/// Publishes when a daemon will next refresh the local identity.
+pub(crate) type NextRefreshSender = watch::Sender<Option<SystemTime>>;
+
 #[derive(Debug)]
 pub struct Daemon {
     crt_key_watch: CrtKeySender,
+    next_refresh: NextRefreshSender,
     refreshes: Arc<linkerd_metrics::Counter>,
     config: Config,
 }
@@ -59,11 +64,11 @@ pub struct Daemon {
 // === impl Config ===
 
 impl Config {
-    /// Returns a future that fires when a refresh should occur.
+    /// Returns the time until a refresh should occur.
     ///
     /// A refresh is scheduled at 70% of the current certificate's lifetime;
     /// though it is never less than min_refresh or larger than max_refresh.
-    fn refresh(&self, expiry: SystemTime) -> Sleep {
+    fn refresh(&self, expiry: SystemTime) -> Duration {
         let refresh = match expiry
             .duration_since(SystemTime::now())
             .ok()
@@ -75,7 +80,7 @@ impl Config {
             Some(lifetime) => lifetime,
         };
         trace!("will refresh in {:?}", refresh);
-        time::sleep(refresh)
+        refresh
     }
 }
 
@@ -92,6 +97,7 @@ impl Daemon {
     {
         let Self {
             crt_key_watch,
+            next_refresh,
             refreshes,
             config,
         } = self;
@@ -160,7 +166,9 @@ impl Daemon {
                 }
                 Err(e) => error!("Failed to read authentication token: {}", e),
             }
-            config.refresh(curr_expiry).await;
+            let refresh = config.refresh(curr_expiry);
+            let _ = next_refresh.send(Some(SystemTime::now() + refresh));
+            time::sleep(refresh).await;
         }
     }
 }
@@ -169,22 +177,36 @@ impl Daemon {
 
 impl LocalCrtKey {
     pub fn new(config: &Config) -> (Self, Daemon) {
-        let (s, w) = watch::channel(None);
-        let refreshes = Arc::new(Counter::new());
-        let l = Self {
-            id: config.local_id.clone(),
-            trust_anchors: config.trust_anchors.clone(),
-            crt_key: w,
-            refreshes: refreshes.clone(),
-        };
+        let (l, s, next_refresh, refreshes) =
+            Self::channel(config.local_id.clone(), config.trust_anchors.clone());
         let daemon = Daemon {
             config: config.clone(),
             refreshes,
+            next_refresh,
             crt_key_watch: s,
         };
         (l, daemon)
     }
 
+    /// Returns a local identity without a certificate, along with the handles
+    /// a daemon uses to provision it.
+    pub(crate) fn channel(
+        id: id::LocalId,
+        trust_anchors: id::TrustAnchors,
+    ) -> (Self, CrtKeySender, NextRefreshSender, Arc<Counter>) {
+        let (s, w) = watch::channel(None);
+        let (next_refresh_tx, next_refresh) = watch::channel(None);
+        let refreshes = Arc::new(Counter::new());
+        let l = Self {
+            id,
+            trust_anchors,
+            crt_key: w,
+            next_refresh,
+            refreshes: refreshes.clone(),
+        };
+        (l, s, next_refresh_tx, refreshes)
+    }
+
     pub async fn await_crt(mut self) -> Result<Self, LostDaemon> {
         while self.crt_key.borrow().is_none() {
             // If the sender is dropped, the daemon task has ended.
@@ -196,7 +218,11 @@ impl LocalCrtKey {
     }
 
     pub fn metrics(&self) -> crate::metrics::Report {
-        crate::metrics::Report::new(self.crt_key.clone(), self.refreshes.clone())
+        crate::metrics::Report::new(
+            self.crt_key.clone(),
+            self.refreshes.clone(),
+            self.trust_anchors.session_cache(),
+        )
     }
 
     pub fn id(&self) -> &id::LocalId {
@@ -207,6 +233,26 @@ impl LocalCrtKey {
         self.id.as_ref()
     }
 
+    /// Returns the current certificate, if one has been provisioned.
+    pub fn crt_key(&self) -> Option<id::CrtKey> {
+        self.crt_key.borrow().clone()
+    }
+
+    pub fn trust_anchors(&self) -> &id::TrustAnchors {
+        &self.trust_anchors
+    }
+
+    /// Returns when the daemon will next refresh the certificate, if it is
+    /// running.
+    pub fn next_refresh(&self) -> Option<SystemTime> {
+        *self.next_refresh.borrow()
+    }
+
+    /// Returns the number of times the certificate has been refreshed.
+    pub fn refreshes(&self) -> u64 {
+        u64::from(&*self.refreshes)
+    }
+
     pub fn client_config(&self) -> tls::client::Config {
         if let Some(ref c) = *self.crt_key.borrow() {
             return c.client_config();
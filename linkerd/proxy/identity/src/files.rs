@@ -0,0 +1,291 @@
+//! Provisions the local identity from files.
+//!
+//! Rather than certifying with the identity controller, the proxy may read its
+//! certificate, key, and trust anchors from files that are provisioned (and
+//! rotated) by another process, e.g. cert-manager or a SPIRE agent. The files
+//! are reloaded whenever their directories change and periodically, in case a
+//! change is missed.
+
+use crate::certify::{CrtKeySender, LocalCrtKey, NextRefreshSender};
+use linkerd_error::Error;
+use linkerd_identity as id;
+use linkerd_metrics::Counter;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use thiserror::Error;
+use tokio::time;
+use tracing::{debug, error, trace, warn};
+
+/// Configures the local identity to be read from files.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub local_id: id::LocalId,
+
+    /// The configured trust anchors, whose roots are replaced by the contents
+    /// of the trust anchors file as it is reloaded.
+    pub trust_anchors: id::TrustAnchors,
+    pub trust_anchors_path: PathBuf,
+
+    /// A PEM-encoded certificate chain, leaf first.
+    pub crt_path: PathBuf,
+
+    /// A PEM-encoded PKCS#8 private key.
+    pub key_path: PathBuf,
+
+    /// How often the files are reloaded when no changes are observed.
+    pub reload_interval: Duration,
+}
+
+#[derive(Debug)]
+pub struct Daemon {
+    crt_key_watch: CrtKeySender,
+    next_refresh: NextRefreshSender,
+    refreshes: Arc<Counter>,
+    config: Config,
+}
+
+#[derive(Debug, Error)]
+#[error("invalid identity file: {}", .0.display())]
+pub struct InvalidFile(PathBuf);
+
+/// The contents of the identity files, as last read.
+#[derive(PartialEq)]
+struct Files {
+    trust_anchors: String,
+    crt: Vec<u8>,
+    key: Vec<u8>,
+}
+
+/// Notifies the daemon when the identity files may have changed.
+struct Watch {
+    #[cfg(target_os = "linux")]
+    inotify: Option<tokio::io::unix::AsyncFd<linkerd_system::Inotify>>,
+}
+
+/// Files are often replaced in several steps, so changes are allowed to settle
+/// before the files are reloaded.
+const SETTLE_DELAY: Duration = Duration::from_secs(1);
+
+// === impl Config ===
+
+impl Config {
+    fn paths(&self) -> impl Iterator<Item = &Path> {
+        vec![
+            self.trust_anchors_path.as_path(),
+            self.crt_path.as_path(),
+            self.key_path.as_path(),
+        ]
+        .into_iter()
+    }
+}
+
+// === impl Daemon ===
+
+impl Daemon {
+    pub async fn run(self) {
+        let Self {
+            crt_key_watch,
+            next_refresh,
+            refreshes,
+            config,
+        } = self;
+
+        debug!(
+            crt = %config.crt_path.display(),
+            key = %config.key_path.display(),
+            trust_anchors = %config.trust_anchors_path.display(),
+            "Identity file daemon running"
+        );
+        let mut watch = Watch::new(&config);
+        let mut loaded = None;
+
+        loop {
+            match Files::read(&config) {
+                Err(error) => error!(%error, "Failed to read identity files"),
+                Ok(files) if loaded.as_ref() == Some(&files) => {
+                    trace!("Identity files unchanged");
+                }
+                Ok(files) => match files.certify(&config) {
+                    Err(error) => error!(%error, "Failed to load identity"),
+                    Ok(crt_key) => {
+                        debug!(expiry = ?crt_key.expiry(), "Loaded identity");
+                        if crt_key_watch.send(Some(crt_key)).is_err() {
+                            // If we can't store a value, than all observations
+                            // have been dropped and we can stop reloading.
+                            return;
+                        }
+
+                        refreshes.incr();
+                        loaded = Some(files);
+                    }
+                },
+            }
+            // The files are reloaded sooner if they are observed to change.
+            let _ = next_refresh.send(Some(SystemTime::now() + config.reload_interval));
+            watch.changed(config.reload_interval).await;
+        }
+    }
+}
+
+// === impl LocalCrtKey ===
+
+impl LocalCrtKey {
+    /// Returns a local identity that is provisioned by a daemon that reads
+    /// the configured files.
+    pub fn from_files(config: &Config) -> (Self, Daemon) {
+        let (local, crt_key_watch, next_refresh, refreshes) =
+            Self::channel(config.local_id.clone(), config.trust_anchors.clone());
+        let daemon = Daemon {
+            config: config.clone(),
+            crt_key_watch,
+            next_refresh,
+            refreshes,
+        };
+        (local, daemon)
+    }
+}
+
+// === impl Files ===
+
+impl Files {
+    fn read(config: &Config) -> io::Result<Self> {
+        Ok(Self {
+            trust_anchors: fs::read_to_string(&config.trust_anchors_path)?,
+            crt: fs::read(&config.crt_path)?,
+            key: fs::read(&config.key_path)?,
+        })
+    }
+
+    fn certify(&self, config: &Config) -> Result<id::CrtKey, Error> {
+        let trust_anchors = config
+            .trust_anchors
+            .with_roots_from_pem(&self.trust_anchors)
+            .ok_or_else(|| InvalidFile(config.trust_anchors_path.clone()))?;
+        let crt = id::Crt::from_pem(config.local_id.clone(), &self.crt)
+            .ok_or_else(|| InvalidFile(config.crt_path.clone()))?;
+        let key =
+            id::Key::from_pem(&self.key).ok_or_else(|| InvalidFile(config.key_path.clone()))?;
+        let crt_key = trust_anchors.certify(key, crt)?;
+        Ok(crt_key)
+    }
+}
+
+// === impl Watch ===
+
+#[cfg(target_os = "linux")]
+impl Watch {
+    fn new(config: &Config) -> Self {
+        let inotify = Self::inotify(config)
+            .map_err(|error| warn!(%error, "Failed to watch identity files; polling for changes"))
+            .ok();
+        Self { inotify }
+    }
+
+    /// Watches the directories that contain the files, so that files replaced
+    /// by renames or symlink swaps (e.g. in Kubernetes volumes) are observed.
+    fn inotify(config: &Config) -> io::Result<tokio::io::unix::AsyncFd<linkerd_system::Inotify>> {
+        let inotify = linkerd_system::Inotify::new()?;
+        let mut dirs = config
+            .paths()
+            .map(|p| match p.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            })
+            .collect::<Vec<_>>();
+        dirs.sort();
+        dirs.dedup();
+        for dir in dirs {
+            inotify.watch_dir(dir)?;
+        }
+        tokio::io::unix::AsyncFd::new(inotify)
+    }
+
+    /// Completes when the files' directories change or when the interval
+    /// elapses, whichever is first.
+    async fn changed(&mut self, interval: Duration) {
+        let inotify = match self.inotify.as_ref() {
+            Some(inotify) => inotify,
+            None => return time::sleep(interval).await,
+        };
+
+        let events = time::timeout(interval, async {
+            loop {
+                let mut ready = inotify.readable().await?;
+                if let Ok(res) = ready.try_io(|fd| fd.get_ref().drain()) {
+                    return res;
+                }
+            }
+        })
+        .await;
+        match events {
+            Err(_) => trace!("Reloading identity files"),
+            Ok(Ok(_)) => {
+                trace!("Identity files changed");
+                time::sleep(SETTLE_DELAY).await;
+                // Discard the events that occurred while the changes settled.
+                let _ = inotify.get_ref().drain();
+            }
+            Ok(Err(error)) => {
+                warn!(%error, "Failed to watch identity files; polling for changes");
+                self.inotify = None;
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Watch {
+    fn new(_: &Config) -> Self {
+        Self {}
+    }
+
+    async fn changed(&mut self, interval: Duration) {
+        time::sleep(interval).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn certify_files() {
+        let config = |crt: &str, key: &str| Config {
+            local_id: id::LocalId(
+                "foo.ns1.serviceaccount.identity.linkerd.cluster.local"
+                    .parse()
+                    .unwrap(),
+            ),
+            trust_anchors: id::TrustAnchors::from_pem(include_str!(
+                "../../../identity/src/testdata/ca1.pem"
+            ))
+            .unwrap(),
+            trust_anchors_path: testdata("ca1.pem"),
+            crt_path: testdata(crt),
+            key_path: testdata(key),
+            reload_interval: Duration::from_secs(60),
+        };
+
+        let valid = config("foo-ns1-ca1/crt.pem", "foo-ns1-ca1/key.pem");
+        let files = Files::read(&valid).expect("files must be readable");
+        assert!(files.certify(&valid).is_ok());
+
+        let unsigned = config("foo-ns1-ca2/crt.pem", "foo-ns1-ca2/key.pem");
+        let files = Files::read(&unsigned).expect("files must be readable");
+        assert!(files.certify(&unsigned).is_err());
+
+        let invalid = config("foo-ns1-ca1/key.pem", "foo-ns1-ca1/key.pem");
+        let files = Files::read(&invalid).expect("files must be readable");
+        assert!(files.certify(&invalid).is_err());
+    }
+
+    fn testdata(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../identity/src/testdata")
+            .join(name)
+    }
+}
@@ -2,6 +2,7 @@
 #![forbid(unsafe_code)]
 
 pub mod certify;
+pub mod files;
 pub mod metrics;
 
 pub use self::certify::{AwaitCrt, CrtKeySender, LocalCrtKey};
@@ -1,4 +1,4 @@
-use linkerd_identity::CrtKey;
+use linkerd_identity::{CrtKey, SessionCache};
 use linkerd_metrics::{metrics, Counter, FmtMetrics, Gauge};
 use std::{fmt, sync::Arc, time::UNIX_EPOCH};
 use tokio::sync::watch;
@@ -15,6 +15,14 @@ metrics! {
 
     identity_cert_refresh_count: Counter {
         "The total number of times this proxy's mTLS identity certificate has been refreshed by the Identity service."
+    },
+
+    tls_client_session_lookups_total: Counter {
+        "The total number of times a cached TLS session was looked up for an outbound mTLS connection."
+    },
+
+    tls_client_session_resumptions_total: Counter {
+        "The total number of times a cached TLS session was offered to resume an outbound mTLS connection."
     }
 }
 
@@ -22,11 +30,13 @@ impl Report {
     pub(crate) fn new(
         crt_key_watch: watch::Receiver<Option<CrtKey>>,
         refreshes: Arc<Counter>,
+        sessions: Option<Arc<SessionCache>>,
     ) -> Self {
         Self {
             inner: Some(Inner {
                 crt_key_watch,
                 refreshes,
+                sessions,
             }),
         }
     }
@@ -40,6 +50,7 @@ impl Report {
 struct Inner {
     crt_key_watch: watch::Receiver<Option<CrtKey>>,
     refreshes: Arc<Counter>,
+    sessions: Option<Arc<SessionCache>>,
 }
 
 impl FmtMetrics for Report {
@@ -65,6 +76,14 @@ impl FmtMetrics for Report {
         identity_cert_refresh_count.fmt_help(f)?;
         identity_cert_refresh_count.fmt_metric(f, &this.refreshes)?;
 
+        if let Some(sessions) = this.sessions.as_ref() {
+            tls_client_session_lookups_total.fmt_help(f)?;
+            tls_client_session_lookups_total.fmt_metric(f, &Counter::from(sessions.lookups()))?;
+            tls_client_session_resumptions_total.fmt_help(f)?;
+            tls_client_session_resumptions_total
+                .fmt_metric(f, &Counter::from(sessions.resumptions()))?;
+        }
+
         Ok(())
     }
 }
@@ -3,3 +3,4 @@
 
 pub mod map_endpoint;
 pub mod recover;
+pub mod stale;
@@ -0,0 +1,491 @@
+//! Caches resolutions so that they may be served while the resolver is
+//! unavailable.
+//!
+//! The cache is populated by [`Record`], which wraps a resolver and tracks the
+//! endpoints of each of its resolutions. Once all of a target's resolutions
+//! have failed (or ended), its cached endpoints become stale.
+//!
+//! [`Serve`] wraps a (recovering) resolver so that new resolutions are
+//! initialized with the cached endpoints, as long as they have been stale for
+//! no longer than the cache's maximum age. Updates from the inner resolution
+//! then replace the cached endpoints once it connects.
+//!
+//! Only endpoint resolutions are cached; other discovery (e.g. of service
+//! profiles) is not.
+
+use futures::{prelude::*, ready};
+use linkerd_metrics::{metrics, FmtLabels, FmtMetric, FmtMetrics};
+use linkerd_proxy_core::resolve::{self, Update};
+use linkerd_stack::Param;
+use parking_lot::Mutex;
+use pin_project::{pin_project, pinned_drop};
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    hash::Hash,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+metrics! {
+    resolution_cache_stale_seconds: StaleSeconds {
+        "The time since a cached resolution was last refreshed by a connected resolver"
+    }
+}
+
+/// A shared cache of resolved endpoints, keyed by target.
+pub struct Cache<K, E> {
+    entries: Arc<Mutex<HashMap<K, Entry<E>>>>,
+    max_age: Duration,
+}
+
+/// Records the endpoints of an inner resolver's resolutions in a [`Cache`].
+#[derive(Clone, Debug)]
+pub struct Record<K, E, R> {
+    cache: Cache<K, E>,
+    inner: R,
+}
+
+/// Initializes an inner resolver's resolutions with endpoints from a
+/// [`Cache`].
+#[derive(Clone, Debug)]
+pub struct Serve<K, E, R> {
+    cache: Cache<K, E>,
+    inner: R,
+}
+
+#[derive(Debug)]
+struct Entry<E> {
+    endpoints: Option<HashMap<SocketAddr, E>>,
+    /// The number of connected resolutions for the target.
+    connected: usize,
+    /// The time at which the last connected resolution was lost, if any.
+    stale_since: Option<Instant>,
+}
+
+#[pin_project]
+pub struct RecordFuture<K, E, F> {
+    key: Option<K>,
+    cache: Cache<K, E>,
+    #[pin]
+    inner: F,
+}
+
+#[pin_project(PinnedDrop)]
+pub struct RecordResolution<K: Hash + Eq, E, S> {
+    key: K,
+    cache: Cache<K, E>,
+    #[pin]
+    inner: S,
+}
+
+pub struct ServeFuture<E, F> {
+    stale: Option<Update<E>>,
+    inner: Option<F>,
+}
+
+#[pin_project(project = ServeResolutionProj)]
+pub enum ServeResolution<E, F, S> {
+    Stale {
+        update: Option<Update<E>>,
+        #[pin]
+        future: F,
+    },
+    Connected(#[pin] S),
+}
+
+struct DstLabel<'k, K>(&'k K);
+
+/// The time for which a cached resolution has been stale, as a gauge in
+/// (fractional) seconds.
+struct StaleSeconds(Duration);
+
+// === impl Cache ===
+
+impl<K, E> Cache<K, E>
+where
+    K: Hash + Eq,
+{
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            entries: Default::default(),
+            max_age,
+        }
+    }
+
+    pub fn record<R>(&self, inner: R) -> Record<K, E, R> {
+        Record {
+            cache: self.clone(),
+            inner,
+        }
+    }
+
+    pub fn serve<R>(&self, inner: R) -> Serve<K, E, R> {
+        Serve {
+            cache: self.clone(),
+            inner,
+        }
+    }
+
+    fn connect(&self, key: K) {
+        let mut entries = self.entries.lock();
+
+        // Evict entries that have been stale for longer than the maximum age.
+        let max_age = self.max_age;
+        entries.retain(|_, e| {
+            e.stale_since
+                .map(|t| t.elapsed() <= max_age)
+                .unwrap_or(true)
+        });
+
+        let entry = entries.entry(key).or_insert_with(|| Entry {
+            endpoints: Some(HashMap::default()),
+            connected: 0,
+            stale_since: None,
+        });
+        entry.connected += 1;
+        entry.stale_since = None;
+    }
+
+    fn disconnect(&self, key: &K) {
+        let mut entries = self.entries.lock();
+        if let Some(entry) = entries.get_mut(key) {
+            entry.connected = entry.connected.saturating_sub(1);
+            if entry.connected == 0 {
+                entry.stale_since = Some(Instant::now());
+            }
+        }
+    }
+}
+
+impl<K, E> Cache<K, E>
+where
+    K: Hash + Eq,
+    E: Clone,
+{
+    /// Returns an update describing the target's cached endpoints, unless they
+    /// have been stale for longer than the maximum age.
+    fn get(&self, key: &K) -> Option<Update<E>> {
+        let mut entries = self.entries.lock();
+        let entry = entries.get(key)?;
+        if let Some(since) = entry.stale_since {
+            if since.elapsed() > self.max_age {
+                entries.remove(key);
+                return None;
+            }
+        }
+        let update = match entry.endpoints.as_ref() {
+            Some(eps) => Update::Reset(eps.iter().map(|(a, e)| (*a, e.clone())).collect()),
+            None => Update::DoesNotExist,
+        };
+        Some(update)
+    }
+
+    fn update(&self, key: &K, update: &Update<E>) {
+        let mut entries = self.entries.lock();
+        let entry = match entries.get_mut(key) {
+            Some(entry) => entry,
+            None => return,
+        };
+        match update {
+            Update::Reset(eps) => {
+                entry.endpoints = Some(eps.iter().cloned().collect());
+            }
+            Update::Add(eps) => {
+                entry
+                    .endpoints
+                    .get_or_insert_with(HashMap::default)
+                    .extend(eps.iter().cloned());
+            }
+            Update::Remove(addrs) => {
+                if let Some(endpoints) = entry.endpoints.as_mut() {
+                    for addr in addrs.iter() {
+                        endpoints.remove(addr);
+                    }
+                }
+            }
+            Update::DoesNotExist => {
+                entry.endpoints = None;
+            }
+        }
+    }
+}
+
+impl<K, E> Clone for Cache<K, E> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            max_age: self.max_age,
+        }
+    }
+}
+
+impl<K, E> fmt::Debug for Cache<K, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cache")
+            .field("entries", &self.entries.lock().len())
+            .field("max_age", &self.max_age)
+            .finish()
+    }
+}
+
+impl<K: fmt::Display, E> FmtMetrics for Cache<K, E> {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.entries.lock();
+        let mut stale = entries
+            .iter()
+            .filter_map(|(k, e)| Some((k, e.stale_since?.elapsed())))
+            .filter(|(_, age)| *age <= self.max_age)
+            .peekable();
+        if stale.peek().is_none() {
+            return Ok(());
+        }
+
+        resolution_cache_stale_seconds.fmt_help(f)?;
+        for (key, age) in stale {
+            resolution_cache_stale_seconds.fmt_metric_labeled(
+                f,
+                &StaleSeconds(age),
+                DstLabel(key),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// === impl Record ===
+
+impl<T, K, E, R> tower::Service<T> for Record<K, E, R>
+where
+    T: Param<K>,
+    K: Clone + Hash + Eq,
+    E: Clone,
+    R: resolve::Resolve<T, Endpoint = E>,
+{
+    type Response = RecordResolution<K, E, R::Resolution>;
+    type Error = R::Error;
+    type Future = RecordFuture<K, E, R::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        RecordFuture {
+            key: Some(target.param()),
+            cache: self.cache.clone(),
+            inner: self.inner.resolve(target),
+        }
+    }
+}
+
+impl<K, E, F, S> Future for RecordFuture<K, E, F>
+where
+    K: Clone + Hash + Eq,
+    E: Clone,
+    F: TryFuture<Ok = S>,
+{
+    type Output = Result<RecordResolution<K, E, S>, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let inner = ready!(this.inner.try_poll(cx))?;
+        let key = this.key.take().expect("polled after ready");
+        this.cache.connect(key.clone());
+        Poll::Ready(Ok(RecordResolution {
+            key,
+            cache: this.cache.clone(),
+            inner,
+        }))
+    }
+}
+
+impl<K, E, S, Er> Stream for RecordResolution<K, E, S>
+where
+    K: Hash + Eq,
+    E: Clone,
+    S: TryStream<Ok = Update<E>, Error = Er>,
+{
+    type Item = Result<Update<E>, Er>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let update = ready!(this.inner.try_poll_next(cx));
+        if let Some(Ok(ref update)) = update {
+            this.cache.update(this.key, update);
+        }
+        Poll::Ready(update)
+    }
+}
+
+#[pinned_drop]
+impl<K: Hash + Eq, E, S> PinnedDrop for RecordResolution<K, E, S> {
+    fn drop(self: Pin<&mut Self>) {
+        let this = self.project();
+        this.cache.disconnect(this.key);
+    }
+}
+
+// === impl Serve ===
+
+impl<T, K, E, R> tower::Service<T> for Serve<K, E, R>
+where
+    T: Param<K>,
+    K: Hash + Eq,
+    E: Clone,
+    R: resolve::Resolve<T, Endpoint = E>,
+{
+    type Response = ServeResolution<E, R::Future, R::Resolution>;
+    type Error = R::Error;
+    type Future = ServeFuture<E, R::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        let stale = self.cache.get(&target.param());
+        ServeFuture {
+            stale,
+            inner: Some(self.inner.resolve(target)),
+        }
+    }
+}
+
+impl<E, F, S> Future for ServeFuture<E, F>
+where
+    E: Unpin,
+    F: TryFuture<Ok = S> + Unpin,
+{
+    type Output = Result<ServeResolution<E, F, S>, F::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // If the target's endpoints are cached, return a resolution
+        // immediately, while the inner resolution is pending.
+        if let Some(update) = self.stale.take() {
+            tracing::debug!("Initializing resolution from cache");
+            let future = self.inner.take().expect("polled after ready");
+            return Poll::Ready(Ok(ServeResolution::Stale {
+                update: Some(update),
+                future,
+            }));
+        }
+
+        let inner = self.inner.as_mut().expect("polled after ready");
+        let resolution = ready!(inner.try_poll_unpin(cx))?;
+        Poll::Ready(Ok(ServeResolution::Connected(resolution)))
+    }
+}
+
+impl<E, F, S, Er> Stream for ServeResolution<E, F, S>
+where
+    F: TryFuture<Ok = S, Error = Er>,
+    S: TryStream<Ok = Update<E>, Error = Er>,
+{
+    type Item = Result<Update<E>, Er>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.as_mut().project() {
+                ServeResolutionProj::Stale { update, future } => {
+                    if let Some(update) = update.take() {
+                        return Poll::Ready(Some(Ok(update)));
+                    }
+                    let resolution = match ready!(future.try_poll(cx)) {
+                        Ok(resolution) => resolution,
+                        Err(error) => return Poll::Ready(Some(Err(error))),
+                    };
+                    self.set(ServeResolution::Connected(resolution));
+                }
+                ServeResolutionProj::Connected(resolution) => {
+                    return resolution.try_poll_next(cx);
+                }
+            }
+        }
+    }
+}
+
+// === impl StaleSeconds ===
+
+impl FmtMetric for StaleSeconds {
+    const KIND: &'static str = "gauge";
+
+    fn fmt_metric<N: fmt::Display>(&self, f: &mut fmt::Formatter<'_>, name: N) -> fmt::Result {
+        writeln!(f, "{} {}", name, self.0.as_secs_f64())
+    }
+
+    fn fmt_metric_labeled<N, L>(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        name: N,
+        labels: L,
+    ) -> fmt::Result
+    where
+        N: fmt::Display,
+        L: FmtLabels,
+    {
+        write!(f, "{}{{", name)?;
+        labels.fmt_labels(f)?;
+        writeln!(f, "}} {}", self.0.as_secs_f64())
+    }
+}
+
+// === impl DstLabel ===
+
+impl<K: fmt::Display> FmtLabels for DstLabel<'_, K> {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "dst=\"{}\"", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn serves_stale_endpoints_until_max_age() {
+        let cache = Cache::<&'static str, ()>::new(Duration::from_secs(60));
+        assert!(cache.get(&"web").is_none());
+
+        cache.connect("web");
+        cache.update(&"web", &Update::Reset(vec![(addr(1), ()), (addr(2), ())]));
+        cache.update(&"web", &Update::Remove(vec![addr(1)]));
+        cache.update(&"web", &Update::Add(vec![(addr(3), ())]));
+        match cache.get(&"web") {
+            Some(Update::Reset(mut eps)) => {
+                eps.sort_by_key(|(a, _)| *a);
+                assert_eq!(eps, vec![(addr(2), ()), (addr(3), ())]);
+            }
+            update => panic!("unexpected update: {:?}", update),
+        }
+
+        // The entry remains available while stale...
+        cache.disconnect(&"web");
+        assert!(matches!(cache.get(&"web"), Some(Update::Reset(_))));
+
+        // ...but not once it's older than the maximum age.
+        cache.entries.lock().get_mut(&"web").unwrap().stale_since =
+            Some(Instant::now() - Duration::from_secs(61));
+        assert!(cache.get(&"web").is_none());
+        assert!(cache.entries.lock().is_empty());
+    }
+
+    #[test]
+    fn caches_nonexistence() {
+        let cache = Cache::<&'static str, ()>::new(Duration::from_secs(60));
+        cache.connect("web");
+        cache.update(&"web", &Update::DoesNotExist);
+        cache.disconnect(&"web");
+        assert!(matches!(cache.get(&"web"), Some(Update::DoesNotExist)));
+    }
+}
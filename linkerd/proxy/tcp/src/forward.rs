@@ -1,13 +1,13 @@
 use futures::prelude::*;
 use linkerd_duplex::Duplex;
 use linkerd_error::Error;
+use linkerd_io::{self as io, AsyncRead, AsyncWrite};
 use linkerd_stack::layer;
 use std::{
     future::Future,
     pin::Pin,
     task::{Context, Poll},
 };
-use tokio::io::{AsyncRead, AsyncWrite};
 use tower::Service;
 
 #[derive(Clone, Debug)]
@@ -27,11 +27,11 @@ impl<C> Forward<C> {
 
 impl<C, I> Service<I> for Forward<C>
 where
-    I: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    I: AsyncRead + AsyncWrite + io::Splice + Send + Unpin + 'static,
     C: tower::Service<()> + Send + 'static,
     C::Error: Into<Error>,
     C::Future: Send + 'static,
-    C::Response: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    C::Response: AsyncRead + AsyncWrite + io::Splice + Send + Unpin + 'static,
 {
     type Response = ();
     type Error = Error;
@@ -47,7 +47,7 @@ where
             self.connect
                 .call(())
                 .err_into::<Error>()
-                .and_then(|dst_io| Duplex::new(src_io, dst_io).err_into::<Error>()),
+                .and_then(|dst_io| Duplex::splice(src_io, dst_io).err_into::<Error>()),
         )
     }
 }
@@ -3,20 +3,31 @@ use linkerd_io as io;
 use linkerd_stack::{Param, Service};
 use std::{
     future::Future,
+    net::SocketAddr,
     pin::Pin,
     task::{Context, Poll},
 };
-use tokio::net::TcpStream;
+use tokio::net::{TcpSocket, TcpStream};
 use tracing::debug;
 
 #[derive(Copy, Clone, Debug)]
 pub struct ConnectTcp {
     keepalive: Keepalive,
+    mark: Option<u32>,
 }
 
 impl ConnectTcp {
     pub fn new(keepalive: Keepalive) -> Self {
-        Self { keepalive }
+        Self {
+            keepalive,
+            mark: None,
+        }
+    }
+
+    /// Sets `SO_MARK` on each socket before it connects, so that the host's
+    /// routing policy and packet filters may distinguish its traffic.
+    pub fn with_mark(self, mark: Option<u32>) -> Self {
+        Self { mark, ..self }
     }
 }
 
@@ -31,11 +42,15 @@ impl<T: Param<Remote<ServerAddr>>> Service<T> for ConnectTcp {
     }
 
     fn call(&mut self, t: T) -> Self::Future {
-        let Keepalive(keepalive) = self.keepalive;
+        let keepalive = self.keepalive;
+        let mark = self.mark;
         let Remote(ServerAddr(addr)) = t.param();
-        debug!(server.addr = %addr, "Connecting");
+        debug!(server.addr = %addr, ?mark, "Connecting");
         Box::pin(async move {
-            let io = TcpStream::connect(&addr).await?;
+            let io = match mark {
+                None => TcpStream::connect(&addr).await?,
+                Some(mark) => connect_marked(addr, mark).await?,
+            };
             super::set_nodelay_or_warn(&io);
             let io = super::set_keepalive_or_warn(io, keepalive)?;
             debug!(
@@ -47,3 +62,13 @@ impl<T: Param<Remote<ServerAddr>>> Service<T> for ConnectTcp {
         })
     }
 }
+
+async fn connect_marked(addr: SocketAddr, mark: u32) -> io::Result<TcpStream> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    super::set_mark(&socket, mark)?;
+    socket.connect(addr).await
+}
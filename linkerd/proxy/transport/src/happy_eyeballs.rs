@@ -0,0 +1,121 @@
+//! Races connection attempts across multiple addresses (RFC 8305).
+//!
+//! Attempts are started in order, each one `delay` after the previous attempt
+//! started or immediately after it failed. The first attempt to succeed wins
+//! and all others are dropped.
+
+use futures::stream::{FuturesUnordered, StreamExt};
+use linkerd_io as io;
+use std::{future::Future, net::SocketAddr, time::Duration};
+use tracing::debug;
+
+/// Connects to the first reachable address in `addrs`.
+///
+/// Addresses should already be ordered by preference (e.g. with alternating
+/// address families). If all attempts fail, the last error is returned.
+pub async fn connect<C, F, T>(
+    addrs: Vec<SocketAddr>,
+    delay: Duration,
+    mut connect: C,
+) -> io::Result<T>
+where
+    C: FnMut(SocketAddr) -> F,
+    F: Future<Output = io::Result<T>>,
+{
+    let mut addrs = addrs.into_iter();
+    let mut pending = FuturesUnordered::new();
+    let mut error = None;
+
+    loop {
+        // Start an attempt if none are in flight, since there's nothing to
+        // wait on.
+        if pending.is_empty() {
+            match addrs.next() {
+                Some(addr) => pending.push(attempt(addr, connect(addr))),
+                None => {
+                    return Err(error.unwrap_or_else(|| {
+                        io::Error::new(io::ErrorKind::NotFound, "no addresses to connect to")
+                    }))
+                }
+            }
+        }
+
+        let remaining = addrs.len() > 0;
+        tokio::select! {
+            res = pending.next() => match res.expect("attempts must be pending") {
+                (_, Ok(io)) => return Ok(io),
+                (addr, Err(e)) => {
+                    debug!(%addr, error = %e, "Connection attempt failed");
+                    error = Some(e);
+                    // The next attempt is started immediately.
+                    if let Some(addr) = addrs.next() {
+                        pending.push(attempt(addr, connect(addr)));
+                    }
+                }
+            },
+            _ = tokio::time::sleep(delay), if remaining => {
+                if let Some(addr) = addrs.next() {
+                    debug!(%addr, ?delay, "Starting fallback connection attempt");
+                    pending.push(attempt(addr, connect(addr)));
+                }
+            }
+        }
+    }
+}
+
+fn attempt<F, T>(addr: SocketAddr, connect: F) -> impl Future<Output = (SocketAddr, io::Result<T>)>
+where
+    F: Future<Output = io::Result<T>>,
+{
+    async move { (addr, connect.await) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{future, FutureExt};
+
+    const DELAY: Duration = Duration::from_millis(10);
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    fn refused() -> io::Error {
+        io::Error::new(io::ErrorKind::ConnectionRefused, "refused")
+    }
+
+    #[tokio::test]
+    async fn falls_back_on_failure() {
+        let res = connect(vec![addr(1), addr(2)], Duration::from_secs(600), |a| {
+            future::ready(if a == addr(1) { Err(refused()) } else { Ok(a) })
+        })
+        .await;
+        assert_eq!(res.unwrap(), addr(2));
+    }
+
+    #[tokio::test]
+    async fn falls_back_after_delay() {
+        let res = connect(vec![addr(1), addr(2)], DELAY, |a| {
+            if a == addr(1) {
+                future::pending().boxed()
+            } else {
+                future::ok(a).boxed()
+            }
+        })
+        .await;
+        assert_eq!(res.unwrap(), addr(2));
+    }
+
+    #[tokio::test]
+    async fn fails_when_all_attempts_fail() {
+        let res = connect(vec![addr(1), addr(2)], DELAY, |_| {
+            future::ready(Err::<(), _>(refused()))
+        })
+        .await;
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::ConnectionRefused);
+
+        let res = connect(vec![], DELAY, |_| future::ready(Ok(()))).await;
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -7,26 +7,54 @@
 
 pub mod addrs;
 mod connect;
+pub mod happy_eyeballs;
 pub mod listen;
 pub mod orig_dst;
+pub mod proxy_protocol;
 
+use self::sockopt::{set_keepalive_probes, set_mark, set_transparent, set_user_timeout};
 pub use self::{
     addrs::{ClientAddr, ListenAddr, Local, OrigDstAddr, Remote, ServerAddr},
     connect::ConnectTcp,
-    listen::{Bind, BindTcp},
+    listen::{Backlog, Bind, BindTcp},
     orig_dst::BindWithOrigDst,
+    proxy_protocol::BindWithProxyProtocol,
 };
 use linkerd_io as io;
 use socket2::TcpKeepalive;
 use std::time::Duration;
 use tokio::net::TcpStream;
 
-#[derive(Copy, Clone, Debug, Default)]
-pub struct Keepalive(pub Option<Duration>);
+/// Configures how idle and unresponsive TCP connections are detected.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Keepalive {
+    /// How long a connection must be idle before keepalive probes are sent.
+    pub time: Option<Duration>,
+
+    /// How long to wait for a probe to be acknowledged before sending the
+    /// next one.
+    pub interval: Option<Duration>,
+
+    /// How many probes may go unacknowledged before the connection is closed.
+    pub probes: Option<u32>,
+
+    /// How long transmitted data may remain unacknowledged before the
+    /// connection is closed (i.e. `TCP_USER_TIMEOUT`).
+    pub user_timeout: Option<Duration>,
+}
+
+impl From<Option<Duration>> for Keepalive {
+    fn from(time: Option<Duration>) -> Self {
+        Self {
+            time,
+            ..Self::default()
+        }
+    }
+}
 
 impl From<Keepalive> for Option<Duration> {
-    fn from(Keepalive(duration): Keepalive) -> Option<Duration> {
-        duration
+    fn from(Keepalive { time, .. }: Keepalive) -> Option<Duration> {
+        time
     }
 }
 
@@ -38,20 +66,123 @@ fn set_nodelay_or_warn(socket: &TcpStream) {
     }
 }
 
-fn set_keepalive_or_warn(
-    tcp: TcpStream,
-    keepalive_duration: Option<Duration>,
-) -> io::Result<TcpStream> {
+fn set_keepalive_or_warn(tcp: TcpStream, keepalive: Keepalive) -> io::Result<TcpStream> {
     let sock = {
         let stream = tokio::net::TcpStream::into_std(tcp)?;
         socket2::Socket::from(stream)
     };
-    let ka = keepalive_duration
+    let ka = keepalive
+        .time
         .into_iter()
         .fold(TcpKeepalive::new(), |k, t| k.with_time(t));
     if let Err(e) = sock.set_tcp_keepalive(&ka) {
         tracing::warn!("failed to set keepalive: {}", e);
     }
+    if let Err(e) = set_keepalive_probes(&sock, keepalive.interval, keepalive.probes) {
+        tracing::warn!("failed to set keepalive probes: {}", e);
+    }
+    if let Some(timeout) = keepalive.user_timeout {
+        if let Err(e) = set_user_timeout(&sock, timeout) {
+            tracing::warn!("failed to set user timeout: {}", e);
+        }
+    }
     let stream: std::net::TcpStream = socket2::Socket::into(sock);
     tokio::net::TcpStream::from_std(stream)
 }
+
+#[cfg(target_os = "linux")]
+mod sockopt {
+    use super::io;
+    use std::{convert::TryFrom, os::unix::io::AsRawFd, time::Duration};
+
+    pub(super) fn set_keepalive_probes(
+        sock: &socket2::Socket,
+        interval: Option<Duration>,
+        probes: Option<u32>,
+    ) -> io::Result<()> {
+        if let Some(interval) = interval {
+            let secs = libc::c_int::try_from(interval.as_secs().max(1)).unwrap_or(libc::c_int::MAX);
+            set(sock, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL, secs)?;
+        }
+        if let Some(probes) = probes {
+            let probes = libc::c_int::try_from(probes).unwrap_or(libc::c_int::MAX);
+            set(sock, libc::IPPROTO_TCP, libc::TCP_KEEPCNT, probes)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn set_user_timeout(sock: &socket2::Socket, timeout: Duration) -> io::Result<()> {
+        let millis = libc::c_int::try_from(timeout.as_millis()).unwrap_or(libc::c_int::MAX);
+        set(sock, libc::IPPROTO_TCP, libc::TCP_USER_TIMEOUT, millis)
+    }
+
+    pub(super) fn set_mark(sock: &impl AsRawFd, mark: u32) -> io::Result<()> {
+        // The kernel reads the mark as an unsigned 32-bit value.
+        set(sock, libc::SOL_SOCKET, libc::SO_MARK, mark as libc::c_int)
+    }
+
+    pub(super) fn set_transparent(sock: &socket2::Socket, ipv6: bool) -> io::Result<()> {
+        if ipv6 {
+            set(sock, libc::SOL_IPV6, libc::IPV6_TRANSPARENT, 1)
+        } else {
+            set(sock, libc::SOL_IP, libc::IP_TRANSPARENT, 1)
+        }
+    }
+
+    fn set(
+        sock: &impl AsRawFd,
+        level: libc::c_int,
+        name: libc::c_int,
+        value: libc::c_int,
+    ) -> io::Result<()> {
+        let ret = unsafe {
+            libc::setsockopt(
+                sock.as_raw_fd(),
+                level,
+                name,
+                &value as *const _ as *const libc::c_void,
+                std::mem::size_of_val(&value) as libc::socklen_t,
+            )
+        };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sockopt {
+    use super::io;
+    use std::time::Duration;
+
+    pub(super) fn set_keepalive_probes(
+        _: &socket2::Socket,
+        interval: Option<Duration>,
+        probes: Option<u32>,
+    ) -> io::Result<()> {
+        if interval.is_none() && probes.is_none() {
+            return Ok(());
+        }
+        Err(unsupported("TCP_KEEPINTVL and TCP_KEEPCNT"))
+    }
+
+    pub(super) fn set_user_timeout(_: &socket2::Socket, _: Duration) -> io::Result<()> {
+        Err(unsupported("TCP_USER_TIMEOUT"))
+    }
+
+    pub(super) fn set_mark<S>(_: &S, _: u32) -> io::Result<()> {
+        Err(unsupported("SO_MARK"))
+    }
+
+    pub(super) fn set_transparent(_: &socket2::Socket, _: bool) -> io::Result<()> {
+        Err(unsupported("IP_TRANSPARENT"))
+    }
+
+    fn unsupported(opt: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Other,
+            format!("{} is not supported on this platform", opt),
+        )
+    }
+}
@@ -2,7 +2,7 @@ use crate::{addrs::*, Keepalive};
 use futures::prelude::*;
 use linkerd_io as io;
 use linkerd_stack::Param;
-use std::{fmt, pin::Pin};
+use std::{fmt, net::SocketAddr, pin::Pin};
 use tokio::net::TcpStream;
 use tokio_stream::wrappers::TcpListenerStream;
 
@@ -15,6 +15,7 @@ pub trait Bind<T> {
         + io::AsyncWrite
         + io::Peek
         + io::PeerAddr
+        + io::Splice
         + fmt::Debug
         + Unpin
         + Send
@@ -29,7 +30,14 @@ pub trait Bind<T> {
 pub type Bound<I> = (Local<ServerAddr>, I);
 
 #[derive(Copy, Clone, Debug, Default)]
-pub struct BindTcp(());
+pub struct BindTcp {
+    transparent: bool,
+}
+
+/// The maximum number of connections that a listener's kernel queue holds
+/// before they are accepted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Backlog(pub u32);
 
 #[derive(Clone, Debug)]
 pub struct Addrs {
@@ -43,11 +51,17 @@ impl BindTcp {
     pub fn with_orig_dst() -> super::BindWithOrigDst<Self> {
         super::BindWithOrigDst::from(Self::default())
     }
+
+    /// Binds transparent listeners that accept connections intercepted by
+    /// `TPROXY` rules.
+    pub fn with_tproxy() -> super::BindWithOrigDst<Self> {
+        super::BindWithOrigDst::new(super::orig_dst::Mode::Tproxy, Self { transparent: true })
+    }
 }
 
 impl<T> Bind<T> for BindTcp
 where
-    T: Param<ListenAddr> + Param<Keepalive>,
+    T: Param<ListenAddr> + Param<Keepalive> + Param<Backlog>,
 {
     type Addrs = Addrs;
     type Incoming = Pin<Box<dyn Stream<Item = io::Result<(Self::Addrs, Self::Io)>> + Send + Sync>>;
@@ -56,13 +70,13 @@ where
     fn bind(self, params: &T) -> io::Result<Bound<Self::Incoming>> {
         let listen = {
             let ListenAddr(addr) = params.param();
-            let l = std::net::TcpListener::bind(addr)?;
+            let l = bind(addr, self.transparent, params.param())?;
             // Ensure that O_NONBLOCK is set on the socket before using it with Tokio.
             l.set_nonblocking(true)?;
             tokio::net::TcpListener::from_std(l).expect("listener must be valid")
         };
         let server = Local(ServerAddr(listen.local_addr()?));
-        let Keepalive(keepalive) = params.param();
+        let keepalive: Keepalive = params.param();
         let accept = TcpListenerStream::new(listen).map(move |res| {
             let tcp = res?;
             super::set_nodelay_or_warn(&tcp);
@@ -75,6 +89,38 @@ where
     }
 }
 
+/// Binds a listener with the given backlog. Transparent listeners have
+/// `IP_TRANSPARENT` set, so that they accept connections regardless of their
+/// destination addresses.
+fn bind(
+    addr: SocketAddr,
+    transparent: bool,
+    Backlog(backlog): Backlog,
+) -> io::Result<std::net::TcpListener> {
+    use socket2::{Domain, Socket, Type};
+
+    let sock = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    // Like the standard library's listeners, permit rebinding addresses in
+    // TIME_WAIT.
+    #[cfg(unix)]
+    sock.set_reuse_address(true)?;
+    if transparent {
+        super::set_transparent(&sock, addr.is_ipv6())?;
+    }
+    sock.bind(&addr.into())?;
+    sock.listen(backlog.min(i32::MAX as u32) as i32)?;
+    Ok(sock.into())
+}
+
+// === impl Backlog ===
+
+impl Default for Backlog {
+    /// The standard library's default backlog.
+    fn default() -> Self {
+        Self(128)
+    }
+}
+
 // === impl Addrs ===
 
 impl Param<Remote<ClientAddr>> for Addrs {
@@ -11,6 +11,21 @@ use tokio::net::TcpStream;
 #[derive(Copy, Clone, Debug, Default)]
 pub struct BindWithOrigDst<B = listen::BindTcp> {
     inner: B,
+    mode: Mode,
+}
+
+/// Determines how an accepted connection's original destination is read.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Connections are redirected to the proxy (e.g. by an iptables
+    /// `REDIRECT` rule) and their original destinations are read with
+    /// `SO_ORIGINAL_DST`.
+    Redirect,
+
+    /// Connections are intercepted by a `TPROXY` rule, so their original
+    /// destinations are the accepted sockets' local addresses. The listener
+    /// must be bound with `IP_TRANSPARENT`.
+    Tproxy,
 }
 
 #[derive(Clone, Debug)]
@@ -45,11 +60,25 @@ where
     }
 }
 
+// === impl Mode ===
+
+impl Default for Mode {
+    fn default() -> Self {
+        Self::Redirect
+    }
+}
+
 // === impl WithOrigDst ===
 
+impl<B> BindWithOrigDst<B> {
+    pub fn new(mode: Mode, inner: B) -> Self {
+        Self { inner, mode }
+    }
+}
+
 impl<B> From<B> for BindWithOrigDst<B> {
     fn from(inner: B) -> Self {
-        Self { inner }
+        Self::new(Mode::default(), inner)
     }
 }
 
@@ -65,9 +94,13 @@ where
     fn bind(self, t: &T) -> io::Result<Bound<Self::Incoming>> {
         let (addr, incoming) = self.inner.bind(t)?;
 
-        let incoming = incoming.map(|res| {
+        let mode = self.mode;
+        let incoming = incoming.map(move |res| {
             let (inner, tcp) = res?;
-            let orig_dst = orig_dst_addr(&tcp)?;
+            let orig_dst = match mode {
+                Mode::Redirect => orig_dst_addr(&tcp)?,
+                Mode::Tproxy => OrigDstAddr(tcp.local_addr()?),
+            };
             let addrs = Addrs { inner, orig_dst };
             Ok((addrs, tcp))
         });
@@ -189,3 +222,45 @@ mod linux {
         <u32>::from_be(i)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keepalive;
+
+    struct Server;
+
+    impl Param<ListenAddr> for Server {
+        fn param(&self) -> ListenAddr {
+            ListenAddr(([127, 0, 0, 1], 0).into())
+        }
+    }
+
+    impl Param<Keepalive> for Server {
+        fn param(&self) -> Keepalive {
+            Keepalive::default()
+        }
+    }
+
+    impl Param<listen::Backlog> for Server {
+        fn param(&self) -> listen::Backlog {
+            listen::Backlog::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn tproxy_reads_local_addr() {
+        // The listener is bound without `IP_TRANSPARENT`, which requires
+        // elevated privileges.
+        let bind = BindWithOrigDst::new(Mode::Tproxy, listen::BindTcp::default());
+        let (Local(ServerAddr(addr)), mut incoming) = bind.bind(&Server).expect("must bind");
+
+        let _client = TcpStream::connect(addr).await.expect("must connect");
+        let (addrs, _io) = incoming
+            .next()
+            .await
+            .expect("must accept")
+            .expect("must accept");
+        assert_eq!(addrs.orig_dst, OrigDstAddr(addr));
+    }
+}
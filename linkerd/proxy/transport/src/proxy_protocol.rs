@@ -0,0 +1,489 @@
+//! Reads PROXY protocol v2 headers from connections accepted from trusted
+//! networks.
+//!
+//! When a load balancer fronts the proxy, the connection's peer address is the
+//! load balancer's. Load balancers that support the PROXY protocol send a
+//! binary preamble describing the original client's address. Connections from
+//! trusted networks must begin with such a preamble; the client address it
+//! describes is then used as the connection's `Remote<ClientAddr>` and as its
+//! I/O's peer address.
+//!
+//! Headers may also be encoded for connections to upstream servers that expect
+//! them.
+//!
+//! See <https://www.haproxy.org/download/2.4/doc/proxy-protocol.txt>.
+
+use crate::{
+    addrs::*,
+    listen::{Bind, Bound},
+};
+use futures::prelude::*;
+use ipnet::IpNet;
+use linkerd_io::{self as io, AsyncReadExt};
+use linkerd_stack::Param;
+use std::{
+    iter::FromIterator,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+    task::Context,
+    time::Duration,
+};
+use tokio::sync::{mpsc, Semaphore};
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, Instrument};
+
+/// Networks from which connections must present a PROXY protocol header.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedNetworks(Arc<[IpNet]>);
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BindWithProxyProtocol<B> {
+    inner: B,
+}
+
+#[derive(Clone, Debug)]
+pub struct Addrs<A> {
+    pub inner: A,
+
+    /// The client address read from the connection's PROXY protocol header,
+    /// if any.
+    pub client: Option<Remote<ClientAddr>>,
+}
+
+/// A connection whose peer address may have been read from a PROXY protocol
+/// header.
+#[derive(Debug)]
+pub struct Io<I> {
+    io: I,
+    client: Option<SocketAddr>,
+}
+
+/// Bounds the time that a connection may take to send its header.
+const HEADER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Bounds the number of connections whose headers may be read concurrently.
+const MAX_PENDING_HEADERS: usize = 1_024;
+
+const SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+// === impl TrustedNetworks ===
+
+impl TrustedNetworks {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.iter().any(|net| net.contains(ip))
+    }
+}
+
+impl FromIterator<IpNet> for TrustedNetworks {
+    fn from_iter<T: IntoIterator<Item = IpNet>>(iter: T) -> Self {
+        Self(iter.into_iter().collect::<Vec<_>>().into())
+    }
+}
+
+// === impl BindWithProxyProtocol ===
+
+impl<B> From<B> for BindWithProxyProtocol<B> {
+    fn from(inner: B) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T, B> Bind<T> for BindWithProxyProtocol<B>
+where
+    T: Param<TrustedNetworks>,
+    B: Bind<T> + 'static,
+    B::Addrs: Param<Remote<ClientAddr>>,
+{
+    type Addrs = Addrs<B::Addrs>;
+    type Io = Io<B::Io>;
+    type Incoming =
+        Pin<Box<dyn Stream<Item = io::Result<(Self::Addrs, Self::Io)>> + Send + Sync + 'static>>;
+
+    fn bind(self, t: &T) -> io::Result<Bound<Self::Incoming>> {
+        let trusted: TrustedNetworks = t.param();
+        let (addr, incoming) = self.inner.bind(t)?;
+
+        // Headers are read on per-connection tasks so that a slow client
+        // doesn't delay other connections. Connections are yielded as their
+        // headers are read.
+        let (tx, rx) = mpsc::channel(MAX_PENDING_HEADERS);
+        tokio::spawn(accept_all(trusted, incoming, tx).in_current_span());
+
+        Ok((addr, Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+async fn accept_all<A, I>(
+    trusted: TrustedNetworks,
+    incoming: impl Stream<Item = io::Result<(A, I)>>,
+    tx: mpsc::Sender<io::Result<(Addrs<A>, Io<I>)>>,
+) where
+    A: Param<Remote<ClientAddr>> + Send + 'static,
+    I: io::AsyncRead + Unpin + Send + 'static,
+{
+    let pending = Arc::new(Semaphore::new(MAX_PENDING_HEADERS));
+    futures::pin_mut!(incoming);
+    loop {
+        let res = tokio::select! {
+            res = incoming.next() => match res {
+                Some(res) => res,
+                None => return,
+            },
+            _ = tx.closed() => return,
+        };
+
+        let permit = match pending.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => return,
+        };
+        let tx = tx.clone();
+        let trusted = trusted.clone();
+        tokio::spawn(
+            async move {
+                let res = accept(trusted, res).await;
+                drop(permit);
+                let _ = tx.send(res).await;
+            }
+            .in_current_span(),
+        );
+    }
+}
+
+async fn accept<A, I>(
+    trusted: TrustedNetworks,
+    res: io::Result<(A, I)>,
+) -> io::Result<(Addrs<A>, Io<I>)>
+where
+    A: Param<Remote<ClientAddr>>,
+    I: io::AsyncRead + Unpin,
+{
+    let (inner, mut io) = res?;
+    let Remote(ClientAddr(peer)) = inner.param();
+    if !trusted.contains(&peer.ip()) {
+        return Ok((Addrs::new(inner, None), Io::new(io, None)));
+    }
+
+    let client = tokio::time::timeout(HEADER_TIMEOUT, read_header(&mut io))
+        .await
+        .map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::TimedOut,
+                "timed out reading PROXY protocol header",
+            )
+        })??;
+    debug!(%peer, ?client, "Read PROXY protocol header");
+    Ok((Addrs::new(inner, client), Io::new(io, client)))
+}
+
+/// Reads a PROXY protocol v2 header, returning the source address of proxied
+/// TCP and UDP connections.
+///
+/// `LOCAL` connections (e.g. health checks initiated by the load balancer)
+/// and connections over other address families do not describe a client
+/// address.
+async fn read_header<I: io::AsyncRead + Unpin>(io: &mut I) -> io::Result<Option<SocketAddr>> {
+    let mut hdr = [0u8; 16];
+    io.read_exact(&mut hdr).await?;
+    if hdr[..12] != SIGNATURE {
+        return Err(invalid("missing PROXY protocol v2 signature"));
+    }
+    let version = hdr[12] >> 4;
+    let command = hdr[12] & 0x0f;
+    if version != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+    let family = hdr[13] >> 4;
+    let len = u16::from_be_bytes([hdr[14], hdr[15]]) as usize;
+
+    // Read the entire address block, including any TLVs, so that the
+    // application's data follows.
+    let mut addrs = vec![0u8; len];
+    io.read_exact(&mut addrs).await?;
+
+    const LOCAL: u8 = 0x0;
+    const PROXY: u8 = 0x1;
+    const AF_INET: u8 = 0x1;
+    const AF_INET6: u8 = 0x2;
+    match (command, family) {
+        (LOCAL, _) => Ok(None),
+        (PROXY, AF_INET) if len >= 12 => {
+            let mut ip = [0u8; 4];
+            ip.copy_from_slice(&addrs[..4]);
+            let port = u16::from_be_bytes([addrs[8], addrs[9]]);
+            Ok(Some((Ipv4Addr::from(ip), port).into()))
+        }
+        (PROXY, AF_INET6) if len >= 36 => {
+            let mut ip = [0u8; 16];
+            ip.copy_from_slice(&addrs[..16]);
+            let port = u16::from_be_bytes([addrs[32], addrs[33]]);
+            Ok(Some((Ipv6Addr::from(ip), port).into()))
+        }
+        (PROXY, AF_INET) | (PROXY, AF_INET6) => Err(invalid("truncated PROXY protocol addresses")),
+        (PROXY, _) => Ok(None),
+        _ => Err(invalid("unsupported PROXY protocol command")),
+    }
+}
+
+/// Encodes a PROXY protocol v2 header describing a proxied TCP connection from
+/// `client` to `server`.
+///
+/// If the addresses are of different families, IPv4 addresses are encoded as
+/// IPv4-mapped IPv6 addresses.
+pub fn encode_header(client: SocketAddr, server: SocketAddr) -> Vec<u8> {
+    const PROXY_TCP4: u8 = 0x11;
+    const PROXY_TCP6: u8 = 0x21;
+
+    let mut hdr = SIGNATURE.to_vec();
+    hdr.push(0x21); // Version 2, PROXY command.
+    match (client, server) {
+        (SocketAddr::V4(client), SocketAddr::V4(server)) => {
+            hdr.push(PROXY_TCP4);
+            hdr.extend_from_slice(&12u16.to_be_bytes());
+            hdr.extend_from_slice(&client.ip().octets());
+            hdr.extend_from_slice(&server.ip().octets());
+        }
+        (client, server) => {
+            let to_v6 = |addr: SocketAddr| match addr.ip() {
+                IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+                IpAddr::V6(ip) => ip,
+            };
+            hdr.push(PROXY_TCP6);
+            hdr.extend_from_slice(&36u16.to_be_bytes());
+            hdr.extend_from_slice(&to_v6(client).octets());
+            hdr.extend_from_slice(&to_v6(server).octets());
+        }
+    }
+    hdr.extend_from_slice(&client.port().to_be_bytes());
+    hdr.extend_from_slice(&server.port().to_be_bytes());
+    hdr
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// === impl Addrs ===
+
+impl<A> Addrs<A> {
+    fn new(inner: A, client: Option<SocketAddr>) -> Self {
+        Self {
+            inner,
+            client: client.map(|addr| Remote(ClientAddr(addr))),
+        }
+    }
+}
+
+impl<A> Param<Remote<ClientAddr>> for Addrs<A>
+where
+    A: Param<Remote<ClientAddr>>,
+{
+    fn param(&self) -> Remote<ClientAddr> {
+        self.client.unwrap_or_else(|| self.inner.param())
+    }
+}
+
+impl<A> Param<Local<ServerAddr>> for Addrs<A>
+where
+    A: Param<Local<ServerAddr>>,
+{
+    fn param(&self) -> Local<ServerAddr> {
+        self.inner.param()
+    }
+}
+
+impl<A> Param<OrigDstAddr> for Addrs<A>
+where
+    A: Param<OrigDstAddr>,
+{
+    fn param(&self) -> OrigDstAddr {
+        self.inner.param()
+    }
+}
+
+// === impl Io ===
+
+impl<I> Io<I> {
+    fn new(io: I, client: Option<SocketAddr>) -> Self {
+        Self { io, client }
+    }
+}
+
+#[async_trait::async_trait]
+impl<I: io::Peek + Send + Sync> io::Peek for Io<I> {
+    async fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.io.peek(buf).await
+    }
+}
+
+impl<I: io::PeerAddr> io::PeerAddr for Io<I> {
+    #[inline]
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match self.client {
+            Some(client) => Ok(client),
+            None => self.io.peer_addr(),
+        }
+    }
+}
+
+impl<I: io::Splice> io::Splice for Io<I> {
+    #[inline]
+    fn splice_socket(&self) -> Option<&tokio::net::TcpStream> {
+        self.io.splice_socket()
+    }
+
+    #[inline]
+    fn record_splice_read(&mut self, sz: usize) {
+        self.io.record_splice_read(sz)
+    }
+
+    #[inline]
+    fn record_splice_write(&mut self, sz: usize) {
+        self.io.record_splice_write(sz)
+    }
+}
+
+impl<I: io::AsyncRead + Unpin> io::AsyncRead for Io<I> {
+    #[inline]
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut io::ReadBuf<'_>,
+    ) -> io::Poll<()> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl<I: io::AsyncWrite + Unpin> io::AsyncWrite for Io<I> {
+    #[inline]
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+
+    #[inline]
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> io::Poll<usize> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> io::Poll<usize> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(command: u8, family: u8, addrs: &[u8]) -> Vec<u8> {
+        let mut hdr = SIGNATURE.to_vec();
+        hdr.push(0x20 | command);
+        hdr.push(family);
+        hdr.extend_from_slice(&(addrs.len() as u16).to_be_bytes());
+        hdr.extend_from_slice(addrs);
+        hdr
+    }
+
+    #[tokio::test]
+    async fn slow_headers_do_not_delay_other_connections() {
+        #[derive(Clone, Debug)]
+        struct Peer(SocketAddr);
+
+        impl Param<Remote<ClientAddr>> for Peer {
+            fn param(&self) -> Remote<ClientAddr> {
+                Remote(ClientAddr(self.0))
+            }
+        }
+
+        let trusted = vec!["10.0.0.0/8".parse::<IpNet>().unwrap()]
+            .into_iter()
+            .collect::<TrustedNetworks>();
+        // The trusted client never sends its header.
+        let (slow, _slow_client) = tokio::io::duplex(64);
+        let (fast, _fast_client) = tokio::io::duplex(64);
+        let incoming = futures::stream::iter(vec![
+            Ok((Peer(([10, 0, 0, 1], 40000).into()), slow)),
+            Ok((Peer(([192, 0, 2, 1], 40000).into()), fast)),
+        ]);
+
+        let (tx, mut rx) = mpsc::channel(1);
+        tokio::spawn(accept_all(trusted, incoming, tx));
+        let (addrs, _) = rx.recv().await.unwrap().unwrap();
+        assert_eq!(addrs.inner.0, SocketAddr::from(([192, 0, 2, 1], 40000)));
+        assert!(addrs.client.is_none());
+    }
+
+    #[tokio::test]
+    async fn reads_inet_header() {
+        let addrs = [192, 0, 2, 1, 10, 0, 0, 2, 0x9c, 0x40, 0x1f, 0x90];
+        let mut buf = header(0x1, 0x11, &addrs);
+        buf.extend_from_slice(b"GET / HTTP/1.1\r\n");
+
+        let mut io = &buf[..];
+        let client = read_header(&mut io).await.unwrap();
+        assert_eq!(client, Some(([192, 0, 2, 1], 40000).into()));
+        // The application's data is not consumed.
+        assert_eq!(io, b"GET / HTTP/1.1\r\n");
+    }
+
+    #[tokio::test]
+    async fn reads_inet6_header() {
+        let mut addrs = vec![0u8; 36];
+        addrs[15] = 1;
+        addrs[31] = 2;
+        addrs[32..34].copy_from_slice(&443u16.to_be_bytes());
+        addrs[34..36].copy_from_slice(&8443u16.to_be_bytes());
+        let buf = header(0x1, 0x21, &addrs);
+
+        let client = read_header(&mut &buf[..]).await.unwrap();
+        assert_eq!(client, Some((Ipv6Addr::LOCALHOST, 443).into()));
+    }
+
+    #[tokio::test]
+    async fn reads_local_header() {
+        let buf = header(0x0, 0x00, &[]);
+        assert_eq!(read_header(&mut &buf[..]).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_headers() {
+        let buf = b"GET / HTTP/1.1\r\nhost: example.com\r\n\r\n";
+        assert!(read_header(&mut &buf[..]).await.is_err());
+
+        // Truncated addresses.
+        let buf = header(0x1, 0x11, &[192, 0, 2, 1]);
+        assert!(read_header(&mut &buf[..]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn encodes_headers() {
+        let client = SocketAddr::from(([192, 0, 2, 1], 40000));
+        let buf = encode_header(client, ([10, 0, 0, 2], 8080).into());
+        assert_eq!(buf.len(), 28);
+        assert_eq!(read_header(&mut &buf[..]).await.unwrap(), Some(client));
+
+        let server = SocketAddr::from((Ipv6Addr::LOCALHOST, 8080));
+        let buf = encode_header(client, server);
+        assert_eq!(buf.len(), 52);
+        let mapped = SocketAddr::from((Ipv4Addr::new(192, 0, 2, 1).to_ipv6_mapped(), 40000));
+        assert_eq!(read_header(&mut &buf[..]).await.unwrap(), Some(mapped));
+    }
+}
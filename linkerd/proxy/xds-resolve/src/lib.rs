@@ -0,0 +1,251 @@
+//! Resolves endpoints from an Envoy-compatible control plane.
+//!
+//! Each resolution opens an aggregated discovery (ADS) stream on which the
+//! logical name's cluster is requested, by its `host:port` name, with the
+//! cluster discovery service (CDS). Clusters that are discovered with the
+//! endpoint discovery service (EDS) are then subscribed to on the same stream;
+//! statically-defined clusters provide their endpoints directly. Every update
+//! received from the control plane replaces the resolution's endpoints.
+
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+use async_stream::try_stream;
+use envoy_xds_proto::{
+    envoy::{
+        config::{cluster::v3::Cluster, core::v3::Node, endpoint::v3::ClusterLoadAssignment},
+        service::discovery::v3::{
+            aggregated_discovery_service_client::AggregatedDiscoveryServiceClient,
+            DiscoveryRequest, DiscoveryResponse,
+        },
+    },
+    google::rpc,
+};
+use futures::prelude::*;
+use http_body::Body;
+use linkerd_error::Error;
+use linkerd_proxy_api_resolve::{ConcreteAddr, Metadata};
+use linkerd_proxy_core::resolve::Update;
+use linkerd_stack::Param;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::{self as grpc, body::BoxBody, client::GrpcService};
+use tower::Service;
+use tracing::{debug, trace, warn};
+
+mod pb;
+
+const CLUSTER_TYPE_URL: &str = "type.googleapis.com/envoy.config.cluster.v3.Cluster";
+const ENDPOINTS_TYPE_URL: &str =
+    "type.googleapis.com/envoy.config.endpoint.v3.ClusterLoadAssignment";
+
+#[derive(Clone)]
+pub struct Resolve<S> {
+    client: AggregatedDiscoveryServiceClient<S>,
+    node: Node,
+}
+
+/// Tracks the state of a subscription to a single resource of a given type.
+#[derive(Debug)]
+struct Subscription {
+    type_url: &'static str,
+    name: String,
+    version: String,
+    nonce: String,
+}
+
+type UpdatesStream =
+    Pin<Box<dyn Stream<Item = Result<Update<Metadata>, grpc::Status>> + Send + 'static>>;
+
+type ResolveFuture =
+    Pin<Box<dyn Future<Output = Result<UpdatesStream, grpc::Status>> + Send + 'static>>;
+
+// === impl Resolve ===
+
+impl<S> Resolve<S>
+where
+    S: GrpcService<BoxBody> + Clone + Send + 'static,
+    S::Error: Into<Error> + Send,
+    S::ResponseBody: Send + Sync,
+    <S::ResponseBody as Body>::Data: Send,
+    <S::ResponseBody as Body>::Error: Into<Error> + Send,
+    S::Future: Send,
+{
+    /// Creates a resolver that identifies itself to the control plane with the
+    /// given node ID and cluster.
+    pub fn new(svc: S, node_id: String, node_cluster: String) -> Self {
+        Self {
+            client: AggregatedDiscoveryServiceClient::new(svc),
+            node: Node {
+                id: node_id,
+                cluster: node_cluster,
+                user_agent_name: "linkerd2-proxy".to_string(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl<T, S> Service<T> for Resolve<S>
+where
+    T: Param<ConcreteAddr>,
+    S: GrpcService<BoxBody> + Clone + Send + 'static,
+    S::Error: Into<Error> + Send,
+    S::ResponseBody: Send + Sync,
+    <S::ResponseBody as Body>::Data: Send,
+    <S::ResponseBody as Body>::Error: Into<Error> + Send,
+    S::Future: Send,
+{
+    type Response = UpdatesStream;
+    type Error = grpc::Status;
+    type Future = ResolveFuture;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        let ConcreteAddr(addr) = target.param();
+        debug!(dst = %addr, node = %self.node.id, "Resolving");
+
+        let cluster = Subscription::new(CLUSTER_TYPE_URL, addr.to_string());
+        let (tx, rx) = mpsc::unbounded_channel();
+        let _ = tx.send(cluster.request(&self.node, None));
+
+        let node = self.node.clone();
+        let mut client = self.client.clone();
+        Box::pin(async move {
+            // Wait for the server to respond once before returning a stream.
+            // This lets us eagerly detect errors.
+            let rsp = client
+                .stream_aggregated_resources(grpc::Request::new(UnboundedReceiverStream::new(rx)))
+                .await?;
+            trace!(metadata = ?rsp.metadata());
+            let stream: UpdatesStream = Box::pin(resolution(node, cluster, tx, rsp.into_inner()));
+            Ok(stream)
+        })
+    }
+}
+
+fn resolution(
+    node: Node,
+    mut cluster: Subscription,
+    tx: mpsc::UnboundedSender<DiscoveryRequest>,
+    mut responses: grpc::Streaming<DiscoveryResponse>,
+) -> impl Stream<Item = Result<Update<Metadata>, grpc::Status>> {
+    try_stream! {
+        let mut endpoints: Option<Subscription> = None;
+        while let Some(rsp) = responses.next().await {
+            let rsp = rsp?;
+            trace!(type_url = %rsp.type_url, version = %rsp.version_info, nonce = %rsp.nonce);
+
+            if rsp.type_url == CLUSTER_TYPE_URL {
+                let res = pb::find::<Cluster>(&rsp, &cluster.name, |c| &c.name);
+                let error = res.as_ref().err().map(ToString::to_string);
+                let _ = tx.send(cluster.respond(&rsp, &node, error));
+                match res {
+                    Ok(None) => {
+                        debug!(cluster = %cluster.name, "Does not exist");
+                        endpoints = None;
+                        yield Update::DoesNotExist;
+                    }
+                    Ok(Some(c)) => match pb::eds_service_name(&c) {
+                        Some(name) => {
+                            // Subscribe to the cluster's endpoints, unless
+                            // already subscribed.
+                            if endpoints.as_ref().map(|s| s.name != name).unwrap_or(true) {
+                                debug!(cluster = %cluster.name, service = %name, "Subscribing to endpoints");
+                                let eps = Subscription::new(ENDPOINTS_TYPE_URL, name);
+                                let _ = tx.send(eps.request(&node, None));
+                                endpoints = Some(eps);
+                            }
+                        }
+                        None => {
+                            endpoints = None;
+                            let addrs = match c.load_assignment {
+                                Some(cla) => pb::to_addr_metas(cla),
+                                None => {
+                                    warn!(cluster = %cluster.name, "Cluster has no endpoints");
+                                    Vec::new()
+                                }
+                            };
+                            debug!(endpoints = %addrs.len(), "Reset");
+                            yield Update::Reset(addrs);
+                        }
+                    },
+                    Err(error) => {
+                        warn!(cluster = %cluster.name, %error, "Rejected cluster update");
+                    }
+                }
+                continue;
+            }
+
+            if rsp.type_url == ENDPOINTS_TYPE_URL {
+                if let Some(eps) = endpoints.as_mut() {
+                    let res = pb::find::<ClusterLoadAssignment>(&rsp, &eps.name, |c| &c.cluster_name);
+                    let error = res.as_ref().err().map(ToString::to_string);
+                    let _ = tx.send(eps.respond(&rsp, &node, error));
+                    match res {
+                        Ok(cla) => {
+                            let addrs = cla.map(pb::to_addr_metas).unwrap_or_default();
+                            debug!(endpoints = %addrs.len(), "Reset");
+                            yield Update::Reset(addrs);
+                        }
+                        Err(error) => {
+                            warn!(service = %eps.name, %error, "Rejected endpoints update");
+                        }
+                    }
+                }
+                continue;
+            }
+
+            debug!(type_url = %rsp.type_url, "Ignoring unexpected resource type");
+        }
+    }
+}
+
+// === impl Subscription ===
+
+impl Subscription {
+    fn new(type_url: &'static str, name: String) -> Self {
+        Self {
+            type_url,
+            name,
+            version: String::new(),
+            nonce: String::new(),
+        }
+    }
+
+    fn request(&self, node: &Node, error: Option<String>) -> DiscoveryRequest {
+        DiscoveryRequest {
+            version_info: self.version.clone(),
+            node: Some(node.clone()),
+            resource_names: vec![self.name.clone()],
+            type_url: self.type_url.to_string(),
+            response_nonce: self.nonce.clone(),
+            error_detail: error.map(|message| rpc::Status {
+                code: grpc::Code::InvalidArgument as i32,
+                message,
+            }),
+        }
+    }
+
+    /// Acknowledges a response or, if it could not be processed, rejects it
+    /// while retaining the last accepted version.
+    fn respond(
+        &mut self,
+        rsp: &DiscoveryResponse,
+        node: &Node,
+        error: Option<String>,
+    ) -> DiscoveryRequest {
+        self.nonce = rsp.nonce.clone();
+        if error.is_none() {
+            self.version = rsp.version_info.clone();
+        }
+        self.request(node, error)
+    }
+}
@@ -0,0 +1,220 @@
+use envoy_xds_proto::envoy::{
+    config::{
+        cluster::v3::{cluster::ClusterDiscoveryType, cluster::DiscoveryType, Cluster},
+        core::v3::{address, socket_address::PortSpecifier, HealthStatus, Locality},
+        endpoint::v3::{lb_endpoint::HostIdentifier, ClusterLoadAssignment, LbEndpoint},
+    },
+    service::discovery::v3::DiscoveryResponse,
+};
+use linkerd_proxy_api_resolve::{Metadata, ProtocolHint};
+use std::net::{IpAddr, SocketAddr};
+
+/// Decodes the response's resources, returning the one with the given name.
+pub(crate) fn find<M: prost::Message + Default>(
+    rsp: &DiscoveryResponse,
+    name: &str,
+    name_of: impl Fn(&M) -> &String,
+) -> Result<Option<M>, prost::DecodeError> {
+    for any in rsp.resources.iter() {
+        if any.type_url != rsp.type_url {
+            continue;
+        }
+        let resource = M::decode(any.value.as_slice())?;
+        if name_of(&resource) == name {
+            return Ok(Some(resource));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the name with which a cluster's endpoints are discovered, if the
+/// cluster is discovered with EDS.
+pub(crate) fn eds_service_name(cluster: &Cluster) -> Option<String> {
+    match cluster.cluster_discovery_type {
+        Some(ClusterDiscoveryType::Type(t)) if t == DiscoveryType::Eds as i32 => {
+            let name = cluster
+                .eds_cluster_config
+                .as_ref()
+                .map(|c| c.service_name.clone())
+                .filter(|n| !n.is_empty())
+                .unwrap_or_else(|| cluster.name.clone());
+            Some(name)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the healthy endpoints in a cluster's highest-priority group that
+/// has any healthy endpoints.
+///
+/// Each endpoint is weighted by the product of its own weight and its
+/// locality's weight. Endpoints are labeled with their locality's region and
+/// zone, when known.
+pub(crate) fn to_addr_metas(cla: ClusterLoadAssignment) -> Vec<(SocketAddr, Metadata)> {
+    let mut groups = cla.endpoints;
+    groups.sort_by_key(|g| g.priority);
+
+    let mut addrs = Vec::new();
+    let mut priority = None;
+    for group in groups.into_iter() {
+        // Stop at the first priority level with any usable endpoints.
+        if priority != Some(group.priority) && !addrs.is_empty() {
+            break;
+        }
+        priority = Some(group.priority);
+
+        let locality_weight = group.load_balancing_weight.map(|w| w.value).unwrap_or(1);
+        let labels = group.locality.map(to_labels).unwrap_or_default();
+        for ep in group.lb_endpoints.into_iter() {
+            if let Some((addr, weight)) = to_weighted_addr(ep) {
+                let meta = Metadata::new(labels.clone(), ProtocolHint::Unknown, None, None, None)
+                    .with_weight(weight.saturating_mul(locality_weight).max(1));
+                addrs.push((addr, meta));
+            }
+        }
+    }
+    addrs
+}
+
+fn to_weighted_addr(ep: LbEndpoint) -> Option<(SocketAddr, u32)> {
+    match HealthStatus::from_i32(ep.health_status) {
+        Some(HealthStatus::Unknown)
+        | Some(HealthStatus::Healthy)
+        | Some(HealthStatus::Degraded) => {}
+        _ => return None,
+    }
+
+    let weight = ep.load_balancing_weight.map(|w| w.value).unwrap_or(1);
+    let endpoint = match ep.host_identifier? {
+        HostIdentifier::Endpoint(endpoint) => endpoint,
+        HostIdentifier::EndpointName(_) => return None,
+    };
+    let sa = match endpoint.address?.address? {
+        address::Address::SocketAddress(sa) => sa,
+    };
+    let ip = sa.address.parse::<IpAddr>().ok()?;
+    let port = match sa.port_specifier? {
+        PortSpecifier::PortValue(port) if port > 0 && port <= u16::MAX as u32 => port as u16,
+        _ => return None,
+    };
+    Some((SocketAddr::new(ip, port), weight))
+}
+
+fn to_labels(locality: Locality) -> Vec<(String, String)> {
+    let mut labels = Vec::new();
+    if !locality.region.is_empty() {
+        labels.push(("region".to_string(), locality.region));
+    }
+    if !locality.zone.is_empty() {
+        labels.push(("zone".to_string(), locality.zone));
+    }
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use envoy_xds_proto::envoy::config::{
+        core::v3::{Address, SocketAddress},
+        endpoint::v3::{Endpoint, LocalityLbEndpoints},
+    };
+
+    fn lb_endpoint(addr: &str, port: u32, health: HealthStatus, weight: Option<u32>) -> LbEndpoint {
+        LbEndpoint {
+            host_identifier: Some(HostIdentifier::Endpoint(Endpoint {
+                address: Some(Address {
+                    address: Some(address::Address::SocketAddress(SocketAddress {
+                        address: addr.to_string(),
+                        port_specifier: Some(PortSpecifier::PortValue(port)),
+                        ..Default::default()
+                    })),
+                }),
+                ..Default::default()
+            })),
+            health_status: health as i32,
+            load_balancing_weight: weight.map(|value| prost_types::UInt32Value { value }),
+        }
+    }
+
+    #[test]
+    fn uses_healthy_endpoints_in_highest_priority() {
+        let cla = ClusterLoadAssignment {
+            cluster_name: "web".to_string(),
+            endpoints: vec![
+                LocalityLbEndpoints {
+                    priority: 1,
+                    lb_endpoints: vec![lb_endpoint("10.0.1.1", 80, HealthStatus::Healthy, None)],
+                    ..Default::default()
+                },
+                LocalityLbEndpoints {
+                    priority: 0,
+                    locality: Some(Locality {
+                        zone: "west".to_string(),
+                        ..Default::default()
+                    }),
+                    load_balancing_weight: Some(prost_types::UInt32Value { value: 2 }),
+                    lb_endpoints: vec![
+                        lb_endpoint("10.0.0.1", 80, HealthStatus::Unknown, Some(3)),
+                        lb_endpoint("10.0.0.2", 80, HealthStatus::Draining, None),
+                        lb_endpoint("10.0.0.3", 0, HealthStatus::Healthy, None),
+                    ],
+                },
+            ],
+        };
+
+        let addrs = to_addr_metas(cla);
+        assert_eq!(addrs.len(), 1);
+        let (addr, meta) = &addrs[0];
+        assert_eq!(*addr, SocketAddr::from(([10, 0, 0, 1], 80)));
+        assert_eq!(meta.weight(), 6);
+        assert_eq!(meta.labels().get("zone").map(String::as_str), Some("west"));
+    }
+
+    #[test]
+    fn fails_over_to_lower_priorities() {
+        let cla = ClusterLoadAssignment {
+            cluster_name: "web".to_string(),
+            endpoints: vec![
+                LocalityLbEndpoints {
+                    priority: 0,
+                    lb_endpoints: vec![lb_endpoint("10.0.0.1", 80, HealthStatus::Unhealthy, None)],
+                    ..Default::default()
+                },
+                LocalityLbEndpoints {
+                    priority: 1,
+                    lb_endpoints: vec![lb_endpoint("10.0.1.1", 80, HealthStatus::Healthy, None)],
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let addrs = to_addr_metas(cla);
+        assert_eq!(addrs.len(), 1);
+        assert_eq!(addrs[0].0, SocketAddr::from(([10, 0, 1, 1], 80)));
+        assert_eq!(addrs[0].1.weight(), 1);
+    }
+
+    #[test]
+    fn eds_service_name_defaults_to_cluster_name() {
+        let mut cluster = Cluster {
+            name: "web.ns.svc.cluster.local:80".to_string(),
+            cluster_discovery_type: Some(ClusterDiscoveryType::Type(DiscoveryType::Eds as i32)),
+            ..Default::default()
+        };
+        assert_eq!(
+            eds_service_name(&cluster).as_deref(),
+            Some("web.ns.svc.cluster.local:80")
+        );
+
+        cluster.eds_cluster_config = Some(
+            envoy_xds_proto::envoy::config::cluster::v3::cluster::EdsClusterConfig {
+                service_name: "web".to_string(),
+            },
+        );
+        assert_eq!(eds_service_name(&cluster).as_deref(), Some("web"));
+
+        cluster.cluster_discovery_type =
+            Some(ClusterDiscoveryType::Type(DiscoveryType::Static as i32));
+        assert_eq!(eds_service_name(&cluster), None);
+    }
+}
@@ -24,9 +24,39 @@ pub enum Protocol {
 pub struct Authorization {
     pub networks: Vec<Network>,
     pub authentication: Authentication,
+
+    /// The HTTP routes to which this authorization is scoped. When empty, the authorization
+    /// applies to all traffic on the server; otherwise, it only permits HTTP requests that match
+    /// one of these routes.
+    pub routes: Vec<HttpRoute>,
+
     pub name: String,
 }
 
+/// A named set of HTTP request matches.
+///
+/// A route with no matches matches all requests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpRoute {
+    pub name: String,
+    pub matches: Vec<HttpRouteMatch>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpRouteMatch {
+    pub path: Option<PathMatch>,
+    pub method: Option<http::Method>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathMatch {
+    Exact(String),
+
+    /// Matches paths that start with the given path segments, i.e. `/foo` matches `/foo` and
+    /// `/foo/bar` but not `/foobar`.
+    Prefix(String),
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Authentication {
     Unauthenticated,
@@ -62,6 +92,149 @@ impl Suffix {
     }
 }
 
+// === impl HttpRoute ===
+
+impl HttpRoute {
+    pub fn matches(&self, method: &http::Method, path: &str) -> bool {
+        self.matches.is_empty() || self.matches.iter().any(|m| m.matches(method, path))
+    }
+}
+
+// === impl HttpRouteMatch ===
+
+impl HttpRouteMatch {
+    pub fn matches(&self, method: &http::Method, path: &str) -> bool {
+        self.method.as_ref().map(|m| m == method).unwrap_or(true)
+            && self.path.as_ref().map(|p| p.matches(path)).unwrap_or(true)
+    }
+}
+
+// === impl PathMatch ===
+
+/// Normalizes a request path so that equivalent paths are matched alike: percent-encoded octets
+/// are decoded and `.` and `..` segments are removed (as described in RFC 3986, section 5.2.4).
+///
+/// Returns `None` if the decoded path is not valid UTF-8.
+pub fn normalize_path(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(b) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                decoded.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    let decoded = String::from_utf8(decoded).ok()?;
+
+    let mut segments = Vec::new();
+    let mut trailing_slash = false;
+    for segment in decoded.trim_start_matches('/').split('/') {
+        trailing_slash = false;
+        match segment {
+            "." => trailing_slash = true,
+            ".." => {
+                segments.pop();
+                trailing_slash = true;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let mut path = String::with_capacity(decoded.len());
+    path.push('/');
+    path.push_str(&segments.join("/"));
+    if trailing_slash && !segments.is_empty() {
+        path.push('/');
+    }
+    Some(path)
+}
+
+impl PathMatch {
+    pub fn matches(&self, path: &str) -> bool {
+        match self {
+            Self::Exact(p) => p == path,
+            Self::Prefix(prefix) => {
+                let prefix = prefix.trim_end_matches('/');
+                match path.strip_prefix(prefix) {
+                    Some(rest) => rest.is_empty() || rest.starts_with('/'),
+                    None => false,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod route_tests {
+    use super::*;
+
+    #[test]
+    fn path_prefix() {
+        let prefix = PathMatch::Prefix("/foo".to_string());
+        assert!(prefix.matches("/foo"));
+        assert!(prefix.matches("/foo/"));
+        assert!(prefix.matches("/foo/bar"));
+        assert!(!prefix.matches("/foobar"));
+        assert!(!prefix.matches("/bar/foo"));
+
+        let root = PathMatch::Prefix("/".to_string());
+        assert!(root.matches("/"));
+        assert!(root.matches("/foo"));
+    }
+
+    #[test]
+    fn route_matches() {
+        let route = HttpRoute {
+            name: "get-foo".to_string(),
+            matches: vec![HttpRouteMatch {
+                path: Some(PathMatch::Exact("/foo".to_string())),
+                method: Some(http::Method::GET),
+            }],
+        };
+        assert!(route.matches(&http::Method::GET, "/foo"));
+        assert!(!route.matches(&http::Method::POST, "/foo"));
+        assert!(!route.matches(&http::Method::GET, "/foo/bar"));
+
+        let any = HttpRoute {
+            name: "any".to_string(),
+            matches: vec![],
+        };
+        assert!(any.matches(&http::Method::DELETE, "/bar"));
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        for (path, normalized) in &[
+            ("/", "/"),
+            ("/foo/bar", "/foo/bar"),
+            ("/foo/bar/", "/foo/bar/"),
+            ("/foo/./bar", "/foo/bar"),
+            ("/foo/../bar", "/bar"),
+            ("/foo/bar/..", "/foo/"),
+            ("/../../foo", "/foo"),
+            ("/public/..%2Fadmin", "/admin"),
+            ("/%61dmin", "/admin"),
+            ("/foo%", "/foo%"),
+            ("/foo%zz", "/foo%zz"),
+        ] {
+            assert_eq!(
+                normalize_path(path).as_deref(),
+                Some(*normalized),
+                "{}",
+                path
+            );
+        }
+        assert_eq!(normalize_path("/%ff"), None);
+    }
+}
+
 #[cfg(test)]
 mod network_tests {
     use super::Network;
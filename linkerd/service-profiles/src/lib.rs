@@ -142,6 +142,11 @@ impl Receiver {
         self.inner.borrow().endpoint.clone()
     }
 
+    /// Returns a copy of the most recently received profile.
+    pub fn profile(&self) -> Profile {
+        self.inner.borrow().clone()
+    }
+
     fn targets(&self) -> Vec<Target> {
         self.inner.borrow().targets.clone()
     }
@@ -0,0 +1,85 @@
+use std::{
+    ffi::CString,
+    io,
+    os::unix::{
+        ffi::OsStrExt,
+        io::{AsRawFd, RawFd},
+    },
+    path::Path,
+};
+
+/// A non-blocking inotify(7) instance.
+///
+/// Used to watch for changes to files that are replaced in place, e.g. mounted
+/// certificates that are rotated by another process.
+#[derive(Debug)]
+pub struct Inotify(RawFd);
+
+impl Inotify {
+    pub fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self(fd))
+    }
+
+    /// Watches a directory for entries that are created, written, moved, or
+    /// removed.
+    ///
+    /// Directories are watched (rather than files) so that files replaced by
+    /// renames or symlink swaps (as with Kubernetes volumes) are observed.
+    pub fn watch_dir(&self, path: &Path) -> io::Result<()> {
+        let path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mask = libc::IN_ATTRIB
+            | libc::IN_CLOSE_WRITE
+            | libc::IN_CREATE
+            | libc::IN_DELETE
+            | libc::IN_DELETE_SELF
+            | libc::IN_MOVED_FROM
+            | libc::IN_MOVED_TO
+            | libc::IN_MOVE_SELF;
+        let wd = unsafe { libc::inotify_add_watch(self.0, path.as_ptr(), mask) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Discards all pending events, returning the number of bytes read.
+    ///
+    /// A `WouldBlock` error is returned if no events are pending.
+    pub fn drain(&self) -> io::Result<usize> {
+        let mut buf = [0u8; 4096];
+        let mut read = 0;
+        loop {
+            let n = unsafe { libc::read(self.0, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+            if n < 0 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::WouldBlock && read > 0 {
+                    return Ok(read);
+                }
+                return Err(error);
+            }
+            if n == 0 {
+                return Ok(read);
+            }
+            read += n as usize;
+        }
+    }
+}
+
+impl AsRawFd for Inotify {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
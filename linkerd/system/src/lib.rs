@@ -1,10 +1,15 @@
-//! Unsafe code for accessing system-level counters for memory & CPU usage.
+//! Unsafe code for accessing system-level counters for memory & CPU usage, and
+//! for watching files for changes.
 
 #![deny(warnings, rust_2018_idioms)]
 
+#[cfg(target_os = "linux")]
+mod inotify;
 #[cfg(target_os = "linux")]
 mod linux;
 
+#[cfg(target_os = "linux")]
+pub use self::inotify::Inotify;
 #[cfg(target_os = "linux")]
 pub use self::linux::{blocking_stat, max_fds, ms_per_tick, open_fds, page_size, Stat};
 
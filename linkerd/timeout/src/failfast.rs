@@ -8,6 +8,10 @@ use pin_project::pin_project;
 use std::{
     future::Future,
     pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
 };
 use thiserror::Error;
@@ -21,8 +25,13 @@ pub struct FailFast<S> {
     max_unavailable: Duration,
     wait: Pin<Box<Sleep>>,
     state: State,
+    gauge: FailFastGauge,
 }
 
+/// Observes the number of services that are in failfast.
+#[derive(Clone, Debug, Default)]
+pub struct FailFastGauge(Arc<AtomicUsize>);
+
 /// An error representing that an operation timed out.
 #[derive(Debug, Error)]
 #[error("{} service in fail-fast", self.scope)]
@@ -50,7 +59,21 @@ impl<S> FailFast<S> {
         scope: &'static str,
         max_unavailable: Duration,
     ) -> impl layer::Layer<S, Service = Self> + Clone + Copy {
-        layer::mk(move |inner| Self {
+        layer::mk(move |inner| Self::new(scope, max_unavailable, FailFastGauge::default(), inner))
+    }
+
+    /// Returns a layer whose services record whether they are in failfast in
+    /// `gauge`.
+    pub fn layer_with_gauge(
+        scope: &'static str,
+        max_unavailable: Duration,
+        gauge: FailFastGauge,
+    ) -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(move |inner| Self::new(scope, max_unavailable, gauge.clone(), inner))
+    }
+
+    fn new(scope: &'static str, max_unavailable: Duration, gauge: FailFastGauge, inner: S) -> Self {
+        Self {
             scope,
             inner,
             max_unavailable,
@@ -59,7 +82,8 @@ impl<S> FailFast<S> {
             // now.
             wait: Box::pin(time::sleep(Duration::default())),
             state: State::Open,
-        })
+            gauge,
+        }
     }
 }
 
@@ -81,6 +105,15 @@ where
             // may become ready independently (e.g. semaphore).
             wait: Box::pin(time::sleep(Duration::default())),
             state: State::Open,
+            gauge: self.gauge.clone(),
+        }
+    }
+}
+
+impl<S> Drop for FailFast<S> {
+    fn drop(&mut self) {
+        if let State::FailFast = self.state {
+            self.gauge.0.fetch_sub(1, Ordering::Release);
         }
     }
 }
@@ -119,6 +152,7 @@ where
                             "{} entering failfast after {:?}",
                             self.scope, self.max_unavailable
                         );
+                        self.gauge.0.fetch_add(1, Ordering::Release);
                         State::FailFast
                     }
 
@@ -136,7 +170,10 @@ where
                 match self.state {
                     State::Open => {}
                     State::Waiting => trace!("{} has become ready", self.scope),
-                    State::FailFast => info!("{} service has recovered", self.scope),
+                    State::FailFast => {
+                        info!("{} service has recovered", self.scope);
+                        self.gauge.0.fetch_sub(1, Ordering::Release);
+                    }
                 }
                 self.state = State::Open;
                 ret.map_err(Into::into)
@@ -153,6 +190,15 @@ where
     }
 }
 
+// === impl FailFastGauge ===
+
+impl FailFastGauge {
+    /// Returns the number of services currently in failfast.
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
 impl<F> Future for ResponseFuture<F>
 where
     F: TryFuture,
@@ -170,7 +216,7 @@ where
 
 #[cfg(test)]
 mod test {
-    use super::FailFast;
+    use super::{FailFast, FailFastGauge};
     use std::time::Duration;
     use tokio_test::{assert_pending, assert_ready, assert_ready_ok};
     use tower::layer::Layer;
@@ -180,7 +226,10 @@ mod test {
     async fn fails_fast() {
         let max_unavailable = Duration::from_millis(100);
         let (service, mut handle) = mock::pair::<(), ()>();
-        let mut service = Spawn::new(FailFast::layer("Test", max_unavailable).layer(service));
+        let gauge = FailFastGauge::default();
+        let mut service = Spawn::new(
+            FailFast::layer_with_gauge("Test", max_unavailable, gauge.clone()).layer(service),
+        );
 
         // The inner starts unavailable.
         handle.allow(0);
@@ -190,6 +239,7 @@ mod test {
         // should start failing fast.
         tokio::time::sleep(max_unavailable + Duration::from_millis(1)).await;
         assert_ready_ok!(service.poll_ready());
+        assert_eq!(gauge.get(), 1);
 
         let err = service.call(()).await.err().expect("should failfast");
         assert!(err.is::<super::FailFastError>());
@@ -197,6 +247,7 @@ mod test {
         // Then the inner service becomes available.
         handle.allow(1);
         assert_ready_ok!(service.poll_ready());
+        assert_eq!(gauge.get(), 0);
         let fut = service.call(());
 
         let ((), rsp) = handle.next_request().await.expect("must get a request");
@@ -12,7 +12,7 @@ use tokio::time;
 
 mod failfast;
 
-pub use self::failfast::{FailFast, FailFastError};
+pub use self::failfast::{FailFast, FailFastError, FailFastGauge};
 
 /// A timeout that wraps an underlying operation.
 #[derive(Debug, Clone)]
@@ -6,11 +6,16 @@ use linkerd_io as io;
 pub use tokio_rustls::rustls::Session;
 
 pub mod client;
+pub mod originate;
 pub mod server;
 
 pub use self::{
     client::{Client, ClientTls, ConditionalClientTls, NoClientTls, ServerId},
-    server::{ClientId, ConditionalServerTls, NewDetectTls, NoServerTls, ServerTls},
+    originate::{InvalidOriginate, Originate},
+    server::{
+        ClientCertHash, ClientId, ConditionalServerTls, NewClientCertHash, NewDetectTls,
+        NoServerTls, ServerTls,
+    },
 };
 
 /// A trait implented by transport streams to indicate its negotiated protocol.
@@ -0,0 +1,204 @@
+//! Originates TLS to servers outside of the mesh.
+//!
+//! Unlike meshed connections, which are authenticated by the proxy's mesh
+//! identity, connections to external servers (e.g. APIs and databases that
+//! require mutual TLS) are authenticated with operator-supplied trust anchors
+//! and, optionally, a client certificate. Servers are verified against the
+//! name sent in the SNI extension unless other names are expected, e.g. when
+//! a server's certificate names a load balancer rather than the server.
+
+use crate::client::TlsStream;
+use linkerd_identity as id;
+use linkerd_io as io;
+use std::{fmt, io::Cursor, sync::Arc};
+use thiserror::Error;
+use tokio_rustls::rustls;
+
+/// Configures TLS origination to a single server.
+#[derive(Clone)]
+pub struct Originate {
+    server_name: id::Name,
+    config: Arc<rustls::ClientConfig>,
+}
+
+/// Verifies that a server's certificate is valid for any of the expected names.
+struct VerifyExpectedNames(Vec<id::Name>);
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum InvalidOriginate {
+    #[error("no trust anchors found")]
+    NoTrustAnchors,
+
+    #[error("no client certificate found")]
+    NoCertificate,
+
+    #[error("no valid client key found")]
+    InvalidKey,
+}
+
+// === impl Originate ===
+
+impl Originate {
+    /// Configures TLS to a server named `server_name`, whose certificate is
+    /// validated against the PEM-encoded `trust_anchors`.
+    ///
+    /// If `client` is set, it holds a PEM-encoded certificate chain (leaf
+    /// first) and private key with which the proxy authenticates itself.
+    pub fn new(
+        server_name: id::Name,
+        trust_anchors: &[u8],
+        client: Option<(&[u8], &[u8])>,
+    ) -> Result<Self, InvalidOriginate> {
+        let mut config = rustls::ClientConfig::new();
+        match config
+            .root_store
+            .add_pem_file(&mut Cursor::new(trust_anchors))
+        {
+            Ok((added, _)) if added > 0 => {}
+            _ => return Err(InvalidOriginate::NoTrustAnchors),
+        }
+
+        if let Some((crt, key)) = client {
+            let chain = rustls::internal::pemfile::certs(&mut Cursor::new(crt))
+                .map_err(|()| InvalidOriginate::NoCertificate)?;
+            if chain.is_empty() {
+                return Err(InvalidOriginate::NoCertificate);
+            }
+            config
+                .set_single_client_cert(chain, private_key(key)?)
+                .map_err(|_| InvalidOriginate::InvalidKey)?;
+        }
+
+        Ok(Self {
+            server_name,
+            config: Arc::new(config),
+        })
+    }
+
+    /// Verifies servers against the given names rather than the server name.
+    pub fn with_expected_names(self, names: Vec<id::Name>) -> Self {
+        if names.is_empty() {
+            return self;
+        }
+        let mut config = self.config.as_ref().clone();
+        config
+            .dangerous()
+            .set_certificate_verifier(Arc::new(VerifyExpectedNames(names)));
+        Self {
+            server_name: self.server_name,
+            config: Arc::new(config),
+        }
+    }
+
+    pub fn server_name(&self) -> &id::Name {
+        &self.server_name
+    }
+
+    pub async fn connect<I>(&self, io: I) -> io::Result<TlsStream<I>>
+    where
+        I: io::AsyncRead + io::AsyncWrite + Unpin,
+    {
+        tokio_rustls::TlsConnector::from(self.config.clone())
+            .connect((&self.server_name).into(), io)
+            .await
+    }
+}
+
+impl fmt::Debug for Originate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Originate")
+            .field("server_name", &self.server_name)
+            .finish()
+    }
+}
+
+// === impl VerifyExpectedNames ===
+
+impl rustls::ServerCertVerifier for VerifyExpectedNames {
+    fn verify_server_cert(
+        &self,
+        roots: &rustls::RootCertStore,
+        presented_certs: &[rustls::Certificate],
+        _: webpki::DNSNameRef<'_>,
+        ocsp_response: &[u8],
+    ) -> Result<rustls::ServerCertVerified, rustls::TLSError> {
+        let verifier = rustls::WebPKIVerifier::new();
+        let mut error = None;
+        for name in self.0.iter() {
+            match verifier.verify_server_cert(roots, presented_certs, name.into(), ocsp_response) {
+                Ok(verified) => return Ok(verified),
+                Err(e) => error = Some(e),
+            }
+        }
+        Err(error
+            .unwrap_or_else(|| rustls::TLSError::General("no expected server names".to_string())))
+    }
+}
+
+/// Reads the first PKCS#8 or RSA private key from a PEM document.
+fn private_key(pem: &[u8]) -> Result<rustls::PrivateKey, InvalidOriginate> {
+    let pkcs8 = rustls::internal::pemfile::pkcs8_private_keys(&mut Cursor::new(pem))
+        .map_err(|()| InvalidOriginate::InvalidKey)?;
+    if let Some(key) = pkcs8.into_iter().next() {
+        return Ok(key);
+    }
+    rustls::internal::pemfile::rsa_private_keys(&mut Cursor::new(pem))
+        .map_err(|()| InvalidOriginate::InvalidKey)?
+        .into_iter()
+        .next()
+        .ok_or(InvalidOriginate::InvalidKey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CA: &[u8] = include_bytes!("../../identity/src/testdata/ca1.pem");
+    static CRT: &[u8] = include_bytes!("../../identity/src/testdata/foo-ns1-ca1/crt.pem");
+    static KEY: &[u8] = include_bytes!("../../identity/src/testdata/foo-ns1-ca1/key.pem");
+
+    #[test]
+    fn new() {
+        let name = || "db.example.com".parse::<id::Name>().unwrap();
+        assert!(Originate::new(name(), CA, None).is_ok());
+        assert!(Originate::new(name(), CA, Some((CRT, KEY))).is_ok());
+
+        assert_eq!(
+            Originate::new(name(), KEY, None).unwrap_err(),
+            InvalidOriginate::NoTrustAnchors
+        );
+        assert_eq!(
+            Originate::new(name(), CA, Some((KEY, KEY))).unwrap_err(),
+            InvalidOriginate::NoCertificate
+        );
+        assert_eq!(
+            Originate::new(name(), CA, Some((CRT, CRT))).unwrap_err(),
+            InvalidOriginate::InvalidKey
+        );
+    }
+
+    #[test]
+    fn verify_expected_names() {
+        use rustls::ServerCertVerifier;
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add_pem_file(&mut Cursor::new(CA)).unwrap();
+        let chain = rustls::internal::pemfile::certs(&mut Cursor::new(CRT)).unwrap();
+        let server_name = "db.example.com".parse::<id::Name>().unwrap();
+        let verify = |names: &[&str]| {
+            let names = names.iter().map(|n| n.parse().unwrap()).collect();
+            VerifyExpectedNames(names)
+                .verify_server_cert(&roots, &chain, (&server_name).into(), &[])
+                .is_ok()
+        };
+
+        assert!(verify(&[
+            "bar.ns1.serviceaccount.identity.linkerd.cluster.local",
+            "foo.ns1.serviceaccount.identity.linkerd.cluster.local",
+        ]));
+        assert!(!verify(&[
+            "bar.ns1.serviceaccount.identity.linkerd.cluster.local"
+        ]));
+        assert!(!verify(&[]));
+    }
+}
@@ -0,0 +1,126 @@
+use super::Io;
+use linkerd_io::EitherIo;
+use linkerd_stack::{layer, NewService};
+use std::{
+    fmt,
+    task::{Context, Poll},
+};
+use tokio_rustls::rustls::Session;
+use tower::util::{Oneshot, ServiceExt};
+
+/// The SHA-256 digest of the DER-encoded certificate presented by a client.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ClientCertHash(pub [u8; 32]);
+
+/// Builds the inner service for each accepted connection with the hash of the
+/// client's certificate, if the client presented one.
+#[derive(Clone, Debug)]
+pub struct NewClientCertHash<N> {
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct ClientCertHashService<T, N> {
+    target: T,
+    inner: N,
+}
+
+// === impl ClientCertHash ===
+
+impl ClientCertHash {
+    /// Hashes the end-entity certificate of a terminated TLS connection.
+    pub fn from_io<I>(io: &Io<I>) -> Option<Self> {
+        let tls = match io {
+            EitherIo::Left(tls) => tls,
+            EitherIo::Right(_) => return None,
+        };
+        let (_io, session) = tls.get_ref();
+        let certs = session.get_peer_certificates()?;
+        let crt = certs.first()?;
+        Some(Self::from_der(crt.as_ref()))
+    }
+
+    pub fn from_der(der: &[u8]) -> Self {
+        let digest = ring::digest::digest(&ring::digest::SHA256, der);
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(digest.as_ref());
+        Self(hash)
+    }
+}
+
+impl fmt::Display for ClientCertHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.0.iter() {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ClientCertHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ClientCertHash({})", self)
+    }
+}
+
+// === impl NewClientCertHash ===
+
+impl<N> NewClientCertHash<N> {
+    pub fn new(inner: N) -> Self {
+        Self { inner }
+    }
+
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(Self::new)
+    }
+}
+
+impl<T, N: Clone> NewService<T> for NewClientCertHash<N> {
+    type Service = ClientCertHashService<T, N>;
+
+    fn new_service(&mut self, target: T) -> Self::Service {
+        ClientCertHashService {
+            target,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+// === impl ClientCertHashService ===
+
+impl<T, I, N, S> tower::Service<Io<I>> for ClientCertHashService<T, N>
+where
+    T: Clone,
+    N: NewService<(Option<ClientCertHash>, T), Service = S>,
+    S: tower::Service<Io<I>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Oneshot<S, Io<I>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, io: Io<I>) -> Self::Future {
+        let hash = ClientCertHash::from_io(&io);
+        self.inner
+            .new_service((hash, self.target.clone()))
+            .oneshot(io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_hex() {
+        let hash = ClientCertHash::from_der(b"");
+        assert_eq!(
+            hash.to_string(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
@@ -1,5 +1,8 @@
+mod client_cert;
 mod client_hello;
 
+pub use self::client_cert::{ClientCertHash, ClientCertHashService, NewClientCertHash};
+
 use crate::{LocalId, NegotiatedProtocol, ServerId};
 use bytes::BytesMut;
 use futures::prelude::*;
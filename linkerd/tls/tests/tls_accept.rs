@@ -12,7 +12,7 @@ use linkerd_identity as id;
 use linkerd_io::{self as io, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use linkerd_proxy_transport::{
     addrs::*,
-    listen::{Addrs, Bind, BindTcp},
+    listen::{Addrs, Backlog, Bind, BindTcp},
     ConnectTcp, Keepalive, ListenAddr,
 };
 use linkerd_stack::{ExtractParam, InsertParam, NewService, Param};
@@ -215,7 +215,7 @@ where
         let tls = Some(client_server_id.clone().map(Into::into));
         let client = async move {
             let conn = tls::Client::layer(client_tls)
-                .layer(ConnectTcp::new(Keepalive(None)))
+                .layer(ConnectTcp::new(Keepalive::default()))
                 .oneshot(Target(server_addr.into(), client_server_id.map(Into::into)))
                 .await;
             match conn {
@@ -358,7 +358,13 @@ impl Param<ListenAddr> for Server {
 }
 impl Param<Keepalive> for Server {
     fn param(&self) -> Keepalive {
-        Keepalive(None)
+        Keepalive::default()
+    }
+}
+
+impl Param<Backlog> for Server {
+    fn param(&self) -> Backlog {
+        Backlog::default()
     }
 }
 
@@ -4,13 +4,14 @@
 mod propagation;
 mod service;
 
-pub use self::service::TraceContext;
+pub use self::{propagation::Precedence, service::TraceContext};
 use bytes::Bytes;
 use linkerd_error::Error;
 use rand::Rng;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 use thiserror::Error;
 
@@ -35,14 +36,47 @@ pub struct Span {
     pub start: SystemTime,
     pub end: SystemTime,
     pub labels: HashMap<&'static str, String>,
+    pub events: Vec<Event>,
+    /// Labels describing the request's target (e.g. its route), as recorded by
+    /// inner layers.
+    pub target_labels: Vec<(String, String)>,
 }
 
+/// A time-stamped annotation on a span.
+#[derive(Debug)]
+pub struct Event {
+    pub name: &'static str,
+    pub time: SystemTime,
+    pub labels: HashMap<&'static str, String>,
+}
+
+/// Records events on the span of a sampled request.
+///
+/// A handle is added to the extensions of each request that is sampled so
+/// that inner layers can annotate the request's span.
+#[derive(Clone, Debug, Default)]
+pub struct SpanEvents(Arc<Mutex<Vec<Event>>>);
+
+/// Records labels on the span of a sampled request.
+///
+/// Like `SpanEvents`, a handle is added to the extensions of each request that
+/// is sampled.
+#[derive(Clone, Debug, Default)]
+pub struct SpanLabels(Arc<Mutex<Vec<(String, String)>>>);
+
 pub trait SpanSink {
     fn is_enabled(&self) -> bool;
 
     fn try_send(&mut self, span: Span) -> Result<(), Error>;
 }
 
+/// Returns the ID of the trace propagated by a request, if any.
+///
+/// This does not depend on whether the request is sampled.
+pub fn request_trace_id<B>(req: &http::Request<B>, precedence: Precedence) -> Option<Id> {
+    propagation::unpack_trace_context(req, precedence).map(|ctx| ctx.trace_id)
+}
+
 impl<K: SpanSink> SpanSink for Option<K> {
     #[inline]
     fn is_enabled(&self) -> bool {
@@ -55,6 +89,54 @@ impl<K: SpanSink> SpanSink for Option<K> {
     }
 }
 
+// === impl SpanEvents ===
+
+impl SpanEvents {
+    /// Records an event on the request's span.
+    pub fn record(&self, name: &'static str, labels: HashMap<&'static str, String>) {
+        let event = Event {
+            name,
+            time: SystemTime::now(),
+            labels,
+        };
+        self.0
+            .lock()
+            .expect("span events lock poisoned")
+            .push(event);
+    }
+
+    /// Records an event on the span of the given request, if it is sampled.
+    pub fn record_on<B>(
+        req: &http::Request<B>,
+        name: &'static str,
+        labels: impl FnOnce() -> HashMap<&'static str, String>,
+    ) {
+        if let Some(events) = req.extensions().get::<Self>() {
+            events.record(name, labels());
+        }
+    }
+
+    fn take(&self) -> Vec<Event> {
+        std::mem::take(&mut *self.0.lock().expect("span events lock poisoned"))
+    }
+}
+
+// === impl SpanLabels ===
+
+impl SpanLabels {
+    /// Adds labels to the request's span.
+    pub fn extend(&self, labels: impl IntoIterator<Item = (String, String)>) {
+        self.0
+            .lock()
+            .expect("span labels lock poisoned")
+            .extend(labels);
+    }
+
+    fn take(&self) -> Vec<(String, String)> {
+        std::mem::take(&mut *self.0.lock().expect("span labels lock poisoned"))
+    }
+}
+
 // === impl Id ===
 
 impl Id {
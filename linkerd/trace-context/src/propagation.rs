@@ -7,9 +7,16 @@ use std::convert::TryInto;
 use thiserror::Error;
 use tracing::{trace, warn};
 
-const HTTP_TRACE_ID_HEADER: &str = "x-b3-traceid";
-const HTTP_SPAN_ID_HEADER: &str = "x-b3-spanid";
-const HTTP_SAMPLED_HEADER: &str = "x-b3-sampled";
+const B3_TRACE_ID_HEADER: &str = "x-b3-traceid";
+const B3_SPAN_ID_HEADER: &str = "x-b3-spanid";
+const B3_PARENT_SPAN_ID_HEADER: &str = "x-b3-parentspanid";
+const B3_SAMPLED_HEADER: &str = "x-b3-sampled";
+const B3_FLAGS_HEADER: &str = "x-b3-flags";
+const B3_SINGLE_HEADER: &str = "b3";
+
+const W3C_TRACEPARENT_HEADER: &str = "traceparent";
+const W3C_TRACESTATE_HEADER: &str = "tracestate";
+const W3C_VERSION: &str = "00";
 
 const GRPC_TRACE_HEADER: &str = "grpc-trace-bin";
 const GRPC_TRACE_FIELD_TRACE_ID: u8 = 0;
@@ -18,8 +25,20 @@ const GRPC_TRACE_FIELD_TRACE_OPTIONS: u8 = 2;
 
 #[derive(Debug)]
 pub enum Propagation {
-    Http,
+    /// B3 `x-b3-*` headers.
+    B3Multi,
+    /// The B3 single `b3` header.
+    B3Single,
     Grpc,
+    W3c,
+}
+
+/// Determines which trace context is used when a request has both B3 and W3C
+/// (`traceparent`) headers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Precedence {
+    B3,
+    W3c,
 }
 
 #[derive(Debug)]
@@ -28,6 +47,8 @@ pub struct TraceContext {
     pub trace_id: Id,
     pub parent_id: Id,
     pub flags: Flags,
+    /// The W3C `tracestate` header, which is forwarded unmodified.
+    pub trace_state: Option<HeaderValue>,
 }
 
 #[derive(Debug, Error)]
@@ -42,8 +63,27 @@ impl TraceContext {
     }
 }
 
-pub fn unpack_trace_context<B>(request: &http::Request<B>) -> Option<TraceContext> {
-    unpack_grpc_trace_context(request).or_else(|| unpack_http_trace_context(request))
+// === impl Precedence ===
+
+/// B3 takes precedence by default, for compatibility with earlier versions.
+impl Default for Precedence {
+    fn default() -> Self {
+        Self::B3
+    }
+}
+
+pub fn unpack_trace_context<B>(
+    request: &http::Request<B>,
+    precedence: Precedence,
+) -> Option<TraceContext> {
+    unpack_grpc_trace_context(request).or_else(|| match precedence {
+        Precedence::B3 => {
+            unpack_b3_trace_context(request).or_else(|| unpack_w3c_trace_context(request))
+        }
+        Precedence::W3c => {
+            unpack_w3c_trace_context(request).or_else(|| unpack_b3_trace_context(request))
+        }
+    })
 }
 
 // Generates a new span id, writes it to the request in the appropriate
@@ -51,7 +91,9 @@ pub fn unpack_trace_context<B>(request: &http::Request<B>) -> Option<TraceContex
 pub fn increment_span_id<B>(request: &mut http::Request<B>, context: &TraceContext) -> Id {
     match context.propagation {
         Propagation::Grpc => increment_grpc_span_id(request, context),
-        Propagation::Http => increment_http_span_id(request),
+        Propagation::B3Multi => increment_b3_multi_span_id(request, context),
+        Propagation::B3Single => increment_b3_single_span_id(request, context),
+        Propagation::W3c => increment_w3c_span_id(request, context),
     }
 }
 
@@ -83,6 +125,7 @@ fn parse_grpc_trace_context_fields(buf: &mut Bytes) -> Option<TraceContext> {
         trace_id: Default::default(),
         parent_id: Default::default(),
         flags: Default::default(),
+        trace_state: None,
     };
 
     while !buf.is_empty() {
@@ -173,32 +216,185 @@ fn increment_grpc_span_id<B>(request: &mut http::Request<B>, context: &TraceCont
     span_id
 }
 
-fn unpack_http_trace_context<B>(request: &http::Request<B>) -> Option<TraceContext> {
-    let parent_id = parse_header_id(request, HTTP_SPAN_ID_HEADER, 8)?;
-    let trace_id = parse_header_id(request, HTTP_TRACE_ID_HEADER, 16)?;
-    let flags = match get_header_str(request, HTTP_SAMPLED_HEADER) {
-        Some("1") => Flags(1),
+/// Reads a B3 trace context, preferring the multi-header format when both the
+/// `x-b3-*` headers and the single `b3` header are present.
+fn unpack_b3_trace_context<B>(request: &http::Request<B>) -> Option<TraceContext> {
+    unpack_b3_multi_trace_context(request).or_else(|| unpack_b3_single_trace_context(request))
+}
+
+fn unpack_b3_multi_trace_context<B>(request: &http::Request<B>) -> Option<TraceContext> {
+    let parent_id = parse_header_id(request, B3_SPAN_ID_HEADER, 8)?;
+    let trace_id = parse_header_id(request, B3_TRACE_ID_HEADER, 16)?;
+    // A debug flag implies that the trace is sampled.
+    let debug = get_header_str(request, B3_FLAGS_HEADER) == Some("1");
+    let flags = match get_header_str(request, B3_SAMPLED_HEADER) {
+        Some("1") | Some("true") => Flags(1),
+        _ if debug => Flags(1),
         _ => Flags(0),
     };
     Some(TraceContext {
-        propagation: Propagation::Http,
+        propagation: Propagation::B3Multi,
+        trace_id,
+        parent_id,
+        flags,
+        trace_state: None,
+    })
+}
+
+/// Reads the single `b3` header, formatted as
+/// `{trace-id}-{span-id}[-{sampling-state}[-{parent-span-id}]]`.
+///
+/// A header that only carries a sampling state (e.g. `b3: 0`) does not
+/// describe a trace context and is ignored.
+fn unpack_b3_single_trace_context<B>(request: &http::Request<B>) -> Option<TraceContext> {
+    let header = get_header_str(request, B3_SINGLE_HEADER)?;
+    let mut fields = header.trim().split('-');
+    let trace_id = fields.next()?;
+    let span_id = fields.next()?;
+    let flags = match fields.next() {
+        Some("1") | Some("d") => Flags(1),
+        Some("0") | None => Flags(0),
+        Some(state) => {
+            warn!("Invalid {} sampling state: {:?}", B3_SINGLE_HEADER, state);
+            return None;
+        }
+    };
+    let trace_id = decode_padded_id(trace_id, 16)
+        .map_err(|e| warn!("Header {} has an invalid trace ID: {}", B3_SINGLE_HEADER, e))
+        .ok()?;
+    let parent_id = decode_padded_id(span_id, 8)
+        .map_err(|e| warn!("Header {} has an invalid span ID: {}", B3_SINGLE_HEADER, e))
+        .ok()?;
+    Some(TraceContext {
+        propagation: Propagation::B3Single,
         trace_id,
         parent_id,
         flags,
+        trace_state: None,
     })
 }
 
-fn increment_http_span_id<B>(request: &mut http::Request<B>) -> Id {
+fn increment_b3_multi_span_id<B>(request: &mut http::Request<B>, context: &TraceContext) -> Id {
     let span_id = Id::new_span_id(&mut thread_rng());
 
     trace!("incremented span id: {}", span_id);
 
     let span_str = hex::encode(span_id.as_ref());
+    let parent_str = hex::encode(context.parent_id.as_ref());
 
     if let Result::Ok(hv) = HeaderValue::from_str(&span_str) {
-        request.headers_mut().insert(HTTP_SPAN_ID_HEADER, hv);
+        request.headers_mut().insert(B3_SPAN_ID_HEADER, hv);
+    } else {
+        warn!("invalid {} header: {:?}", B3_SPAN_ID_HEADER, span_str);
+    }
+    // The span that sent the request is the parent of the proxy's span.
+    if let Result::Ok(hv) = HeaderValue::from_str(&parent_str) {
+        request.headers_mut().insert(B3_PARENT_SPAN_ID_HEADER, hv);
+    } else {
+        warn!(
+            "invalid {} header: {:?}",
+            B3_PARENT_SPAN_ID_HEADER, parent_str
+        );
+    }
+    span_id
+}
+
+fn increment_b3_single_span_id<B>(request: &mut http::Request<B>, context: &TraceContext) -> Id {
+    let span_id = Id::new_span_id(&mut thread_rng());
+
+    trace!("incremented span id: {}", span_id);
+
+    let b3 = format!(
+        "{}-{}-{}-{}",
+        hex::encode(context.trace_id.as_ref()),
+        hex::encode(span_id.as_ref()),
+        if context.is_sampled() { "1" } else { "0" },
+        hex::encode(context.parent_id.as_ref()),
+    );
+    if let Result::Ok(hv) = HeaderValue::from_str(&b3) {
+        request.headers_mut().insert(B3_SINGLE_HEADER, hv);
+    } else {
+        warn!("invalid {} header: {:?}", B3_SINGLE_HEADER, b3);
+    }
+    span_id
+}
+
+fn unpack_w3c_trace_context<B>(request: &http::Request<B>) -> Option<TraceContext> {
+    let header = get_header_str(request, W3C_TRACEPARENT_HEADER)?;
+    let mut context = parse_traceparent(header).or_else(|| {
+        warn!("Invalid {} header: {:?}", W3C_TRACEPARENT_HEADER, header);
+        None
+    })?;
+    context.trace_state = request.headers().get(W3C_TRACESTATE_HEADER).cloned();
+    Some(context)
+}
+
+/// Parses a `traceparent` header, formatted as
+/// `{version}-{trace-id}-{parent-id}-{trace-flags}`.
+fn parse_traceparent(header: &str) -> Option<TraceContext> {
+    let mut fields = header.trim().split('-');
+    let version = fields.next()?;
+    let trace_id = fields.next()?;
+    let parent_id = fields.next()?;
+    let flags = fields.next()?;
+
+    // Later versions may append fields, but they must be parsed as version 00
+    // is. Version `ff` is always invalid.
+    if version == "ff" || (version == W3C_VERSION && fields.next().is_some()) {
+        return None;
+    }
+    decode_lower_hex(version, 1)?;
+
+    let flags = decode_lower_hex(flags, 1)?[0];
+    Some(TraceContext {
+        propagation: Propagation::W3c,
+        trace_id: Id(decode_nonzero_id(trace_id, 16)?),
+        parent_id: Id(decode_nonzero_id(parent_id, 8)?),
+        flags: Flags(flags),
+        trace_state: None,
+    })
+}
+
+fn decode_nonzero_id(s: &str, len: usize) -> Option<Vec<u8>> {
+    let id = decode_lower_hex(s, len)?;
+    if id.iter().all(|b| *b == 0) {
+        return None;
+    }
+    Some(id)
+}
+
+fn decode_lower_hex(s: &str, len: usize) -> Option<Vec<u8>> {
+    if s.len() != len * 2 || !s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return None;
+    }
+    hex::decode(s).ok()
+}
+
+fn increment_w3c_span_id<B>(request: &mut http::Request<B>, context: &TraceContext) -> Id {
+    let span_id = Id::new_span_id(&mut thread_rng());
+
+    trace!(message = "incremented span id", %span_id);
+
+    let traceparent = format!(
+        "{}-{}-{}-{}",
+        W3C_VERSION,
+        hex::encode(context.trace_id.as_ref()),
+        hex::encode(span_id.as_ref()),
+        context.flags,
+    );
+    if let Result::Ok(hv) = HeaderValue::from_str(&traceparent) {
+        request.headers_mut().insert(W3C_TRACEPARENT_HEADER, hv);
     } else {
-        warn!("invalid {} header: {:?}", HTTP_SPAN_ID_HEADER, span_str);
+        warn!(
+            "invalid {} header: {:?}",
+            W3C_TRACEPARENT_HEADER, traceparent
+        );
+    }
+
+    if let Some(trace_state) = context.trace_state.clone() {
+        request
+            .headers_mut()
+            .insert(W3C_TRACESTATE_HEADER, trace_state);
     }
     span_id
 }
@@ -212,21 +408,25 @@ fn get_header_str<'a, B>(request: &'a http::Request<B>, header: &str) -> Option<
 
 fn parse_header_id<B>(request: &http::Request<B>, header: &str, pad_to: usize) -> Option<Id> {
     let header_value = get_header_str(request, header)?;
-    hex::decode(header_value)
-        .map(|mut data| {
-            if data.len() < pad_to {
-                let padding = pad_to - data.len();
-                let mut padded = vec![0u8; padding];
-                padded.append(&mut data);
-                Id(padded)
-            } else {
-                Id(data)
-            }
-        })
+    decode_padded_id(header_value, pad_to)
         .map_err(|e| warn!("Header {} does not contain a hex value: {}", header, e))
         .ok()
 }
 
+/// Decodes a hex-encoded ID, left-padding it with zeroes to `pad_to` bytes.
+fn decode_padded_id(value: &str, pad_to: usize) -> Result<Id, hex::FromHexError> {
+    hex::decode(value).map(|mut data| {
+        if data.len() < pad_to {
+            let padding = pad_to - data.len();
+            let mut padded = vec![0u8; padding];
+            padded.append(&mut data);
+            Id(padded)
+        } else {
+            Id(data)
+        }
+    })
+}
+
 /// Attempt to split_to the given index.  If there are not enough bytes then
 /// Err is returned and the given Bytes is not modified.
 fn try_split_to(buf: &mut Bytes, n: usize) -> Result<Bytes, InsufficientBytes> {
@@ -236,3 +436,119 @@ fn try_split_to(buf: &mut Bytes, n: usize) -> Result<Bytes, InsufficientBytes> {
         Err(InsufficientBytes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACEPARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+    fn request(headers: &[(&'static str, &str)]) -> http::Request<()> {
+        let mut req = http::Request::builder();
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        req.body(()).unwrap()
+    }
+
+    #[test]
+    fn parses_traceparent() {
+        let ctx = parse_traceparent(TRACEPARENT).expect("traceparent must parse");
+        assert!(matches!(ctx.propagation, Propagation::W3c));
+        assert_eq!(ctx.trace_id.to_string(), "0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(ctx.parent_id.to_string(), "b7ad6b7169203331");
+        assert!(ctx.is_sampled());
+
+        for invalid in &[
+            "ff-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-extra",
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01",
+            "00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b71692033-01",
+        ] {
+            assert!(parse_traceparent(invalid).is_none(), "{}", invalid);
+        }
+
+        // Future versions may include additional fields.
+        assert!(
+            parse_traceparent("01-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00-extra")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn precedence() {
+        let req = request(&[
+            ("traceparent", TRACEPARENT),
+            ("x-b3-traceid", "463ac35c9f6413ad48485a3953bb6124"),
+            ("x-b3-spanid", "a2fb4a1d1a96d312"),
+        ]);
+        let ctx = unpack_trace_context(&req, Precedence::B3).unwrap();
+        assert!(matches!(ctx.propagation, Propagation::B3Multi));
+        let ctx = unpack_trace_context(&req, Precedence::W3c).unwrap();
+        assert!(matches!(ctx.propagation, Propagation::W3c));
+
+        let req = request(&[("traceparent", TRACEPARENT)]);
+        let ctx = unpack_trace_context(&req, Precedence::B3).unwrap();
+        assert!(matches!(ctx.propagation, Propagation::W3c));
+    }
+
+    #[test]
+    fn injects_traceparent() {
+        let mut req = request(&[
+            ("traceparent", TRACEPARENT),
+            ("tracestate", "congo=t61rcWkgMzE"),
+        ]);
+        let ctx = unpack_trace_context(&req, Precedence::W3c).unwrap();
+        let span_id = increment_span_id(&mut req, &ctx);
+
+        assert_eq!(
+            req.headers()["traceparent"],
+            format!("00-0af7651916cd43dd8448eb211c80319c-{}-01", span_id).as_str()
+        );
+        assert_eq!(req.headers()["tracestate"], "congo=t61rcWkgMzE");
+    }
+
+    #[test]
+    fn b3_multi() {
+        let mut req = request(&[
+            ("x-b3-traceid", "463ac35c9f6413ad48485a3953bb6124"),
+            ("x-b3-spanid", "a2fb4a1d1a96d312"),
+            ("x-b3-flags", "1"),
+        ]);
+        let ctx = unpack_trace_context(&req, Precedence::B3).unwrap();
+        assert!(matches!(ctx.propagation, Propagation::B3Multi));
+        assert!(ctx.is_sampled(), "debug flag implies sampling");
+
+        let span_id = increment_span_id(&mut req, &ctx);
+        assert_eq!(req.headers()["x-b3-spanid"], span_id.to_string().as_str());
+        assert_eq!(req.headers()["x-b3-parentspanid"], "a2fb4a1d1a96d312");
+        assert_eq!(
+            req.headers()["x-b3-traceid"],
+            "463ac35c9f6413ad48485a3953bb6124"
+        );
+    }
+
+    #[test]
+    fn b3_single() {
+        let mut req = request(&[("b3", "a3ce929d0e0e4736-00f067aa0ba902b7-1")]);
+        let ctx = unpack_trace_context(&req, Precedence::B3).unwrap();
+        assert!(matches!(ctx.propagation, Propagation::B3Single));
+        assert_eq!(ctx.trace_id.to_string(), "0000000000000000a3ce929d0e0e4736");
+        assert!(ctx.is_sampled());
+
+        let span_id = increment_span_id(&mut req, &ctx);
+        assert_eq!(
+            req.headers()["b3"],
+            format!(
+                "0000000000000000a3ce929d0e0e4736-{}-1-00f067aa0ba902b7",
+                span_id
+            )
+            .as_str()
+        );
+
+        // A sampling decision alone does not describe a context.
+        assert!(unpack_trace_context(&request(&[("b3", "0")]), Precedence::B3).is_none());
+    }
+}
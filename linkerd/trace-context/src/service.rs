@@ -1,4 +1,4 @@
-use crate::{propagation, Span, SpanSink};
+use crate::{propagation, Precedence, Span, SpanEvents, SpanLabels, SpanSink};
 use futures::{future::Either, prelude::*};
 use linkerd_stack::layer;
 use std::{
@@ -8,30 +8,36 @@ use std::{
     task::{Context, Poll},
     time::SystemTime,
 };
-use tracing::{debug, info, trace};
+use tracing::{debug, trace};
 
 /// A layer that adds distributed tracing instrumentation.
 ///
-/// This layer reads the `traceparent` HTTP header from the request. If this
-/// header is absent, the request is fowarded unmodified.  If the header is
-/// present, a new span will be started in the current trace by creating a new
-/// random span id setting it into the `traceparent` header before forwarding
-/// the request. If the sampled bit of the header was set, we emit metadata
-/// about the span to the given SpanSink when the span is complete, i.e. when
-/// we receive the response.
+/// This layer reads the `grpc-trace-bin`, B3, or W3C `traceparent` HTTP
+/// headers from the request. If these headers are absent, the request is
+/// fowarded unmodified. If a header is present, a new span will be started in
+/// the current trace by creating a new random span id setting it into the
+/// header before forwarding the request. When a request has both B3 and W3C
+/// headers, the `Precedence` determines which is used. If the sampled bit of
+/// the header was set, we emit metadata about the span to the given SpanSink
+/// when the span is complete, i.e. when we receive the response.
 #[derive(Clone, Debug)]
 pub struct TraceContext<K, S> {
     inner: S,
     sink: K,
+    precedence: Precedence,
 }
 
 // === impl TraceContext ===
 
 impl<K: Clone, S> TraceContext<K, S> {
-    pub fn layer(sink: K) -> impl layer::Layer<S, Service = TraceContext<K, S>> + Clone {
+    pub fn layer(
+        sink: K,
+        precedence: Precedence,
+    ) -> impl layer::Layer<S, Service = TraceContext<K, S>> + Clone {
         layer::mk(move |inner| TraceContext {
             inner,
             sink: sink.clone(),
+            precedence,
         })
     }
 
@@ -85,7 +91,7 @@ where
 
     fn call(&mut self, mut req: http::Request<ReqB>) -> Self::Future {
         if self.sink.is_enabled() {
-            if let Some(context) = propagation::unpack_trace_context(&req) {
+            if let Some(context) = propagation::unpack_trace_context(&req, self.precedence) {
                 // Update the trace ID if the request set one and the proxy is configured to emit
                 // spans.
                 let span_id = propagation::increment_span_id(&mut req, &context);
@@ -95,6 +101,11 @@ where
                     // If the request has been marked for sampling, record its metadata.
                     let start = SystemTime::now();
                     let req_labels = Self::request_labels(&req);
+                    // Allow inner layers to annotate the span.
+                    let events = SpanEvents::default();
+                    req.extensions_mut().insert(events.clone());
+                    let target_labels = SpanLabels::default();
+                    req.extensions_mut().insert(target_labels.clone());
                     let mut sink = self.sink.clone();
                     let span_name = req.uri().path().to_owned();
                     return Either::Right(Box::pin(self.inner.call(req).map_ok(move |rsp| {
@@ -107,10 +118,12 @@ where
                             start,
                             end: SystemTime::now(),
                             labels: Self::add_response_labels(req_labels, &rsp),
+                            events: events.take(),
+                            target_labels: target_labels.take(),
                         };
                         trace!(?span);
                         if let Err(error) = sink.try_send(span) {
-                            info!(%error, "Span dropped");
+                            debug!(%error, "Span dropped");
                         }
                         rsp
                     })));
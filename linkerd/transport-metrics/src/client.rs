@@ -72,7 +72,9 @@ impl<F: TryFuture> Future for Connect<F> {
             .metrics
             .take()
             .expect("future must not be polled after ready");
-        let t = SensorIo::new(io, Sensor::open(metrics));
+        // Client connections are described by their target's labels, so the
+        // peer's address is not recorded separately.
+        let t = SensorIo::new(io, Sensor::open(metrics, None));
         Poll::Ready(Ok(t))
     }
 }
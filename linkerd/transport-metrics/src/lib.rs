@@ -19,7 +19,11 @@ use std::{
     collections::HashMap,
     fmt,
     hash::Hash,
-    sync::Arc,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
@@ -35,11 +39,20 @@ metrics! {
 pub fn new<K: Eq + Hash + FmtLabels>(retain_idle: Duration) -> (Registry<K>, Report<K>) {
     let inner = Arc::new(Mutex::new(Inner::new()));
     let report = Report::new(inner.clone(), retain_idle);
-    (Registry(inner), report)
+    let registry = Registry {
+        inner,
+        retain_idle,
+        evicted_at: Arc::new(Mutex::new(Instant::now())),
+    };
+    (registry, report)
 }
 
 #[derive(Clone, Debug)]
-pub struct Registry<K: Eq + Hash + FmtLabels>(Arc<Mutex<Inner<K>>>);
+pub struct Registry<K: Eq + Hash + FmtLabels> {
+    inner: Arc<Mutex<Inner<K>>>,
+    retain_idle: Duration,
+    evicted_at: Arc<Mutex<Instant>>,
+}
 
 type Inner<K> = Store<K, Metrics>;
 
@@ -52,6 +65,27 @@ pub struct Metrics {
     read_bytes_total: Counter,
 
     by_eos: Arc<Mutex<ByEos>>,
+
+    /// Tracks each of the currently-open connections, by a locally-unique ID.
+    open: Mutex<HashMap<u64, Arc<Conn>>>,
+    next_id: AtomicU64,
+}
+
+/// Describes a currently-open connection.
+#[derive(Clone, Debug)]
+pub struct Connection {
+    pub peer_addr: Option<SocketAddr>,
+    pub age: Duration,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+#[derive(Debug)]
+struct Conn {
+    peer_addr: Option<SocketAddr>,
+    opened_at: Instant,
+    read_bytes: AtomicU64,
+    write_bytes: AtomicU64,
 }
 
 #[derive(Debug)]
@@ -78,7 +112,54 @@ struct EosMetrics {
 
 impl<K: Eq + Hash + FmtLabels> Registry<K> {
     pub fn metrics(&self, labels: K) -> Arc<Metrics> {
-        self.0.lock().get_or_default(labels).clone()
+        let mut inner = self.inner.lock();
+        if let Some(metrics) = inner.get(&labels) {
+            return metrics.clone();
+        }
+
+        // Idle series are evicted when metrics are reported; but, if metrics
+        // are never scraped, the registry would grow with endpoint churn. So
+        // registering a new series also evicts idle ones (at most once per
+        // idle timeout).
+        let now = Instant::now();
+        let mut evicted_at = self.evicted_at.lock();
+        if now.saturating_duration_since(*evicted_at) >= self.retain_idle {
+            if let Some(epoch) = now.checked_sub(self.retain_idle) {
+                inner.retain_since(epoch);
+            }
+            *evicted_at = now;
+        }
+
+        inner.get_or_default(labels).clone()
+    }
+
+    /// Lists all currently-open connections with the labels of their transport.
+    pub fn connections(&self) -> Vec<(K, Connection)>
+    where
+        K: Clone,
+    {
+        let now = Instant::now();
+        let inner = self.inner.lock();
+        let mut conns = Vec::new();
+        for (labels, metrics) in inner.iter() {
+            for conn in metrics.open.lock().values() {
+                conns.push((labels.clone(), conn.snapshot(now)));
+            }
+        }
+        conns
+    }
+}
+
+// === impl Conn ===
+
+impl Conn {
+    fn snapshot(&self, now: Instant) -> Connection {
+        Connection {
+            peer_addr: self.peer_addr,
+            age: now.saturating_duration_since(self.opened_at),
+            read_bytes: self.read_bytes.load(Ordering::Acquire),
+            write_bytes: self.write_bytes.load(Ordering::Acquire),
+        }
     }
 }
 
@@ -114,23 +195,23 @@ impl Default for ByEos {
 
 #[cfg(test)]
 mod tests {
-    #[test]
-    fn expiry() {
-        use linkerd_metrics::FmtLabels;
-        use std::fmt;
-        use std::time::{Duration, Instant};
-
-        #[derive(Clone, Debug, Hash, Eq, PartialEq)]
-        struct Target(usize);
-        impl FmtLabels for Target {
-            fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                write!(f, "n=\"{}\"", self.0)
-            }
+    use linkerd_metrics::FmtLabels;
+    use std::fmt;
+    use std::time::{Duration, Instant};
+
+    #[derive(Clone, Debug, Hash, Eq, PartialEq)]
+    struct Target(usize);
+    impl FmtLabels for Target {
+        fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "n=\"{}\"", self.0)
         }
+    }
 
+    #[test]
+    fn expiry() {
         let retain_idle_for = Duration::from_secs(1);
         let (r, report) = super::new(retain_idle_for);
-        let mut registry = r.0.lock();
+        let mut registry = r.inner.lock();
 
         let before_update = Instant::now();
         let metrics = registry.entry(Target(123)).or_default().clone();
@@ -161,4 +242,28 @@ mod tests {
 
         drop((registry, report));
     }
+
+    #[test]
+    fn expiry_on_register() {
+        let retain_idle_for = Duration::from_millis(10);
+        let (r, _report) = super::new(retain_idle_for);
+
+        let held = r.metrics(Target(1));
+        drop(r.metrics(Target(2)));
+        assert_eq!(r.inner.lock().len(), 2);
+
+        std::thread::sleep(retain_idle_for * 2);
+        let _new = r.metrics(Target(3));
+        let registry = r.inner.lock();
+        assert!(
+            registry.get(&Target(1)).is_some(),
+            "held targets are retained"
+        );
+        assert!(
+            registry.get(&Target(2)).is_none(),
+            "idle targets are evicted"
+        );
+        assert!(registry.get(&Target(3)).is_some());
+        drop(held);
+    }
 }
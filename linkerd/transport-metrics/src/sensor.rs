@@ -1,12 +1,19 @@
-use super::{Eos, EosMetrics, Metrics};
+use super::{Conn, Eos, EosMetrics, Metrics};
 use linkerd_errno::Errno;
 use linkerd_io as io;
-use std::{sync::Arc, task::Poll, time::Instant};
+use std::{
+    net::SocketAddr,
+    sync::{atomic::Ordering, Arc},
+    task::Poll,
+    time::Instant,
+};
 
 /// Tracks the state of a single instance of `Io` throughout its lifetime.
 #[derive(Debug)]
 pub struct Sensor {
     metrics: Option<Arc<Metrics>>,
+    conn: Arc<Conn>,
+    id: u64,
     opened_at: Instant,
 }
 
@@ -15,13 +22,26 @@ pub type SensorIo<T> = io::SensorIo<T, Sensor>;
 // === impl Sensor ===
 
 impl Sensor {
-    pub(crate) fn open(metrics: Arc<Metrics>) -> Self {
+    pub(crate) fn open(metrics: Arc<Metrics>, peer_addr: Option<SocketAddr>) -> Self {
         metrics.open_total.incr();
         metrics.open_connections.incr();
         metrics.by_eos.lock().last_update = Instant::now();
+
+        let opened_at = Instant::now();
+        let conn = Arc::new(Conn {
+            peer_addr,
+            opened_at,
+            read_bytes: Default::default(),
+            write_bytes: Default::default(),
+        });
+        let id = metrics.next_id.fetch_add(1, Ordering::Relaxed);
+        metrics.open.lock().insert(id, conn.clone());
+
         Self {
             metrics: Some(metrics),
-            opened_at: Instant::now(),
+            conn,
+            id,
+            opened_at,
         }
     }
 }
@@ -30,6 +50,7 @@ impl io::Sensor for Sensor {
     fn record_read(&mut self, sz: usize) {
         if let Some(ref m) = self.metrics {
             m.read_bytes_total.add(sz as u64);
+            self.conn.read_bytes.fetch_add(sz as u64, Ordering::Release);
             m.by_eos.lock().last_update = Instant::now();
         }
     }
@@ -37,6 +58,9 @@ impl io::Sensor for Sensor {
     fn record_write(&mut self, sz: usize) {
         if let Some(ref m) = self.metrics {
             m.write_bytes_total.add(sz as u64);
+            self.conn
+                .write_bytes
+                .fetch_add(sz as u64, Ordering::Release);
             m.by_eos.lock().last_update = Instant::now();
         }
     }
@@ -47,6 +71,7 @@ impl io::Sensor for Sensor {
         // on Drop).
         if let Some(m) = self.metrics.take() {
             m.open_connections.decr();
+            m.open.lock().remove(&self.id);
 
             let mut by_eos = m.by_eos.lock();
             let class = by_eos
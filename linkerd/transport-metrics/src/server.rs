@@ -1,4 +1,5 @@
 use super::{Metrics, Sensor, SensorIo};
+use linkerd_io as io;
 use linkerd_stack::{layer, ExtractParam, NewService, Service};
 use std::{
     sync::Arc,
@@ -46,6 +47,7 @@ where
 
 impl<I, A> Service<I> for Server<A>
 where
+    I: io::PeerAddr,
     A: Service<SensorIo<I>, Response = ()>,
 {
     type Response = ();
@@ -57,7 +59,8 @@ where
     }
 
     fn call(&mut self, io: I) -> Self::Future {
-        let io = SensorIo::new(io, Sensor::open(self.metrics.clone()));
+        let peer_addr = io.peer_addr().ok();
+        let io = SensorIo::new(io, Sensor::open(self.metrics.clone(), peer_addr));
         self.inner.call(io)
     }
 }
@@ -4,9 +4,12 @@
 #![forbid(unsafe_code)]
 #![type_length_limit = "16289823"]
 
-use linkerd_app::{core::transport::BindTcp, trace, Config};
+use linkerd_app::{
+    core::transport::{orig_dst, BindTcp, BindWithProxyProtocol},
+    trace, Config,
+};
 use linkerd_signal as signal;
-use tokio::sync::mpsc;
+use tokio::{sync::mpsc, time};
 pub use tracing::{debug, error, info, warn};
 
 #[cfg(feature = "mimalloc")]
@@ -40,9 +43,18 @@ fn main() {
     // by cgroups, when possible).
     rt::build().block_on(async move {
         let (shutdown_tx, mut shutdown_rx) = mpsc::unbounded_channel();
-        let bind = BindTcp::with_orig_dst();
+        let bind = match config.orig_dst_mode {
+            orig_dst::Mode::Redirect => BindTcp::with_orig_dst(),
+            orig_dst::Mode::Tproxy => BindTcp::with_tproxy(),
+        };
         let app = match config
-            .build(bind, bind, BindTcp::default(), shutdown_tx, trace)
+            .build(
+                BindWithProxyProtocol::from(bind),
+                bind,
+                BindWithProxyProtocol::from(BindTcp::default()),
+                shutdown_tx,
+                trace,
+            )
             .await
         {
             Ok(app) => app,
@@ -52,7 +64,12 @@ fn main() {
             }
         };
 
-        info!("Admin interface on {}", app.admin_addr());
+        if let Some(addr) = app.admin_addr() {
+            info!("Admin interface on {}", addr);
+        }
+        if let Some(path) = app.admin_uds_path() {
+            info!("Admin interface on {}", path.display());
+        }
         info!("Inbound interface on {}", app.inbound_addr());
         info!("Outbound interface on {}", app.outbound_addr());
 
@@ -65,12 +82,14 @@ fn main() {
             None => warn!("Identity is DISABLED"),
             Some(identity) => {
                 info!("Local identity is {}", identity.name());
-                let addr = app.identity_addr().expect("must have identity addr");
-                match addr.identity.value() {
-                    None => info!("Identity verified via {}", addr.addr),
-                    Some(tls) => {
-                        info!("Identity verified via {} ({})", addr.addr, tls.server_id);
-                    }
+                match app.identity_addr() {
+                    None => info!("Identity read from files"),
+                    Some(addr) => match addr.identity.value() {
+                        None => info!("Identity verified via {}", addr.addr),
+                        Some(tls) => {
+                            info!("Identity verified via {} ({})", addr.addr, tls.server_id);
+                        }
+                    },
                 }
             }
         }
@@ -97,14 +116,23 @@ fn main() {
         }
 
         let drain = app.spawn();
-        tokio::select! {
+        let grace = tokio::select! {
             _ = signal::shutdown() => {
                 info!("Received shutdown signal");
+                None
             }
-            _ = shutdown_rx.recv() => {
+            shutdown = shutdown_rx.recv() => {
                 info!("Received shutdown via admin interface");
+                shutdown.and_then(|s| s.grace)
+            }
+        };
+        match grace {
+            None => drain.drain().await,
+            Some(grace) => {
+                if time::timeout(grace, drain.drain()).await.is_err() {
+                    warn!(?grace, "Grace period elapsed before drain completed");
+                }
             }
         }
-        drain.drain().await;
     });
 }
@@ -0,0 +1,14 @@
+fn main() {
+    let iface_files = &["opentelemetry/proto/collector/trace/v1/trace_service.proto"];
+    let dirs = &["."];
+
+    tonic_build::configure()
+        .build_client(true)
+        .compile(iface_files, dirs)
+        .unwrap_or_else(|e| panic!("protobuf compilation failed: {}", e));
+
+    // recompile protobufs only if any of the proto files changes.
+    for file in iface_files {
+        println!("cargo:rerun-if-changed={}", file);
+    }
+}